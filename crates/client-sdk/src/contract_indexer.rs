@@ -4,12 +4,14 @@ use tokio::sync::RwLock;
 use tracing::debug;
 
 use axum::{
+    extract::{Path, State},
     http::StatusCode,
     response::{IntoResponse, Response},
-    Router,
+    Json, Router,
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 use sdk::*;
+use serde::Serialize;
 use utoipa::openapi::OpenApi;
 
 pub use axum;
@@ -18,11 +20,21 @@ pub use utoipa_axum;
 
 use crate::transaction_builder::TxExecutorHandler;
 
+/// Number of past states kept by the [`StateHistory`] of the indexer
+pub const STATE_HISTORY_LENGTH: usize = 1000;
+
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct ContractStateStore<State> {
     pub state: Option<State>,
     pub contract_name: ContractName,
     pub unsettled_blobs: BTreeMap<TxId, (BlobTransaction, TxContext)>,
+    /// Past states of the contract, read from the storage of the indexer.
+    #[borsh(skip, bound(deserialize = ""))]
+    pub history: Option<Arc<dyn StateHistory<State>>>,
+    /// Secondary indexes maintained by [`ContractHandler::update_indexes`].
+    /// Persisted separately by the indexer, like the history.
+    #[borsh(skip)]
+    pub indexes: SecondaryIndexes,
 }
 
 pub type ContractHandlerStore<T> = Arc<RwLock<ContractStateStore<T>>>;
@@ -33,10 +45,156 @@ impl<State> Default for ContractStateStore<State> {
             state: None,
             contract_name: Default::default(),
             unsettled_blobs: BTreeMap::new(),
+            history: None,
+            indexes: SecondaryIndexes::default(),
+        }
+    }
+}
+
+impl<State> ContractStateStore<State> {
+    /// State of the contract at `height`, if it is still in the history.
+    pub fn state_at(&self, height: BlockHeight) -> Result<Option<State>> {
+        match &self.history {
+            Some(history) => history.state_at(height),
+            None => Ok(None),
+        }
+    }
+}
+
+/// States of a contract from the block they were reached at, up to [`STATE_HISTORY_LENGTH`] of
+/// them. Kept on disk by the indexer storage, one entry per block, rather than in the store.
+pub trait StateHistory<State>: Send + Sync {
+    /// Last state recorded at or before `height`
+    fn state_at(&self, height: BlockHeight) -> Result<Option<State>>;
+}
+
+/// Serves the state of the contract at a past block height.
+/// Shared by the contract handlers, add it to the `api` router with `routes!(get_state_at_height)`.
+#[utoipa::path(
+    get,
+    path = "/state/{height}",
+    params(
+        ("height" = u64, Path, description = "Block height")
+    ),
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Get json state of contract at a past block height")
+    )
+)]
+pub async fn get_state_at_height<S: Serialize + 'static>(
+    Path(height): Path<BlockHeight>,
+    State(state): State<ContractHandlerStore<S>>,
+) -> Result<impl IntoResponse, AppError> {
+    let store = state.read().await;
+    store.state_at(height)?.map(Json).ok_or(AppError(
+        StatusCode::NOT_FOUND,
+        anyhow::anyhow!(
+            "No state found for contract '{}' at height {}",
+            store.contract_name,
+            height
+        ),
+    ))
+}
+
+/// Lookup tables derived from the state of a contract, e.g. balances by owner, so that the
+/// [`ContractHandler::api`] router doesn't have to scan the whole state.
+///
+/// Values are borsh encoded, entries are grouped by index name then sorted by key.
+/// Writes are tracked so that the indexer only persists the entries changed by each block.
+#[derive(Debug, Clone, Default, BorshSerialize, BorshDeserialize)]
+pub struct SecondaryIndexes {
+    entries: BTreeMap<String, BTreeMap<String, Vec<u8>>>,
+    #[borsh(skip)]
+    changed: Vec<(String, String)>,
+}
+
+impl SecondaryIndexes {
+    pub fn set<V: BorshSerialize>(&mut self, index: &str, key: &str, value: &V) -> Result<()> {
+        let value = borsh::to_vec(value).context(format!("encoding {index} entry {key}"))?;
+        self.insert_raw(index, key, value);
+        self.changed.push((index.to_string(), key.to_string()));
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: &str, key: &str) {
+        if let Some(entries) = self.entries.get_mut(index) {
+            entries.remove(key);
         }
+        self.changed.push((index.to_string(), key.to_string()));
+    }
+
+    pub fn get<V: BorshDeserialize>(&self, index: &str, key: &str) -> Result<Option<V>> {
+        self.get_raw(index, key)
+            .map(|value| borsh::from_slice(value).context(format!("decoding {index} entry {key}")))
+            .transpose()
+    }
+
+    /// Entries of the index, sorted by key
+    pub fn iter<V: BorshDeserialize>(
+        &self,
+        index: &str,
+    ) -> impl Iterator<Item = Result<(&String, V)>> {
+        self.entries
+            .get(index)
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| Ok((key, borsh::from_slice(value)?)))
+    }
+
+    pub fn get_raw(&self, index: &str, key: &str) -> Option<&Vec<u8>> {
+        self.entries.get(index)?.get(key)
+    }
+
+    /// Inserts an encoded entry without tracking it as changed, used when loading from storage
+    pub fn insert_raw(&mut self, index: &str, key: &str, value: Vec<u8>) {
+        self.entries
+            .entry(index.to_string())
+            .or_default()
+            .insert(key.to_string(), value);
+    }
+
+    /// Index names and keys of the entries set or removed since the last call
+    pub fn take_changed(&mut self) -> Vec<(String, String)> {
+        std::mem::take(&mut self.changed)
     }
 }
 
+/// Index of the [`supply::SupplyEvent`]s of a token, keyed by their zero-padded sequence number
+pub const SUPPLY_EVENTS_INDEX: &str = "supply_events";
+const SUPPLY_EVENTS_COUNT_INDEX: &str = "supply_events_count";
+
+/// Appends the event to the [`SUPPLY_EVENTS_INDEX`], after the events already recorded
+pub fn record_supply_event(
+    indexes: &mut SecondaryIndexes,
+    event: &supply::SupplyEvent,
+) -> Result<()> {
+    let count: u64 = indexes.get(SUPPLY_EVENTS_COUNT_INDEX, "")?.unwrap_or(0);
+    indexes.set(SUPPLY_EVENTS_INDEX, &format!("{count:020}"), event)?;
+    indexes.set(SUPPLY_EVENTS_COUNT_INDEX, "", &(count + 1))
+}
+
+/// Serves the events recorded by [`record_supply_event`].
+/// Shared by the token handlers, add it to the `api` router with `routes!(get_supply_events)`.
+#[utoipa::path(
+    get,
+    path = "/supply/events",
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Get mints, burns and role changes of the token, oldest first")
+    )
+)]
+pub async fn get_supply_events<S: 'static>(
+    State(state): State<ContractHandlerStore<S>>,
+) -> Result<impl IntoResponse, AppError> {
+    let store = state.read().await;
+    let events = store
+        .indexes
+        .iter::<supply::SupplyEvent>(SUPPLY_EVENTS_INDEX)
+        .map(|entry| entry.map(|(_, event)| event))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Json(events))
+}
+
 pub trait ContractHandler<Event = ()>
 where
     Self: Sized + TxExecutorHandler + 'static,
@@ -103,6 +261,30 @@ where
     ) -> Result<Option<Event>> {
         Ok(None)
     }
+
+    /// Updates the secondary indexes after a blob of the contract settled successfully and
+    /// [`ContractHandler::handle_transaction_success`] updated the state.
+    ///
+    /// To serve balances by owner for example, set the entries of the accounts touched by the
+    /// transaction, then read them from the `api` router:
+    /// ```ignore
+    /// fn update_indexes(&self, tx: &BlobTransaction, _index: BlobIndex, indexes: &mut SecondaryIndexes) -> Result<()> {
+    ///     indexes.set("balances", &tx.identity.0, &self.balance_of(&tx.identity.0)?)
+    /// }
+    ///
+    /// async fn get_balance(Path(account): Path<String>, State(store): State<ContractHandlerStore<Token>>) -> Result<impl IntoResponse, AppError> {
+    ///     let balance: Option<u128> = store.read().await.indexes.get("balances", &account)?;
+    ///     balance.map(Json).ok_or(AppError(StatusCode::NOT_FOUND, anyhow!("No balance for {account}")))
+    /// }
+    /// ```
+    fn update_indexes(
+        &self,
+        _tx: &BlobTransaction,
+        _index: BlobIndex,
+        _indexes: &mut SecondaryIndexes,
+    ) -> Result<()> {
+        Ok(())
+    }
 }
 
 // Make our own error that wraps `anyhow::Error`.
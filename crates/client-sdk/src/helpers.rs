@@ -42,6 +42,26 @@ pub struct ProverInfo {
     pub version: String,
 }
 
+/// Cost of executing a guest program in the zkvm, measured without proving
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionCost {
+    /// Total cycles, including the padding of the segments, that will be proven
+    pub cycles: u64,
+    /// Cycles spent running the program itself, when the zkvm reports them
+    pub user_cycles: Option<u64>,
+    /// Number of segments the execution is split into
+    pub segments: Option<u64>,
+    /// Memory touched by the execution, in bytes
+    pub memory: Option<u64>,
+}
+
+impl ExecutionCost {
+    /// Average cycles of each of the `blobs` blobs executed together, None if there are none
+    pub fn cycles_per_blob(&self, blobs: u64) -> Option<u64> {
+        self.cycles.checked_div(blobs)
+    }
+}
+
 pub trait ClientSdkProver<T: BorshSerialize + Send> {
     fn prove(
         &self,
@@ -53,6 +73,11 @@ pub trait ClientSdkProver<T: BorshSerialize + Send> {
 
     // investigate dedundacy between info().zkvm
     fn verifier(&self) -> Verifier;
+
+    /// Executes the program without proving it, to know what proving it would cost
+    fn meter(&self, _commitment_metadata: Vec<u8>, _calldatas: T) -> Result<ExecutionCost> {
+        anyhow::bail!("{} prover does not support metering", self.info().zkvm)
+    }
 }
 
 #[cfg(feature = "risc0")]
@@ -131,6 +156,31 @@ pub mod risc0 {
                 metadata,
             })
         }
+
+        /// Runs the program in the local executor, segments are padded to a power of two cycles
+        pub fn meter<T: BorshSerialize>(
+            &self,
+            commitment_metadata: Vec<u8>,
+            calldatas: T,
+        ) -> Result<ExecutionCost> {
+            let input_data = borsh::to_vec(&(commitment_metadata, calldatas))?;
+            let env = risc0_zkvm::ExecutorEnv::builder()
+                .write(&input_data.len())?
+                .write_slice(&input_data)
+                .build()?;
+
+            let session = risc0_zkvm::default_executor().execute(env, self.binary)?;
+            Ok(ExecutionCost {
+                cycles: session
+                    .segments
+                    .iter()
+                    .map(|segment| 1u64 << segment.po2)
+                    .sum(),
+                user_cycles: Some(session.cycles()),
+                segments: Some(session.segments.len() as u64),
+                memory: None,
+            })
+        }
     }
 
     impl<T: BorshSerialize + Send + 'static> ClientSdkProver<T> for Risc0Prover<'_> {
@@ -156,6 +206,10 @@ pub mod risc0 {
         fn program_id(&self) -> ProgramId {
             ProgramId(self.program_id.into())
         }
+
+        fn meter(&self, commitment_metadata: Vec<u8>, calldatas: T) -> Result<ExecutionCost> {
+            self.meter(commitment_metadata, calldatas)
+        }
     }
 }
 
@@ -273,6 +327,29 @@ pub mod sp1 {
                 metadata,
             })
         }
+
+        /// Runs the program in the local executor, whatever the prover type
+        pub fn meter<T: BorshSerialize>(
+            &self,
+            commitment_metadata: Vec<u8>,
+            calldatas: T,
+        ) -> Result<ExecutionCost> {
+            let mut stdin = SP1Stdin::new();
+            let encoded = borsh::to_vec(&(commitment_metadata, calldatas))?;
+            stdin.write_vec(encoded);
+
+            let (_, report) = match &self.client {
+                ProverType::Local(client) => client.execute(&self.pk.elf, &stdin).run()?,
+                ProverType::Network(client) => client.execute(&self.pk.elf, &stdin).run()?,
+            };
+            Ok(ExecutionCost {
+                cycles: report.total_instruction_count(),
+                user_cycles: None,
+                segments: None,
+                // Addresses are of 32 bits words
+                memory: Some(report.touched_memory_addresses * 4),
+            })
+        }
     }
 
     impl<T: BorshSerialize + Send + 'static> ClientSdkProver<T> for SP1Prover {
@@ -297,6 +374,129 @@ pub mod sp1 {
         fn program_id(&self) -> ProgramId {
             ProgramId(serde_json::to_vec(&self.pk.vk).expect("Failed to serialize SP1 Proving Key"))
         }
+
+        fn meter(&self, commitment_metadata: Vec<u8>, calldatas: T) -> Result<ExecutionCost> {
+            self.meter(commitment_metadata, calldatas)
+        }
+    }
+}
+
+#[cfg(feature = "rest")]
+pub mod remote {
+    use std::time::Duration;
+
+    use anyhow::bail;
+    use sdk::api::{APIProvingJobRequest, APIProvingJobStatus};
+
+    use super::*;
+    use crate::rest_client::ProverApiHttpClient;
+
+    /// Proves by submitting jobs to a prover server, instead of running the prover in-process.
+    /// The server has to know the program with `program_id`.
+    pub struct RemoteProver {
+        client: ProverApiHttpClient,
+        program_id: ProgramId,
+        verifier: Verifier,
+        priority: u32,
+        poll_interval: Duration,
+        timeout: Duration,
+    }
+
+    impl RemoteProver {
+        pub fn new(client: ProverApiHttpClient, program_id: ProgramId, verifier: Verifier) -> Self {
+            Self {
+                client,
+                program_id,
+                verifier,
+                priority: 0,
+                poll_interval: Duration::from_millis(500),
+                timeout: Duration::from_secs(3600),
+            }
+        }
+
+        /// Jobs with a higher priority are proven first by the server
+        pub fn with_priority(self, priority: u32) -> Self {
+            Self { priority, ..self }
+        }
+
+        pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
+            Self {
+                poll_interval,
+                ..self
+            }
+        }
+
+        /// Time after which `prove` gives up waiting for the job, one hour by default
+        pub fn with_timeout(self, timeout: Duration) -> Self {
+            Self { timeout, ..self }
+        }
+
+        pub async fn prove(
+            &self,
+            commitment_metadata: Vec<u8>,
+            calldatas: Vec<Calldata>,
+        ) -> Result<Proof> {
+            let id = self
+                .client
+                .submit_job(&APIProvingJobRequest {
+                    program_id: self.program_id.clone(),
+                    commitment_metadata,
+                    calldatas,
+                    priority: self.priority,
+                })
+                .await?;
+            let deadline = tokio::time::Instant::now() + self.timeout;
+            loop {
+                let job = self.client.get_job(id).await?;
+                match job.status {
+                    APIProvingJobStatus::Succeeded => match job.proof {
+                        Some(proof) => return Ok(proof),
+                        None => bail!("Proving job {id} succeeded without a proof"),
+                    },
+                    APIProvingJobStatus::Failed => bail!(
+                        "Proving job {id} failed after {} attempts: {}",
+                        job.attempts,
+                        job.error.unwrap_or_default()
+                    ),
+                    APIProvingJobStatus::Queued | APIProvingJobStatus::Proving => {
+                        if tokio::time::Instant::now() >= deadline {
+                            bail!(
+                                "Proving job {id} not done after {:?}, it is still {:?}",
+                                self.timeout,
+                                job.status
+                            );
+                        }
+                        tokio::time::sleep(self.poll_interval).await;
+                    }
+                }
+            }
+        }
+    }
+
+    impl ClientSdkProver<Vec<Calldata>> for RemoteProver {
+        fn prove(
+            &self,
+            commitment_metadata: Vec<u8>,
+            calldatas: Vec<Calldata>,
+        ) -> Pin<Box<dyn std::future::Future<Output = Result<Proof>> + Send + '_>> {
+            Box::pin(self.prove(commitment_metadata, calldatas))
+        }
+
+        fn info(&self) -> ProverInfo {
+            ProverInfo {
+                name: format!("remote:{}", self.client.url),
+                zkvm: self.verifier.0.clone(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            }
+        }
+
+        fn program_id(&self) -> ProgramId {
+            self.program_id.clone()
+        }
+
+        fn verifier(&self) -> Verifier {
+            self.verifier.clone()
+        }
     }
 }
 
@@ -461,3 +661,26 @@ pub mod test {
         Ok(hyle_output)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycles_per_blob() {
+        let cost = ExecutionCost {
+            cycles: 1_000,
+            ..Default::default()
+        };
+        assert_eq!(cost.cycles_per_blob(4), Some(250));
+        assert_eq!(cost.cycles_per_blob(3), Some(333));
+        assert_eq!(cost.cycles_per_blob(0), None);
+    }
+
+    #[test]
+    fn test_meter_unsupported() {
+        let err = ClientSdkProver::<Vec<Calldata>>::meter(&test::MockProver {}, vec![], vec![])
+            .unwrap_err();
+        assert_eq!(err.to_string(), "mock prover does not support metering");
+    }
+}
@@ -2,7 +2,11 @@
 pub mod contract_indexer;
 pub mod helpers;
 pub mod light_executor;
+#[cfg(feature = "smt")]
+pub mod merkle_state;
 #[cfg(feature = "rest")]
 pub mod rest_client;
 pub mod tcp_client;
 pub mod transaction_builder;
+#[cfg(feature = "rest")]
+pub mod tx_tracker;
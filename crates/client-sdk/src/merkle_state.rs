@@ -0,0 +1,469 @@
+use std::marker::PhantomData;
+
+use anyhow::{bail, Context, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+use sdk::merkle_state::{MerkleKey, MerkleStateContract, MerkleStep, MerkleWitness};
+use sdk::merkle_utils::{BorshableMerkleProof, SHA256Hasher};
+use sdk::utils::as_hyle_output;
+use sdk::{
+    Blob, Calldata, ConstructorMetadata, HyleOutput, RegisterContractEffect, StateCommitment,
+    StructuredBlob,
+};
+use sparse_merkle_tree::{default_store::DefaultStore, SparseMerkleTree, H256};
+
+use crate::transaction_builder::TxExecutorHandler;
+
+type Tree<V> = SparseMerkleTree<SHA256Hasher, V, DefaultStore<V>>;
+
+/// Whole state of a [MerkleStateContract], see [sdk::merkle_state].
+///
+/// It builds the commitment metadata of the blobs of the contract from the keys they touch, and
+/// handles them by executing the contract on that same witness, as the guest will. The leaves
+/// written by the contract are then updated in the tree.
+pub struct MerkleState<C: MerkleStateContract> {
+    tree: Tree<C::Value>,
+    phantom: PhantomData<fn() -> C>,
+}
+
+impl<C: MerkleStateContract> MerkleState<C> {
+    pub fn new(leaves: impl IntoIterator<Item = (C::Key, C::Value)>) -> Result<Self> {
+        let mut state = Self::default();
+        for (key, value) in leaves {
+            state.set(&key, value)?;
+        }
+        Ok(state)
+    }
+
+    /// Value of the leaf, the zero value if it was never set
+    pub fn get(&self, key: &C::Key) -> Result<C::Value> {
+        Ok(self.tree.get(&key.merkle_key())?)
+    }
+
+    pub fn set(&mut self, key: &C::Key, value: C::Value) -> Result<()> {
+        self.tree.update(key.merkle_key(), value)?;
+        Ok(())
+    }
+
+    pub fn tree(&self) -> &Tree<C::Value> {
+        &self.tree
+    }
+
+    /// Leaves of the keys and their merkle proof against the current root, without any step if
+    /// there are no keys
+    pub fn witness(&self, keys: Vec<C::Key>) -> Result<MerkleWitness<C::Key, C::Value>> {
+        let mut leaves: Vec<(C::Key, C::Value)> = vec![];
+        for key in keys {
+            if !leaves.iter().any(|(k, _)| k == &key) {
+                let value = self.get(&key)?;
+                leaves.push((key, value));
+            }
+        }
+        if leaves.is_empty() {
+            return Ok(MerkleWitness::new(self.get_state_commitment(), vec![]));
+        }
+        let proof = self
+            .tree
+            .merkle_proof(leaves.iter().map(|(k, _)| k.merkle_key()).collect())
+            .context("Failed to generate merkle proof")?;
+        Ok(MerkleWitness::new(
+            self.get_state_commitment(),
+            vec![MerkleStep {
+                proof: BorshableMerkleProof(proof),
+                leaves,
+            }],
+        ))
+    }
+
+    fn blob_witness(&self, blob: &Blob) -> Result<MerkleWitness<C::Key, C::Value>> {
+        match StructuredBlob::<C::Action>::try_from(blob.clone()) {
+            Ok(parsed_blob) => self.witness(C::touched_keys(&parsed_blob.data.parameters)),
+            // Return a valid metadata, the contract can handle this.
+            Err(_) => Ok(MerkleWitness::new(self.get_state_commitment(), vec![])),
+        }
+    }
+}
+
+impl<C: MerkleStateContract> Default for MerkleState<C> {
+    fn default() -> Self {
+        MerkleState {
+            tree: SparseMerkleTree::default(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C: MerkleStateContract> Clone for MerkleState<C> {
+    fn clone(&self) -> Self {
+        let store = self.tree.store().clone();
+        let root = *self.tree.root();
+        MerkleState {
+            tree: SparseMerkleTree::new(root, store),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C: MerkleStateContract> BorshSerialize for MerkleState<C> {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let map = self.tree.store().leaves_map();
+        borsh::BorshSerialize::serialize(&(map.len() as u32), writer)?;
+        for (key, value) in map.iter() {
+            writer.write_all(key.as_slice())?;
+            borsh::BorshSerialize::serialize(value, writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<C: MerkleStateContract> BorshDeserialize for MerkleState<C> {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let len: u32 = borsh::BorshDeserialize::deserialize_reader(reader)?;
+        let mut tree = Tree::default();
+        for _ in 0..len {
+            let mut key = [0u8; 32];
+            reader.read_exact(&mut key)?;
+            let value: C::Value = borsh::BorshDeserialize::deserialize_reader(reader)?;
+            tree.update(H256::from(key), value)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        }
+        Ok(MerkleState {
+            tree,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<C: MerkleStateContract> TxExecutorHandler for MerkleState<C> {
+    fn handle(&mut self, calldata: &Calldata) -> Result<HyleOutput> {
+        let initial_state_commitment = self.get_state_commitment();
+        let blob = calldata
+            .blobs
+            .get(&calldata.index)
+            .context("Blob of the calldata not found")?;
+        let mut contract = C::from_witness(self.blob_witness(blob)?);
+
+        let mut res = contract
+            .initialize()
+            .and_then(|_| contract.execute(calldata));
+        let next_state_commitment = match res {
+            Ok(_) => contract.commit(),
+            Err(_) => initial_state_commitment.clone(),
+        };
+        let output = as_hyle_output(
+            initial_state_commitment,
+            next_state_commitment,
+            calldata,
+            &mut res,
+        );
+
+        if output.success {
+            for (key, value) in contract.witness().applied() {
+                self.set(key, value.clone())?;
+            }
+            if self.get_state_commitment() != output.next_state {
+                bail!("Merkle state diverged from the commitment of the contract");
+            }
+        }
+        Ok(output)
+    }
+
+    /// The contract built from the merkle proof of the leaves touched by the blob
+    fn build_commitment_metadata(&self, blob: &Blob) -> Result<Vec<u8>> {
+        borsh::to_vec(&C::from_witness(self.blob_witness(blob)?))
+            .context("Failed to serialize merkle state contract")
+    }
+
+    fn merge_commitment_metadata(
+        &self,
+        initial: Vec<u8>,
+        next: Vec<u8>,
+    ) -> Result<Vec<u8>, String> {
+        let mut initial_contract: C = borsh::from_slice(&initial).map_err(|e| e.to_string())?;
+        let next_contract: C = borsh::from_slice(&next).map_err(|e| e.to_string())?;
+
+        initial_contract
+            .witness_mut()
+            .merge(next_contract.witness().clone());
+
+        borsh::to_vec(&initial_contract).map_err(|e| e.to_string())
+    }
+
+    fn construct_state(
+        _register_blob: &RegisterContractEffect,
+        metadata: &Option<Vec<u8>>,
+    ) -> Result<Self> {
+        let initial_state = ConstructorMetadata::initial_state_of(metadata)
+            .context("Failed to decode constructor metadata")?;
+        Self::new(C::initial_leaves(&initial_state).map_err(anyhow::Error::msg)?)
+    }
+
+    fn get_state_commitment(&self) -> StateCommitment {
+        StateCommitment(Into::<[u8; 32]>::into(*self.tree.root()).to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdk::merkle_state::MerkleLeaves;
+    use sdk::utils::parse_calldata;
+    use sdk::{
+        BlobIndex, ContractName, Identity, IndexedBlobs, RunResult, StructuredBlobData, TxHash,
+        ZkContract,
+    };
+    use sha2::{Digest, Sha256};
+    use sparse_merkle_tree::traits::Value;
+
+    use super::*;
+
+    #[derive(Debug, Default, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+    struct Balance(u64);
+
+    impl Value for Balance {
+        fn to_h256(&self) -> H256 {
+            if self.0 == 0 {
+                return H256::zero();
+            }
+            H256::from(Into::<[u8; 32]>::into(Sha256::digest(self.0.to_le_bytes())))
+        }
+
+        fn zero() -> Self {
+            Balance(0)
+        }
+    }
+
+    #[derive(Debug, BorshSerialize, BorshDeserialize)]
+    enum LedgerAction {
+        Transfer {
+            from: Identity,
+            to: Identity,
+            amount: u64,
+        },
+        Ping,
+    }
+
+    /// Balances of identities, whose initial state is the borsh encoded leaves
+    #[derive(BorshSerialize, BorshDeserialize)]
+    struct Ledger {
+        witness: MerkleWitness<Identity, Balance>,
+    }
+
+    impl ZkContract for Ledger {
+        fn execute(&mut self, calldata: &Calldata) -> RunResult {
+            let (action, exec_ctx) = parse_calldata::<LedgerAction>(calldata)?;
+            if let LedgerAction::Transfer { from, to, amount } = action {
+                let mut step = self.witness.next_step()?;
+                let balance = step.get_mut(&from)?;
+                balance.0 = balance
+                    .0
+                    .checked_sub(amount)
+                    .ok_or("Insufficient balance")?;
+                step.get_mut(&to)?.0 += amount;
+                self.witness.apply(step)?;
+            }
+            Ok((vec![], exec_ctx, vec![]))
+        }
+
+        fn commit(&self) -> StateCommitment {
+            self.witness.commitment.clone()
+        }
+    }
+
+    impl MerkleStateContract for Ledger {
+        type Key = Identity;
+        type Value = Balance;
+        type Action = LedgerAction;
+
+        fn touched_keys(action: &LedgerAction) -> Vec<Identity> {
+            match action {
+                LedgerAction::Transfer { from, to, .. } => vec![from.clone(), to.clone()],
+                LedgerAction::Ping => vec![],
+            }
+        }
+
+        fn from_witness(witness: MerkleWitness<Identity, Balance>) -> Self {
+            Ledger { witness }
+        }
+
+        fn witness(&self) -> &MerkleWitness<Identity, Balance> {
+            &self.witness
+        }
+
+        fn witness_mut(&mut self) -> &mut MerkleWitness<Identity, Balance> {
+            &mut self.witness
+        }
+
+        fn initial_leaves(
+            initial_state: &Option<Vec<u8>>,
+        ) -> Result<MerkleLeaves<Identity, Balance>, String> {
+            match initial_state {
+                Some(state) => borsh::from_slice(state).map_err(|e| e.to_string()),
+                None => Ok(vec![]),
+            }
+        }
+    }
+
+    fn ledger() -> MerkleState<Ledger> {
+        MerkleState::new([("alice".into(), Balance(10)), ("bob".into(), Balance(5))]).unwrap()
+    }
+
+    fn blob(action: LedgerAction) -> Blob {
+        StructuredBlob {
+            contract_name: ContractName::new("ledger"),
+            data: StructuredBlobData {
+                caller: None,
+                callees: None,
+                parameters: action,
+            },
+        }
+        .into()
+    }
+
+    fn transfer(from: &str, to: &str, amount: u64) -> Blob {
+        blob(LedgerAction::Transfer {
+            from: from.into(),
+            to: to.into(),
+            amount,
+        })
+    }
+
+    fn calldata(blob: Blob) -> Calldata {
+        Calldata {
+            tx_hash: TxHash::new("tx"),
+            identity: "alice@ledger".into(),
+            blobs: IndexedBlobs::from(vec![blob]),
+            tx_blob_count: 1,
+            index: BlobIndex(0),
+            tx_ctx: None,
+            private_input: vec![],
+        }
+    }
+
+    #[test]
+    fn test_handle() {
+        let mut state = ledger();
+        let initial = state.get_state_commitment();
+
+        let output = state
+            .handle(&calldata(transfer("alice", "carol", 3)))
+            .unwrap();
+        assert!(output.success);
+        assert_eq!(output.initial_state, initial);
+        assert_eq!(output.next_state, state.get_state_commitment());
+        assert_eq!(state.get(&"alice".into()).unwrap(), Balance(7));
+        assert_eq!(state.get(&"carol".into()).unwrap(), Balance(3));
+
+        // A failed execution leaves the state untouched
+        let before = state.get_state_commitment();
+        let output = state
+            .handle(&calldata(transfer("bob", "carol", 6)))
+            .unwrap();
+        assert!(!output.success);
+        assert_eq!(output.next_state, before);
+        assert_eq!(state.get_state_commitment(), before);
+        assert_eq!(state.get(&"bob".into()).unwrap(), Balance(5));
+
+        // An action touching no keys doesn't need a witness
+        let output = state.handle(&calldata(blob(LedgerAction::Ping))).unwrap();
+        assert!(output.success);
+        assert_eq!(output.next_state, before);
+    }
+
+    #[test]
+    fn test_build_commitment_metadata() {
+        let state = ledger();
+
+        let metadata = state
+            .build_commitment_metadata(&transfer("alice", "carol", 3))
+            .unwrap();
+        let mut contract: Ledger = borsh::from_slice(&metadata).unwrap();
+        assert_eq!(contract.commit(), state.get_state_commitment());
+        let step = contract.witness.next_step().unwrap();
+        assert_eq!(step.get(&"alice".into()), Ok(&Balance(10)));
+        assert_eq!(step.get(&"carol".into()), Ok(&Balance(0)));
+        assert!(step.get(&"bob".into()).is_err());
+
+        // Actions touching no keys and unparsable blobs get an empty witness
+        for blob in [
+            blob(LedgerAction::Ping),
+            Blob {
+                contract_name: ContractName::new("ledger"),
+                data: sdk::BlobData(vec![42]),
+            },
+        ] {
+            let metadata = state.build_commitment_metadata(&blob).unwrap();
+            let contract: Ledger = borsh::from_slice(&metadata).unwrap();
+            assert_eq!(contract.commit(), state.get_state_commitment());
+            assert!(contract.witness.steps.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_merge_commitment_metadata() {
+        let mut state = ledger();
+        let initial = state.get_state_commitment();
+        let blobs = [transfer("alice", "carol", 3), transfer("carol", "bob", 2)];
+
+        // The metadata of each blob is built on the state left by the previous ones
+        let mut metadata = vec![];
+        for blob in blobs.iter() {
+            metadata.push(state.build_commitment_metadata(blob).unwrap());
+            assert!(state.handle(&calldata(blob.clone())).unwrap().success);
+        }
+        let [first, second] = metadata.try_into().unwrap();
+        let merged = state.merge_commitment_metadata(first, second).unwrap();
+
+        // The guest executes the whole batch from the merged metadata
+        let mut contract: Ledger = borsh::from_slice(&merged).unwrap();
+        assert_eq!(contract.commit(), initial);
+        for blob in blobs {
+            contract.execute(&calldata(blob)).unwrap();
+        }
+        assert_eq!(contract.commit(), state.get_state_commitment());
+        assert_eq!(state.get(&"bob".into()).unwrap(), Balance(7));
+    }
+
+    #[test]
+    fn test_borsh_round_trip() {
+        let mut state = ledger();
+        state.set(&"alice".into(), Balance(0)).unwrap();
+
+        let decoded: MerkleState<Ledger> =
+            borsh::from_slice(&borsh::to_vec(&state).unwrap()).unwrap();
+        assert_eq!(decoded.get_state_commitment(), state.get_state_commitment());
+        assert_eq!(decoded.get(&"alice".into()).unwrap(), Balance(0));
+        assert_eq!(decoded.get(&"bob".into()).unwrap(), Balance(5));
+    }
+
+    #[test]
+    fn test_construct_state() {
+        let register = RegisterContractEffect::default();
+        let leaves = borsh::to_vec(&vec![(Identity::from("alice"), Balance(10))]).unwrap();
+        let expected = MerkleState::<Ledger>::new([("alice".into(), Balance(10))]).unwrap();
+
+        let metadata = ConstructorMetadata {
+            schema: None,
+            initial_state: Some(leaves.clone()),
+        };
+        for metadata in [Some(metadata.to_bytes()), Some(leaves)] {
+            let state = MerkleState::<Ledger>::construct_state(&register, &metadata).unwrap();
+            assert_eq!(
+                state.get_state_commitment(),
+                expected.get_state_commitment()
+            );
+        }
+
+        let empty = MerkleState::<Ledger>::construct_state(&register, &None).unwrap();
+        assert_eq!(
+            empty.get_state_commitment(),
+            MerkleState::<Ledger>::default().get_state_commitment()
+        );
+
+        let invalid = ConstructorMetadata {
+            schema: None,
+            initial_state: Some(vec![1]),
+        };
+        assert!(
+            MerkleState::<Ledger>::construct_state(&register, &Some(invalid.to_bytes())).is_err()
+        );
+    }
+}
@@ -9,11 +9,14 @@ use anyhow::{Context, Result};
 use hyle_net::http::HttpClient;
 use sdk::{
     api::{
-        APIBlob, APIBlock, APIContract, APINodeContract, APIRegisterContract, APIStaking,
-        APITransaction, NodeInfo, TransactionWithBlobs,
+        APIBlob, APIBlock, APIContract, APIContractSettlementStats, APIContractStateDiff,
+        APIContractStateTransition, APIIdentitySummary, APINodeContract, APIProvingJob,
+        APIProvingJobRequest, APIRegisterContract, APISearchResult, APISettledTx, APIStaking,
+        APITransaction, APITransactionEvents, NodeInfo, TransactionStatusDb, TransactionWithBlobs,
     },
     BlobIndex, BlobTransaction, BlockHash, BlockHeight, ConsensusInfo, Contract, ContractName,
-    ProofTransaction, TxHash, UnsettledBlobTransaction, ValidatorPublicKey,
+    ContractSchema, Identity, ProofTransaction, TxHash, UnsettledBlobTransaction,
+    ValidatorPublicKey,
 };
 
 #[derive(Clone)]
@@ -43,6 +46,15 @@ impl IndexerApiHttpClient {
             .context(format!("getting contract {contract_name}"))
     }
 
+    pub async fn get_contract_schema(
+        &self,
+        contract_name: &ContractName,
+    ) -> Result<ContractSchema> {
+        self.get(&format!("v1/indexer/contract/{contract_name}/schema"))
+            .await
+            .context(format!("getting schema of contract {contract_name}"))
+    }
+
     pub async fn fetch_current_state<State>(&self, contract_name: &ContractName) -> Result<State>
     where
         State: serde::de::DeserializeOwned,
@@ -105,12 +117,138 @@ impl IndexerApiHttpClient {
             .context(format!("getting transactions for contract {contract_name}"))
     }
 
+    /// Returns the transactions sent by `identity`, most recent first.
+    /// Pass the (height, index) of the last transaction received as `before` to fetch the next page.
+    pub async fn get_transactions_by_identity(
+        &self,
+        identity: &Identity,
+        before: Option<(BlockHeight, u32)>,
+        nb_results: Option<u32>,
+        status: Option<TransactionStatusDb>,
+        contract_name: Option<&ContractName>,
+    ) -> Result<Vec<APITransaction>> {
+        let mut params = vec![];
+        if let Some((height, index)) = before {
+            params.push(format!("before_block={height}&before_index={index}"));
+        }
+        if let Some(nb_results) = nb_results {
+            params.push(format!("nb_results={nb_results}"));
+        }
+        if let Some(status) = status {
+            let status = serde_json::to_value(status)?;
+            params.push(format!("status={}", status.as_str().unwrap_or_default()));
+        }
+        if let Some(contract_name) = contract_name {
+            params.push(format!("contract={}", url_encode(&contract_name.0)));
+        }
+        self.get(&format!(
+            "v1/indexer/transactions/identity/{}?{}",
+            url_encode(&identity.0),
+            params.join("&")
+        ))
+        .await
+        .context(format!("getting transactions for identity {identity}"))
+    }
+
+    pub async fn get_identity_summary(&self, identity: &Identity) -> Result<APIIdentitySummary> {
+        self.get(&format!(
+            "v1/indexer/identity/{}/summary",
+            url_encode(&identity.0)
+        ))
+        .await
+        .context(format!("getting summary of identity {identity}"))
+    }
+
+    /// Resolves `query` to the blocks, transactions, contracts, identities or data proposals it
+    /// designates. Hashes and contract names can be given as prefixes.
+    pub async fn search(&self, query: &str) -> Result<Vec<APISearchResult>> {
+        self.get(&format!("v1/indexer/search?q={}", url_encode(query)))
+            .await
+            .context(format!("searching {query}"))
+    }
+
+    /// Returns the state transitions of `contract_name` applied between `from_height` and
+    /// `to_height` included, in chronological order.
+    pub async fn get_contract_state_transitions(
+        &self,
+        contract_name: &ContractName,
+        from_height: Option<BlockHeight>,
+        to_height: Option<BlockHeight>,
+        nb_results: Option<u32>,
+    ) -> Result<Vec<APIContractStateTransition>> {
+        let mut params = vec![];
+        if let Some(from_height) = from_height {
+            params.push(format!("from_height={from_height}"));
+        }
+        if let Some(to_height) = to_height {
+            params.push(format!("to_height={to_height}"));
+        }
+        if let Some(nb_results) = nb_results {
+            params.push(format!("nb_results={nb_results}"));
+        }
+        self.get(&format!(
+            "v1/indexer/state/contract/{contract_name}/transitions?{}",
+            params.join("&")
+        ))
+        .await
+        .context(format!(
+            "getting state transitions for contract {contract_name}"
+        ))
+    }
+
+    /// Compares the state of `contract_name` at heights `from` and `to`.
+    pub async fn get_contract_state_diff(
+        &self,
+        contract_name: &ContractName,
+        from: BlockHeight,
+        to: BlockHeight,
+    ) -> Result<APIContractStateDiff> {
+        self.get(&format!(
+            "v1/indexer/state/contract/{contract_name}/diff?from={from}&to={to}"
+        ))
+        .await
+        .context(format!("getting state diff for contract {contract_name}"))
+    }
+
+    /// Returns settlement latencies, failure rates and blob sizes of the transactions of
+    /// `contract_name` sequenced between `from` and `to` (UNIX timestamps in milliseconds),
+    /// in buckets of `bucket_secs` seconds.
+    pub async fn get_contract_settlement_stats(
+        &self,
+        contract_name: &ContractName,
+        from: i64,
+        to: i64,
+        bucket_secs: u64,
+        slo_secs: Option<u64>,
+    ) -> Result<APIContractSettlementStats> {
+        let mut params = format!("from={from}&to={to}&bucket_secs={bucket_secs}");
+        if let Some(slo_secs) = slo_secs {
+            params.push_str(&format!("&slo_secs={slo_secs}"));
+        }
+        self.get(&format!(
+            "v1/indexer/stats/contract/{contract_name}/settlement?{params}"
+        ))
+        .await
+        .context(format!(
+            "getting settlement stats for contract {contract_name}"
+        ))
+    }
+
     pub async fn get_transaction_with_hash(&self, tx_hash: &TxHash) -> Result<APITransaction> {
         self.get(&format!("v1/indexer/transaction/hash/{tx_hash}"))
             .await
             .context(format!("getting transaction with hash {tx_hash}"))
     }
 
+    pub async fn get_transaction_events(
+        &self,
+        tx_hash: &TxHash,
+    ) -> Result<Vec<APITransactionEvents>> {
+        self.get(&format!("v1/indexer/transaction/hash/{tx_hash}/events"))
+            .await
+            .context(format!("getting events of transaction {tx_hash}"))
+    }
+
     pub async fn get_blob_transactions_by_contract(
         &self,
         contract_name: &ContractName,
@@ -141,6 +279,19 @@ impl IndexerApiHttpClient {
     }
 }
 
+/// Percent-encodes a path segment or a query value.
+fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
 impl Deref for IndexerApiHttpClient {
     type Target = HttpClient;
 
@@ -198,6 +349,12 @@ pub trait NodeApiClient {
         &self,
         blob_tx_hash: TxHash,
     ) -> Pin<Box<dyn Future<Output = Result<UnsettledBlobTransaction>> + Send + '_>>;
+
+    /// How a blob transaction was settled, known for the latest settled transactions only
+    fn get_settled_tx(
+        &self,
+        blob_tx_hash: TxHash,
+    ) -> Pin<Box<dyn Future<Output = Result<APISettledTx>> + Send + '_>>;
 }
 
 impl NodeApiHttpClient {
@@ -224,6 +381,15 @@ impl NodeApiHttpClient {
     pub fn retry_15times_1000ms(&self) -> Self {
         self.with_retry(8, Duration::from_millis(4000))
     }
+
+    pub async fn get_contract_schema(
+        &self,
+        contract_name: &ContractName,
+    ) -> Result<ContractSchema> {
+        self.get(&format!("v1/contract/{contract_name}/schema"))
+            .await
+            .context(format!("getting schema of contract {contract_name}"))
+    }
 }
 
 impl NodeApiClient for NodeApiHttpClient {
@@ -322,6 +488,17 @@ impl NodeApiClient for NodeApiHttpClient {
         })
     }
 
+    fn get_settled_tx(
+        &self,
+        blob_tx_hash: TxHash,
+    ) -> Pin<Box<dyn Future<Output = Result<APISettledTx>> + Send + '_>> {
+        Box::pin(async move {
+            self.get(&format!("v1/settled_tx/{blob_tx_hash}"))
+                .await
+                .context(format!("getting settled tx {blob_tx_hash}"))
+        })
+    }
+
     fn get_settled_height(
         &self,
         contract_name: ContractName,
@@ -348,6 +525,51 @@ impl DerefMut for NodeApiHttpClient {
     }
 }
 
+/// Client of a prover server, see [crate::helpers::remote::RemoteProver]
+#[derive(Clone)]
+pub struct ProverApiHttpClient {
+    pub client: HttpClient,
+}
+
+impl ProverApiHttpClient {
+    pub fn new(url: String) -> Result<Self> {
+        Ok(ProverApiHttpClient {
+            client: HttpClient {
+                url: url.parse()?,
+                api_key: None,
+                retry: None,
+            },
+        })
+    }
+
+    /// Queues a proving job, returns its id
+    pub async fn submit_job(&self, job: &APIProvingJobRequest) -> Result<u64> {
+        self.post_json("v1/proving/jobs", job)
+            .await
+            .context("submitting proving job")
+    }
+
+    pub async fn get_job(&self, id: u64) -> Result<APIProvingJob> {
+        self.get(&format!("v1/proving/jobs/{id}"))
+            .await
+            .context(format!("getting proving job {id}"))
+    }
+
+    pub async fn list_jobs(&self) -> Result<Vec<APIProvingJob>> {
+        self.get("v1/proving/jobs")
+            .await
+            .context("listing proving jobs")
+    }
+}
+
+impl Deref for ProverApiHttpClient {
+    type Target = HttpClient;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
 #[allow(dead_code)]
 pub mod test {
     use sdk::{hyle_model_utils::TimestampMs, Hashed, TimeoutWindow};
@@ -364,6 +586,7 @@ pub mod test {
         pub staking_state: Arc<Mutex<APIStaking>>,
         pub contracts: Arc<Mutex<std::collections::HashMap<ContractName, Contract>>>,
         pub unsettled_txs: Arc<Mutex<std::collections::HashMap<TxHash, UnsettledBlobTransaction>>>,
+        pub settled_txs: Arc<Mutex<std::collections::HashMap<TxHash, APISettledTx>>>,
         pub pending_proofs: Arc<Mutex<Vec<ProofTransaction>>>,
         pub pending_blobs: Arc<Mutex<Vec<BlobTransaction>>>,
     }
@@ -388,6 +611,7 @@ pub mod test {
                 staking_state: Arc::new(Mutex::new(APIStaking::default())),
                 contracts: Arc::new(Mutex::new(std::collections::HashMap::new())),
                 unsettled_txs: Arc::new(Mutex::new(std::collections::HashMap::new())),
+                settled_txs: Arc::new(Mutex::new(std::collections::HashMap::new())),
                 pending_proofs: Arc::new(Mutex::new(vec![])),
                 pending_blobs: Arc::new(Mutex::new(vec![])),
             }
@@ -423,6 +647,12 @@ pub mod test {
         pub fn add_unsettled_tx(&self, tx_hash: TxHash, tx: UnsettledBlobTransaction) {
             self.unsettled_txs.lock().unwrap().insert(tx_hash, tx);
         }
+
+        /// Settles a transaction added with `add_unsettled_tx`
+        pub fn settle_tx(&self, tx_hash: TxHash, settled: APISettledTx) {
+            self.unsettled_txs.lock().unwrap().remove(&tx_hash);
+            self.settled_txs.lock().unwrap().insert(tx_hash, settled);
+        }
     }
 
     impl Default for NodeApiMockClient {
@@ -522,6 +752,20 @@ pub mod test {
             })
         }
 
+        fn get_settled_tx(
+            &self,
+            blob_tx_hash: TxHash,
+        ) -> Pin<Box<dyn Future<Output = Result<APISettledTx>> + Send + '_>> {
+            Box::pin(async move {
+                self.settled_txs
+                    .lock()
+                    .unwrap()
+                    .get(&blob_tx_hash)
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("Settled transaction not found"))
+            })
+        }
+
         fn get_settled_height(
             &self,
             _contract_name: ContractName,
@@ -530,3 +774,16 @@ pub mod test {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::url_encode;
+
+    #[test]
+    fn test_url_encode() {
+        assert_eq!(url_encode("bob@hydentity"), "bob%40hydentity");
+        assert_eq!(url_encode("a b/c?d&e#f"), "a%20b%2Fc%3Fd%26e%23f");
+        assert_eq!(url_encode("Az09-_.~"), "Az09-_.~");
+        assert_eq!(url_encode("é"), "%C3%A9");
+    }
+}
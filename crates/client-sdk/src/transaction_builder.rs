@@ -3,16 +3,17 @@ use std::{
     collections::{BTreeMap, HashMap},
     future::Future,
     ops::{Deref, DerefMut},
-    sync::{Arc, OnceLock},
+    pin::Pin,
+    sync::{Arc, Mutex, OnceLock},
 };
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use sdk::{
     Blob, BlobIndex, BlobTransaction, Calldata, ContractAction, ContractName, Hashed, HyleOutput,
     Identity, ProofTransaction, RegisterContractEffect, StateCommitment, TxContext,
 };
 
-use crate::helpers::ClientSdkProver;
+use crate::helpers::{ClientSdkProver, ExecutionCost};
 
 pub struct ProvableBlobTx {
     pub identity: Identity,
@@ -54,6 +55,87 @@ impl ProvableBlobTx {
     pub fn add_context(&mut self, tx_context: TxContext) {
         self.tx_context = Some(tx_context);
     }
+
+    /// Digest to sign to authorize the blobs added so far with `nonce`, see [sdk::nonce]
+    pub fn authorization_digest(&self, nonce: u32) -> [u8; 32] {
+        sdk::nonce::authorization_digest(
+            &self.identity,
+            nonce,
+            self.blobs
+                .iter()
+                .enumerate()
+                .map(|(index, blob)| (BlobIndex(index), blob)),
+        )
+    }
+
+    /// Adds the signature blob authorizing the blobs added so far with the next nonce of the identity.
+    /// `sign` builds the signature blob from the digest to sign. It has to be the last blob added.
+    pub async fn add_signed_nonce<F>(&mut self, nonces: &NonceCache, sign: F) -> Result<u32>
+    where
+        F: FnOnce([u8; 32]) -> Result<Blob>,
+    {
+        let nonce = nonces.next(&self.identity).await?;
+        match sign(self.authorization_digest(nonce)) {
+            Ok(blob) => {
+                self.blobs.push(blob);
+                Ok(nonce)
+            }
+            Err(e) => {
+                nonces.reset(&self.identity);
+                Err(e)
+            }
+        }
+    }
+}
+
+type NonceFetcher =
+    Box<dyn Fn(Identity) -> Pin<Box<dyn Future<Output = Result<u32>> + Send>> + Send + Sync>;
+
+/// Nonces of identities, fetched once (e.g. from an indexer) then incremented locally for each
+/// transaction signed with [ProvableBlobTx::add_signed_nonce].
+pub struct NonceCache {
+    fetch: NonceFetcher,
+    nonces: Mutex<HashMap<Identity, u32>>,
+}
+
+impl NonceCache {
+    pub fn new<F, Fut>(fetch: F) -> Self
+    where
+        F: Fn(Identity) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<u32>> + Send + 'static,
+    {
+        NonceCache {
+            fetch: Box::new(move |identity| Box::pin(fetch(identity))),
+            nonces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the next nonce of the identity and increments it
+    pub async fn next(&self, identity: &Identity) -> Result<u32> {
+        let mut fetched = None;
+        if !self.nonces.lock().unwrap().contains_key(identity) {
+            fetched = Some(
+                (self.fetch)(identity.clone())
+                    .await
+                    .context(format!("fetching nonce of {identity}"))?,
+            );
+        }
+        let mut nonces = self.nonces.lock().unwrap();
+        let nonce = match fetched {
+            Some(fetched) => nonces.entry(identity.clone()).or_insert(fetched),
+            None => nonces
+                .get_mut(identity)
+                .context(format!("nonce of {identity} was reset while in use"))?,
+        };
+        let next = *nonce;
+        *nonce += 1;
+        Ok(next)
+    }
+
+    /// Forgets the nonce of the identity, to fetch it again after a transaction failed
+    pub fn reset(&self, identity: &Identity) {
+        self.nonces.lock().unwrap().remove(identity);
+    }
 }
 
 impl From<ProvableBlobTx> for BlobTransaction {
@@ -67,6 +149,8 @@ pub struct ProofTxBuilder {
     pub blobs: Vec<Blob>,
     runners: Vec<ContractRunner>,
     pub outputs: Vec<(ContractName, HyleOutput)>,
+    /// Execution cost of each blob, filled when the executor meters, see [TxExecutorBuilder::with_metering]
+    pub costs: Vec<(ContractName, ExecutionCost)>,
     provers: BTreeMap<ContractName, Arc<dyn ClientSdkProver<Vec<Calldata>> + Sync + Send>>,
 }
 
@@ -120,6 +204,16 @@ impl ProofTxBuilder {
     }
 }
 
+/// States of the contracts of a transaction before it was processed
+type StatesSnapshot = Vec<(ContractName, Box<dyn Any>)>;
+
+/// Transactions processed together by [TxExecutor::process_batch], each one executed on top of
+/// the optimistic states left by the previous ones.
+pub struct TxBatch {
+    pub txs: Vec<ProofTxBuilder>,
+    snapshots: Vec<StatesSnapshot>,
+}
+
 pub trait StateUpdater
 where
     Self: std::marker::Sized,
@@ -142,6 +236,7 @@ where
 pub struct TxExecutor<S: StateUpdater> {
     states: S,
     provers: BTreeMap<ContractName, Arc<dyn ClientSdkProver<Vec<Calldata>> + Sync + Send>>,
+    metering: bool,
 }
 
 impl<S: StateUpdater> Deref for TxExecutor<S> {
@@ -160,6 +255,7 @@ impl<S: StateUpdater> DerefMut for TxExecutor<S> {
 pub struct TxExecutorBuilder<S> {
     full_states: Option<S>,
     provers: BTreeMap<ContractName, Arc<dyn ClientSdkProver<Vec<Calldata>> + Sync + Send>>,
+    metering: bool,
 }
 
 impl<S: StateUpdater> TxExecutorBuilder<S> {
@@ -167,6 +263,7 @@ impl<S: StateUpdater> TxExecutorBuilder<S> {
         let mut ret = Self {
             full_states: None,
             provers: BTreeMap::new(),
+            metering: false,
         };
         full_states.setup(&mut ret);
         ret.full_states = Some(full_states);
@@ -178,6 +275,7 @@ impl<S: StateUpdater> TxExecutorBuilder<S> {
             // Safe to unwrap because we set it in the constructor
             states: self.full_states.unwrap(),
             provers: self.provers,
+            metering: self.metering,
         }
     }
 
@@ -200,6 +298,14 @@ impl<S: StateUpdater> TxExecutorBuilder<S> {
         self.provers.insert(contract_name, Arc::new(prover));
         self
     }
+
+    /// Runs each blob in the zkvm of its prover when processing a transaction, to report its
+    /// execution cost. This is slower than the native execution, it is meant for pricing and
+    /// benchmarking operations.
+    pub fn with_metering(mut self) -> Self {
+        self.metering = true;
+        self
+    }
 }
 
 impl<S: StateUpdater> TxExecutor<S> {
@@ -213,6 +319,132 @@ impl<S: StateUpdater> TxExecutor<S> {
         iter.into_iter().map(move |tx| self.process(tx))
     }
 
+    /// Executes the transactions in order, each one on top of the optimistic states left by the
+    /// previous ones, so that they can depend on each other while unsettled.
+    /// If one of them fails, the states are rolled back to before the batch.
+    pub fn process_batch<I>(&mut self, txs: I) -> Result<TxBatch>
+    where
+        I: IntoIterator<Item = ProvableBlobTx>,
+    {
+        let mut batch = TxBatch {
+            txs: vec![],
+            snapshots: vec![],
+        };
+        for (i, tx) in txs.into_iter().enumerate() {
+            let mut snapshot: StatesSnapshot = vec![];
+            for runner in tx.runners.iter() {
+                if !snapshot
+                    .iter()
+                    .any(|(name, _)| name == &runner.contract_name)
+                {
+                    let state = self.states.get(&runner.contract_name)?;
+                    snapshot.push((runner.contract_name.clone(), state));
+                }
+            }
+            match self.process(tx) {
+                Ok(proof_tx) => {
+                    batch.txs.push(proof_tx);
+                    batch.snapshots.push(snapshot);
+                }
+                Err(e) => {
+                    self.rollback_batch(batch, 0)?;
+                    return Err(e.context(format!("Processing transaction {i} of the batch")));
+                }
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Rolls the states back to before the transaction at index `from` of the batch, e.g. when it
+    /// failed to settle, as the following ones depending on it can't settle either.
+    pub fn rollback_batch(&mut self, batch: TxBatch, from: usize) -> Result<()> {
+        self.rollback_snapshots(batch.snapshots, from)
+    }
+
+    fn rollback_snapshots(&mut self, snapshots: Vec<StatesSnapshot>, from: usize) -> Result<()> {
+        for snapshot in snapshots.into_iter().skip(from).rev() {
+            for (contract_name, mut state) in snapshot {
+                self.states.update(&contract_name, &mut *state)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Proves all the transactions of the batch in parallel with the registered provers, as their
+    /// outputs were already computed in order, then sends each blob transaction with its proofs
+    /// in order as they complete, so that a transaction is only sent after the ones it depends on.
+    /// Returns the hashes of the blob transactions.
+    ///
+    /// If a transaction can't be proven or sent, neither it nor the following ones are sent, and
+    /// the states are rolled back to before it. The transactions sent before it are unaffected.
+    /// A blob transaction whose proofs failed to be sent times out.
+    #[cfg(feature = "rest")]
+    pub async fn submit_batch(
+        &mut self,
+        batch: TxBatch,
+        node: &(dyn crate::rest_client::NodeApiClient + Send + Sync),
+    ) -> Result<Vec<sdk::TxHash>> {
+        let TxBatch { txs, snapshots } = batch;
+
+        let mut proving = txs
+            .into_iter()
+            .map(|tx| {
+                let blob_tx = tx.to_blob_tx();
+                let handles = tx.iter_prove().map(tokio::spawn).collect::<Vec<_>>();
+                (blob_tx, handles)
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .enumerate();
+
+        let mut tx_hashes = vec![];
+        while let Some((i, (blob_tx, handles))) = proving.next() {
+            match Self::submit_tx(blob_tx, handles, node).await {
+                Ok(tx_hash) => tx_hashes.push(tx_hash),
+                Err(e) => {
+                    for (_, (_, handles)) in proving {
+                        handles.iter().for_each(|handle| handle.abort());
+                    }
+                    self.rollback_snapshots(snapshots, i)?;
+                    return Err(e.context(format!("Submitting transaction {i} of the batch")));
+                }
+            }
+        }
+        Ok(tx_hashes)
+    }
+
+    /// Waits for the proofs of the transaction, then sends it with its proofs
+    #[cfg(feature = "rest")]
+    async fn submit_tx(
+        blob_tx: BlobTransaction,
+        handles: Vec<tokio::task::JoinHandle<Result<ProofTransaction>>>,
+        node: &(dyn crate::rest_client::NodeApiClient + Send + Sync),
+    ) -> Result<sdk::TxHash> {
+        let mut handles = handles.into_iter();
+        let mut proofs = vec![];
+        while let Some(handle) = handles.next() {
+            let proof = match handle.await {
+                Ok(proof) => proof,
+                Err(e) => Err(e.into()),
+            };
+            match proof {
+                Ok(proof) => proofs.push(proof),
+                Err(e) => {
+                    for handle in handles {
+                        handle.abort();
+                    }
+                    return Err(e.context("Proving transaction"));
+                }
+            }
+        }
+
+        let tx_hash = node.send_tx_blob(blob_tx).await?;
+        for proof in proofs {
+            node.send_tx_proof(proof).await?;
+        }
+        Ok(tx_hash)
+    }
+
     /// Executes the transaction and updates the state of the associated contracts.
     ///
     /// This function processes a given `ProvableBlobTx` by iterating over each blob,
@@ -230,10 +462,11 @@ impl<S: StateUpdater> TxExecutor<S> {
         let mut outputs = vec![];
         let mut old_states = HashMap::new();
 
-        // Keep track of all state involved in the transaction
-        for blob in tx.blobs.iter() {
-            let state = self.states.get(&blob.contract_name)?;
-            old_states.insert(blob.contract_name.clone(), state);
+        // Keep track of all state involved in the transaction.
+        // Blobs without runner, like native signature blobs, don't have a state.
+        for runner in tx.runners.iter() {
+            let state = self.states.get(&runner.contract_name)?;
+            old_states.insert(runner.contract_name.clone(), state);
         }
 
         for runner in tx.runners.iter_mut() {
@@ -262,7 +495,7 @@ impl<S: StateUpdater> TxExecutor<S> {
                     tracing::error!("Execution failed for {}: {}", runner.contract_name, e);
                     // Revert all state changes
                     for (contract_name, state) in old_states.iter_mut() {
-                        self.states.update(contract_name, &mut *state)?;
+                        self.states.update(contract_name, &mut **state)?;
                     }
                     bail!("Execution failed for {}: {}", runner.contract_name, e);
                 }
@@ -276,7 +509,7 @@ impl<S: StateUpdater> TxExecutor<S> {
                 );
                 // Revert all state changes
                 for (contract_name, state) in old_states.iter_mut() {
-                    self.states.update(contract_name, &mut *state)?;
+                    self.states.update(contract_name, &mut **state)?;
                 }
                 let program_error = std::str::from_utf8(&out.program_outputs).unwrap();
                 bail!(
@@ -290,14 +523,56 @@ impl<S: StateUpdater> TxExecutor<S> {
             outputs.push((runner.contract_name.clone(), out));
         }
 
+        let mut costs = vec![];
+        if self.metering {
+            for runner in tx.runners.iter() {
+                let cost = match self.meter(runner) {
+                    Ok(cost) => cost,
+                    Err(e) => {
+                        // Revert all state changes
+                        for (contract_name, state) in old_states.iter_mut() {
+                            self.states.update(contract_name, &mut **state)?;
+                        }
+                        return Err(e);
+                    }
+                };
+                tracing::info!(
+                    "Execution cost for {} blob {}: {} cycles, {:?} user cycles, {:?} segments, {:?} bytes of memory",
+                    runner.contract_name,
+                    runner.index,
+                    cost.cycles,
+                    cost.user_cycles,
+                    cost.segments,
+                    cost.memory
+                );
+                costs.push((runner.contract_name.clone(), cost));
+            }
+        }
+
         Ok(ProofTxBuilder {
             identity: tx.identity,
             blobs: tx.blobs,
             runners: tx.runners,
             outputs,
+            costs,
             provers: self.provers.clone(),
         })
     }
+
+    fn meter(&self, runner: &ContractRunner) -> Result<ExecutionCost> {
+        let prover = self
+            .provers
+            .get(&runner.contract_name)
+            .context(format!("No prover to meter {}", runner.contract_name))?;
+        let (Some(commitment_metadata), Some(calldata)) =
+            (runner.commitment_metadata.get(), runner.calldata.get())
+        else {
+            bail!("No program input to meter {}", runner.contract_name);
+        };
+        prover
+            .meter(commitment_metadata.clone(), vec![calldata.clone()])
+            .context(format!("Metering {}", runner.contract_name))
+    }
 }
 
 #[derive(Debug)]
@@ -451,3 +726,376 @@ macro_rules! contract_states {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    use sdk::{
+        nonce::{CheckSignedNonce, Nonces, SignatureScheme},
+        verifiers::Secp256k1Blob,
+        BlobData, Calldata, IndexedBlobs, ProgramId, Proof, ProofData, ProofMetadata, TxHash,
+        Verifier,
+    };
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::helpers::{ClientSdkProver, ExecutionCost, ProverInfo};
+
+    /// Adds its amount to the counter, fails to execute with 0, and fails to prove or meter with 13.
+    /// Proving 7 waits for 8 to be proving.
+    struct Add(u64);
+
+    impl ContractAction for Add {
+        fn as_blob(
+            &self,
+            contract_name: ContractName,
+            _caller: Option<BlobIndex>,
+            _callees: Option<Vec<BlobIndex>>,
+        ) -> Blob {
+            Blob {
+                contract_name,
+                data: BlobData(borsh::to_vec(&self.0).unwrap()),
+            }
+        }
+    }
+
+    fn amount(calldata: &Calldata) -> Result<u64> {
+        let blob = calldata
+            .blobs
+            .get(&calldata.index)
+            .context("missing blob")?;
+        Ok(borsh::from_slice(&blob.data.0)?)
+    }
+
+    static PROVING_EIGHT: AtomicBool = AtomicBool::new(false);
+
+    struct CounterProver;
+
+    impl ClientSdkProver<Vec<Calldata>> for CounterProver {
+        fn prove(
+            &self,
+            _commitment_metadata: Vec<u8>,
+            calldatas: Vec<Calldata>,
+        ) -> Pin<Box<dyn Future<Output = Result<Proof>> + Send + '_>> {
+            Box::pin(async move {
+                for calldata in calldatas.iter() {
+                    match amount(calldata)? {
+                        13 => bail!("unprovable amount"),
+                        8 => PROVING_EIGHT.store(true, Ordering::SeqCst),
+                        7 => {
+                            while !PROVING_EIGHT.load(Ordering::SeqCst) {
+                                tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(Proof {
+                    data: ProofData(vec![]),
+                    metadata: ProofMetadata {
+                        cycles: None,
+                        prover: None,
+                        id: None,
+                    },
+                })
+            })
+        }
+
+        fn info(&self) -> ProverInfo {
+            ProverInfo {
+                name: "CounterProver".to_string(),
+                zkvm: "test".to_string(),
+                version: "1.0.0".to_string(),
+            }
+        }
+
+        fn program_id(&self) -> ProgramId {
+            ProgramId(vec![])
+        }
+
+        fn verifier(&self) -> Verifier {
+            "test".into()
+        }
+
+        /// Costs 100 cycles per unit added
+        fn meter(
+            &self,
+            _commitment_metadata: Vec<u8>,
+            calldatas: Vec<Calldata>,
+        ) -> Result<ExecutionCost> {
+            let mut cycles = 0;
+            for calldata in calldatas.iter() {
+                match amount(calldata)? {
+                    13 => bail!("unmeterable amount"),
+                    amount => cycles += amount * 100,
+                }
+            }
+            Ok(ExecutionCost {
+                cycles,
+                ..Default::default()
+            })
+        }
+    }
+
+    struct Counter(u64);
+
+    impl StateUpdater for Counter {
+        fn setup(&self, ctx: &mut TxExecutorBuilder<Self>) {
+            ctx.init_with("counter".into(), CounterProver);
+        }
+
+        fn update(&mut self, _contract_name: &ContractName, new_state: &mut dyn Any) -> Result<()> {
+            self.0 = *new_state.downcast_ref::<u64>().context("not a counter")?;
+            Ok(())
+        }
+
+        fn get(&self, _contract_name: &ContractName) -> Result<Box<dyn Any>> {
+            Ok(Box::new(self.0))
+        }
+
+        fn build_commitment_metadata(
+            &self,
+            _contract_name: &ContractName,
+            _blob: &Blob,
+        ) -> Result<Vec<u8>> {
+            Ok(borsh::to_vec(&self.0)?)
+        }
+
+        fn execute(
+            &mut self,
+            _contract_name: &ContractName,
+            calldata: &Calldata,
+        ) -> Result<HyleOutput> {
+            let amount = amount(calldata)?;
+            if amount == 0 {
+                return Ok(HyleOutput {
+                    success: false,
+                    program_outputs: b"Nothing to add".to_vec(),
+                    ..HyleOutput::default()
+                });
+            }
+            self.0 += amount;
+            Ok(HyleOutput {
+                success: true,
+                ..HyleOutput::default()
+            })
+        }
+    }
+
+    fn adds(amounts: &[u64]) -> Vec<ProvableBlobTx> {
+        amounts
+            .iter()
+            .map(|amount| {
+                let mut tx = ProvableBlobTx::new("alice@counter".into());
+                tx.add_action("counter".into(), Add(*amount), None, None, None)
+                    .unwrap();
+                tx
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_process_batch() {
+        let mut executor = TxExecutorBuilder::new(Counter(0)).build();
+
+        let batch = executor.process_batch(adds(&[1, 2])).unwrap();
+        assert_eq!(batch.txs.len(), 2);
+        assert_eq!(executor.0, 3);
+
+        // A failing transaction rolls back the whole batch
+        assert!(executor.process_batch(adds(&[4, 0, 5])).is_err());
+        assert_eq!(executor.0, 3);
+    }
+
+    #[test]
+    fn test_rollback_batch() {
+        let mut executor = TxExecutorBuilder::new(Counter(0)).build();
+        let batch = executor.process_batch(adds(&[1, 2, 4])).unwrap();
+        assert_eq!(executor.0, 7);
+
+        executor.rollback_batch(batch, 1).unwrap();
+        assert_eq!(executor.0, 1);
+    }
+
+    #[test]
+    fn test_metering() {
+        let mut executor = TxExecutorBuilder::new(Counter(0)).build();
+        let proof_tx = executor.process(adds(&[3]).remove(0)).unwrap();
+        assert!(proof_tx.costs.is_empty());
+
+        let mut executor = TxExecutorBuilder::new(Counter(0)).with_metering().build();
+        let proof_tx = executor.process(adds(&[3]).remove(0)).unwrap();
+        assert_eq!(
+            proof_tx.costs,
+            vec![(
+                "counter".into(),
+                ExecutionCost {
+                    cycles: 300,
+                    ..Default::default()
+                }
+            )]
+        );
+        assert_eq!(executor.0, 3);
+
+        // A transaction that can't be metered is rolled back
+        let Err(err) = executor.process(adds(&[13]).remove(0)) else {
+            panic!("an unmeterable transaction should fail");
+        };
+        assert!(format!("{err:#}").contains("unmeterable amount"));
+        assert_eq!(executor.0, 3);
+    }
+
+    #[cfg(feature = "rest")]
+    #[tokio::test]
+    async fn test_submit_batch() {
+        use crate::rest_client::test::NodeApiMockClient;
+
+        let mut executor = TxExecutorBuilder::new(Counter(0)).build();
+        let node = NodeApiMockClient::new();
+
+        let batch = executor.process_batch(adds(&[1, 2])).unwrap();
+        let blob_txs: Vec<_> = batch.txs.iter().map(|tx| tx.to_blob_tx()).collect();
+        let tx_hashes = executor.submit_batch(batch, &node).await.unwrap();
+        assert_eq!(
+            tx_hashes,
+            blob_txs.iter().map(|tx| tx.hashed()).collect::<Vec<_>>()
+        );
+        assert_eq!(*node.pending_blobs.lock().unwrap(), blob_txs);
+        assert_eq!(node.pending_proofs.lock().unwrap().len(), 2);
+        assert_eq!(executor.0, 3);
+    }
+
+    #[cfg(feature = "rest")]
+    #[tokio::test]
+    async fn test_submit_batch_proves_in_parallel() {
+        use crate::rest_client::test::NodeApiMockClient;
+
+        let mut executor = TxExecutorBuilder::new(Counter(0)).build();
+        let node = NodeApiMockClient::new();
+
+        // The first transaction can only be proven while the second one is being proven
+        let batch = executor.process_batch(adds(&[7, 8])).unwrap();
+        let blob_txs: Vec<_> = batch.txs.iter().map(|tx| tx.to_blob_tx()).collect();
+        let submitted = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            executor.submit_batch(batch, &node),
+        )
+        .await
+        .expect("proofs should be generated in parallel");
+        assert_eq!(submitted.unwrap().len(), 2);
+        assert_eq!(*node.pending_blobs.lock().unwrap(), blob_txs);
+    }
+
+    #[cfg(feature = "rest")]
+    #[tokio::test]
+    async fn test_submit_batch_partial_failure() {
+        use crate::rest_client::test::NodeApiMockClient;
+
+        let mut executor = TxExecutorBuilder::new(Counter(0)).build();
+        let node = NodeApiMockClient::new();
+
+        let batch = executor.process_batch(adds(&[1, 13, 2])).unwrap();
+        assert_eq!(executor.0, 16);
+        let first = batch.txs.first().unwrap().to_blob_tx();
+        assert!(executor.submit_batch(batch, &node).await.is_err());
+
+        // Only the transaction before the unprovable one is sent, with its proof
+        assert_eq!(*node.pending_blobs.lock().unwrap(), vec![first]);
+        assert_eq!(node.pending_proofs.lock().unwrap().len(), 1);
+        assert_eq!(executor.0, 1);
+    }
+
+    /// Cache fetching the nonce 5 for every identity, and counting its fetches
+    fn nonce_cache() -> (Arc<AtomicU32>, NonceCache) {
+        let fetches = Arc::new(AtomicU32::new(0));
+        let counter = fetches.clone();
+        let cache = NonceCache::new(move |_identity| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { Ok(5) }
+        });
+        (fetches, cache)
+    }
+
+    fn sign(identity: &Identity, digest: [u8; 32]) -> Result<Blob> {
+        Ok(Secp256k1Blob {
+            identity: identity.clone(),
+            data: Sha256::digest(digest).into(),
+            public_key: [2; 33],
+            signature: [0; 64],
+        }
+        .as_blob())
+    }
+
+    #[tokio::test]
+    async fn test_nonce_cache() {
+        let (fetches, cache) = nonce_cache();
+        let alice = Identity::new("alice@wallet");
+        let bob = Identity::new("bob@wallet");
+
+        assert_eq!(cache.next(&alice).await.unwrap(), 5);
+        assert_eq!(cache.next(&alice).await.unwrap(), 6);
+        assert_eq!(cache.next(&bob).await.unwrap(), 5);
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        // A reset nonce is fetched again
+        cache.reset(&alice);
+        assert_eq!(cache.next(&alice).await.unwrap(), 5);
+        assert_eq!(fetches.load(Ordering::SeqCst), 3);
+
+        let failing = NonceCache::new(|_identity| async { bail!("indexer unreachable") });
+        assert!(failing.next(&alice).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_add_signed_nonce() {
+        let (fetches, cache) = nonce_cache();
+        let identity = Identity::new("alice@wallet");
+        let mut tx = ProvableBlobTx::new(identity.clone());
+        tx.blobs.push(Blob {
+            contract_name: "wallet".into(),
+            data: BlobData(vec![1, 2, 3]),
+        });
+
+        let nonce = tx
+            .add_signed_nonce(&cache, |digest| sign(&identity, digest))
+            .await
+            .unwrap();
+        assert_eq!(nonce, 5);
+        assert_eq!(tx.blobs.len(), 2);
+
+        // The signature blob authorizes the transaction for the contract with that nonce
+        let calldata = Calldata {
+            tx_hash: TxHash::new("tx"),
+            identity: identity.clone(),
+            tx_blob_count: tx.blobs.len(),
+            blobs: IndexedBlobs::from(tx.blobs.clone()),
+            index: BlobIndex(0),
+            tx_ctx: None,
+            private_input: vec![],
+        };
+        assert_eq!(
+            CheckSignedNonce::new(&calldata, 5).expect().unwrap(),
+            vec![2; 33]
+        );
+        let mut nonces = Nonces(BTreeMap::from([(identity.clone(), 5)]));
+        assert_eq!(
+            nonces.use_nonce(&calldata, SignatureScheme::Secp256k1, &[2; 33]),
+            Ok(5)
+        );
+
+        // A failed signature resets the nonce, so that it is fetched again
+        let mut tx = ProvableBlobTx::new(identity.clone());
+        assert!(tx
+            .add_signed_nonce(&cache, |_| bail!("wallet locked"))
+            .await
+            .is_err());
+        assert!(tx.blobs.is_empty());
+        let nonce = tx
+            .add_signed_nonce(&cache, |digest| sign(&identity, digest))
+            .await
+            .unwrap();
+        assert_eq!(nonce, 5);
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,497 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use sdk::{
+    api::{APISettledTx, APITransactionEvents, TransactionStatusDb},
+    BlobTransaction, BlockHeight, TransactionStateEvent, TxHash,
+};
+use tokio::time::Instant;
+use tracing::debug;
+
+use crate::rest_client::{IndexerApiHttpClient, NodeApiClient};
+
+/// Stage of the lifecycle of a blob transaction to wait for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitUntil {
+    /// Included in a block, its blobs may not be proven yet
+    Sequenced,
+    /// Settled as a success or a failure, or timed out
+    Settled,
+}
+
+/// Last known stage of a blob transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxOutcome {
+    /// Sequenced in the block at `block_height`, not settled yet
+    Sequenced { block_height: BlockHeight },
+    /// Settled as a success in the block at `block_height`
+    Success { block_height: BlockHeight },
+    /// Rejected when sequenced, or settled as failed, in the block at `block_height`
+    Failure {
+        block_height: BlockHeight,
+        reason: Option<String>,
+    },
+    /// Not proven within the timeout window of its contracts, timed out in the block at `block_height`
+    TimedOut { block_height: BlockHeight },
+    /// No longer unsettled on the node, and settled too long ago for the node to know how.
+    /// Only reported without an indexer.
+    Settled { sequenced_at: BlockHeight },
+    /// The deadline passed before the awaited stage was reached
+    DeadlineExceeded { sequenced_at: Option<BlockHeight> },
+}
+
+impl TxOutcome {
+    /// Whether the transaction is at least at the `until` stage
+    pub fn reached(&self, until: WaitUntil) -> bool {
+        match self {
+            TxOutcome::Sequenced { .. } => until == WaitUntil::Sequenced,
+            TxOutcome::DeadlineExceeded { .. } => false,
+            _ => true,
+        }
+    }
+
+    /// Outcome of a transaction settled on the node
+    fn from_settled(settled: APISettledTx) -> TxOutcome {
+        let block_height = settled.block_height;
+        match settled.status {
+            TransactionStatusDb::Failure => TxOutcome::Failure {
+                block_height,
+                reason: None,
+            },
+            TransactionStatusDb::TimedOut => TxOutcome::TimedOut { block_height },
+            _ => TxOutcome::Success { block_height },
+        }
+    }
+
+    fn sequenced_at(&self) -> Option<BlockHeight> {
+        match self {
+            TxOutcome::Sequenced { block_height } => Some(*block_height),
+            TxOutcome::Settled { sequenced_at } => Some(*sequenced_at),
+            TxOutcome::DeadlineExceeded { sequenced_at } => *sequenced_at,
+            _ => None,
+        }
+    }
+
+    /// Outcome of a transaction from the state events stored by the indexer, if it was sequenced
+    pub fn from_events(events: &[APITransactionEvents]) -> Option<TxOutcome> {
+        let mut blocks = events.iter().collect::<Vec<_>>();
+        blocks.sort_by_key(|block| block.block_height);
+
+        let mut outcome = None;
+        let mut reason = None;
+        for block in blocks {
+            let block_height = block.block_height;
+            for event in &block.events {
+                let Ok(event) = serde_json::from_value::<TransactionStateEvent>(event.clone())
+                else {
+                    continue;
+                };
+                match event {
+                    TransactionStateEvent::Sequenced => {
+                        outcome = Some(TxOutcome::Sequenced { block_height })
+                    }
+                    TransactionStateEvent::Error(msg) | TransactionStateEvent::SettleEvent(msg) => {
+                        reason = Some(msg)
+                    }
+                    TransactionStateEvent::Settled => {
+                        return Some(TxOutcome::Success { block_height })
+                    }
+                    TransactionStateEvent::SettledAsFailed => {
+                        return Some(TxOutcome::Failure {
+                            block_height,
+                            reason,
+                        })
+                    }
+                    TransactionStateEvent::TimedOut => {
+                        return Some(TxOutcome::TimedOut { block_height })
+                    }
+                    TransactionStateEvent::NewProof { .. }
+                    | TransactionStateEvent::DroppedAsDuplicate => {}
+                }
+            }
+            // A transaction rejected by the node is never sequenced, its only event is the error
+            if outcome.is_none() && reason.is_some() {
+                return Some(TxOutcome::Failure {
+                    block_height,
+                    reason,
+                });
+            }
+        }
+        outcome
+    }
+}
+
+#[cfg(feature = "ws")]
+type WsStream =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Sends blob transactions and follows them until they are sequenced or settled.
+///
+/// Statuses are streamed from the indexer websocket with the `ws` feature, and polled from the
+/// indexer, or from the unsettled and settled transactions of the node when there is no indexer,
+/// otherwise.
+pub struct TxTracker {
+    pub node: Arc<dyn NodeApiClient + Send + Sync>,
+    pub indexer: Option<IndexerApiHttpClient>,
+    /// Maximum time to wait for a transaction, sending included
+    pub deadline: Duration,
+    pub poll_interval: Duration,
+}
+
+impl TxTracker {
+    pub fn new(node: Arc<dyn NodeApiClient + Send + Sync>) -> Self {
+        TxTracker {
+            node,
+            indexer: None,
+            deadline: Duration::from_secs(60),
+            poll_interval: Duration::from_millis(500),
+        }
+    }
+
+    pub fn with_indexer(mut self, indexer: IndexerApiHttpClient) -> Self {
+        self.indexer = Some(indexer);
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sends the transaction to the node, then waits for it to reach the `until` stage
+    pub async fn send_and_wait(&self, tx: BlobTransaction, until: WaitUntil) -> Result<TxOutcome> {
+        let deadline = Instant::now() + self.deadline;
+
+        // Subscribe before sending so that no status is missed
+        #[cfg(feature = "ws")]
+        let socket = {
+            use sdk::Hashed;
+            self.subscribe(&tx.hashed(), deadline).await
+        };
+
+        let tx_hash = self.node.send_tx_blob(tx).await?;
+
+        #[cfg(feature = "ws")]
+        if let Some(socket) = socket {
+            return self.wait_ws(socket, &tx_hash, until, deadline, None).await;
+        }
+
+        self.poll_until(&tx_hash, until, deadline, None).await
+    }
+
+    /// Waits for an already sent transaction to reach the `until` stage
+    pub async fn wait(&self, tx_hash: &TxHash, until: WaitUntil) -> Result<TxOutcome> {
+        let deadline = Instant::now() + self.deadline;
+
+        #[cfg(feature = "ws")]
+        if let Some(socket) = self.subscribe(tx_hash, deadline).await {
+            // Statuses sent before the subscription are missed, start from the current one
+            let mut sequenced_at = None;
+            match self.poll(tx_hash, None).await {
+                Ok(Some(outcome)) if outcome.reached(until) => return Ok(outcome),
+                Ok(Some(outcome)) => sequenced_at = outcome.sequenced_at(),
+                Ok(None) => {}
+                Err(e) => debug!("Polling status of tx {tx_hash}: {e:#}"),
+            }
+            return self
+                .wait_ws(socket, tx_hash, until, deadline, sequenced_at)
+                .await;
+        }
+
+        self.poll_until(tx_hash, until, deadline, None).await
+    }
+
+    async fn poll_until(
+        &self,
+        tx_hash: &TxHash,
+        until: WaitUntil,
+        deadline: Instant,
+        mut sequenced_at: Option<BlockHeight>,
+    ) -> Result<TxOutcome> {
+        loop {
+            match self.poll(tx_hash, sequenced_at).await {
+                Ok(Some(outcome)) if outcome.reached(until) => return Ok(outcome),
+                Ok(Some(outcome)) => sequenced_at = outcome.sequenced_at().or(sequenced_at),
+                Ok(None) => {}
+                Err(e) => debug!("Polling status of tx {tx_hash}: {e:#}"),
+            }
+            if Instant::now() + self.poll_interval >= deadline {
+                return Ok(TxOutcome::DeadlineExceeded { sequenced_at });
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    async fn poll(
+        &self,
+        tx_hash: &TxHash,
+        sequenced_at: Option<BlockHeight>,
+    ) -> Result<Option<TxOutcome>> {
+        if let Some(indexer) = &self.indexer {
+            let events = indexer.get_transaction_events(tx_hash).await?;
+            return Ok(TxOutcome::from_events(&events));
+        }
+        if let Ok(tx) = self.node.get_unsettled_tx(tx_hash.clone()).await {
+            return Ok(Some(TxOutcome::Sequenced {
+                block_height: tx.tx_context.block_height,
+            }));
+        }
+        // Settled transactions are looked up whether they were seen sequenced or not, as they
+        // can be settled in the block that sequences them
+        if let Ok(settled) = self.node.get_settled_tx(tx_hash.clone()).await {
+            return Ok(Some(TxOutcome::from_settled(settled)));
+        }
+        // The node answers the same for unknown and forgotten transactions, check it is reachable
+        self.node.get_block_height().await?;
+        Ok(sequenced_at.map(|sequenced_at| TxOutcome::Settled { sequenced_at }))
+    }
+
+    #[cfg(feature = "ws")]
+    async fn subscribe(&self, tx_hash: &TxHash, deadline: Instant) -> Option<WsStream> {
+        let indexer = self.indexer.as_ref()?;
+        // The indexer url ends with a slash, and its scheme is http or https
+        let url = format!("{}v1/indexer/ws", indexer.url).replacen("http", "ws", 1);
+        match tokio::time::timeout_at(deadline, connect_ws(&url, tx_hash)).await {
+            Ok(Ok(socket)) => Some(socket),
+            Ok(Err(e)) => {
+                tracing::warn!("Could not subscribe to {url}, polling tx status instead: {e:#}");
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    #[cfg(feature = "ws")]
+    async fn wait_ws(
+        &self,
+        mut socket: WsStream,
+        tx_hash: &TxHash,
+        until: WaitUntil,
+        deadline: Instant,
+        mut sequenced_at: Option<BlockHeight>,
+    ) -> Result<TxOutcome> {
+        use futures::StreamExt;
+        use sdk::api::ExplorerWsEvent;
+
+        loop {
+            let message = match tokio::time::timeout_at(deadline, socket.next()).await {
+                Err(_) => return Ok(TxOutcome::DeadlineExceeded { sequenced_at }),
+                Ok(Some(Ok(message))) => message,
+                Ok(_) => {
+                    tracing::warn!("Indexer websocket closed, polling status of tx {tx_hash}");
+                    return self
+                        .poll_until(tx_hash, until, deadline, sequenced_at)
+                        .await;
+                }
+            };
+            let Ok(text) = message.to_text() else {
+                continue;
+            };
+            let Ok(ExplorerWsEvent::TransactionStatus {
+                status,
+                block_height,
+                ..
+            }) = serde_json::from_str(text)
+            else {
+                continue;
+            };
+            let outcome = match status {
+                TransactionStatusDb::Sequenced => {
+                    sequenced_at = Some(block_height);
+                    TxOutcome::Sequenced { block_height }
+                }
+                TransactionStatusDb::Success => TxOutcome::Success { block_height },
+                TransactionStatusDb::Failure => TxOutcome::Failure {
+                    block_height,
+                    reason: self.failure_reason(tx_hash).await,
+                },
+                TransactionStatusDb::TimedOut => TxOutcome::TimedOut { block_height },
+                TransactionStatusDb::WaitingDissemination
+                | TransactionStatusDb::DataProposalCreated => continue,
+            };
+            if outcome.reached(until) {
+                return Ok(outcome);
+            }
+        }
+    }
+
+    /// Failure reason from the events stored by the indexer, they may not be stored yet
+    #[cfg(feature = "ws")]
+    async fn failure_reason(&self, tx_hash: &TxHash) -> Option<String> {
+        match self.poll(tx_hash, None).await {
+            Ok(Some(TxOutcome::Failure { reason, .. })) => reason,
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "ws")]
+async fn connect_ws(url: &str, tx_hash: &TxHash) -> Result<WsStream> {
+    use futures::{SinkExt, StreamExt};
+    use sdk::api::{ExplorerWsEvent, ExplorerWsRequest, ExplorerWsTopic};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(url).await?;
+    let topic = ExplorerWsTopic::TransactionStatus {
+        tx_hash: tx_hash.clone(),
+    };
+    let request = serde_json::to_string(&ExplorerWsRequest::Subscribe(topic.clone()))?;
+    socket.send(Message::Text(request.into())).await?;
+
+    // Statuses are only sent once the subscription is acknowledged
+    while let Some(message) = socket.next().await {
+        let message = message?;
+        if let Ok(ExplorerWsEvent::Subscribed { topic: subscribed }) =
+            serde_json::from_str(message.to_text()?)
+        {
+            if subscribed == topic {
+                return Ok(socket);
+            }
+        }
+    }
+    anyhow::bail!("Websocket closed before the subscription was acknowledged")
+}
+
+#[cfg(test)]
+mod tests {
+    use sdk::{BlobsHashes, DataProposalHash, Identity, TxContext};
+
+    use super::*;
+    use crate::rest_client::test::NodeApiMockClient;
+
+    fn unsettled_tx(tx_hash: &TxHash, block_height: BlockHeight) -> sdk::UnsettledBlobTransaction {
+        sdk::UnsettledBlobTransaction {
+            identity: Identity::new("alice@wallet"),
+            parent_dp_hash: DataProposalHash::default(),
+            hash: tx_hash.clone(),
+            tx_context: TxContext {
+                block_height,
+                ..TxContext::default()
+            },
+            blobs_hash: BlobsHashes::default(),
+            blobs: Default::default(),
+        }
+    }
+
+    fn tracker(node: &NodeApiMockClient) -> TxTracker {
+        TxTracker::new(Arc::new(node.clone()))
+            .with_deadline(Duration::from_millis(100))
+            .with_poll_interval(Duration::from_millis(10))
+    }
+
+    #[tokio::test]
+    async fn test_node_settled_without_sequenced() {
+        let node = NodeApiMockClient::new();
+        let tx_hash = TxHash::new("tx");
+
+        // Settled in the block that sequenced it, before the first poll
+        node.settle_tx(
+            tx_hash.clone(),
+            APISettledTx {
+                block_height: BlockHeight(4),
+                status: TransactionStatusDb::Success,
+            },
+        );
+        assert_eq!(
+            tracker(&node)
+                .wait(&tx_hash, WaitUntil::Settled)
+                .await
+                .unwrap(),
+            TxOutcome::Success {
+                block_height: BlockHeight(4)
+            }
+        );
+        // Settling is past sequencing
+        assert_eq!(
+            tracker(&node)
+                .wait(&tx_hash, WaitUntil::Sequenced)
+                .await
+                .unwrap(),
+            TxOutcome::Success {
+                block_height: BlockHeight(4)
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_node_settlement() {
+        let node = NodeApiMockClient::new();
+        let tx_hash = TxHash::new("tx");
+
+        // Unknown transactions are awaited until the deadline
+        assert_eq!(
+            tracker(&node)
+                .wait(&tx_hash, WaitUntil::Sequenced)
+                .await
+                .unwrap(),
+            TxOutcome::DeadlineExceeded { sequenced_at: None }
+        );
+
+        node.add_unsettled_tx(tx_hash.clone(), unsettled_tx(&tx_hash, BlockHeight(2)));
+        let sequenced = TxOutcome::Sequenced {
+            block_height: BlockHeight(2),
+        };
+        assert_eq!(
+            tracker(&node)
+                .wait(&tx_hash, WaitUntil::Sequenced)
+                .await
+                .unwrap(),
+            sequenced
+        );
+        assert_eq!(
+            tracker(&node)
+                .wait(&tx_hash, WaitUntil::Settled)
+                .await
+                .unwrap(),
+            TxOutcome::DeadlineExceeded {
+                sequenced_at: Some(BlockHeight(2))
+            }
+        );
+
+        // Settled while waiting
+        let settle = {
+            let node = node.clone();
+            let tx_hash = tx_hash.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(30)).await;
+                node.settle_tx(
+                    tx_hash,
+                    APISettledTx {
+                        block_height: BlockHeight(3),
+                        status: TransactionStatusDb::Failure,
+                    },
+                );
+            })
+        };
+        let outcome = tracker(&node)
+            .with_deadline(Duration::from_secs(5))
+            .wait(&tx_hash, WaitUntil::Settled)
+            .await
+            .unwrap();
+        settle.await.unwrap();
+        assert_eq!(
+            outcome,
+            TxOutcome::Failure {
+                block_height: BlockHeight(3),
+                reason: None
+            }
+        );
+
+        // Forgotten by the node once seen sequenced
+        node.settled_txs.lock().unwrap().clear();
+        assert_eq!(
+            tracker(&node)
+                .poll(&tx_hash, Some(BlockHeight(2)))
+                .await
+                .unwrap(),
+            Some(TxOutcome::Settled {
+                sequenced_at: BlockHeight(2)
+            })
+        );
+    }
+}
@@ -0,0 +1,488 @@
+//! Procedural macros of the Hyli contract SDK, re-exported by `hyle-contract-sdk`.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, Error, Expr, FnArg, Ident, ImplItem,
+    ImplItemFn, ItemImpl, Lit, LitStr, Meta, Pat, Path, Result, Type,
+};
+
+/// Generates the boilerplate of a contract from an impl block of its state.
+///
+/// Methods marked `#[action]` become the variants of the action enum, named after the method in
+/// UpperCamelCase (or `#[action(rename = "Name")]`), with the owned arguments of the method as
+/// fields. Arguments of type `&ExecutionContext`, `&mut ExecutionContext` and `&Calldata` are
+/// filled by the dispatch instead. Actions return `Result<T, E>` where `T` implements
+/// `ActionOutput` and `E: Into<String>`.
+///
+/// The method marked `#[commit]` computes the state commitment, the optional one marked
+/// `#[initialize]` runs before each execution.
+///
+/// Arguments of the attribute:
+/// - `action = Name`: name of the generated action enum, required.
+/// - `raw_blobs`: blobs are the borsh encoded action instead of a `StructuredBlobData`.
+/// - `client_feature = "client"`: also implements `TxExecutorHandler` behind this feature.
+/// - `schema_feature = "schema"`: behind this feature, the action enum derives
+///   `borsh::BorshSchema` and gets a `schema()` function returning its `ContractSchema`.
+/// - `crate = path`, `client_crate = path`: paths of the SDK crates, `sdk` and `client_sdk` by
+///   default.
+///
+/// The generated enum derives borsh and serde traits, so the contract crate must depend on
+/// `borsh` and `serde`.
+#[proc_macro_attribute]
+pub fn hyle_contract(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut config = Config::default();
+    let parser = syn::meta::parser(|meta| config.parse(meta));
+    parse_macro_input!(args with parser);
+    let item = parse_macro_input!(input as ItemImpl);
+
+    expand(config, item)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct Config {
+    action: Option<Ident>,
+    raw_blobs: bool,
+    client_feature: Option<LitStr>,
+    schema_feature: Option<LitStr>,
+    krate: Option<Path>,
+    client_crate: Option<Path>,
+}
+
+impl Config {
+    fn parse(&mut self, meta: syn::meta::ParseNestedMeta) -> Result<()> {
+        if meta.path.is_ident("action") {
+            self.action = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("raw_blobs") {
+            self.raw_blobs = true;
+        } else if meta.path.is_ident("client_feature") {
+            self.client_feature = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("schema_feature") {
+            self.schema_feature = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("crate") {
+            self.krate = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("client_crate") {
+            self.client_crate = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("unsupported hyle_contract argument"));
+        }
+        Ok(())
+    }
+}
+
+/// How an argument of an action method is filled by the dispatch
+enum Arg {
+    Field(Ident, Box<Type>),
+    ExecutionContext { mutable: bool },
+    Calldata,
+}
+
+struct Action {
+    variant: Ident,
+    method: Ident,
+    docs: Vec<String>,
+    args: Vec<Arg>,
+}
+
+fn expand(config: Config, mut item: ItemImpl) -> Result<TokenStream2> {
+    let Some(action_enum) = config.action.clone() else {
+        return Err(Error::new(
+            Span::call_site(),
+            "missing `action = Name` argument",
+        ));
+    };
+    if !item.generics.params.is_empty() {
+        return Err(Error::new(
+            item.generics.span(),
+            "hyle_contract does not support generic contracts",
+        ));
+    }
+    if let Some((_, path, _)) = &item.trait_ {
+        return Err(Error::new(
+            path.span(),
+            "hyle_contract must be put on an inherent impl block",
+        ));
+    }
+    let krate = config
+        .krate
+        .clone()
+        .unwrap_or_else(|| syn::parse_quote!(sdk));
+    let client_crate = config
+        .client_crate
+        .clone()
+        .unwrap_or_else(|| syn::parse_quote!(client_sdk));
+    let self_ty = item.self_ty.clone();
+
+    let mut actions = vec![];
+    let mut commit = None;
+    let mut initialize = None;
+    for impl_item in item.items.iter_mut() {
+        let ImplItem::Fn(method) = impl_item else {
+            continue;
+        };
+        if take_attribute(&mut method.attrs, "commit").is_some() {
+            commit = Some(method.sig.ident.clone());
+        }
+        if take_attribute(&mut method.attrs, "initialize").is_some() {
+            initialize = Some(method.sig.ident.clone());
+        }
+        if let Some(attr) = take_attribute(&mut method.attrs, "action") {
+            actions.push(parse_action(&attr, method)?);
+        }
+    }
+    let Some(commit) = commit else {
+        return Err(Error::new(
+            self_ty.span(),
+            "missing a `#[commit]` method computing the state commitment",
+        ));
+    };
+
+    let variants = actions.iter().map(|action| {
+        let variant = &action.variant;
+        let docs = &action.docs;
+        let fields: Vec<_> = action
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                Arg::Field(name, ty) => Some(quote!(#name: #ty)),
+                _ => None,
+            })
+            .collect();
+        if fields.is_empty() {
+            quote! { #(#[doc = #docs])* #variant }
+        } else {
+            quote! { #(#[doc = #docs])* #variant { #(#fields),* } }
+        }
+    });
+
+    let arms = actions.iter().map(|action| {
+        let variant = &action.variant;
+        let method = &action.method;
+        let fields = action.args.iter().filter_map(|arg| match arg {
+            Arg::Field(name, _) => Some(name),
+            _ => None,
+        });
+        let args = action.args.iter().map(|arg| match arg {
+            Arg::Field(name, _) => quote!(#name),
+            Arg::ExecutionContext { mutable: false } => quote!(&exec_ctx),
+            Arg::ExecutionContext { mutable: true } => quote!(&mut exec_ctx),
+            Arg::Calldata => quote!(calldata),
+        });
+        quote! {
+            #action_enum::#variant { #(#fields),* } => {
+                #krate::utils::action_result(self.#method(#(#args),*))?
+            }
+        }
+    });
+
+    let mutable_ctx = actions.iter().any(|action| {
+        action
+            .args
+            .iter()
+            .any(|arg| matches!(arg, Arg::ExecutionContext { mutable: true }))
+    });
+    let exec_ctx = if mutable_ctx {
+        quote!(mut exec_ctx)
+    } else {
+        quote!(exec_ctx)
+    };
+    let parse = if config.raw_blobs {
+        quote!(#krate::utils::parse_raw_calldata::<#action_enum>(calldata)?)
+    } else {
+        quote!(#krate::utils::parse_calldata::<#action_enum>(calldata)?)
+    };
+    let execute = if actions.is_empty() {
+        quote! {
+            let (action, _exec_ctx) = #parse;
+            match action {}
+        }
+    } else {
+        quote! {
+            let (action, #exec_ctx) = #parse;
+            let (output, effects) = match action {
+                #(#arms)*
+            };
+            Ok((output, exec_ctx, effects))
+        }
+    };
+    let initialize = initialize.map(|initialize| {
+        quote! {
+            fn initialize(&mut self) -> Result<(), #krate::alloc::string::String> {
+                self.#initialize()
+            }
+        }
+    });
+
+    let as_blob = if config.raw_blobs {
+        quote! {
+            #krate::Blob {
+                contract_name,
+                data: #krate::BlobData(
+                    ::borsh::to_vec(self).expect("failed to encode contract action"),
+                ),
+            }
+        }
+    } else {
+        quote! {
+            #krate::Blob {
+                contract_name,
+                data: #krate::BlobData::from(#krate::StructuredBlobData {
+                    caller,
+                    callees,
+                    parameters: ::core::clone::Clone::clone(self),
+                }),
+            }
+        }
+    };
+    let raw_as_blob = config.raw_blobs.then(|| {
+        quote! {
+            pub fn as_blob(&self, contract_name: #krate::ContractName) -> #krate::Blob {
+                <Self as #krate::ContractAction>::as_blob(self, contract_name, None, None)
+            }
+        }
+    });
+
+    let encoding = if config.raw_blobs {
+        quote!(#krate::ActionEncoding::Raw)
+    } else {
+        quote!(#krate::ActionEncoding::Structured)
+    };
+    let derive_schema = config
+        .schema_feature
+        .as_ref()
+        .map(|feature| quote!(#[cfg_attr(feature = #feature, derive(::borsh::BorshSchema))]));
+    let schema = config.schema_feature.as_ref().map(|feature| {
+        quote! {
+            /// Schema of the contract, describing the borsh layout of its actions
+            #[cfg(feature = #feature)]
+            pub fn schema() -> #krate::ContractSchema {
+                #krate::ContractSchema::new::<Self>(#encoding)
+            }
+        }
+    });
+
+    let tx_executor_handler = config.client_feature.as_ref().map(|feature| {
+        quote! {
+            #[cfg(feature = #feature)]
+            impl #client_crate::transaction_builder::TxExecutorHandler for #self_ty {
+                fn handle(
+                    &mut self,
+                    calldata: &#krate::Calldata,
+                ) -> ::anyhow::Result<#krate::HyleOutput> {
+                    let initial_state_commitment = <Self as #krate::ZkContract>::commit(self);
+                    let mut res = <Self as #krate::ZkContract>::execute(self, calldata);
+                    let next_state_commitment = <Self as #krate::ZkContract>::commit(self);
+                    Ok(#krate::utils::as_hyle_output(
+                        initial_state_commitment,
+                        next_state_commitment,
+                        calldata,
+                        &mut res,
+                    ))
+                }
+
+                fn build_commitment_metadata(
+                    &self,
+                    _blob: &#krate::Blob,
+                ) -> ::anyhow::Result<#krate::alloc::vec::Vec<u8>> {
+                    Ok(::borsh::to_vec(self)?)
+                }
+
+                fn construct_state(
+                    _register_blob: &#krate::RegisterContractEffect,
+                    metadata: &Option<#krate::alloc::vec::Vec<u8>>,
+                ) -> ::anyhow::Result<Self> {
+                    match #krate::ConstructorMetadata::initial_state_of(metadata)? {
+                        Some(state) => Ok(::borsh::from_slice(&state)?),
+                        None => Ok(::core::default::Default::default()),
+                    }
+                }
+
+                fn get_state_commitment(&self) -> #krate::StateCommitment {
+                    <Self as #krate::ZkContract>::commit(self)
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        #item
+
+        #[derive(
+            ::borsh::BorshSerialize,
+            ::borsh::BorshDeserialize,
+            ::serde::Serialize,
+            ::serde::Deserialize,
+            Debug,
+            Clone,
+            PartialEq,
+        )]
+        #derive_schema
+        pub enum #action_enum {
+            #(#variants),*
+        }
+
+        impl #action_enum {
+            #schema
+
+            #raw_as_blob
+        }
+
+        impl #krate::ContractAction for #action_enum {
+            #[allow(clippy::expect_used)]
+            fn as_blob(
+                &self,
+                contract_name: #krate::ContractName,
+                caller: Option<#krate::BlobIndex>,
+                callees: Option<#krate::alloc::vec::Vec<#krate::BlobIndex>>,
+            ) -> #krate::Blob {
+                let _ = (&caller, &callees);
+                #as_blob
+            }
+        }
+
+        impl #krate::ZkContract for #self_ty {
+            fn execute(&mut self, calldata: &#krate::Calldata) -> #krate::RunResult {
+                #execute
+            }
+
+            fn commit(&self) -> #krate::StateCommitment {
+                self.#commit()
+            }
+
+            #initialize
+        }
+
+        #tx_executor_handler
+    })
+}
+
+/// Removes the first attribute named `name` from `attrs`, and returns it
+fn take_attribute(attrs: &mut Vec<Attribute>, name: &str) -> Option<Attribute> {
+    let position = attrs.iter().position(|attr| attr.path().is_ident(name))?;
+    Some(attrs.remove(position))
+}
+
+fn parse_action(attr: &Attribute, method: &ImplItemFn) -> Result<Action> {
+    let mut variant = format_ident!("{}", upper_camel_case(&method.sig.ident.to_string()));
+    if let Meta::List(_) = attr.meta {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let name: LitStr = meta.value()?.parse()?;
+                variant = name.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported action argument"))
+            }
+        })?;
+    }
+
+    let mut args = vec![];
+    for input in &method.sig.inputs {
+        let FnArg::Typed(input) = input else {
+            continue;
+        };
+        if let Type::Reference(reference) = input.ty.as_ref() {
+            let arg = match last_segment(&reference.elem).as_deref() {
+                Some("ExecutionContext") => Arg::ExecutionContext {
+                    mutable: reference.mutability.is_some(),
+                },
+                Some("Calldata") if reference.mutability.is_none() => Arg::Calldata,
+                _ => {
+                    return Err(Error::new(
+                        input.ty.span(),
+                        "action arguments must be owned, besides &ExecutionContext, &mut ExecutionContext and &Calldata",
+                    ))
+                }
+            };
+            args.push(arg);
+            continue;
+        }
+        let Pat::Ident(name) = input.pat.as_ref() else {
+            return Err(Error::new(
+                input.pat.span(),
+                "action arguments must be plain identifiers",
+            ));
+        };
+        args.push(Arg::Field(name.ident.clone(), input.ty.clone()));
+    }
+
+    let docs = method
+        .attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(doc) if doc.path.is_ident("doc") => match &doc.value {
+                Expr::Lit(expr) => match &expr.lit {
+                    Lit::Str(doc) => Some(doc.value()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+
+    Ok(Action {
+        variant,
+        method: method.sig.ident.clone(),
+        docs,
+        args,
+    })
+}
+
+fn last_segment(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        _ => None,
+    }
+}
+
+fn upper_camel_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upper_camel_case() {
+        assert_eq!(upper_camel_case("transfer_from"), "TransferFrom");
+        assert_eq!(upper_camel_case("claim"), "Claim");
+    }
+
+    #[test]
+    fn test_expand_requires_commit() {
+        let item: ItemImpl = syn::parse_quote! {
+            impl Counter {
+                #[action]
+                fn increment(&mut self) -> Result<String, String> {
+                    Ok(String::new())
+                }
+            }
+        };
+        let config = Config {
+            action: Some(format_ident!("CounterAction")),
+            ..Config::default()
+        };
+        let error = expand(config, item).err().map(|e| e.to_string());
+        assert_eq!(
+            error.as_deref(),
+            Some("missing a `#[commit]` method computing the state commitment")
+        );
+    }
+}
@@ -21,10 +21,16 @@
 //!
 //! If your contract needs to exchange data with others, refer to [`StructuredBlobData`].
 //! More documentation about this will follow.
+//!
+//! ## Generating the boilerplate
+//!
+//! The [`hyle_contract`] attribute generates the action enum, its blob encoding and the
+//! [`ZkContract`] dispatch from the methods of the contract state.
 
 #![cfg_attr(not(test), no_std)]
 
-extern crate alloc;
+#[doc(hidden)]
+pub extern crate alloc;
 
 use alloc::string::String;
 use alloc::vec::Vec;
@@ -32,8 +38,12 @@ use alloc::vec::Vec;
 pub mod caller;
 pub mod guest;
 #[cfg(feature = "smt")]
+pub mod merkle_state;
+#[cfg(feature = "smt")]
 pub mod merkle_utils;
+pub mod nonce;
 pub mod secp256k1;
+pub mod supply;
 pub mod utils;
 
 use caller::ExecutionContext;
@@ -42,6 +52,8 @@ pub use hyle_model::*;
 
 pub use hyle_model::utils as hyle_model_utils;
 
+pub use hyle_contract_sdk_macros::hyle_contract;
+
 #[cfg(feature = "tracing")]
 pub use tracing;
 
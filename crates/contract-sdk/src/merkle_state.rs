@@ -0,0 +1,274 @@
+//! Contract states held in a sparse merkle tree. The guest doesn't receive the whole state, only
+//! the leaves touched by each calldata along with their merkle proof against the state commitment.
+//!
+//! The host side, holding the whole tree, is `client_sdk::merkle_state::MerkleState`.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use sha2::{Digest, Sha256};
+use sparse_merkle_tree::{traits::Value, H256};
+
+use crate::merkle_utils::{BorshableMerkleProof, SHA256Hasher};
+use crate::{Identity, StateCommitment, ZkContract};
+
+/// Key of a leaf of a merkle state
+pub trait MerkleKey {
+    fn merkle_key(&self) -> H256;
+}
+
+fn sha256(data: &[u8]) -> H256 {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    let result = hasher.finalize();
+    let mut h = [0u8; 32];
+    h.copy_from_slice(&result);
+    H256::from(h)
+}
+
+impl MerkleKey for Identity {
+    fn merkle_key(&self) -> H256 {
+        sha256(self.0.as_bytes())
+    }
+}
+
+impl MerkleKey for String {
+    fn merkle_key(&self) -> H256 {
+        sha256(self.as_bytes())
+    }
+}
+
+/// Keys and values of leaves of a merkle state
+pub type MerkleLeaves<K, V> = Vec<(K, V)>;
+
+/// Leaves touched by one calldata, and their merkle proof
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct MerkleStep<K, V> {
+    pub proof: BorshableMerkleProof,
+    /// Keys are unique, missing leaves have the zero value
+    pub leaves: Vec<(K, V)>,
+}
+
+impl<K: MerkleKey + PartialEq, V: Value> MerkleStep<K, V> {
+    pub fn get(&self, key: &K) -> Result<&V, String> {
+        self.leaves
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+            .ok_or_else(|| "Key not in the merkle witness".to_string())
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Result<&mut V, String> {
+        self.leaves
+            .iter_mut()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+            .ok_or_else(|| "Key not in the merkle witness".to_string())
+    }
+
+    fn hashed_leaves(&self) -> Vec<(H256, H256)> {
+        self.leaves
+            .iter()
+            .map(|(k, v)| (k.merkle_key(), v.to_h256()))
+            .collect()
+    }
+}
+
+/// Commitment metadata of a merkle state: the root, and the leaves touched by each calldata
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct MerkleWitness<K, V> {
+    pub commitment: StateCommitment,
+    /// 1 step per calldata, in reverse order (last step is 1st calldata)
+    pub steps: Vec<MerkleStep<K, V>>,
+    /// Leaves written by the steps applied so far, read by the host to update the whole tree
+    #[borsh(skip)]
+    applied: Vec<(K, V)>,
+}
+
+impl<K: MerkleKey + PartialEq + Clone, V: Value + Clone> MerkleWitness<K, V> {
+    pub fn new(commitment: StateCommitment, steps: Vec<MerkleStep<K, V>>) -> Self {
+        MerkleWitness {
+            commitment,
+            steps,
+            applied: Vec::new(),
+        }
+    }
+
+    /// Takes the leaves of the current calldata, once checked against the commitment
+    pub fn next_step(&mut self) -> Result<MerkleStep<K, V>, String> {
+        let step = self
+            .steps
+            .pop()
+            .ok_or_else(|| "No merkle witness for this calldata".to_string())?;
+        let root: [u8; 32] = self
+            .commitment
+            .0
+            .as_slice()
+            .try_into()
+            .map_err(|_| "Invalid merkle root".to_string())?;
+        let verified = step
+            .proof
+            .0
+            .clone()
+            .verify::<SHA256Hasher>(&H256::from(root), step.hashed_leaves())
+            .map_err(|e| format!("Failed to verify merkle proof: {e:?}"))?;
+        if !verified {
+            return Err("Merkle proof invalid".to_string());
+        }
+        Ok(step)
+    }
+
+    /// Moves the commitment to the root of the tree with the leaves of the step
+    pub fn apply(&mut self, step: MerkleStep<K, V>) -> Result<(), String> {
+        let new_root = step
+            .proof
+            .0
+            .clone()
+            .compute_root::<SHA256Hasher>(step.hashed_leaves())
+            .map_err(|e| format!("Failed to compute merkle root: {e:?}"))?;
+        self.commitment = StateCommitment(Into::<[u8; 32]>::into(new_root).to_vec());
+        self.applied.extend(step.leaves);
+        Ok(())
+    }
+
+    /// Adds the steps of the calldatas of `next`, executed after the ones of `self`
+    pub fn merge(&mut self, next: Self) {
+        self.steps.splice(0..0, next.steps);
+    }
+
+    pub fn applied(&self) -> &[(K, V)] {
+        &self.applied
+    }
+}
+
+/// Contract whose state is a [MerkleWitness], so that the host can build its commitment metadata
+/// and execute it on the whole tree with `client_sdk::merkle_state::MerkleState`.
+///
+/// `execute` takes the [MerkleWitness::next_step] of the calldata, updates its leaves, then
+/// [MerkleWitness::apply] it. `commit` returns the commitment of the witness.
+pub trait MerkleStateContract: ZkContract + BorshSerialize + BorshDeserialize {
+    type Key: MerkleKey + PartialEq + Clone + Default + BorshSerialize + BorshDeserialize;
+    type Value: Value + Clone + Default + BorshSerialize + BorshDeserialize;
+    /// Action parsed from the structured blobs of the contract
+    type Action: BorshDeserialize;
+
+    /// Keys of the leaves read or written by the action. An action touching no keys gets no
+    /// [MerkleStep], it must not take one.
+    fn touched_keys(action: &Self::Action) -> Vec<Self::Key>;
+
+    fn from_witness(witness: MerkleWitness<Self::Key, Self::Value>) -> Self;
+
+    fn witness(&self) -> &MerkleWitness<Self::Key, Self::Value>;
+
+    fn witness_mut(&mut self) -> &mut MerkleWitness<Self::Key, Self::Value>;
+
+    /// Leaves of the state of a newly registered contract, from the initial state of its
+    /// constructor metadata
+    fn initial_leaves(
+        _initial_state: &Option<Vec<u8>>,
+    ) -> Result<MerkleLeaves<Self::Key, Self::Value>, String> {
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sparse_merkle_tree::{default_store::DefaultStore, SparseMerkleTree};
+
+    use super::*;
+
+    #[derive(Debug, Default, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+    struct Balance(u64);
+
+    impl Value for Balance {
+        fn to_h256(&self) -> H256 {
+            if self.0 == 0 {
+                return H256::zero();
+            }
+            sha256(&self.0.to_le_bytes())
+        }
+
+        fn zero() -> Self {
+            Balance(0)
+        }
+    }
+
+    type Tree = SparseMerkleTree<SHA256Hasher, Balance, DefaultStore<Balance>>;
+
+    fn commitment(tree: &Tree) -> StateCommitment {
+        StateCommitment(Into::<[u8; 32]>::into(*tree.root()).to_vec())
+    }
+
+    fn step(tree: &Tree, keys: &[&str]) -> MerkleStep<Identity, Balance> {
+        let keys: Vec<Identity> = keys.iter().map(|k| Identity::from(*k)).collect();
+        let proof = tree
+            .merkle_proof(keys.iter().map(|k| k.merkle_key()).collect())
+            .unwrap();
+        MerkleStep {
+            proof: BorshableMerkleProof(proof),
+            leaves: keys
+                .into_iter()
+                .map(|k| {
+                    let v = tree.get(&k.merkle_key()).unwrap();
+                    (k, v)
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_witness_verifies_and_updates_root() {
+        let mut tree = Tree::default();
+        tree.update(Identity::from("alice").merkle_key(), Balance(10))
+            .unwrap();
+        tree.update(Identity::from("bob").merkle_key(), Balance(5))
+            .unwrap();
+
+        let mut witness =
+            MerkleWitness::new(commitment(&tree), vec![step(&tree, &["alice", "carol"])]);
+        let mut step = witness.next_step().unwrap();
+        assert_eq!(step.get(&"carol".into()), Ok(&Balance(0)));
+        step.get_mut(&"alice".into()).unwrap().0 -= 3;
+        step.get_mut(&"carol".into()).unwrap().0 += 3;
+        witness.apply(step).unwrap();
+
+        tree.update(Identity::from("alice").merkle_key(), Balance(7))
+            .unwrap();
+        tree.update(Identity::from("carol").merkle_key(), Balance(3))
+            .unwrap();
+        assert_eq!(witness.commitment, commitment(&tree));
+        assert_eq!(witness.applied().len(), 2);
+    }
+
+    #[test]
+    fn test_witness_rejects_wrong_leaves() {
+        let mut tree = Tree::default();
+        tree.update(Identity::from("alice").merkle_key(), Balance(10))
+            .unwrap();
+
+        let mut tampered = step(&tree, &["alice"]);
+        *tampered.get_mut(&Identity::from("alice")).unwrap() = Balance(1000);
+        let mut witness = MerkleWitness::new(commitment(&tree), vec![tampered]);
+        assert!(witness.next_step().is_err());
+        assert_eq!(
+            witness.next_step().unwrap_err(),
+            "No merkle witness for this calldata"
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_calldata_order() {
+        let tree = Tree::default();
+        let mut first = MerkleWitness::new(commitment(&tree), vec![step(&tree, &["alice"])]);
+        let second = MerkleWitness::new(commitment(&tree), vec![step(&tree, &["bob"])]);
+        first.merge(second);
+
+        let encoded = borsh::to_vec(&first).unwrap();
+        let mut decoded: MerkleWitness<Identity, Balance> = borsh::from_slice(&encoded).unwrap();
+        assert!(decoded.next_step().unwrap().get(&"alice".into()).is_ok());
+        assert!(decoded.next_step().unwrap().get(&"bob".into()).is_ok());
+    }
+}
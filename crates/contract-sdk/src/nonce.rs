@@ -0,0 +1,308 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use borsh::{BorshDeserialize, BorshSerialize};
+use hyle_model::{verifiers::BlstSignatureBlob, Blob, BlobIndex, Calldata, ContractName, Identity};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::secp256k1::CheckSecp256k1;
+
+/// Native contract verifying the signature authorizing a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureScheme {
+    /// A [hyle_model::verifiers::Secp256k1Blob] signing the sha256 of the digest
+    Secp256k1,
+    /// A [BlstSignatureBlob] signing the digest concatenated with the identity
+    Blst,
+}
+
+impl SignatureScheme {
+    pub fn contract_name(&self) -> ContractName {
+        match self {
+            SignatureScheme::Secp256k1 => ContractName::new("secp256k1"),
+            SignatureScheme::Blst => ContractName::new("blst"),
+        }
+    }
+}
+
+fn is_signature_blob(blob: &Blob) -> bool {
+    blob.contract_name.0 == "secp256k1" || blob.contract_name.0 == "blst"
+}
+
+/// Digest an identity signs to authorize the blobs of a transaction with one of its nonces.
+///
+/// The transaction hash can't be signed as it covers the signature blob itself, so the digest
+/// covers what the hash is computed from: the identity and every other blob, with its index.
+pub fn authorization_digest<'a>(
+    identity: &Identity,
+    nonce: u32,
+    blobs: impl IntoIterator<Item = (BlobIndex, &'a Blob)>,
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"hyli-authorization:");
+    hasher.update(identity.0.as_bytes());
+    hasher.update(nonce.to_be_bytes());
+    for (index, blob) in blobs {
+        if is_signature_blob(blob) {
+            continue;
+        }
+        hasher.update((index.0 as u64).to_be_bytes());
+        hasher.update((blob.contract_name.0.len() as u64).to_be_bytes());
+        hasher.update(blob.contract_name.0.as_bytes());
+        hasher.update((blob.data.0.len() as u64).to_be_bytes());
+        hasher.update(&blob.data.0);
+    }
+    hasher.finalize().into()
+}
+
+/// Per-identity nonces of an identity contract, protecting it against replayed transactions.
+///
+/// Each transaction carries a signature blob over its [authorization_digest] with the next nonce
+/// of its identity. Once checked, the nonce is used and the same signature can't be replayed.
+/// Example usage:
+/// ```rust,no_run,compile_fail
+/// let account = self.accounts.get(&calldata.identity).ok_or("Unknown identity")?;
+/// let nonce = self
+///     .nonces
+///     .use_nonce(calldata, SignatureScheme::Secp256k1, &account.public_key)?;
+/// ```
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize,
+)]
+pub struct Nonces(pub BTreeMap<Identity, u32>);
+
+impl Nonces {
+    /// Next nonce expected from the identity
+    pub fn get(&self, identity: &Identity) -> u32 {
+        self.0.get(identity).copied().unwrap_or_default()
+    }
+
+    /// Checks that the transaction is authorized by its identity with its next nonce, signed by
+    /// `public_key`, the key registered for the identity. Then uses the nonce and returns it.
+    pub fn use_nonce(
+        &mut self,
+        calldata: &Calldata,
+        scheme: SignatureScheme,
+        public_key: &[u8],
+    ) -> Result<u32, &'static str> {
+        let nonce = self.get(&calldata.identity);
+        let signer = CheckSignedNonce::new(calldata, nonce)
+            .with_scheme(scheme)
+            .expect()?;
+        if signer != public_key {
+            return Err("Transaction is not signed by the identity's public key");
+        }
+        let next = nonce.checked_add(1).ok_or("Nonce overflow")?;
+        self.0.insert(calldata.identity.clone(), next);
+        Ok(nonce)
+    }
+}
+
+/// This struct allows to check that a signature blob authorizes the transaction with a nonce.
+/// It will check:
+/// - that all the blobs of the transaction are in the calldata.
+/// - the identity of the signature blob.
+/// - that the signed data is the [authorization_digest] of the transaction with the nonce.
+///
+/// The signature itself is natively verified by the node.
+pub struct CheckSignedNonce<'a> {
+    calldata: &'a Calldata,
+    nonce: u32,
+    scheme: SignatureScheme,
+    blob_index: Option<BlobIndex>,
+}
+
+impl<'a> CheckSignedNonce<'a> {
+    pub fn new(calldata: &'a Calldata, nonce: u32) -> Self {
+        Self {
+            calldata,
+            nonce,
+            scheme: SignatureScheme::Secp256k1,
+            blob_index: None,
+        }
+    }
+
+    pub fn with_scheme(mut self, scheme: SignatureScheme) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    pub fn with_blob_index(mut self, blob_index: BlobIndex) -> Self {
+        self.blob_index = Some(blob_index);
+        self
+    }
+
+    /// Returns the public key that signed the transaction
+    pub fn expect(self) -> Result<Vec<u8>, &'static str> {
+        if self.calldata.blobs.len() != self.calldata.tx_blob_count {
+            return Err("All blobs of the transaction are needed to check its nonce");
+        }
+        let digest = authorization_digest(
+            &self.calldata.identity,
+            self.nonce,
+            self.calldata
+                .blobs
+                .iter()
+                .map(|(index, blob)| (*index, blob)),
+        );
+
+        match self.scheme {
+            SignatureScheme::Secp256k1 => {
+                let mut check = CheckSecp256k1::new(self.calldata, &digest);
+                if let Some(blob_index) = self.blob_index {
+                    check = check.with_blob_index(blob_index);
+                }
+                Ok(check.expect()?.public_key.to_vec())
+            }
+            SignatureScheme::Blst => {
+                let contract_name = self.scheme.contract_name();
+                let blob = match self.blob_index {
+                    Some(index) => self
+                        .calldata
+                        .blobs
+                        .get(&index)
+                        .filter(|blob| blob.contract_name == contract_name)
+                        .ok_or("Invalid blob index for BlstSignatureBlob")?,
+                    None => self
+                        .calldata
+                        .blobs
+                        .iter()
+                        .map(|(_, blob)| blob)
+                        .find(|blob| blob.contract_name == contract_name)
+                        .ok_or("Missing BlstSignatureBlob")?,
+                };
+                let blst_data: BlstSignatureBlob = borsh::from_slice(&blob.data.0)
+                    .map_err(|_| "Failed to decode BlstSignatureBlob")?;
+                if blst_data.identity != self.calldata.identity {
+                    return Err("BlstSignatureBlob identity does not match");
+                }
+                if blst_data.data != digest {
+                    return Err("BlstSignatureBlob data does not match");
+                }
+                Ok(blst_data.public_key)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use hyle_model::{verifiers::Secp256k1Blob, BlobData, IndexedBlobs, TxHash};
+
+    fn calldata(identity: &str, blobs: Vec<Blob>) -> Calldata {
+        Calldata {
+            tx_hash: TxHash::new("tx"),
+            identity: Identity::new(identity),
+            tx_blob_count: blobs.len(),
+            blobs: IndexedBlobs(
+                blobs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, blob)| (BlobIndex(i), blob))
+                    .collect(),
+            ),
+            index: BlobIndex(0),
+            tx_ctx: None,
+            private_input: vec![],
+        }
+    }
+
+    fn action_blob() -> Blob {
+        Blob {
+            contract_name: "wallet".into(),
+            data: BlobData(vec![1, 2, 3]),
+        }
+    }
+
+    fn signed_blobs(identity: &str, nonce: u32) -> Vec<Blob> {
+        let identity = Identity::new(identity);
+        let action = action_blob();
+        let digest = authorization_digest(&identity, nonce, [(BlobIndex(0), &action)]);
+        vec![
+            action,
+            Secp256k1Blob {
+                identity,
+                data: Sha256::digest(digest).into(),
+                public_key: [2; 33],
+                signature: [0; 64],
+            }
+            .as_blob(),
+        ]
+    }
+
+    #[test]
+    fn test_use_nonce() {
+        let mut nonces = Nonces::default();
+        let first = calldata("alice@wallet", signed_blobs("alice@wallet", 0));
+        assert_eq!(
+            nonces.use_nonce(&first, SignatureScheme::Secp256k1, &[2; 33]),
+            Ok(0)
+        );
+        assert_eq!(nonces.get(&Identity::new("alice@wallet")), 1);
+
+        // The same signed transaction can't be replayed
+        assert!(nonces
+            .use_nonce(&first, SignatureScheme::Secp256k1, &[2; 33])
+            .is_err());
+
+        let second = calldata("alice@wallet", signed_blobs("alice@wallet", 1));
+        assert_eq!(
+            nonces.use_nonce(&second, SignatureScheme::Secp256k1, &[2; 33]),
+            Ok(1)
+        );
+        assert_eq!(nonces.get(&Identity::new("bob@wallet")), 0);
+    }
+
+    #[test]
+    fn test_use_nonce_foreign_key() {
+        let mut nonces = Nonces::default();
+        let tx = calldata("alice@wallet", signed_blobs("alice@wallet", 0));
+        assert_eq!(
+            nonces.use_nonce(&tx, SignatureScheme::Secp256k1, &[3; 33]),
+            Err("Transaction is not signed by the identity's public key")
+        );
+        // The nonce is not used by a rejected transaction
+        assert_eq!(nonces.get(&Identity::new("alice@wallet")), 0);
+    }
+
+    #[test]
+    fn test_signed_nonce_binds_blobs() {
+        let signature = signed_blobs("alice@wallet", 0).pop().unwrap();
+        let tampered = Blob {
+            data: BlobData(vec![4, 5, 6]),
+            ..action_blob()
+        };
+        assert_eq!(
+            CheckSignedNonce::new(&calldata("alice@wallet", vec![tampered, signature]), 0).expect(),
+            Err("Secp256k1Blob data does not match")
+        );
+
+        let mut partial = calldata("alice@wallet", signed_blobs("alice@wallet", 0));
+        partial.tx_blob_count += 1;
+        assert!(CheckSignedNonce::new(&partial, 0).expect().is_err());
+    }
+
+    #[test]
+    fn test_blst_signed_nonce() {
+        let identity = Identity::new("alice@wallet");
+        let action = action_blob();
+        let blst = BlstSignatureBlob {
+            identity: identity.clone(),
+            data: authorization_digest(&identity, 3, [(BlobIndex(0), &action)]).to_vec(),
+            signature: vec![],
+            public_key: vec![7; 48],
+        };
+        let calldata = calldata("alice@wallet", vec![action, blst.as_blob()]);
+        assert_eq!(
+            CheckSignedNonce::new(&calldata, 3)
+                .with_scheme(SignatureScheme::Blst)
+                .expect(),
+            Ok(vec![7; 48])
+        );
+        assert!(CheckSignedNonce::new(&calldata, 2)
+            .with_scheme(SignatureScheme::Blst)
+            .expect()
+            .is_err());
+    }
+}
@@ -0,0 +1,53 @@
+//! Roles over the supply of a token, and the events recorded when the supply or the roles change.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use hyle_model::Identity;
+use serde::{Deserialize, Serialize};
+
+/// Privileges over the supply of a token, managed by the admins.
+#[derive(
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub enum TokenRole {
+    /// Grants and revokes roles
+    Admin,
+    /// Mints tokens, within the supply cap of the token
+    Minter,
+    /// Burns its own tokens
+    Burner,
+}
+
+/// Changes of the supply and of the roles, recorded by the indexer.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum SupplyEvent {
+    Minted {
+        minter: Identity,
+        recipient: Identity,
+        amount: u128,
+    },
+    Burned {
+        burner: Identity,
+        amount: u128,
+    },
+    RoleGranted {
+        admin: Identity,
+        account: Identity,
+        role: TokenRole,
+    },
+    RoleRevoked {
+        admin: Identity,
+        account: Identity,
+        role: TokenRole,
+    },
+}
@@ -1,5 +1,6 @@
 use crate::{
     alloc::string::{String, ToString},
+    alloc::vec::Vec,
     caller::ExecutionContext,
     Identity, StructuredBlobData,
 };
@@ -8,8 +9,8 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use core::result::Result;
 
 use hyle_model::{
-    Blob, BlobIndex, Calldata, DropEndOfReader, HyleOutput, IndexedBlobs, StateCommitment,
-    StructuredBlob,
+    Blob, BlobIndex, Calldata, DropEndOfReader, HyleOutput, IndexedBlobs, OnchainEffect,
+    StateCommitment, StructuredBlob,
 };
 
 /// This function is used to parse the contract input blob data into a given template `Action`
@@ -251,6 +252,47 @@ where
     Ok(calldata.identity.clone())
 }
 
+/// Output of a contract action, converted into the `program_outputs` and onchain effects
+/// of the [HyleOutput]. Used by the code generated by [hyle_contract](crate::hyle_contract).
+pub trait ActionOutput {
+    fn into_output(self) -> (Vec<u8>, Vec<OnchainEffect>);
+}
+
+impl ActionOutput for String {
+    fn into_output(self) -> (Vec<u8>, Vec<OnchainEffect>) {
+        (self.into_bytes(), vec![])
+    }
+}
+
+impl ActionOutput for Vec<u8> {
+    fn into_output(self) -> (Vec<u8>, Vec<OnchainEffect>) {
+        (self, vec![])
+    }
+}
+
+impl ActionOutput for () {
+    fn into_output(self) -> (Vec<u8>, Vec<OnchainEffect>) {
+        (vec![], vec![])
+    }
+}
+
+impl<T: ActionOutput> ActionOutput for (T, Vec<OnchainEffect>) {
+    fn into_output(self) -> (Vec<u8>, Vec<OnchainEffect>) {
+        let (output, mut effects) = self.0.into_output();
+        effects.extend(self.1);
+        (output, effects)
+    }
+}
+
+/// Converts the result of a contract action into the outputs expected in a [crate::RunResult].
+pub fn action_result<T, E>(result: Result<T, E>) -> Result<(Vec<u8>, Vec<OnchainEffect>), String>
+where
+    T: ActionOutput,
+    E: Into<String>,
+{
+    result.map(ActionOutput::into_output).map_err(Into::into)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
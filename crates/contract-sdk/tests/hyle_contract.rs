@@ -0,0 +1,163 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use hyle_contract_sdk::{
+    caller::ExecutionContext, hyle_contract, Blob, BlobIndex, Calldata, ContractAction,
+    ContractName, Identity, IndexedBlobs, OnchainEffect, StateCommitment, TxHash, ZkContract,
+};
+
+#[derive(Debug, Default, BorshSerialize, BorshDeserialize)]
+struct Counter {
+    value: u64,
+    last_caller: Option<Identity>,
+}
+
+#[hyle_contract(
+    action = CounterAction,
+    schema_feature = "schema",
+    crate = hyle_contract_sdk
+)]
+impl Counter {
+    /// Adds `amount` to the counter
+    #[action]
+    fn increment(&mut self, amount: u64, exec_ctx: &ExecutionContext) -> Result<String, String> {
+        self.value = self
+            .value
+            .checked_add(amount)
+            .ok_or("Counter overflow".to_string())?;
+        self.last_caller = Some(exec_ctx.caller.clone());
+        Ok(format!("Counter is {}", self.value))
+    }
+
+    #[action(rename = "Clear")]
+    fn reset(&mut self) -> Result<(Vec<u8>, Vec<OnchainEffect>), &'static str> {
+        if self.value == 0 {
+            return Err("Counter is already reset");
+        }
+        self.value = 0;
+        Ok((vec![], vec![]))
+    }
+
+    #[commit]
+    fn commitment(&self) -> StateCommitment {
+        StateCommitment(self.value.to_le_bytes().to_vec())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Echo {
+    messages: Vec<String>,
+}
+
+#[hyle_contract(
+    action = EchoAction,
+    raw_blobs,
+    schema_feature = "schema",
+    crate = hyle_contract_sdk
+)]
+impl Echo {
+    #[action]
+    fn say(&mut self, message: String) -> Result<Vec<u8>, String> {
+        self.messages.push(message.clone());
+        Ok(message.into_bytes())
+    }
+
+    #[commit]
+    fn commitment(&self) -> StateCommitment {
+        StateCommitment(borsh::to_vec(&self.messages).unwrap_or_default())
+    }
+}
+
+fn calldata(blob: Blob) -> Calldata {
+    Calldata {
+        tx_hash: TxHash::new("tx"),
+        identity: Identity::new("alice@wallet"),
+        blobs: IndexedBlobs(vec![(BlobIndex(0), blob)]),
+        tx_blob_count: 1,
+        index: BlobIndex(0),
+        tx_ctx: None,
+        private_input: vec![],
+    }
+}
+
+#[test]
+fn test_structured_dispatch() {
+    let mut counter = Counter::default();
+
+    let blob = CounterAction::Increment { amount: 3 }.as_blob("counter".into(), None, None);
+    let (output, exec_ctx, effects) = counter.execute(&calldata(blob)).unwrap();
+    assert_eq!(output, b"Counter is 3".to_vec());
+    assert_eq!(exec_ctx.contract_name, ContractName::new("counter"));
+    assert!(effects.is_empty());
+    assert_eq!(counter.value, 3);
+    assert_eq!(counter.last_caller, Some(Identity::new("alice@wallet")));
+    assert_eq!(
+        counter.commit(),
+        StateCommitment(3u64.to_le_bytes().to_vec())
+    );
+
+    let blob = CounterAction::Clear.as_blob("counter".into(), None, None);
+    let (output, _, _) = counter.execute(&calldata(blob.clone())).unwrap();
+    assert!(output.is_empty());
+    assert_eq!(counter.value, 0);
+
+    let err = counter.execute(&calldata(blob)).unwrap_err();
+    assert_eq!(err, "Counter is already reset");
+}
+
+#[test]
+fn test_raw_dispatch() {
+    let mut echo = Echo::default();
+
+    let action = EchoAction::Say {
+        message: "hello".to_string(),
+    };
+    let blob = action.as_blob("echo".into());
+    assert_eq!(
+        borsh::from_slice::<EchoAction>(&blob.data.0).unwrap(),
+        action
+    );
+
+    let (output, _, _) = echo.execute(&calldata(blob)).unwrap();
+    assert_eq!(output, b"hello".to_vec());
+    assert_eq!(echo.messages, vec!["hello".to_string()]);
+
+    // A structured blob is not a valid raw action
+    let blob = CounterAction::Increment { amount: 1 }.as_blob("echo".into(), None, None);
+    assert!(echo.execute(&calldata(blob)).is_err());
+}
+
+#[cfg(feature = "schema")]
+#[test]
+fn test_schema() {
+    use hyle_contract_sdk::{ActionEncoding, FieldDefinition, TypeDefinition};
+
+    let schema = CounterAction::schema();
+    assert_eq!(schema.action, "CounterAction");
+    assert_eq!(schema.action_encoding, ActionEncoding::Structured);
+    let Some(TypeDefinition::Enum {
+        tag_width,
+        variants,
+    }) = schema.definitions.get("CounterAction")
+    else {
+        panic!("CounterAction is not an enum: {schema:?}");
+    };
+    assert_eq!(*tag_width, 1);
+    assert_eq!(
+        variants
+            .iter()
+            .map(|variant| (variant.discriminant, variant.name.as_str()))
+            .collect::<Vec<_>>(),
+        vec![(0, "Increment"), (1, "Clear")]
+    );
+    let increment = &variants.first().unwrap().declaration;
+    assert_eq!(
+        schema.definitions.get(increment),
+        Some(&TypeDefinition::Struct {
+            fields: vec![FieldDefinition {
+                name: Some("amount".to_string()),
+                declaration: "u64".to_string(),
+            }]
+        })
+    );
+
+    assert_eq!(EchoAction::schema().action_encoding, ActionEncoding::Raw);
+}
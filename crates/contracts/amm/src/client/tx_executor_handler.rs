@@ -61,6 +61,7 @@ pub fn new_pair(
     contract_name: ContractName,
     pair: (ContractName, ContractName),
     amounts: (u128, u128),
+    fee_bps: u32,
 ) -> anyhow::Result<()> {
     let idx = builder.blobs.len();
     builder.add_action(
@@ -68,6 +69,54 @@ pub fn new_pair(
         AmmAction::NewPair {
             pair: (pair.0.to_string(), pair.1.to_string()),
             amounts,
+            fee_bps,
+        },
+        None,
+        None,
+        Some(vec![BlobIndex(idx + 1), BlobIndex(idx + 2)]),
+    )?;
+    deposit(builder, contract_name, pair, amounts, idx)
+}
+
+pub fn add_liquidity(
+    builder: &mut ProvableBlobTx,
+    contract_name: ContractName,
+    pair: (ContractName, ContractName),
+    amounts: (u128, u128),
+    min_shares: u128,
+) -> anyhow::Result<()> {
+    let idx = builder.blobs.len();
+    builder.add_action(
+        contract_name.clone(),
+        AmmAction::AddLiquidity {
+            pair: (pair.0.to_string(), pair.1.to_string()),
+            amounts,
+            min_shares,
+        },
+        None,
+        None,
+        Some(vec![BlobIndex(idx + 1), BlobIndex(idx + 2)]),
+    )?;
+    deposit(builder, contract_name, pair, amounts, idx)
+}
+
+/// `amounts` are transferred to the caller, they must be the amounts owned by the shares when the
+/// transaction is executed, see [crate::Amm::get_liquidity_amounts]
+pub fn remove_liquidity(
+    builder: &mut ProvableBlobTx,
+    contract_name: ContractName,
+    pair: (ContractName, ContractName),
+    shares: u128,
+    amounts: (u128, u128),
+    min_amounts: (u128, u128),
+) -> anyhow::Result<()> {
+    let idx = builder.blobs.len();
+    builder.add_action(
+        contract_name,
+        AmmAction::RemoveLiquidity {
+            pair: (pair.0.to_string(), pair.1.to_string()),
+            shares,
+            min_amounts,
         },
         None,
         None,
@@ -75,9 +124,8 @@ pub fn new_pair(
     )?;
     builder.add_action(
         pair.0,
-        HyllarAction::TransferFrom {
-            owner: builder.identity.0.clone(),
-            recipient: contract_name.to_string(),
+        HyllarAction::Transfer {
+            recipient: builder.identity.0.clone(),
             amount: amounts.0,
         },
         None,
@@ -86,9 +134,8 @@ pub fn new_pair(
     )?;
     builder.add_action(
         pair.1,
-        HyllarAction::TransferFrom {
-            owner: builder.identity.0.clone(),
-            recipient: contract_name.to_string(),
+        HyllarAction::Transfer {
+            recipient: builder.identity.0.clone(),
             amount: amounts.1,
         },
         None,
@@ -98,18 +145,23 @@ pub fn new_pair(
     Ok(())
 }
 
+/// `amount_out` is transferred to the caller, it must be the output of the pool when the
+/// transaction is executed, see [crate::Amm::get_paired_amount]
 pub fn swap(
     builder: &mut ProvableBlobTx,
     contract_name: ContractName,
     pair: (ContractName, ContractName),
-    amounts: (u128, u128),
+    amount_in: u128,
+    amount_out: u128,
+    min_amount_out: u128,
 ) -> anyhow::Result<()> {
     let idx = builder.blobs.len();
     builder.add_action(
         contract_name.clone(),
         AmmAction::Swap {
             pair: (pair.0.to_string(), pair.1.to_string()),
-            amounts,
+            amount_in,
+            min_amount_out,
         },
         None,
         None,
@@ -121,7 +173,7 @@ pub fn swap(
         HyllarAction::TransferFrom {
             owner: builder.identity.0.clone(),
             recipient: contract_name.to_string(),
-            amount: amounts.0,
+            amount: amount_in,
         },
         None,
         Some(BlobIndex(idx)),
@@ -131,7 +183,7 @@ pub fn swap(
         pair.1,
         HyllarAction::Transfer {
             recipient: builder.identity.0.clone(),
-            amount: amounts.1,
+            amount: amount_out,
         },
         None,
         Some(BlobIndex(idx)),
@@ -140,3 +192,36 @@ pub fn swap(
 
     Ok(())
 }
+
+/// Transfers of both tokens of the pair from the caller to the amm, called by the blob at `idx`
+fn deposit(
+    builder: &mut ProvableBlobTx,
+    contract_name: ContractName,
+    pair: (ContractName, ContractName),
+    amounts: (u128, u128),
+    idx: usize,
+) -> anyhow::Result<()> {
+    builder.add_action(
+        pair.0,
+        HyllarAction::TransferFrom {
+            owner: builder.identity.0.clone(),
+            recipient: contract_name.to_string(),
+            amount: amounts.0,
+        },
+        None,
+        Some(BlobIndex(idx)),
+        None,
+    )?;
+    builder.add_action(
+        pair.1,
+        HyllarAction::TransferFrom {
+            owner: builder.identity.0.clone(),
+            recipient: contract_name.to_string(),
+            amount: amounts.1,
+        },
+        None,
+        Some(BlobIndex(idx)),
+        None,
+    )?;
+    Ok(())
+}
@@ -14,9 +14,14 @@ pub mod client;
 type TokenPair = (String, String);
 type TokenPairAmount = (u128, u128);
 
+/// Fees a pair can be created with, in basis points of the swapped amount
+pub const FEE_TIERS: [u32; 3] = [5, 30, 100];
+const BPS: u128 = 10_000;
+
 #[derive(
     Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, Ord, PartialOrd,
 )]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub struct UnorderedTokenPair {
     a: String,
     b: String,
@@ -47,9 +52,39 @@ impl Hash for UnorderedTokenPair {
     }
 }
 
+/// Liquidity pool of a pair. Reserves are in the order of the [UnorderedTokenPair].
+#[derive(
+    Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, Default, PartialEq,
+)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct Pool {
+    pub reserves: TokenPairAmount,
+    /// Taken on the input of swaps and left in the reserves, so it accrues to the shares.
+    pub fee_bps: u32,
+    pub total_shares: u128,
+    pub shares: BTreeMap<String, u128>,
+}
+
+impl Pool {
+    pub fn shares_of(&self, identity: &str) -> u128 {
+        self.shares.get(identity).copied().unwrap_or(0)
+    }
+
+    /// Amount of the other token received for `amount_in`, after the fee
+    fn amount_out(&self, amount_in: u128, reserve_in: u128, reserve_out: u128) -> Option<u128> {
+        let amount_in_with_fee = amount_in.checked_mul(BPS - u128::from(self.fee_bps))?;
+        let numerator = reserve_out.checked_mul(amount_in_with_fee)?;
+        let denominator = reserve_in
+            .checked_mul(BPS)?
+            .checked_add(amount_in_with_fee)?;
+        numerator.checked_div(denominator)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, Default)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub struct Amm {
-    pairs: BTreeMap<UnorderedTokenPair, TokenPairAmount>,
+    pairs: BTreeMap<UnorderedTokenPair, Pool>,
 }
 
 impl sdk::FullStateRevert for Amm {}
@@ -60,7 +95,8 @@ impl ZkContract for Amm {
         let output = match action {
             AmmAction::Swap {
                 pair,
-                amounts: (from_amount, to_amount),
+                amount_in,
+                min_amount_out,
             } => {
                 // Check that a blob for the transfer exists for first token in swap
                 execution_ctx.is_in_callee_blobs(
@@ -68,20 +104,25 @@ impl ZkContract for Amm {
                     HyllarAction::TransferFrom {
                         owner: execution_ctx.caller.0.clone(),
                         recipient: execution_ctx.contract_name.0.clone(),
-                        amount: from_amount,
+                        amount: amount_in,
                     },
                 )?;
-                // Check that a blob for the transfer exists for second token in swap
+                // Check that a blob for the transfer of the output exists for second token in swap
+                let amount_out = self.quote_swap(&pair, amount_in, min_amount_out)?;
                 execution_ctx.is_in_callee_blobs(
                     &ContractName(pair.1.clone()),
                     HyllarAction::Transfer {
                         recipient: execution_ctx.caller.0.clone(),
-                        amount: to_amount,
+                        amount: amount_out,
                     },
                 )?;
-                self.verify_swap(pair, from_amount, to_amount)
+                self.verify_swap(pair, amount_in, min_amount_out)
             }
-            AmmAction::NewPair { pair, amounts } => {
+            AmmAction::NewPair {
+                pair,
+                amounts,
+                fee_bps,
+            } => {
                 // Check that a blob for the transfer exists for first token in pair
                 execution_ctx.is_in_callee_blobs(
                     &ContractName(pair.0.clone()),
@@ -100,7 +141,57 @@ impl ZkContract for Amm {
                         amount: amounts.1,
                     },
                 )?;
-                self.create_new_pair(pair, amounts)
+                self.create_new_pair(&execution_ctx.caller.0, pair, amounts, fee_bps)
+            }
+            AmmAction::AddLiquidity {
+                pair,
+                amounts,
+                min_shares,
+            } => {
+                execution_ctx.is_in_callee_blobs(
+                    &ContractName(pair.0.clone()),
+                    HyllarAction::TransferFrom {
+                        owner: execution_ctx.caller.0.clone(),
+                        recipient: execution_ctx.contract_name.0.clone(),
+                        amount: amounts.0,
+                    },
+                )?;
+                execution_ctx.is_in_callee_blobs(
+                    &ContractName(pair.1.clone()),
+                    HyllarAction::TransferFrom {
+                        owner: execution_ctx.caller.0.clone(),
+                        recipient: execution_ctx.contract_name.0.clone(),
+                        amount: amounts.1,
+                    },
+                )?;
+                self.add_liquidity(&execution_ctx.caller.0, pair, amounts, min_shares)
+            }
+            AmmAction::RemoveLiquidity {
+                pair,
+                shares,
+                min_amounts,
+            } => {
+                let owed = self.quote_remove_liquidity(
+                    &execution_ctx.caller.0,
+                    &pair,
+                    shares,
+                    min_amounts,
+                )?;
+                execution_ctx.is_in_callee_blobs(
+                    &ContractName(pair.0.clone()),
+                    HyllarAction::Transfer {
+                        recipient: execution_ctx.caller.0.clone(),
+                        amount: owed.0,
+                    },
+                )?;
+                execution_ctx.is_in_callee_blobs(
+                    &ContractName(pair.1.clone()),
+                    HyllarAction::Transfer {
+                        recipient: execution_ctx.caller.0.clone(),
+                        amount: owed.1,
+                    },
+                )?;
+                self.remove_liquidity(&execution_ctx.caller.0, pair, shares, min_amounts)
             }
         };
         match output {
@@ -114,23 +205,67 @@ impl ZkContract for Amm {
     }
 }
 
+/// Amounts given in the order of `pair`, returned in the order of its [UnorderedTokenPair]
+fn normalize_amounts(pair: &TokenPair, amounts: TokenPairAmount) -> TokenPairAmount {
+    if pair.0 <= pair.1 {
+        amounts
+    } else {
+        (amounts.1, amounts.0)
+    }
+}
+
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = n / 2 + 1;
+    let mut y = (x + n / x) / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
 impl Amm {
-    pub fn new(pairs: BTreeMap<UnorderedTokenPair, TokenPairAmount>) -> Self {
+    pub fn new(pairs: BTreeMap<UnorderedTokenPair, Pool>) -> Self {
         Amm { pairs }
     }
 
+    pub fn pool(&self, token_a: String, token_b: String) -> Option<&Pool> {
+        self.pairs.get(&UnorderedTokenPair::new(token_a, token_b))
+    }
+
     pub fn get_paired_amount(
         &self,
         token_a: String,
         token_b: String,
         amount_a: u128,
     ) -> Option<u128> {
-        let pair = UnorderedTokenPair::new(token_a, token_b);
-        if let Some((k, x, y)) = self.pairs.get(&pair).map(|(x, y)| (x * y, *x, *y)) {
-            let amount_b = y - (k / (x + amount_a));
-            return Some(amount_b);
-        }
-        None
+        let pair = (token_a, token_b);
+        let pool = self.pool(pair.0.clone(), pair.1.clone())?;
+        let (reserve_a, reserve_b) = normalize_amounts(&pair, pool.reserves);
+        pool.amount_out(amount_a, reserve_a, reserve_b)
+    }
+
+    /// Amounts of the pair, in the order of the tokens given, owned by `shares` of its pool
+    pub fn get_liquidity_amounts(
+        &self,
+        token_a: String,
+        token_b: String,
+        shares: u128,
+    ) -> Option<TokenPairAmount> {
+        let pair = (token_a, token_b);
+        let pool = self.pool(pair.0.clone(), pair.1.clone())?;
+        let amounts = (
+            shares
+                .checked_mul(pool.reserves.0)?
+                .checked_div(pool.total_shares)?,
+            shares
+                .checked_mul(pool.reserves.1)?
+                .checked_div(pool.total_shares)?,
+        );
+        Some(normalize_amounts(&pair, amounts))
     }
 
     pub fn as_bytes(&self) -> Vec<u8> {
@@ -139,70 +274,228 @@ impl Amm {
 
     pub fn create_new_pair(
         &mut self,
+        provider: &str,
         pair: (String, String),
         amounts: TokenPairAmount,
+        fee_bps: u32,
     ) -> Result<String, String> {
         // Check that new pair is about two different tokens and that there is one blob for each
         if pair.0 == pair.1 {
             return Err("Swap can only happen between two different tokens".to_string());
         }
+        if !FEE_TIERS.contains(&fee_bps) {
+            return Err(format!(
+                "Invalid fee of {fee_bps} bps, allowed fee tiers are {FEE_TIERS:?}"
+            ));
+        }
 
+        let reserves = normalize_amounts(&pair, amounts);
         let normalized_pair = UnorderedTokenPair::new(pair.0, pair.1);
 
         if self.pairs.contains_key(&normalized_pair) {
             return Err(format!("Pair {normalized_pair:?} already exists"));
         }
 
-        let program_outputs = format!("Pair {normalized_pair:?} created");
+        let shares = amounts
+            .0
+            .checked_mul(amounts.1)
+            .map(isqrt)
+            .ok_or("Initial liquidity is too large")?;
+        if shares == 0 {
+            return Err("Initial liquidity can't be empty".to_string());
+        }
 
-        self.pairs.insert(normalized_pair, amounts);
+        let program_outputs =
+            format!("Pair {normalized_pair:?} created, {provider} received {shares} shares");
+
+        self.pairs.insert(
+            normalized_pair,
+            Pool {
+                reserves,
+                fee_bps,
+                total_shares: shares,
+                shares: BTreeMap::from([(provider.to_string(), shares)]),
+            },
+        );
 
         Ok(program_outputs)
     }
 
-    pub fn verify_swap(
+    pub fn add_liquidity(
         &mut self,
+        provider: &str,
         pair: TokenPair,
-        from_amount: u128,
-        to_amount: u128,
+        amounts: TokenPairAmount,
+        min_shares: u128,
     ) -> Result<String, String> {
-        // Check that swap is only about two different tokens
-        if pair.0 == pair.1 {
-            return Err("Swap can only happen between two different tokens".to_string());
+        let normalized_pair = UnorderedTokenPair::new(pair.0.clone(), pair.1.clone());
+        let Some(pool) = self.pairs.get_mut(&normalized_pair) else {
+            return Err(format!("Pair {pair:?} not found in AMM state"));
+        };
+        let (amount_a, amount_b) = normalize_amounts(&pair, amounts);
+
+        // Shares are minted for the smallest side of the deposit, the excess goes to the pool
+        let shares = if pool.total_shares == 0 {
+            amount_a.checked_mul(amount_b).map(isqrt)
+        } else {
+            amount_a
+                .checked_mul(pool.total_shares)
+                .and_then(|s| s.checked_div(pool.reserves.0))
+                .zip(
+                    amount_b
+                        .checked_mul(pool.total_shares)
+                        .and_then(|s| s.checked_div(pool.reserves.1)),
+                )
+                .map(|(shares_a, shares_b)| shares_a.min(shares_b))
         }
+        .ok_or("Invalid liquidity amounts")?;
 
-        // Compute x,y and check swap is legit (x*y=k)
+        if shares == 0 {
+            return Err("Liquidity added is too small to mint shares".to_string());
+        }
+        if shares < min_shares {
+            return Err(format!(
+                "Slippage exceeded: {shares} shares minted, expected at least {min_shares}"
+            ));
+        }
+
+        // A pool without shares starts over from the deposit, reserves it may still hold are not
+        // owned by the new provider
+        if pool.total_shares == 0 {
+            pool.reserves = (0, 0);
+        }
+        pool.reserves.0 += amount_a;
+        pool.reserves.1 += amount_b;
+        pool.total_shares += shares;
+        *pool.shares.entry(provider.to_string()).or_default() += shares;
+
+        Ok(format!(
+            "Added liquidity to pair {normalized_pair:?}, {provider} received {shares} shares"
+        ))
+    }
+
+    /// Amounts of the pair, in the order of `pair`, paid by [Amm::remove_liquidity]
+    fn quote_remove_liquidity(
+        &self,
+        provider: &str,
+        pair: &TokenPair,
+        shares: u128,
+        min_amounts: TokenPairAmount,
+    ) -> Result<TokenPairAmount, String> {
         let normalized_pair = UnorderedTokenPair::new(pair.0.clone(), pair.1.clone());
-        let is_normalized_order = pair.0 <= pair.1;
-        let Some((prev_x, prev_y)) = self.pairs.get_mut(&normalized_pair) else {
+        let Some(provided) = self
+            .pairs
+            .get(&normalized_pair)
+            .map(|p| p.shares_of(provider))
+        else {
             return Err(format!("Pair {pair:?} not found in AMM state"));
         };
-        let expected_to_amount = if is_normalized_order {
-            let amount = *prev_y - (*prev_x * *prev_y / (*prev_x + from_amount));
-            *prev_x += from_amount;
-            *prev_y -= amount; // we need to remove the full amount to avoid slipping
-            amount
+        if shares == 0 || shares > provided {
+            return Err(format!(
+                "Invalid amount of shares: {provider} has {provided} shares of pair {normalized_pair:?}"
+            ));
+        }
+        let owed = self
+            .get_liquidity_amounts(pair.0.clone(), pair.1.clone(), shares)
+            .ok_or("Invalid amount of shares")?;
+        if min_amounts.0 > owed.0 || min_amounts.1 > owed.1 {
+            return Err(format!(
+                "Slippage exceeded: {shares} shares are worth {} {} and {} {}",
+                owed.0, pair.0, owed.1, pair.1
+            ));
+        }
+        Ok(owed)
+    }
+
+    /// Pays the amounts owned by the shares burnt, if they are at least `min_amounts`
+    pub fn remove_liquidity(
+        &mut self,
+        provider: &str,
+        pair: TokenPair,
+        shares: u128,
+        min_amounts: TokenPairAmount,
+    ) -> Result<String, String> {
+        let owed = self.quote_remove_liquidity(provider, &pair, shares, min_amounts)?;
+        let normalized_pair = UnorderedTokenPair::new(pair.0.clone(), pair.1.clone());
+        let Some(pool) = self.pairs.get_mut(&normalized_pair) else {
+            return Err(format!("Pair {pair:?} not found in AMM state"));
+        };
+        let provided = pool.shares_of(provider);
+        let (paid_a, paid_b) = normalize_amounts(&pair, owed);
+        pool.reserves.0 -= paid_a;
+        pool.reserves.1 -= paid_b;
+        pool.total_shares -= shares;
+        if provided == shares {
+            pool.shares.remove(provider);
         } else {
-            let amount = *prev_x - (*prev_y * *prev_x / (*prev_y + from_amount));
-            *prev_y += from_amount;
-            *prev_x -= amount; // we need to remove the full amount to avoid slipping
-            amount
+            pool.shares.insert(provider.to_string(), provided - shares);
+        }
+
+        Ok(format!(
+            "Removed {shares} shares of pair {normalized_pair:?} for {} {} and {} {}",
+            owed.0, pair.0, owed.1, pair.1
+        ))
+    }
+
+    /// Amount of the second token of `pair` paid by [Amm::verify_swap] for `amount_in`
+    fn quote_swap(
+        &self,
+        pair: &TokenPair,
+        amount_in: u128,
+        min_amount_out: u128,
+    ) -> Result<u128, String> {
+        // Check that swap is only about two different tokens
+        if pair.0 == pair.1 {
+            return Err("Swap can only happen between two different tokens".to_string());
+        }
+        if amount_in == 0 {
+            return Err("Swap amount can't be zero".to_string());
+        }
+
+        // Compute the output with the fee taken on the input (x*y=k)
+        let Some(pool) = self.pool(pair.0.clone(), pair.1.clone()) else {
+            return Err(format!("Pair {pair:?} not found in AMM state"));
         };
+        let (reserve_in, reserve_out) = normalize_amounts(pair, pool.reserves);
+        let amount_out = pool
+            .amount_out(amount_in, reserve_in, reserve_out)
+            .ok_or("Invalid swap: amounts overflow")?;
 
-        // Assert that we transferred less than that, within 2%
-        if to_amount > expected_to_amount || to_amount < expected_to_amount * 98 / 100 {
+        if min_amount_out > amount_out {
             return Err(format!(
-                "Invalid swap: expected to receive between {} and {} {}",
-                expected_to_amount * 100 / 102,
-                expected_to_amount,
-                pair.1
+                "Invalid swap: expected to receive at most {} {}",
+                amount_out, pair.1
             ));
         }
+        Ok(amount_out)
+    }
+
+    /// Pays the output of the pool for `amount_in`, if it is at least `min_amount_out`
+    pub fn verify_swap(
+        &mut self,
+        pair: TokenPair,
+        amount_in: u128,
+        min_amount_out: u128,
+    ) -> Result<String, String> {
+        let amount_out = self.quote_swap(&pair, amount_in, min_amount_out)?;
+        let normalized_pair = UnorderedTokenPair::new(pair.0.clone(), pair.1.clone());
+        let is_normalized_order = pair.0 <= pair.1;
+        let Some(pool) = self.pairs.get_mut(&normalized_pair) else {
+            return Err(format!("Pair {pair:?} not found in AMM state"));
+        };
+
+        // The fee stays in the reserves for the shares
+        if is_normalized_order {
+            pool.reserves.0 += amount_in;
+            pool.reserves.1 -= amount_out;
+        } else {
+            pool.reserves.1 += amount_in;
+            pool.reserves.0 -= amount_out;
+        }
 
-        // At this point the contract has effectively taken some fees but we don't actually count them.
         Ok(format!(
             "Swap of {} {} for {} {} is valid",
-            from_amount, pair.0, to_amount, pair.1
+            amount_in, pair.0, amount_out, pair.1
         ))
     }
 }
@@ -216,15 +509,32 @@ impl TryFrom<StateCommitment> for Amm {
 }
 
 /// Enum representing the actions that can be performed by the Amm state.
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+///
+/// Amounts paid by the AMM are computed from its reserves when the action is executed, the
+/// transfer blobs to the caller must hold these exact amounts, and the `min_*` amounts bound them
+/// against slippage.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub enum AmmAction {
     Swap {
         pair: TokenPair, // User swaps the first token of the pair for the second token
-        amounts: TokenPairAmount,
+        amount_in: u128,
+        min_amount_out: u128,
     },
     NewPair {
         pair: TokenPair,
         amounts: TokenPairAmount,
+        fee_bps: u32,
+    },
+    AddLiquidity {
+        pair: TokenPair,
+        amounts: TokenPairAmount,
+        min_shares: u128,
+    },
+    RemoveLiquidity {
+        pair: TokenPair,
+        shares: u128,
+        min_amounts: TokenPairAmount,
     },
 }
 
@@ -246,18 +556,43 @@ impl ContractAction for AmmAction {
     }
 }
 
+/// Interface of the contract, to give in the constructor metadata when registering it
+#[cfg(feature = "schema")]
+pub fn contract_schema() -> sdk::ContractSchema {
+    sdk::ContractSchema::new::<AmmAction>(sdk::ActionEncoding::Structured)
+        .with_state::<Amm>()
+        .with_utf8_program_outputs()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::collections::BTreeMap;
 
-    #[test]
-    fn test_verify_swap_success() {
+    fn pool(reserves: TokenPairAmount, shares: &[(&str, u128)]) -> Pool {
+        Pool {
+            reserves,
+            fee_bps: 30,
+            total_shares: shares.iter().map(|(_, s)| s).sum(),
+            shares: shares.iter().map(|(id, s)| (id.to_string(), *s)).collect(),
+        }
+    }
+
+    fn state_with(reserves: TokenPairAmount) -> (Amm, UnorderedTokenPair) {
         let normalized_token_pair =
             UnorderedTokenPair::new("token1".to_string(), "token2".to_string());
-        let mut state = Amm {
-            pairs: BTreeMap::from([(normalized_token_pair.clone(), (20, 50))]),
+        let state = Amm {
+            pairs: BTreeMap::from([(
+                normalized_token_pair.clone(),
+                pool(reserves, &[("alice", 100)]),
+            )]),
         };
+        (state, normalized_token_pair)
+    }
+
+    #[test]
+    fn test_verify_swap_success() {
+        let (mut state, normalized_token_pair) = state_with((20, 50));
         println!(
             "default state: {:?}",
             Amm {
@@ -266,58 +601,62 @@ mod tests {
             .commit()
         );
 
-        let result = state.verify_swap(("token1".to_string(), "token2".to_string()), 5, 10);
+        let result = state.verify_swap(("token1".to_string(), "token2".to_string()), 5, 9);
         assert!(result.is_ok());
         // Assert that the amounts for the pair token1/token2 have been updated
-        assert_eq!(state.pairs.get(&normalized_token_pair), Some(&(25, 40)));
+        assert_eq!(state.pairs[&normalized_token_pair].reserves, (25, 41));
     }
 
     #[test]
     fn test_verify_opposite_swap_success() {
-        let normalized_token_pair =
-            UnorderedTokenPair::new("token1".to_string(), "token2".to_string());
-        let mut state = Amm {
-            pairs: BTreeMap::from([(normalized_token_pair.clone(), (20, 50))]),
-        };
+        let (mut state, normalized_token_pair) = state_with((20, 50));
 
-        let result = state.verify_swap(("token2".to_string(), "token1".to_string()), 50, 10);
+        let result = state.verify_swap(("token2".to_string(), "token1".to_string()), 50, 9);
 
         assert!(result.is_ok());
         // Assert that the amounts for the pair token1/token2 have been updated
-        println!("{:?}", state.pairs.get(&normalized_token_pair));
-        assert!(state.pairs.get(&normalized_token_pair) == Some(&(10, 100)));
+        assert_eq!(state.pairs[&normalized_token_pair].reserves, (11, 100));
     }
 
     #[test]
     fn test_verify_swap_success_with_slippage() {
-        let normalized_token_pair =
-            UnorderedTokenPair::new("token1".to_string(), "token2".to_string());
-        let mut state = Amm {
-            pairs: BTreeMap::from([(normalized_token_pair.clone(), (2000, 5000))]),
-        };
-        println!(
-            "default state: {:?}",
-            Amm {
-                pairs: BTreeMap::default(),
-            }
-            .commit()
-        );
+        let (mut state, normalized_token_pair) = state_with((2000, 5000));
 
         let result = state.verify_swap(("token1".to_string(), "token2".to_string()), 500, 980);
         assert!(result.is_ok());
         assert_eq!(
-            state.pairs.get(&normalized_token_pair),
-            Some(&(2500, 4000)) // 20 of token2 taken as 'fees'
+            state.pairs[&normalized_token_pair].reserves,
+            (2500, 4003) // 997 paid, above the bound of 980
         );
+        assert!(result.unwrap().contains("for 997 token2"));
+    }
+
+    #[test]
+    fn test_verify_swap_fee_accrues_to_pool() {
+        let (mut state, normalized_token_pair) = state_with((1_000_000, 1_000_000));
+
+        let out = state
+            .get_paired_amount("token1".to_string(), "token2".to_string(), 10_000)
+            .unwrap();
+        state
+            .verify_swap(("token1".to_string(), "token2".to_string()), 10_000, out)
+            .unwrap();
+        let back = state
+            .get_paired_amount("token2".to_string(), "token1".to_string(), out)
+            .unwrap();
+        state
+            .verify_swap(("token2".to_string(), "token1".to_string()), out, back)
+            .unwrap();
+
+        // The round trip costs the swapper the fees, which stay in the reserves
+        assert!(back < 10_000);
+        let (x, y) = state.pairs[&normalized_token_pair].reserves;
+        assert!(x * y > 1_000_000 * 1_000_000);
     }
 
     #[test]
     fn test_verify_swap_invalid_pair() {
-        let normalized_token_pair =
-            UnorderedTokenPair::new("token1".to_string(), "token2".to_string());
-        let mut state = Amm {
-            pairs: BTreeMap::from([(normalized_token_pair.clone(), (20, 50))]),
-        };
+        let (mut state, _) = state_with((20, 50));
 
         let result = state.verify_swap(
             ("token1".to_string(), "rubbish".to_string()), // Invalid pair
@@ -329,18 +668,17 @@ mod tests {
 
     #[test]
     fn test_verify_swap_invalid_swap_formula() {
-        let normalized_token_pair =
-            UnorderedTokenPair::new("token1".to_string(), "token2".to_string());
-        let mut state = Amm {
-            pairs: BTreeMap::from([(normalized_token_pair.clone(), (20, 50))]),
-        };
+        let (mut state, normalized_token_pair) = state_with((20, 50));
 
-        let result = state.verify_swap(("token1".to_string(), "token2".to_string()), 0, 50);
-        assert!(result.is_err());
+        let result = state.verify_swap(("token1".to_string(), "token2".to_string()), 5, 10);
         assert!(result
             .err()
             .unwrap()
             .contains("Invalid swap: expected to receive"));
+        assert_eq!(state.pairs[&normalized_token_pair].reserves, (20, 50));
+
+        let result = state.verify_swap(("token1".to_string(), "token2".to_string()), 0, 0);
+        assert!(result.is_err());
     }
 
     #[test]
@@ -349,30 +687,37 @@ mod tests {
             pairs: BTreeMap::new(),
         };
 
-        let result = state.create_new_pair(("token1".to_string(), "token2".to_string()), (20, 50));
+        let result = state.create_new_pair(
+            "alice",
+            ("token2".to_string(), "token1".to_string()),
+            (50, 20),
+            30,
+        );
 
         println!("result: {result:?}");
         assert!(result.is_ok());
         let normalized_token_pair =
             UnorderedTokenPair::new("token1".to_string(), "token2".to_string());
-        assert!(state.pairs.get(&normalized_token_pair) == Some(&(20, 50)));
+        let pool = &state.pairs[&normalized_token_pair];
+        assert_eq!(pool.reserves, (20, 50));
+        assert_eq!(pool.fee_bps, 30);
+        assert_eq!(pool.total_shares, 31);
+        assert_eq!(pool.shares_of("alice"), 31);
     }
 
     #[test]
     fn test_create_new_pair_already_exists() {
-        let normalized_token_pair =
-            UnorderedTokenPair::new("token1".to_string(), "token2".to_string());
-        let mut state = Amm {
-            pairs: BTreeMap::from([(
-                UnorderedTokenPair::new("token1".to_string(), "token2".to_string()),
-                (100, 200),
-            )]),
-        };
+        let (mut state, normalized_token_pair) = state_with((100, 200));
 
-        let result = state.create_new_pair(("token1".to_string(), "token2".to_string()), (20, 50));
+        let result = state.create_new_pair(
+            "bob",
+            ("token1".to_string(), "token2".to_string()),
+            (20, 50),
+            30,
+        );
 
         assert!(result.is_err());
-        assert!(state.pairs.get(&normalized_token_pair) == Some(&(100, 200)));
+        assert_eq!(state.pairs[&normalized_token_pair].reserves, (100, 200));
     }
 
     #[test]
@@ -382,25 +727,164 @@ mod tests {
         };
 
         let result = state.create_new_pair(
+            "alice",
             ("token1".to_string(), "token1".to_string()), // same tokens
             (20, 50),
+            30,
         );
 
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_create_new_pair_invalid_fee_or_liquidity() {
+        let mut state = Amm {
+            pairs: BTreeMap::new(),
+        };
+        let pair = ("token1".to_string(), "token2".to_string());
+
+        assert!(state
+            .create_new_pair("alice", pair.clone(), (20, 50), 42)
+            .is_err());
+        assert!(state
+            .create_new_pair("alice", pair.clone(), (0, 50), 30)
+            .is_err());
+        assert!(state.pairs.is_empty());
+    }
+
+    #[test]
+    fn test_add_liquidity_mints_proportional_shares() {
+        let (mut state, normalized_token_pair) = state_with((200, 500));
+
+        // Given in the reverse order of the pair, 10% of the pool on the smallest side
+        let result = state.add_liquidity(
+            "bob",
+            ("token2".to_string(), "token1".to_string()),
+            (60, 20),
+            10,
+        );
+        assert!(result.is_ok());
+
+        let pool = &state.pairs[&normalized_token_pair];
+        assert_eq!(pool.reserves, (220, 560));
+        assert_eq!(pool.total_shares, 110);
+        assert_eq!(pool.shares_of("bob"), 10);
+        assert_eq!(pool.shares_of("alice"), 100);
+    }
+
+    #[test]
+    fn test_add_liquidity_slippage() {
+        let (mut state, normalized_token_pair) = state_with((200, 500));
+
+        let result = state.add_liquidity(
+            "bob",
+            ("token1".to_string(), "token2".to_string()),
+            (20, 50),
+            11,
+        );
+        assert!(result.err().unwrap().contains("Slippage exceeded"));
+        assert_eq!(state.pairs[&normalized_token_pair].reserves, (200, 500));
+
+        let result = state.add_liquidity(
+            "bob",
+            ("token1".to_string(), "rubbish".to_string()),
+            (20, 50),
+            0,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_remove_liquidity_success() {
+        let (mut state, normalized_token_pair) = state_with((200, 500));
+
+        assert_eq!(
+            state.get_liquidity_amounts("token2".to_string(), "token1".to_string(), 40),
+            Some((200, 80))
+        );
+        let result = state.remove_liquidity(
+            "alice",
+            ("token2".to_string(), "token1".to_string()),
+            40,
+            (200, 80),
+        );
+        assert!(result.is_ok());
+
+        let pool = &state.pairs[&normalized_token_pair];
+        assert_eq!(pool.reserves, (120, 300));
+        assert_eq!(pool.total_shares, 60);
+        assert_eq!(pool.shares_of("alice"), 60);
+
+        // The amounts owned are paid whatever the bounds below them
+        let result = state.remove_liquidity(
+            "alice",
+            ("token1".to_string(), "token2".to_string()),
+            60,
+            (0, 0),
+        );
+        assert!(result.unwrap().contains("for 120 token1 and 300 token2"));
+        let pool = &state.pairs[&normalized_token_pair];
+        assert_eq!(pool.reserves, (0, 0));
+        assert!(pool.shares.is_empty());
+    }
+
+    #[test]
+    fn test_add_liquidity_to_pool_without_shares() {
+        let (mut state, normalized_token_pair) = state_with((200, 500));
+        let pool = state.pairs.get_mut(&normalized_token_pair).unwrap();
+        pool.total_shares = 0;
+        pool.shares.clear();
+
+        // The reserves left in the pool don't go to the next provider
+        let result = state.add_liquidity(
+            "bob",
+            ("token1".to_string(), "token2".to_string()),
+            (4, 9),
+            0,
+        );
+        assert!(result.is_ok());
+        let pool = &state.pairs[&normalized_token_pair];
+        assert_eq!(pool.reserves, (4, 9));
+        assert_eq!(pool.total_shares, 6);
+        assert_eq!(
+            state.get_liquidity_amounts("token1".to_string(), "token2".to_string(), 6),
+            Some((4, 9))
+        );
+    }
+
+    #[test]
+    fn test_remove_liquidity_invalid() {
+        let (mut state, normalized_token_pair) = state_with((200, 500));
+        let pair = ("token1".to_string(), "token2".to_string());
+
+        // More than owned
+        assert!(state
+            .remove_liquidity("alice", pair.clone(), 101, (0, 0))
+            .is_err());
+        // Not a provider
+        assert!(state
+            .remove_liquidity("bob", pair.clone(), 1, (0, 0))
+            .is_err());
+        // More than the shares are worth
+        let result = state.remove_liquidity("alice", pair, 10, (20, 51));
+        assert!(result.err().unwrap().contains("Slippage exceeded"));
+
+        assert_eq!(state.pairs[&normalized_token_pair].reserves, (200, 500));
+        assert_eq!(state.pairs[&normalized_token_pair].total_shares, 100);
+    }
+
     #[test]
     fn test_get_paired_amount_existing_pair() {
-        let pair = UnorderedTokenPair::new("token1".into(), "token2".into());
-        let mut pairs = BTreeMap::new();
-        pairs.insert(pair.clone(), (10, 20));
-        let state = Amm { pairs };
+        let (state, _) = state_with((10, 20));
 
         let result = state.get_paired_amount("token1".to_string(), "token2".to_string(), 5);
 
         assert!(result.is_some());
         let amount_b = result.unwrap();
-        assert_eq!(amount_b, 20 - (10 * 20 / (10 + 5)));
+        assert_eq!(amount_b, 20 * 5 * 9970 / (10 * 10_000 + 5 * 9970));
+
+        let result = state.get_paired_amount("token2".to_string(), "token1".to_string(), 5);
+        assert_eq!(result, Some(10 * 5 * 9970 / (20 * 10_000 + 5 * 9970)));
     }
 
     #[test]
@@ -416,29 +900,36 @@ mod tests {
 
     #[test]
     fn test_get_paired_amount_zero_amount_a() {
-        let pair = UnorderedTokenPair::new("token1".into(), "token2".into());
-        let mut pairs = BTreeMap::new();
-        pairs.insert(pair.clone(), (10, 20));
-        let state = Amm { pairs };
+        let (state, _) = state_with((10, 20));
 
         let result = state.get_paired_amount("token1".to_string(), "token2".to_string(), 0);
 
         assert!(result.is_some());
         let amount_b = result.unwrap();
-        assert_eq!(amount_b, 20 - (10 * 20 / 10));
+        assert_eq!(amount_b, 0);
     }
 
     #[test]
     fn test_get_paired_amount_division_by_zero() {
-        let pair = UnorderedTokenPair::new("token1".into(), "token2".into());
-        let mut pairs = BTreeMap::new();
-        pairs.insert(pair.clone(), (0, 20));
-        let state = Amm { pairs };
+        let (state, _) = state_with((0, 20));
 
         let result = state.get_paired_amount("token1".to_string(), "token2".to_string(), 5);
 
         assert!(result.is_some());
         let amount_b = result.unwrap();
         assert_eq!(amount_b, 20);
+
+        let (state, _) = state_with((0, 0));
+        let result = state.get_paired_amount("token1".to_string(), "token2".to_string(), 0);
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_isqrt() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(1), 1);
+        assert_eq!(isqrt(1000), 31);
+        assert_eq!(isqrt(1024), 32);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
     }
 }
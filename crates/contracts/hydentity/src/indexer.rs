@@ -25,6 +25,7 @@ impl ContractHandler for Hydentity {
     async fn api(store: ContractHandlerStore<Self>) -> (Router<()>, OpenApi) {
         let (router, api) = OpenApiRouter::default()
             .routes(routes!(get_state))
+            .routes(routes!(client_sdk::contract_indexer::get_state_at_height))
             .routes(routes!(get_nonce))
             .split_for_parts();
 
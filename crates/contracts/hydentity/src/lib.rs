@@ -42,12 +42,14 @@ impl ZkContract for Hydentity {
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub struct Hydentity {
     identities: BTreeMap<String, AccountInfo>,
 }
 
 /// Enum representing the actions that can be performed by the IdentityVerification contract.
 #[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub enum HydentityAction {
     RegisterIdentity { account: String },
     VerifyIdentity { account: String, nonce: u32 },
@@ -55,6 +57,7 @@ pub enum HydentityAction {
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub struct AccountInfo {
     pub hash: String,
     pub nonce: u32,
@@ -186,6 +189,14 @@ impl ContractAction for HydentityAction {
     }
 }
 
+/// Interface of the contract, to give in the constructor metadata when registering it
+#[cfg(feature = "schema")]
+pub fn contract_schema() -> sdk::ContractSchema {
+    sdk::ContractSchema::new::<HydentityAction>(sdk::ActionEncoding::Raw)
+        .with_state::<Hydentity>()
+        .with_utf8_program_outputs()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
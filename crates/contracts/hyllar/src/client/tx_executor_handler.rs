@@ -8,7 +8,7 @@ use sdk::{
     ZkContract,
 };
 
-use crate::{Hyllar, HyllarAction};
+use crate::{Hyllar, HyllarAction, TokenRole};
 
 pub mod metadata {
     pub const HYLLAR_ELF: &[u8] = include_bytes!("../../hyllar.img");
@@ -107,3 +107,66 @@ pub fn approve(
     )?;
     Ok(())
 }
+
+pub fn mint(
+    builder: &mut ProvableBlobTx,
+    contract_name: ContractName,
+    recipient: String,
+    amount: u128,
+) -> anyhow::Result<()> {
+    builder.add_action(
+        contract_name,
+        HyllarAction::Mint { recipient, amount },
+        None,
+        None,
+        None,
+    )?;
+    Ok(())
+}
+
+pub fn burn(
+    builder: &mut ProvableBlobTx,
+    contract_name: ContractName,
+    amount: u128,
+) -> anyhow::Result<()> {
+    builder.add_action(
+        contract_name,
+        HyllarAction::Burn { amount },
+        None,
+        None,
+        None,
+    )?;
+    Ok(())
+}
+
+pub fn grant_role(
+    builder: &mut ProvableBlobTx,
+    contract_name: ContractName,
+    account: String,
+    role: TokenRole,
+) -> anyhow::Result<()> {
+    builder.add_action(
+        contract_name,
+        HyllarAction::GrantRole { account, role },
+        None,
+        None,
+        None,
+    )?;
+    Ok(())
+}
+
+pub fn revoke_role(
+    builder: &mut ProvableBlobTx,
+    contract_name: ContractName,
+    account: String,
+    role: TokenRole,
+) -> anyhow::Result<()> {
+    builder.add_action(
+        contract_name,
+        HyllarAction::RevokeRole { account, role },
+        None,
+        None,
+        None,
+    )?;
+    Ok(())
+}
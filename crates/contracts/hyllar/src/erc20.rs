@@ -5,7 +5,7 @@ use alloc::{
 
 use sdk::caller::ExecutionContext;
 
-use crate::HyllarAction;
+use crate::{HyllarAction, TokenRole};
 
 /// Trait representing the ERC-20 token standard interface.
 pub trait ERC20 {
@@ -85,6 +85,79 @@ pub trait ERC20 {
     /// * `Result<u128, String>` - The remaining allowance on success, or an error message on failure.
     fn allowance(&self, owner: &str, spender: &str) -> Result<u128, String>;
 
+    /// Returns the maximum supply of tokens, if it is capped.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<u128>, String>` - The supply cap on success, or an error message on failure.
+    fn max_supply(&self) -> Result<Option<u128>, String> {
+        Ok(None)
+    }
+
+    /// Creates tokens for a recipient, increasing the total supply.
+    ///
+    /// # Arguments
+    ///
+    /// * `minter` - The address of the minter as a string slice.
+    /// * `recipient` - The address of the recipient as a string slice.
+    /// * `amount` - The amount of tokens to create.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - `Ok(())` if the mint was successful, or an error message on failure.
+    fn mint(&mut self, _minter: &str, _recipient: &str, _amount: u128) -> Result<(), String> {
+        Err("Minting is not supported by this token".to_string())
+    }
+
+    /// Destroys tokens of the burner, decreasing the total supply.
+    ///
+    /// # Arguments
+    ///
+    /// * `burner` - The address of the token holder as a string slice.
+    /// * `amount` - The amount of tokens to destroy.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - `Ok(())` if the burn was successful, or an error message on failure.
+    fn burn(&mut self, _burner: &str, _amount: u128) -> Result<(), String> {
+        Err("Burning is not supported by this token".to_string())
+    }
+
+    /// Grants a role over the supply of the token to an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `admin` - The address of the admin granting the role as a string slice.
+    /// * `account` - The address receiving the role as a string slice.
+    /// * `role` - The role to grant.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - `Ok(())` if the role was granted, or an error message on failure.
+    fn grant_role(&mut self, _admin: &str, _account: &str, _role: TokenRole) -> Result<(), String> {
+        Err("Roles are not supported by this token".to_string())
+    }
+
+    /// Revokes a role over the supply of the token from an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `admin` - The address of the admin revoking the role as a string slice.
+    /// * `account` - The address losing the role as a string slice.
+    /// * `role` - The role to revoke.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - `Ok(())` if the role was revoked, or an error message on failure.
+    fn revoke_role(
+        &mut self,
+        _admin: &str,
+        _account: &str,
+        _role: TokenRole,
+    ) -> Result<(), String> {
+        Err("Roles are not supported by this token".to_string())
+    }
+
     /// Executes an action on an object that implements the ERC20 trait based on the ERC20Action enum.
     ///
     /// # Arguments
@@ -124,6 +197,18 @@ pub trait ERC20 {
             HyllarAction::Allowance { owner, spender } => self
                 .allowance(&owner, &spender)
                 .map(|allowance| format!("Allowance of {spender} by {owner}: {allowance}")),
+            HyllarAction::Mint { recipient, amount } => self
+                .mint(&caller, &recipient, amount)
+                .map(|_| format!("Minted {amount} to {recipient}")),
+            HyllarAction::Burn { amount } => self
+                .burn(&caller, amount)
+                .map(|_| format!("Burned {amount}")),
+            HyllarAction::GrantRole { account, role } => self
+                .grant_role(&caller, &account, role)
+                .map(|_| format!("Granted {role:?} role to {account}")),
+            HyllarAction::RevokeRole { account, role } => self
+                .revoke_role(&caller, &account, role)
+                .map(|_| format!("Revoked {role:?} role from {account}")),
         }
     }
 
@@ -202,6 +287,10 @@ mod tests {
             fn transfer_from(&mut self, owner: &str, spender: &str, recipient: &str, amount: u128) -> Result<(), String>;
             fn approve(&mut self, owner: &str, spender: &str, amount: u128) -> Result<(), String>;
             fn allowance(&self, owner: &str, spender: &str) -> Result<u128, String>;
+            fn mint(&mut self, minter: &str, recipient: &str, amount: u128) -> Result<(), String>;
+            fn burn(&mut self, burner: &str, amount: u128) -> Result<(), String>;
+            fn grant_role(&mut self, admin: &str, account: &str, role: TokenRole) -> Result<(), String>;
+            fn revoke_role(&mut self, admin: &str, account: &str, role: TokenRole) -> Result<(), String>;
         }
         impl ZkContract for ERC20Contract {
             fn execute(&mut self, zk_program_input: &sdk::Calldata) -> crate::RunResult {
@@ -347,4 +436,71 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "Allowance of spender1 by owner1: 500");
     }
+
+    #[test]
+    fn test_mint() {
+        let mut mock = MockERC20Contract::new();
+        mock.expect_mint()
+            .with(
+                predicate::eq("minter"),
+                predicate::eq("recipient"),
+                predicate::eq(700),
+            )
+            .returning(|_, _, _| Ok(()));
+
+        let action = HyllarAction::Mint {
+            recipient: "recipient".to_string(),
+            amount: 700,
+        };
+        let execution_ctx = ExecutionContext {
+            caller: "minter".into(),
+            ..ExecutionContext::default()
+        };
+        let result = mock.execute_token_action(action, &execution_ctx);
+
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), "Minted 700 to recipient");
+    }
+
+    #[test]
+    fn test_burn() {
+        let mut mock = MockERC20Contract::new();
+        mock.expect_burn()
+            .with(predicate::eq("burner"), predicate::eq(50))
+            .returning(|_, _| Err("Insufficient balance".to_string()));
+
+        let action = HyllarAction::Burn { amount: 50 };
+        let execution_ctx = ExecutionContext {
+            caller: "burner".into(),
+            ..ExecutionContext::default()
+        };
+        let result = mock.execute_token_action(action, &execution_ctx);
+
+        assert_eq!(result.unwrap_err(), "Insufficient balance");
+    }
+
+    #[test]
+    fn test_grant_role() {
+        let mut mock = MockERC20Contract::new();
+        mock.expect_grant_role()
+            .with(
+                predicate::eq("admin"),
+                predicate::eq("minter"),
+                predicate::eq(TokenRole::Minter),
+            )
+            .returning(|_, _, _| Ok(()));
+
+        let action = HyllarAction::GrantRole {
+            account: "minter".to_string(),
+            role: TokenRole::Minter,
+        };
+        let execution_ctx = ExecutionContext {
+            caller: "admin".into(),
+            ..ExecutionContext::default()
+        };
+        let result = mock.execute_token_action(action, &execution_ctx);
+
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), "Granted Minter role to minter");
+    }
 }
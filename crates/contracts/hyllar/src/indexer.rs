@@ -6,11 +6,13 @@ use client_sdk::contract_indexer::{
         response::IntoResponse,
         Json, Router,
     },
+    record_supply_event,
     utoipa::{openapi::OpenApi, ToSchema},
     utoipa_axum::{router::OpenApiRouter, routes},
-    AppError, ContractHandler, ContractHandlerStore,
+    AppError, ContractHandler, ContractHandlerStore, SecondaryIndexes,
 };
-use sdk::Identity;
+use client_sdk::light_executor::parse_structured_blob_from_tx;
+use sdk::{BlobIndex, BlobTransaction, Identity};
 use serde::Serialize;
 
 use crate::*;
@@ -21,12 +23,62 @@ impl ContractHandler for Hyllar {
     async fn api(store: ContractHandlerStore<Hyllar>) -> (Router<()>, OpenApi) {
         let (router, api) = OpenApiRouter::default()
             .routes(routes!(get_state))
+            .routes(routes!(client_sdk::contract_indexer::get_state_at_height))
             .routes(routes!(get_balance))
             .routes(routes!(get_allowance))
+            .routes(routes!(get_supply))
+            .routes(routes!(client_sdk::contract_indexer::get_supply_events))
+            .routes(routes!(get_roles))
             .split_for_parts();
 
         (router.with_state(store), api)
     }
+
+    fn update_indexes(
+        &self,
+        tx: &BlobTransaction,
+        index: BlobIndex,
+        indexes: &mut SecondaryIndexes,
+    ) -> Result<()> {
+        match supply_event(tx, index) {
+            Some(event) => record_supply_event(indexes, &event),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Event of a successful supply or role action, its caller being the identity of the
+/// transaction unless the blob was called by another contract.
+fn supply_event(tx: &BlobTransaction, index: BlobIndex) -> Option<SupplyEvent> {
+    let blob = parse_structured_blob_from_tx::<HyllarAction>(tx, index)?;
+    let caller = blob
+        .data
+        .caller
+        .and_then(|caller| tx.blobs.get(caller.0))
+        .map(|caller| Identity(caller.contract_name.0.clone()))
+        .unwrap_or_else(|| tx.identity.clone());
+    match blob.data.parameters {
+        HyllarAction::Mint { recipient, amount } => Some(SupplyEvent::Minted {
+            minter: caller,
+            recipient: recipient.into(),
+            amount,
+        }),
+        HyllarAction::Burn { amount } => Some(SupplyEvent::Burned {
+            burner: caller,
+            amount,
+        }),
+        HyllarAction::GrantRole { account, role } => Some(SupplyEvent::RoleGranted {
+            admin: caller,
+            account: account.into(),
+            role,
+        }),
+        HyllarAction::RevokeRole { account, role } => Some(SupplyEvent::RoleRevoked {
+            admin: caller,
+            account: account.into(),
+            role,
+        }),
+        _ => None,
+    }
 }
 
 #[utoipa::path(
@@ -122,3 +174,207 @@ pub async fn get_allowance(
         .map(Json)
         .map_err(|err| AppError(StatusCode::NOT_FOUND, anyhow!("{err}'")))
 }
+
+#[derive(Serialize, ToSchema)]
+struct SupplyResponse {
+    total_supply: u128,
+    max_supply: Option<u128>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/supply",
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Get total and maximum supply of the token", body = SupplyResponse)
+    )
+)]
+pub async fn get_supply(
+    State(state): State<ContractHandlerStore<Hyllar>>,
+) -> Result<impl IntoResponse, AppError> {
+    let store = state.read().await;
+    let state = store.state.as_ref().ok_or(AppError(
+        StatusCode::NOT_FOUND,
+        anyhow!("Contract '{}' not found", store.contract_name),
+    ))?;
+
+    let response = state
+        .total_supply()
+        .and_then(|total_supply| {
+            state.max_supply().map(|max_supply| SupplyResponse {
+                total_supply,
+                max_supply,
+            })
+        })
+        .map_err(|err| AppError(StatusCode::INTERNAL_SERVER_ERROR, anyhow!("{err}")))?;
+    Ok(Json(response))
+}
+
+#[derive(Serialize, ToSchema)]
+struct RolesResponse {
+    account: String,
+    roles: Vec<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/roles/{account}",
+    params(
+        ("account" = String, Path, description = "Account")
+    ),
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Get roles of account over the supply", body = RolesResponse)
+    )
+)]
+pub async fn get_roles(
+    Path(account): Path<Identity>,
+    State(state): State<ContractHandlerStore<Hyllar>>,
+) -> Result<impl IntoResponse, AppError> {
+    let store = state.read().await;
+    let state = store.state.as_ref().ok_or(AppError(
+        StatusCode::NOT_FOUND,
+        anyhow!("Contract '{}' not found", store.contract_name),
+    ))?;
+
+    Ok(Json(RolesResponse {
+        roles: state
+            .roles_of(&account.0)
+            .iter()
+            .map(|role| format!("{role:?}"))
+            .collect(),
+        account: account.0,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use client_sdk::contract_indexer::SUPPLY_EVENTS_INDEX;
+    use sdk::{Blob, BlobData, ContractAction, ContractName};
+
+    use super::*;
+
+    fn index_tx(hyllar: &Hyllar, tx: &BlobTransaction, indexes: &mut SecondaryIndexes) {
+        for (index, blob) in tx.blobs.iter().enumerate() {
+            if blob.contract_name.0 == "hyllar" {
+                hyllar
+                    .update_indexes(tx, BlobIndex(index), indexes)
+                    .unwrap();
+            }
+        }
+    }
+
+    fn supply_events(indexes: &SecondaryIndexes) -> Vec<SupplyEvent> {
+        indexes
+            .iter::<SupplyEvent>(SUPPLY_EVENTS_INDEX)
+            .map(|entry| entry.unwrap().1)
+            .collect()
+    }
+
+    #[test]
+    fn test_update_indexes_records_supply_events() {
+        let hyllar = Hyllar::default();
+        let contract_name = ContractName::new("hyllar");
+        let mut indexes = SecondaryIndexes::default();
+
+        let tx = BlobTransaction::new(
+            "admin",
+            vec![
+                HyllarAction::GrantRole {
+                    account: "minter".to_string(),
+                    role: TokenRole::Minter,
+                }
+                .as_blob(contract_name.clone(), None, None),
+                HyllarAction::Transfer {
+                    recipient: "bob".to_string(),
+                    amount: 5,
+                }
+                .as_blob(contract_name.clone(), None, None),
+            ],
+        );
+        index_tx(&hyllar, &tx, &mut indexes);
+
+        let tx = BlobTransaction::new(
+            "minter",
+            vec![
+                HyllarAction::Mint {
+                    recipient: "bob".to_string(),
+                    amount: 100,
+                }
+                .as_blob(contract_name.clone(), None, None),
+                HyllarAction::Burn { amount: 30 }.as_blob(contract_name.clone(), None, None),
+            ],
+        );
+        index_tx(&hyllar, &tx, &mut indexes);
+
+        assert_eq!(
+            supply_events(&indexes),
+            vec![
+                SupplyEvent::RoleGranted {
+                    admin: "admin".into(),
+                    account: "minter".into(),
+                    role: TokenRole::Minter,
+                },
+                SupplyEvent::Minted {
+                    minter: "minter".into(),
+                    recipient: "bob".into(),
+                    amount: 100,
+                },
+                SupplyEvent::Burned {
+                    burner: "minter".into(),
+                    amount: 30,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_update_indexes_caller_contract() {
+        let hyllar = Hyllar::default();
+        let mut indexes = SecondaryIndexes::default();
+
+        let tx = BlobTransaction::new(
+            "bob",
+            vec![
+                Blob {
+                    contract_name: ContractName::new("amm"),
+                    data: BlobData(vec![]),
+                },
+                HyllarAction::Mint {
+                    recipient: "bob".to_string(),
+                    amount: 10,
+                }
+                .as_blob(ContractName::new("hyllar"), Some(BlobIndex(0)), None),
+            ],
+        );
+        index_tx(&hyllar, &tx, &mut indexes);
+
+        assert_eq!(
+            supply_events(&indexes),
+            vec![SupplyEvent::Minted {
+                minter: "amm".into(),
+                recipient: "bob".into(),
+                amount: 10,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_update_indexes_ignores_other_actions() {
+        let hyllar = Hyllar::default();
+        let mut indexes = SecondaryIndexes::default();
+
+        let tx = BlobTransaction::new(
+            "bob",
+            vec![HyllarAction::Approve {
+                spender: "alice".to_string(),
+                amount: 10,
+            }
+            .as_blob(ContractName::new("hyllar"), None, None)],
+        );
+        index_tx(&hyllar, &tx, &mut indexes);
+
+        assert!(supply_events(&indexes).is_empty());
+        assert!(indexes.take_changed().is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use erc20::ERC20;
@@ -18,6 +18,8 @@ pub mod indexer;
 
 pub mod erc20;
 
+pub use sdk::supply::{SupplyEvent, TokenRole};
+
 pub const TOTAL_SUPPLY: u128 = 100_000_000_000_000;
 pub const FAUCET_ID: &str = "faucet@hydentity";
 
@@ -37,6 +39,13 @@ impl ZkContract for Hyllar {
     fn commit(&self) -> sdk::StateCommitment {
         let mut hasher = Sha256::new();
         hasher.update(self.total_supply.to_le_bytes());
+        match self.max_supply {
+            Some(max_supply) => {
+                hasher.update([1]);
+                hasher.update(max_supply.to_le_bytes());
+            }
+            None => hasher.update([0]),
+        }
         for (account, balance) in self.balances.iter() {
             hasher.update(account.as_bytes());
             hasher.update(balance.to_le_bytes());
@@ -46,6 +55,12 @@ impl ZkContract for Hyllar {
             hasher.update(spender.as_bytes());
             hasher.update(allowance.to_le_bytes());
         }
+        for (account, roles) in self.roles.iter() {
+            hasher.update(account.as_bytes());
+            for role in roles {
+                hasher.update([*role as u8]);
+            }
+        }
         sdk::StateCommitment(hasher.finalize().to_vec())
     }
 }
@@ -53,15 +68,19 @@ impl ZkContract for Hyllar {
 /// Struct representing the Hyllar token.
 #[serde_as]
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub struct Hyllar {
     total_supply: u128,
     balances: BTreeMap<String, u128>, // Balances for each account
     #[serde_as(as = "Vec<(_, _)>")]
     allowances: BTreeMap<(String, String), u128>, // Allowances (owner, spender)
+    max_supply: Option<u128>,         // No cap if None
+    roles: BTreeMap<String, BTreeSet<TokenRole>>,
 }
 
 /// Enum representing possible calls to ERC-20 contract functions.
 #[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub enum HyllarAction {
     TotalSupply,
     BalanceOf {
@@ -84,6 +103,21 @@ pub enum HyllarAction {
         owner: String,
         spender: String,
     },
+    Mint {
+        recipient: String,
+        amount: u128,
+    },
+    Burn {
+        amount: u128,
+    },
+    GrantRole {
+        account: String,
+        role: TokenRole,
+    },
+    RevokeRole {
+        account: String,
+        role: TokenRole,
+    },
 }
 
 impl Default for Hyllar {
@@ -100,8 +134,39 @@ impl Hyllar {
             total_supply: TOTAL_SUPPLY,
             balances,
             allowances: BTreeMap::new(),
+            max_supply: Some(TOTAL_SUPPLY),
+            roles: BTreeMap::new(),
+        }
+    }
+
+    /// Token without any supply, minted by the minters `admin` grants the role to.
+    pub fn with_supply_control(admin: String, max_supply: Option<u128>) -> Self {
+        Hyllar {
+            total_supply: 0,
+            balances: BTreeMap::new(),
+            allowances: BTreeMap::new(),
+            max_supply,
+            roles: BTreeMap::from([(admin, BTreeSet::from([TokenRole::Admin]))]),
         }
     }
+
+    pub fn has_role(&self, account: &str, role: TokenRole) -> bool {
+        self.roles
+            .get(account)
+            .is_some_and(|roles| roles.contains(&role))
+    }
+
+    pub fn roles_of(&self, account: &str) -> BTreeSet<TokenRole> {
+        self.roles.get(account).cloned().unwrap_or_default()
+    }
+
+    fn check_role(&self, account: &str, role: TokenRole) -> Result<(), String> {
+        if !self.has_role(account, role) {
+            return Err(format!("{account} doesn't have the {role:?} role"));
+        }
+        Ok(())
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         borsh::to_vec(self).expect("Failed to encode Balances")
     }
@@ -177,6 +242,66 @@ impl ERC20 for Hyllar {
             None => Ok(0), // No allowance set
         }
     }
+
+    fn max_supply(&self) -> Result<Option<u128>, String> {
+        Ok(self.max_supply)
+    }
+
+    fn mint(&mut self, minter: &str, recipient: &str, amount: u128) -> Result<(), String> {
+        self.check_role(minter, TokenRole::Minter)?;
+
+        let total_supply = self
+            .total_supply
+            .checked_add(amount)
+            .ok_or("Total supply overflow")?;
+        if let Some(max_supply) = self.max_supply {
+            if total_supply > max_supply {
+                return Err(format!(
+                    "Supply cap exceeded: minting {amount} would bring the supply to {total_supply}, above {max_supply}"
+                ));
+            }
+        }
+
+        self.total_supply = total_supply;
+        *self.balances.entry(recipient.to_string()).or_insert(0) += amount;
+        Ok(())
+    }
+
+    fn burn(&mut self, burner: &str, amount: u128) -> Result<(), String> {
+        self.check_role(burner, TokenRole::Burner)?;
+
+        let balance = self.balance_of(burner)?;
+        if balance < amount {
+            return Err("Insufficient balance".to_string());
+        }
+
+        self.balances.insert(burner.to_string(), balance - amount);
+        self.total_supply -= amount;
+        Ok(())
+    }
+
+    fn grant_role(&mut self, admin: &str, account: &str, role: TokenRole) -> Result<(), String> {
+        self.check_role(admin, TokenRole::Admin)?;
+        self.roles
+            .entry(account.to_string())
+            .or_default()
+            .insert(role);
+        Ok(())
+    }
+
+    fn revoke_role(&mut self, admin: &str, account: &str, role: TokenRole) -> Result<(), String> {
+        self.check_role(admin, TokenRole::Admin)?;
+        if admin == account && role == TokenRole::Admin {
+            return Err("Admins can't revoke their own admin role".to_string());
+        }
+        if let Some(roles) = self.roles.get_mut(account) {
+            roles.remove(&role);
+            if roles.is_empty() {
+                self.roles.remove(account);
+            }
+        }
+        Ok(())
+    }
 }
 
 impl ContractAction for HyllarAction {
@@ -197,6 +322,14 @@ impl ContractAction for HyllarAction {
     }
 }
 
+/// Interface of the contract, to give in the constructor metadata when registering it
+#[cfg(feature = "schema")]
+pub fn contract_schema() -> sdk::ContractSchema {
+    sdk::ContractSchema::new::<HyllarAction>(sdk::ActionEncoding::Structured)
+        .with_state::<Hyllar>()
+        .with_utf8_program_outputs()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -282,6 +415,82 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mint_and_burn() {
+        let mut token = Hyllar::with_supply_control("admin".to_string(), Some(1000));
+
+        assert_eq!(
+            token.mint("minter", "recipient", 100).unwrap_err(),
+            "minter doesn't have the Minter role"
+        );
+        assert!(token
+            .grant_role("minter", "minter", TokenRole::Minter)
+            .is_err());
+
+        token
+            .grant_role("admin", "minter", TokenRole::Minter)
+            .unwrap();
+        token.mint("minter", "recipient", 600).unwrap();
+        assert_eq!(token.total_supply().unwrap(), 600);
+        assert_eq!(token.balance_of("recipient").unwrap(), 600);
+
+        // Supply cap
+        assert!(token
+            .mint("minter", "recipient", 401)
+            .unwrap_err()
+            .starts_with("Supply cap exceeded"));
+        token.mint("minter", "recipient", 400).unwrap();
+        assert_eq!(token.total_supply().unwrap(), 1000);
+
+        assert!(token.burn("recipient", 100).is_err());
+        token
+            .grant_role("admin", "recipient", TokenRole::Burner)
+            .unwrap();
+        assert_eq!(
+            token.burn("recipient", 1001).unwrap_err(),
+            "Insufficient balance"
+        );
+        token.burn("recipient", 100).unwrap();
+        assert_eq!(token.total_supply().unwrap(), 900);
+        assert_eq!(token.balance_of("recipient").unwrap(), 900);
+
+        // Burnt tokens can be minted again
+        token.mint("minter", "minter", 100).unwrap();
+        assert_eq!(token.total_supply().unwrap(), 1000);
+    }
+
+    #[test]
+    fn test_revoke_role() {
+        let mut token = Hyllar::with_supply_control("admin".to_string(), None);
+        token
+            .grant_role("admin", "minter", TokenRole::Minter)
+            .unwrap();
+        token.mint("minter", "recipient", u128::MAX).unwrap();
+        assert!(token.mint("minter", "recipient", 1).is_err());
+
+        let commitment = token.commit();
+        token
+            .revoke_role("admin", "minter", TokenRole::Minter)
+            .unwrap();
+        assert_ne!(token.commit(), commitment);
+        assert!(!token.has_role("minter", TokenRole::Minter));
+        assert!(!token.roles.contains_key("minter"));
+        assert!(token
+            .revoke_role("admin", "admin", TokenRole::Admin)
+            .is_err());
+        assert!(token.has_role("admin", TokenRole::Admin));
+    }
+
+    #[test]
+    fn test_default_supply_is_fixed() {
+        let mut token = Hyllar::default();
+        assert_eq!(token.max_supply().unwrap(), Some(TOTAL_SUPPLY));
+        assert!(token.mint(FAUCET_ID, FAUCET_ID, 1).is_err());
+        assert!(token
+            .grant_role(FAUCET_ID, FAUCET_ID, TokenRole::Minter)
+            .is_err());
+    }
+
     #[test]
     fn test_transfer_from_insufficient_balance() {
         let mut token = Hyllar::default();
@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use sdk::merkle_utils::SHA256Hasher;
@@ -7,7 +7,9 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use sparse_merkle_tree::{default_store::DefaultStore, traits::Value, SparseMerkleTree, H256};
 
-use crate::{FAUCET_ID, TOTAL_SUPPLY};
+use crate::{FAUCET_ID, MINT_RESERVE_ID, TOTAL_SUPPLY};
+
+pub use sdk::supply::TokenRole;
 
 #[derive(Debug)]
 pub struct AccountSMT(pub SparseMerkleTree<SHA256Hasher, Account, DefaultStore<Account>>);
@@ -41,6 +43,19 @@ impl BorshDeserialize for AccountSMT {
     }
 }
 
+/// Serialized as the accounts by address, as served by the indexer
+impl Serialize for AccountSMT {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            self.0
+                .store()
+                .leaves_map()
+                .values()
+                .map(|account| (&account.address, account)),
+        )
+    }
+}
+
 impl Default for AccountSMT {
     fn default() -> Self {
         let mut accounts = SparseMerkleTree::default();
@@ -48,6 +63,7 @@ impl Default for AccountSMT {
             address: FAUCET_ID.into(),
             balance: TOTAL_SUPPLY,
             allowances: BTreeMap::new(),
+            roles: BTreeSet::new(),
         };
         let faucet_key = faucet_account.get_key();
         accounts
@@ -58,6 +74,24 @@ impl Default for AccountSMT {
     }
 }
 
+impl AccountSMT {
+    /// Token without any supply, minted by the minters `admin` grants the role to.
+    /// The mint reserve holds what can still be minted, all of it if the supply isn't capped.
+    pub fn with_supply_control(admin: Identity, max_supply: Option<u128>) -> Self {
+        let mut accounts = SparseMerkleTree::default();
+        let reserve_account = Account::new(MINT_RESERVE_ID.into(), max_supply.unwrap_or(u128::MAX));
+        let mut admin_account = Account::new(admin, 0);
+        admin_account.roles.insert(TokenRole::Admin);
+        for account in [reserve_account, admin_account] {
+            accounts
+                .update(account.get_key(), account)
+                .expect("Failed to initialize supply control accounts");
+        }
+
+        AccountSMT(accounts)
+    }
+}
+
 #[derive(
     Debug, Default, Clone, PartialEq, BorshDeserialize, BorshSerialize, Serialize, Deserialize,
 )]
@@ -65,6 +99,8 @@ pub struct Account {
     pub address: Identity,
     pub balance: u128,
     pub allowances: BTreeMap<Identity, u128>,
+    #[serde(default)]
+    pub roles: BTreeSet<TokenRole>,
 }
 
 impl Account {
@@ -73,9 +109,14 @@ impl Account {
             address,
             balance,
             allowances: BTreeMap::new(),
+            roles: BTreeSet::new(),
         }
     }
 
+    pub fn has_role(&self, role: TokenRole) -> bool {
+        self.roles.contains(&role)
+    }
+
     pub fn get_key(&self) -> H256 {
         Account::compute_key(&self.address)
     }
@@ -96,7 +137,8 @@ impl Account {
 
 impl Value for Account {
     fn to_h256(&self) -> H256 {
-        if self.balance == 0 {
+        // Accounts holding roles are kept even without balance
+        if self.balance == 0 && self.roles.is_empty() {
             return H256::zero();
         }
 
@@ -4,9 +4,12 @@ use client_sdk::light_executor::{
     parse_structured_blob_from_tx, LightContractExecutor, LightExecutorOutput,
 };
 use sdk::{BlobIndex, BlobTransaction, Identity, TxContext};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-use crate::{account::Account, SmtTokenAction, FAUCET_ID, TOTAL_SUPPLY};
+use crate::{
+    account::Account, apply_supply_action, check_not_reserve, SmtTokenAction, FAUCET_ID,
+    TOTAL_SUPPLY,
+};
 
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct LightSmtExecutor {
@@ -21,6 +24,7 @@ impl Default for LightSmtExecutor {
             address: FAUCET_ID.into(),
             balance: TOTAL_SUPPLY,
             allowances: BTreeMap::new(),
+            roles: BTreeSet::new(),
         };
         balances.insert(faucet_account.address.clone(), faucet_account);
         LightSmtExecutor { balances }
@@ -52,7 +56,14 @@ impl LightContractExecutor<'_, '_> for LightSmtExecutor {
             return Err(anyhow!("Failed to parse structured blob from transaction"));
         };
 
-        self.inner_handle(parsed_blob.data.parameters)
+        let caller = parsed_blob
+            .data
+            .caller
+            .and_then(|caller| tx.blobs.get(caller.0))
+            .map(|caller| Identity(caller.contract_name.0.clone()))
+            .unwrap_or_else(|| tx.identity.clone());
+
+        self.inner_handle(&caller, parsed_blob.data.parameters)
             .map(|ok| LightExecutorOutput {
                 success: true,
                 program_outputs: ok.into_bytes(),
@@ -75,13 +86,14 @@ impl LightContractExecutor<'_, '_> for LightSmtExecutor {
 }
 
 impl LightSmtExecutor {
-    pub fn inner_handle(&mut self, action: SmtTokenAction) -> Result<String> {
+    pub fn inner_handle(&mut self, caller: &Identity, action: SmtTokenAction) -> Result<String> {
         match action {
             SmtTokenAction::Transfer {
                 sender,
                 recipient,
                 amount,
             } => {
+                check_not_reserve(&[&sender, &recipient]).map_err(|e| anyhow!(e))?;
                 let sender_account = self
                     .balances
                     .get_mut(&sender)
@@ -119,6 +131,7 @@ impl LightSmtExecutor {
                 recipient,
                 amount,
             } => {
+                check_not_reserve(&[&owner, &recipient]).map_err(|e| anyhow!(e))?;
                 let owner_account = self
                     .balances
                     .get_mut(&owner)
@@ -182,6 +195,7 @@ impl LightSmtExecutor {
                 spender,
                 amount,
             } => {
+                check_not_reserve(&[&owner]).map_err(|e| anyhow!(e))?;
                 let owner_account = self
                     .balances
                     .get_mut(&owner)
@@ -189,6 +203,25 @@ impl LightSmtExecutor {
                 owner_account.update_allowances(spender.clone(), amount);
                 Ok(format!("Approved {amount} to {spender}"))
             }
+            action => {
+                let mut accounts: BTreeMap<Identity, Account> = action
+                    .supply_accounts()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|address| {
+                        let account = self
+                            .balances
+                            .get(&address)
+                            .cloned()
+                            .unwrap_or(Account::new(address.clone(), 0));
+                        (address, account)
+                    })
+                    .collect();
+                let output =
+                    apply_supply_action(caller, action, &mut accounts).map_err(|e| anyhow!(e))?;
+                self.balances.extend(accounts);
+                Ok(output)
+            }
         }
     }
 }
@@ -214,7 +247,7 @@ mod tests {
             recipient: recipient.clone(),
             amount,
         };
-        let res = exec.inner_handle(action).unwrap();
+        let res = exec.inner_handle(&sender, action).unwrap();
         assert_eq!(res, format!("Transferred {amount} to {recipient}"));
         assert_eq!(exec.balances[&sender].balance, TOTAL_SUPPLY - amount);
         assert_eq!(exec.balances[&recipient].balance, amount);
@@ -231,7 +264,7 @@ mod tests {
             recipient: recipient.clone(),
             amount,
         };
-        let err = exec.inner_handle(action).unwrap_err();
+        let err = exec.inner_handle(&sender, action).unwrap_err();
         assert!(err.to_string().contains("not found"));
     }
 
@@ -248,7 +281,7 @@ mod tests {
             spender: spender.clone(),
             amount,
         };
-        exec.inner_handle(approve).unwrap();
+        exec.inner_handle(&owner, approve).unwrap();
         assert_eq!(exec.balances[&owner].allowances[&spender], amount);
         // TransferFrom
         let transfer_from = SmtTokenAction::TransferFrom {
@@ -257,7 +290,7 @@ mod tests {
             recipient: recipient.clone(),
             amount,
         };
-        let res = exec.inner_handle(transfer_from).unwrap();
+        let res = exec.inner_handle(&spender, transfer_from).unwrap();
         assert_eq!(res, format!("Transferred {amount} to {recipient}"));
         assert_eq!(exec.balances[&owner].balance, TOTAL_SUPPLY - amount);
         assert_eq!(exec.balances[&owner].allowances[&spender], 0);
@@ -277,14 +310,14 @@ mod tests {
             spender: spender.clone(),
             amount: 100,
         };
-        exec.inner_handle(approve).unwrap();
+        exec.inner_handle(&owner, approve).unwrap();
         let transfer_from = SmtTokenAction::TransferFrom {
             owner: owner.clone(),
             spender: spender.clone(),
             recipient: recipient.clone(),
             amount,
         };
-        let err = exec.inner_handle(transfer_from).unwrap_err();
+        let err = exec.inner_handle(&spender, transfer_from).unwrap_err();
         assert!(err.to_string().contains("Allowance exceeded"));
     }
 }
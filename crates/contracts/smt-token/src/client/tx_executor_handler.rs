@@ -16,8 +16,8 @@ pub mod metadata {
 use sparse_merkle_tree::{traits::StoreReadOps, SparseMerkleTree};
 
 use crate::{
-    account::{Account, AccountSMT},
-    SmtTokenAction, SmtTokenContract,
+    account::{Account, AccountSMT, TokenRole},
+    apply_supply_action, check_not_reserve, SmtTokenAction, SmtTokenContract, MINT_RESERVE_ID,
 };
 
 pub type SmtTokenProvableState = AccountSMT;
@@ -36,6 +36,39 @@ impl SmtTokenProvableState {
         let key = Account::compute_key(address);
         self.0.store().get_leaf(&key).map_err(anyhow::Error::from)
     }
+
+    /// Sum of the balances, outside of the mint reserve
+    pub fn total_supply(&self) -> u128 {
+        self.0
+            .store()
+            .leaves_map()
+            .values()
+            .filter(|account| account.address.0 != MINT_RESERVE_ID)
+            .fold(0u128, |total, account| {
+                total.saturating_add(account.balance)
+            })
+    }
+
+    /// Amount that can still be minted, u128::MAX if the supply isn't capped
+    pub fn mintable_supply(&self) -> Result<u128> {
+        Ok(self
+            .get_account(&MINT_RESERVE_ID.into())?
+            .map(|reserve| reserve.balance)
+            .unwrap_or(0))
+    }
+
+    /// Accounts as they are in the tree, or empty for the missing ones
+    fn accounts(&self, addresses: &[Identity]) -> Result<BTreeMap<Identity, Account>> {
+        addresses
+            .iter()
+            .map(|address| {
+                let account = self
+                    .get_account(address)?
+                    .unwrap_or(Account::new(address.clone(), 0));
+                Ok((address.clone(), account))
+            })
+            .collect()
+    }
 }
 
 impl Clone for SmtTokenProvableState {
@@ -54,7 +87,9 @@ impl TxExecutorHandler for SmtTokenProvableState {
         let (action, execution_ctx) =
             parse_calldata::<SmtTokenAction>(calldata).map_err(|e| anyhow::anyhow!(e))?;
 
-        let output = self.inner_handle(action).map_err(|e| e.to_string());
+        let output = self
+            .inner_handle(&execution_ctx.caller, action)
+            .map_err(|e| e.to_string());
 
         let new_rooot = *self.0.root();
         let next_state_commitment = StateCommitment(Into::<[u8; 32]>::into(new_rooot).to_vec());
@@ -92,6 +127,21 @@ impl TxExecutorHandler for SmtTokenProvableState {
         let action = parsed_blob.data.parameters;
 
         let (proof, accounts) = match action {
+            action @ (SmtTokenAction::Mint { .. }
+            | SmtTokenAction::Burn { .. }
+            | SmtTokenAction::GrantRole { .. }
+            | SmtTokenAction::RevokeRole { .. }) => {
+                let addresses = action.supply_accounts().unwrap_or_default();
+                let accounts = self.accounts(&addresses)?;
+                (
+                    BorshableMerkleProof(
+                        self.0
+                            .merkle_proof(accounts.values().map(Account::get_key).collect())
+                            .context("Failed to generate proof")?,
+                    ),
+                    accounts,
+                )
+            }
             SmtTokenAction::Transfer {
                 sender,
                 recipient,
@@ -332,13 +382,97 @@ impl SmtTokenProvableState {
         Ok(())
     }
 
-    fn inner_handle(&mut self, action: SmtTokenAction) -> Result<String> {
+    pub fn mint(
+        &self,
+        builder: &mut ProvableBlobTx,
+        contract_name: ContractName,
+        minter: Identity,
+        recipient: Identity,
+        amount: u128,
+    ) -> anyhow::Result<()> {
+        builder.add_action(
+            contract_name,
+            SmtTokenAction::Mint {
+                minter,
+                recipient,
+                amount,
+            },
+            None,
+            None,
+            None,
+        )?;
+        Ok(())
+    }
+
+    pub fn burn(
+        &self,
+        builder: &mut ProvableBlobTx,
+        contract_name: ContractName,
+        burner: Identity,
+        amount: u128,
+    ) -> anyhow::Result<()> {
+        builder.add_action(
+            contract_name,
+            SmtTokenAction::Burn { burner, amount },
+            None,
+            None,
+            None,
+        )?;
+        Ok(())
+    }
+
+    pub fn grant_role(
+        &self,
+        builder: &mut ProvableBlobTx,
+        contract_name: ContractName,
+        admin: Identity,
+        account: Identity,
+        role: TokenRole,
+    ) -> anyhow::Result<()> {
+        builder.add_action(
+            contract_name,
+            SmtTokenAction::GrantRole {
+                admin,
+                account,
+                role,
+            },
+            None,
+            None,
+            None,
+        )?;
+        Ok(())
+    }
+
+    pub fn revoke_role(
+        &self,
+        builder: &mut ProvableBlobTx,
+        contract_name: ContractName,
+        admin: Identity,
+        account: Identity,
+        role: TokenRole,
+    ) -> anyhow::Result<()> {
+        builder.add_action(
+            contract_name,
+            SmtTokenAction::RevokeRole {
+                admin,
+                account,
+                role,
+            },
+            None,
+            None,
+            None,
+        )?;
+        Ok(())
+    }
+
+    fn inner_handle(&mut self, caller: &Identity, action: SmtTokenAction) -> Result<String> {
         match action {
             SmtTokenAction::Transfer {
                 sender,
                 recipient,
                 amount,
             } => {
+                check_not_reserve(&[&sender, &recipient]).map_err(|e| anyhow!(e))?;
                 let mut sender_account = self
                     .get_account(&sender)?
                     .ok_or(anyhow!("Sender account {} not found", sender))?;
@@ -379,6 +513,7 @@ impl SmtTokenProvableState {
                 recipient,
                 amount,
             } => {
+                check_not_reserve(&[&owner, &recipient]).map_err(|e| anyhow!(e))?;
                 let mut owner_account = self
                     .get_account(&owner)?
                     .ok_or(anyhow!("Owner account {} not found", owner))?;
@@ -437,6 +572,7 @@ impl SmtTokenProvableState {
                 spender,
                 amount,
             } => {
+                check_not_reserve(&[&owner]).map_err(|e| anyhow!(e))?;
                 let mut owner_account = self
                     .get_account(&owner)?
                     .ok_or(anyhow!("Owner account {} not found", owner))?;
@@ -447,6 +583,18 @@ impl SmtTokenProvableState {
                 }
                 Ok(format!("Approved {amount} to {spender}"))
             }
+            action => {
+                let addresses = action.supply_accounts().unwrap_or_default();
+                let mut accounts = self.accounts(&addresses)?;
+                let output =
+                    apply_supply_action(caller, action, &mut accounts).map_err(|e| anyhow!(e))?;
+                for account in accounts.into_values() {
+                    if let Err(e) = self.0.update(account.get_key(), account) {
+                        bail!("Failed to update account: {e}");
+                    }
+                }
+                Ok(output)
+            }
         }
     }
 }
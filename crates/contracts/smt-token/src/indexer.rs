@@ -6,28 +6,50 @@ use client_sdk::contract_indexer::{
         response::IntoResponse,
         Json, Router,
     },
+    record_supply_event,
     utoipa::{openapi::OpenApi, ToSchema},
     utoipa_axum::{router::OpenApiRouter, routes},
-    AppError, ContractHandler, ContractHandlerStore,
+    AppError, ContractHandler, ContractHandlerStore, SecondaryIndexes,
 };
-use sdk::Identity;
+use client_sdk::light_executor::parse_structured_blob_from_tx;
+use sdk::{BlobIndex, BlobTransaction, Identity};
 use serde::Serialize;
 
 use client_sdk::contract_indexer::axum;
 use client_sdk::contract_indexer::utoipa;
 
 use crate::client::tx_executor_handler::SmtTokenProvableState;
+use crate::SmtTokenAction;
 
 impl ContractHandler for SmtTokenProvableState {
     async fn api(store: ContractHandlerStore<SmtTokenProvableState>) -> (Router<()>, OpenApi) {
         let (router, api) = OpenApiRouter::default()
             .routes(routes!(get_state))
+            .routes(routes!(client_sdk::contract_indexer::get_state_at_height))
             .routes(routes!(get_balance))
             .routes(routes!(get_allowance))
+            .routes(routes!(get_supply))
+            .routes(routes!(client_sdk::contract_indexer::get_supply_events))
+            .routes(routes!(get_roles))
             .split_for_parts();
 
         (router.with_state(store), api)
     }
+
+    fn update_indexes(
+        &self,
+        tx: &BlobTransaction,
+        index: BlobIndex,
+        indexes: &mut SecondaryIndexes,
+    ) -> Result<()> {
+        let Some(blob) = parse_structured_blob_from_tx::<SmtTokenAction>(tx, index) else {
+            return Ok(());
+        };
+        match blob.data.parameters.supply_event() {
+            Some(event) => record_supply_event(indexes, &event),
+            None => Ok(()),
+        }
+    }
 }
 
 #[utoipa::path(
@@ -134,3 +156,184 @@ pub async fn get_allowance(
         .map(Json)
         .ok_or_else(|| AppError(StatusCode::NOT_FOUND, anyhow!("Account not found")))
 }
+
+#[derive(Serialize, ToSchema)]
+struct SupplyResponse {
+    total_supply: u128,
+    mintable_supply: u128,
+}
+
+#[utoipa::path(
+    get,
+    path = "/supply",
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Get total supply of the token, and what can still be minted", body = SupplyResponse)
+    )
+)]
+pub async fn get_supply(
+    State(state): State<ContractHandlerStore<SmtTokenProvableState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let store = state.read().await;
+
+    let contract = store.state.as_ref().ok_or(AppError(
+        StatusCode::NOT_FOUND,
+        anyhow!("Contract '{}' not found", store.contract_name),
+    ))?;
+
+    Ok(Json(SupplyResponse {
+        total_supply: contract.total_supply(),
+        mintable_supply: contract.mintable_supply()?,
+    }))
+}
+
+#[derive(Serialize, ToSchema)]
+struct RolesResponse {
+    address: String,
+    roles: Vec<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/roles/{account}",
+    params(
+        ("account" = String, Path, description = "Account")
+    ),
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Get roles of account over the supply", body = RolesResponse)
+    )
+)]
+pub async fn get_roles(
+    Path(address): Path<Identity>,
+    State(state): State<ContractHandlerStore<SmtTokenProvableState>>,
+) -> Result<impl IntoResponse, AppError> {
+    let store = state.read().await;
+
+    let contract = store.state.as_ref().ok_or(AppError(
+        StatusCode::NOT_FOUND,
+        anyhow!("Contract '{}' not found", store.contract_name),
+    ))?;
+
+    let roles = contract
+        .get_account(&address)?
+        .map(|account| account.roles)
+        .unwrap_or_default();
+
+    Ok(Json(RolesResponse {
+        address: address.0,
+        roles: roles.iter().map(|role| format!("{role:?}")).collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use client_sdk::contract_indexer::SUPPLY_EVENTS_INDEX;
+    use sdk::{ContractAction, ContractName};
+
+    use super::*;
+    use crate::{account::TokenRole, SupplyEvent};
+
+    fn index_tx(
+        state: &SmtTokenProvableState,
+        tx: &BlobTransaction,
+        indexes: &mut SecondaryIndexes,
+    ) {
+        for index in 0..tx.blobs.len() {
+            state.update_indexes(tx, BlobIndex(index), indexes).unwrap();
+        }
+    }
+
+    fn supply_events(indexes: &SecondaryIndexes) -> Vec<SupplyEvent> {
+        indexes
+            .iter::<SupplyEvent>(SUPPLY_EVENTS_INDEX)
+            .map(|entry| entry.unwrap().1)
+            .collect()
+    }
+
+    #[test]
+    fn test_update_indexes_records_supply_events() {
+        let state = SmtTokenProvableState::with_supply_control("admin".into(), Some(1000));
+        let contract_name = ContractName::new("oranj");
+        let mut indexes = SecondaryIndexes::default();
+
+        let tx = BlobTransaction::new(
+            "admin",
+            vec![
+                SmtTokenAction::GrantRole {
+                    admin: "admin".into(),
+                    account: "minter".into(),
+                    role: TokenRole::Minter,
+                }
+                .as_blob(contract_name.clone(), None, None),
+                SmtTokenAction::Transfer {
+                    sender: "admin".into(),
+                    recipient: "bob".into(),
+                    amount: 5,
+                }
+                .as_blob(contract_name.clone(), None, None),
+            ],
+        );
+        index_tx(&state, &tx, &mut indexes);
+
+        let tx = BlobTransaction::new(
+            "minter",
+            vec![
+                SmtTokenAction::Mint {
+                    minter: "minter".into(),
+                    recipient: "bob".into(),
+                    amount: 100,
+                }
+                .as_blob(contract_name.clone(), None, None),
+                SmtTokenAction::RevokeRole {
+                    admin: "admin".into(),
+                    account: "minter".into(),
+                    role: TokenRole::Minter,
+                }
+                .as_blob(contract_name.clone(), None, None),
+            ],
+        );
+        index_tx(&state, &tx, &mut indexes);
+
+        assert_eq!(
+            supply_events(&indexes),
+            vec![
+                SupplyEvent::RoleGranted {
+                    admin: "admin".into(),
+                    account: "minter".into(),
+                    role: TokenRole::Minter,
+                },
+                SupplyEvent::Minted {
+                    minter: "minter".into(),
+                    recipient: "bob".into(),
+                    amount: 100,
+                },
+                SupplyEvent::RoleRevoked {
+                    admin: "admin".into(),
+                    account: "minter".into(),
+                    role: TokenRole::Minter,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_update_indexes_ignores_other_actions() {
+        let state = SmtTokenProvableState::default();
+        let mut indexes = SecondaryIndexes::default();
+
+        let tx = BlobTransaction::new(
+            "bob",
+            vec![SmtTokenAction::Approve {
+                owner: "bob".into(),
+                spender: "alice".into(),
+                amount: 10,
+            }
+            .as_blob(ContractName::new("oranj"), None, None)],
+        );
+        index_tx(&state, &tx, &mut indexes);
+
+        assert!(supply_events(&indexes).is_empty());
+        assert!(indexes.take_changed().is_empty());
+    }
+}
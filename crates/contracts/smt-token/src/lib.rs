@@ -1,6 +1,6 @@
 use std::collections::BTreeMap;
 
-use account::Account;
+use account::{Account, TokenRole};
 use borsh::{BorshDeserialize, BorshSerialize};
 use sdk::merkle_utils::{BorshableMerkleProof, SHA256Hasher};
 use sdk::utils::parse_calldata;
@@ -9,6 +9,7 @@ use sdk::{
     StructuredBlobData, TransactionalZkContract,
 };
 use sdk::{RunResult, ZkContract};
+use serde::{Deserialize, Serialize};
 use sparse_merkle_tree::traits::Value;
 
 extern crate alloc;
@@ -19,11 +20,17 @@ pub mod client;
 #[cfg(feature = "client")]
 pub mod indexer;
 
+pub use sdk::supply::SupplyEvent;
+
 pub const TOTAL_SUPPLY: u128 = 100_000_000_000_000;
 pub const FAUCET_ID: &str = "faucet@hydentity";
+/// Account holding what can still be minted under the supply cap, burns are paid back to it.
+/// It isn't a valid identity, so it can't be transferred from or to.
+pub const MINT_RESERVE_ID: &str = "mint-reserve";
 
 /// Enum representing possible calls to Token contract functions.
-#[derive(Debug, Clone, PartialEq, BorshDeserialize, BorshSerialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, BorshDeserialize, BorshSerialize)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub enum SmtTokenAction {
     Transfer {
         sender: Identity,
@@ -41,6 +48,193 @@ pub enum SmtTokenAction {
         spender: Identity,
         amount: u128,
     },
+    Mint {
+        minter: Identity,
+        recipient: Identity,
+        amount: u128,
+    },
+    Burn {
+        burner: Identity,
+        amount: u128,
+    },
+    GrantRole {
+        admin: Identity,
+        account: Identity,
+        role: TokenRole,
+    },
+    RevokeRole {
+        admin: Identity,
+        account: Identity,
+        role: TokenRole,
+    },
+}
+
+impl SmtTokenAction {
+    /// Event of the action if it is a supply or role action
+    pub fn supply_event(&self) -> Option<SupplyEvent> {
+        match self.clone() {
+            SmtTokenAction::Mint {
+                minter,
+                recipient,
+                amount,
+            } => Some(SupplyEvent::Minted {
+                minter,
+                recipient,
+                amount,
+            }),
+            SmtTokenAction::Burn { burner, amount } => Some(SupplyEvent::Burned { burner, amount }),
+            SmtTokenAction::GrantRole {
+                admin,
+                account,
+                role,
+            } => Some(SupplyEvent::RoleGranted {
+                admin,
+                account,
+                role,
+            }),
+            SmtTokenAction::RevokeRole {
+                admin,
+                account,
+                role,
+            } => Some(SupplyEvent::RoleRevoked {
+                admin,
+                account,
+                role,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Accounts read and written by the supply and role actions, None for the other actions
+    pub fn supply_accounts(&self) -> Option<Vec<Identity>> {
+        let accounts = match self {
+            SmtTokenAction::Mint {
+                minter, recipient, ..
+            } => vec![
+                minter.clone(),
+                recipient.clone(),
+                Identity::from(MINT_RESERVE_ID),
+            ],
+            SmtTokenAction::Burn { burner, .. } => {
+                vec![burner.clone(), Identity::from(MINT_RESERVE_ID)]
+            }
+            SmtTokenAction::GrantRole { admin, account, .. }
+            | SmtTokenAction::RevokeRole { admin, account, .. } => {
+                vec![admin.clone(), account.clone()]
+            }
+            _ => return None,
+        };
+        let mut unique: Vec<Identity> = Vec::new();
+        for account in accounts {
+            if !unique.contains(&account) {
+                unique.push(account);
+            }
+        }
+        Some(unique)
+    }
+}
+
+/// Errors if one of the accounts is the [MINT_RESERVE_ID], which only supply actions can move
+pub fn check_not_reserve(accounts: &[&Identity]) -> Result<(), String> {
+    if accounts.iter().any(|account| account.0 == MINT_RESERVE_ID) {
+        return Err("The mint reserve can only be used by mints and burns".to_string());
+    }
+    Ok(())
+}
+
+fn supply_account<'a>(
+    accounts: &'a mut BTreeMap<Identity, Account>,
+    address: &Identity,
+) -> Result<&'a mut Account, String> {
+    accounts
+        .get_mut(address)
+        .ok_or_else(|| format!("Account {address} not found"))
+}
+
+fn check_role(
+    accounts: &mut BTreeMap<Identity, Account>,
+    caller: &Identity,
+    account: &Identity,
+    role: TokenRole,
+) -> Result<(), String> {
+    if caller != account {
+        return Err(format!("Caller {caller} can't act as {account}"));
+    }
+    if !supply_account(accounts, account)?.has_role(role) {
+        return Err(format!("{account} doesn't have the {role:?} role"));
+    }
+    Ok(())
+}
+
+/// Applies a supply or role action of `caller` to the accounts of its
+/// [SmtTokenAction::supply_accounts]. Shared by the contract and the host-side states, the
+/// accounts must be discarded on error.
+pub fn apply_supply_action(
+    caller: &Identity,
+    action: SmtTokenAction,
+    accounts: &mut BTreeMap<Identity, Account>,
+) -> Result<String, String> {
+    let reserve = Identity::from(MINT_RESERVE_ID);
+    match action {
+        SmtTokenAction::Mint {
+            minter,
+            recipient,
+            amount,
+        } => {
+            check_role(accounts, caller, &minter, TokenRole::Minter)?;
+            check_not_reserve(&[&recipient])?;
+
+            let reserve_account = supply_account(accounts, &reserve)?;
+            reserve_account.balance = reserve_account
+                .balance
+                .checked_sub(amount)
+                .ok_or("Supply cap exceeded")?;
+            let recipient_account = supply_account(accounts, &recipient)?;
+            recipient_account.balance = recipient_account
+                .balance
+                .checked_add(amount)
+                .ok_or("Overflow in recipient balance")?;
+            Ok(format!("Minted {amount} to {recipient}"))
+        }
+        SmtTokenAction::Burn { burner, amount } => {
+            check_role(accounts, caller, &burner, TokenRole::Burner)?;
+
+            let burner_account = supply_account(accounts, &burner)?;
+            burner_account.balance = burner_account
+                .balance
+                .checked_sub(amount)
+                .ok_or("Insufficient balance")?;
+            // An uncapped reserve stays at the max
+            let reserve_account = supply_account(accounts, &reserve)?;
+            reserve_account.balance = reserve_account.balance.saturating_add(amount);
+            Ok(format!("Burned {amount}"))
+        }
+        SmtTokenAction::GrantRole {
+            admin,
+            account,
+            role,
+        } => {
+            check_role(accounts, caller, &admin, TokenRole::Admin)?;
+            check_not_reserve(&[&account])?;
+
+            supply_account(accounts, &account)?.roles.insert(role);
+            Ok(format!("Granted {role:?} role to {account}"))
+        }
+        SmtTokenAction::RevokeRole {
+            admin,
+            account,
+            role,
+        } => {
+            check_role(accounts, caller, &admin, TokenRole::Admin)?;
+            if admin == account && role == TokenRole::Admin {
+                return Err("Admins can't revoke their own admin role".to_string());
+            }
+
+            supply_account(accounts, &account)?.roles.remove(&role);
+            Ok(format!("Revoked {role:?} role from {account}"))
+        }
+        _ => Err("Not a supply action".to_string()),
+    }
 }
 
 /// Struct representing the SMT token.
@@ -104,6 +298,7 @@ impl ZkContract for SmtTokenContract {
                 spender,
                 amount,
             } => self.approve(owner, spender, amount),
+            action => self.execute_supply_action(&execution_ctx.caller, action),
         };
 
         match output {
@@ -134,6 +329,7 @@ impl SmtTokenContract {
             mut accounts,
             proof,
         } = self.steps.pop().expect("Incorrect proof setup");
+        check_not_reserve(&[&sender, &recipient])?;
         {
             let sender_account = accounts.get(&sender).ok_or("Sender not found")?;
             let recipient_account = accounts.get(&recipient).ok_or("Recipient not found")?;
@@ -224,6 +420,7 @@ impl SmtTokenContract {
             mut accounts,
             proof,
         } = self.steps.pop().expect("Incorrect proof setup");
+        check_not_reserve(&[&owner, &recipient])?;
 
         let recipient_account = accounts.get(&recipient).ok_or("Recipient not found")?;
         if recipient_account.address != recipient {
@@ -261,6 +458,7 @@ impl SmtTokenContract {
             mut accounts,
             proof,
         } = self.steps.pop().expect("Incorrect proof setup");
+        check_not_reserve(&[&owner])?;
         {
             let owner_account = accounts.get(&owner).ok_or("Owner account not found")?;
 
@@ -301,6 +499,60 @@ impl SmtTokenContract {
         self.commitment = StateCommitment(Into::<[u8; 32]>::into(new_root).to_vec());
         Ok(format!("Approved {amount} to {spender}"))
     }
+
+    fn execute_supply_action(
+        &mut self,
+        caller: &Identity,
+        action: SmtTokenAction,
+    ) -> Result<String, String> {
+        let SmtTokenStep {
+            mut accounts,
+            proof,
+        } = self
+            .steps
+            .pop()
+            .ok_or("No merkle witness for this calldata")?;
+        let addresses = action.supply_accounts().unwrap_or_default();
+
+        let root: [u8; 32] = self
+            .commitment
+            .0
+            .clone()
+            .try_into()
+            .map_err(|_| "Invalid merkle root".to_string())?;
+        let verified = proof
+            .0
+            .clone()
+            .verify::<SHA256Hasher>(&root.into(), Self::leaves(&accounts, &addresses)?)
+            .map_err(|e| format!("Failed to verify merkle proof: {e:?}"))?;
+        if !verified {
+            return Err("Merkle proof invalid".to_string());
+        }
+
+        let output = apply_supply_action(caller, action, &mut accounts)?;
+
+        let new_root = proof
+            .0
+            .compute_root::<SHA256Hasher>(Self::leaves(&accounts, &addresses)?)
+            .map_err(|e| format!("Failed to compute new root: {e:?}"))?;
+        self.commitment = StateCommitment(Into::<[u8; 32]>::into(new_root).to_vec());
+        Ok(output)
+    }
+
+    fn leaves(
+        accounts: &BTreeMap<Identity, Account>,
+        addresses: &[Identity],
+    ) -> Result<Vec<(sparse_merkle_tree::H256, sparse_merkle_tree::H256)>, String> {
+        addresses
+            .iter()
+            .map(|address| match accounts.get(address) {
+                Some(account) if account.address == *address => {
+                    Ok((account.get_key(), account.to_h256()))
+                }
+                _ => Err(format!("Account {address} not in the merkle witness")),
+            })
+            .collect()
+    }
 }
 
 impl ContractAction for SmtTokenAction {
@@ -321,6 +573,14 @@ impl ContractAction for SmtTokenAction {
     }
 }
 
+/// Interface of the contract, to give in the constructor metadata when registering it.
+/// The state is a sparse merkle tree root, so it has no borsh layout.
+#[cfg(feature = "schema")]
+pub fn contract_schema() -> sdk::ContractSchema {
+    sdk::ContractSchema::new::<SmtTokenAction>(sdk::ActionEncoding::Structured)
+        .with_utf8_program_outputs()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::account::AccountSMT;
@@ -674,4 +934,75 @@ mod tests {
             smt_token.commit()
         );
     }
+
+    #[test_log::test]
+    fn test_smt_token_supply_control() {
+        let admin = Identity::from("admin@hydentity");
+        let bob = Identity::from("bob@hydentity");
+        let reserve = Identity::from(MINT_RESERVE_ID);
+        let smt = AccountSMT::with_supply_control(admin.clone(), Some(100));
+
+        let mut accounts: BTreeMap<Identity, Account> = [&admin, &bob, &reserve]
+            .into_iter()
+            .map(|address| {
+                let account = smt
+                    .0
+                    .get(&Account::compute_key(address))
+                    .expect("Failed to get account");
+                let account = if account.address == *address {
+                    account
+                } else {
+                    Account::new(address.clone(), 0)
+                };
+                (address.clone(), account)
+            })
+            .collect();
+
+        let mint = |amount| SmtTokenAction::Mint {
+            minter: admin.clone(),
+            recipient: bob.clone(),
+            amount,
+        };
+        assert_eq!(
+            apply_supply_action(&admin, mint(10), &mut accounts.clone()),
+            Err(format!("{admin} doesn't have the Minter role"))
+        );
+
+        for role in [TokenRole::Minter, TokenRole::Burner] {
+            let grant = SmtTokenAction::GrantRole {
+                admin: admin.clone(),
+                account: admin.clone(),
+                role,
+            };
+            apply_supply_action(&admin, grant, &mut accounts).unwrap();
+        }
+        assert_eq!(
+            apply_supply_action(&bob, mint(10), &mut accounts.clone()),
+            Err(format!("Caller {bob} can't act as {admin}"))
+        );
+
+        apply_supply_action(&admin, mint(60), &mut accounts).unwrap();
+        assert_eq!(accounts[&bob].balance, 60);
+        assert_eq!(accounts[&reserve].balance, 40);
+        assert_eq!(
+            apply_supply_action(&admin, mint(50), &mut accounts.clone()),
+            Err("Supply cap exceeded".to_string())
+        );
+
+        let burn = SmtTokenAction::Burn {
+            burner: bob.clone(),
+            amount: 10,
+        };
+        assert_eq!(
+            apply_supply_action(&bob, burn, &mut accounts.clone()),
+            Err(format!("{bob} doesn't have the Burner role"))
+        );
+
+        let revoke = SmtTokenAction::RevokeRole {
+            admin: admin.clone(),
+            account: admin.clone(),
+            role: TokenRole::Admin,
+        };
+        assert!(apply_supply_action(&admin, revoke, &mut accounts).is_err());
+    }
 }
@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 #[derive(
     Debug, Default, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq,
 )]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub struct ValidatorFeeState {
     /// balance could go negative, the validator would then not be able to
     /// disseminate anymore, and would need to increase its balance first.
@@ -18,6 +19,7 @@ pub struct ValidatorFeeState {
 #[derive(
     Debug, Default, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq,
 )]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub struct Fees {
     /// Cumulative size of the data disseminated by the validators, pending fee distribution
     pub(crate) pending_fees: Vec<(ValidatorPublicKey, LaneBytesSize)>,
@@ -98,3 +98,11 @@ fn check_transfer_blob(blobs: &IndexedBlobs, index: BlobIndex, amount: u128) ->
         )),
     }
 }
+
+/// Interface of the contract, to give in the constructor metadata when registering it
+#[cfg(feature = "schema")]
+pub fn contract_schema() -> sdk::ContractSchema {
+    sdk::ContractSchema::new::<StakingAction>(sdk::ActionEncoding::Structured)
+        .with_state::<Staking>()
+        .with_utf8_program_outputs()
+}
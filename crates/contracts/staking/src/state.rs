@@ -8,6 +8,7 @@ use serde::{Deserialize, Serialize};
 use crate::fees::Fees;
 
 #[derive(Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub struct Staking {
     pub(crate) stakes: BTreeMap<Identity, u128>,
     pub(crate) delegations: BTreeMap<ValidatorPublicKey, Vec<Identity>>,
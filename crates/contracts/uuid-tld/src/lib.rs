@@ -9,12 +9,14 @@ use sdk::{
     Blob, BlobData, BlobIndex, Calldata, ContractAction, ContractName, OnchainEffect,
     RegisterContractAction, RunResult, ZkContract,
 };
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 #[cfg(feature = "client")]
 pub mod client;
 
-#[derive(Clone, BorshSerialize, BorshDeserialize)]
+#[derive(Clone, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub enum UuidTldAction {
     Claim,
 }
@@ -88,6 +90,7 @@ impl ZkContract for UuidTld {
 }
 
 #[derive(Default, Debug, Clone, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub struct UuidTld {
     // Maps UUID to the identity that claimed it
     registered_contracts: BTreeMap<u128, String>,
@@ -150,6 +153,15 @@ impl UuidTld {
     }
 }
 
+/// Interface of the contract, to give in the constructor metadata when registering it.
+/// Registrations of sub-contracts are `RegisterContractAction` blobs, which the schema does not cover.
+#[cfg(feature = "schema")]
+pub fn contract_schema() -> sdk::ContractSchema {
+    sdk::ContractSchema::new::<UuidTldAction>(sdk::ActionEncoding::Raw)
+        .with_state::<UuidTld>()
+        .with_utf8_program_outputs()
+}
+
 #[cfg(test)]
 mod test {
     use crate::*;
@@ -1,14 +1,16 @@
 use std::collections::BTreeMap;
 
+use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use strum::IntoDiscriminant;
 use utoipa::ToSchema;
 
 use crate::{
-    utils::TimestampMs, BlockHash, BlockHeight, ConsensusProposalHash, ContractName,
-    DataProposalHash, Identity, LaneBytesSize, LaneId, ProgramId, StateCommitment, TimeoutWindow,
-    Transaction, TransactionKind, TxHash, ValidatorPublicKey, Verifier,
+    utils::TimestampMs, BlobIndex, BlockHash, BlockHeight, Calldata, ConsensusProposalHash,
+    ContractName, DataProposalHash, Identity, LaneBytesSize, LaneId, ProgramId, Proof,
+    StateCommitment, TimeoutWindow, Transaction, TransactionKind, TxHash, ValidatorPublicKey,
+    Verifier,
 };
 
 #[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
@@ -36,6 +38,39 @@ pub struct ProofStat {
     pub proof_count: i64,
 }
 
+/// Distribution of durations, in milliseconds
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema, PartialEq, Eq)]
+pub struct APILatencyPercentiles {
+    pub count: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema, PartialEq)]
+pub struct APISettlementStatsPoint {
+    pub timestamp: i64, // UNIX timestamp of the start of the bucket, in milliseconds
+    pub sequenced_txs: u64, // Blob transactions with a blob for the contract sequenced in the bucket
+    pub successful_txs: u64,
+    pub failed_txs: u64,
+    pub timed_out_txs: u64,
+    pub failure_rate: f64, // Share of the sequenced transactions that failed
+    pub timeout_rate: f64, // Share of the sequenced transactions that timed out
+    pub blob_bytes: u64,   // Size of the blobs of the contract
+    pub settlement_latency: Option<APILatencyPercentiles>, // From sequencing to settlement
+    pub proof_settlement_lag: Option<APILatencyPercentiles>, // From the sequencing of the proof to settlement
+    pub settled_within_slo: Option<u64>, // Transactions settled within `slo_secs`, if given
+}
+
+/// Settlement analytics of a contract, bucketed by the time transactions were sequenced
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema, PartialEq)]
+pub struct APIContractSettlementStats {
+    pub contract_name: ContractName,
+    pub bucket_secs: u64,
+    pub points: Vec<APISettlementStatsPoint>,
+}
+
 #[derive(Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct APIRegisterContract {
     pub verifier: Verifier,
@@ -75,7 +110,7 @@ pub struct APIFees {
     pub balances: BTreeMap<ValidatorPublicKey, APIFeesBalance>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, PartialEq)]
 pub struct APIBlock {
     // Struct for the blocks table
     pub hash: ConsensusProposalHash,
@@ -113,6 +148,15 @@ pub enum TransactionStatusDb {
     TimedOut,
 }
 
+/// Settlement of a blob transaction, among the latest ones seen by the node
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
+pub struct APISettledTx {
+    /// Height of the block that settled the transaction
+    pub block_height: BlockHeight,
+    /// `Success`, `Failure` or `TimedOut`
+    pub status: TransactionStatusDb,
+}
+
 impl TransactionTypeDb {
     pub fn from(transaction: &Transaction) -> Self {
         transaction.transaction_data.discriminant().into()
@@ -151,6 +195,34 @@ pub struct APITransactionEvents {
     pub events: Vec<serde_json::Value>,
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
+pub struct APIIdentitySummary {
+    pub identity: String,
+    pub first_seen_block: Option<BlockHeight>, // Height of the first sequenced transaction of the identity
+    pub first_seen_timestamp: Option<TimestampMs>, // Timestamp of that block
+    pub total_txs: i64,                        // Number of blob transactions sent by the identity
+    pub txs_by_status: Vec<(TransactionStatusDb, i64)>, // Number of transactions for each status
+    pub contracts: Vec<String>,                // Contracts the identity sent blobs to
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum APISearchResultKind {
+    Block,
+    Transaction,
+    Proof,
+    Contract,
+    Identity,
+    DataProposal,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
+pub struct APISearchResult {
+    pub kind: APISearchResultKind,
+    pub id: String,   // Hash, height or name of the matching item
+    pub link: String, // Endpoint serving the item, relative to the indexer api root
+}
+
 #[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq)]
 pub struct TransactionWithBlobs {
     // Should match APITransaction
@@ -168,6 +240,76 @@ pub struct TransactionWithBlobs {
     pub blobs: Vec<BlobWithStatus>,
 }
 
+/// What a client of the explorer websocket can subscribe to
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq, Hash)]
+#[serde(tag = "topic", rename_all = "snake_case")]
+pub enum ExplorerWsTopic {
+    /// Every new block
+    Blocks,
+    /// Status transitions of a transaction: sequenced, then success, failure or timed out
+    TransactionStatus { tx_hash: TxHash },
+    /// Blob transactions with a blob for the contract
+    ContractTransactions { contract_name: ContractName },
+    /// Proofs verified for the contract
+    ContractProofs { contract_name: ContractName },
+    /// State commitments of the contract
+    ContractState { contract_name: ContractName },
+}
+
+/// Messages sent by a client of the explorer websocket
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ExplorerWsRequest {
+    Subscribe(ExplorerWsTopic),
+    Unsubscribe(ExplorerWsTopic),
+}
+
+/// Messages sent by the explorer websocket
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ExplorerWsEvent {
+    Subscribed {
+        topic: ExplorerWsTopic,
+    },
+    Unsubscribed {
+        topic: ExplorerWsTopic,
+    },
+    Block {
+        block: APIBlock,
+    },
+    TransactionStatus {
+        tx_hash: TxHash,
+        status: TransactionStatusDb,
+        block_hash: ConsensusProposalHash,
+        block_height: BlockHeight,
+    },
+    Transaction {
+        transaction: TransactionWithBlobs,
+    },
+    Proof {
+        contract_name: ContractName,
+        proof_tx_hash: TxHash,
+        blob_tx_hash: TxHash,
+        blob_index: u32,
+        block_height: BlockHeight,
+    },
+    ContractState {
+        contract_name: ContractName,
+        #[serde_as(as = "serde_with::hex::Hex")]
+        state_commitment: Vec<u8>,
+        block_hash: ConsensusProposalHash,
+        block_height: BlockHeight,
+    },
+    /// The client didn't read fast enough, up to `skipped` events of its topics were dropped
+    Lagged {
+        skipped: u64,
+    },
+    Error {
+        message: String,
+    },
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
 pub struct APIProofDetails {
     // Should match APITransaction (but without identity)
@@ -189,7 +331,8 @@ pub struct BlobWithStatus {
     pub contract_name: String, // Contract name associated with the blob
     #[serde_as(as = "serde_with::hex::Hex")]
     pub data: Vec<u8>, // Actual blob data
-    pub proof_outputs: Vec<serde_json::Value>, // outputs of proofs
+    pub decoded_data: Option<serde_json::Value>, // Human-readable data, if the contract has a known decoder
+    pub proof_outputs: Vec<serde_json::Value>,   // outputs of proofs
 }
 
 #[serde_as]
@@ -313,6 +456,50 @@ pub struct APIContractState {
     pub state_commitment: Vec<u8>,         // The contract state stored in JSON format
 }
 
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum APIContractStateTransitionKind {
+    /// The contract was registered, or registered again
+    Registration,
+    /// A blob of the contract settled with a proof output
+    Settlement,
+}
+
+/// A change of the state commitment of a contract
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct APIContractStateTransition {
+    pub contract_name: ContractName,
+    pub kind: APIContractStateTransitionKind,
+    /// Block where the transition was applied
+    pub block_height: BlockHeight,
+    /// Transaction that caused the transition, unknown for registrations indexed before it was recorded
+    pub tx_hash: Option<TxHash>,
+    /// Blob of `tx_hash` that caused the transition, for settlements
+    pub blob_index: Option<BlobIndex>,
+    /// State before the transition, `None` for registrations
+    pub initial_state: Option<StateCommitment>,
+    pub next_state: StateCommitment,
+    /// Program id at the time of the transition, unknown for rows indexed before it was recorded
+    pub program_id: Option<ProgramId>,
+}
+
+/// State of a contract at two block heights, and the transitions in between
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct APIContractStateDiff {
+    pub contract_name: ContractName,
+    pub from_height: BlockHeight,
+    pub to_height: BlockHeight,
+    /// `None` if the contract was not registered yet
+    pub from_state: Option<StateCommitment>,
+    pub to_state: Option<StateCommitment>,
+    pub from_program_id: Option<ProgramId>,
+    pub to_program_id: Option<ProgramId>,
+    /// Transitions applied after `from_height` up to `to_height` included
+    pub transitions: Vec<APIContractStateTransition>,
+    /// Whether `transitions` was cut short, the remaining ones can be listed from the last height
+    pub truncated: bool,
+}
+
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct APIBlob {
@@ -322,6 +509,59 @@ pub struct APIBlob {
     pub contract_name: String, // Contract name associated with the blob
     #[serde_as(as = "serde_with::hex::Hex")]
     pub data: Vec<u8>, // Actual blob data
-    pub proof_outputs: Vec<serde_json::Value>, // outputs of proofs
-    pub verified: bool,        // Verification status
+    pub decoded_data: Option<serde_json::Value>, // Human-readable data, if the contract has a known decoder
+    pub proof_outputs: Vec<serde_json::Value>,   // outputs of proofs
+    pub verified: bool,                          // Verification status
+}
+
+/// Proving job submitted to a prover server
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, BorshSerialize, BorshDeserialize)]
+pub struct APIProvingJobRequest {
+    /// Program to prove, it has to be known by the prover server
+    pub program_id: ProgramId,
+    #[serde_as(as = "serde_with::hex::Hex")]
+    pub commitment_metadata: Vec<u8>,
+    #[schema(value_type = Vec<Object>)]
+    pub calldatas: Vec<Calldata>,
+    /// Jobs with a higher priority are proven first
+    #[serde(default)]
+    pub priority: u32,
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    ToSchema,
+    PartialEq,
+    Eq,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum APIProvingJobStatus {
+    Queued,
+    Proving,
+    Succeeded,
+    /// The job failed on every attempt
+    Failed,
+}
+
+#[derive(
+    Clone, Debug, Serialize, Deserialize, ToSchema, PartialEq, BorshSerialize, BorshDeserialize,
+)]
+pub struct APIProvingJob {
+    pub id: u64,
+    pub program_id: ProgramId,
+    pub priority: u32,
+    pub status: APIProvingJobStatus,
+    /// Number of times the job was tried
+    pub attempts: u32,
+    /// Error of the last attempt
+    pub error: Option<String>,
+    /// Set once the job succeeded
+    pub proof: Option<Proof>,
 }
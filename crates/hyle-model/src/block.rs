@@ -27,6 +27,8 @@ pub struct Block {
     pub blob_proof_outputs: Vec<HandledBlobProofOutput>,
     pub verified_blobs: Vec<(TxHash, BlobIndex, Option<usize>)>,
     pub new_bounded_validators: Vec<ValidatorPublicKey>,
+    /// Leaders of the views that timed out before this block's consensus proposal
+    pub timed_out_leaders: Vec<ValidatorPublicKey>,
     pub staking_actions: Vec<(Identity, StakingAction)>,
     pub registered_contracts:
         BTreeMap<ContractName, (TxHash, RegisterContractEffect, Option<Vec<u8>>)>,
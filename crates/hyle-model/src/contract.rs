@@ -147,6 +147,7 @@ impl std::fmt::Debug for StateCommitment {
     PartialOrd,
 )]
 #[cfg_attr(feature = "full", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 /// An identity is a string that identifies the person that sent
 /// the BlobTransaction
 pub struct Identity(pub String);
@@ -444,6 +445,7 @@ pub trait ContractAction: Send {
     PartialOrd,
 )]
 #[cfg_attr(feature = "full", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub struct ContractName(pub String);
 
 #[derive(
@@ -481,7 +483,7 @@ pub struct ProgramId(pub Vec<u8>);
 pub struct ProofData(#[cfg_attr(feature = "full", serde(with = "base64_field"))] pub Vec<u8>);
 
 #[derive(Debug, Default, PartialEq, Eq, Clone, BorshSerialize, BorshDeserialize)]
-#[cfg_attr(feature = "full", derive(Serialize, utoipa::ToSchema))]
+#[cfg_attr(feature = "full", derive(Serialize, Deserialize, utoipa::ToSchema))]
 pub struct ProofMetadata {
     pub cycles: Option<u64>,
     pub prover: Option<String>,
@@ -490,7 +492,7 @@ pub struct ProofMetadata {
 }
 
 #[derive(Debug, Default, PartialEq, Eq, Clone, BorshSerialize, BorshDeserialize)]
-#[cfg_attr(feature = "full", derive(Serialize, utoipa::ToSchema))]
+#[cfg_attr(feature = "full", derive(Serialize, Deserialize, utoipa::ToSchema))]
 pub struct Proof {
     pub data: ProofData,
     pub metadata: ProofMetadata,
@@ -742,6 +744,7 @@ impl From<usize> for BlobIndex {
     Ord,
 )]
 #[cfg_attr(feature = "full", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub struct BlockHeight(pub u64);
 
 impl Add<BlockHeight> for u64 {
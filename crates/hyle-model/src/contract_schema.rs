@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+
+/// Prefix of a [ConstructorMetadata] in the `constructor_metadata` of a contract registration.
+/// Metadata without this prefix are opaque bytes given to the indexers to build the initial state.
+pub const CONSTRUCTOR_METADATA_MAGIC: &[u8] = b"hyli-constructor-metadata:v1\0";
+
+/// Interface of a contract: the borsh layout of its actions, state and program outputs.
+///
+/// Types are referenced by their declaration, as in borsh schemas, and described in `definitions`.
+/// With the `schema` feature, it can be exported from types deriving `borsh::BorshSchema`.
+#[derive(Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(utoipa::ToSchema))]
+pub struct ContractSchema {
+    /// Declaration of the action type of the contract
+    pub action: String,
+    pub action_encoding: ActionEncoding,
+    /// Declaration of the state type, if the contract state is borsh encoded
+    pub state: Option<String>,
+    pub program_outputs: Option<ProgramOutputsEncoding>,
+    pub definitions: BTreeMap<String, TypeDefinition>,
+}
+
+/// How actions are encoded in blobs
+#[derive(Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ActionEncoding {
+    /// The action is the parameters of a [crate::StructuredBlobData]
+    Structured,
+    /// The blob data is the borsh encoded action
+    Raw,
+}
+
+/// How the contract encodes its `program_outputs`
+#[derive(Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(utoipa::ToSchema))]
+#[serde(tag = "encoding", rename_all = "snake_case")]
+pub enum ProgramOutputsEncoding {
+    /// Raw UTF-8 text, as output by most contracts
+    Utf8,
+    /// Borsh encoded value of the given declaration
+    Borsh { declaration: String },
+}
+
+/// Borsh layout of a type, mirroring `borsh::schema::Definition`
+#[derive(Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(utoipa::ToSchema))]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TypeDefinition {
+    /// Fixed size value, its declaration (`u64`, `bool`...) tells how to read it
+    Primitive {
+        size: u8,
+    },
+    /// Elements prefixed by their count on `length_width` bytes, or a fixed count if 0
+    Sequence {
+        length_width: u8,
+        min_length: u64,
+        max_length: u64,
+        elements: String,
+    },
+    Tuple {
+        elements: Vec<String>,
+    },
+    /// Variants prefixed by their discriminant on `tag_width` bytes
+    Enum {
+        tag_width: u8,
+        variants: Vec<EnumVariantDefinition>,
+    },
+    /// Fields encoded in order, unnamed fields have no name
+    Struct {
+        fields: Vec<FieldDefinition>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(utoipa::ToSchema))]
+pub struct EnumVariantDefinition {
+    pub discriminant: i64,
+    pub name: String,
+    pub declaration: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "full", derive(utoipa::ToSchema))]
+pub struct FieldDefinition {
+    pub name: Option<String>,
+    pub declaration: String,
+}
+
+#[cfg(feature = "schema")]
+impl ContractSchema {
+    /// Schema of a contract whose actions are of type `Action`
+    pub fn new<Action: borsh::BorshSchema>(action_encoding: ActionEncoding) -> Self {
+        let mut schema = ContractSchema {
+            action: String::new(),
+            action_encoding,
+            state: None,
+            program_outputs: None,
+            definitions: BTreeMap::new(),
+        };
+        schema.action = schema.add_type::<Action>();
+        schema
+    }
+
+    pub fn with_state<State: borsh::BorshSchema>(mut self) -> Self {
+        self.state = Some(self.add_type::<State>());
+        self
+    }
+
+    pub fn with_program_outputs<Output: borsh::BorshSchema>(mut self) -> Self {
+        let declaration = self.add_type::<Output>();
+        self.program_outputs = Some(ProgramOutputsEncoding::Borsh { declaration });
+        self
+    }
+
+    pub fn with_utf8_program_outputs(mut self) -> Self {
+        self.program_outputs = Some(ProgramOutputsEncoding::Utf8);
+        self
+    }
+
+    /// Adds the definitions of `T` and the types it depends on, returns its declaration
+    fn add_type<T: borsh::BorshSchema>(&mut self) -> String {
+        use borsh::schema::{BorshSchemaContainer, Definition, Fields};
+
+        let container = BorshSchemaContainer::for_type::<T>();
+        for (declaration, definition) in container.definitions() {
+            let definition = match definition {
+                Definition::Primitive(size) => TypeDefinition::Primitive { size: *size },
+                Definition::Sequence {
+                    length_width,
+                    length_range,
+                    elements,
+                } => TypeDefinition::Sequence {
+                    length_width: *length_width,
+                    min_length: *length_range.start(),
+                    max_length: *length_range.end(),
+                    elements: elements.clone(),
+                },
+                Definition::Tuple { elements } => TypeDefinition::Tuple {
+                    elements: elements.clone(),
+                },
+                Definition::Enum {
+                    tag_width,
+                    variants,
+                } => TypeDefinition::Enum {
+                    tag_width: *tag_width,
+                    variants: variants
+                        .iter()
+                        .map(|(discriminant, name, declaration)| EnumVariantDefinition {
+                            discriminant: *discriminant,
+                            name: name.clone(),
+                            declaration: declaration.clone(),
+                        })
+                        .collect(),
+                },
+                Definition::Struct { fields } => TypeDefinition::Struct {
+                    fields: match fields {
+                        Fields::NamedFields(fields) => fields
+                            .iter()
+                            .map(|(name, declaration)| FieldDefinition {
+                                name: Some(name.clone()),
+                                declaration: declaration.clone(),
+                            })
+                            .collect(),
+                        Fields::UnnamedFields(fields) => fields
+                            .iter()
+                            .map(|declaration| FieldDefinition {
+                                name: None,
+                                declaration: declaration.clone(),
+                            })
+                            .collect(),
+                        Fields::Empty => vec![],
+                    },
+                },
+            };
+            self.definitions.insert(declaration.clone(), definition);
+        }
+        container.declaration().clone()
+    }
+}
+
+/// Structured content of the `constructor_metadata` of a contract registration.
+///
+/// Nodes and indexers read the schema from it, while the initial state is what
+/// `TxExecutorHandler::construct_state` implementations expect.
+#[derive(
+    Debug, Default, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq,
+)]
+pub struct ConstructorMetadata {
+    pub schema: Option<ContractSchema>,
+    pub initial_state: Option<Vec<u8>>,
+}
+
+impl ConstructorMetadata {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = CONSTRUCTOR_METADATA_MAGIC.to_vec();
+        // Serializing into a Vec can't fail
+        #[allow(clippy::unwrap_used)]
+        BorshSerialize::serialize(self, &mut bytes).unwrap();
+        bytes
+    }
+
+    /// Reads constructor metadata, bytes without the [CONSTRUCTOR_METADATA_MAGIC] prefix
+    /// are considered to be the initial state. Fails if the prefixed metadata can't be decoded.
+    pub fn from_bytes(bytes: &[u8]) -> borsh::io::Result<Self> {
+        match bytes.strip_prefix(CONSTRUCTOR_METADATA_MAGIC) {
+            Some(metadata) => borsh::from_slice(metadata),
+            None => Ok(ConstructorMetadata {
+                schema: None,
+                initial_state: Some(bytes.to_vec()),
+            }),
+        }
+    }
+
+    /// Initial state in optional constructor metadata, as given to `construct_state`
+    pub fn initial_state_of(metadata: &Option<Vec<u8>>) -> borsh::io::Result<Option<Vec<u8>>> {
+        match metadata.as_deref() {
+            Some(metadata) => Ok(Self::from_bytes(metadata)?.initial_state),
+            None => Ok(None),
+        }
+    }
+
+    /// Schema in optional constructor metadata
+    pub fn schema_of(metadata: &Option<Vec<u8>>) -> borsh::io::Result<Option<ContractSchema>> {
+        match metadata.as_deref() {
+            Some(metadata) => Ok(Self::from_bytes(metadata)?.schema),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constructor_metadata_roundtrip() {
+        let metadata = ConstructorMetadata {
+            schema: Some(ContractSchema {
+                action: "u8".to_string(),
+                action_encoding: ActionEncoding::Raw,
+                state: None,
+                program_outputs: None,
+                definitions: BTreeMap::from([(
+                    "u8".to_string(),
+                    TypeDefinition::Primitive { size: 1 },
+                )]),
+            }),
+            initial_state: Some(vec![1, 2, 3]),
+        };
+        let bytes = metadata.to_bytes();
+        assert_eq!(ConstructorMetadata::from_bytes(&bytes).unwrap(), metadata);
+        assert_eq!(
+            ConstructorMetadata::initial_state_of(&Some(bytes)).unwrap(),
+            Some(vec![1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn test_constructor_metadata_invalid() {
+        let mut bytes = CONSTRUCTOR_METADATA_MAGIC.to_vec();
+        bytes.push(7);
+        assert!(ConstructorMetadata::from_bytes(&bytes).is_err());
+        assert!(ConstructorMetadata::schema_of(&Some(bytes.clone())).is_err());
+        assert!(ConstructorMetadata::initial_state_of(&Some(bytes)).is_err());
+    }
+
+    #[test]
+    fn test_constructor_metadata_opaque_bytes() {
+        let metadata = Some(vec![4, 5, 6]);
+        assert_eq!(
+            ConstructorMetadata::initial_state_of(&metadata).unwrap(),
+            Some(vec![4, 5, 6])
+        );
+        assert_eq!(ConstructorMetadata::schema_of(&metadata).unwrap(), None);
+        assert_eq!(ConstructorMetadata::schema_of(&None).unwrap(), None);
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_schema_from_borsh_schema() {
+        #[derive(borsh::BorshSchema)]
+        #[allow(dead_code)]
+        enum Action {
+            Transfer { to: String, amount: u128 },
+            Burn(Option<u64>),
+        }
+
+        let schema = ContractSchema::new::<Action>(ActionEncoding::Structured)
+            .with_program_outputs::<Vec<String>>();
+        assert_eq!(schema.action, "Action");
+        assert_eq!(
+            schema.program_outputs,
+            Some(ProgramOutputsEncoding::Borsh {
+                declaration: "Vec<String>".to_string()
+            })
+        );
+        let Some(TypeDefinition::Enum {
+            tag_width,
+            variants,
+        }) = schema.definitions.get("Action")
+        else {
+            panic!("Action should be an enum");
+        };
+        assert_eq!(*tag_width, 1);
+        assert_eq!(
+            variants.iter().map(|v| v.name.as_str()).collect::<Vec<_>>(),
+            vec!["Transfer", "Burn"]
+        );
+        assert_eq!(
+            schema.definitions.get("u128"),
+            Some(&TypeDefinition::Primitive { size: 16 })
+        );
+        assert!(schema.definitions.contains_key("Option<u64>"));
+    }
+}
@@ -19,8 +19,10 @@ pub use transaction::*;
 pub mod api;
 
 mod contract;
+mod contract_schema;
 mod staking;
 pub use contract::*;
+pub use contract_schema::*;
 pub use staking::*;
 
 pub const HASH_DISPLAY_SIZE: usize = 3;
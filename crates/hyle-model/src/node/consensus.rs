@@ -49,34 +49,47 @@ impl Display for SignedByValidator<ValidatorCandidacy> {
 }
 
 #[cfg(feature = "sqlx")]
-impl sqlx::Type<sqlx::Postgres> for ConsensusProposalHash {
-    fn type_info() -> sqlx::postgres::PgTypeInfo {
-        <String as sqlx::Type<sqlx::Postgres>>::type_info()
+impl<DB: sqlx::Database> sqlx::Type<DB> for ConsensusProposalHash
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
     }
 }
 
 #[cfg(feature = "sqlx")]
-impl sqlx::Encode<'_, sqlx::Postgres> for ConsensusProposalHash {
+impl<'q, DB: sqlx::Database> sqlx::Encode<'q, DB> for ConsensusProposalHash
+where
+    String: sqlx::Encode<'q, DB>,
+{
     fn encode_by_ref(
         &self,
-        buf: &mut sqlx::postgres::PgArgumentBuffer,
+        buf: &mut <DB as sqlx::Database>::ArgumentBuffer<'q>,
     ) -> std::result::Result<
         sqlx::encode::IsNull,
         std::boxed::Box<(dyn std::error::Error + std::marker::Send + std::marker::Sync + 'static)>,
     > {
-        <String as sqlx::Encode<sqlx::Postgres>>::encode_by_ref(&self.0, buf)
+        <String as sqlx::Encode<DB>>::encode_by_ref(&self.0, buf)
     }
 }
 
 #[cfg(feature = "sqlx")]
-impl<'r> sqlx::Decode<'r, sqlx::Postgres> for ConsensusProposalHash {
+impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for ConsensusProposalHash
+where
+    String: sqlx::Decode<'r, DB>,
+{
     fn decode(
-        value: sqlx::postgres::PgValueRef<'r>,
+        value: <DB as sqlx::Database>::ValueRef<'r>,
     ) -> std::result::Result<
         ConsensusProposalHash,
         std::boxed::Box<(dyn std::error::Error + std::marker::Send + std::marker::Sync + 'static)>,
     > {
-        let inner = <String as sqlx::Decode<sqlx::Postgres>>::decode(value)?;
+        let inner = <String as sqlx::Decode<DB>>::decode(value)?;
         Ok(ConsensusProposalHash(inner))
     }
 }
@@ -100,6 +113,10 @@ pub struct ConsensusProposal {
     pub cut: Cut,
     pub staking_actions: Vec<ConsensusStakingAction>,
     pub timestamp: TimestampMs,
+    /// Leaders of the previous views of this slot, which timed out.
+    /// Leader reputation is derived from these once the proposal is committed.
+    #[serde(default)]
+    pub timed_out_leaders: Vec<ValidatorPublicKey>,
 }
 
 /// This is the hash of the proposal, signed by validators
@@ -126,6 +143,9 @@ impl Hashed<ConsensusProposalHash> for ConsensusProposal {
         });
         hasher.update(self.timestamp.0.to_le_bytes());
         hasher.update(self.parent_hash.0.as_bytes());
+        self.timed_out_leaders
+            .iter()
+            .for_each(|leader| hasher.update(&leader.0));
         ConsensusProposalHash(hex::encode(hasher.finalize()))
     }
 }
@@ -204,6 +224,7 @@ mod tests {
             staking_actions: vec![],
             timestamp: TimestampMs(1),
             parent_hash: ConsensusProposalHash("".to_string()),
+            timed_out_leaders: vec![],
         };
         let hash = proposal.hashed();
         assert_eq!(hash.0.len(), 64);
@@ -231,6 +252,7 @@ mod tests {
             .into()],
             timestamp: TimestampMs(1),
             parent_hash: ConsensusProposalHash("parent".to_string()),
+            timed_out_leaders: vec![],
         };
         let mut b = ConsensusProposal {
             slot: 1,
@@ -255,6 +277,7 @@ mod tests {
             .into()],
             timestamp: TimestampMs(1),
             parent_hash: ConsensusProposalHash("parent".to_string()),
+            timed_out_leaders: vec![],
         };
         assert_ne!(a.hashed(), b.hashed());
         if let ConsensusStakingAction::Bond { candidate: a } =
@@ -286,5 +309,10 @@ mod tests {
         assert_ne!(a.hashed(), b.hashed());
         b.parent_hash = ConsensusProposalHash("different".to_string());
         assert_eq!(a.hashed(), b.hashed());
+
+        a.timed_out_leaders = vec![ValidatorPublicKey(vec![1])];
+        assert_ne!(a.hashed(), b.hashed());
+        b.timed_out_leaders = vec![ValidatorPublicKey(vec![1])];
+        assert_eq!(a.hashed(), b.hashed());
     }
 }
@@ -29,6 +29,9 @@ pub enum MempoolBlockEvent {
 pub struct DataProposal {
     pub parent_data_proposal_hash: Option<DataProposalHash>,
     pub txs: Vec<Transaction>,
+    /// Root of the erasure-coded chunks of the DataProposal, when it is disseminated as shards.
+    /// It is part of the hash, so that votes on the DataProposal are bound to a single encoding.
+    pub chunk_root: Option<[u8; 32]>,
     /// Internal cache of the hash of the transaction
     #[borsh(skip)]
     hash_cache: RwLock<Option<DataProposalHash>>,
@@ -39,10 +42,42 @@ impl DataProposal {
         Self {
             parent_data_proposal_hash,
             txs,
+            chunk_root: None,
             hash_cache: RwLock::new(None),
         }
     }
 
+    /// Binds the DataProposal to the root of its erasure-coded chunks, which changes its hash.
+    pub fn with_chunk_root(mut self, chunk_root: [u8; 32]) -> Self {
+        self.chunk_root = Some(chunk_root);
+        self.hash_cache = RwLock::new(None);
+        self
+    }
+
+    /// Hash of the parent and transactions only, which is the hash of a DataProposal without chunk root.
+    pub fn body_hash(&self) -> DataProposalHash {
+        let mut hasher = Sha3_256::new();
+        if let Some(ref parent_data_proposal_hash) = self.parent_data_proposal_hash {
+            hasher.update(parent_data_proposal_hash.0.as_bytes());
+        }
+        for tx in self.txs.iter() {
+            hasher.update(tx.hashed().0);
+        }
+        DataProposalHash(hex::encode(hasher.finalize()))
+    }
+
+    /// Hash of a DataProposal bound to a chunk root, computable without the DataProposal
+    /// so that a single shard can be checked against it.
+    pub fn hash_with_chunk_root(
+        body_hash: &DataProposalHash,
+        chunk_root: &[u8; 32],
+    ) -> DataProposalHash {
+        let mut hasher = Sha3_256::new();
+        hasher.update(body_hash.0.as_bytes());
+        hasher.update(chunk_root);
+        DataProposalHash(hex::encode(hasher.finalize()))
+    }
+
     pub fn remove_proofs(&mut self) {
         self.txs.iter_mut().for_each(|tx| {
             match &mut tx.transaction_data {
@@ -59,6 +94,21 @@ impl DataProposal {
         });
     }
 
+    /// Removes the proofs of a DataProposal whose transactions were not all valid: the proof
+    /// transactions at `invalid_txs` have their proven blobs cleared, so that they settle nothing.
+    /// Unlike [Self::remove_proofs], unverified proof transactions are left as they are.
+    /// Transaction hashes don't cover proofs nor proven blobs, so the hash is unchanged.
+    pub fn remove_proofs_and_invalid_outputs(&mut self, invalid_txs: &[usize]) {
+        for (index, tx) in self.txs.iter_mut().enumerate() {
+            if let TransactionData::VerifiedProof(proof_tx) = &mut tx.transaction_data {
+                proof_tx.proof = None;
+                if invalid_txs.contains(&index) {
+                    proof_tx.proven_blobs.clear();
+                }
+            }
+        }
+    }
+
     /// This is used to set the hash of the DataProposal when we can trust we know it
     /// (specifically - deserializating from local storage)
     /// # Safety
@@ -73,6 +123,7 @@ impl Clone for DataProposal {
         let mut new = Self::default();
         new.parent_data_proposal_hash = self.parent_data_proposal_hash.clone();
         new.txs = self.txs.clone();
+        new.chunk_root = self.chunk_root;
         new.hash_cache = RwLock::new(self.hash_cache.read().unwrap().clone());
         new
     }
@@ -130,14 +181,11 @@ impl Hashed<DataProposalHash> for DataProposal {
         if let Some(hash) = self.hash_cache.read().unwrap().as_ref() {
             return hash.clone();
         }
-        let mut hasher = Sha3_256::new();
-        if let Some(ref parent_data_proposal_hash) = self.parent_data_proposal_hash {
-            hasher.update(parent_data_proposal_hash.0.as_bytes());
-        }
-        for tx in self.txs.iter() {
-            hasher.update(tx.hashed().0);
-        }
-        let hash = DataProposalHash(hex::encode(hasher.finalize()));
+        let body_hash = self.body_hash();
+        let hash = match &self.chunk_root {
+            Some(chunk_root) => DataProposal::hash_with_chunk_root(&body_hash, chunk_root),
+            None => body_hash,
+        };
         *self.hash_cache.write().unwrap() = Some(hash.clone());
         hash
     }
@@ -7,12 +7,14 @@ use serde::{
 use crate::*;
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub struct RewardsClaim {
     block_heights: Vec<BlockHeight>,
 }
 
 /// Enum representing the actions that can be performed by the Staking contract.
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub enum StakingAction {
     Stake {
         amount: u128,
@@ -52,6 +54,7 @@ impl ContractAction for StakingAction {
 #[derive(
     Clone, BorshSerialize, BorshDeserialize, Default, Eq, PartialEq, Hash, PartialOrd, Ord,
 )]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub struct ValidatorPublicKey(pub Vec<u8>);
 
 impl ValidatorPublicKey {
@@ -159,6 +162,7 @@ pub struct LaneId(pub ValidatorPublicKey);
     Ord,
 )]
 #[cfg_attr(feature = "full", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
 pub struct LaneBytesSize(pub u64); // 16M Terabytes, is it enough ?
 
 impl std::ops::Add<usize> for LaneBytesSize {
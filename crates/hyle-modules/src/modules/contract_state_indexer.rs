@@ -9,12 +9,16 @@ use client_sdk::contract_indexer::{ContractHandler, ContractStateStore};
 use sdk::*;
 use std::{any::TypeId, ops::Deref, path::PathBuf, sync::Arc};
 use tokio::sync::RwLock;
-use tracing::debug;
+use tracing::{debug, info};
 
 use crate::node_state::module::NodeStateEvent;
 
 use super::SharedBuildApiCtx;
 
+pub mod storage;
+
+use storage::{BlockChanges, ContractStateStorage, ContractStateStorageKind};
+
 #[derive(Debug, Clone)]
 pub struct CSIBusEvent<E> {
     #[allow(unused)]
@@ -33,17 +37,41 @@ struct CSIBusClient<E: Clone + Send + Sync + BusMessage + 'static> {
 }
 }
 
+/// Indexes the state of a contract from the node state events, and serves it through the
+/// [`ContractHandler::api`] router.
+///
+/// The store is persisted with the [`ContractStateStorageKind`] backend of the context. Blocks
+/// at or below the checkpoint, the last block written, were already applied and are skipped,
+/// so a restart can resume from [`ContractStateIndexerCtx::checkpoint`] instead of replaying
+/// everything.
 pub struct ContractStateIndexer<State, Event: Clone + Send + Sync + BusMessage + 'static = ()> {
     bus: CSIBusClient<Event>,
     store: Arc<RwLock<ContractStateStore<State>>>,
     contract_name: ContractName,
-    file: PathBuf,
+    storage: Box<dyn ContractStateStorage<State>>,
+    checkpoint: Option<BlockHeight>,
+    // Changes of the block being handled
+    changes: BlockChanges,
 }
 
 pub struct ContractStateIndexerCtx {
     pub data_directory: PathBuf,
     pub contract_name: ContractName,
     pub api: SharedBuildApiCtx,
+    pub storage: ContractStateStorageKind,
+}
+
+impl ContractStateIndexerCtx {
+    /// Height of the last block written by the indexer, the DA can be read from the next one.
+    /// Has to be called before the module is built, as the storage is opened by a single owner.
+    pub fn checkpoint<State>(&self) -> Result<Option<BlockHeight>>
+    where
+        State: BorshSerialize + BorshDeserialize + Clone + Send + Sync + 'static,
+    {
+        let mut storage =
+            storage::open::<State>(self.storage, &self.data_directory, &self.contract_name)?;
+        Ok(storage.load()?.1)
+    }
 }
 
 impl<State, Event> Module for ContractStateIndexer<State, Event>
@@ -62,13 +90,14 @@ where
 
     async fn build(bus: SharedMessageBus, ctx: Self::Context) -> Result<Self> {
         let bus = CSIBusClient::new_from_bus(bus.new_handle()).await;
-        let file = ctx
-            .data_directory
-            .join(format!("state_indexer_{}.bin", ctx.contract_name).as_str());
+        let mut storage =
+            storage::open::<State>(ctx.storage, &ctx.data_directory, &ctx.contract_name)?;
 
-        let mut store =
-            Self::load_from_disk_or_default::<ContractStateStore<State>>(file.as_path());
+        let (mut store, checkpoint) = storage.load()?;
         store.contract_name = ctx.contract_name.clone();
+        if let Some(checkpoint) = checkpoint {
+            info!(cn = %ctx.contract_name, "📝 Resuming contract state indexer after block {}", checkpoint);
+        }
         let store = Arc::new(RwLock::new(store));
 
         let (nested, mut api) = State::api(Arc::clone(&store)).await;
@@ -100,9 +129,11 @@ where
 
         Ok(ContractStateIndexer {
             bus,
-            file,
             store,
             contract_name: ctx.contract_name,
+            storage,
+            checkpoint,
+            changes: BlockChanges::default(),
         })
     }
 
@@ -121,10 +152,8 @@ where
     }
 
     async fn persist(&mut self) -> Result<()> {
-        if let Err(e) = Self::save_on_disk::<ContractStateStore<State>>(
-            self.file.as_path(),
-            self.store.read().await.deref(),
-        ) {
+        let store = self.store.read().await;
+        if let Err(e) = self.storage.flush(store.deref()) {
             tracing::warn!(cn = %self.contract_name, "Failed to save contract state indexer on disk: {}", e);
         }
 
@@ -153,16 +182,19 @@ where
         Ok(())
     }
 
+    /// Returns whether a transaction of the contract was handled.
     async fn handle_txs<'a, T: IntoIterator<Item = &'a TxHash>, F>(
         &mut self,
         txs: T,
         block: &Block,
         handler: F,
         remove_from_unsettled: bool,
-    ) -> Result<()>
+        update_indexes: bool,
+    ) -> Result<bool>
     where
         F: Fn(&mut State, &BlobTransaction, BlobIndex, TxContext) -> Result<Option<Event>>,
     {
+        let mut handled = false;
         for tx in txs {
             let dp_hash = block.resolve_parent_dp_hash(tx)?.clone();
             let tx_id = TxId(dp_hash.clone(), tx.clone());
@@ -179,9 +211,13 @@ where
                     continue;
                 }
             };
+            handled = true;
+            if remove_from_unsettled {
+                self.changes.unsettled_removed.push(tx_id);
+            }
 
-            let state = store
-                .state
+            let ContractStateStore { state, indexes, .. } = &mut *store;
+            let state = state
                 .as_mut()
                 .ok_or(anyhow!("No state found for {}", self.contract_name))?;
 
@@ -191,6 +227,9 @@ where
                 }
 
                 let event = handler(state, &tx, BlobIndex(index), tx_context.clone())?;
+                if update_indexes {
+                    state.update_indexes(&tx, BlobIndex(index), indexes)?;
+                }
                 if TypeId::of::<Event>() != TypeId::of::<()>() {
                     if let Some(event) = event {
                         let _ = log_debug!(
@@ -203,7 +242,7 @@ where
                 }
             }
         }
-        Ok(())
+        Ok(handled)
     }
 
     // Used in lieu of a closure below to work around a weird lifetime check issue.
@@ -212,9 +251,19 @@ where
     }
 
     async fn handle_processed_block(&mut self, block: Block) -> Result<()> {
+        if self
+            .checkpoint
+            .is_some_and(|checkpoint| block.block_height <= checkpoint)
+        {
+            debug!(cn = %self.contract_name, "🔨 Skipping block {} already indexed", block.block_height);
+            return Ok(());
+        }
+
+        let mut state_changed = false;
         for (_, contract, metadata) in block.registered_contracts.values() {
             if self.contract_name == contract.contract_name {
                 self.handle_register_contract(contract, metadata).await?;
+                state_changed = true;
             }
         }
 
@@ -230,37 +279,53 @@ where
             }
         }
 
-        self.handle_txs(
-            block.txs.iter().map(Self::get_hash),
-            &block,
-            |state, tx, index, ctx| state.handle_transaction_sequenced(tx, index, ctx),
-            false,
-        )
-        .await?;
+        state_changed |= self
+            .handle_txs(
+                block.txs.iter().map(Self::get_hash),
+                &block,
+                |state, tx, index, ctx| state.handle_transaction_sequenced(tx, index, ctx),
+                false,
+                false,
+            )
+            .await?;
 
-        self.handle_txs(
-            &block.timed_out_txs,
-            &block,
-            |state, tx, index, ctx| state.handle_transaction_timeout(tx, index, ctx),
-            true,
-        )
-        .await?;
+        state_changed |= self
+            .handle_txs(
+                &block.timed_out_txs,
+                &block,
+                |state, tx, index, ctx| state.handle_transaction_timeout(tx, index, ctx),
+                true,
+                false,
+            )
+            .await?;
 
-        self.handle_txs(
-            &block.failed_txs,
-            &block,
-            |state, tx, index, ctx| state.handle_transaction_failed(tx, index, ctx),
-            true,
-        )
-        .await?;
+        state_changed |= self
+            .handle_txs(
+                &block.failed_txs,
+                &block,
+                |state, tx, index, ctx| state.handle_transaction_failed(tx, index, ctx),
+                true,
+                false,
+            )
+            .await?;
 
-        self.handle_txs(
-            &block.successful_txs,
-            &block,
-            |state, tx, index, ctx| state.handle_transaction_success(tx, index, ctx),
-            true,
-        )
-        .await?;
+        state_changed |= self
+            .handle_txs(
+                &block.successful_txs,
+                &block,
+                |state, tx, index, ctx| state.handle_transaction_success(tx, index, ctx),
+                true,
+                true,
+            )
+            .await?;
+
+        let mut store = self.store.write().await;
+        let mut changes = std::mem::take(&mut self.changes);
+        changes.height = block.block_height;
+        changes.state_changed = state_changed;
+        changes.indexes_changed = store.indexes.take_changed();
+        self.storage.write_block(&store, &changes)?;
+        self.checkpoint = Some(block.block_height);
 
         Ok(())
     }
@@ -286,7 +351,8 @@ where
                 .write()
                 .await
                 .unsettled_blobs
-                .insert(tx_id, (tx, tx_context));
+                .insert(tx_id.clone(), (tx, tx_context));
+            self.changes.unsettled_inserted.push(tx_id);
         }
 
         Ok(())
@@ -321,6 +387,7 @@ where
 
 #[cfg(test)]
 mod tests {
+    use client_sdk::contract_indexer::SecondaryIndexes;
     use client_sdk::transaction_builder::TxExecutorHandler;
     use sdk::*;
     use utoipa::openapi::OpenApi;
@@ -384,16 +451,42 @@ mod tests {
             Ok(None)
         }
 
+        fn update_indexes(
+            &self,
+            tx: &BlobTransaction,
+            _index: BlobIndex,
+            indexes: &mut SecondaryIndexes,
+        ) -> Result<()> {
+            if let Some(first) = self.0.first() {
+                indexes.set("first_byte", &tx.identity.0, first)?;
+            }
+            Ok(())
+        }
+
         async fn api(_store: Arc<RwLock<ContractStateStore<Self>>>) -> (axum::Router<()>, OpenApi) {
             (axum::Router::new(), OpenApi::default())
         }
     }
 
     async fn build_indexer(contract_name: ContractName) -> ContractStateIndexer<MockState> {
+        build_indexer_with_storage(
+            contract_name,
+            PathBuf::from("test_data"),
+            ContractStateStorageKind::File,
+        )
+        .await
+    }
+
+    async fn build_indexer_with_storage(
+        contract_name: ContractName,
+        data_directory: PathBuf,
+        storage: ContractStateStorageKind,
+    ) -> ContractStateIndexer<MockState> {
         let ctx = ContractStateIndexerCtx {
             contract_name,
-            data_directory: PathBuf::from("test_data"),
+            data_directory,
             api: Default::default(),
+            storage,
         };
 
         ContractStateIndexer::<MockState>::build(
@@ -490,6 +583,7 @@ mod tests {
                 },
                 |state, tx, index, ctx| state.handle_transaction_success(tx, index, ctx),
                 true,
+                true,
             )
             .await
             .unwrap();
@@ -499,6 +593,239 @@ mod tests {
         assert_eq!(store.state.clone().unwrap().0, vec![1, 2, 3]);
     }
 
+    #[test_log::test(tokio::test)]
+    async fn test_state_history() {
+        let contract_name = ContractName::from("test_contract");
+        let blob = Blob {
+            contract_name: contract_name.clone(),
+            data: BlobData(vec![1, 2, 3]),
+        };
+        let tx = BlobTransaction::new("test", vec![blob]);
+        let tx_id = TxId(DataProposalHash::default(), tx.hashed());
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut indexer = build_indexer_with_storage(
+            contract_name.clone(),
+            dir.path().to_path_buf(),
+            ContractStateStorageKind::File,
+        )
+        .await;
+        indexer
+            .handle_processed_block(Block {
+                block_height: BlockHeight(1),
+                registered_contracts: vec![(
+                    contract_name.clone(),
+                    (
+                        TxHash::default(),
+                        RegisterContractEffect {
+                            contract_name: contract_name.clone(),
+                            ..Default::default()
+                        },
+                        None,
+                    ),
+                )]
+                .into_iter()
+                .collect(),
+                ..Block::default()
+            })
+            .await
+            .unwrap();
+        {
+            let mut store = indexer.store.write().await;
+            store
+                .unsettled_blobs
+                .insert(tx_id.clone(), (tx, TxContext::default()));
+        }
+        indexer
+            .handle_processed_block(Block {
+                block_height: BlockHeight(3),
+                dp_parent_hashes: vec![(tx_id.1.clone(), tx_id.0.clone())]
+                    .into_iter()
+                    .collect(),
+                successful_txs: vec![tx_id.1.clone()],
+                ..Block::default()
+            })
+            .await
+            .unwrap();
+
+        let store = indexer.store.read().await;
+        let state_at = |height| store.state_at(BlockHeight(height)).unwrap();
+        assert!(state_at(0).is_none());
+        assert!(state_at(2).unwrap().0.is_empty());
+        assert_eq!(state_at(3).unwrap().0, vec![1, 2, 3]);
+        assert_eq!(state_at(10).unwrap().0, vec![1, 2, 3]);
+        drop(store);
+
+        // The history is read back from the files of a new indexer
+        let indexer = build_indexer_with_storage(
+            contract_name.clone(),
+            dir.path().to_path_buf(),
+            ContractStateStorageKind::File,
+        )
+        .await;
+        let store = indexer.store.read().await;
+        assert_eq!(
+            store.state_at(BlockHeight(3)).unwrap().unwrap().0,
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let contract_name = ContractName::from("test_contract");
+        for kind in [
+            ContractStateStorageKind::File,
+            ContractStateStorageKind::Fjall,
+        ] {
+            let data_directory = dir.path().join(format!("{kind:?}"));
+            std::fs::create_dir_all(&data_directory).unwrap();
+            let mut storage =
+                storage::open::<MockState>(kind, &data_directory, &contract_name).unwrap();
+            let (mut store, _) = storage.load().unwrap();
+            let recorded = client_sdk::contract_indexer::STATE_HISTORY_LENGTH as u64 + 10;
+            for height in 1..=recorded {
+                store.state = Some(MockState(height.to_be_bytes().to_vec()));
+                let changes = BlockChanges {
+                    height: BlockHeight(height),
+                    state_changed: true,
+                    ..BlockChanges::default()
+                };
+                storage.write_block(&store, &changes).unwrap();
+            }
+
+            // The oldest states are forgotten
+            assert!(
+                store.state_at(BlockHeight(10)).unwrap().is_none(),
+                "{kind:?}"
+            );
+            assert_eq!(
+                store.state_at(BlockHeight(11)).unwrap().unwrap().0,
+                11u64.to_be_bytes().to_vec(),
+                "{kind:?}"
+            );
+            assert_eq!(
+                store
+                    .state_at(BlockHeight(recorded + 5))
+                    .unwrap()
+                    .unwrap()
+                    .0,
+                recorded.to_be_bytes().to_vec(),
+                "{kind:?}"
+            );
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_fjall_storage_resumes_from_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let contract_name = ContractName::from("test_contract");
+        let blob = Blob {
+            contract_name: contract_name.clone(),
+            data: BlobData(vec![1, 2, 3]),
+        };
+        let tx = BlobTransaction::new("test", vec![blob]);
+        let tx_id = TxId(DataProposalHash::default(), tx.hashed());
+        let register_block = Block {
+            block_height: BlockHeight(1),
+            registered_contracts: vec![(
+                contract_name.clone(),
+                (
+                    TxHash::default(),
+                    RegisterContractEffect {
+                        contract_name: contract_name.clone(),
+                        ..Default::default()
+                    },
+                    None,
+                ),
+            )]
+            .into_iter()
+            .collect(),
+            ..Block::default()
+        };
+        let settle_block = Block {
+            block_height: BlockHeight(2),
+            dp_parent_hashes: vec![(tx_id.1.clone(), tx_id.0.clone())]
+                .into_iter()
+                .collect(),
+            successful_txs: vec![tx_id.1.clone()],
+            ..Block::default()
+        };
+
+        {
+            let mut indexer = build_indexer_with_storage(
+                contract_name.clone(),
+                dir.path().to_path_buf(),
+                ContractStateStorageKind::Fjall,
+            )
+            .await;
+            indexer
+                .handle_processed_block(register_block)
+                .await
+                .unwrap();
+            indexer
+                .handle_blob(tx_id.clone(), tx.clone(), TxContext::default())
+                .await
+                .unwrap();
+            indexer
+                .handle_processed_block(Block {
+                    block_height: BlockHeight(2),
+                    ..Block::default()
+                })
+                .await
+                .unwrap();
+            // Dropped without persisting, blocks were written incrementally
+        }
+
+        let ctx = ContractStateIndexerCtx {
+            contract_name: contract_name.clone(),
+            data_directory: dir.path().to_path_buf(),
+            api: Default::default(),
+            storage: ContractStateStorageKind::Fjall,
+        };
+        assert_eq!(ctx.checkpoint::<MockState>().unwrap(), Some(BlockHeight(2)));
+
+        let mut indexer = build_indexer_with_storage(
+            contract_name.clone(),
+            dir.path().to_path_buf(),
+            ContractStateStorageKind::Fjall,
+        )
+        .await;
+        {
+            let store = indexer.store.read().await;
+            assert!(store.state.is_some());
+            assert!(store.unsettled_blobs.contains_key(&tx_id));
+        }
+
+        // Already indexed, skipped
+        indexer.handle_processed_block(settle_block).await.unwrap();
+        assert!(indexer
+            .store
+            .read()
+            .await
+            .unsettled_blobs
+            .contains_key(&tx_id));
+
+        indexer
+            .handle_processed_block(Block {
+                block_height: BlockHeight(3),
+                dp_parent_hashes: vec![(tx_id.1.clone(), tx_id.0.clone())]
+                    .into_iter()
+                    .collect(),
+                successful_txs: vec![tx_id.1.clone()],
+                ..Block::default()
+            })
+            .await
+            .unwrap();
+        let store = indexer.store.read().await;
+        assert!(!store.unsettled_blobs.contains_key(&tx_id));
+        assert_eq!(store.state.clone().unwrap().0, vec![1, 2, 3]);
+        assert_eq!(
+            store.indexes.get::<u8>("first_byte", "test").unwrap(),
+            Some(1)
+        );
+    }
+
     #[tokio::test]
     async fn test_handle_node_state_event() {
         let contract_name = ContractName::from("test_contract");
@@ -0,0 +1,359 @@
+//! Persistence backends of the [`super::ContractStateIndexer`].
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{anyhow, Context, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+use client_sdk::contract_indexer::{ContractStateStore, StateHistory, STATE_HISTORY_LENGTH};
+use fjall::{Config, Keyspace, PartitionCreateOptions, PartitionHandle};
+use sdk::{BlockHeight, ContractName, TxId};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::modules::Module;
+use crate::node_state::module::NodeStateModule;
+
+/// Backend used to persist the store of a contract state indexer
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractStateStorageKind {
+    /// The whole store is serialized to files when the module persists, the history is written
+    /// as blocks are handled, one file per state
+    #[default]
+    File,
+    /// Each block is written incrementally to a fjall keyspace
+    Fjall,
+}
+
+/// What a block changed in the store, written by [`ContractStateStorage::write_block`]
+#[derive(Debug, Default)]
+pub struct BlockChanges {
+    pub height: BlockHeight,
+    /// The state changed, and was recorded in the history at `height`
+    pub state_changed: bool,
+    pub unsettled_inserted: Vec<TxId>,
+    pub unsettled_removed: Vec<TxId>,
+    /// Index names and keys of the secondary index entries set or removed
+    pub indexes_changed: Vec<(String, String)>,
+}
+
+/// Persistence of a [`ContractStateStore`], values are read from the store when writing.
+pub trait ContractStateStorage<State>: Send + Sync {
+    /// Loads the store and the checkpoint, the height of the last block written.
+    /// The history of the store reads from the storage.
+    fn load(&mut self) -> Result<(ContractStateStore<State>, Option<BlockHeight>)>;
+
+    /// Writes the changes of a block, then moves the checkpoint to its height
+    fn write_block(
+        &mut self,
+        store: &ContractStateStore<State>,
+        changes: &BlockChanges,
+    ) -> Result<()>;
+
+    /// Makes everything written so far durable, called when the module persists
+    fn flush(&mut self, store: &ContractStateStore<State>) -> Result<()>;
+}
+
+pub fn open<State>(
+    kind: ContractStateStorageKind,
+    data_directory: &Path,
+    contract_name: &ContractName,
+) -> Result<Box<dyn ContractStateStorage<State>>>
+where
+    State: BorshSerialize + BorshDeserialize + Clone + Send + Sync + 'static,
+{
+    Ok(match kind {
+        ContractStateStorageKind::File => {
+            Box::new(FileStorage::new(data_directory, contract_name)?)
+        }
+        ContractStateStorageKind::Fjall => {
+            Box::new(FjallStorage::new(data_directory, contract_name)?)
+        }
+    })
+}
+
+/// Dumps the whole store on flush, blocks written since the last flush are replayed on restart
+pub struct FileStorage {
+    file: PathBuf,
+    history: Arc<FileHistory>,
+    indexes_file: PathBuf,
+    checkpoint_file: PathBuf,
+    checkpoint: Option<BlockHeight>,
+}
+
+impl FileStorage {
+    pub fn new(data_directory: &Path, contract_name: &ContractName) -> Result<Self> {
+        let file = |suffix: &str| {
+            data_directory.join(format!("state_indexer_{contract_name}{suffix}.bin").as_str())
+        };
+        Ok(FileStorage {
+            file: file(""),
+            history: Arc::new(FileHistory::open(
+                data_directory.join(format!("state_indexer_{contract_name}_history")),
+            )?),
+            indexes_file: file("_indexes"),
+            checkpoint_file: file("_checkpoint"),
+            checkpoint: None,
+        })
+    }
+}
+
+impl<State> ContractStateStorage<State> for FileStorage
+where
+    State: BorshSerialize + BorshDeserialize + Clone + Send + Sync + 'static,
+{
+    fn load(&mut self) -> Result<(ContractStateStore<State>, Option<BlockHeight>)> {
+        let mut store =
+            NodeStateModule::load_from_disk_or_default::<ContractStateStore<State>>(&self.file);
+        store.history = Some(self.history.clone());
+        store.indexes = NodeStateModule::load_from_disk_or_default(&self.indexes_file);
+        self.checkpoint = NodeStateModule::load_from_disk(&self.checkpoint_file);
+        Ok((store, self.checkpoint))
+    }
+
+    fn write_block(
+        &mut self,
+        store: &ContractStateStore<State>,
+        changes: &BlockChanges,
+    ) -> Result<()> {
+        if changes.state_changed {
+            if let Some(state) = &store.state {
+                self.history.record(changes.height, state)?;
+            }
+        }
+        self.checkpoint = Some(changes.height);
+        Ok(())
+    }
+
+    fn flush(&mut self, store: &ContractStateStore<State>) -> Result<()> {
+        NodeStateModule::save_on_disk(&self.file, store)?;
+        NodeStateModule::save_on_disk(&self.indexes_file, &store.indexes)?;
+        if let Some(checkpoint) = &self.checkpoint {
+            NodeStateModule::save_on_disk(&self.checkpoint_file, checkpoint)?;
+        }
+        Ok(())
+    }
+}
+
+/// History of the [`FileStorage`], each state in the file named after its block height
+pub struct FileHistory {
+    directory: PathBuf,
+    heights: RwLock<BTreeSet<BlockHeight>>,
+}
+
+impl FileHistory {
+    fn open(directory: PathBuf) -> Result<Self> {
+        std::fs::create_dir_all(&directory).context(format!(
+            "creating history directory {}",
+            directory.display()
+        ))?;
+        let mut heights = BTreeSet::new();
+        for entry in std::fs::read_dir(&directory)? {
+            let path = entry?.path();
+            if let Some(height) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse().ok())
+            {
+                heights.insert(BlockHeight(height));
+            }
+        }
+        Ok(FileHistory {
+            directory,
+            heights: RwLock::new(heights),
+        })
+    }
+
+    fn file(&self, height: BlockHeight) -> PathBuf {
+        self.directory.join(format!("{}.bin", height.0))
+    }
+
+    /// Writes the state reached at `height`, then forgets the oldest states beyond
+    /// [`STATE_HISTORY_LENGTH`]
+    fn record<State: BorshSerialize>(&self, height: BlockHeight, state: &State) -> Result<()> {
+        NodeStateModule::save_on_disk(&self.file(height), state)?;
+        let mut heights = self
+            .heights
+            .write()
+            .map_err(|_| anyhow!("history lock poisoned"))?;
+        heights.insert(height);
+        while heights.len() > STATE_HISTORY_LENGTH {
+            if let Some(oldest) = heights.pop_first() {
+                std::fs::remove_file(self.file(oldest))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<State: BorshDeserialize> StateHistory<State> for FileHistory {
+    fn state_at(&self, height: BlockHeight) -> Result<Option<State>> {
+        let recorded = self
+            .heights
+            .read()
+            .map_err(|_| anyhow!("history lock poisoned"))?
+            .range(..=height)
+            .next_back()
+            .copied();
+        let Some(recorded) = recorded else {
+            return Ok(None);
+        };
+        let file = self.file(recorded);
+        let bytes =
+            std::fs::read(&file).context(format!("reading history file {}", file.display()))?;
+        Ok(Some(borsh::from_slice(&bytes).context("decoding state")?))
+    }
+}
+
+const STATE_KEY: &[u8] = b"state";
+const CONTRACT_NAME_KEY: &[u8] = b"contract_name";
+const CHECKPOINT_KEY: &[u8] = b"checkpoint";
+
+/// Writes each block as an atomic batch, so that a restart resumes from the last block written
+pub struct FjallStorage {
+    db: Keyspace,
+    meta: PartitionHandle,
+    unsettled: PartitionHandle,
+    history: PartitionHandle,
+    /// Number of states in the history partition
+    history_len: usize,
+    indexes: PartitionHandle,
+}
+
+impl FjallStorage {
+    pub fn new(data_directory: &Path, contract_name: &ContractName) -> Result<Self> {
+        let db = Config::new(data_directory.join(format!("state_indexer_{contract_name}.fjall")))
+            .open()
+            .context("opening contract state indexer keyspace")?;
+        let partition = |name| db.open_partition(name, PartitionCreateOptions::default());
+        Ok(FjallStorage {
+            meta: partition("meta")?,
+            unsettled: partition("unsettled")?,
+            history: partition("history")?,
+            history_len: 0,
+            indexes: partition("indexes")?,
+            db,
+        })
+    }
+
+    fn index_key(index: &str, key: &str) -> Vec<u8> {
+        [index.as_bytes(), &[0], key.as_bytes()].concat()
+    }
+}
+
+impl<State> ContractStateStorage<State> for FjallStorage
+where
+    State: BorshSerialize + BorshDeserialize + Clone + Send + Sync + 'static,
+{
+    fn load(&mut self) -> Result<(ContractStateStore<State>, Option<BlockHeight>)> {
+        let mut store = ContractStateStore::<State>::default();
+        if let Some(state) = self.meta.get(STATE_KEY)? {
+            store.state = Some(borsh::from_slice(&state).context("decoding state")?);
+        }
+        if let Some(contract_name) = self.meta.get(CONTRACT_NAME_KEY)? {
+            store.contract_name = borsh::from_slice(&contract_name)?;
+        }
+        for item in self.unsettled.iter() {
+            let (tx_id, tx) = item?;
+            store
+                .unsettled_blobs
+                .insert(borsh::from_slice(&tx_id)?, borsh::from_slice(&tx)?);
+        }
+        self.history_len = self.history.len()?;
+        store.history = Some(Arc::new(FjallHistory(self.history.clone())));
+        for item in self.indexes.iter() {
+            let (key, value) = item?;
+            let key = String::from_utf8(key.to_vec())?;
+            if let Some((index, key)) = key.split_once('\0') {
+                store.indexes.insert_raw(index, key, value.to_vec());
+            }
+        }
+        let checkpoint = self
+            .meta
+            .get(CHECKPOINT_KEY)?
+            .map(|checkpoint| borsh::from_slice(&checkpoint))
+            .transpose()?;
+        info!(
+            "Loaded contract state indexer store with {} unsettled transactions at checkpoint {:?}",
+            store.unsettled_blobs.len(),
+            checkpoint
+        );
+        Ok((store, checkpoint))
+    }
+
+    fn write_block(
+        &mut self,
+        store: &ContractStateStore<State>,
+        changes: &BlockChanges,
+    ) -> Result<()> {
+        let mut batch = self.db.batch();
+        let mut history_len = self.history_len;
+        if changes.state_changed {
+            if let Some(state) = &store.state {
+                let state = borsh::to_vec(state)?;
+                batch.insert(&self.meta, STATE_KEY, state.clone());
+                // Keys are big endian, so that the history is sorted by height
+                batch.insert(
+                    &self.history,
+                    changes.height.0.to_be_bytes().to_vec(),
+                    state,
+                );
+                // Forget the oldest states beyond the length of the history
+                history_len += 1;
+                let forgotten = history_len.saturating_sub(STATE_HISTORY_LENGTH);
+                for item in self.history.keys().take(forgotten) {
+                    batch.remove(&self.history, item?);
+                }
+                history_len -= forgotten;
+            }
+            batch.insert(
+                &self.meta,
+                CONTRACT_NAME_KEY,
+                borsh::to_vec(&store.contract_name)?,
+            );
+        }
+        for tx_id in changes.unsettled_inserted.iter() {
+            if let Some(tx) = store.unsettled_blobs.get(tx_id) {
+                batch.insert(&self.unsettled, borsh::to_vec(tx_id)?, borsh::to_vec(tx)?);
+            }
+        }
+        for tx_id in changes.unsettled_removed.iter() {
+            batch.remove(&self.unsettled, borsh::to_vec(tx_id)?);
+        }
+        for (index, key) in changes.indexes_changed.iter() {
+            let entry = Self::index_key(index, key);
+            match store.indexes.get_raw(index, key) {
+                Some(value) => batch.insert(&self.indexes, entry, value.clone()),
+                None => batch.remove(&self.indexes, entry),
+            }
+        }
+        batch.insert(&self.meta, CHECKPOINT_KEY, borsh::to_vec(&changes.height)?);
+        batch.commit()?;
+        self.history_len = history_len;
+        Ok(())
+    }
+
+    fn flush(&mut self, _store: &ContractStateStore<State>) -> Result<()> {
+        self.db
+            .persist(fjall::PersistMode::SyncAll)
+            .map_err(Into::into)
+    }
+}
+
+/// History of the [`FjallStorage`], keyed by block height
+pub struct FjallHistory(PartitionHandle);
+
+impl<State: BorshDeserialize> StateHistory<State> for FjallHistory {
+    fn state_at(&self, height: BlockHeight) -> Result<Option<State>> {
+        self.0
+            .range(..=height.0.to_be_bytes())
+            .next_back()
+            .transpose()?
+            .map(|(_, state)| borsh::from_slice(&state).context("decoding state"))
+            .transpose()
+    }
+}
@@ -88,6 +88,8 @@ pub struct AutoProverCtx<Contract> {
     pub buffer_blocks: u32,
     pub max_txs_per_proof: usize,
     pub tx_working_window_size: usize,
+    /// Executes each batch before proving it, to record its execution cost in the metrics
+    pub metering: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -1117,12 +1119,43 @@ where
         let node_client = self.ctx.node.clone();
         let prover = self.ctx.prover.clone();
         let contract_name = self.ctx.contract_name.clone();
+        let metering = self.ctx.metering;
 
         let metrics = self.metrics.clone();
         let handle = logged_task(async move {
             let mut retries = 0;
             const MAX_RETRIES: u32 = 30;
 
+            // If we are in metering mode, we execute the batch first to record its cost
+            if metering {
+                let meter_prover = prover.clone();
+                let meter_commitment_metadata = commitment_metadata.clone();
+                let meter_calldatas = calldatas.clone();
+                match tokio::task::spawn_blocking(move || {
+                    meter_prover.meter(meter_commitment_metadata, meter_calldatas)
+                })
+                .await
+                {
+                    Ok(Ok(cost)) => {
+                        info!(
+                            cn =% contract_name,
+                            "Batch id: {batch_id}, execution cost: {} cycles for {} txs, {:?} segments, {:?} bytes of memory",
+                            cost.cycles,
+                            calldatas.len(),
+                            cost.segments,
+                            cost.memory,
+                        );
+                        metrics.record_execution_cost(&cost, calldatas.len() as u64);
+                    }
+                    Ok(Err(e)) => {
+                        warn!(cn =% contract_name, "Batch id: {batch_id}, metering failed: {e:#}")
+                    }
+                    Err(e) => {
+                        warn!(cn =% contract_name, "Batch id: {batch_id}, metering task failed: {e}")
+                    }
+                }
+            }
+
             loop {
                 info!(
                     cn =% contract_name,
@@ -133,6 +133,7 @@ async fn new_buffering_auto_prover(
         buffer_blocks,
         max_txs_per_proof,
         tx_working_window_size: max_txs_per_proof,
+        metering: false,
     });
 
     let bus = SharedMessageBus::new(BusMetrics::global("default".to_string()));
@@ -1563,6 +1564,7 @@ async fn test_auto_prover_serialize_and_resume() -> Result<()> {
         buffer_blocks: 0,
         max_txs_per_proof: 1,
         tx_working_window_size: 3,
+        metering: false,
     });
 
     let bus = SharedMessageBus::new(BusMetrics::global("default".to_string()));
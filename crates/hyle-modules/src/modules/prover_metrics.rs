@@ -1,6 +1,6 @@
-use client_sdk::helpers::ProverInfo;
+use client_sdk::helpers::{ExecutionCost, ProverInfo};
 use opentelemetry::{
-    metrics::{Counter, Gauge, Histogram},
+    metrics::{Counter, Gauge, Histogram, Meter},
     KeyValue,
 };
 
@@ -15,6 +15,11 @@ pub struct AutoProverMetrics {
     proof_cycles_histogram: Histogram<u64>,
     proof_cycles_counter: Counter<u64>,
     proof_num_retries: Counter<u64>,
+    execution_cycles_histogram: Histogram<u64>,
+    execution_cycles_per_blob_histogram: Histogram<u64>,
+    execution_user_cycles_histogram: Histogram<u64>,
+    execution_segments_histogram: Histogram<u64>,
+    execution_memory_bytes_histogram: Histogram<u64>,
     buffered_blobs: Gauge<u64>,
     unsettled_blobs: Gauge<u64>,
     contract_name: String,
@@ -23,8 +28,14 @@ pub struct AutoProverMetrics {
 
 impl AutoProverMetrics {
     pub fn global(contract_name: String, infos: ProverInfo) -> AutoProverMetrics {
-        let my_meter = opentelemetry::global::meter("auto_prover");
+        Self::new(
+            &opentelemetry::global::meter("auto_prover"),
+            contract_name,
+            infos,
+        )
+    }
 
+    pub fn new(my_meter: &Meter, contract_name: String, infos: ProverInfo) -> AutoProverMetrics {
         AutoProverMetrics {
             proofs_requested: my_meter
                 .u64_counter("proof_client_proofs_requested")
@@ -51,6 +62,21 @@ impl AutoProverMetrics {
             proof_cycles_counter: my_meter
                 .u64_counter("proof_client_proof_cycles_counter")
                 .build(),
+            execution_cycles_histogram: my_meter
+                .u64_histogram("proof_client_execution_cycles_histogram")
+                .build(),
+            execution_cycles_per_blob_histogram: my_meter
+                .u64_histogram("proof_client_execution_cycles_per_blob_histogram")
+                .build(),
+            execution_user_cycles_histogram: my_meter
+                .u64_histogram("proof_client_execution_user_cycles_histogram")
+                .build(),
+            execution_segments_histogram: my_meter
+                .u64_histogram("proof_client_execution_segments_histogram")
+                .build(),
+            execution_memory_bytes_histogram: my_meter
+                .u64_histogram("proof_client_execution_memory_bytes_histogram")
+                .build(),
             buffered_blobs: my_meter.u64_gauge("proof_client_buffered_blobs").build(),
             unsettled_blobs: my_meter.u64_gauge("proof_client_unsettled_blobs").build(),
             contract_name,
@@ -98,6 +124,27 @@ impl AutoProverMetrics {
         self.proof_cycles_counter.add(cycles, &self.get_labels());
     }
 
+    /// Records the cost of executing `blobs` blobs together, measured before proving them
+    pub fn record_execution_cost(&self, cost: &ExecutionCost, blobs: u64) {
+        let labels = self.get_labels();
+        self.execution_cycles_histogram.record(cost.cycles, &labels);
+        if let Some(cycles_per_blob) = cost.cycles_per_blob(blobs) {
+            self.execution_cycles_per_blob_histogram
+                .record(cycles_per_blob, &labels);
+        }
+        if let Some(user_cycles) = cost.user_cycles {
+            self.execution_user_cycles_histogram
+                .record(user_cycles, &labels);
+        }
+        if let Some(segments) = cost.segments {
+            self.execution_segments_histogram.record(segments, &labels);
+        }
+        if let Some(memory) = cost.memory {
+            self.execution_memory_bytes_histogram
+                .record(memory, &labels);
+        }
+    }
+
     pub fn record_proof_retry(&self) {
         self.proof_num_retries.add(1, &self.get_labels());
     }
@@ -110,3 +157,125 @@ impl AutoProverMetrics {
         self.unsettled_blobs.record(count, &self.get_labels());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Weak};
+
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::{
+        error::OTelSdkResult,
+        metrics::{
+            data::{Histogram, ResourceMetrics},
+            reader::MetricReader,
+            InstrumentKind, ManualReader, MetricResult, Pipeline, SdkMeterProvider, Temporality,
+        },
+        Resource,
+    };
+
+    use super::*;
+
+    /// Lets the test collect the metrics of the provider owning the reader
+    #[derive(Debug, Clone)]
+    struct SharedReader(Arc<ManualReader>);
+
+    impl MetricReader for SharedReader {
+        fn register_pipeline(&self, pipeline: Weak<Pipeline>) {
+            self.0.register_pipeline(pipeline)
+        }
+
+        fn collect(&self, rm: &mut ResourceMetrics) -> MetricResult<()> {
+            self.0.collect(rm)
+        }
+
+        fn force_flush(&self) -> OTelSdkResult {
+            self.0.force_flush()
+        }
+
+        fn shutdown(&self) -> OTelSdkResult {
+            self.0.shutdown()
+        }
+
+        fn temporality(&self, kind: InstrumentKind) -> Temporality {
+            self.0.temporality(kind)
+        }
+    }
+
+    /// Sums of the values recorded in the histogram, with the count of records
+    fn histogram(reader: &SharedReader, name: &str) -> Option<(u64, u64)> {
+        let mut rm = ResourceMetrics {
+            resource: Resource::builder_empty().build(),
+            scope_metrics: vec![],
+        };
+        reader.collect(&mut rm).unwrap();
+        let metric = rm
+            .scope_metrics
+            .iter()
+            .flat_map(|scope| scope.metrics.iter())
+            .find(|metric| metric.name == name)?;
+        let data = metric
+            .data
+            .as_any()
+            .downcast_ref::<Histogram<u64>>()
+            .unwrap();
+        Some(data.data_points.iter().fold((0, 0), |(sum, count), point| {
+            (sum + point.sum, count + point.count)
+        }))
+    }
+
+    #[test]
+    fn test_record_execution_cost() {
+        let reader = SharedReader(Arc::new(ManualReader::builder().build()));
+        let provider = SdkMeterProvider::builder()
+            .with_reader(reader.clone())
+            .build();
+        let metrics = AutoProverMetrics::new(
+            &provider.meter("auto_prover"),
+            "counter".to_string(),
+            ProverInfo {
+                name: "test".to_string(),
+                zkvm: "test".to_string(),
+                version: "1.0.0".to_string(),
+            },
+        );
+
+        metrics.record_execution_cost(
+            &ExecutionCost {
+                cycles: 1_000,
+                user_cycles: Some(600),
+                segments: Some(2),
+                memory: None,
+            },
+            4,
+        );
+        // A batch without blobs is not averaged
+        metrics.record_execution_cost(
+            &ExecutionCost {
+                cycles: 500,
+                ..Default::default()
+            },
+            0,
+        );
+
+        assert_eq!(
+            histogram(&reader, "proof_client_execution_cycles_histogram"),
+            Some((1_500, 2))
+        );
+        assert_eq!(
+            histogram(&reader, "proof_client_execution_cycles_per_blob_histogram"),
+            Some((250, 1))
+        );
+        assert_eq!(
+            histogram(&reader, "proof_client_execution_user_cycles_histogram"),
+            Some((600, 1))
+        );
+        assert_eq!(
+            histogram(&reader, "proof_client_execution_segments_histogram"),
+            Some((2, 1))
+        );
+        assert_eq!(
+            histogram(&reader, "proof_client_execution_memory_bytes_histogram"),
+            None
+        );
+    }
+}
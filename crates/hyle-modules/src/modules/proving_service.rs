@@ -0,0 +1,750 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::bus::SharedMessageBus;
+use crate::modules::SharedBuildApiCtx;
+use crate::{log_error, module_bus_client, module_handle_messages, modules::Module};
+use anyhow::{anyhow, Context, Result};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use client_sdk::contract_indexer::AppError;
+use client_sdk::helpers::ClientSdkProver;
+use fjall::{Config, Keyspace, PartitionCreateOptions, PartitionHandle};
+use hyle_net::clock::TimestampMsClock;
+use sdk::api::{APIProvingJob, APIProvingJobRequest, APIProvingJobStatus};
+use sdk::hyle_model_utils::TimestampMs;
+use sdk::{Calldata, ProgramId, Proof};
+use tokio::sync::Notify;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+/// `ProvingService` is a module that proves jobs submitted over HTTP, so that app servers don't
+/// have to run provers locally. See [client_sdk::helpers::remote::RemoteProver] for the client.
+/// Jobs are queued by priority, then by submission order, and retried with a backoff according to
+/// the [ProvingJobsPolicy]. The jobs are saved on disk on every change, see [ProvingServiceDb].
+/// Jobs that were being proven when the server stopped are queued again on restart.
+pub struct ProvingService {
+    bus: ProvingServiceBusClient,
+    ctx: ProvingServiceCtx,
+    db: ProvingServiceDb,
+    store: Arc<Mutex<ProvingServiceStore>>,
+    new_job: Arc<Notify>,
+}
+
+module_bus_client! {
+#[derive(Debug)]
+pub struct ProvingServiceBusClient {
+}
+}
+
+pub struct ProvingServiceCtx {
+    pub data_directory: PathBuf,
+    /// Provers of the programs that can be proven, by program id
+    pub provers: HashMap<ProgramId, Arc<dyn ClientSdkProver<Vec<Calldata>> + Send + Sync>>,
+    pub api: SharedBuildApiCtx,
+    pub policy: ProvingJobsPolicy,
+    /// Number of jobs proven at the same time
+    pub max_concurrent_jobs: usize,
+}
+
+/// How the proving jobs are retried and kept
+#[derive(Debug, Clone, Copy)]
+pub struct ProvingJobsPolicy {
+    /// Number of attempts before a job is marked as failed
+    pub max_attempts: u32,
+    /// Delay before the second attempt of a job, doubled for each following attempt
+    pub retry_backoff: Duration,
+    /// Number of succeeded or failed jobs kept, the oldest ones are forgotten
+    pub max_completed_jobs: usize,
+}
+
+#[derive(Default)]
+pub struct ProvingServiceStore {
+    next_id: u64,
+    jobs: BTreeMap<u64, APIProvingJob>,
+    // Inputs of the jobs that are not done yet
+    requests: BTreeMap<u64, APIProvingJobRequest>,
+    // Time before which failed jobs are not retried
+    retry_at: BTreeMap<u64, TimestampMs>,
+    // Succeeded or failed jobs, oldest first
+    completed: VecDeque<u64>,
+    // Jobs changed or forgotten since they were last saved
+    changed: BTreeSet<u64>,
+}
+
+impl ProvingServiceStore {
+    fn push(&mut self, request: APIProvingJobRequest) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.jobs.insert(
+            id,
+            APIProvingJob {
+                id,
+                program_id: request.program_id.clone(),
+                priority: request.priority,
+                status: APIProvingJobStatus::Queued,
+                attempts: 0,
+                error: None,
+                proof: None,
+            },
+        );
+        self.requests.insert(id, request);
+        self.changed.insert(id);
+        id
+    }
+
+    /// Marks the next job to prove as proving, highest priority first then oldest first.
+    /// Jobs waiting for their retry backoff are skipped.
+    fn pop(&mut self, now: &TimestampMs) -> Option<(u64, APIProvingJobRequest)> {
+        let retry_at = &self.retry_at;
+        let job = self
+            .jobs
+            .values_mut()
+            .filter(|job| job.status == APIProvingJobStatus::Queued)
+            .filter(|job| retry_at.get(&job.id).is_none_or(|at| at <= now))
+            .min_by_key(|job| (std::cmp::Reverse(job.priority), job.id))?;
+        job.status = APIProvingJobStatus::Proving;
+        job.attempts += 1;
+        let id = job.id;
+        self.retry_at.remove(&id);
+        self.changed.insert(id);
+        let request = self.requests.get(&id)?.clone();
+        Some((id, request))
+    }
+
+    /// Earliest time a failed job can be retried at
+    fn next_retry(&self) -> Option<&TimestampMs> {
+        self.retry_at.values().min()
+    }
+
+    fn complete(
+        &mut self,
+        id: u64,
+        result: Result<Proof>,
+        policy: &ProvingJobsPolicy,
+        now: TimestampMs,
+    ) {
+        let Some(job) = self.jobs.get_mut(&id) else {
+            return;
+        };
+        self.changed.insert(id);
+        match result {
+            Ok(proof) => {
+                job.status = APIProvingJobStatus::Succeeded;
+                job.error = None;
+                job.proof = Some(proof);
+            }
+            Err(e) => {
+                warn!("Proving job {id} failed on attempt {}: {e:#}", job.attempts);
+                job.error = Some(format!("{e:#}"));
+                job.status = match job.attempts < policy.max_attempts {
+                    true => APIProvingJobStatus::Queued,
+                    false => APIProvingJobStatus::Failed,
+                };
+            }
+        }
+        if job.status == APIProvingJobStatus::Queued {
+            let backoff = policy
+                .retry_backoff
+                .saturating_mul(2u32.saturating_pow(job.attempts.saturating_sub(1)));
+            self.retry_at.insert(id, now + backoff);
+            return;
+        }
+
+        self.requests.remove(&id);
+        self.completed.push_back(id);
+        while self.completed.len() > policy.max_completed_jobs {
+            if let Some(oldest) = self.completed.pop_front() {
+                self.jobs.remove(&oldest);
+                self.changed.insert(oldest);
+            }
+        }
+    }
+}
+
+const NEXT_ID_KEY: &[u8] = b"next_id";
+
+/// A job as saved on disk, with its input until it is done
+#[derive(BorshSerialize, BorshDeserialize)]
+struct SavedJob {
+    job: APIProvingJob,
+    request: Option<APIProvingJobRequest>,
+    retry_at: Option<TimestampMs>,
+}
+
+/// Saves each job under its own key, so that a change only writes the jobs it touched
+#[derive(Clone)]
+pub struct ProvingServiceDb {
+    db: Keyspace,
+    meta: PartitionHandle,
+    jobs: PartitionHandle,
+}
+
+impl ProvingServiceDb {
+    fn open(data_directory: &std::path::Path) -> Result<Self> {
+        let db = Config::new(data_directory.join("proving_service.fjall"))
+            .open()
+            .context("opening proving service keyspace")?;
+        let partition = |name| db.open_partition(name, PartitionCreateOptions::default());
+        Ok(ProvingServiceDb {
+            meta: partition("meta")?,
+            jobs: partition("jobs")?,
+            db,
+        })
+    }
+
+    /// Loads the saved jobs, completed jobs are forgotten oldest submitted first
+    fn load(&self) -> Result<ProvingServiceStore> {
+        let mut store = ProvingServiceStore::default();
+        if let Some(next_id) = self.meta.get(NEXT_ID_KEY)? {
+            store.next_id = borsh::from_slice(&next_id)?;
+        }
+        for item in self.jobs.iter() {
+            let (_, saved) = item?;
+            let SavedJob {
+                job,
+                request,
+                retry_at,
+            } = borsh::from_slice(&saved).context("decoding proving job")?;
+            let id = job.id;
+            if let Some(request) = request {
+                store.requests.insert(id, request);
+            }
+            if let Some(retry_at) = retry_at {
+                store.retry_at.insert(id, retry_at);
+            }
+            if matches!(
+                job.status,
+                APIProvingJobStatus::Succeeded | APIProvingJobStatus::Failed
+            ) {
+                store.completed.push_back(id);
+            }
+            store.jobs.insert(id, job);
+        }
+        Ok(store)
+    }
+
+    /// Writes the jobs changed since the last save in a single batch
+    fn save(&self, store: &mut ProvingServiceStore) -> Result<()> {
+        let mut batch = self.db.batch();
+        for id in store.changed.iter() {
+            // Keys are big endian, so that the jobs are loaded in submission order
+            let key = id.to_be_bytes().to_vec();
+            match store.jobs.get(id) {
+                Some(job) => batch.insert(
+                    &self.jobs,
+                    key,
+                    borsh::to_vec(&SavedJob {
+                        job: job.clone(),
+                        request: store.requests.get(id).cloned(),
+                        retry_at: store.retry_at.get(id).cloned(),
+                    })?,
+                ),
+                None => batch.remove(&self.jobs, key),
+            }
+        }
+        batch.insert(&self.meta, NEXT_ID_KEY, borsh::to_vec(&store.next_id)?);
+        batch.commit()?;
+        store.changed.clear();
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+struct RouterCtx {
+    db: ProvingServiceDb,
+    store: Arc<Mutex<ProvingServiceStore>>,
+    new_job: Arc<Notify>,
+    programs: Arc<Vec<ProgramId>>,
+}
+
+impl Module for ProvingService {
+    type Context = ProvingServiceCtx;
+
+    async fn build(bus: SharedMessageBus, ctx: Self::Context) -> Result<Self> {
+        let bus = ProvingServiceBusClient::new_from_bus(bus.new_handle()).await;
+
+        let db = ProvingServiceDb::open(&ctx.data_directory)?;
+        let mut store = db.load()?;
+        // Jobs interrupted when the server stopped are proven again
+        for job in store.jobs.values_mut() {
+            if job.status == APIProvingJobStatus::Proving {
+                job.status = APIProvingJobStatus::Queued;
+                job.attempts -= 1;
+            }
+        }
+        let store = Arc::new(Mutex::new(store));
+        let new_job = Arc::new(Notify::new());
+
+        if let Ok(mut guard) = ctx.api.router.lock() {
+            if let Some(router) = guard.take() {
+                guard.replace(
+                    router.nest(
+                        "/v1/proving",
+                        Router::new()
+                            .route("/jobs", get(list_jobs).post(submit_job))
+                            .route("/jobs/{id}", get(get_job))
+                            .with_state(RouterCtx {
+                                db: db.clone(),
+                                store: store.clone(),
+                                new_job: new_job.clone(),
+                                programs: Arc::new(ctx.provers.keys().cloned().collect()),
+                            }),
+                    ),
+                );
+            }
+        }
+
+        Ok(ProvingService {
+            bus,
+            ctx,
+            db,
+            store,
+            new_job,
+        })
+    }
+
+    async fn run(&mut self) -> Result<()> {
+        let mut proving = JoinSet::new();
+        let new_job = self.new_job.clone();
+        self.start_jobs(&mut proving);
+
+        module_handle_messages! {
+            on_self self,
+            _ = new_job.notified() => {
+                self.start_jobs(&mut proving);
+            }
+            // Only wakes up for retries when a job can be started
+            _ = wait_until(self.next_retry(proving.len())) => {
+                self.start_jobs(&mut proving);
+            }
+            Some(res) = proving.join_next() => {
+                if let Ok((id, result)) = log_error!(res, "Joining proving job") {
+                    let mut store = self.store.lock().unwrap();
+                    store.complete(id, result, &self.ctx.policy, TimestampMsClock::now());
+                    _ = save_store(&self.db, &mut store);
+                }
+                self.start_jobs(&mut proving);
+            }
+        };
+
+        Ok(())
+    }
+
+    async fn persist(&mut self) -> Result<()> {
+        save_store(&self.db, &mut self.store.lock().unwrap())?;
+        self.db
+            .db
+            .persist(fjall::PersistMode::SyncAll)
+            .map_err(Into::into)
+    }
+}
+
+impl ProvingService {
+    fn next_retry(&self, proving: usize) -> Option<TimestampMs> {
+        if proving >= self.ctx.max_concurrent_jobs {
+            return None;
+        }
+        self.store.lock().unwrap().next_retry().cloned()
+    }
+
+    fn start_jobs(&mut self, proving: &mut JoinSet<(u64, Result<Proof>)>) {
+        let mut store = self.store.lock().unwrap();
+        let mut changed = false;
+        while proving.len() < self.ctx.max_concurrent_jobs {
+            let now = TimestampMsClock::now();
+            let Some((id, request)) = store.pop(&now) else {
+                break;
+            };
+            changed = true;
+            let Some(prover) = self.ctx.provers.get(&request.program_id).cloned() else {
+                let policy = ProvingJobsPolicy {
+                    max_attempts: 0,
+                    ..self.ctx.policy
+                };
+                store.complete(
+                    id,
+                    Err(anyhow!("Unknown program id {:?}", request.program_id)),
+                    &policy,
+                    now,
+                );
+                continue;
+            };
+            info!(
+                "Proving job {id} with {} calldatas (priority {})",
+                request.calldatas.len(),
+                request.priority
+            );
+            proving.spawn(async move {
+                let result = prover
+                    .prove(request.commitment_metadata, request.calldatas)
+                    .await;
+                (id, result)
+            });
+        }
+        if changed {
+            _ = save_store(&self.db, &mut store);
+        }
+    }
+}
+
+/// Saves the changed jobs on disk, on each change so that no accepted job is lost if the server crashes
+fn save_store(db: &ProvingServiceDb, store: &mut ProvingServiceStore) -> Result<()> {
+    log_error!(db.save(store), "Saving proving service")
+}
+
+async fn wait_until(time: Option<TimestampMs>) {
+    match time {
+        Some(time) => {
+            let now = TimestampMsClock::now();
+            if time > now {
+                tokio::time::sleep(time - now).await;
+            }
+        }
+        None => std::future::pending().await,
+    }
+}
+
+async fn submit_job(
+    State(state): State<RouterCtx>,
+    Json(request): Json<APIProvingJobRequest>,
+) -> Result<Json<u64>, AppError> {
+    if !state.programs.contains(&request.program_id) {
+        return Err(AppError(
+            StatusCode::NOT_FOUND,
+            anyhow!("Unknown program id {:?}", request.program_id),
+        ));
+    }
+    let mut store = state.store.lock().unwrap();
+    let id = store.push(request);
+    // The job is only accepted once it is saved
+    if let Err(e) = save_store(&state.db, &mut store) {
+        store.jobs.remove(&id);
+        store.requests.remove(&id);
+        return Err(AppError(StatusCode::INTERNAL_SERVER_ERROR, e));
+    }
+    drop(store);
+    state.new_job.notify_one();
+    Ok(Json(id))
+}
+
+async fn get_job(
+    Path(id): Path<u64>,
+    State(state): State<RouterCtx>,
+) -> Result<Json<APIProvingJob>, AppError> {
+    match state.store.lock().unwrap().jobs.get(&id) {
+        Some(job) => Ok(Json(job.clone())),
+        None => Err(AppError(
+            StatusCode::NOT_FOUND,
+            anyhow!("Proving job {id} not found"),
+        )),
+    }
+}
+
+async fn list_jobs(State(state): State<RouterCtx>) -> Json<Vec<APIProvingJob>> {
+    Json(
+        state
+            .store
+            .lock()
+            .unwrap()
+            .jobs
+            .values()
+            .map(|job| APIProvingJob {
+                // Proofs can be large, they are only returned by `get_job`
+                proof: None,
+                ..job.clone()
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::Pin;
+
+    use client_sdk::helpers::remote::RemoteProver;
+    use client_sdk::helpers::test::MockProver;
+    use client_sdk::helpers::ProverInfo;
+    use client_sdk::rest_client::ProverApiHttpClient;
+    use sdk::{ProofData, Verifier};
+
+    use super::*;
+
+    const POLICY: ProvingJobsPolicy = ProvingJobsPolicy {
+        max_attempts: 2,
+        retry_backoff: Duration::from_secs(10),
+        max_completed_jobs: 2,
+    };
+
+    fn request(priority: u32) -> APIProvingJobRequest {
+        APIProvingJobRequest {
+            program_id: ProgramId(b"MockProver".to_vec()),
+            commitment_metadata: vec![],
+            calldatas: vec![Calldata::default()],
+            priority,
+        }
+    }
+
+    fn proof() -> Proof {
+        Proof {
+            data: ProofData(vec![1, 2, 3]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_queue_order_by_priority_then_age() {
+        let mut store = ProvingServiceStore::default();
+        let now = TimestampMs(0);
+        let low = store.push(request(0));
+        let high = store.push(request(5));
+        let low_2 = store.push(request(0));
+
+        assert_eq!(store.pop(&now).map(|(id, _)| id), Some(high));
+        assert_eq!(store.pop(&now).map(|(id, _)| id), Some(low));
+        assert_eq!(store.pop(&now).map(|(id, _)| id), Some(low_2));
+        assert!(store.pop(&now).is_none());
+    }
+
+    #[test]
+    fn test_retries_with_backoff_then_fails() {
+        let mut store = ProvingServiceStore::default();
+        let id = store.push(request(0));
+
+        store.pop(&TimestampMs(0));
+        store.complete(id, Err(anyhow!("prover crashed")), &POLICY, TimestampMs(0));
+        assert_eq!(store.jobs[&id].status, APIProvingJobStatus::Queued);
+        assert_eq!(store.jobs[&id].error.as_deref(), Some("prover crashed"));
+
+        // The job is not retried before its backoff
+        assert_eq!(store.next_retry(), Some(&TimestampMs(10_000)));
+        assert!(store.pop(&TimestampMs(9_999)).is_none());
+        assert_eq!(store.pop(&TimestampMs(10_000)).map(|(id, _)| id), Some(id));
+        assert!(store.next_retry().is_none());
+
+        store.complete(
+            id,
+            Err(anyhow!("prover crashed again")),
+            &POLICY,
+            TimestampMs(10_000),
+        );
+        assert_eq!(store.jobs[&id].status, APIProvingJobStatus::Failed);
+        assert_eq!(store.jobs[&id].attempts, 2);
+        assert!(store.requests.is_empty());
+        assert!(store.retry_at.is_empty());
+        assert!(store.pop(&TimestampMs(u128::MAX)).is_none());
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let policy = ProvingJobsPolicy {
+            max_attempts: 4,
+            ..POLICY
+        };
+        let mut store = ProvingServiceStore::default();
+        let id = store.push(request(0));
+
+        let mut now = TimestampMs(0);
+        for backoff in [10_000, 20_000, 40_000] {
+            store.pop(&now);
+            store.complete(id, Err(anyhow!("prover crashed")), &policy, now.clone());
+            assert_eq!(store.next_retry(), Some(&TimestampMs(now.0 + backoff)));
+            now = TimestampMs(now.0 + backoff);
+        }
+    }
+
+    #[test]
+    fn test_success_keeps_proof() {
+        let mut store = ProvingServiceStore::default();
+        let id = store.push(request(0));
+
+        store.pop(&TimestampMs(0));
+        store.complete(id, Ok(proof()), &POLICY, TimestampMs(0));
+        assert_eq!(store.jobs[&id].status, APIProvingJobStatus::Succeeded);
+        assert_eq!(store.jobs[&id].proof, Some(proof()));
+        assert!(store.requests.is_empty());
+    }
+
+    #[test]
+    fn test_completed_jobs_retention() {
+        let mut store = ProvingServiceStore::default();
+        let ids: Vec<u64> = (0..4).map(|_| store.push(request(0))).collect();
+        let queued = store.push(request(0));
+
+        for id in ids.iter() {
+            store.pop(&TimestampMs(0));
+            store.complete(*id, Ok(proof()), &POLICY, TimestampMs(0));
+        }
+
+        // Only the latest completed jobs are kept, queued ones are never forgotten
+        assert_eq!(
+            store.jobs.keys().copied().collect::<Vec<_>>(),
+            vec![2, 3, queued]
+        );
+    }
+
+    /// Prover that never finishes
+    struct StuckProver;
+
+    impl ClientSdkProver<Vec<Calldata>> for StuckProver {
+        fn prove(
+            &self,
+            _commitment_metadata: Vec<u8>,
+            _calldatas: Vec<Calldata>,
+        ) -> Pin<Box<dyn Future<Output = Result<Proof>> + Send + '_>> {
+            Box::pin(std::future::pending())
+        }
+
+        fn info(&self) -> ProverInfo {
+            ProverInfo {
+                name: "StuckProver".to_string(),
+                zkvm: "test".to_string(),
+                version: "1.0.0".to_string(),
+            }
+        }
+
+        fn program_id(&self) -> ProgramId {
+            ProgramId(b"StuckProver".to_vec())
+        }
+
+        fn verifier(&self) -> Verifier {
+            "test".into()
+        }
+    }
+
+    async fn build_service(dir: &std::path::Path) -> Result<(ProvingService, Router)> {
+        let bus = SharedMessageBus::new(crate::bus::metrics::BusMetrics::global(
+            "proving_service".to_string(),
+        ));
+        let api = SharedBuildApiCtx::default();
+        api.router.lock().unwrap().replace(Router::new());
+
+        let mut provers: HashMap<ProgramId, Arc<dyn ClientSdkProver<Vec<Calldata>> + Send + Sync>> =
+            HashMap::new();
+        provers.insert(ProgramId(b"MockProver".to_vec()), Arc::new(MockProver {}));
+        provers.insert(ProgramId(b"StuckProver".to_vec()), Arc::new(StuckProver));
+
+        let service = ProvingService::build(
+            bus,
+            ProvingServiceCtx {
+                data_directory: dir.to_path_buf(),
+                provers,
+                api: api.clone(),
+                policy: POLICY,
+                max_concurrent_jobs: 2,
+            },
+        )
+        .await?;
+        let router = api.router.lock().unwrap().take().unwrap();
+        Ok((service, router))
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_proves_with_registered_prover() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (mut service, _) = build_service(dir.path()).await?;
+
+        let id = service.store.lock().unwrap().push(request(0));
+        let mut proving = JoinSet::new();
+        service.start_jobs(&mut proving);
+        let (done, result) = proving.join_next().await.unwrap()?;
+        assert_eq!(done, id);
+        service
+            .store
+            .lock()
+            .unwrap()
+            .complete(done, result, &POLICY, TimestampMs(0));
+        assert_eq!(
+            service.store.lock().unwrap().jobs[&id].status,
+            APIProvingJobStatus::Succeeded
+        );
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_jobs_saved_across_restarts() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (service, router) = build_service(dir.path()).await?;
+        let (done, interrupted) = {
+            let mut store = service.store.lock().unwrap();
+            let done = store.push(request(0));
+            let interrupted = store.push(request(0));
+            store.pop(&TimestampMs(0));
+            store.complete(done, Ok(proof()), &POLICY, TimestampMs(0));
+            store.pop(&TimestampMs(0));
+            service.db.save(&mut store)?;
+            (done, interrupted)
+        };
+        drop((service, router));
+
+        let (service, _) = build_service(dir.path()).await?;
+        let store = service.store.lock().unwrap();
+        assert_eq!(store.next_id, 2);
+        assert_eq!(store.jobs[&done].proof, Some(proof()));
+        assert_eq!(store.completed, VecDeque::from([done]));
+        // The interrupted job is queued again, its input kept until it is done
+        assert_eq!(store.jobs[&interrupted].status, APIProvingJobStatus::Queued);
+        assert_eq!(store.jobs[&interrupted].attempts, 0);
+        assert_eq!(
+            store.requests.keys().collect::<Vec<_>>(),
+            vec![&interrupted]
+        );
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_remote_prover() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (mut service, router) = build_service(dir.path()).await?;
+        let db = service.db.clone();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        tokio::spawn(async move { service.run().await });
+
+        let prover = RemoteProver::new(
+            ProverApiHttpClient::new(url.clone())?,
+            ProgramId(b"MockProver".to_vec()),
+            "mock".into(),
+        )
+        .with_poll_interval(Duration::from_millis(10));
+        let calldata = Calldata::default();
+        let proof = prover.prove(vec![], vec![calldata.clone()]).await?;
+        let expected = MockProver {}.prove(vec![], vec![calldata]).await?;
+        assert_eq!(proof.data, expected.data);
+
+        // Accepted jobs are saved right away
+        assert_eq!(db.load()?.jobs.len(), 1);
+
+        // The client gives up on jobs that take too long
+        let stuck = RemoteProver::new(
+            ProverApiHttpClient::new(url.clone())?,
+            ProgramId(b"StuckProver".to_vec()),
+            "test".into(),
+        )
+        .with_poll_interval(Duration::from_millis(10))
+        .with_timeout(Duration::from_millis(100));
+        assert!(stuck
+            .prove(vec![], vec![Calldata::default()])
+            .await
+            .is_err());
+
+        // Unknown programs are rejected
+        let unknown = RemoteProver::new(
+            ProverApiHttpClient::new(url)?,
+            ProgramId(b"Unknown".to_vec()),
+            "test".into(),
+        );
+        assert!(unknown.prove(vec![], vec![]).await.is_err());
+        Ok(())
+    }
+}
@@ -203,6 +203,7 @@ impl NodeState {
                     _ => None,
                 })
                 .collect(),
+            timed_out_leaders: signed_block.consensus_proposal.timed_out_leaders.clone(),
             timed_out_txs: vec![], // Added below as it needs the block
             dropped_duplicate_txs: vec![],
             registered_contracts: BTreeMap::new(),
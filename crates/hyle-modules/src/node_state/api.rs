@@ -6,7 +6,10 @@ use axum::{
     Json, Router,
 };
 use client_sdk::contract_indexer::AppError;
-use sdk::{api::APINodeContract, *};
+use sdk::{
+    api::{APINodeContract, APISettledTx},
+    *,
+};
 use tracing::error;
 use utoipa::OpenApi;
 use utoipa_axum::{router::OpenApiRouter, routes};
@@ -19,7 +22,10 @@ use crate::{
         SharedMessageBus,
     },
     modules::signal::ShutdownModule,
-    node_state::module::{QueryBlockHeight, QueryUnsettledTx, QueryUnsettledTxCount},
+    node_state::module::{
+        QueryBlockHeight, QueryContractSchema, QuerySettledTx, QueryUnsettledTx,
+        QueryUnsettledTxCount,
+    },
 };
 
 use super::module::{NodeStateCtx, QuerySettledHeight};
@@ -31,6 +37,8 @@ struct RestBusClient {
     sender(Query<QueryUnsettledTxCount, u64>),
     sender(Query<QueryBlockHeight, BlockHeight>),
     sender(Query<QueryUnsettledTx, UnsettledBlobTransaction>),
+    sender(Query<QueryContractSchema, ContractSchema>),
+    sender(Query<QuerySettledTx, APISettledTx>),
     receiver(ShutdownModule),
 }
 }
@@ -51,10 +59,12 @@ pub async fn api(bus: SharedMessageBus, ctx: &NodeStateCtx) -> Router<()> {
         .routes(routes!(get_block_height))
         .routes(routes!(get_contract))
         .routes(routes!(get_contract_settled_height))
+        .routes(routes!(get_contract_schema))
         .routes(routes!(get_contract_unsettled_txs_count))
         .routes(routes!(get_unsettled_txs_count))
         // TODO: figure out if we want to rely on the indexer instead
         .routes(routes!(get_unsettled_tx))
+        .routes(routes!(get_settled_tx))
         .split_for_parts();
 
     if let Ok(mut o) = ctx.api.openapi.lock() {
@@ -153,6 +163,48 @@ pub async fn get_contract_settled_height(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/contract/{name}/schema",
+    params(
+        ("name" = String, Path, description = "Contract name")
+    ),
+    description = "The schema given in the constructor metadata when registering the contract",
+    tag = "Node State",
+    responses(
+        (status = OK, body = ContractSchema)
+    )
+)]
+pub async fn get_contract_schema(
+    Path(name): Path<ContractName>,
+    State(mut state): State<RouterState>,
+) -> Result<impl IntoResponse, AppError> {
+    let name_clone = name.clone();
+    match state
+        .bus
+        .shutdown_aware_request::<()>(QueryContractSchema(name))
+        .await
+    {
+        Ok(schema) => Ok(Json(schema)),
+        err => {
+            if let Err(e) = err.as_ref() {
+                if e.to_string().contains("Contract schema not found") {
+                    return Err(AppError(
+                        StatusCode::NOT_FOUND,
+                        anyhow!("No schema registered for contract {}", name_clone),
+                    ));
+                }
+            }
+            error!("{:?}", err);
+
+            Err(AppError(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow!("Error while getting schema of contract {}", name_clone),
+            ))
+        }
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/contract/{name}/unsettled_txs_count",
@@ -243,6 +295,47 @@ pub async fn get_unsettled_tx(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/settled_tx/{blob_tx_hash}",
+    params(
+        ("blob_tx_hash" = String, Path, description = "Blob tx hash"),
+    ),
+    description = "How a blob transaction was settled, among the latest transactions settled since the node started",
+    tag = "Node State",
+    responses(
+        (status = OK, body = APISettledTx)
+    )
+)]
+pub async fn get_settled_tx(
+    Path(blob_tx_hash): Path<String>,
+    State(mut state): State<RouterState>,
+) -> Result<impl IntoResponse, AppError> {
+    match state
+        .bus
+        .shutdown_aware_request::<()>(QuerySettledTx(TxHash(blob_tx_hash.clone())))
+        .await
+    {
+        Ok(settled) => Ok(Json(settled)),
+        err => {
+            if let Err(e) = err.as_ref() {
+                if e.to_string().contains("Settled transaction not found") {
+                    return Err(AppError(
+                        StatusCode::NOT_FOUND,
+                        anyhow!("Transaction {} not settled recently", blob_tx_hash),
+                    ));
+                }
+            }
+            error!("{:?}", err);
+
+            Err(AppError(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow!("Error while getting settled tx"),
+            ))
+        }
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/da/block/height",
@@ -301,6 +394,16 @@ impl Clone for RouterState {
                     >,
                 >::get(&self.bus)
                 .clone(),
+                Pick::<
+                    tokio::sync::broadcast::Sender<
+                        Query<QueryContractSchema, ContractSchema>,
+                    >,
+                >::get(&self.bus)
+                .clone(),
+                Pick::<tokio::sync::broadcast::Sender<Query<QuerySettledTx, APISettledTx>>>::get(
+                    &self.bus,
+                )
+                .clone(),
                 Pick::<tokio::sync::broadcast::Receiver<ShutdownModule>>::get(&self.bus).resubscribe(),
             ),
         }
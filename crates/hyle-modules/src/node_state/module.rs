@@ -4,11 +4,13 @@ use super::metrics::NodeStateMetrics;
 use super::{NodeState, NodeStateStore};
 use crate::bus::SharedMessageBus;
 use crate::bus::{command_response::Query, BusClientSender};
-use crate::log_error;
 use crate::module_handle_messages;
 use crate::modules::{module_bus_client, Module, SharedBuildApiCtx};
+use crate::{log_error, log_warn};
 use anyhow::Result;
+use sdk::api::{APISettledTx, TransactionStatusDb};
 use sdk::*;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::path::PathBuf;
 use tracing::info;
 
@@ -19,9 +21,50 @@ use tracing::info;
 pub struct NodeStateModule {
     bus: NodeStateBusClient,
     inner: NodeState,
+    /// Schemas given in the constructor metadata of registered contracts.
+    /// Kept out of the node state as they play no role in consensus.
+    contract_schemas: BTreeMap<ContractName, ContractSchema>,
+    settled_txs: SettledTxs,
     data_directory: PathBuf,
 }
 
+/// Number of settled transactions whose outcome can be queried
+const SETTLED_TXS_HISTORY: usize = 10_000;
+
+/// Outcomes of the latest settled transactions, for clients following their transactions without
+/// an indexer. Only kept in memory, transactions settled before a restart are unknown.
+#[derive(Default)]
+struct SettledTxs {
+    by_hash: HashMap<TxHash, APISettledTx>,
+    order: VecDeque<TxHash>,
+}
+
+impl SettledTxs {
+    fn record(&mut self, block: &Block) {
+        let outcomes = [
+            (&block.successful_txs, TransactionStatusDb::Success),
+            (&block.failed_txs, TransactionStatusDb::Failure),
+            (&block.timed_out_txs, TransactionStatusDb::TimedOut),
+        ];
+        for (tx_hashes, status) in outcomes {
+            for tx_hash in tx_hashes {
+                let settled = APISettledTx {
+                    block_height: block.block_height,
+                    status: status.clone(),
+                };
+                if self.by_hash.insert(tx_hash.clone(), settled).is_none() {
+                    self.order.push_back(tx_hash.clone());
+                }
+            }
+        }
+        while self.order.len() > SETTLED_TXS_HISTORY {
+            if let Some(tx_hash) = self.order.pop_front() {
+                self.by_hash.remove(&tx_hash);
+            }
+        }
+    }
+}
+
 pub use sdk::NodeStateEvent;
 
 #[derive(Clone)]
@@ -36,6 +79,12 @@ pub struct QueryUnsettledTxCount(pub Option<ContractName>);
 #[derive(Clone)]
 pub struct QueryUnsettledTx(pub TxHash);
 
+#[derive(Clone)]
+pub struct QueryContractSchema(pub ContractName);
+
+#[derive(Clone)]
+pub struct QuerySettledTx(pub TxHash);
+
 module_bus_client! {
 #[derive(Debug)]
 pub struct NodeStateBusClient {
@@ -46,6 +95,8 @@ pub struct NodeStateBusClient {
     receiver(Query<QueryUnsettledTxCount, u64>),
     receiver(Query<QueryBlockHeight , BlockHeight>),
     receiver(Query<QueryUnsettledTx, UnsettledBlobTransaction>),
+    receiver(Query<QueryContractSchema, ContractSchema>),
+    receiver(Query<QuerySettledTx, APISettledTx>),
 }
 }
 
@@ -75,12 +126,18 @@ impl Module for NodeStateModule {
             info!("📝 Loaded contract state for {}", name);
         }
 
+        let contract_schemas = Self::load_from_disk_or_default::<
+            BTreeMap<ContractName, ContractSchema>,
+        >(ctx.data_directory.join("contract_schemas.bin").as_path());
+
         let node_state = NodeState { store, metrics };
         let bus = NodeStateBusClient::new_from_bus(bus.new_handle()).await;
 
         Ok(Self {
             bus,
             inner: node_state,
+            contract_schemas,
+            settled_txs: SettledTxs::default(),
             data_directory: ctx.data_directory,
         })
     }
@@ -120,11 +177,25 @@ impl Module for NodeStateModule {
                     None => Err(anyhow::anyhow!("Transaction not found")),
                 }
             }
+            command_response<QuerySettledTx, APISettledTx> tx_hash => {
+                match self.settled_txs.by_hash.get(&tx_hash.0) {
+                    Some(settled) => Ok(settled.clone()),
+                    None => Err(anyhow::anyhow!("Settled transaction not found")),
+                }
+            }
+            command_response<QueryContractSchema, ContractSchema> cmd => {
+                match self.contract_schemas.get(&cmd.0) {
+                    Some(schema) => Ok(schema.clone()),
+                    None => Err(anyhow::anyhow!("Contract schema not found for {}", cmd.0)),
+                }
+            }
             listen<DataEvent> block => {
                 match block {
                     DataEvent::OrderedSignedBlock(block) => {
                         // TODO: If we are in a broken state, this will likely kill the node every time.
                         let node_state_block = self.inner.handle_signed_block(&block)?;
+                        self.update_contract_schemas(&node_state_block);
+                        self.settled_txs.record(&node_state_block);
                         _ = log_error!(self
                             .bus
                             .send(NodeStateEvent::NewBlock(Box::new(node_state_block))), "Sending DataEvent while processing SignedBlock");
@@ -137,6 +208,13 @@ impl Module for NodeStateModule {
     }
 
     async fn persist(&mut self) -> Result<()> {
+        _ = log_error!(
+            Self::save_on_disk::<BTreeMap<ContractName, ContractSchema>>(
+                self.data_directory.join("contract_schemas.bin").as_path(),
+                &self.contract_schemas,
+            ),
+            "Saving contract schemas"
+        );
         log_error!(
             Self::save_on_disk::<NodeStateStore>(
                 self.data_directory.join("node_state.bin").as_path(),
@@ -146,3 +224,70 @@ impl Module for NodeStateModule {
         )
     }
 }
+
+impl NodeStateModule {
+    fn update_contract_schemas(&mut self, block: &Block) {
+        for contract_name in block.deleted_contracts.keys() {
+            self.contract_schemas.remove(contract_name);
+        }
+        for (contract_name, (_, _, metadata)) in block.registered_contracts.iter() {
+            // A registration without schema replaces the previous program, so its schema too
+            match log_warn!(
+                ConstructorMetadata::schema_of(metadata),
+                "Reading schema of contract {}",
+                contract_name
+            ) {
+                Ok(Some(schema)) => {
+                    self.contract_schemas.insert(contract_name.clone(), schema);
+                }
+                Ok(None) | Err(_) => {
+                    self.contract_schemas.remove(contract_name);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settled_txs() {
+        let mut settled_txs = SettledTxs::default();
+        settled_txs.record(&Block {
+            block_height: BlockHeight(3),
+            successful_txs: vec![TxHash::new("ok")],
+            failed_txs: vec![TxHash::new("ko")],
+            timed_out_txs: vec![TxHash::new("late")],
+            ..Block::default()
+        });
+        assert_eq!(
+            settled_txs.by_hash.get(&TxHash::new("ko")),
+            Some(&APISettledTx {
+                block_height: BlockHeight(3),
+                status: TransactionStatusDb::Failure,
+            })
+        );
+        assert_eq!(
+            settled_txs
+                .by_hash
+                .get(&TxHash::new("late"))
+                .map(|settled| &settled.status),
+            Some(&TransactionStatusDb::TimedOut)
+        );
+
+        // The oldest outcomes are dropped past the history length
+        settled_txs.record(&Block {
+            block_height: BlockHeight(4),
+            successful_txs: (0..SETTLED_TXS_HISTORY)
+                .map(|i| TxHash(format!("{i}")))
+                .collect(),
+            ..Block::default()
+        });
+        assert_eq!(settled_txs.by_hash.len(), SETTLED_TXS_HISTORY);
+        assert_eq!(settled_txs.order.len(), SETTLED_TXS_HISTORY);
+        assert!(!settled_txs.by_hash.contains_key(&TxHash::new("ok")));
+        assert!(settled_txs.by_hash.contains_key(&TxHash::new("0")));
+    }
+}
@@ -0,0 +1,430 @@
+//! In-process Hyli chain, to test contracts and multi-contract flows without running a node.
+//!
+//! Transactions submitted to a [TestChain] are sequenced in the next block it produces, and proofs
+//! are verified when submitted, as the mempool does. Blocks are handled by a [NodeState], so
+//! transactions settle, fail and time out as they would on a node. Heights and timestamps only
+//! move when blocks are produced, so tests are deterministic.
+//!
+//! ```ignore
+//! let mut chain = TestChain::new();
+//! chain.register_test_contract("hyllar".into(), hyllar.get_state_commitment());
+//! chain.produce_block()?;
+//!
+//! // The executor proves "hyllar" blobs with TxExecutorTestProver
+//! let tx_hash = chain.send(&mut executor, tx).await?;
+//! chain.produce_block()?;
+//! chain.assert_settled(&tx_hash);
+//! chain.assert_handler_state(&"hyllar".into(), &executor.hyllar);
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use client_sdk::transaction_builder::{
+    ProvableBlobTx, StateUpdater, TxExecutor, TxExecutorHandler,
+};
+use hyle_modules::node_state::NodeState;
+use sdk::api::TransactionStatusDb;
+use sdk::hyle_model_utils::TimestampMs;
+use sdk::{
+    AggregateSignature, BlobProofOutput, BlobTransaction, Block, BlockHeight, ConsensusProposal,
+    ConsensusProposalHash, Contract, ContractAction, ContractName, DataProposal, DataProposalHash,
+    Hashed, HyleOutput, LaneId, ProgramId, ProofTransaction, RegisterContractAction, SignedBlock,
+    StateCommitment, Transaction, TransactionData, TxHash, VerifiedProofTransaction, Verifier,
+};
+
+/// Time between two blocks, unless set with [TestChain::with_block_time]
+pub const DEFAULT_BLOCK_TIME_MS: u128 = 1000;
+
+pub struct TestChain {
+    node_state: NodeState,
+    /// Transactions to sequence in the next block
+    pending: Vec<Transaction>,
+    last_block: Option<Block>,
+    last_dp_hash: Option<DataProposalHash>,
+    statuses: HashMap<TxHash, TransactionStatusDb>,
+    timestamp: TimestampMs,
+    block_time_ms: u128,
+}
+
+impl Default for TestChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestChain {
+    /// A chain at height 0, the first block produced is the genesis block
+    pub fn new() -> Self {
+        TestChain {
+            node_state: NodeState::create("testkit".to_string(), "testkit"),
+            pending: vec![],
+            last_block: None,
+            last_dp_hash: None,
+            statuses: HashMap::new(),
+            timestamp: TimestampMs::ZERO,
+            block_time_ms: DEFAULT_BLOCK_TIME_MS,
+        }
+    }
+
+    pub fn with_block_time(mut self, block_time_ms: u128) -> Self {
+        self.block_time_ms = block_time_ms;
+        self
+    }
+
+    pub fn node_state(&self) -> &NodeState {
+        &self.node_state
+    }
+
+    pub fn height(&self) -> BlockHeight {
+        self.node_state.current_height
+    }
+
+    /// Timestamp of the last block produced
+    pub fn timestamp(&self) -> &TimestampMs {
+        &self.timestamp
+    }
+
+    pub fn last_block(&self) -> Option<&Block> {
+        self.last_block.as_ref()
+    }
+
+    pub fn contract(&self, contract_name: &ContractName) -> Option<&Contract> {
+        self.node_state.contracts.get(contract_name)
+    }
+
+    /// Status of a blob transaction, `None` until it is sequenced
+    pub fn tx_status(&self, tx_hash: &TxHash) -> Option<&TransactionStatusDb> {
+        self.statuses.get(tx_hash)
+    }
+
+    /// Registers a contract under the hyle TLD in the next block
+    pub fn register_contract(&mut self, action: RegisterContractAction) -> TxHash {
+        self.submit_blob_tx(BlobTransaction::new(
+            "hyle@hyle",
+            vec![action.as_blob("hyle".into(), None, None)],
+        ))
+    }
+
+    /// Registers a contract verified with the `test` verifier, whose proofs are the borsh encoded
+    /// outputs of the program, as generated by [client_sdk::helpers::test::TxExecutorTestProver]
+    pub fn register_test_contract(
+        &mut self,
+        contract_name: ContractName,
+        state_commitment: StateCommitment,
+    ) -> TxHash {
+        self.register_contract(RegisterContractAction {
+            verifier: test_verifier(),
+            program_id: ProgramId(vec![]),
+            state_commitment,
+            contract_name,
+            ..Default::default()
+        })
+    }
+
+    pub fn submit_blob_tx(&mut self, tx: BlobTransaction) -> TxHash {
+        let tx_hash = tx.hashed();
+        self.pending.push(tx.into());
+        tx_hash
+    }
+
+    /// Verifies the proof, then submits it in the next block
+    pub fn submit_proof_tx(&mut self, tx: ProofTransaction) -> Result<()> {
+        let hyle_outputs = verify_proof(&tx)
+            .context(format!("Verifying proof for contract {}", tx.contract_name))?;
+        let proof_hash = tx.proof.hashed();
+        self.pending.push(
+            VerifiedProofTransaction {
+                proven_blobs: hyle_outputs
+                    .into_iter()
+                    .map(|hyle_output| BlobProofOutput {
+                        original_proof_hash: proof_hash.clone(),
+                        blob_tx_hash: hyle_output.tx_hash.clone(),
+                        hyle_output,
+                        program_id: tx.program_id.clone(),
+                        verifier: tx.verifier.clone(),
+                    })
+                    .collect(),
+                proof_hash,
+                proof_size: tx.estimate_size(),
+                contract_name: tx.contract_name.clone(),
+                program_id: tx.program_id.clone(),
+                verifier: tx.verifier.clone(),
+                is_recursive: false,
+                proof: Some(tx.proof),
+            }
+            .into(),
+        );
+        Ok(())
+    }
+
+    /// Executes the transaction with the executor, then submits it and its proofs, generated by
+    /// the provers of the executor, in the next block
+    pub async fn send<S: StateUpdater>(
+        &mut self,
+        executor: &mut TxExecutor<S>,
+        tx: ProvableBlobTx,
+    ) -> Result<TxHash> {
+        let proof_tx_builder = executor.process(tx)?;
+        let tx_hash = self.submit_blob_tx(proof_tx_builder.to_blob_tx());
+        for proof in proof_tx_builder.iter_prove() {
+            self.submit_proof_tx(proof.await?)?;
+        }
+        Ok(tx_hash)
+    }
+
+    /// Sequences the pending transactions in a block, `block_time_ms` after the previous one
+    pub fn produce_block(&mut self) -> Result<&Block> {
+        let (height, parent_hash) = match &self.last_block {
+            Some(block) => (block.block_height + 1, block.hash.clone()),
+            None => (BlockHeight(0), ConsensusProposalHash::default()),
+        };
+        if self.last_block.is_some() {
+            self.timestamp = TimestampMs(self.timestamp.0 + self.block_time_ms);
+        }
+
+        let data_proposal =
+            DataProposal::new(self.last_dp_hash.clone(), std::mem::take(&mut self.pending));
+        self.last_dp_hash = Some(data_proposal.hashed());
+        let signed_block = SignedBlock {
+            certificate: AggregateSignature::default(),
+            consensus_proposal: ConsensusProposal {
+                slot: height.0,
+                parent_hash,
+                timestamp: self.timestamp.clone(),
+                ..ConsensusProposal::default()
+            },
+            data_proposals: vec![(LaneId::default(), vec![data_proposal])],
+        };
+        let block = self.node_state.handle_signed_block(&signed_block)?;
+        self.record_statuses(&block);
+        tracing::debug!(
+            "Produced block {} with {} txs",
+            block.block_height,
+            block.txs.len()
+        );
+        Ok(self.last_block.insert(block))
+    }
+
+    /// Produces `count` blocks, with the pending transactions in the first one
+    pub fn produce_blocks(&mut self, count: u64) -> Result<()> {
+        for _ in 0..count {
+            self.produce_block()?;
+        }
+        Ok(())
+    }
+
+    /// Produces blocks until the chain reaches the height
+    pub fn advance_to_height(&mut self, height: BlockHeight) -> Result<()> {
+        if self.last_block.is_some() && height < self.height() {
+            bail!("Chain is already at height {}", self.height());
+        }
+        while self.last_block.is_none() || self.height() < height {
+            self.produce_block()?;
+        }
+        Ok(())
+    }
+
+    /// Moves the clock forward, the next block will be produced `block_time_ms` after it
+    pub fn advance_time(&mut self, duration_ms: u128) {
+        self.timestamp = TimestampMs(self.timestamp.0 + duration_ms);
+    }
+
+    fn record_statuses(&mut self, block: &Block) {
+        for (_, tx) in block.txs.iter() {
+            if let TransactionData::Blob(blob_tx) = &tx.transaction_data {
+                self.statuses
+                    .insert(blob_tx.hashed(), TransactionStatusDb::Sequenced);
+            }
+        }
+        for (tx_hashes, status) in [
+            (&block.successful_txs, TransactionStatusDb::Success),
+            (&block.failed_txs, TransactionStatusDb::Failure),
+            (&block.timed_out_txs, TransactionStatusDb::TimedOut),
+        ] {
+            for tx_hash in tx_hashes {
+                self.statuses.insert(tx_hash.clone(), status.clone());
+            }
+        }
+    }
+
+    #[track_caller]
+    fn assert_status(&self, tx_hash: &TxHash, expected: TransactionStatusDb) {
+        let status = self.tx_status(tx_hash);
+        assert_eq!(
+            status,
+            Some(&expected),
+            "Transaction {tx_hash} is {status:?} at height {}, expected {expected:?}",
+            self.height()
+        );
+    }
+
+    #[track_caller]
+    pub fn assert_settled(&self, tx_hash: &TxHash) {
+        self.assert_status(tx_hash, TransactionStatusDb::Success);
+    }
+
+    #[track_caller]
+    pub fn assert_failed(&self, tx_hash: &TxHash) {
+        self.assert_status(tx_hash, TransactionStatusDb::Failure);
+    }
+
+    #[track_caller]
+    pub fn assert_timed_out(&self, tx_hash: &TxHash) {
+        self.assert_status(tx_hash, TransactionStatusDb::TimedOut);
+    }
+
+    /// Asserts the transaction is sequenced, and waits for proofs to settle
+    #[track_caller]
+    pub fn assert_unsettled(&self, tx_hash: &TxHash) {
+        self.assert_status(tx_hash, TransactionStatusDb::Sequenced);
+    }
+
+    #[track_caller]
+    pub fn assert_contract_state(&self, contract_name: &ContractName, expected: &StateCommitment) {
+        let Some(contract) = self.contract(contract_name) else {
+            panic!("Contract {contract_name} is not registered");
+        };
+        assert_eq!(
+            &contract.state, expected,
+            "Unexpected state for contract {contract_name}"
+        );
+    }
+
+    /// Asserts the state of the contract settled onchain is the one of the handler, e.g. the state
+    /// of a [TxExecutor] once its transactions settled
+    #[track_caller]
+    pub fn assert_handler_state<H: TxExecutorHandler>(
+        &self,
+        contract_name: &ContractName,
+        handler: &H,
+    ) {
+        self.assert_contract_state(contract_name, &handler.get_state_commitment());
+    }
+}
+
+fn verify_proof(tx: &ProofTransaction) -> Result<Vec<HyleOutput>> {
+    match tx.verifier.0.as_str() {
+        "test" => borsh::from_slice(&tx.proof.0).context("parsing test proof"),
+        _ => hyle_verifiers::verify(&tx.verifier, &tx.proof, &tx.program_id),
+    }
+}
+
+/// Verifier of the contracts registered with [TestChain::register_test_contract]
+pub fn test_verifier() -> Verifier {
+    "test".into()
+}
+
+#[cfg(test)]
+mod tests {
+    use sdk::{Blob, BlobData, BlobIndex, Identity, ProofData, TimeoutWindow};
+
+    use super::*;
+
+    fn blob_tx(contract: &str) -> BlobTransaction {
+        BlobTransaction::new(
+            Identity::new(format!("alice@{contract}")),
+            vec![Blob {
+                contract_name: contract.into(),
+                data: BlobData(vec![1, 2, 3]),
+            }],
+        )
+    }
+
+    fn proof_tx(tx: &BlobTransaction, initial_state: &[u8], success: bool) -> ProofTransaction {
+        let hyle_output = HyleOutput {
+            version: 1,
+            identity: tx.identity.clone(),
+            index: BlobIndex(0),
+            blobs: tx.blobs.clone().into(),
+            tx_blob_count: tx.blobs.len(),
+            initial_state: StateCommitment(initial_state.to_vec()),
+            next_state: StateCommitment(vec![4, 5, 6]),
+            success,
+            tx_hash: tx.hashed(),
+            tx_ctx: None,
+            state_reads: vec![],
+            onchain_effects: vec![],
+            program_outputs: vec![],
+        };
+        ProofTransaction {
+            contract_name: tx.blobs[0].contract_name.clone(),
+            program_id: ProgramId(vec![]),
+            verifier: test_verifier(),
+            proof: ProofData(borsh::to_vec(&vec![hyle_output]).unwrap()),
+        }
+    }
+
+    #[test_log::test]
+    fn test_register_and_settle() -> Result<()> {
+        let mut chain = TestChain::new();
+        let register = chain.register_test_contract("c1".into(), StateCommitment(vec![0, 1]));
+        chain.produce_block()?;
+        chain.assert_settled(&register);
+        assert_eq!(chain.height(), BlockHeight(0));
+
+        let tx = blob_tx("c1");
+        let tx_hash = chain.submit_blob_tx(tx.clone());
+        chain.produce_block()?;
+        chain.assert_unsettled(&tx_hash);
+
+        chain.submit_proof_tx(proof_tx(&tx, &[0, 1], true))?;
+        chain.produce_block()?;
+        chain.assert_settled(&tx_hash);
+        chain.assert_contract_state(&"c1".into(), &StateCommitment(vec![4, 5, 6]));
+        assert_eq!(chain.height(), BlockHeight(2));
+        assert_eq!(chain.timestamp(), &TimestampMs(2 * DEFAULT_BLOCK_TIME_MS));
+        Ok(())
+    }
+
+    #[test_log::test]
+    fn test_proof_of_failure() -> Result<()> {
+        let mut chain = TestChain::new();
+        chain.register_test_contract("c1".into(), StateCommitment(vec![0, 1]));
+        chain.produce_block()?;
+
+        let tx = blob_tx("c1");
+        let tx_hash = chain.submit_blob_tx(tx.clone());
+        chain.submit_proof_tx(proof_tx(&tx, &[0, 1], false))?;
+        chain.produce_block()?;
+        chain.assert_failed(&tx_hash);
+        chain.assert_contract_state(&"c1".into(), &StateCommitment(vec![0, 1]));
+        Ok(())
+    }
+
+    #[test_log::test]
+    fn test_timeout() -> Result<()> {
+        let mut chain = TestChain::new().with_block_time(500);
+        chain.register_contract(RegisterContractAction {
+            verifier: test_verifier(),
+            program_id: ProgramId(vec![]),
+            state_commitment: StateCommitment(vec![0, 1]),
+            contract_name: "c1".into(),
+            timeout_window: Some(TimeoutWindow::Timeout(BlockHeight(5))),
+            ..Default::default()
+        });
+        chain.produce_block()?;
+
+        let tx_hash = chain.submit_blob_tx(blob_tx("c1"));
+        chain.produce_block()?;
+        chain.advance_to_height(BlockHeight(3))?;
+        chain.assert_unsettled(&tx_hash);
+
+        chain.advance_to_height(BlockHeight(10))?;
+        chain.assert_timed_out(&tx_hash);
+        assert_eq!(chain.timestamp(), &TimestampMs(10 * 500));
+        Ok(())
+    }
+
+    #[test_log::test]
+    fn test_rejects_invalid_proof() {
+        let mut chain = TestChain::new();
+        let proof = ProofTransaction {
+            contract_name: "c1".into(),
+            program_id: ProgramId(vec![]),
+            verifier: test_verifier(),
+            proof: ProofData(vec![1, 2, 3]),
+        };
+        assert!(chain.submit_proof_tx(proof).is_err());
+    }
+}
@@ -200,6 +200,7 @@ async fn main() -> Result<()> {
                 buffer_blocks: 0,
                 max_txs_per_proof: 40,
                 tx_working_window_size: 180,
+                metering: false,
             }))
             .await?;
 
@@ -213,6 +214,7 @@ async fn main() -> Result<()> {
                 contract_name: "oranj".into(),
                 data_directory: dump_folder.clone(),
                 api: build_api_ctx.clone(),
+                storage: Default::default(),
             })
             .await?;
 
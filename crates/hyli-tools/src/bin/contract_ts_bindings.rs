@@ -0,0 +1,65 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use hyle_model::ContractSchema;
+use std::path::PathBuf;
+
+/// Generates TypeScript encode/decode helpers from a contract schema, read from a file
+/// or fetched from the schema registered for the contract on a node or an indexer.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// JSON file holding the contract schema
+    #[arg(long, conflicts_with = "contract")]
+    schema: Option<PathBuf>,
+
+    /// Name of the contract to fetch the schema of
+    #[arg(long, required_unless_present = "schema")]
+    contract: Option<String>,
+
+    /// Node base URL, or indexer base URL with `--indexer`
+    #[arg(long, default_value = "http://localhost:4321")]
+    url: String,
+
+    /// Fetch the schema from an indexer instead of a node
+    #[arg(long)]
+    indexer: bool,
+
+    /// Output file, standard output if not set
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let schema: ContractSchema = match (&args.schema, &args.contract) {
+        (Some(path), _) => serde_json::from_slice(
+            &std::fs::read(path).context(format!("reading {}", path.display()))?,
+        )
+        .context("parsing contract schema")?,
+        (None, Some(contract)) => {
+            let url = if args.indexer {
+                format!("{}/v1/indexer/contract/{contract}/schema", args.url)
+            } else {
+                format!("{}/v1/contract/{contract}/schema", args.url)
+            };
+            ureq::get(&url)
+                .header("Accept", "application/json")
+                .call()
+                .context(format!("fetching schema of contract {contract}"))?
+                .body_mut()
+                .read_json::<ContractSchema>()
+                .context("parsing contract schema")?
+        }
+        (None, None) => anyhow::bail!("Either --schema or --contract is required"),
+    };
+
+    let bindings = hyli_tools::ts_bindings::generate(&schema)?;
+    match &args.out {
+        Some(path) => {
+            std::fs::write(path, bindings).context(format!("writing {}", path.display()))?
+        }
+        None => print!("{bindings}"),
+    }
+    Ok(())
+}
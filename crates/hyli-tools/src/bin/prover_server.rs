@@ -0,0 +1,202 @@
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use clap::{Parser, command};
+
+use client_sdk::{
+    contract_indexer::utoipa::OpenApi,
+    helpers::{ClientSdkProver, risc0::Risc0Prover},
+};
+use hyle_contract_sdk::{Calldata, ProgramId, api::NodeInfo};
+use hyle_modules::{
+    bus::{SharedMessageBus, metrics::BusMetrics},
+    modules::{
+        BuildApiContextInner, ModulesHandler,
+        admin::{AdminApi, AdminApiRunContext},
+        proving_service::{ProvingJobsPolicy, ProvingService, ProvingServiceCtx},
+        rest::{ApiDoc, RestApi, RestApiRunContext, Router},
+    },
+    utils::logger::setup_tracing,
+};
+use prometheus::Registry;
+use serde::{Deserialize, Serialize};
+
+/// Self-hostable prover server, proving jobs submitted over HTTP with `RemoteProver`
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+pub struct Args {
+    #[arg(long, default_value = "config.toml")]
+    pub config_file: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let config = Conf::new(args.config_file).context("reading config file")?;
+
+    setup_tracing(&config.log_format, "prover server".to_string())?;
+
+    std::fs::create_dir_all(&config.data_directory).context("creating data directory")?;
+
+    tracing::info!("Starting prover server");
+
+    let bus = SharedMessageBus::new(BusMetrics::global("prover_server".to_string()));
+
+    let registry = Registry::new();
+    // Init global metrics meter we expose as an endpoint
+    let provider = opentelemetry_sdk::metrics::SdkMeterProvider::builder()
+        .with_reader(
+            opentelemetry_prometheus::exporter()
+                .with_registry(registry.clone())
+                .build()
+                .context("starting prometheus exporter")?,
+        )
+        .build();
+
+    opentelemetry::global::set_meter_provider(provider.clone());
+
+    let mut provers: HashMap<ProgramId, Arc<dyn ClientSdkProver<Vec<Calldata>> + Send + Sync>> =
+        HashMap::new();
+    for program in config.programs.iter() {
+        let program_id: [u8; 32] = hex::decode(&program.program_id)
+            .context("decoding program id")?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("program id {} is not 32 bytes", program.program_id))?;
+        let elf = std::fs::read(&program.elf)
+            .context(format!("reading ELF {}", program.elf.display()))?;
+        tracing::info!(
+            "Serving program {} from {}",
+            program.program_id,
+            program.elf.display()
+        );
+        // The ELF is kept for the whole lifetime of the server
+        provers.insert(
+            ProgramId(program_id.to_vec()),
+            Arc::new(Risc0Prover::new(elf.leak(), program_id)),
+        );
+    }
+
+    let build_api_ctx = Arc::new(BuildApiContextInner {
+        router: std::sync::Mutex::new(Some(Router::new())),
+        openapi: std::sync::Mutex::new(ApiDoc::openapi()),
+    });
+
+    let mut handler = ModulesHandler::new(&bus).await;
+
+    handler
+        .build_module::<ProvingService>(ProvingServiceCtx {
+            data_directory: config.data_directory.clone(),
+            provers,
+            api: build_api_ctx.clone(),
+            policy: ProvingJobsPolicy {
+                max_attempts: config.max_attempts,
+                retry_backoff: Duration::from_millis(config.retry_backoff_ms),
+                max_completed_jobs: config.max_completed_jobs,
+            },
+            max_concurrent_jobs: config.max_concurrent_jobs,
+        })
+        .await?;
+
+    let router = build_api_ctx
+        .router
+        .lock()
+        .expect("Context router should be available.")
+        .take()
+        .expect("Context router should be available.");
+    let openapi = build_api_ctx
+        .openapi
+        .lock()
+        .expect("OpenAPI should be available")
+        .clone();
+
+    if config.run_admin_server {
+        handler
+            .build_module::<AdminApi>(AdminApiRunContext::new(
+                config.admin_server_port,
+                Router::new(),
+                config.admin_server_max_body_size,
+                config.data_directory.clone(),
+            ))
+            .await?;
+    }
+
+    handler
+        .build_module::<RestApi>(
+            RestApiRunContext::new(
+                config.rest_server_port,
+                NodeInfo {
+                    id: "prover_server".to_string(),
+                    pubkey: None,
+                    da_address: "".to_string(),
+                },
+                router,
+                config.rest_server_max_body_size,
+                openapi,
+            )
+            .with_registry(registry),
+        )
+        .await?;
+
+    tracing::info!("Starting modules");
+
+    // Run forever
+    handler.start_modules().await?;
+    handler.exit_process().await?;
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ProgramConf {
+    /// Hex encoded risc0 image id
+    pub program_id: String,
+    pub elf: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct Conf {
+    /// The log format to use - "json", "node" or "full" (default)
+    pub log_format: String,
+
+    /// Directory name to store the job queue.
+    pub data_directory: PathBuf,
+
+    pub max_attempts: u32,
+    /// Delay before retrying a failed job, doubled after each attempt
+    pub retry_backoff_ms: u64,
+    /// Number of succeeded or failed jobs kept
+    pub max_completed_jobs: usize,
+    pub max_concurrent_jobs: usize,
+
+    /// Programs that can be proven
+    pub programs: Vec<ProgramConf>,
+
+    pub rest_server_port: u16,
+    pub rest_server_max_body_size: usize,
+
+    pub run_admin_server: bool,
+    pub admin_server_port: u16,
+    pub admin_server_max_body_size: usize,
+}
+
+impl Conf {
+    pub fn new(config_files: Vec<String>) -> Result<Self, anyhow::Error> {
+        let mut s = config::Config::builder().add_source(config::File::from_str(
+            include_str!("../prover_server_conf_defaults.toml"),
+            config::FileFormat::Toml,
+        ));
+        // Priority order: config file, then environment variables, then CLI
+        for config_file in config_files {
+            s = s.add_source(config::File::with_name(&config_file).required(false));
+        }
+        let conf: Self = s
+            .add_source(
+                config::Environment::with_prefix("hyle")
+                    .separator("__")
+                    .prefix_separator("_"),
+            )
+            .build()?
+            .try_deserialize()?;
+        Ok(conf)
+    }
+}
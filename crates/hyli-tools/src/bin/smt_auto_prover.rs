@@ -82,6 +82,7 @@ async fn main() -> Result<()> {
             buffer_blocks: config.buffer_blocks,
             max_txs_per_proof: config.max_txs_per_proof,
             tx_working_window_size: config.tx_working_window_size,
+            metering: config.metering,
         }))
         .await?;
 
@@ -160,6 +161,8 @@ struct Conf {
     pub buffer_blocks: u32,
     pub max_txs_per_proof: usize,
     pub tx_working_window_size: usize,
+    /// Executes each batch before proving it, to record its execution cost in the metrics
+    pub metering: bool,
 
     /// Contract name to prove
     pub contract_name: String,
@@ -1 +1,2 @@
 pub mod gcs_block_uploader;
+pub mod ts_bindings;
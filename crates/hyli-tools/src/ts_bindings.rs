@@ -0,0 +1,683 @@
+//! Generates TypeScript encode/decode helpers from a [ContractSchema].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use anyhow::{Context, Result};
+use hyle_model::{
+    ActionEncoding, ContractSchema, EnumVariantDefinition, FieldDefinition, ProgramOutputsEncoding,
+    TypeDefinition,
+};
+
+const RUNTIME: &str = r#"export class BorshWriter {
+    private buffer: number[] = [];
+
+    uint(value: number | bigint, size: number): void {
+        let v = BigInt(value);
+        if (v < 0n || v >= 1n << BigInt(size * 8)) {
+            throw new RangeError(`${value} does not fit in ${size} unsigned bytes`);
+        }
+        for (let i = 0; i < size; i++) {
+            this.buffer.push(Number(v & 0xffn));
+            v >>= 8n;
+        }
+    }
+
+    int(value: number | bigint, size: number): void {
+        const v = BigInt(value);
+        const bound = 1n << BigInt(size * 8 - 1);
+        if (v < -bound || v >= bound) {
+            throw new RangeError(`${value} does not fit in ${size} signed bytes`);
+        }
+        this.uint(BigInt.asUintN(size * 8, v), size);
+    }
+
+    f32(value: number): void {
+        const view = new DataView(new ArrayBuffer(4));
+        view.setFloat32(0, value, true);
+        this.bytes(new Uint8Array(view.buffer));
+    }
+
+    f64(value: number): void {
+        const view = new DataView(new ArrayBuffer(8));
+        view.setFloat64(0, value, true);
+        this.bytes(new Uint8Array(view.buffer));
+    }
+
+    bytes(value: Uint8Array): void {
+        for (const byte of value) {
+            this.buffer.push(byte);
+        }
+    }
+
+    toBytes(): Uint8Array {
+        return Uint8Array.from(this.buffer);
+    }
+}
+
+export class BorshReader {
+    private offset = 0;
+
+    constructor(private readonly data: Uint8Array) {}
+
+    bytes(length: number): Uint8Array {
+        if (this.offset + length > this.data.length) {
+            throw new RangeError("Unexpected end of borsh data");
+        }
+        const bytes = this.data.slice(this.offset, this.offset + length);
+        this.offset += length;
+        return bytes;
+    }
+
+    uint(size: number): bigint {
+        const bytes = this.bytes(size);
+        let value = 0n;
+        for (let i = size - 1; i >= 0; i--) {
+            value = (value << 8n) | BigInt(bytes[i]);
+        }
+        return value;
+    }
+
+    int(size: number): bigint {
+        return BigInt.asIntN(size * 8, this.uint(size));
+    }
+
+    f32(): number {
+        return new DataView(this.bytes(4).buffer).getFloat32(0, true);
+    }
+
+    f64(): number {
+        return new DataView(this.bytes(8).buffer).getFloat64(0, true);
+    }
+
+    finish(): void {
+        if (this.offset !== this.data.length) {
+            throw new Error("Unexpected trailing bytes in borsh data");
+        }
+    }
+}
+"#;
+
+/// Generates a self-contained TypeScript module encoding and decoding the actions, state and
+/// program outputs described by `schema`.
+pub fn generate(schema: &ContractSchema) -> Result<String> {
+    let generator = Generator::new(schema);
+    let mut out = String::new();
+    writeln!(
+        out,
+        "// Generated from the schema of the contract, do not edit.\n"
+    )?;
+    out.push_str(RUNTIME);
+
+    for (declaration, definition) in &schema.definitions {
+        generator.definition(&mut out, declaration, definition)?;
+    }
+    generator.entrypoints(&mut out)?;
+    Ok(out)
+}
+
+struct Generator<'a> {
+    schema: &'a ContractSchema,
+    /// Identifier of each declaration in the generated code
+    names: BTreeMap<&'a str, String>,
+}
+
+impl<'a> Generator<'a> {
+    fn new(schema: &'a ContractSchema) -> Self {
+        let mut used = BTreeSet::new();
+        let mut names = BTreeMap::new();
+        for declaration in schema.definitions.keys() {
+            let base = identifier(declaration);
+            let mut name = base.clone();
+            let mut suffix = 2;
+            while !used.insert(name.clone()) {
+                name = format!("{base}{suffix}");
+                suffix += 1;
+            }
+            names.insert(declaration.as_str(), name);
+        }
+        Generator { schema, names }
+    }
+
+    fn get(&self, declaration: &str) -> Result<(&str, &'a TypeDefinition)> {
+        let name = self
+            .names
+            .get(declaration)
+            .with_context(|| format!("Type {declaration} is not defined in the schema"))?;
+        let definition = self
+            .schema
+            .definitions
+            .get(declaration)
+            .with_context(|| format!("Type {declaration} is not defined in the schema"))?;
+        Ok((name, definition))
+    }
+
+    /// TypeScript type of a declaration, named types are only used for structs and enums
+    fn ts_type(&self, declaration: &str) -> Result<String> {
+        let (name, definition) = self.get(declaration)?;
+        Ok(match definition {
+            TypeDefinition::Primitive { size } => primitive_type(declaration, *size).to_string(),
+            TypeDefinition::Sequence { elements, .. } => {
+                if is_string(declaration) {
+                    "string".to_string()
+                } else if elements == "u8" {
+                    "Uint8Array".to_string()
+                } else {
+                    format!("Array<{}>", self.ts_type(elements)?)
+                }
+            }
+            TypeDefinition::Tuple { elements } if elements.is_empty() => "null".to_string(),
+            TypeDefinition::Tuple { elements } => format!(
+                "[{}]",
+                elements
+                    .iter()
+                    .map(|e| self.ts_type(e))
+                    .collect::<Result<Vec<_>>>()?
+                    .join(", ")
+            ),
+            TypeDefinition::Enum { variants, .. } => match option_of(declaration, variants) {
+                Some(inner) => format!("{} | null", self.ts_type(inner)?),
+                None => name.to_string(),
+            },
+            TypeDefinition::Struct { .. } => name.to_string(),
+        })
+    }
+
+    fn definition(
+        &self,
+        out: &mut String,
+        declaration: &str,
+        definition: &TypeDefinition,
+    ) -> Result<()> {
+        let (name, _) = self.get(declaration)?;
+        let ts_type = self.ts_type(declaration)?;
+        let (encode, decode) = match definition {
+            TypeDefinition::Primitive { size } => primitive_codec(declaration, *size),
+            TypeDefinition::Sequence {
+                length_width,
+                min_length,
+                elements,
+                ..
+            } => self.sequence_codec(declaration, *length_width, *min_length, elements)?,
+            TypeDefinition::Tuple { elements } => self.tuple_codec(elements)?,
+            TypeDefinition::Enum {
+                tag_width,
+                variants,
+            } => {
+                if let Some(inner) = option_of(declaration, variants) {
+                    self.option_codec(*tag_width, inner)?
+                } else if variants.is_empty() {
+                    writeln!(out, "export type {name} = never;\n")?;
+                    self.enum_codec(name, *tag_width, variants)?
+                } else {
+                    writeln!(
+                        out,
+                        "export type {name} =\n{};\n",
+                        variants
+                            .iter()
+                            .map(|v| Ok(format!(
+                                "    | {{ {}: {} }}",
+                                v.name,
+                                self.ts_type(&v.declaration)?
+                            )))
+                            .collect::<Result<Vec<_>>>()?
+                            .join("\n")
+                    )?;
+                    self.enum_codec(name, *tag_width, variants)?
+                }
+            }
+            TypeDefinition::Struct { fields } => {
+                writeln!(out, "export type {name} = {};\n", self.struct_type(fields)?)?;
+                self.struct_codec(fields)?
+            }
+        };
+        writeln!(
+            out,
+            "export function encode{name}(w: BorshWriter, v: {ts_type}): void {{\n{encode}}}\n"
+        )?;
+        writeln!(
+            out,
+            "export function decode{name}(r: BorshReader): {ts_type} {{\n{decode}}}\n"
+        )?;
+        Ok(())
+    }
+
+    fn sequence_codec(
+        &self,
+        declaration: &str,
+        length_width: u8,
+        min_length: u64,
+        elements: &str,
+    ) -> Result<(String, String)> {
+        let (element, _) = self.get(elements)?;
+        let write_length = |length: &str| {
+            if length_width == 0 {
+                format!(
+                    "    if ({length} !== {min_length}) {{\n        throw new RangeError(`Expected {min_length} elements, got ${{{length}}}`);\n    }}\n"
+                )
+            } else {
+                format!("    w.uint({length}, {length_width});\n")
+            }
+        };
+        let read_length = if length_width == 0 {
+            format!("    const length = {min_length};\n")
+        } else {
+            format!("    const length = Number(r.uint({length_width}));\n")
+        };
+        Ok(if is_string(declaration) {
+            (
+                format!(
+                    "    const bytes = new TextEncoder().encode(v);\n{}    w.bytes(bytes);\n",
+                    write_length("bytes.length")
+                ),
+                format!("{read_length}    return new TextDecoder().decode(r.bytes(length));\n"),
+            )
+        } else if elements == "u8" {
+            (
+                format!("{}    w.bytes(v);\n", write_length("v.length")),
+                format!("{read_length}    return r.bytes(length);\n"),
+            )
+        } else {
+            (
+                format!(
+                    "{}    for (const e of v) {{\n        encode{element}(w, e);\n    }}\n",
+                    write_length("v.length")
+                ),
+                format!(
+                    "{read_length}    const out: {} = [];\n    for (let i = 0; i < length; i++) {{\n        out.push(decode{element}(r));\n    }}\n    return out;\n",
+                    self.ts_type(declaration)?
+                ),
+            )
+        })
+    }
+
+    fn tuple_codec(&self, elements: &[String]) -> Result<(String, String)> {
+        if elements.is_empty() {
+            return Ok((
+                "    void v;\n".to_string(),
+                "    return null;\n".to_string(),
+            ));
+        }
+        let mut encode = String::new();
+        let mut decode = Vec::new();
+        for (i, element) in elements.iter().enumerate() {
+            let (name, _) = self.get(element)?;
+            writeln!(encode, "    encode{name}(w, v[{i}]);")?;
+            decode.push(format!("decode{name}(r)"));
+        }
+        Ok((encode, format!("    return [{}];\n", decode.join(", "))))
+    }
+
+    fn option_codec(&self, tag_width: u8, inner: &str) -> Result<(String, String)> {
+        let (name, _) = self.get(inner)?;
+        Ok((
+            format!(
+                "    if (v === null) {{\n        w.uint(0, {tag_width});\n    }} else {{\n        w.uint(1, {tag_width});\n        encode{name}(w, v);\n    }}\n"
+            ),
+            format!(
+                "    const tag = Number(r.uint({tag_width}));\n    if (tag === 0) {{\n        return null;\n    }} else if (tag === 1) {{\n        return decode{name}(r);\n    }}\n    throw new Error(`Invalid option tag ${{tag}}`);\n"
+            ),
+        ))
+    }
+
+    fn enum_codec(
+        &self,
+        enum_name: &str,
+        tag_width: u8,
+        variants: &[EnumVariantDefinition],
+    ) -> Result<(String, String)> {
+        let mut encode = String::new();
+        let mut decode =
+            format!("    const tag = Number(r.uint({tag_width}));\n    switch (tag) {{\n");
+        for variant in variants {
+            let (name, _) = self.get(&variant.declaration)?;
+            let EnumVariantDefinition {
+                discriminant,
+                name: variant,
+                ..
+            } = variant;
+            writeln!(
+                encode,
+                "    if (\"{variant}\" in v) {{\n        w.uint({discriminant}, {tag_width});\n        encode{name}(w, v.{variant});\n        return;\n    }}"
+            )?;
+            writeln!(
+                decode,
+                "        case {discriminant}:\n            return {{ {variant}: decode{name}(r) }};"
+            )?;
+        }
+        writeln!(
+            encode,
+            "    throw new Error(\"Unknown variant of {enum_name}\");"
+        )?;
+        writeln!(
+            decode,
+            "        default:\n            throw new Error(`Unknown tag ${{tag}} for {enum_name}`);\n    }}"
+        )?;
+        Ok((encode, decode))
+    }
+
+    fn struct_type(&self, fields: &[FieldDefinition]) -> Result<String> {
+        Ok(match fields {
+            [] => "null".to_string(),
+            [field] if field.name.is_none() => self.ts_type(&field.declaration)?,
+            fields if fields.iter().all(|f| f.name.is_some()) => format!(
+                "{{\n{}\n}}",
+                fields
+                    .iter()
+                    .map(|f| Ok(format!(
+                        "    {}: {};",
+                        f.name.as_deref().unwrap_or_default(),
+                        self.ts_type(&f.declaration)?
+                    )))
+                    .collect::<Result<Vec<_>>>()?
+                    .join("\n")
+            ),
+            fields => format!(
+                "[{}]",
+                fields
+                    .iter()
+                    .map(|f| self.ts_type(&f.declaration))
+                    .collect::<Result<Vec<_>>>()?
+                    .join(", ")
+            ),
+        })
+    }
+
+    fn struct_codec(&self, fields: &[FieldDefinition]) -> Result<(String, String)> {
+        Ok(match fields {
+            [] => (
+                "    void v;\n".to_string(),
+                "    return null;\n".to_string(),
+            ),
+            [field] if field.name.is_none() => {
+                let (name, _) = self.get(&field.declaration)?;
+                (
+                    format!("    encode{name}(w, v);\n"),
+                    format!("    return decode{name}(r);\n"),
+                )
+            }
+            fields if fields.iter().all(|f| f.name.is_some()) => {
+                let mut encode = String::new();
+                let mut decode = Vec::new();
+                for field in fields {
+                    let (name, _) = self.get(&field.declaration)?;
+                    let field = field.name.as_deref().unwrap_or_default();
+                    writeln!(encode, "    encode{name}(w, v.{field});")?;
+                    decode.push(format!("        {field}: decode{name}(r),"));
+                }
+                (
+                    encode,
+                    format!("    return {{\n{}\n    }};\n", decode.join("\n")),
+                )
+            }
+            fields => {
+                let elements: Vec<_> = fields.iter().map(|f| f.declaration.clone()).collect();
+                self.tuple_codec(&elements)?
+            }
+        })
+    }
+
+    fn entrypoints(&self, out: &mut String) -> Result<()> {
+        let (action, _) = self.get(&self.schema.action)?;
+        let action_type = self.ts_type(&self.schema.action)?;
+        match self.schema.action_encoding {
+            ActionEncoding::Raw => {
+                writeln!(
+                    out,
+                    "/** Blob data of an action */\nexport function encodeAction(action: {action_type}): Uint8Array {{\n    const w = new BorshWriter();\n    encode{action}(w, action);\n    return w.toBytes();\n}}\n"
+                )?;
+                writeln!(
+                    out,
+                    "export function decodeAction(data: Uint8Array): {action_type} {{\n    const r = new BorshReader(data);\n    const action = decode{action}(r);\n    r.finish();\n    return action;\n}}\n"
+                )?;
+            }
+            ActionEncoding::Structured => {
+                writeln!(
+                    out,
+                    "export type StructuredAction = {{\n    caller: number | null;\n    callees: number[] | null;\n    parameters: {action_type};\n}};\n"
+                )?;
+                writeln!(
+                    out,
+                    "/** Blob data of an action, with the indexes of its caller and callees blobs */\nexport function encodeAction(\n    action: {action_type},\n    caller: number | null = null,\n    callees: number[] | null = null,\n): Uint8Array {{\n    const w = new BorshWriter();\n    if (caller === null) {{\n        w.uint(0, 1);\n    }} else {{\n        w.uint(1, 1);\n        w.uint(caller, 8);\n    }}\n    if (callees === null) {{\n        w.uint(0, 1);\n    }} else {{\n        w.uint(1, 1);\n        w.uint(callees.length, 4);\n        for (const callee of callees) {{\n            w.uint(callee, 8);\n        }}\n    }}\n    encode{action}(w, action);\n    return w.toBytes();\n}}\n"
+                )?;
+                writeln!(
+                    out,
+                    "export function decodeAction(data: Uint8Array): StructuredAction {{\n    const r = new BorshReader(data);\n    const caller = r.uint(1) === 0n ? null : Number(r.uint(8));\n    let callees: number[] | null = null;\n    if (r.uint(1) !== 0n) {{\n        callees = [];\n        const length = Number(r.uint(4));\n        for (let i = 0; i < length; i++) {{\n            callees.push(Number(r.uint(8)));\n        }}\n    }}\n    const parameters = decode{action}(r);\n    r.finish();\n    return {{ caller, callees, parameters }};\n}}\n"
+                )?;
+            }
+        }
+
+        if let Some(state) = &self.schema.state {
+            let (name, _) = self.get(state)?;
+            let state_type = self.ts_type(state)?;
+            writeln!(
+                out,
+                "export function decodeState(data: Uint8Array): {state_type} {{\n    const r = new BorshReader(data);\n    const state = decode{name}(r);\n    r.finish();\n    return state;\n}}\n"
+            )?;
+        }
+
+        match &self.schema.program_outputs {
+            Some(ProgramOutputsEncoding::Utf8) => writeln!(
+                out,
+                "export function decodeProgramOutputs(data: Uint8Array): string {{\n    return new TextDecoder().decode(data);\n}}"
+            )?,
+            Some(ProgramOutputsEncoding::Borsh { declaration }) => {
+                let (name, _) = self.get(declaration)?;
+                let output_type = self.ts_type(declaration)?;
+                writeln!(
+                    out,
+                    "export function decodeProgramOutputs(data: Uint8Array): {output_type} {{\n    const r = new BorshReader(data);\n    const outputs = decode{name}(r);\n    r.finish();\n    return outputs;\n}}"
+                )?
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+/// Identifier for a declaration, e.g. `BTreeMap<String, u128>` becomes `BTreeMap_String_u128`
+fn identifier(declaration: &str) -> String {
+    let words: Vec<_> = declaration
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+        .collect();
+    if words.is_empty() {
+        return "Unit".to_string();
+    }
+    let prefix = match declaration.chars().next() {
+        Some('(') => "Tuple_",
+        Some('[') => "Array_",
+        _ => "",
+    };
+    let name = format!("{prefix}{}", words.join("_"));
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_digit() => format!("T{name}"),
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name,
+    }
+}
+
+fn is_string(declaration: &str) -> bool {
+    declaration == "String" || declaration == "str"
+}
+
+/// Inner declaration of an `Option`, encoded as a nullable value
+fn option_of<'a>(declaration: &str, variants: &'a [EnumVariantDefinition]) -> Option<&'a str> {
+    match variants {
+        [none, some]
+            if declaration.starts_with("Option<")
+                && none.name == "None"
+                && none.discriminant == 0
+                && some.name == "Some"
+                && some.discriminant == 1 =>
+        {
+            Some(some.declaration.as_str())
+        }
+        _ => None,
+    }
+}
+
+fn primitive_type(declaration: &str, size: u8) -> &'static str {
+    match declaration {
+        "bool" => "boolean",
+        "f32" | "f64" => "number",
+        _ if size == 0 => "null",
+        _ if size <= 4 => "number",
+        _ => "bigint",
+    }
+}
+
+fn primitive_codec(declaration: &str, size: u8) -> (String, String) {
+    let signed = declaration.starts_with('i');
+    let (write, read) = if signed {
+        ("int", "int")
+    } else {
+        ("uint", "uint")
+    };
+    match declaration {
+        "bool" => (
+            "    w.uint(v ? 1 : 0, 1);\n".to_string(),
+            "    return r.uint(1) !== 0n;\n".to_string(),
+        ),
+        "f32" | "f64" => (
+            format!("    w.{declaration}(v);\n"),
+            format!("    return r.{declaration}();\n"),
+        ),
+        _ if size == 0 => (
+            "    void v;\n".to_string(),
+            "    return null;\n".to_string(),
+        ),
+        _ if size <= 4 => (
+            format!("    w.{write}(v, {size});\n"),
+            format!("    return Number(r.{read}({size}));\n"),
+        ),
+        _ => (
+            format!("    w.{write}(v, {size});\n"),
+            format!("    return r.{read}({size});\n"),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> ContractSchema {
+        let definitions = [
+            ("u8", TypeDefinition::Primitive { size: 1 }),
+            ("u128", TypeDefinition::Primitive { size: 16 }),
+            (
+                "String",
+                TypeDefinition::Sequence {
+                    length_width: 4,
+                    min_length: 0,
+                    max_length: u32::MAX as u64,
+                    elements: "u8".to_string(),
+                },
+            ),
+            (
+                "Identity",
+                TypeDefinition::Struct {
+                    fields: vec![FieldDefinition {
+                        name: None,
+                        declaration: "String".to_string(),
+                    }],
+                },
+            ),
+            (
+                "Option<u128>",
+                TypeDefinition::Enum {
+                    tag_width: 1,
+                    variants: vec![
+                        EnumVariantDefinition {
+                            discriminant: 0,
+                            name: "None".to_string(),
+                            declaration: "()".to_string(),
+                        },
+                        EnumVariantDefinition {
+                            discriminant: 1,
+                            name: "Some".to_string(),
+                            declaration: "u128".to_string(),
+                        },
+                    ],
+                },
+            ),
+            ("()", TypeDefinition::Primitive { size: 0 }),
+            (
+                "TokenActionTransfer",
+                TypeDefinition::Struct {
+                    fields: vec![
+                        FieldDefinition {
+                            name: Some("recipient".to_string()),
+                            declaration: "Identity".to_string(),
+                        },
+                        FieldDefinition {
+                            name: Some("amount".to_string()),
+                            declaration: "Option<u128>".to_string(),
+                        },
+                    ],
+                },
+            ),
+            (
+                "TokenAction",
+                TypeDefinition::Enum {
+                    tag_width: 1,
+                    variants: vec![EnumVariantDefinition {
+                        discriminant: 0,
+                        name: "Transfer".to_string(),
+                        declaration: "TokenActionTransfer".to_string(),
+                    }],
+                },
+            ),
+        ];
+        ContractSchema {
+            action: "TokenAction".to_string(),
+            action_encoding: ActionEncoding::Structured,
+            state: None,
+            program_outputs: Some(ProgramOutputsEncoding::Utf8),
+            definitions: definitions
+                .into_iter()
+                .map(|(declaration, definition)| (declaration.to_string(), definition))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_identifier() {
+        assert_eq!(identifier("BTreeMap<String, u128>"), "BTreeMap_String_u128");
+        assert_eq!(identifier("(String, u8)"), "Tuple_String_u8");
+        assert_eq!(identifier("[u8; 32]"), "Array_u8_32");
+        assert_eq!(identifier("u64"), "U64");
+        assert_eq!(identifier("()"), "Unit");
+    }
+
+    #[test]
+    fn test_generate() {
+        let ts = generate(&schema()).unwrap();
+
+        assert!(ts.contains("export type Identity = string;"));
+        assert!(ts.contains("export type TokenActionTransfer = {\n    recipient: Identity;\n    amount: bigint | null;\n};"));
+        assert!(ts.contains("export type TokenAction =\n    | { Transfer: TokenActionTransfer };"));
+        assert!(ts.contains(
+            "export function encodeOption_u128(w: BorshWriter, v: bigint | null): void {"
+        ));
+        assert!(ts.contains("        encodeU128(w, v);"));
+        assert!(ts.contains("    w.uint(v, 16);"));
+        assert!(ts.contains("    return new TextDecoder().decode(r.bytes(length));"));
+        assert!(ts.contains("export function encodeAction(\n    action: TokenAction,"));
+        assert!(ts.contains("export function decodeProgramOutputs(data: Uint8Array): string {"));
+        assert!(!ts.contains("decodeState"));
+    }
+
+    #[test]
+    fn test_generate_missing_definition() {
+        let mut schema = schema();
+        schema.definitions.remove("Identity");
+        let err = generate(&schema).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Type Identity is not defined in the schema"
+        );
+    }
+}
@@ -1,7 +1,15 @@
+use std::path::PathBuf;
+
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use hyle::{
     entrypoint::RunPg,
+    explorer::{blob_decoders::BlobDecoders, db::IndexerDb},
+    indexer::{
+        export::{export, ExportConf, ExportFormat},
+        reindex::ReindexConf,
+    },
+    model::BlockHeight,
     utils::conf::{self, P2pMode},
 };
 use hyle_modules::{log_error, utils::logger::setup_tracing};
@@ -14,6 +22,45 @@ pub struct Args {
 
     #[clap(long, action)]
     pub pg: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Deletes then indexes again a range of blocks, and exits
+    Reindex {
+        /// First block to index again
+        #[arg(long)]
+        from: u64,
+        /// Last block to index again, defaults to the last indexed block
+        #[arg(long)]
+        to: Option<u64>,
+        /// Number of block ranges fetched in parallel from the DA server
+        #[arg(long, default_value_t = 4)]
+        fetchers: usize,
+        /// Blocks source overriding `da_read_from`: a DA server address, `folder:<dump dir>` or `da:<DA storage dir>`
+        #[arg(long)]
+        da_read_from: Option<String>,
+    },
+    /// Writes the indexed data of a range of blocks to CSV or Parquet files partitioned by day, and exits
+    Export {
+        /// First block to export
+        #[arg(long, default_value_t = 0)]
+        from: u64,
+        /// Last block to export, defaults to the last indexed block
+        #[arg(long)]
+        to: Option<u64>,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Parquet)]
+        format: ExportFormat,
+        /// Directory the tables are written to
+        #[arg(long, default_value = "export")]
+        output: PathBuf,
+        /// Adds the decoded blobs of contracts with a known decoder
+        #[arg(long, action)]
+        decode_blobs: bool,
+    },
 }
 
 #[cfg(feature = "dhat")]
@@ -37,6 +84,45 @@ async fn main() -> Result<()> {
     // The indexer binary skips the TCP server
     config.run_tcp_server = false;
 
+    let mut export_conf = None;
+    match args.command {
+        Some(Command::Reindex {
+            from,
+            to,
+            fetchers,
+            da_read_from,
+        }) => {
+            config.reindex = Some(ReindexConf {
+                from: BlockHeight(from),
+                to: to.map(BlockHeight),
+                fetchers,
+            });
+            if let Some(da_read_from) = da_read_from {
+                config.da_read_from = da_read_from;
+            }
+            // Only the rest server stays up, to expose the reindexing metrics
+            config.run_explorer = false;
+            config.run_admin_server = false;
+            config.websocket.enabled = false;
+        }
+        Some(Command::Export {
+            from,
+            to,
+            format,
+            output,
+            decode_blobs,
+        }) => {
+            export_conf = Some(ExportConf {
+                from: BlockHeight(from),
+                to: to.map(BlockHeight),
+                format,
+                output,
+                decode_blobs,
+            });
+        }
+        None => {}
+    }
+
     setup_tracing(&config.log_format, format!("{}(nopkey)", config.id.clone()))?;
 
     let _pg = if args.pg {
@@ -45,6 +131,17 @@ async fn main() -> Result<()> {
         None
     };
 
+    // Exports run against the database only, without starting any module
+    if let Some(export_conf) = export_conf {
+        let db = IndexerDb::connect(&config.database_url).await?;
+        let mut blob_decoders = BlobDecoders::with_builtins();
+        blob_decoders.register_schemas(&config.indexer.blob_schemas);
+        return log_error!(
+            export(&db, &export_conf, &blob_decoders).await,
+            "Error exporting indexed data"
+        );
+    }
+
     log_error!(
         hyle::entrypoint::main_process(config, None).await,
         "Error running hyle indexer"
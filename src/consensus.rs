@@ -23,9 +23,11 @@ use hyle_model::utils::TimestampMs;
 use hyle_modules::bus::BusMessage;
 use hyle_modules::{log_error, module_bus_client, module_handle_messages, modules::Module};
 use hyle_net::clock::TimestampMsClock;
+use leader_election::LeaderReputation;
 use metrics::ConsensusMetrics;
 use role_follower::FollowerState;
 use role_leader::LeaderState;
+use role_sync::{CommittedHistory, StateSyncState};
 use role_timeout::TimeoutRoleState;
 use serde::{Deserialize, Serialize};
 use staking::state::{Staking, MIN_STAKE};
@@ -37,6 +39,7 @@ use tokio::time::interval;
 use tracing::{debug, info, trace};
 
 pub mod api;
+pub mod leader_election;
 pub mod metrics;
 pub mod module;
 mod network;
@@ -113,6 +116,10 @@ pub struct BFTRoundState {
     follower: FollowerState,
     timeout: TimeoutRoleState,
     joining: JoiningState,
+    state_sync: StateSyncState,
+    /// Rebuilt from the committed history when loading the consensus state, then updated with processed blocks
+    #[borsh(skip)]
+    leader_reputation: LeaderReputation,
     genesis: GenesisState,
     state_tag: StateTag,
 }
@@ -139,6 +146,8 @@ pub struct ConsensusStore {
     bft_round_state: BFTRoundState,
     /// Validators that asked to be part of consensus
     validator_candidates: Vec<SignedByValidator<ValidatorCandidacy>>,
+    /// Latest committed proposals, served to validators catching up
+    committed_history: CommittedHistory,
 }
 
 pub struct Consensus {
@@ -180,21 +189,26 @@ macro_rules! with_metric {
 
 impl Consensus {
     fn round_leader(&self) -> Result<ValidatorPublicKey> {
-        // Find out who the next leader will be.
-        // For now, this is round-robin on slot + view for simplicity.
-        // (we remove 1 for backwards compatibility of the tests when making the change)
-        let index = (self.bft_round_state.slot as usize + self.bft_round_state.view as usize)
-            .wrapping_sub(1)
-            % self.bft_round_state.staking.bonded().len();
-
-        // Get the next leader based on the random index
-        Ok(self
-            .bft_round_state
-            .staking
-            .bonded()
-            .get(index)
-            .context("No next leader found")?
-            .clone())
+        // Find out who the leader of the current slot & view is, according to the configured policy.
+        self.config.consensus.leader_election.election().leader(
+            &self.bft_round_state.staking,
+            &self.bft_round_state.leader_reputation,
+            self.bft_round_state.slot,
+            self.bft_round_state.view,
+        )
+    }
+    /// Leaders of the views of the current slot before `view`, which all timed out.
+    fn timed_out_leaders(&self, view: View) -> Result<Vec<ValidatorPublicKey>> {
+        (0..view)
+            .map(|view| {
+                self.config.consensus.leader_election.election().leader(
+                    &self.bft_round_state.staking,
+                    &self.bft_round_state.leader_reputation,
+                    self.bft_round_state.slot,
+                    view,
+                )
+            })
+            .collect()
     }
     fn next_view_leader(&mut self) -> Result<ValidatorPublicKey> {
         self.bft_round_state.view += 1;
@@ -495,6 +509,20 @@ impl Consensus {
             ConsensusNetMessage::SyncReply(prepare) => {
                 with_metric!(self.metrics, "on_sync_reply", self.on_sync_reply(prepare))
             }
+            ConsensusNetMessage::StateSyncRequest(from, to) => {
+                with_metric!(
+                    self.metrics,
+                    "on_state_sync_request",
+                    self.on_state_sync_request(sender, from, to)
+                )
+            }
+            ConsensusNetMessage::StateSyncReply(committed_proposals) => {
+                with_metric!(
+                    self.metrics,
+                    "on_state_sync_reply",
+                    self.on_state_sync_reply(sender, committed_proposals)
+                )
+            }
         }
     }
 
@@ -551,6 +579,16 @@ impl Consensus {
     fn emit_commit_event(&mut self, commit_quorum_certificate: &CommitQC) -> Result<()> {
         self.metrics.commit();
 
+        // Forced commits have no QC, and could not be verified by validators catching up
+        if !commit_quorum_certificate.validators.is_empty() {
+            let consensus_proposal = self.bft_round_state.current_proposal.clone();
+            self.store.committed_history.push(
+                consensus_proposal,
+                commit_quorum_certificate.clone(),
+                self.config.consensus.state_sync_history_size,
+            );
+        }
+
         self.bus
             .send(ConsensusEvent::CommitConsensusProposal(
                 CommittedConsensusProposal {
@@ -620,6 +658,12 @@ impl Consensus {
                     .staking
                     .process_block(block.as_ref())
                     .map_err(|e| anyhow!(e))?;
+                // Like staking, reputation only changes with processed blocks, so all validators agree on it,
+                // including the ones catching up and after forced commits
+                self.store
+                    .bft_round_state
+                    .leader_reputation
+                    .record_block(block.as_ref());
 
                 if let StateTag::Joining = self.bft_round_state.state_tag {
                     if self.store.bft_round_state.joining.staking_updated_to < block.block_height.0
@@ -630,6 +674,13 @@ impl Consensus {
                         );
                         self.store.bft_round_state.joining.staking_updated_to =
                             block.block_height.0;
+
+                        // We already committed further through state sync, don't go back
+                        if self.is_behind_state_sync(block.block_height) {
+                            return Ok(());
+                        }
+                        let caught_up_state_sync = self.take_state_synced_slot();
+
                         self.store.bft_round_state.slot = block.block_height.0 + 1;
                         self.store.bft_round_state.view = 0;
                         self.store.bft_round_state.parent_hash = block.hash.clone();
@@ -640,6 +691,20 @@ impl Consensus {
                         };
 
                         self.bft_round_state.timeout.requests.clear();
+
+                        // The next prepare might have been buffered while waiting for the blocks
+                        if let Some((sender, consensus_proposal, ticket, view)) = self
+                            .bft_round_state
+                            .follower
+                            .buffered_prepares
+                            .next_prepare(block.hash.clone())
+                            .filter(|_| caught_up_state_sync)
+                        {
+                            _ = log_error!(
+                                self.on_prepare(sender, consensus_proposal, ticket, view),
+                                "Processing buffered Prepare after catching up"
+                            );
+                        }
                     }
                 }
                 Ok(())
@@ -841,7 +906,7 @@ pub mod test {
         },
         utils::conf::Conf,
     };
-    use assertables::assert_contains;
+    use assertables::{assert_contains, assert_matches};
     use tokio::sync::broadcast::Receiver;
     use utils::TimestampMs;
 
@@ -885,6 +950,7 @@ pub mod test {
             let mut conf = Conf::default();
             conf.consensus.slot_duration = Duration::from_millis(1000);
             conf.consensus.timeout_after = Duration::from_millis(5000);
+            conf.consensus.state_sync_history_size = 100;
             let bus = ConsensusBusClient::new_from_bus(shared_bus.new_handle()).await;
 
             Consensus {
@@ -1334,6 +1400,7 @@ pub mod test {
             )],
             staking_actions: vec![],
             parent_hash: ConsensusProposalHash("hash".into()),
+            timed_out_leaders: vec![],
         };
 
         // Create wrong prepare
@@ -1414,6 +1481,7 @@ pub mod test {
                     )],
                     staking_actions: vec![],
                     parent_hash: ConsensusProposalHash("hash".into()),
+                    timed_out_leaders: vec![],
                 },
                 Ticket::Genesis,
                 0,
@@ -1463,6 +1531,7 @@ pub mod test {
                     )],
                     staking_actions: vec![],
                     parent_hash: ConsensusProposalHash("hash".into()),
+                    timed_out_leaders: vec![],
                 },
                 Ticket::Genesis,
                 0,
@@ -1962,6 +2031,24 @@ pub mod test {
         assert_eq!(cp.slot, 1);
         assert_eq!(cp_view, 1);
         assert_eq!(cp.parent_hash, ConsensusProposalHash("genesis".into()));
+
+        // The leader of view 0 is recorded in the committed proposal, and in everyone's reputation once its block is processed
+        let timed_out_leader = node1.validator_pubkey();
+        assert_eq!(cp.timed_out_leaders, vec![timed_out_leader.clone()]);
+        for node in [&mut node1, &mut node2, &mut node3, &mut node4] {
+            node.handle_node_state_event(NodeStateEvent::NewBlock(Box::new(Block {
+                block_height: BlockHeight(cp.slot),
+                timed_out_leaders: cp.timed_out_leaders.clone(),
+                ..Default::default()
+            })))
+            .await
+            .unwrap();
+            assert!(node
+                .consensus
+                .bft_round_state
+                .leader_reputation
+                .recently_timed_out(&timed_out_leader, 2));
+        }
     }
 
     #[test_log::test(tokio::test)]
@@ -2280,6 +2367,132 @@ pub mod test {
         assert_eq!(node3.consensus.bft_round_state.slot, 6);
     }
 
+    #[test_log::test(tokio::test)]
+    async fn state_sync_joining_validator_far_behind() {
+        let (mut node1, mut node2, mut node3): (
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+        ) = build_nodes!(3).await;
+
+        let mut node4 = ConsensusTestCtx::new_node("node-4").await;
+        node4.setup_for_joining(&[&node1, &node2, &node3]);
+        node4.consensus.bft_round_state.slot = 1;
+        node4.consensus.bft_round_state.parent_hash = ConsensusProposalHash("genesis".to_string());
+
+        // Slots 1 to 12 get committed while node4 is away
+        let mut last_cp = ConsensusProposal::default();
+        for slot in 1..=12 {
+            let timestamp = TimestampMs(1000 * slot as u128);
+            let (cp, ..) = match (slot - 1) % 3 {
+                0 => {
+                    node1.start_round_at(timestamp).await;
+                    simple_commit_round! { leader: node1, followers: [node2, node3] }
+                }
+                1 => {
+                    node2.start_round_at(timestamp).await;
+                    simple_commit_round! { leader: node2, followers: [node1, node3] }
+                }
+                _ => {
+                    node3.start_round_at(timestamp).await;
+                    simple_commit_round! { leader: node3, followers: [node1, node2] }
+                }
+            };
+            assert_eq!(cp.slot, slot);
+            last_cp = cp;
+        }
+
+        // Slot 13: node4 receives the prepare, and asks for the committed proposals in bulk
+        node1.start_round_at(TimestampMs(13_000)).await;
+        let prepare = broadcast! {
+            description: "Leader - Prepare",
+            from: node1, to: [node2, node3, node4],
+            message_matches: ConsensusNetMessage::Prepare(cp, ..) => {
+                assert_eq!(cp.slot, 13);
+            }
+        };
+
+        let state_sync_request = node4
+            .assert_send(&node1.validator_pubkey(), "StateSyncRequest")
+            .await;
+        assert_matches!(
+            state_sync_request.msg,
+            ConsensusNetMessage::StateSyncRequest(1, 12)
+        );
+
+        // The same gap is only requested once
+        node4.handle_msg(&prepare, "Prepare again").await;
+        assert!(node4.out_receiver.try_recv().is_err());
+        node1
+            .handle_msg(&state_sync_request, "StateSyncRequest")
+            .await;
+
+        let state_sync_reply = node1
+            .assert_send(&node4.validator_pubkey(), "StateSyncReply")
+            .await;
+        assert_matches!(
+            &state_sync_reply.msg,
+            ConsensusNetMessage::StateSyncReply(committed) if committed.len() == 12
+        );
+        node4.handle_msg(&state_sync_reply, "StateSyncReply").await;
+
+        // Fast-forwarded, but still waiting for the blocks before voting again
+        assert!(node4.is_joining());
+        assert_eq!(node4.consensus.bft_round_state.slot, 13);
+        assert_eq!(
+            node4.consensus.bft_round_state.parent_hash,
+            last_cp.hashed()
+        );
+
+        node4
+            .handle_node_state_event(NodeStateEvent::NewBlock(Box::new(Block {
+                block_height: BlockHeight(11),
+                ..Default::default()
+            })))
+            .await
+            .unwrap();
+        assert!(node4.is_joining());
+        assert_eq!(node4.consensus.bft_round_state.slot, 13);
+
+        node4
+            .handle_node_state_event(NodeStateEvent::NewBlock(Box::new(Block {
+                block_height: BlockHeight(12),
+                hash: last_cp.hashed(),
+                ..Default::default()
+            })))
+            .await
+            .unwrap();
+
+        // The buffered prepare for slot 13 got processed
+        assert!(!node4.is_joining());
+        assert_eq!(node4.consensus.bft_round_state.current_proposal.slot, 13);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn state_sync_rejects_invalid_commit_qc() {
+        let (mut node1, mut node2): (ConsensusTestCtx, ConsensusTestCtx) = build_nodes!(2).await;
+
+        node1.start_round_at(TimestampMs(1000)).await;
+        let (cp, ..) = simple_commit_round! { leader: node1, followers: [node2] };
+
+        let mut node3 = ConsensusTestCtx::new_node("node-3").await;
+        node3.setup_for_joining(&[&node1, &node2]);
+        node3.consensus.bft_round_state.slot = 1;
+        node3.consensus.bft_round_state.parent_hash = ConsensusProposalHash("genesis".to_string());
+
+        let forged_reply = node1
+            .consensus
+            .sign_net_message(ConsensusNetMessage::StateSyncReply(vec![(
+                cp,
+                QuorumCertificate(AggregateSignature::default(), ConfirmAckMarker),
+            )]))
+            .unwrap();
+        node3.handle_msg_err(&forged_reply).await;
+
+        assert!(node3.is_joining());
+        assert_eq!(node3.consensus.bft_round_state.slot, 1);
+    }
+
     bus_client! {
         struct TestBC {
             sender(Query<QueryConsensusInfo, ConsensusInfo>),
@@ -0,0 +1,322 @@
+//! Leader election policies.
+//!
+//! Every policy only relies on data all validators following the consensus share (staking, slot, view,
+//! and the timed out leaders recorded in committed proposals), so that followers can verify the expected leader.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+use hyle_model::{Block, ConsensusProposal, Slot, ValidatorPublicKey, View};
+use sha3::{Digest, Sha3_256};
+use staking::state::Staking;
+
+use crate::utils::conf::LeaderElectionPolicy;
+
+/// Number of slots during which a validator that timed out as leader is skipped
+pub const REPUTATION_WINDOW: Slot = 100;
+
+pub trait LeaderElection {
+    /// Returns the leader expected for the given slot and view.
+    fn leader(
+        &self,
+        staking: &Staking,
+        reputation: &LeaderReputation,
+        slot: Slot,
+        view: View,
+    ) -> Result<ValidatorPublicKey>;
+}
+
+impl LeaderElectionPolicy {
+    pub fn election(&self) -> &'static dyn LeaderElection {
+        match self {
+            LeaderElectionPolicy::RoundRobin => &RoundRobin,
+            LeaderElectionPolicy::StakeWeighted => &StakeWeighted,
+            LeaderElectionPolicy::Reputation => &ReputationBased,
+        }
+    }
+}
+
+/// Tracks the validators that timed out as leaders, as recorded in the proposals of processed blocks
+#[derive(BorshSerialize, BorshDeserialize, Default, Debug, Clone)]
+pub struct LeaderReputation {
+    last_timeouts: BTreeMap<ValidatorPublicKey, Slot>,
+}
+
+impl LeaderReputation {
+    /// Rebuilds the reputation from committed proposals, in slot order.
+    pub fn from_committed<'a>(proposals: impl Iterator<Item = &'a ConsensusProposal>) -> Self {
+        let mut reputation = Self::default();
+        proposals.for_each(|proposal| reputation.record_committed(proposal));
+        reputation
+    }
+
+    pub fn record_committed(&mut self, proposal: &ConsensusProposal) {
+        for leader in proposal.timed_out_leaders.iter() {
+            self.record_timeout(leader.clone(), proposal.slot);
+        }
+    }
+
+    pub fn record_block(&mut self, block: &Block) {
+        for leader in block.timed_out_leaders.iter() {
+            self.record_timeout(leader.clone(), block.block_height.0);
+        }
+    }
+
+    /// Blocks may be processed again, so an older timeout never overrides a more recent one
+    pub fn record_timeout(&mut self, leader: ValidatorPublicKey, slot: Slot) {
+        let last_timeout = self.last_timeouts.entry(leader).or_default();
+        *last_timeout = (*last_timeout).max(slot);
+        self.last_timeouts
+            .retain(|_, timeout_slot| *timeout_slot + REPUTATION_WINDOW >= slot);
+    }
+
+    /// Timeouts of the current slot are not taken into account, so that the leaders
+    /// of the next views stay the same whatever the order timeout certificates are processed in.
+    pub fn recently_timed_out(&self, validator: &ValidatorPublicKey, slot: Slot) -> bool {
+        self.last_timeouts
+            .get(validator)
+            .is_some_and(|timeout_slot| {
+                *timeout_slot < slot && timeout_slot + REPUTATION_WINDOW >= slot
+            })
+    }
+}
+
+/// Each bonded validator leads in turn.
+pub struct RoundRobin;
+
+impl LeaderElection for RoundRobin {
+    fn leader(
+        &self,
+        staking: &Staking,
+        _reputation: &LeaderReputation,
+        slot: Slot,
+        view: View,
+    ) -> Result<ValidatorPublicKey> {
+        // (we remove 1 for backwards compatibility of the tests when making the change)
+        let index = (slot as usize + view as usize).wrapping_sub(1) % staking.bonded().len().max(1);
+
+        staking
+            .bonded()
+            .get(index)
+            .context("No next leader found")
+            .cloned()
+    }
+}
+
+/// Validators lead a share of the slots proportional to their stake.
+pub struct StakeWeighted;
+
+impl LeaderElection for StakeWeighted {
+    fn leader(
+        &self,
+        staking: &Staking,
+        _reputation: &LeaderReputation,
+        slot: Slot,
+        view: View,
+    ) -> Result<ValidatorPublicKey> {
+        weighted_pick(staking, staking.bonded().iter(), slot, view)
+    }
+}
+
+/// Stake-weighted election, skipping validators that recently timed out as leaders.
+/// If all of them did, falls back to all bonded validators.
+pub struct ReputationBased;
+
+impl LeaderElection for ReputationBased {
+    fn leader(
+        &self,
+        staking: &Staking,
+        reputation: &LeaderReputation,
+        slot: Slot,
+        view: View,
+    ) -> Result<ValidatorPublicKey> {
+        let reputable: Vec<&ValidatorPublicKey> = staking
+            .bonded()
+            .iter()
+            .filter(|validator| !reputation.recently_timed_out(validator, slot))
+            .collect();
+
+        if reputable.is_empty() {
+            return weighted_pick(staking, staking.bonded().iter(), slot, view);
+        }
+        weighted_pick(staking, reputable.into_iter(), slot, view)
+    }
+}
+
+/// Deterministically picks a validator among the candidates, with a probability proportional to its stake.
+fn weighted_pick<'a>(
+    staking: &Staking,
+    candidates: impl Iterator<Item = &'a ValidatorPublicKey> + Clone,
+    slot: Slot,
+    view: View,
+) -> Result<ValidatorPublicKey> {
+    let total_stake: u128 = candidates
+        .clone()
+        .map(|validator| staking.get_stake(validator).unwrap_or(0))
+        .sum();
+    if total_stake == 0 {
+        anyhow::bail!("No staked validator to elect as leader");
+    }
+
+    let mut hasher = Sha3_256::new();
+    hasher.update(slot.to_le_bytes());
+    hasher.update(view.to_le_bytes());
+    let seed: [u8; 16] = hasher
+        .finalize()
+        .get(..16)
+        .and_then(|bytes| bytes.try_into().ok())
+        .context("Hash too short")?;
+    let mut target = u128::from_le_bytes(seed) % total_stake;
+
+    for validator in candidates {
+        let stake = staking.get_stake(validator).unwrap_or(0);
+        if target < stake {
+            return Ok(validator.clone());
+        }
+        target -= stake;
+    }
+    anyhow::bail!("No next leader found")
+}
+
+#[cfg(test)]
+#[allow(clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use hyle_model::BlockHeight;
+
+    fn staking_with(stakes: &[(&str, u128)]) -> (Staking, Vec<ValidatorPublicKey>) {
+        let mut staking = Staking::new();
+        let mut validators = vec![];
+        for (name, amount) in stakes {
+            let pubkey = ValidatorPublicKey(name.as_bytes().to_vec());
+            staking.stake((*name).into(), *amount).unwrap();
+            staking.delegate_to((*name).into(), pubkey.clone()).unwrap();
+            staking.bond(pubkey.clone()).unwrap();
+            validators.push(pubkey);
+        }
+        (staking, validators)
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let (staking, _) = staking_with(&[("a", 100), ("b", 100), ("c", 100)]);
+        let reputation = LeaderReputation::default();
+
+        let leaders: Vec<ValidatorPublicKey> = (1..=4)
+            .map(|slot| RoundRobin.leader(&staking, &reputation, slot, 0).unwrap())
+            .collect();
+        let bonded = staking.bonded();
+        assert_eq!(
+            leaders,
+            vec![
+                bonded[0].clone(),
+                bonded[1].clone(),
+                bonded[2].clone(),
+                bonded[0].clone()
+            ]
+        );
+        assert_eq!(
+            RoundRobin.leader(&staking, &reputation, 1, 1).unwrap(),
+            bonded[1]
+        );
+    }
+
+    #[test]
+    fn test_stake_weighted_is_deterministic_and_proportional() {
+        let (staking, validators) = staking_with(&[("a", 100), ("b", 300)]);
+        let reputation = LeaderReputation::default();
+
+        let mut led_by_b = 0;
+        for slot in 1..=1000 {
+            let leader = StakeWeighted
+                .leader(&staking, &reputation, slot, 0)
+                .unwrap();
+            assert_eq!(
+                leader,
+                StakeWeighted
+                    .leader(&staking, &reputation, slot, 0)
+                    .unwrap()
+            );
+            if leader == validators[1] {
+                led_by_b += 1;
+            }
+        }
+        assert!((650..850).contains(&led_by_b), "b led {led_by_b} slots");
+    }
+
+    #[test]
+    fn test_reputation_skips_validators_that_timed_out() {
+        let (staking, validators) = staking_with(&[("a", 100), ("b", 100), ("c", 100)]);
+        let mut reputation = LeaderReputation::default();
+        reputation.record_timeout(validators[1].clone(), 10);
+
+        // Not skipped during the slot it timed out in
+        assert!(!reputation.recently_timed_out(&validators[1], 10));
+
+        for slot in 11..=10 + REPUTATION_WINDOW {
+            for view in 0..3 {
+                assert_ne!(
+                    ReputationBased
+                        .leader(&staking, &reputation, slot, view)
+                        .unwrap(),
+                    validators[1]
+                );
+            }
+        }
+        assert!(!reputation.recently_timed_out(&validators[1], 11 + REPUTATION_WINDOW));
+    }
+
+    #[test]
+    fn test_reputation_is_rebuilt_from_committed_proposals() {
+        let (_, validators) = staking_with(&[("a", 100), ("b", 100)]);
+        let proposals = [
+            ConsensusProposal {
+                slot: 10,
+                timed_out_leaders: vec![validators[0].clone()],
+                ..ConsensusProposal::default()
+            },
+            ConsensusProposal {
+                slot: 11,
+                ..ConsensusProposal::default()
+            },
+        ];
+
+        let reputation = LeaderReputation::from_committed(proposals.iter());
+
+        assert!(reputation.recently_timed_out(&validators[0], 11));
+        assert!(!reputation.recently_timed_out(&validators[1], 11));
+    }
+
+    #[test]
+    fn test_reputation_ignores_blocks_processed_again() {
+        let (_, validators) = staking_with(&[("a", 100), ("b", 100)]);
+        let block = |height, timed_out_leaders| Block {
+            block_height: BlockHeight(height),
+            timed_out_leaders,
+            ..Block::default()
+        };
+        let mut reputation = LeaderReputation::default();
+        reputation.record_block(&block(10, vec![validators[0].clone()]));
+        reputation.record_block(&block(50, vec![validators[0].clone()]));
+        reputation.record_block(&block(10, vec![validators[0].clone()]));
+
+        assert!(reputation.recently_timed_out(&validators[0], 11 + REPUTATION_WINDOW));
+        assert!(!reputation.recently_timed_out(&validators[1], 51));
+    }
+
+    #[test]
+    fn test_reputation_falls_back_when_all_timed_out() {
+        let (staking, validators) = staking_with(&[("a", 100), ("b", 100)]);
+        let mut reputation = LeaderReputation::default();
+        reputation.record_timeout(validators[0].clone(), 10);
+        reputation.record_timeout(validators[1].clone(), 10);
+
+        assert_eq!(
+            ReputationBased
+                .leader(&staking, &reputation, 11, 0)
+                .unwrap(),
+            StakeWeighted.leader(&staking, &reputation, 11, 0).unwrap()
+        );
+    }
+}
@@ -27,6 +27,10 @@ pub struct ConsensusMetrics {
     pub on_sync_request_err: Counter<u64>,
     pub on_sync_reply_ok: Counter<u64>,
     pub on_sync_reply_err: Counter<u64>,
+    pub on_state_sync_request_ok: Counter<u64>,
+    pub on_state_sync_request_err: Counter<u64>,
+    pub on_state_sync_reply_ok: Counter<u64>,
+    pub on_state_sync_reply_err: Counter<u64>,
 }
 
 macro_rules! build {
@@ -61,6 +65,10 @@ impl ConsensusMetrics {
             on_sync_request_err: build!(my_meter, counter, "on_sync_request_err"),
             on_sync_reply_ok: build!(my_meter, counter, "on_sync_reply_ok"),
             on_sync_reply_err: build!(my_meter, counter, "on_sync_reply_err"),
+            on_state_sync_request_ok: build!(my_meter, counter, "on_state_sync_request_ok"),
+            on_state_sync_request_err: build!(my_meter, counter, "on_state_sync_request_err"),
+            on_state_sync_reply_ok: build!(my_meter, counter, "on_state_sync_reply_ok"),
+            on_state_sync_reply_err: build!(my_meter, counter, "on_state_sync_reply_err"),
         }
     }
 
@@ -4,8 +4,8 @@ use hyle_modules::{bus::SharedMessageBus, log_error, modules::Module};
 use crate::model::SharedRunContext;
 
 use super::{
-    api, consensus_bus_client::ConsensusBusClient, metrics::ConsensusMetrics, Consensus,
-    ConsensusStore,
+    api, consensus_bus_client::ConsensusBusClient, leader_election::LeaderReputation,
+    metrics::ConsensusMetrics, Consensus, ConsensusStore,
 };
 
 impl Module for Consensus {
@@ -13,7 +13,9 @@ impl Module for Consensus {
 
     async fn build(bus: SharedMessageBus, ctx: Self::Context) -> Result<Self> {
         let file = ctx.config.data_directory.clone().join("consensus.bin");
-        let store: ConsensusStore = Self::load_from_disk_or_default(file.as_path());
+        let mut store: ConsensusStore = Self::load_from_disk_or_default(file.as_path());
+        store.bft_round_state.leader_reputation =
+            LeaderReputation::from_committed(store.committed_history.proposals());
         let metrics = ConsensusMetrics::global(ctx.config.id.clone());
 
         let api = api::api(&bus, &ctx).await;
@@ -149,6 +149,9 @@ pub enum ConsensusNetMessage {
     ValidatorCandidacy(SignedByValidator<ValidatorCandidacy>),
     SyncRequest(ConsensusProposalHash),
     SyncReply((ValidatorPublicKey, ConsensusProposal, Ticket, View)),
+    /// Asks for the committed proposals from the first slot to the second one (inclusive)
+    StateSyncRequest(Slot, Slot),
+    StateSyncReply(Vec<(ConsensusProposal, CommitQC)>),
 }
 
 impl<T> Hashed<QuorumCertificateHash> for QuorumCertificate<T> {
@@ -266,6 +269,16 @@ impl Display for ConsensusNetMessage {
                     "{enum_variant} sender: {sender}, CP: {consensus_proposal}, ticket: {ticket}, view: {view}"
                 )
             }
+            ConsensusNetMessage::StateSyncRequest(from, to) => {
+                write!(f, "{enum_variant} (slots {from} to {to})")
+            }
+            ConsensusNetMessage::StateSyncReply(committed_proposals) => {
+                _ = write!(f, "{enum_variant} slots ");
+                for (cp, _) in committed_proposals.iter() {
+                    _ = write!(f, "{},", cp.slot);
+                }
+                write!(f, "")
+            }
         }
     }
 }
@@ -309,6 +322,16 @@ impl IntoHeaderSignableData for ConsensusNetMessage {
                 v,
             ))
             .unwrap_or_default(),
+            ConsensusNetMessage::StateSyncRequest(from, to) => {
+                borsh::to_vec(&(from, to)).unwrap_or_default()
+            }
+            ConsensusNetMessage::StateSyncReply(committed_proposals) => borsh::to_vec(
+                &committed_proposals
+                    .iter()
+                    .map(|(cp, qc)| (cp.hashed(), &qc.signature))
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap_or_default(),
         })
     }
 }
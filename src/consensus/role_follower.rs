@@ -3,7 +3,7 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use std::collections::BTreeMap;
 use tracing::{debug, info, trace, warn};
 
-use super::*;
+use super::{role_sync::STATE_SYNC_MIN_GAP, *};
 use crate::{
     bus::BusClientSender,
     consensus::StateTag,
@@ -55,12 +55,18 @@ impl Consensus {
                 && (consensus_proposal.slot == self.bft_round_state.slot + 1
                     || consensus_proposal.slot == self.bft_round_state.slot
                         && view > self.bft_round_state.view);
-            if prepare_follows_commit || is_for_current_slot {
+            // - we committed through state sync - wait until the matching blocks are processed.
+            if (prepare_follows_commit || is_for_current_slot)
+                && self.state_synced_blocks_processed()
+            {
                 info!(
                     "Received Prepare message for next slot while joining. Exiting joining mode."
                 );
                 self.bft_round_state.state_tag = StateTag::Follower;
             } else {
+                if consensus_proposal.slot > self.bft_round_state.slot + STATE_SYNC_MIN_GAP {
+                    self.request_state_sync(sender.clone(), consensus_proposal.slot)?;
+                }
                 self.follower_state().buffered_prepares.push((
                     sender.clone(),
                     consensus_proposal,
@@ -151,6 +157,8 @@ impl Consensus {
 
         self.verify_timestamp(&consensus_proposal)?;
 
+        self.verify_timed_out_leaders(&consensus_proposal, view)?;
+
         // At this point we are OK with this new consensus proposal, update locally and vote.
         self.bft_round_state.current_proposal = consensus_proposal.clone();
         let cp_hash = self.bft_round_state.current_proposal.hashed();
@@ -661,6 +669,7 @@ impl Consensus {
         view: View,
     ) -> Result<()> {
         let mut missing_dp_hash = consensus_proposal.parent_hash.clone();
+        let slot = consensus_proposal.slot;
 
         // Buffer this prepare if we don't know one.
         if !self
@@ -674,9 +683,13 @@ impl Consensus {
                 .push(prepare_message);
         }
 
+        // Too far behind to fetch the missing prepares one by one, catch up in bulk instead.
+        if slot > self.bft_round_state.slot + STATE_SYNC_MIN_GAP {
+            return self.request_state_sync(sender, slot);
+        }
+
         // Check if we have a missing DP up to our current known DP (this assumes we're not on a fork)
         let current_dp_hash = self.bft_round_state.current_proposal.hashed();
-        // TODO: we should switch back to joining if we try to catch up on too many prepares.
         while let Some(prep) = self
             .follower_state()
             .buffered_prepares
@@ -773,6 +786,23 @@ impl Consensus {
         Ok(TicketVerifyAndProcess::NotProcessed)
     }
 
+    /// A proposal lists the leaders of the views before the one it was first proposed in.
+    /// Proposals re-proposed after a timeout keep their original list, hence the prefix check.
+    fn verify_timed_out_leaders(&self, proposal: &ConsensusProposal, view: View) -> Result<()> {
+        let timed_out_leaders = &proposal.timed_out_leaders;
+        if timed_out_leaders.len() > view as usize
+            || *timed_out_leaders != self.timed_out_leaders(timed_out_leaders.len() as View)?
+        {
+            bail!(
+                "Proposal for slot {} view {} lists wrong timed out leaders: {:?}",
+                proposal.slot,
+                view,
+                timed_out_leaders
+            );
+        }
+        Ok(())
+    }
+
     fn verify_staking_actions(&mut self, proposal: &ConsensusProposal) -> Result<()> {
         for action in &proposal.staking_actions {
             match action {
@@ -863,7 +893,7 @@ impl BufferedPrepares {
         self.prepares.get(proposal_hash)
     }
 
-    fn next_prepare(&self, proposal_hash: ConsensusProposalHash) -> Option<Prepare> {
+    pub(super) fn next_prepare(&self, proposal_hash: ConsensusProposalHash) -> Option<Prepare> {
         self.children
             .get(&proposal_hash)
             .and_then(|children| self.prepares.get(children).cloned())
@@ -167,6 +167,7 @@ impl Consensus {
                 staking_actions,
                 timestamp: current_timestamp,
                 parent_hash: self.bft_round_state.parent_hash.clone(),
+                timed_out_leaders: self.timed_out_leaders(self.bft_round_state.view)?,
             };
         }
         self.bft_round_state.leader.step = Step::PrepareVote;
@@ -1,8 +1,68 @@
-use anyhow::Result;
-use tracing::debug;
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+use tracing::{debug, info};
 
 use super::{role_follower::Prepare, *};
-use hyle_model::{ConsensusProposalHash, ValidatorPublicKey};
+use hyle_model::{BlockHeight, ConsensusProposal, ConsensusProposalHash, Slot, ValidatorPublicKey};
+
+/// A validator lagging more than this number of slots behind catches up through a state sync
+/// instead of fetching the missing prepares one by one.
+pub(super) const STATE_SYNC_MIN_GAP: Slot = 10;
+/// Maximum number of committed proposals sent in a single StateSyncReply
+const STATE_SYNC_BATCH_SIZE: usize = 100;
+
+/// Latest committed proposals, with the commit QC that committed them
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub(super) struct CommittedHistory {
+    committed: BTreeMap<Slot, (ConsensusProposal, CommitQC)>,
+}
+
+impl CommittedHistory {
+    /// Records a committed proposal, keeping at most `max_size` proposals
+    pub(super) fn push(
+        &mut self,
+        consensus_proposal: ConsensusProposal,
+        commit_qc: CommitQC,
+        max_size: usize,
+    ) {
+        self.committed
+            .insert(consensus_proposal.slot, (consensus_proposal, commit_qc));
+        while self.committed.len() > max_size {
+            self.committed.pop_first();
+        }
+    }
+
+    /// Returns the contiguous committed proposals starting at slot `from`, up to slot `to`.
+    fn range(&self, from: Slot, to: Slot) -> Vec<(ConsensusProposal, CommitQC)> {
+        let mut expected_slot = from;
+        self.committed
+            .range(from..=to)
+            .take_while(|(slot, _)| {
+                let contiguous = **slot == expected_slot;
+                expected_slot += 1;
+                contiguous
+            })
+            .take(STATE_SYNC_BATCH_SIZE)
+            .map(|(_, committed)| committed.clone())
+            .collect()
+    }
+
+    pub(super) fn proposals(&self) -> impl Iterator<Item = &ConsensusProposal> {
+        self.committed.values().map(|(proposal, _)| proposal)
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub(super) struct StateSyncState {
+    /// Slot we are catching up to
+    target: Option<Slot>,
+    /// Last slot committed through state sync, while its block was not processed yet
+    synced_to: Option<Slot>,
+    /// First slot and target of the pending request, so that a gap is only requested once
+    requested: Option<(Slot, Slot)>,
+}
 
 impl Consensus {
     /// When a validator receives a sync request from another validator, it will check if it has the prepare message in its buffer.
@@ -30,4 +90,174 @@ impl Consensus {
         let (sender, proposal, ticket, view) = prepare;
         self.on_prepare(sender, proposal, ticket, view)
     }
+
+    /// Asks a peer for all the committed proposals we miss before slot `target`.
+    pub(super) fn request_state_sync(
+        &mut self,
+        peer: ValidatorPublicKey,
+        target: Slot,
+    ) -> Result<()> {
+        let from = self.bft_round_state.slot;
+        if target <= from {
+            return Ok(());
+        }
+        let state_sync = &mut self.bft_round_state.state_sync;
+        state_sync.target = Some(state_sync.target.unwrap_or_default().max(target));
+
+        // Buffered prepares of the same gap don't trigger new requests. If we made no progress
+        // while the gap grew again, the request is considered lost and is sent again.
+        if let Some((requested_from, requested_target)) = state_sync.requested {
+            if requested_from == from && target <= requested_target + STATE_SYNC_MIN_GAP {
+                debug!(
+                    "State sync from slot {} to {} already requested",
+                    from, requested_target
+                );
+                return Ok(());
+            }
+        }
+        state_sync.requested = Some((from, target));
+
+        info!(
+            "🛰️ Requesting committed proposals from slot {} to {} to {}",
+            from,
+            target - 1,
+            peer
+        );
+        self.send_net_message(
+            peer,
+            ConsensusNetMessage::StateSyncRequest(from, target - 1),
+        )
+        .context("Sending StateSyncRequest")
+    }
+
+    /// Sends back the committed proposals we know of in the requested range, along with their commit QCs.
+    pub(super) fn on_state_sync_request(
+        &mut self,
+        sender: ValidatorPublicKey,
+        from: Slot,
+        to: Slot,
+    ) -> Result<()> {
+        debug!(
+            "Got state sync request from {} for slots {} to {}",
+            sender, from, to
+        );
+        let committed_proposals = self.committed_history.range(from, to);
+        if committed_proposals.is_empty() {
+            debug!(
+                "No committed proposal from slot {} to send to {}",
+                from, sender
+            );
+            return Ok(());
+        }
+        self.send_net_message(
+            sender,
+            ConsensusNetMessage::StateSyncReply(committed_proposals),
+        )
+    }
+
+    /// Commits the received proposals one after the other, verifying each commit QC against our staking
+    /// as it gets updated by the previous proposals.
+    /// We then stay in joining mode, without voting, until the matching blocks have been processed.
+    pub(super) fn on_state_sync_reply(
+        &mut self,
+        sender: ValidatorPublicKey,
+        committed_proposals: Vec<(ConsensusProposal, CommitQC)>,
+    ) -> Result<()> {
+        let was_joining = matches!(self.bft_round_state.state_tag, StateTag::Joining);
+        let mut synced_to = None;
+        let res = self.commit_synced_proposals(committed_proposals, &mut synced_to);
+
+        if let Some(synced_to) = synced_to {
+            info!("🛰️ Synchronized to slot {} through state sync", synced_to);
+            self.bft_round_state.state_sync.synced_to = Some(synced_to);
+        }
+        if was_joining || synced_to.is_some() {
+            self.bft_round_state.state_tag = StateTag::Joining;
+        }
+        res?;
+
+        match self.bft_round_state.state_sync.target {
+            Some(target) if target > self.bft_round_state.slot && synced_to.is_some() => {
+                // Fetch the next batch
+                self.request_state_sync(sender, target)
+            }
+            Some(target) if target <= self.bft_round_state.slot => {
+                self.bft_round_state.state_sync.target = None;
+                self.bft_round_state.state_sync.requested = None;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn commit_synced_proposals(
+        &mut self,
+        mut committed_proposals: Vec<(ConsensusProposal, CommitQC)>,
+        synced_to: &mut Option<Slot>,
+    ) -> Result<()> {
+        committed_proposals.sort_by_key(|(cp, _)| cp.slot);
+
+        for (consensus_proposal, commit_qc) in committed_proposals {
+            if consensus_proposal.slot < self.bft_round_state.slot {
+                continue;
+            }
+            if consensus_proposal.slot != self.bft_round_state.slot {
+                bail!(
+                    "State sync proposal for slot {} while at slot {}",
+                    consensus_proposal.slot,
+                    self.bft_round_state.slot
+                );
+            }
+            if consensus_proposal.parent_hash != self.bft_round_state.parent_hash {
+                bail!(
+                    "State sync proposal for slot {} does not follow our parent {}",
+                    consensus_proposal.slot,
+                    self.bft_round_state.parent_hash
+                );
+            }
+
+            let previous_proposal = std::mem::replace(
+                &mut self.bft_round_state.current_proposal,
+                consensus_proposal,
+            );
+            if let Err(e) =
+                self.verify_commit_quorum_certificate_against_current_proposal(&commit_qc)
+            {
+                self.bft_round_state.current_proposal = previous_proposal;
+                return Err(e.context("Verifying state sync commit QC"));
+            }
+
+            let slot = self.bft_round_state.slot;
+            self.emit_commit_event(&commit_qc)?;
+            // Tickets can only be applied once synchronized to the consensus
+            if matches!(self.bft_round_state.state_tag, StateTag::Joining) {
+                self.bft_round_state.state_tag = StateTag::Follower;
+            }
+            self.apply_ticket(Ticket::CommitQC(commit_qc))?;
+            *synced_to = Some(slot);
+        }
+        Ok(())
+    }
+
+    /// Whether all the blocks committed through state sync have been processed
+    pub(super) fn state_synced_blocks_processed(&self) -> bool {
+        self.bft_round_state
+            .state_sync
+            .synced_to
+            .is_none_or(|slot| self.bft_round_state.joining.staking_updated_to >= slot)
+    }
+
+    /// Forgets the slot committed through state sync once its block is processed,
+    /// returns whether there was one
+    pub(super) fn take_state_synced_slot(&mut self) -> bool {
+        self.bft_round_state.state_sync.synced_to.take().is_some()
+    }
+
+    /// Whether a block must not reset our joining state, as we already committed further through state sync
+    pub(super) fn is_behind_state_sync(&self, block_height: BlockHeight) -> bool {
+        self.bft_round_state
+            .state_sync
+            .synced_to
+            .is_some_and(|slot| block_height.0 < slot)
+    }
 }
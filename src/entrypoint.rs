@@ -6,7 +6,7 @@ use crate::{
     data_availability::DataAvailability,
     explorer::Explorer,
     genesis::Genesis,
-    indexer::Indexer,
+    indexer::{reindex::Reindexer, Indexer},
     mempool::Mempool,
     model::{api::NodeInfo, SharedRunContext},
     node_state::module::NodeStateModule,
@@ -162,6 +162,7 @@ pub fn welcome_message(conf: &conf::Conf) {
         data_directory = conf.data_directory.to_string_lossy(),
         validator_details = if matches!(conf.p2p.mode, P2pMode::FullValidator) {
             let timestamp_checks: &'static str = (&conf.consensus.timestamp_checks).into();
+            let leader_election: &'static str = (&conf.consensus.leader_election).into();
             let c_mode = if conf.consensus.solo {
                 "single"
             } else {
@@ -173,7 +174,9 @@ pub fn welcome_message(conf: &conf::Conf) {
             } else {
                 format!("| peers: [{}]", conf.p2p.peers.join(" ")).to_string()
             };
-            format!("{c_mode} | {sd}ms | timestamps: {timestamp_checks} {peers}")
+            format!(
+                "{c_mode} | {sd}ms | timestamps: {timestamp_checks} | leaders: {leader_election} {peers}"
+            )
         } else {
             "".to_string()
         },
@@ -254,48 +257,71 @@ async fn common_main(
 
     let mut handler = ModulesHandler::new(&bus).await;
 
-    if config.run_indexer {
+    // Last block applied by each contract state indexer
+    let mut indexer_checkpoints = vec![];
+
+    if config.reindex.is_some() {
+        handler.build_module::<Reindexer>(config.clone()).await?;
+    } else if config.run_indexer {
+        let ctx = ContractStateIndexerCtx {
+            contract_name: "hyllar".into(),
+            data_directory: config.data_directory.clone(),
+            api: build_api_ctx.clone(),
+            storage: config.indexer.contract_state_storage,
+        };
+        indexer_checkpoints.push(ctx.checkpoint::<Hyllar>()?);
         handler
-            .build_module::<ContractStateIndexer<Hyllar>>(ContractStateIndexerCtx {
-                contract_name: "hyllar".into(),
-                data_directory: config.data_directory.clone(),
-                api: build_api_ctx.clone(),
-            })
+            .build_module::<ContractStateIndexer<Hyllar>>(ctx)
             .await?;
+        let ctx = ContractStateIndexerCtx {
+            contract_name: "hyllar2".into(),
+            data_directory: config.data_directory.clone(),
+            api: build_api_ctx.clone(),
+            storage: config.indexer.contract_state_storage,
+        };
+        indexer_checkpoints.push(ctx.checkpoint::<Hyllar>()?);
         handler
-            .build_module::<ContractStateIndexer<Hyllar>>(ContractStateIndexerCtx {
-                contract_name: "hyllar2".into(),
-                data_directory: config.data_directory.clone(),
-                api: build_api_ctx.clone(),
-            })
+            .build_module::<ContractStateIndexer<Hyllar>>(ctx)
             .await?;
+        let ctx = ContractStateIndexerCtx {
+            contract_name: "hydentity".into(),
+            data_directory: config.data_directory.clone(),
+            api: build_api_ctx.clone(),
+            storage: config.indexer.contract_state_storage,
+        };
+        indexer_checkpoints.push(ctx.checkpoint::<Hydentity>()?);
         handler
-            .build_module::<ContractStateIndexer<Hydentity>>(ContractStateIndexerCtx {
-                contract_name: "hydentity".into(),
-                data_directory: config.data_directory.clone(),
-                api: build_api_ctx.clone(),
-            })
+            .build_module::<ContractStateIndexer<Hydentity>>(ctx)
             .await?;
+        let ctx = ContractStateIndexerCtx {
+            contract_name: "oranj".into(),
+            data_directory: config.data_directory.clone(),
+            api: build_api_ctx.clone(),
+            storage: config.indexer.contract_state_storage,
+        };
+        indexer_checkpoints.push(ctx.checkpoint::<AccountSMT>()?);
         handler
-            .build_module::<ContractStateIndexer<AccountSMT>>(ContractStateIndexerCtx {
-                contract_name: "oranj".into(),
-                data_directory: config.data_directory.clone(),
-                api: build_api_ctx.clone(),
-            })
+            .build_module::<ContractStateIndexer<AccountSMT>>(ctx)
             .await?;
+        let ctx = ContractStateIndexerCtx {
+            contract_name: "oxygen".into(),
+            data_directory: config.data_directory.clone(),
+            api: build_api_ctx.clone(),
+            storage: config.indexer.contract_state_storage,
+        };
+        indexer_checkpoints.push(ctx.checkpoint::<AccountSMT>()?);
         handler
-            .build_module::<ContractStateIndexer<AccountSMT>>(ContractStateIndexerCtx {
-                contract_name: "oxygen".into(),
-                data_directory: config.data_directory.clone(),
-                api: build_api_ctx.clone(),
-            })
+            .build_module::<ContractStateIndexer<AccountSMT>>(ctx)
             .await?;
+        let ctx = ContractStateIndexerCtx {
+            contract_name: "vitamin".into(),
+            data_directory: config.data_directory.clone(),
+            api: build_api_ctx.clone(),
+            storage: config.indexer.contract_state_storage,
+        };
+        indexer_checkpoints.push(ctx.checkpoint::<AccountSMT>()?);
         handler
-            .build_module::<ContractStateIndexer<AccountSMT>>(ContractStateIndexerCtx {
-                contract_name: "vitamin".into(),
-                data_directory: config.data_directory.clone(),
-                api: build_api_ctx.clone(),
-            })
+            .build_module::<ContractStateIndexer<AccountSMT>>(ctx)
             .await?;
         handler
             .build_module::<Indexer>((config.clone(), build_api_ctx.clone()))
@@ -345,12 +371,17 @@ async fn common_main(
         }
 
         handler.build_module::<P2P>(ctx.clone()).await?;
-    } else if config.run_indexer {
+    } else if config.run_indexer && config.reindex.is_none() {
         handler
             .build_module::<SignedDAListener>(DAListenerConf {
                 data_directory: config.data_directory.clone(),
                 da_read_from: config.da_read_from.clone(),
-                start_block: None,
+                // Resume after the oldest checkpoint, so that no contract state indexer misses a
+                // block. Blocks an indexer already applied are skipped by it.
+                start_block: indexer_checkpoints
+                    .iter()
+                    .map(|checkpoint| checkpoint.map(|height| height + 1).unwrap_or_default())
+                    .min(),
                 timeout_client_secs: config.da_timeout_client_secs,
             })
             .await?;
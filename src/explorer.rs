@@ -1,9 +1,14 @@
 //! Index system for historical data.
 
 pub mod api;
+pub mod blob_decoders;
+pub mod db;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod subscriptions;
 
 use crate::{model::*, utils::conf::SharedConf};
-use anyhow::{Context, Result};
+use anyhow::Result;
 use api::*;
 use axum::extract::ws::Message;
 use axum::{
@@ -15,9 +20,11 @@ use axum::{
     routing::get,
     Router,
 };
+use blob_decoders::BlobDecoders;
+use db::{with_db, IndexerDb};
 use futures::{SinkExt, StreamExt};
 use hyle_model::api::{
-    BlobWithStatus, TransactionStatusDb, TransactionTypeDb, TransactionWithBlobs,
+    BlobWithStatus, ExplorerWsEvent, TransactionStatusDb, TransactionTypeDb, TransactionWithBlobs,
 };
 use hyle_model::utils::TimestampMs;
 use hyle_modules::bus::BusMessage;
@@ -28,9 +35,8 @@ use hyle_modules::{
     modules::{module_bus_client, Module, SharedBuildApiCtx},
 };
 use hyle_net::logged_task::logged_task;
-use sqlx::Row;
-use sqlx::{postgres::PgPoolOptions, PgPool, Pool, Postgres};
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::select;
 use tokio::sync::{broadcast, mpsc};
 use tracing::{error, info};
@@ -42,10 +48,12 @@ module_bus_client! {
 #[derive(Debug)]
 struct ExplorerBusClient {
     receiver(WsExplorerBlobTx),
+    receiver(WsExplorerBlock),
 }
 }
 
 impl BusMessage for WsExplorerBlobTx {}
+impl BusMessage for WsExplorerBlock {}
 
 #[derive(Debug, Clone)]
 pub struct WsExplorerBlobTx {
@@ -59,13 +67,19 @@ pub struct WsExplorerBlobTx {
     pub timestamp: Option<TimestampMs>,
 }
 
+/// Block sent by the indexer once stored, to notify the websocket subscribers
+#[derive(Debug, Clone)]
+pub struct WsExplorerBlock(pub Arc<Block>);
+
 // TODO: generalize for all tx types
 type Subscribers = HashMap<ContractName, Vec<broadcast::Sender<TransactionWithBlobs>>>;
 
 #[derive(Debug, Clone)]
 pub struct ExplorerApiState {
-    db: PgPool,
+    db: IndexerDb,
+    blob_decoders: Arc<BlobDecoders>,
     new_sub_sender: mpsc::Sender<(ContractName, WebSocket)>,
+    ws_events: broadcast::Sender<Arc<ExplorerWsEvent>>,
 }
 
 #[derive(Debug)]
@@ -77,33 +91,34 @@ pub struct Explorer {
 }
 
 impl Explorer {
-    pub async fn new(bus: SharedMessageBus, db: Pool<Postgres>) -> Self {
+    pub async fn new(bus: SharedMessageBus, db: IndexerDb, blob_decoders: BlobDecoders) -> Self {
         let (new_sub_sender, new_sub_receiver) = tokio::sync::mpsc::channel(100);
+        let (ws_events, _) = broadcast::channel(subscriptions::WS_EVENTS_CAPACITY);
         Self {
             bus: ExplorerBusClient::new_from_bus(bus.new_handle()).await,
-            state: ExplorerApiState { db, new_sub_sender },
+            state: ExplorerApiState {
+                db,
+                blob_decoders: Arc::new(blob_decoders),
+                new_sub_sender,
+                ws_events,
+            },
             new_sub_receiver,
             subscribers: HashMap::new(),
         }
     }
 }
 
-pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./src/indexer/migrations");
-
 impl Module for Explorer {
     type Context = (SharedConf, SharedBuildApiCtx);
 
     async fn build(bus: SharedMessageBus, ctx: Self::Context) -> Result<Self> {
-        let pool = PgPoolOptions::new()
-            .max_connections(20)
-            .acquire_timeout(std::time::Duration::from_secs(1))
-            .connect(&ctx.0.database_url)
-            .await
-            .context("Failed to connect to the database")?;
+        let db = IndexerDb::connect(&ctx.0.database_url).await?;
+        db.migrate().await?;
 
-        tokio::time::timeout(tokio::time::Duration::from_secs(60), MIGRATOR.run(&pool)).await??;
+        let mut blob_decoders = BlobDecoders::with_builtins();
+        blob_decoders.register_schemas(&ctx.0.indexer.blob_schemas);
 
-        let explorer = Explorer::new(bus, pool).await;
+        let explorer = Explorer::new(bus, db, blob_decoders).await;
 
         if let Ok(mut guard) = ctx.1.openapi.lock() {
             tracing::info!("Adding OpenAPI for Indexer");
@@ -137,6 +152,13 @@ impl Explorer {
                     info
                 );
             }
+            listen<WsExplorerBlock> WsExplorerBlock(block) => {
+                if self.state.ws_events.receiver_count() > 0 {
+                    for event in subscriptions::block_events(&block) {
+                        self.publish_ws_event(event);
+                    }
+                }
+            }
 
             Some((contract_name, socket)) = self.new_sub_receiver.recv() => {
 
@@ -192,13 +214,12 @@ impl Explorer {
     }
 
     pub async fn get_last_block(&self) -> Result<Option<BlockHeight>> {
-        let rows = sqlx::query("SELECT max(height) as max FROM blocks")
-            .fetch_one(&self.state.db)
-            .await?;
-        Ok(rows
-            .try_get("max")
-            .map(|m: i64| Some(BlockHeight(m as u64)))
-            .unwrap_or(None))
+        let max: Option<i64> = with_db!(&self.state.db, |pool| sqlx::query_scalar(
+            "SELECT max(height) as max FROM blocks"
+        )
+        .fetch_one(pool)
+        .await?);
+        Ok(max.map(|m| BlockHeight(m as u64)))
     }
 
     pub fn api(&self, ctx: Option<&SharedBuildApiCtx>) -> Router<()> {
@@ -209,6 +230,7 @@ impl Explorer {
             // stats
             .routes(routes!(api::get_stats))
             .routes(routes!(api::get_proof_stats))
+            .routes(routes!(api::get_contract_settlement_stats))
             // block
             .routes(routes!(api::get_blocks))
             .routes(routes!(api::get_last_block))
@@ -217,7 +239,10 @@ impl Explorer {
             // transaction
             .routes(routes!(api::get_transactions))
             .routes(routes!(api::get_transactions_by_height))
+            .routes(routes!(api::get_transactions_by_data_proposal))
             .routes(routes!(api::get_transactions_by_contract))
+            .routes(routes!(api::get_transactions_by_identity))
+            .routes(routes!(api::get_identity_summary))
             .routes(routes!(api::get_transaction_with_hash))
             .routes(routes!(api::get_transaction_events))
             .routes(routes!(api::get_blob_transactions_by_contract))
@@ -225,6 +250,7 @@ impl Explorer {
                 "/blob_transactions/contract/{contract_name}/ws",
                 get(Self::get_blob_transactions_by_contract_ws_handler),
             )
+            .route("/ws", get(Self::ws_handler))
             // proof transaction
             .routes(routes!(api::get_proofs))
             .routes(routes!(api::get_proofs_by_height))
@@ -235,7 +261,12 @@ impl Explorer {
             // contract
             .routes(routes!(api::list_contracts))
             .routes(routes!(api::get_contract))
+            .routes(routes!(api::get_contract_schema))
             .routes(routes!(api::get_contract_state_by_height))
+            .routes(routes!(api::get_contract_state_transitions))
+            .routes(routes!(api::get_contract_state_diff))
+            // search
+            .routes(routes!(api::search))
             .split_for_parts();
 
         if let Some(ctx) = ctx {
@@ -244,9 +275,20 @@ impl Explorer {
             }
         }
 
+        #[cfg(feature = "graphql")]
+        let router = router.merge(graphql::routes(&self.state));
+
         router.with_state(self.state.clone())
     }
 
+    async fn ws_handler(
+        ws: WebSocketUpgrade,
+        State(state): State<ExplorerApiState>,
+    ) -> impl IntoResponse {
+        let events = state.ws_events.subscribe();
+        ws.on_upgrade(move |socket| subscriptions::handle_socket(socket, events))
+    }
+
     async fn get_blob_transactions_by_contract_ws_handler(
         ws: WebSocketUpgrade,
         Path(contract_name): Path<String>,
@@ -268,6 +310,11 @@ impl Explorer {
             .await;
     }
 
+    fn publish_ws_event(&self, event: ExplorerWsEvent) {
+        // Fails only when no client is connected
+        let _ = self.state.ws_events.send(Arc::new(event));
+    }
+
     fn send_blob_transaction_to_websocket_subscribers(&self, info: WsExplorerBlobTx) {
         let WsExplorerBlobTx {
             tx,
@@ -280,43 +327,56 @@ impl Explorer {
             timestamp,
         } = info;
 
+        let enriched_tx = TransactionWithBlobs {
+            tx_hash: tx_hash.0.clone(),
+            parent_dp_hash: dp_hash.0.clone(),
+            block_hash: block_hash.clone(),
+            index,
+            version,
+            transaction_type: TransactionTypeDb::BlobTransaction,
+            transaction_status: TransactionStatusDb::Sequenced,
+            lane_id: lane_id.clone(),
+            timestamp: timestamp.clone(),
+            identity: tx.identity.0.clone(),
+            blobs: tx
+                .blobs
+                .iter()
+                .map(|blob| BlobWithStatus {
+                    contract_name: blob.contract_name.0.clone(),
+                    data: blob.data.0.clone(),
+                    // The program id is not known here, only decoders registered by name apply
+                    decoded_data: self.state.blob_decoders.decode(
+                        &blob.contract_name,
+                        None,
+                        &blob.data.0,
+                    ),
+                    proof_outputs: vec![],
+                })
+                .collect(),
+        };
+
         for (contrat_name, senders) in self.subscribers.iter() {
             if tx
                 .blobs
                 .iter()
                 .any(|blob| &blob.contract_name == contrat_name)
             {
-                let enriched_tx = TransactionWithBlobs {
-                    tx_hash: tx_hash.0.clone(),
-                    parent_dp_hash: dp_hash.0.clone(),
-                    block_hash: block_hash.clone(),
-                    index,
-                    version,
-                    transaction_type: TransactionTypeDb::BlobTransaction,
-                    transaction_status: TransactionStatusDb::Sequenced,
-                    lane_id: lane_id.clone(),
-                    timestamp: timestamp.clone(),
-                    identity: tx.identity.0.clone(),
-                    blobs: tx
-                        .blobs
-                        .iter()
-                        .map(|blob| BlobWithStatus {
-                            contract_name: blob.contract_name.0.clone(),
-                            data: blob.data.0.clone(),
-                            proof_outputs: vec![],
-                        })
-                        .collect(),
-                };
                 senders.iter().for_each(|sender| {
                     let _ = sender.send(enriched_tx.clone());
                 });
             }
         }
+
+        if self.state.ws_events.receiver_count() > 0 {
+            self.publish_ws_event(ExplorerWsEvent::Transaction {
+                transaction: enriched_tx,
+            });
+        }
     }
 }
 
 impl std::ops::Deref for Explorer {
-    type Target = Pool<Postgres>;
+    type Target = IndexerDb;
 
     fn deref(&self) -> &Self::Target {
         &self.state.db
@@ -1,4 +1,5 @@
-use super::{ExplorerApiState, TxHashDb};
+use super::{with_db, ExplorerApiState, TxHashDb};
+use crate::explorer::{blob_decoders::BlobDecoders, db::IndexerDb};
 use api::APIBlob;
 use axum::{
     extract::{Path, State},
@@ -17,20 +18,36 @@ pub struct BlobDb {
     pub identity: String,  // Identity of the blob
     pub contract_name: String, // Contract name associated with the blob
     pub data: Vec<u8>,     // Actual blob data
+    pub program_id: Option<Vec<u8>>, // Program id of the contract, if still registered
+    #[sqlx(json)]
     pub proof_outputs: Vec<serde_json::Value>, // outputs of proofs
     pub verified: bool,    // Verification status
 }
 
-impl From<BlobDb> for APIBlob {
-    fn from(value: BlobDb) -> Self {
+/// Aggregates the proof outputs of a blob, joined as `blob_proof_outputs`, into a json array.
+pub(crate) fn proof_outputs_agg(db: &IndexerDb) -> &'static str {
+    db.by_backend(
+        "COALESCE(jsonb_agg(blob_proof_outputs.hyle_output) FILTER (WHERE blob_proof_outputs.hyle_output IS NOT NULL), '[]'::jsonb)",
+        "json_group_array(json(blob_proof_outputs.hyle_output)) FILTER (WHERE blob_proof_outputs.hyle_output IS NOT NULL)",
+    )
+}
+
+impl BlobDb {
+    fn into_api_blob(self, blob_decoders: &BlobDecoders) -> APIBlob {
+        let decoded_data = blob_decoders.decode(
+            &ContractName(self.contract_name.clone()),
+            self.program_id.map(ProgramId).as_ref(),
+            &self.data,
+        );
         APIBlob {
-            tx_hash: value.tx_hash.0,
-            blob_index: value.blob_index,
-            identity: value.identity,
-            contract_name: value.contract_name,
-            data: value.data,
-            proof_outputs: value.proof_outputs,
-            verified: value.verified,
+            tx_hash: self.tx_hash.0,
+            blob_index: self.blob_index,
+            identity: self.identity,
+            contract_name: self.contract_name,
+            data: self.data,
+            decoded_data,
+            proof_outputs: self.proof_outputs,
+            verified: self.verified,
         }
     }
 }
@@ -50,9 +67,8 @@ pub async fn get_blobs_by_tx_hash(
     Path(tx_hash): Path<String>,
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<Vec<APIBlob>>, StatusCode> {
-    let blobs = log_error!(
-        sqlx::query_as::<_, BlobDb>(
-            r#"
+    let query = format!(
+        r#"
 WITH latest_height_for_this_tx_hash AS (
   SELECT MAX(block_height) as max_height
   FROM transactions
@@ -62,8 +78,10 @@ WITH latest_height_for_this_tx_hash AS (
 
 SELECT 
       blobs.*,
-      array_remove(ARRAY_AGG(blob_proof_outputs.hyle_output), NULL) AS proof_outputs
+      contracts.program_id,
+      {proof_outputs} AS proof_outputs
 FROM blobs
+LEFT JOIN contracts ON contracts.contract_name = blobs.contract_name
 LEFT JOIN
      blob_proof_outputs
 	ON blobs.parent_dp_hash = blob_proof_outputs.blob_parent_dp_hash 
@@ -78,13 +96,20 @@ GROUP BY
       blobs.parent_dp_hash,
       blobs.tx_hash,
       blobs.blob_index,
-      blobs.identity
+      blobs.identity,
+      contracts.program_id
 "#,
-        )
-        .bind(tx_hash)
-        .fetch_all(&state.db)
-        .await
-        .map(|db| db.into_iter().map(Into::<APIBlob>::into).collect()),
+        proof_outputs = proof_outputs_agg(&state.db)
+    );
+    let blobs = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, BlobDb>(&query)
+            .bind(&tx_hash)
+            .fetch_all(pool)
+            .await)
+        .map(|db| db
+            .into_iter()
+            .map(|blob| blob.into_api_blob(&state.blob_decoders))
+            .collect()),
         "Failed to fetch blobs by tx hash"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -108,13 +133,14 @@ pub async fn get_blob(
     Path((tx_hash, blob_index)): Path<(String, i32)>,
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<APIBlob>, StatusCode> {
-    let blob = log_error!(
-        sqlx::query_as::<_, BlobDb>(
-            r#"
+    let query = format!(
+        r#"
 SELECT 
   blobs.*, 
-  array_remove(ARRAY_AGG(blob_proof_outputs.hyle_output), NULL) AS proof_outputs
+  contracts.program_id,
+  {proof_outputs} AS proof_outputs
 FROM blobs
+LEFT JOIN contracts ON contracts.contract_name = blobs.contract_name
 LEFT JOIN blob_proof_outputs 
   ON blobs.parent_dp_hash = blob_proof_outputs.blob_parent_dp_hash
   AND blobs.tx_hash = blob_proof_outputs.blob_tx_hash
@@ -128,16 +154,20 @@ GROUP BY
   blobs.parent_dp_hash, 
   blobs.tx_hash, 
   blobs.blob_index,
-  transactions.block_height
+  transactions.block_height,
+  contracts.program_id
 ORDER BY transactions.block_height DESC
 LIMIT 1;
 "#,
-        )
-        .bind(tx_hash)
-        .bind(blob_index)
-        .fetch_optional(&state.db)
-        .await
-        .map(|db| db.map(Into::<APIBlob>::into)),
+        proof_outputs = proof_outputs_agg(&state.db)
+    );
+    let blob = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, BlobDb>(&query)
+            .bind(&tx_hash)
+            .bind(blob_index)
+            .fetch_optional(pool)
+            .await)
+        .map(|db| db.map(|blob| blob.into_api_blob(&state.blob_decoders))),
         "Failed to fetch blob"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
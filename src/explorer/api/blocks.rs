@@ -1,4 +1,4 @@
-use super::{BlockPagination, ExplorerApiState};
+use super::{with_db, BlockPagination, ExplorerApiState};
 use api::APIBlock;
 use axum::{
     extract::{Path, Query, State},
@@ -46,20 +46,29 @@ pub async fn get_blocks(
     Query(pagination): Query<BlockPagination>,
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<Vec<APIBlock>>, StatusCode> {
-    let blocks = log_error!(match pagination.start_block {
-        Some(start_block) => sqlx::query_as::<_, BlockDb>(
-            "SELECT * FROM blocks WHERE height <= $1 and height > $2 ORDER BY height DESC LIMIT $3",
-        )
-        .bind(start_block)
-        .bind(start_block - pagination.nb_results.unwrap_or(10)) // Fine if this goes negative
-        .bind(pagination.nb_results.unwrap_or(10)),
-        None => sqlx::query_as::<_, BlockDb>("SELECT * FROM blocks ORDER BY height DESC LIMIT $1")
-            .bind(pagination.nb_results.unwrap_or(10)),
-    }
-    .fetch_all(&state.db)
-    .await
-    .map(|db| db.into_iter().map(Into::<APIBlock>::into).collect()),
-    "Failed to fetch blocks")
+    let nb_results = pagination.nb_results.unwrap_or(10);
+    let blocks = log_error!(
+        with_db!(&state.db, |pool| match pagination.start_block {
+            Some(start_block) => {
+                sqlx::query_as::<_, BlockDb>(
+                    "SELECT * FROM blocks WHERE height <= $1 and height > $2 ORDER BY height DESC LIMIT $3",
+                )
+                .bind(start_block)
+                .bind(start_block - nb_results) // Fine if this goes negative
+                .bind(nb_results)
+                .fetch_all(pool)
+                .await
+            }
+            None => {
+                sqlx::query_as::<_, BlockDb>("SELECT * FROM blocks ORDER BY height DESC LIMIT $1")
+                    .bind(nb_results)
+                    .fetch_all(pool)
+                    .await
+            }
+        })
+        .map(|db| db.into_iter().map(Into::<APIBlock>::into).collect()),
+        "Failed to fetch blocks"
+    )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Json(blocks))
@@ -77,10 +86,12 @@ pub async fn get_last_block(
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<APIBlock>, StatusCode> {
     let block = log_error!(
-        sqlx::query_as::<_, BlockDb>("SELECT * FROM blocks ORDER BY height DESC LIMIT 1")
-            .fetch_optional(&state.db)
-            .await
-            .map(|db| db.map(Into::<APIBlock>::into)),
+        with_db!(&state.db, |pool| sqlx::query_as::<_, BlockDb>(
+            "SELECT * FROM blocks ORDER BY height DESC LIMIT 1"
+        )
+        .fetch_optional(pool)
+        .await)
+        .map(|db| db.map(Into::<APIBlock>::into)),
         "Failed to fetch last block"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -107,11 +118,13 @@ pub async fn get_block(
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<APIBlock>, StatusCode> {
     let block = log_error!(
-        sqlx::query_as::<_, BlockDb>("SELECT * FROM blocks WHERE height = $1")
-            .bind(height)
-            .fetch_optional(&state.db)
-            .await
-            .map(|db| db.map(Into::<APIBlock>::into)),
+        with_db!(&state.db, |pool| sqlx::query_as::<_, BlockDb>(
+            "SELECT * FROM blocks WHERE height = $1"
+        )
+        .bind(height)
+        .fetch_optional(pool)
+        .await)
+        .map(|db| db.map(Into::<APIBlock>::into)),
         "Failed to fetch block by height"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -138,11 +151,13 @@ pub async fn get_block_by_hash(
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<APIBlock>, StatusCode> {
     let block = log_error!(
-        sqlx::query_as::<_, BlockDb>("SELECT * FROM blocks WHERE hash = $1")
-            .bind(hash)
-            .fetch_optional(&state.db)
-            .await
-            .map(|db| db.map(Into::<APIBlock>::into)),
+        with_db!(&state.db, |pool| sqlx::query_as::<_, BlockDb>(
+            "SELECT * FROM blocks WHERE hash = $1"
+        )
+        .bind(&hash)
+        .fetch_optional(pool)
+        .await)
+        .map(|db| db.map(Into::<APIBlock>::into)),
         "Failed to fetch block by hash"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
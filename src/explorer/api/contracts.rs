@@ -1,11 +1,17 @@
-use super::{ExplorerApiState, TxHashDb};
-use api::{APIContract, APIContractState};
+use super::{with_db, ExplorerApiState, TxHashDb};
+use api::{
+    APIContract, APIContractState, APIContractStateDiff, APIContractStateTransition,
+    APIContractStateTransitionKind, TransactionStatusDb,
+};
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
 
+use sqlx::types::Json as SqlJson;
+use sqlx::Database;
+
 use crate::model::*;
 use hyle_modules::log_error;
 
@@ -69,12 +75,12 @@ pub async fn list_contracts(
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<Vec<APIContract>>, StatusCode> {
     let contract = log_error!(
-        sqlx::query_as::<_, ContractDb>(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, ContractDb>(
             r#"
 SELECT
     c.*,
-    COUNT(tx_c.*) AS total_tx,
-    COUNT(t.*) FILTER (WHERE t.transaction_status = 'sequenced') AS unsettled_tx,
+    COUNT(tx_c.tx_hash) AS total_tx,
+    COUNT(t.tx_hash) FILTER (WHERE t.transaction_status = 'sequenced') AS unsettled_tx,
     min(t.block_height) FILTER (WHERE t.transaction_status = 'sequenced') as earliest_unsettled
 FROM contracts AS c
 LEFT JOIN txs_contracts as tx_c
@@ -85,8 +91,8 @@ LEFT JOIN transactions AS t
 GROUP BY c.contract_name
 "#
         )
-        .fetch_all(&state.db)
-        .await
+        .fetch_all(pool)
+        .await)
         .map(|db| db.into_iter().map(Into::<APIContract>::into).collect()),
         "Failed to fetch contracts"
     )
@@ -111,12 +117,12 @@ pub async fn get_contract(
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<APIContract>, StatusCode> {
     let contract = log_error!(
-        sqlx::query_as::<_, ContractDb>(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, ContractDb>(
             r#"
         SELECT
           c.*,
-          COUNT(t.*)                             			          AS total_tx,
-          COUNT(t.*)
+          COUNT(t.tx_hash)                                      AS total_tx,
+          COUNT(t.tx_hash)
             FILTER (WHERE t.transaction_status = 'sequenced')   AS unsettled_tx,
           (
             SELECT min(bl.height)
@@ -140,9 +146,9 @@ pub async fn get_contract(
         GROUP BY c.contract_name;
         "#
         )
-        .bind(contract_name)
-        .fetch_optional(&state.db)
-        .await
+        .bind(&contract_name)
+        .fetch_optional(pool)
+        .await)
         .map(|db| db.map(Into::<APIContract>::into)),
         "Failed to fetch contract"
     )
@@ -154,6 +160,41 @@ pub async fn get_contract(
     }
 }
 
+#[utoipa::path(
+    get,
+    tag = "Indexer",
+    params(
+        ("contract_name" = String, Path, description = "Contract name"),
+    ),
+    path = "/contract/{contract_name}/schema",
+    responses(
+        (status = OK, body = ContractSchema)
+    )
+)]
+pub async fn get_contract_schema(
+    Path(contract_name): Path<String>,
+    State(state): State<ExplorerApiState>,
+) -> Result<Json<ContractSchema>, StatusCode> {
+    let schema = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_scalar::<
+            _,
+            Option<SqlJson<ContractSchema>>,
+        >(
+            "SELECT contract_schema FROM contracts WHERE contract_name = $1"
+        )
+        .bind(&contract_name)
+        .fetch_optional(pool)
+        .await),
+        "Failed to fetch contract schema"
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    match schema.flatten() {
+        Some(SqlJson(schema)) => Ok(Json(schema)),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
 #[utoipa::path(
     get,
     tag = "Indexer",
@@ -171,17 +212,17 @@ pub async fn get_contract_state_by_height(
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<APIContractState>, StatusCode> {
     let contract = log_error!(
-        sqlx::query_as::<_, ContractStateDb>(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, ContractStateDb>(
             r#"
         SELECT cs.*
         FROM contract_state cs
         JOIN blocks b ON cs.block_hash = b.hash
         WHERE contract_name = $1 AND height = $2"#,
         )
-        .bind(contract_name)
+        .bind(&contract_name)
         .bind(height)
-        .fetch_optional(&state.db)
-        .await
+        .fetch_optional(pool)
+        .await)
         .map(|db| db.map(Into::<APIContractState>::into)),
         "Failed to fetch contract state by height"
     )
@@ -193,6 +234,265 @@ pub async fn get_contract_state_by_height(
     }
 }
 
+/// Bounds the transitions returned by a single request
+const MAX_STATE_TRANSITIONS: i64 = 1000;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct StateTransitionsQuery {
+    pub from_height: Option<i64>,
+    pub to_height: Option<i64>,
+    pub nb_results: Option<i64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct StateDiffQuery {
+    pub from: i64,
+    pub to: i64,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+struct RegistrationTransitionRow {
+    block_height: i64,
+    tx_hash: Option<TxHashDb>,
+    state_commitment: Vec<u8>,
+    program_id: Option<Vec<u8>>,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+struct SettlementTransitionRow {
+    block_height: i64,
+    tx_hash: TxHashDb,
+    blob_index: i32,
+    program_id: Option<Vec<u8>>,
+    hyle_output: SqlJson<HyleOutputStates>,
+}
+
+/// The states of a stored hyle output, the rest of it is not needed for transitions
+#[derive(serde::Deserialize, Debug)]
+struct HyleOutputStates {
+    initial_state: StateCommitment,
+    next_state: StateCommitment,
+}
+
+/// Position of a transition within the chain of states of its contract.
+/// Registrations come first in their block, settlements follow the sequencing order of their
+/// transactions.
+type TransitionKey = (i64, u8, i64);
+
+/// Fetches up to `limit` transitions of `contract_name` applied between `from` and `to`
+/// included, in chronological order, or the most recent first if `latest_first`.
+async fn fetch_state_transitions(
+    state: &ExplorerApiState,
+    contract_name: &str,
+    from: i64,
+    to: i64,
+    limit: i64,
+    latest_first: bool,
+) -> Result<Vec<APIContractStateTransition>, StatusCode> {
+    let order = if latest_first { "DESC" } else { "ASC" };
+
+    // Rows indexed before registered_state_commitment existed fall back to the state of their block
+    let registrations_query = format!(
+        r#"
+SELECT
+  b.height AS block_height,
+  cs.tx_hash,
+  COALESCE(cs.registered_state_commitment, cs.state_commitment) AS state_commitment,
+  cs.program_id
+FROM contract_state cs
+JOIN blocks b ON cs.block_hash = b.hash
+WHERE cs.contract_name = $1 AND b.height >= $2 AND b.height <= $3
+ORDER BY b.height {order}
+LIMIT $4
+        "#
+    );
+    let registrations = log_error!(
+        with_db!(&state.db, |pool| {
+            sqlx::query_as::<_, RegistrationTransitionRow>(&registrations_query)
+                .bind(contract_name)
+                .bind(from)
+                .bind(to)
+                .bind(limit)
+                .fetch_all(pool)
+                .await
+        }),
+        "Failed to fetch contract registrations"
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Transactions settled before settled_block_height was indexed fall back to their sequencing block
+    let settlements_query = format!(
+        r#"
+SELECT
+  COALESCE(t.settled_block_height, t.block_height) AS block_height,
+  t.block_height AS sequenced_height,
+  t."index" AS tx_index,
+  bpo.blob_tx_hash AS tx_hash,
+  bpo.blob_index,
+  bpo.program_id,
+  bpo.hyle_output
+FROM blob_proof_outputs bpo
+JOIN transactions t ON t.tx_hash = bpo.blob_tx_hash AND t.parent_dp_hash = bpo.blob_parent_dp_hash
+WHERE bpo.contract_name = $1 AND bpo.settled = true AND t.transaction_status = $2
+  AND COALESCE(t.settled_block_height, t.block_height) >= $3
+  AND COALESCE(t.settled_block_height, t.block_height) <= $4
+ORDER BY block_height {order}, sequenced_height {order}, tx_index {order}, bpo.blob_index {order}
+LIMIT $5
+        "#
+    );
+    let settlements = log_error!(
+        with_db!(&state.db, |pool| {
+            sqlx::query_as::<_, SettlementTransitionRow>(&settlements_query)
+                .bind(contract_name)
+                .bind(TransactionStatusDb::Success)
+                .bind(from)
+                .bind(to)
+                .bind(limit)
+                .fetch_all(pool)
+                .await
+        }),
+        "Failed to fetch contract settlements"
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut transitions: Vec<(TransitionKey, APIContractStateTransition)> = registrations
+        .into_iter()
+        .map(|row| {
+            (
+                (row.block_height, 0, 0),
+                APIContractStateTransition {
+                    contract_name: ContractName(contract_name.to_string()),
+                    kind: APIContractStateTransitionKind::Registration,
+                    block_height: BlockHeight(row.block_height as u64),
+                    tx_hash: row.tx_hash.map(|tx_hash| tx_hash.0),
+                    blob_index: None,
+                    initial_state: None,
+                    next_state: StateCommitment(row.state_commitment),
+                    program_id: row.program_id.map(ProgramId),
+                },
+            )
+        })
+        .collect();
+    transitions.extend(settlements.into_iter().enumerate().map(|(position, row)| {
+        // Rows are already sorted by the query, their position stands for the sequencing order
+        let position = if latest_first {
+            -(position as i64)
+        } else {
+            position as i64
+        };
+        let SqlJson(hyle_output) = row.hyle_output;
+        (
+            (row.block_height, 1, position),
+            APIContractStateTransition {
+                contract_name: ContractName(contract_name.to_string()),
+                kind: APIContractStateTransitionKind::Settlement,
+                block_height: BlockHeight(row.block_height as u64),
+                tx_hash: Some(row.tx_hash.0),
+                blob_index: Some(BlobIndex(row.blob_index as usize)),
+                initial_state: Some(hyle_output.initial_state),
+                next_state: hyle_output.next_state,
+                program_id: row.program_id.map(ProgramId),
+            },
+        )
+    }));
+
+    transitions.sort_by_key(|(key, _)| *key);
+    if latest_first {
+        transitions.reverse();
+    }
+    transitions.truncate(limit as usize);
+
+    Ok(transitions
+        .into_iter()
+        .map(|(_, transition)| transition)
+        .collect())
+}
+
+#[utoipa::path(
+    get,
+    tag = "Indexer",
+    params(
+        ("contract_name" = String, Path, description = "Contract name"),
+        ("from_height" = Option<i64>, Query, description = "First block height, defaults to 0"),
+        ("to_height" = Option<i64>, Query, description = "Last block height included, defaults to the latest block"),
+        ("nb_results" = Option<i64>, Query, description = "Maximum number of transitions, defaults to 100"),
+    ),
+    path = "/state/contract/{contract_name}/transitions",
+    responses(
+        (status = OK, body = [APIContractStateTransition])
+    )
+)]
+pub async fn get_contract_state_transitions(
+    Path(contract_name): Path<String>,
+    Query(query): Query<StateTransitionsQuery>,
+    State(state): State<ExplorerApiState>,
+) -> Result<Json<Vec<APIContractStateTransition>>, StatusCode> {
+    let from = query.from_height.unwrap_or(0);
+    let to = query.to_height.unwrap_or(i64::MAX);
+    let nb_results = query
+        .nb_results
+        .unwrap_or(100)
+        .clamp(1, MAX_STATE_TRANSITIONS);
+
+    fetch_state_transitions(&state, &contract_name, from, to, nb_results, false)
+        .await
+        .map(Json)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Indexer",
+    params(
+        ("contract_name" = String, Path, description = "Contract name"),
+        ("from" = i64, Query, description = "Block height of the initial state"),
+        ("to" = i64, Query, description = "Block height of the final state"),
+    ),
+    path = "/state/contract/{contract_name}/diff",
+    responses(
+        (status = OK, body = APIContractStateDiff)
+    )
+)]
+pub async fn get_contract_state_diff(
+    Path(contract_name): Path<String>,
+    Query(query): Query<StateDiffQuery>,
+    State(state): State<ExplorerApiState>,
+) -> Result<Json<APIContractStateDiff>, StatusCode> {
+    if query.from < 0 || query.from > query.to {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let from_transition = fetch_state_transitions(&state, &contract_name, 0, query.from, 1, true)
+        .await?
+        .pop();
+    let to_transition = fetch_state_transitions(&state, &contract_name, 0, query.to, 1, true)
+        .await?
+        .pop();
+
+    let mut transitions = fetch_state_transitions(
+        &state,
+        &contract_name,
+        query.from + 1,
+        query.to,
+        MAX_STATE_TRANSITIONS + 1,
+        false,
+    )
+    .await?;
+    let truncated = transitions.len() as i64 > MAX_STATE_TRANSITIONS;
+    transitions.truncate(MAX_STATE_TRANSITIONS as usize);
+
+    Ok(Json(APIContractStateDiff {
+        contract_name: ContractName(contract_name),
+        from_height: BlockHeight(query.from as u64),
+        to_height: BlockHeight(query.to as u64),
+        from_state: from_transition.as_ref().map(|t| t.next_state.clone()),
+        to_state: to_transition.as_ref().map(|t| t.next_state.clone()),
+        from_program_id: from_transition.and_then(|t| t.program_id),
+        to_program_id: to_transition.and_then(|t| t.program_id),
+        transitions,
+        truncated,
+    }))
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TimeoutWindowDb(pub TimeoutWindow);
 
@@ -208,20 +508,26 @@ impl From<TimeoutWindowDb> for TimeoutWindow {
     }
 }
 
-impl sqlx::Type<sqlx::Postgres> for TimeoutWindowDb {
-    fn type_info() -> sqlx::postgres::PgTypeInfo {
-        <i64 as sqlx::Type<sqlx::Postgres>>::type_info()
+impl<DB: Database> sqlx::Type<DB> for TimeoutWindowDb
+where
+    i64: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <i64 as sqlx::Type<DB>>::type_info()
     }
 
-    fn compatible(ty: &sqlx::postgres::PgTypeInfo) -> bool {
-        <i64 as sqlx::Type<sqlx::Postgres>>::compatible(ty)
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <i64 as sqlx::Type<DB>>::compatible(ty)
     }
 }
 
-impl sqlx::Encode<'_, sqlx::Postgres> for TimeoutWindowDb {
+impl<'q, DB: Database> sqlx::Encode<'q, DB> for TimeoutWindowDb
+where
+    i64: sqlx::Encode<'q, DB>,
+{
     fn encode_by_ref(
         &self,
-        buf: &mut sqlx::postgres::PgArgumentBuffer,
+        buf: &mut <DB as Database>::ArgumentBuffer<'q>,
     ) -> Result<sqlx::encode::IsNull, Box<dyn std::error::Error + Send + Sync + 'static>> {
         match &self.0 {
             TimeoutWindow::NoTimeout => Ok(sqlx::encode::IsNull::Yes),
@@ -230,17 +536,20 @@ impl sqlx::Encode<'_, sqlx::Postgres> for TimeoutWindowDb {
                     .0
                     .try_into()
                     .map_err(|_| format!("BlockHeight value {} overflows i64", height.0))?;
-                <i64 as sqlx::Encode<sqlx::Postgres>>::encode_by_ref(&val, buf)
+                <i64 as sqlx::Encode<DB>>::encode(val, buf)
             }
         }
     }
 }
 
-impl<'r> sqlx::Decode<'r, sqlx::Postgres> for TimeoutWindowDb {
+impl<'r, DB: Database> sqlx::Decode<'r, DB> for TimeoutWindowDb
+where
+    i64: sqlx::Decode<'r, DB>,
+{
     fn decode(
-        value: sqlx::postgres::PgValueRef<'r>,
+        value: <DB as Database>::ValueRef<'r>,
     ) -> Result<TimeoutWindowDb, Box<dyn std::error::Error + Send + Sync + 'static>> {
-        let opt_val: Option<i64> = sqlx::Decode::<sqlx::Postgres>::decode(value)?;
+        let opt_val: Option<i64> = sqlx::Decode::<DB>::decode(value)?;
         let tw = match opt_val {
             None => TimeoutWindow::NoTimeout,
             Some(val) => {
@@ -1,3 +1,4 @@
+use super::db::with_db;
 use super::ExplorerApiState;
 use utoipa::OpenApi;
 
@@ -15,6 +16,7 @@ mod blobs;
 mod blocks;
 mod contracts;
 mod proofs;
+mod search;
 mod stats;
 mod transactions;
 
@@ -22,5 +24,6 @@ pub use blobs::*;
 pub use blocks::*;
 pub use contracts::*;
 pub use proofs::*;
+pub use search::*;
 pub use stats::*;
 pub use transactions::*;
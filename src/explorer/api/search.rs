@@ -0,0 +1,184 @@
+use super::{with_db, ExplorerApiState};
+use api::{APISearchResult, APISearchResultKind, TransactionTypeDb};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+
+use crate::model::*;
+use hyle_modules::log_error;
+
+/// Shorter hash prefixes would match a good part of the tables
+const MIN_HASH_PREFIX_LEN: usize = 4;
+
+const MAX_SEARCH_RESULTS: i64 = 100;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub nb_results: Option<i64>,
+}
+
+/// Builds a LIKE pattern matching the values starting with `q`, used with `ESCAPE '\'`
+fn like_prefix(q: &str) -> String {
+    let mut pattern = String::with_capacity(q.len() + 1);
+    for c in q.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+#[utoipa::path(
+    get,
+    tag = "Indexer",
+    params(
+        ("q" = String, Query, description = "Block hash or height, tx hash, contract name, identity or data proposal hash. Hashes and contract names can be prefixes"),
+        ("nb_results" = Option<i64>, Query, description = "Maximum number of results, at most 100"),
+    ),
+    path = "/search",
+    responses(
+        (status = OK, body = [APISearchResult])
+    )
+)]
+pub async fn search(
+    Query(query): Query<SearchQuery>,
+    State(state): State<ExplorerApiState>,
+) -> Result<Json<Vec<APISearchResult>>, StatusCode> {
+    let q = query.q.trim();
+    let nb_results = query.nb_results.unwrap_or(10).clamp(0, MAX_SEARCH_RESULTS);
+    let mut results = vec![];
+    if q.is_empty() {
+        return Ok(Json(results));
+    }
+    let prefix = like_prefix(q);
+
+    if let Ok(height) = q.parse::<i64>() {
+        let block: Option<i64> = log_error!(
+            with_db!(&state.db, |pool| sqlx::query_scalar(
+                "SELECT height FROM blocks WHERE height = $1"
+            )
+            .bind(height)
+            .fetch_optional(pool)
+            .await),
+            "Failed to search blocks by height"
+        )
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        results.extend(block.map(|height| APISearchResult {
+            kind: APISearchResultKind::Block,
+            id: height.to_string(),
+            link: format!("/block/height/{height}"),
+        }));
+    }
+
+    if q.len() >= MIN_HASH_PREFIX_LEN {
+        let blocks: Vec<String> = log_error!(
+            with_db!(&state.db, |pool| sqlx::query_scalar(
+                r"SELECT hash FROM blocks WHERE hash LIKE $1 ESCAPE '\' ORDER BY height DESC LIMIT $2"
+            )
+            .bind(&prefix)
+            .bind(nb_results)
+            .fetch_all(pool)
+            .await),
+            "Failed to search blocks by hash"
+        )
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        results.extend(blocks.into_iter().map(|hash| APISearchResult {
+            kind: APISearchResultKind::Block,
+            link: format!("/block/hash/{hash}"),
+            id: hash,
+        }));
+
+        let transactions: Vec<(String, TransactionTypeDb)> = log_error!(
+            with_db!(&state.db, |pool| sqlx::query_as(
+                r"SELECT DISTINCT tx_hash, transaction_type FROM transactions WHERE tx_hash LIKE $1 ESCAPE '\' ORDER BY tx_hash LIMIT $2"
+            )
+            .bind(&prefix)
+            .bind(nb_results)
+            .fetch_all(pool)
+            .await),
+            "Failed to search transactions by hash"
+        )
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        results.extend(transactions.into_iter().map(|(tx_hash, transaction_type)| {
+            match transaction_type {
+                TransactionTypeDb::ProofTransaction => APISearchResult {
+                    kind: APISearchResultKind::Proof,
+                    link: format!("/proof/hash/{tx_hash}"),
+                    id: tx_hash,
+                },
+                _ => APISearchResult {
+                    kind: APISearchResultKind::Transaction,
+                    link: format!("/transaction/hash/{tx_hash}"),
+                    id: tx_hash,
+                },
+            }
+        }));
+
+        let data_proposals: Vec<String> = log_error!(
+            with_db!(&state.db, |pool| sqlx::query_scalar(
+                r"SELECT DISTINCT parent_dp_hash FROM transactions WHERE parent_dp_hash LIKE $1 ESCAPE '\' ORDER BY parent_dp_hash LIMIT $2"
+            )
+            .bind(&prefix)
+            .bind(nb_results)
+            .fetch_all(pool)
+            .await),
+            "Failed to search data proposals by hash"
+        )
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        results.extend(data_proposals.into_iter().map(|dp_hash| APISearchResult {
+            kind: APISearchResultKind::DataProposal,
+            link: format!("/transactions/data_proposal/{dp_hash}"),
+            id: dp_hash,
+        }));
+    }
+
+    let contracts: Vec<String> = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_scalar(
+            r"SELECT contract_name FROM contracts WHERE contract_name LIKE $1 ESCAPE '\' ORDER BY contract_name LIMIT $2"
+        )
+        .bind(&prefix)
+        .bind(nb_results)
+        .fetch_all(pool)
+        .await),
+        "Failed to search contracts by name"
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    results.extend(contracts.into_iter().map(|contract_name| APISearchResult {
+        kind: APISearchResultKind::Contract,
+        link: format!("/contract/{contract_name}"),
+        id: contract_name,
+    }));
+
+    let identity: Option<String> = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_scalar(
+            "SELECT identity FROM transactions WHERE identity = $1 LIMIT 1"
+        )
+        .bind(q)
+        .fetch_optional(pool)
+        .await),
+        "Failed to search identities"
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    results.extend(identity.map(|identity| APISearchResult {
+        kind: APISearchResultKind::Identity,
+        link: format!("/identity/{identity}/summary"),
+        id: identity,
+    }));
+
+    // Exact matches first, prefix matches keep their order
+    results.sort_by_key(|result| result.id != q);
+    results.truncate(usize::try_from(nb_results).unwrap_or_default());
+
+    Ok(Json(results))
+}
@@ -1,9 +1,21 @@
-use super::ExplorerApiState;
-use axum::{extract::State, http::StatusCode, Json};
-use hyle_model::api::{NetworkStats, ProofStat};
+use super::{with_db, ExplorerApiState};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use hyle_model::api::{
+    APIContractSettlementStats, APILatencyPercentiles, APISettlementStatsPoint, NetworkStats,
+    ProofStat,
+};
+use sqlx::types::chrono::Utc;
 
+use crate::model::*;
 use hyle_modules::log_error;
 
+/// Bounds the size of the time series, the aggregates loaded are proportional to it
+const MAX_STATS_BUCKETS: u64 = 1000;
+
 #[derive(sqlx::FromRow, Debug)]
 pub struct Point<T = i64> {
     pub x: i64,
@@ -28,26 +40,29 @@ pub async fn get_stats(
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<NetworkStats>, StatusCode> {
     let total_transactions = log_error!(
-        sqlx::query_scalar("SELECT count(*) as txs FROM transactions")
-            .fetch_optional(&state.db)
-            .await,
+        with_db!(&state.db, |pool| sqlx::query_scalar(
+            "SELECT count(*) as txs FROM transactions"
+        )
+        .fetch_optional(pool)
+        .await),
         "Failed to fetch stats"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
     .unwrap_or(0);
 
     let total_contracts = log_error!(
-        sqlx::query_scalar("SELECT count(*) as contracts FROM contracts")
-            .fetch_optional(&state.db)
-            .await,
+        with_db!(&state.db, |pool| sqlx::query_scalar(
+            "SELECT count(*) as contracts FROM contracts"
+        )
+        .fetch_optional(pool)
+        .await),
         "Failed to fetch stats"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
     .unwrap_or(0);
 
-    let txs_last_day = log_error!(
-        sqlx::query_scalar(
-            "
+    let query = state.db.by_backend(
+        "
 WITH recent_blocks AS (
   SELECT hash
   FROM blocks
@@ -56,36 +71,55 @@ WITH recent_blocks AS (
 SELECT count(*)
 FROM transactions
 JOIN recent_blocks b ON transactions.block_hash = b.hash
-            "
-        )
-        .fetch_optional(&state.db)
-        .await,
+            ",
+        "
+WITH recent_blocks AS (
+  SELECT hash
+  FROM blocks
+  WHERE timestamp > datetime('now', '-1 day')
+)
+SELECT count(*)
+FROM transactions
+JOIN recent_blocks b ON transactions.block_hash = b.hash
+            ",
+    );
+    let txs_last_day = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_scalar(query)
+            .fetch_optional(pool)
+            .await),
         "Failed to fetch stats"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
     .unwrap_or(0);
 
-    let contracts_last_day = log_error!(
-        sqlx::query_scalar(
-            "
+    let query = state.db.by_backend(
+        "
             SELECT count(*) as contracts FROM contracts
             LEFT JOIN transactions t ON contracts.tx_hash = t.tx_hash
             LEFT JOIN blocks b ON t.block_hash = b.hash
             WHERE b.timestamp > now() - interval '1 day'
             OR b.timestamp IS NULL
-            "
-        )
-        .fetch_optional(&state.db)
-        .await,
+            ",
+        "
+            SELECT count(*) as contracts FROM contracts
+            LEFT JOIN transactions t ON contracts.tx_hash = t.tx_hash
+            LEFT JOIN blocks b ON t.block_hash = b.hash
+            WHERE b.timestamp > datetime('now', '-1 day')
+            OR b.timestamp IS NULL
+            ",
+    );
+    let contracts_last_day = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_scalar(query)
+            .fetch_optional(pool)
+            .await),
         "Failed to fetch stats"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
     .unwrap_or(0);
 
     // graph is number of txs per hour for the last 6 hours
-    let graph_tx_volume = log_error!(
-        sqlx::query_as::<_, Point>(
-            "
+    let query = state.db.by_backend(
+        "
 WITH hours AS (
   SELECT generate_series(
     date_trunc('hour', now()) - interval '5 hours',
@@ -103,10 +137,31 @@ SELECT
       AND b.timestamp < h.hour_start + interval '1 hour'
   )::bigint AS y
 FROM hours h
-ORDER BY h.hour_start;            "
-        )
-        .fetch_all(&state.db)
-        .await,
+ORDER BY h.hour_start;            ",
+        "
+WITH RECURSIVE hours(hour_start) AS (
+  SELECT datetime(strftime('%Y-%m-%d %H:00:00', 'now'), '-5 hours')
+  UNION ALL
+  SELECT datetime(hour_start, '+1 hour')
+  FROM hours
+  WHERE hour_start < strftime('%Y-%m-%d %H:00:00', 'now')
+)
+SELECT
+  CAST(strftime('%s', h.hour_start) AS INTEGER) AS x,
+  (
+    SELECT count(*)
+    FROM transactions t
+    JOIN blocks b ON t.block_hash = b.hash
+    WHERE b.timestamp >= h.hour_start
+      AND b.timestamp < datetime(h.hour_start, '+1 hour')
+  ) AS y
+FROM hours h
+ORDER BY h.hour_start;            ",
+    );
+    let graph_tx_volume = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, Point>(query)
+            .fetch_all(pool)
+            .await),
         "Failed to fetch tx stats"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -116,9 +171,8 @@ ORDER BY h.hour_start;            "
         .map(|point| (point.x, point.y.unwrap_or(0)))
         .collect::<Vec<(i64, i64)>>();
 
-    let graph_block_time = log_error!(
-        sqlx::query_as::<_, Point<f64>>(
-            "
+    let query = state.db.by_backend(
+        "
             WITH hours AS (
                 SELECT generate_series(
                     date_trunc('hour', now()) - interval '5 hours',
@@ -144,10 +198,37 @@ ORDER BY h.hour_start;            "
             LEFT JOIN block_deltas bd ON bd.bucket = h.hour_start
             GROUP BY h.hour_start
             ORDER BY h.hour_start;
-            "
-        )
-        .fetch_all(&state.db)
-        .await,
+            ",
+        "
+            WITH RECURSIVE hours(hour_start) AS (
+                SELECT datetime(strftime('%Y-%m-%d %H:00:00', 'now'), '-5 hours')
+                UNION ALL
+                SELECT datetime(hour_start, '+1 hour')
+                FROM hours
+                WHERE hour_start < strftime('%Y-%m-%d %H:00:00', 'now')
+            ),
+            block_deltas AS (
+                SELECT
+                    (julianday(b.timestamp) - julianday(bp.timestamp)) * 86400.0 AS delta,
+                    strftime('%Y-%m-%d %H:00:00', b.timestamp) AS bucket
+                FROM blocks b
+                JOIN blocks bp ON b.parent_hash = bp.hash
+                WHERE b.timestamp > datetime('now', '-6 hours')
+                AND bp.height > 0
+            )
+            SELECT
+                CAST(strftime('%s', h.hour_start) AS INTEGER) AS x,
+                AVG(bd.delta) AS y
+            FROM hours h
+            LEFT JOIN block_deltas bd ON bd.bucket = h.hour_start
+            GROUP BY h.hour_start
+            ORDER BY h.hour_start;
+            ",
+    );
+    let graph_block_time = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, Point<f64>>(query)
+            .fetch_all(pool)
+            .await),
         "Failed to fetch block stats"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -157,9 +238,8 @@ ORDER BY h.hour_start;            "
         .map(|point| (point.x, point.y.unwrap_or(0.)))
         .collect::<Vec<(i64, f64)>>();
 
-    let peak_txs = log_error!(
-        sqlx::query_as::<_, PeakStat>(
-            "
+    let query = state.db.by_backend(
+        "
 WITH recent_blocks AS (
   SELECT hash, date_trunc('minute', timestamp) AS minute
   FROM blocks
@@ -176,10 +256,30 @@ tx_counts AS (
 SELECT *
 FROM tx_counts
 ORDER BY tx_count DESC
-LIMIT 1;            "
-        )
-        .fetch_optional(&state.db)
-        .await,
+LIMIT 1;            ",
+        "
+WITH recent_blocks AS (
+  SELECT hash, strftime('%Y-%m-%d %H:%M:00', timestamp) AS minute
+  FROM blocks
+  WHERE timestamp >= datetime('now', '-24 hours')
+),
+tx_counts AS (
+  SELECT
+    CAST(strftime('%s', rb.minute) AS INTEGER) AS minute_bucket,
+    count(*) AS tx_count
+  FROM recent_blocks rb
+  JOIN transactions t ON t.block_hash = rb.hash
+  GROUP BY rb.minute
+)
+SELECT *
+FROM tx_counts
+ORDER BY tx_count DESC
+LIMIT 1;            ",
+    );
+    let peak_txs = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, PeakStat>(query)
+            .fetch_optional(pool)
+            .await),
         "Failed to fetch peak TPM"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
@@ -213,7 +313,7 @@ pub async fn get_proof_stats(
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<Vec<ProofStat>>, StatusCode> {
     let transactions = log_error!(
-        sqlx::query_as::<_, ProofStat>(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, ProofStat>(
             r#"
 WITH bpo_distinct AS (
   SELECT DISTINCT contract_name, proof_tx_hash
@@ -228,11 +328,273 @@ GROUP BY c.verifier
 ORDER BY proof_count DESC;
         "#,
         )
-        .fetch_all(&state.db)
-        .await,
+        .fetch_all(pool)
+        .await),
         "Failed to fetch proof stats"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Json(transactions))
 }
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SettlementStatsQuery {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub bucket_secs: Option<u64>,
+    pub slo_secs: Option<u64>,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+struct SettlementBucketRow {
+    bucket: i64,
+    sequenced_txs: i64,
+    successful_txs: i64,
+    failed_txs: i64,
+    timed_out_txs: i64,
+    blob_bytes: i64,
+    settled_within_slo: i64,
+}
+
+/// Durations of a bucket at the nearest ranks of the percentiles, and its maximum
+#[derive(sqlx::FromRow, Debug)]
+struct RankedDurationRow {
+    bucket: i64,
+    total: i64,
+    duration_rank: i64,
+    duration: i64,
+}
+
+/// Ranks the durations of each bucket, keeping the nearest ranks of the percentiles and the maximum.
+/// `durations` selects the `bucket` and `duration` of the measures, filtered on $1 to $4.
+fn ranked_durations_query(durations: &str) -> String {
+    format!(
+        r#"
+WITH durations AS ({durations}),
+ranked AS (
+  SELECT
+    bucket,
+    duration,
+    ROW_NUMBER() OVER (PARTITION BY bucket ORDER BY duration) AS duration_rank,
+    COUNT(*) OVER (PARTITION BY bucket) AS total
+  FROM durations
+)
+SELECT
+  CAST(bucket AS BIGINT) AS bucket,
+  CAST(total AS BIGINT) AS total,
+  CAST(duration_rank AS BIGINT) AS duration_rank,
+  CAST(duration AS BIGINT) AS duration
+FROM ranked
+WHERE duration_rank = total
+  OR duration_rank = (total * 50 + 99) / 100
+  OR duration_rank = (total * 90 + 99) / 100
+  OR duration_rank = (total * 99 + 99) / 100
+        "#
+    )
+}
+
+/// Nearest-rank percentiles of each bucket, in milliseconds
+fn latency_percentiles(
+    rows: Vec<RankedDurationRow>,
+    nb_buckets: usize,
+) -> Vec<Option<APILatencyPercentiles>> {
+    let mut percentiles = vec![None; nb_buckets];
+    for row in rows {
+        let Some(percentiles) = usize::try_from(row.bucket)
+            .ok()
+            .and_then(|bucket| percentiles.get_mut(bucket))
+        else {
+            continue;
+        };
+        let percentiles = percentiles.get_or_insert(APILatencyPercentiles {
+            count: row.total.max(0) as u64,
+            p50: 0,
+            p90: 0,
+            p99: 0,
+            max: 0,
+        });
+        let duration = row.duration.max(0) as u64;
+        let rank = |percent: i64| (row.total * percent + 99) / 100;
+        if row.duration_rank == rank(50) {
+            percentiles.p50 = duration;
+        }
+        if row.duration_rank == rank(90) {
+            percentiles.p90 = duration;
+        }
+        if row.duration_rank == rank(99) {
+            percentiles.p99 = duration;
+        }
+        if row.duration_rank == row.total {
+            percentiles.max = duration;
+        }
+    }
+    percentiles
+}
+
+#[utoipa::path(
+    get,
+    tag = "Indexer",
+    params(
+        ("contract_name" = String, Path, description = "Contract name"),
+        ("from" = Option<i64>, Query, description = "Start of the time range, UNIX timestamp in milliseconds. Defaults to one day before `to`"),
+        ("to" = Option<i64>, Query, description = "End of the time range, UNIX timestamp in milliseconds. Defaults to now"),
+        ("bucket_secs" = Option<u64>, Query, description = "Duration of the buckets in seconds, defaults to one hour"),
+        ("slo_secs" = Option<u64>, Query, description = "Counts the transactions settled within this duration"),
+    ),
+    path = "/stats/contract/{contract_name}/settlement",
+    responses(
+        (status = OK, body = APIContractSettlementStats)
+    )
+)]
+pub async fn get_contract_settlement_stats(
+    Path(contract_name): Path<String>,
+    Query(query): Query<SettlementStatsQuery>,
+    State(state): State<ExplorerApiState>,
+) -> Result<Json<APIContractSettlementStats>, StatusCode> {
+    let to = query.to.unwrap_or_else(|| Utc::now().timestamp_millis());
+    let from = query.from.unwrap_or(to.saturating_sub(24 * 3600 * 1000));
+    let bucket_secs = query.bucket_secs.unwrap_or(3600).max(1);
+    let bucket_ms = bucket_secs
+        .checked_mul(1000)
+        .and_then(|bucket_ms| i64::try_from(bucket_ms).ok())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let slo_ms = query
+        .slo_secs
+        .map(|slo_secs| {
+            slo_secs
+                .checked_mul(1000)
+                .and_then(|slo_ms| i64::try_from(slo_ms).ok())
+                .ok_or(StatusCode::BAD_REQUEST)
+        })
+        .transpose()?;
+    let nb_buckets = to
+        .checked_sub(from)
+        .filter(|range| *range > 0)
+        .map(|range| (range as u64).div_ceil(bucket_ms as u64))
+        .filter(|nb_buckets| *nb_buckets <= MAX_STATS_BUCKETS)
+        .ok_or(StatusCode::BAD_REQUEST)? as usize;
+
+    // The indexer maintains the settlement of each transaction, aggregated here per bucket
+    let buckets = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, SettlementBucketRow>(
+            r#"
+SELECT
+  CAST(bucket AS BIGINT) AS bucket,
+  CAST(count(*) AS BIGINT) AS sequenced_txs,
+  CAST(sum(CASE WHEN transaction_status = 'success' THEN 1 ELSE 0 END) AS BIGINT) AS successful_txs,
+  CAST(sum(CASE WHEN transaction_status = 'failure' THEN 1 ELSE 0 END) AS BIGINT) AS failed_txs,
+  CAST(sum(CASE WHEN transaction_status = 'timed_out' THEN 1 ELSE 0 END) AS BIGINT) AS timed_out_txs,
+  CAST(sum(blob_bytes) AS BIGINT) AS blob_bytes,
+  CAST(sum(CASE WHEN settlement_latency <= $5 THEN 1 ELSE 0 END) AS BIGINT) AS settled_within_slo
+FROM (
+  SELECT (sequenced_at - $2) / $4 AS bucket, transaction_status, blob_bytes, settlement_latency
+  FROM contract_settlements
+  WHERE contract_name = $1 AND sequenced_at >= $2 AND sequenced_at < $3
+) settlements
+GROUP BY bucket
+        "#,
+        )
+        .bind(&contract_name)
+        .bind(from)
+        .bind(to)
+        .bind(bucket_ms)
+        .bind(slo_ms)
+        .fetch_all(pool)
+        .await),
+        "Failed to fetch settlement stats"
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let latencies = ranked_durations_query(
+        "
+SELECT (sequenced_at - $2) / $4 AS bucket, settlement_latency AS duration
+FROM contract_settlements
+WHERE contract_name = $1 AND sequenced_at >= $2 AND sequenced_at < $3 AND settlement_latency IS NOT NULL
+        ",
+    );
+    let latencies = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, RankedDurationRow>(
+            &latencies
+        )
+        .bind(&contract_name)
+        .bind(from)
+        .bind(to)
+        .bind(bucket_ms)
+        .fetch_all(pool)
+        .await),
+        "Failed to fetch settlement latencies"
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let lags = ranked_durations_query(
+        "
+SELECT (cs.sequenced_at - $2) / $4 AS bucket, bpo.settlement_lag AS duration
+FROM blob_proof_outputs bpo
+JOIN contract_settlements cs ON cs.contract_name = bpo.contract_name AND cs.tx_hash = bpo.blob_tx_hash AND cs.parent_dp_hash = bpo.blob_parent_dp_hash
+WHERE cs.contract_name = $1 AND cs.sequenced_at >= $2 AND cs.sequenced_at < $3 AND bpo.settled = true AND bpo.settlement_lag IS NOT NULL
+        ",
+    );
+    let lags = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, RankedDurationRow>(
+            &lags
+        )
+        .bind(&contract_name)
+        .bind(from)
+        .bind(to)
+        .bind(bucket_ms)
+        .fetch_all(pool)
+        .await),
+        "Failed to fetch proof settlement lags"
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut points = (0..nb_buckets)
+        .map(|i| APISettlementStatsPoint {
+            timestamp: from + i as i64 * bucket_ms,
+            sequenced_txs: 0,
+            successful_txs: 0,
+            failed_txs: 0,
+            timed_out_txs: 0,
+            failure_rate: 0.,
+            timeout_rate: 0.,
+            blob_bytes: 0,
+            settlement_latency: None,
+            proof_settlement_lag: None,
+            settled_within_slo: slo_ms.map(|_| 0),
+        })
+        .collect::<Vec<_>>();
+
+    for row in buckets {
+        let Some(point) = usize::try_from(row.bucket)
+            .ok()
+            .and_then(|bucket| points.get_mut(bucket))
+        else {
+            continue;
+        };
+        point.sequenced_txs = row.sequenced_txs.max(0) as u64;
+        point.successful_txs = row.successful_txs.max(0) as u64;
+        point.failed_txs = row.failed_txs.max(0) as u64;
+        point.timed_out_txs = row.timed_out_txs.max(0) as u64;
+        point.blob_bytes = row.blob_bytes.max(0) as u64;
+        if let Some(within_slo) = point.settled_within_slo.as_mut() {
+            *within_slo = row.settled_within_slo.max(0) as u64;
+        }
+        if point.sequenced_txs > 0 {
+            point.failure_rate = point.failed_txs as f64 / point.sequenced_txs as f64;
+            point.timeout_rate = point.timed_out_txs as f64 / point.sequenced_txs as f64;
+        }
+    }
+
+    let latencies = latency_percentiles(latencies, nb_buckets);
+    let lags = latency_percentiles(lags, nb_buckets);
+    for ((point, latency), lag) in points.iter_mut().zip(latencies).zip(lags) {
+        point.settlement_latency = latency;
+        point.proof_settlement_lag = lag;
+    }
+
+    Ok(Json(APIContractSettlementStats {
+        contract_name: ContractName(contract_name),
+        bucket_secs,
+        points,
+    }))
+}
@@ -1,9 +1,9 @@
 use std::num::TryFromIntError;
 
-use super::{BlockPagination, ExplorerApiState};
+use super::{with_db, BlockPagination, ExplorerApiState};
 use api::{
-    APITransaction, APITransactionEvents, BlobWithStatus, TransactionStatusDb, TransactionTypeDb,
-    TransactionWithBlobs,
+    APIIdentitySummary, APITransaction, APITransactionEvents, BlobWithStatus, TransactionStatusDb,
+    TransactionTypeDb, TransactionWithBlobs,
 };
 use axum::{
     extract::{Path, Query, State},
@@ -11,11 +11,11 @@ use axum::{
     Json,
 };
 use hyle_model::utils::TimestampMs;
-use sqlx::postgres::PgRow;
 use sqlx::types::chrono::NaiveDateTime;
+use sqlx::types::Json as SqlJson;
 use sqlx::FromRow;
 use sqlx::Row;
-use sqlx::{prelude::Type, Postgres};
+use sqlx::{prelude::Type, ColumnIndex, Database, Decode};
 
 use crate::model::*;
 use hyle_modules::log_error;
@@ -29,31 +29,44 @@ impl From<DataProposalHash> for DataProposalHashDb {
     }
 }
 
-impl Type<Postgres> for DataProposalHashDb {
-    fn type_info() -> sqlx::postgres::PgTypeInfo {
-        <String as Type<Postgres>>::type_info()
+impl<DB: Database> Type<DB> for DataProposalHashDb
+where
+    String: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as Type<DB>>::compatible(ty)
     }
 }
-impl sqlx::Encode<'_, sqlx::Postgres> for DataProposalHashDb {
+impl<'q, DB: Database> sqlx::Encode<'q, DB> for DataProposalHashDb
+where
+    String: sqlx::Encode<'q, DB>,
+{
     fn encode_by_ref(
         &self,
-        buf: &mut sqlx::postgres::PgArgumentBuffer,
+        buf: &mut <DB as Database>::ArgumentBuffer<'q>,
     ) -> std::result::Result<
         sqlx::encode::IsNull,
         std::boxed::Box<(dyn std::error::Error + std::marker::Send + std::marker::Sync + 'static)>,
     > {
-        <String as sqlx::Encode<sqlx::Postgres>>::encode_by_ref(&self.0 .0, buf)
+        <String as sqlx::Encode<DB>>::encode_by_ref(&self.0 .0, buf)
     }
 }
 
-impl<'r> sqlx::Decode<'r, sqlx::Postgres> for DataProposalHashDb {
+impl<'r, DB: Database> sqlx::Decode<'r, DB> for DataProposalHashDb
+where
+    String: sqlx::Decode<'r, DB>,
+{
     fn decode(
-        value: sqlx::postgres::PgValueRef<'r>,
+        value: <DB as Database>::ValueRef<'r>,
     ) -> std::result::Result<
         DataProposalHashDb,
         std::boxed::Box<(dyn std::error::Error + std::marker::Send + std::marker::Sync + 'static)>,
     > {
-        let inner = <String as sqlx::Decode<sqlx::Postgres>>::decode(value)?;
+        let inner = <String as sqlx::Decode<DB>>::decode(value)?;
         Ok(DataProposalHashDb(DataProposalHash(inner)))
     }
 }
@@ -67,31 +80,44 @@ impl From<TxHash> for TxHashDb {
     }
 }
 
-impl Type<Postgres> for TxHashDb {
-    fn type_info() -> sqlx::postgres::PgTypeInfo {
-        <String as Type<Postgres>>::type_info()
+impl<DB: Database> Type<DB> for TxHashDb
+where
+    String: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as Type<DB>>::compatible(ty)
     }
 }
-impl sqlx::Encode<'_, sqlx::Postgres> for TxHashDb {
+impl<'q, DB: Database> sqlx::Encode<'q, DB> for TxHashDb
+where
+    String: sqlx::Encode<'q, DB>,
+{
     fn encode_by_ref(
         &self,
-        buf: &mut sqlx::postgres::PgArgumentBuffer,
+        buf: &mut <DB as Database>::ArgumentBuffer<'q>,
     ) -> std::result::Result<
         sqlx::encode::IsNull,
         std::boxed::Box<(dyn std::error::Error + std::marker::Send + std::marker::Sync + 'static)>,
     > {
-        <String as sqlx::Encode<sqlx::Postgres>>::encode_by_ref(&self.0 .0, buf)
+        <String as sqlx::Encode<DB>>::encode_by_ref(&self.0 .0, buf)
     }
 }
 
-impl<'r> sqlx::Decode<'r, sqlx::Postgres> for TxHashDb {
+impl<'r, DB: Database> sqlx::Decode<'r, DB> for TxHashDb
+where
+    String: sqlx::Decode<'r, DB>,
+{
     fn decode(
-        value: sqlx::postgres::PgValueRef<'r>,
+        value: <DB as Database>::ValueRef<'r>,
     ) -> std::result::Result<
         TxHashDb,
         std::boxed::Box<(dyn std::error::Error + std::marker::Send + std::marker::Sync + 'static)>,
     > {
-        let inner = <String as sqlx::Decode<sqlx::Postgres>>::decode(value)?;
+        let inner = <String as sqlx::Decode<DB>>::decode(value)?;
         Ok(TxHashDb(TxHash(inner)))
     }
 }
@@ -105,31 +131,44 @@ impl From<LaneId> for LaneIdDb {
     }
 }
 
-impl Type<Postgres> for LaneIdDb {
-    fn type_info() -> sqlx::postgres::PgTypeInfo {
-        <String as Type<Postgres>>::type_info()
+impl<DB: Database> Type<DB> for LaneIdDb
+where
+    String: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as Type<DB>>::compatible(ty)
     }
 }
-impl sqlx::Encode<'_, sqlx::Postgres> for LaneIdDb {
+impl<'q, DB: Database> sqlx::Encode<'q, DB> for LaneIdDb
+where
+    String: sqlx::Encode<'q, DB>,
+{
     fn encode_by_ref(
         &self,
-        buf: &mut sqlx::postgres::PgArgumentBuffer,
+        buf: &mut <DB as Database>::ArgumentBuffer<'q>,
     ) -> std::result::Result<
         sqlx::encode::IsNull,
         std::boxed::Box<(dyn std::error::Error + std::marker::Send + std::marker::Sync + 'static)>,
     > {
-        <String as sqlx::Encode<sqlx::Postgres>>::encode_by_ref(&hex::encode(&self.0 .0 .0), buf)
+        <String as sqlx::Encode<DB>>::encode_by_ref(&hex::encode(&self.0 .0 .0), buf)
     }
 }
 
-impl<'r> sqlx::Decode<'r, sqlx::Postgres> for LaneIdDb {
+impl<'r, DB: Database> sqlx::Decode<'r, DB> for LaneIdDb
+where
+    String: sqlx::Decode<'r, DB>,
+{
     fn decode(
-        value: sqlx::postgres::PgValueRef<'r>,
+        value: <DB as Database>::ValueRef<'r>,
     ) -> std::result::Result<
         LaneIdDb,
         std::boxed::Box<(dyn std::error::Error + std::marker::Send + std::marker::Sync + 'static)>,
     > {
-        let inner = <String as sqlx::Decode<sqlx::Postgres>>::decode(value)?;
+        let inner = <String as sqlx::Decode<DB>>::decode(value)?;
         Ok(LaneIdDb(LaneId(ValidatorPublicKey(hex::decode(inner)?))))
     }
 }
@@ -149,8 +188,20 @@ pub struct TransactionDb {
     pub identity: Option<String>, // Identity of the transaction sender (null for proofs)
 }
 
-impl<'r> FromRow<'r, PgRow> for TransactionDb {
-    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+impl<'r, R: Row> FromRow<'r, R> for TransactionDb
+where
+    &'r str: ColumnIndex<R>,
+    TxHashDb: Decode<'r, R::Database> + Type<R::Database>,
+    DataProposalHashDb: Decode<'r, R::Database> + Type<R::Database>,
+    ConsensusProposalHash: Decode<'r, R::Database> + Type<R::Database>,
+    LaneIdDb: Decode<'r, R::Database> + Type<R::Database>,
+    TransactionTypeDb: Decode<'r, R::Database> + Type<R::Database>,
+    TransactionStatusDb: Decode<'r, R::Database> + Type<R::Database>,
+    NaiveDateTime: Decode<'r, R::Database> + Type<R::Database>,
+    String: Decode<'r, R::Database> + Type<R::Database>,
+    i32: Decode<'r, R::Database> + Type<R::Database>,
+{
+    fn from_row(row: &'r R) -> Result<Self, sqlx::Error> {
         let tx_hash = row.try_get("tx_hash")?;
         let block_hash = row.try_get("block_hash")?;
         let dp_hash_db: DataProposalHashDb = row.try_get("parent_dp_hash")?;
@@ -222,7 +273,7 @@ pub async fn get_transactions(
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<Vec<APITransaction>>, StatusCode> {
     let transactions = log_error!(
-        match pagination.start_block {
+        with_db!(&state.db, |pool| match pagination.start_block {
             Some(start_block) => sqlx::query_as::<_, TransactionDb>(
                 r#"
                 SELECT t.*, b.timestamp
@@ -231,7 +282,7 @@ pub async fn get_transactions(
                     FROM transactions t
                     WHERE t.transaction_type = 'blob_transaction'
                         AND t.block_height <= $1 AND t.block_height > $2
-                    ORDER BY t.block_height DESC, t.index DESC
+                    ORDER BY t.block_height DESC NULLS FIRST, t."index" DESC
                     LIMIT $3
                 ) t
                 LEFT JOIN blocks b ON t.block_hash = b.hash
@@ -247,7 +298,7 @@ pub async fn get_transactions(
                     SELECT *
                     FROM transactions t
                     WHERE t.transaction_type = 'blob_transaction'
-                    ORDER BY t.block_height DESC, t.index DESC
+                    ORDER BY t.block_height DESC NULLS FIRST, t."index" DESC
                     LIMIT $1
                 ) t
                 LEFT JOIN blocks b ON t.block_hash = b.hash
@@ -255,9 +306,9 @@ pub async fn get_transactions(
             )
             .bind(pagination.nb_results.unwrap_or(10)),
         }
-        .fetch_all(&state.db)
+        .fetch_all(pool)
         .await
-        .map(|db| db.into_iter().map(Into::<APITransaction>::into).collect()),
+        .map(|db| db.into_iter().map(Into::<APITransaction>::into).collect())),
         "Failed to fetch transactions"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -281,7 +332,7 @@ pub async fn get_transactions_by_contract(
     Query(pagination): Query<BlockPagination>,
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<Vec<APITransaction>>, StatusCode> {
-    let transactions = log_error!(match pagination.start_block {
+    let transactions = log_error!(with_db!(&state.db, |pool| match pagination.start_block {
         Some(start_block) => sqlx::query_as::<_, TransactionDb>(
             r#"
             SELECT t.* , bl.timestamp
@@ -289,7 +340,7 @@ pub async fn get_transactions_by_contract(
             JOIN blobs b ON t.tx_hash = b.tx_hash
             LEFT JOIN blocks bl ON t.block_hash = bl.hash
             WHERE b.contract_name = $1 AND bl.height <= $2 AND bl.height > $3 AND t.transaction_type = 'blob_transaction'
-            ORDER BY bl.height DESC, t.index DESC
+            ORDER BY bl.height DESC, t."index" DESC
             LIMIT $4
             "#,
         )
@@ -304,22 +355,218 @@ pub async fn get_transactions_by_contract(
             JOIN blobs b ON t.tx_hash = b.tx_hash AND t.parent_dp_hash = b.parent_dp_hash
             LEFT JOIN blocks bl ON t.block_hash = bl.hash
             WHERE b.contract_name = $1 AND t.transaction_type = 'blob_transaction'
-            ORDER BY bl.height DESC, t.index DESC
+            ORDER BY bl.height DESC NULLS FIRST, t."index" DESC
             LIMIT $2
             "#,
         )
         .bind(contract_name)
         .bind(pagination.nb_results.unwrap_or(10)),
     }
-    .fetch_all(&state.db)
+    .fetch_all(pool)
     .await
-    .map(|db| db.into_iter().map(Into::<APITransaction>::into).collect()),
+    .map(|db| db.into_iter().map(Into::<APITransaction>::into).collect())),
     "Failed to fetch transactions by contract")
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Json(transactions))
 }
 
+const MAX_IDENTITY_TRANSACTIONS: i64 = 100;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct IdentityPagination {
+    /// Cursor: only returns transactions sequenced strictly before (before_block, before_index).
+    /// Both must be set together.
+    pub before_block: Option<i64>,
+    pub before_index: Option<i64>,
+    pub nb_results: Option<i64>,
+    pub status: Option<TransactionStatusDb>,
+    pub contract: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    tag = "Indexer",
+    params(
+        ("identity" = String, Path, description = "Identity"),
+        ("before_block" = Option<i64>, Query, description = "Block height of the pagination cursor, requires before_index"),
+        ("before_index" = Option<i64>, Query, description = "Transaction index of the pagination cursor, requires before_block"),
+        ("nb_results" = Option<i64>, Query, description = "Maximum number of transactions, at most 100"),
+        ("status" = Option<TransactionStatusDb>, Query, description = "Transaction status"),
+        ("contract" = Option<String>, Query, description = "Contract the transactions have a blob for"),
+    ),
+    path = "/transactions/identity/{identity}",
+    responses(
+        (status = OK, body = [APITransaction]),
+        (status = BAD_REQUEST, description = "Incomplete pagination cursor")
+    )
+)]
+pub async fn get_transactions_by_identity(
+    Path(identity): Path<String>,
+    Query(pagination): Query<IdentityPagination>,
+    State(state): State<ExplorerApiState>,
+) -> Result<Json<Vec<APITransaction>>, StatusCode> {
+    if pagination.before_block.is_some() != pagination.before_index.is_some() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let nb_results = pagination
+        .nb_results
+        .unwrap_or(10)
+        .clamp(1, MAX_IDENTITY_TRANSACTIONS);
+
+    // A transaction appears once per data proposal it was sent in, only its latest entry is kept.
+    // Transactions not sequenced yet have no height, they only show up on the first page
+    let query = state.db.by_backend(
+        r#"
+        SELECT t.* FROM (
+            SELECT t.*, bl.timestamp, ROW_NUMBER() OVER (
+                PARTITION BY t.tx_hash ORDER BY t.block_height DESC NULLS FIRST, t."index" DESC
+            ) AS tx_rank
+            FROM transactions t
+            LEFT JOIN blocks bl ON t.block_hash = bl.hash
+            WHERE t.identity = $1 AND t.transaction_type = 'blob_transaction'
+        ) t
+        WHERE t.tx_rank = 1
+            AND ($2::transaction_status IS NULL OR t.transaction_status = $2)
+            AND ($3::text IS NULL OR EXISTS (
+                SELECT 1 FROM blobs b
+                WHERE b.tx_hash = t.tx_hash AND b.parent_dp_hash = t.parent_dp_hash AND b.contract_name = $3
+            ))
+            AND ($4::bigint IS NULL OR (t.block_height, t."index") < ($4, $5))
+        ORDER BY t.block_height DESC NULLS FIRST, t."index" DESC
+        LIMIT $6
+        "#,
+        r#"
+        SELECT t.* FROM (
+            SELECT t.*, bl.timestamp, ROW_NUMBER() OVER (
+                PARTITION BY t.tx_hash ORDER BY t.block_height DESC NULLS FIRST, t."index" DESC
+            ) AS tx_rank
+            FROM transactions t
+            LEFT JOIN blocks bl ON t.block_hash = bl.hash
+            WHERE t.identity = $1 AND t.transaction_type = 'blob_transaction'
+        ) t
+        WHERE t.tx_rank = 1
+            AND ($2 IS NULL OR t.transaction_status = $2)
+            AND ($3 IS NULL OR EXISTS (
+                SELECT 1 FROM blobs b
+                WHERE b.tx_hash = t.tx_hash AND b.parent_dp_hash = t.parent_dp_hash AND b.contract_name = $3
+            ))
+            AND ($4 IS NULL OR (t.block_height, t."index") < ($4, $5))
+        ORDER BY t.block_height DESC NULLS FIRST, t."index" DESC
+        LIMIT $6
+        "#,
+    );
+    let transactions = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, TransactionDb>(query)
+            .bind(&identity)
+            .bind(&pagination.status)
+            .bind(&pagination.contract)
+            .bind(pagination.before_block)
+            .bind(pagination.before_index)
+            .bind(nb_results)
+            .fetch_all(pool)
+            .await
+            .map(|db| db
+                .into_iter()
+                .map(Into::<APITransaction>::into)
+                .collect())),
+        "Failed to fetch transactions by identity"
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(transactions))
+}
+
+#[utoipa::path(
+    get,
+    tag = "Indexer",
+    params(
+        ("identity" = String, Path, description = "Identity"),
+    ),
+    path = "/identity/{identity}/summary",
+    responses(
+        (status = OK, body = APIIdentitySummary)
+    )
+)]
+pub async fn get_identity_summary(
+    Path(identity): Path<String>,
+    State(state): State<ExplorerApiState>,
+) -> Result<Json<APIIdentitySummary>, StatusCode> {
+    let txs_by_status = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_as::<
+            _,
+            (TransactionStatusDb, i64),
+        >(
+            r#"
+            SELECT transaction_status, COUNT(*) FROM (
+                SELECT transaction_status, ROW_NUMBER() OVER (
+                    PARTITION BY tx_hash ORDER BY block_height DESC NULLS FIRST, "index" DESC
+                ) AS tx_rank
+                FROM transactions
+                WHERE identity = $1 AND transaction_type = 'blob_transaction'
+            ) t
+            WHERE tx_rank = 1
+            GROUP BY transaction_status
+            ORDER BY transaction_status
+            "#,
+        )
+        .bind(&identity)
+        .fetch_all(pool)
+        .await),
+        "Failed to count transactions by identity"
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if txs_by_status.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let first_seen = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, (i32, NaiveDateTime)>(
+            r#"
+            SELECT t.block_height, bl.timestamp
+            FROM transactions t
+            JOIN blocks bl ON t.block_hash = bl.hash
+            WHERE t.identity = $1 AND t.transaction_type = 'blob_transaction'
+            ORDER BY t.block_height ASC, t."index" ASC
+            LIMIT 1
+            "#,
+        )
+        .bind(&identity)
+        .fetch_optional(pool)
+        .await),
+        "Failed to fetch first transaction of identity"
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let contracts = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT DISTINCT b.contract_name
+            FROM blobs b
+            JOIN transactions t ON t.tx_hash = b.tx_hash AND t.parent_dp_hash = b.parent_dp_hash
+            WHERE t.identity = $1
+            ORDER BY b.contract_name
+            "#,
+        )
+        .bind(&identity)
+        .fetch_all(pool)
+        .await),
+        "Failed to fetch contracts of identity"
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(APIIdentitySummary {
+        total_txs: txs_by_status.iter().map(|(_, count)| count).sum(),
+        first_seen_block: first_seen.map(|(height, _)| BlockHeight(height as u64)),
+        first_seen_timestamp: first_seen
+            .map(|(_, timestamp)| TimestampMs(timestamp.and_utc().timestamp_millis() as u128)),
+        identity,
+        txs_by_status,
+        contracts,
+    }))
+}
+
 #[utoipa::path(
     get,
     tag = "Indexer",
@@ -336,19 +583,19 @@ pub async fn get_transactions_by_height(
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<Vec<APITransaction>>, StatusCode> {
     let transactions = log_error!(
-        sqlx::query_as::<_, TransactionDb>(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, TransactionDb>(
             r#"
         SELECT t.*, b.timestamp
         FROM transactions t
         JOIN blocks b ON t.block_hash = b.hash
         WHERE b.height = $1 AND t.transaction_type = 'blob_transaction'
-        ORDER BY t.index DESC
+        ORDER BY t."index" DESC
         "#,
         )
         .bind(height)
-        .fetch_all(&state.db)
+        .fetch_all(pool)
         .await
-        .map(|db| db.into_iter().map(Into::<APITransaction>::into).collect()),
+        .map(|db| db.into_iter().map(Into::<APITransaction>::into).collect())),
         "Failed to fetch transactions by height"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -356,6 +603,42 @@ pub async fn get_transactions_by_height(
     Ok(Json(transactions))
 }
 
+#[utoipa::path(
+    get,
+    tag = "Indexer",
+    params(
+        ("dp_hash" = String, Path, description = "Data proposal hash")
+    ),
+    path = "/transactions/data_proposal/{dp_hash}",
+    responses(
+        (status = OK, body = [APITransaction])
+    )
+)]
+pub async fn get_transactions_by_data_proposal(
+    Path(dp_hash): Path<String>,
+    State(state): State<ExplorerApiState>,
+) -> Result<Json<Vec<APITransaction>>, StatusCode> {
+    let transactions = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, TransactionDb>(
+            r#"
+        SELECT t.*, b.timestamp
+        FROM transactions t
+        LEFT JOIN blocks b ON t.block_hash = b.hash
+        WHERE t.parent_dp_hash = $1
+        ORDER BY t.block_height ASC NULLS LAST, t."index" ASC NULLS LAST
+        "#,
+        )
+        .bind(&dp_hash)
+        .fetch_all(pool)
+        .await
+        .map(|db| db.into_iter().map(Into::<APITransaction>::into).collect())),
+        "Failed to fetch transactions by data proposal"
+    )
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(transactions))
+}
+
 #[utoipa::path(
     get,
     tag = "Indexer",
@@ -372,7 +655,7 @@ pub async fn get_transaction_with_hash(
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<APITransaction>, StatusCode> {
     let transaction = log_error!(
-        sqlx::query_as::<_, TransactionDb>(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, TransactionDb>(
             r#"
 SELECT
     tx_hash,
@@ -381,21 +664,21 @@ SELECT
     transaction_status,
     parent_dp_hash,
     block_hash,
-    index,
+    "index",
     b.timestamp,
     lane_id,
     identity
 FROM transactions t
 LEFT JOIN blocks b ON t.block_hash = b.hash
 WHERE t.tx_hash = $1 AND transaction_type='blob_transaction'
-ORDER BY block_height DESC, index DESC
+ORDER BY block_height DESC NULLS FIRST, "index" DESC
 LIMIT 1;
         "#,
         )
         .bind(tx_hash)
-        .fetch_optional(&state.db)
+        .fetch_optional(pool)
         .await
-        .map(|db| db.map(|test| { Into::<APITransaction>::into(test) })),
+        .map(|db| db.map(|test| { Into::<APITransaction>::into(test) }))),
         "Failed to fetch transaction by hash"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -422,7 +705,10 @@ pub async fn get_transaction_events(
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<Vec<APITransactionEvents>>, StatusCode> {
     let rows = log_error!(
-        sqlx::query(
+        with_db!(&state.db, |pool| sqlx::query_as::<
+            _,
+            (ConsensusProposalHash, i64, SqlJson<Vec<serde_json::Value>>),
+        >(
             r#"
 with filtered as (
     SELECT 
@@ -431,7 +717,7 @@ with filtered as (
         t.tx_hash,
         t.parent_dp_hash,
         t.events,
-        t.index
+        t."index"
     FROM transaction_state_events t
     LEFT JOIN blocks b 
         ON t.block_hash = b.hash
@@ -442,35 +728,30 @@ with filtered as (
 SELECT
     block_hash,
     height,
-    tx_hash,
     events
 FROM
-    (SELECT filtered.*, parent_dp_hash = FIRST_VALUE(parent_dp_hash) OVER (ORDER BY height DESC, index DESC) as first_res FROM filtered)
+    (SELECT filtered.*, parent_dp_hash = FIRST_VALUE(parent_dp_hash) OVER (ORDER BY height DESC, "index" DESC) as first_res FROM filtered)
 WHERE first_res = TRUE
 ORDER BY 
     height DESC,
-    index DESC;
+    "index" DESC;
 "#,
         )
         .bind(tx_hash)
-        .fetch_all(&state.db)
-        .await,
+        .fetch_all(pool)
+        .await),
         "Failed to fetch transaction events"
     )
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     let transactions: Result<Vec<APITransactionEvents>, anyhow::Error> = rows
         .into_iter()
-        .map(|row| {
-            let block_hash = row.try_get("block_hash")?;
-            let block_height: i64 = row.try_get("height")?;
+        .map(|(block_hash, block_height, events)| {
             let block_height = BlockHeight(block_height.try_into()?);
-            let events: serde_json::Value = row.try_get("events")?;
-            let events: Vec<serde_json::Value> = serde_json::from_value(events)?;
             Ok(APITransactionEvents {
                 block_hash,
                 block_height,
-                events,
+                events: events.0,
             })
         })
         .collect();
@@ -499,85 +780,119 @@ pub async fn get_blob_transactions_by_contract(
     Path(contract_name): Path<String>,
     State(state): State<ExplorerApiState>,
 ) -> Result<Json<Vec<TransactionWithBlobs>>, StatusCode> {
-    let rows = log_error!(sqlx::query(
+    // One row per blob of the contract, grouped by transaction below
+    let query = state.db.by_backend(
         r#"
-        with blobs as (
-            SELECT blobs.*, array_remove(ARRAY_AGG(blob_proof_outputs.hyle_output), NULL) AS proof_outputs
-            FROM blobs
-            LEFT JOIN blob_proof_outputs ON blobs.parent_dp_hash = blob_proof_outputs.blob_parent_dp_hash AND blobs.tx_hash = blob_proof_outputs.blob_tx_hash AND blobs.blob_index = blob_proof_outputs.blob_index
-            WHERE blobs.contract_name = $1
-            GROUP BY blobs.parent_dp_hash, blobs.tx_hash, blobs.blob_index, blobs.identity
-        )
         SELECT
             t.tx_hash,
             t.parent_dp_hash,
             t.block_hash,
-            t.index,
+            t."index",
             t.version,
             t.transaction_type,
             t.transaction_status,
             t.identity,
-            array_agg(ROW(b.contract_name, b.data, b.proof_outputs)) AS blobs
+            b.contract_name,
+            b.data,
+            c.program_id,
+            COALESCE(jsonb_agg(bpo.hyle_output) FILTER (WHERE bpo.hyle_output IS NOT NULL), '[]'::jsonb) AS proof_outputs
         FROM blobs b
-        JOIN transactions t on t.tx_hash = b.tx_hash AND t.parent_dp_hash = b.parent_dp_hash
-        GROUP BY
+        JOIN transactions t ON t.tx_hash = b.tx_hash AND t.parent_dp_hash = b.parent_dp_hash
+        LEFT JOIN contracts c ON c.contract_name = b.contract_name
+        LEFT JOIN blob_proof_outputs bpo ON b.parent_dp_hash = bpo.blob_parent_dp_hash AND b.tx_hash = bpo.blob_tx_hash AND b.blob_index = bpo.blob_index
+        WHERE b.contract_name = $1
+        GROUP BY t.parent_dp_hash, t.tx_hash, b.parent_dp_hash, b.tx_hash, b.blob_index, c.program_id
+        ORDER BY t.parent_dp_hash, t.tx_hash, b.blob_index
+        "#,
+        r#"
+        SELECT
             t.tx_hash,
             t.parent_dp_hash,
             t.block_hash,
-            t.index,
+            t."index",
             t.version,
             t.transaction_type,
             t.transaction_status,
-            t.identity
+            t.identity,
+            b.contract_name,
+            b.data,
+            c.program_id,
+            json_group_array(json(bpo.hyle_output)) FILTER (WHERE bpo.hyle_output IS NOT NULL) AS proof_outputs
+        FROM blobs b
+        JOIN transactions t ON t.tx_hash = b.tx_hash AND t.parent_dp_hash = b.parent_dp_hash
+        LEFT JOIN contracts c ON c.contract_name = b.contract_name
+        LEFT JOIN blob_proof_outputs bpo ON b.parent_dp_hash = bpo.blob_parent_dp_hash AND b.tx_hash = bpo.blob_tx_hash AND b.blob_index = bpo.blob_index
+        WHERE b.contract_name = $1
+        GROUP BY t.parent_dp_hash, t.tx_hash, b.blob_index, c.program_id
+        ORDER BY t.parent_dp_hash, t.tx_hash, b.blob_index
         "#,
+    );
+    let rows = log_error!(
+        with_db!(&state.db, |pool| sqlx::query_as::<_, BlobOfTransactionDb>(
+            query
+        )
+        .bind(&contract_name)
+        .fetch_all(pool)
+        .await),
+        "Failed to fetch blob transactions by contract"
     )
-    .bind(contract_name.clone())
-    .fetch_all(&state.db)
-    .await, "Failed to fetch blob transactions by contract")
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let transactions: Result<Vec<TransactionWithBlobs>, _> = rows
-        .into_iter()
-        .map(|row| {
-            let api_tx: TransactionDb = FromRow::from_row(&row)?;
-            let Some(block_hash) = api_tx.block_hash else {
-                return Err(sqlx::Error::RowNotFound);
-            };
-            let blobs: Vec<(String, Vec<u8>, Vec<serde_json::Value>)> = row.try_get("blobs")?;
-            let blobs = blobs
-                .into_iter()
-                .map(|(contract_name, data, proof_outputs)| BlobWithStatus {
-                    contract_name,
-                    data,
-                    proof_outputs,
-                })
-                .collect();
-            let Some(identity) = api_tx.identity else {
-                return Err(sqlx::Error::RowNotFound);
-            };
-
-            Ok(TransactionWithBlobs {
-                tx_hash: api_tx.tx_hash.0,
-                parent_dp_hash: api_tx.parent_dp_hash,
-                block_hash,
-                index: api_tx.index.unwrap_or(0),
-                version: api_tx.version,
-                transaction_type: api_tx.transaction_type,
-                transaction_status: api_tx.transaction_status,
-                timestamp: api_tx
-                    .timestamp
-                    .map(|t| TimestampMs(t.and_utc().timestamp_millis() as u128)),
-                lane_id: api_tx.lane_id.map(|l| l.0),
-                identity,
-                blobs,
-            })
-        })
-        .collect();
-    match transactions {
-        Ok(transactions) => Ok(Json(transactions)),
-        Err(e) => {
-            tracing::warn!("Failed to parse transactions with blobs: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+    let mut transactions: Vec<TransactionWithBlobs> = vec![];
+    for row in rows {
+        let decoded_data = state.blob_decoders.decode(
+            &ContractName(row.contract_name.clone()),
+            row.program_id.map(ProgramId).as_ref(),
+            &row.data,
+        );
+        let blob = BlobWithStatus {
+            contract_name: row.contract_name,
+            data: row.data,
+            decoded_data,
+            proof_outputs: row.proof_outputs,
+        };
+        if let Some(tx) = transactions.last_mut() {
+            if tx.tx_hash == row.tx.tx_hash.0 && tx.parent_dp_hash == row.tx.parent_dp_hash {
+                tx.blobs.push(blob);
+                continue;
+            }
         }
+
+        let api_tx = row.tx;
+        let (Some(block_hash), Some(identity)) = (api_tx.block_hash, api_tx.identity) else {
+            tracing::warn!(
+                "Failed to parse transactions with blobs: {} is missing its block hash or identity",
+                api_tx.tx_hash.0
+            );
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        };
+        transactions.push(TransactionWithBlobs {
+            tx_hash: api_tx.tx_hash.0,
+            parent_dp_hash: api_tx.parent_dp_hash,
+            block_hash,
+            index: api_tx.index.unwrap_or(0),
+            version: api_tx.version,
+            transaction_type: api_tx.transaction_type,
+            transaction_status: api_tx.transaction_status,
+            timestamp: api_tx
+                .timestamp
+                .map(|t| TimestampMs(t.and_utc().timestamp_millis() as u128)),
+            lane_id: api_tx.lane_id.map(|l| l.0),
+            identity,
+            blobs: vec![blob],
+        });
     }
+
+    Ok(Json(transactions))
+}
+
+#[derive(sqlx::FromRow)]
+struct BlobOfTransactionDb {
+    #[sqlx(flatten)]
+    tx: TransactionDb,
+    contract_name: String,
+    data: Vec<u8>,
+    program_id: Option<Vec<u8>>,
+    #[sqlx(json)]
+    proof_outputs: Vec<serde_json::Value>,
 }
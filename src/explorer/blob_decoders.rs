@@ -0,0 +1,606 @@
+//! Decoders giving a human-readable view of the blobs served by the explorer.
+//!
+//! Decoders are looked up by contract name first, then by the program id of the contract,
+//! so that every instance of a bundled contract is decoded whatever its name.
+
+use std::{collections::HashMap, fmt, io, marker::PhantomData, sync::Arc};
+
+use amm::AmmAction;
+use borsh::BorshDeserialize;
+use hydentity::HydentityAction;
+use hyllar::HyllarAction;
+use serde::Serialize;
+use serde_json::Value;
+use smt_token::SmtTokenAction;
+use uuid_tld::UuidTldAction;
+
+use crate::model::*;
+
+/// Turns the raw data of a blob into a JSON view of the action it holds.
+pub trait BlobDecoder: Send + Sync {
+    /// Returns `None` when the data is not an action this decoder knows about.
+    fn decode(&self, data: &[u8]) -> Option<Value>;
+}
+
+/// JSON view of a decoded blob. `caller` and `callees` are only set for structured blobs.
+#[derive(Serialize)]
+struct DecodedBlob<Parameters> {
+    caller: Option<BlobIndex>,
+    callees: Option<Vec<BlobIndex>>,
+    parameters: Parameters,
+}
+
+/// Decodes blobs holding a borsh-encoded `Action`, wrapped in a [`StructuredBlobData`] or not.
+pub struct ActionDecoder<Action>(PhantomData<fn() -> Action>);
+
+impl<Action> Default for ActionDecoder<Action> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<Action: BorshDeserialize + Serialize> BlobDecoder for ActionDecoder<Action> {
+    fn decode(&self, data: &[u8]) -> Option<Value> {
+        let decoded = match borsh::from_slice::<StructuredBlobData<Action>>(data) {
+            Ok(blob) => DecodedBlob {
+                caller: blob.caller,
+                callees: blob.callees,
+                parameters: blob.parameters,
+            },
+            Err(_) => DecodedBlob {
+                caller: None,
+                callees: None,
+                parameters: borsh::from_slice::<Action>(data).ok()?,
+            },
+        };
+        serde_json::to_value(decoded).ok()
+    }
+}
+
+/// Tries each decoder in turn, for contracts accepting several kinds of actions.
+impl BlobDecoder for Vec<Arc<dyn BlobDecoder>> {
+    fn decode(&self, data: &[u8]) -> Option<Value> {
+        self.iter().find_map(|decoder| decoder.decode(data))
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// 128 bits integers don't fit in json numbers, they are shown as strings when too large.
+fn wide_int<T: TryInto<i64> + ToString + Copy>(value: T) -> Value {
+    match value.try_into() {
+        Ok(value) => value.into(),
+        Err(_) => value.to_string().into(),
+    }
+}
+
+/// Nesting limit of the decoded values, schemas may describe recursive types
+const MAX_DEPTH: usize = 64;
+
+/// Reads values with the borsh layouts of a [ContractSchema], for contracts without a built-in
+/// decoder.
+///
+/// Decoded values follow the serde representation of the equivalent Rust types: unit variants
+/// are strings, other variants are objects keyed by the variant name, `Option`s are null or
+/// their value and newtypes are their content. Bytes are shown as hex.
+struct SchemaReader<'a> {
+    schema: &'a ContractSchema,
+}
+
+impl SchemaReader<'_> {
+    fn read(&self, declaration: &str, reader: &mut &[u8], depth: usize) -> io::Result<Value> {
+        if depth > MAX_DEPTH {
+            return Err(invalid_data(format!("{declaration} is nested too deeply")));
+        }
+        let Some(definition) = self.schema.definitions.get(declaration) else {
+            return Err(invalid_data(format!("No definition of {declaration}")));
+        };
+        Ok(match definition {
+            TypeDefinition::Primitive { size } => Self::read_primitive(declaration, *size, reader)?,
+            TypeDefinition::Sequence {
+                length_width,
+                min_length,
+                max_length,
+                elements,
+            } => {
+                let len = match length_width {
+                    0 => *max_length,
+                    width => read_uint(*width, reader)?,
+                };
+                if len < *min_length || len > *max_length {
+                    return Err(invalid_data(format!(
+                        "Invalid length {len} of {declaration}"
+                    )));
+                }
+                if declaration == "String" {
+                    let bytes = take(reader, len)?;
+                    return String::from_utf8(bytes.to_vec())
+                        .map(Value::from)
+                        .map_err(|e| invalid_data(e.to_string()));
+                }
+                if elements == "u8" {
+                    return Ok(hex::encode(take(reader, len)?).into());
+                }
+                // Each item takes at least one byte, don't trust larger lengths
+                if len > reader.len() as u64 {
+                    return Err(invalid_data(format!(
+                        "Invalid length {len} of {declaration}"
+                    )));
+                }
+                (0..len)
+                    .map(|_| self.read(elements, reader, depth + 1))
+                    .collect::<io::Result<Vec<_>>>()?
+                    .into()
+            }
+            TypeDefinition::Tuple { elements } => elements
+                .iter()
+                .map(|element| self.read(element, reader, depth + 1))
+                .collect::<io::Result<Vec<_>>>()?
+                .into(),
+            TypeDefinition::Enum {
+                tag_width,
+                variants,
+            } => {
+                let tag = read_uint(*tag_width, reader)?;
+                let Some(variant) = variants
+                    .iter()
+                    .find(|variant| u64::try_from(variant.discriminant).ok() == Some(tag))
+                else {
+                    return Err(invalid_data(format!(
+                        "Invalid variant {tag} of {declaration}"
+                    )));
+                };
+                let content = self.read(&variant.declaration, reader, depth + 1)?;
+                if declaration.starts_with("Option<") {
+                    content
+                } else if self.is_unit(&variant.declaration) {
+                    variant.name.clone().into()
+                } else {
+                    Value::Object([(variant.name.clone(), content)].into_iter().collect())
+                }
+            }
+            TypeDefinition::Struct { fields } => match fields.as_slice() {
+                [] => Value::Null,
+                [FieldDefinition {
+                    name: None,
+                    declaration,
+                }] => self.read(declaration, reader, depth + 1)?,
+                fields if fields.iter().all(|field| field.name.is_none()) => fields
+                    .iter()
+                    .map(|field| self.read(&field.declaration, reader, depth + 1))
+                    .collect::<io::Result<Vec<_>>>()?
+                    .into(),
+                fields => Value::Object(
+                    fields
+                        .iter()
+                        .map(|field| {
+                            Ok((
+                                field.name.clone().unwrap_or_default(),
+                                self.read(&field.declaration, reader, depth + 1)?,
+                            ))
+                        })
+                        .collect::<io::Result<_>>()?,
+                ),
+            },
+        })
+    }
+
+    fn read_primitive(declaration: &str, size: u8, reader: &mut &[u8]) -> io::Result<Value> {
+        Ok(match declaration {
+            "bool" => bool::deserialize_reader(reader)?.into(),
+            "u8" => u8::deserialize_reader(reader)?.into(),
+            "u16" => u16::deserialize_reader(reader)?.into(),
+            "u32" => u32::deserialize_reader(reader)?.into(),
+            "u64" => u64::deserialize_reader(reader)?.into(),
+            "u128" => wide_int(u128::deserialize_reader(reader)?),
+            "i8" => i8::deserialize_reader(reader)?.into(),
+            "i16" => i16::deserialize_reader(reader)?.into(),
+            "i32" => i32::deserialize_reader(reader)?.into(),
+            "i64" => i64::deserialize_reader(reader)?.into(),
+            "i128" => wide_int(i128::deserialize_reader(reader)?),
+            "f32" => f32::deserialize_reader(reader)?.into(),
+            "f64" => f64::deserialize_reader(reader)?.into(),
+            _ if size == 0 => Value::Null,
+            _ => hex::encode(take(reader, size as u64)?).into(),
+        })
+    }
+
+    fn is_unit(&self, declaration: &str) -> bool {
+        match self.schema.definitions.get(declaration) {
+            Some(TypeDefinition::Struct { fields }) => fields.is_empty(),
+            Some(TypeDefinition::Primitive { size: 0 }) => true,
+            _ => false,
+        }
+    }
+
+    /// Reads the whole data as a value of `declaration`
+    fn read_all(&self, declaration: &str, mut data: &[u8]) -> Option<Value> {
+        let value = self.read(declaration, &mut data, 0).ok()?;
+        data.is_empty().then_some(value)
+    }
+}
+
+/// Reads a little endian unsigned integer of `width` bytes
+fn read_uint(width: u8, reader: &mut &[u8]) -> io::Result<u64> {
+    if width > 8 {
+        return Err(invalid_data(format!("Invalid integer width {width}")));
+    }
+    let mut bytes = [0u8; 8];
+    for (byte, value) in bytes.iter_mut().zip(take(reader, width as u64)?) {
+        *byte = *value;
+    }
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn take<'a>(reader: &mut &'a [u8], len: u64) -> io::Result<&'a [u8]> {
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|len| *len <= reader.len())
+        .ok_or_else(|| invalid_data(format!("Unexpected end of data, {len} bytes needed")))?;
+    let (taken, rest) = reader.split_at(len);
+    *reader = rest;
+    Ok(taken)
+}
+
+/// Decodes the actions of a contract with its [ContractSchema], as registered along the contract.
+impl BlobDecoder for ContractSchema {
+    fn decode(&self, data: &[u8]) -> Option<Value> {
+        let reader = SchemaReader { schema: self };
+        let decoded = match self.action_encoding {
+            ActionEncoding::Structured => {
+                let mut data = data;
+                let caller = Option::<BlobIndex>::deserialize_reader(&mut data).ok()?;
+                let callees = Option::<Vec<BlobIndex>>::deserialize_reader(&mut data).ok()?;
+                DecodedBlob {
+                    caller,
+                    callees,
+                    parameters: reader.read_all(&self.action, data)?,
+                }
+            }
+            ActionEncoding::Raw => DecodedBlob {
+                caller: None,
+                callees: None,
+                parameters: reader.read_all(&self.action, data)?,
+            },
+        };
+        serde_json::to_value(decoded).ok()
+    }
+}
+
+/// Registry of the blob decoders, by contract name and by program id.
+#[derive(Clone, Default)]
+pub struct BlobDecoders {
+    by_contract_name: HashMap<ContractName, Arc<dyn BlobDecoder>>,
+    by_program_id: HashMap<ProgramId, Arc<dyn BlobDecoder>>,
+}
+
+impl fmt::Debug for BlobDecoders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlobDecoders")
+            .field("by_contract_name", &self.by_contract_name.keys())
+            .field("by_program_id", &self.by_program_id.keys())
+            .finish()
+    }
+}
+
+impl BlobDecoders {
+    /// Registry with the decoders of the contracts bundled with the node.
+    pub fn with_builtins() -> Self {
+        let mut decoders = Self::default();
+
+        let hyle_tld: Vec<Arc<dyn BlobDecoder>> = vec![
+            Arc::new(ActionDecoder::<RegisterContractAction>::default()),
+            Arc::new(ActionDecoder::<DeleteContractAction>::default()),
+            Arc::new(ActionDecoder::<UpdateContractProgramIdAction>::default()),
+            Arc::new(ActionDecoder::<UpdateContractTimeoutWindowAction>::default()),
+        ];
+        decoders.register_contract("hyle".into(), Arc::new(hyle_tld));
+
+        decoders.register_program_id(
+            ProgramId(hyle_contracts::HYDENTITY_ID.to_vec()),
+            Arc::new(ActionDecoder::<HydentityAction>::default()),
+        );
+        decoders.register_program_id(
+            ProgramId(hyle_contracts::HYLLAR_ID.to_vec()),
+            Arc::new(ActionDecoder::<HyllarAction>::default()),
+        );
+        decoders.register_program_id(
+            ProgramId(hyle_contracts::SMT_TOKEN_ID.to_vec()),
+            Arc::new(ActionDecoder::<SmtTokenAction>::default()),
+        );
+        decoders.register_program_id(
+            ProgramId(hyle_contracts::STAKING_ID.to_vec()),
+            Arc::new(ActionDecoder::<StakingAction>::default()),
+        );
+        decoders.register_program_id(
+            ProgramId(hyle_contracts::AMM_ID.to_vec()),
+            Arc::new(ActionDecoder::<AmmAction>::default()),
+        );
+        decoders.register_program_id(
+            ProgramId(hyle_contracts::UUID_TLD_ID.to_vec()),
+            Arc::new(ActionDecoder::<UuidTldAction>::default()),
+        );
+
+        decoders
+    }
+
+    pub fn register_contract(
+        &mut self,
+        contract_name: ContractName,
+        decoder: Arc<dyn BlobDecoder>,
+    ) {
+        self.by_contract_name.insert(contract_name, decoder);
+    }
+
+    pub fn register_program_id(&mut self, program_id: ProgramId, decoder: Arc<dyn BlobDecoder>) {
+        self.by_program_id.insert(program_id, decoder);
+    }
+
+    pub fn register_schemas(&mut self, schemas: &HashMap<String, ContractSchema>) {
+        for (contract_name, schema) in schemas {
+            self.register_contract(contract_name.into(), Arc::new(schema.clone()));
+        }
+    }
+
+    /// Decodes a blob sent to `contract_name`, whose program id is given if known.
+    pub fn decode(
+        &self,
+        contract_name: &ContractName,
+        program_id: Option<&ProgramId>,
+        data: &[u8],
+    ) -> Option<Value> {
+        self.by_contract_name
+            .get(contract_name)
+            .or_else(|| program_id.and_then(|program_id| self.by_program_id.get(program_id)))
+            .and_then(|decoder| decoder.decode(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_builtin_decoders() {
+        let decoders = BlobDecoders::with_builtins();
+        let hyllar_id = ProgramId(hyle_contracts::HYLLAR_ID.to_vec());
+
+        let blob = HyllarAction::Transfer {
+            recipient: "bob@hydentity".to_string(),
+            amount: 10,
+        }
+        .as_blob("oranj".into(), None, Some(vec![BlobIndex(1)]));
+        assert_eq!(
+            decoders.decode(&"oranj".into(), Some(&hyllar_id), &blob.data.0),
+            Some(json!({
+                "caller": null,
+                "callees": [1],
+                "parameters": { "Transfer": { "recipient": "bob@hydentity", "amount": 10 } }
+            }))
+        );
+
+        // Unknown program ids are not decoded
+        assert_eq!(
+            decoders.decode(
+                &"oranj".into(),
+                Some(&ProgramId(vec![1, 2, 3])),
+                &blob.data.0
+            ),
+            None
+        );
+
+        // Hydentity blobs are not structured
+        let hydentity_id = ProgramId(hyle_contracts::HYDENTITY_ID.to_vec());
+        let blob = HydentityAction::RegisterIdentity {
+            account: "bob@hydentity".to_string(),
+        }
+        .as_blob("hydentity".into());
+        assert_eq!(
+            decoders.decode(&"hydentity".into(), Some(&hydentity_id), &blob.data.0),
+            Some(json!({
+                "caller": null,
+                "callees": null,
+                "parameters": { "RegisterIdentity": { "account": "bob@hydentity" } }
+            }))
+        );
+
+        let blob = DeleteContractAction {
+            contract_name: "amm".into(),
+        }
+        .as_blob("hyle".into(), None, None);
+        assert_eq!(
+            decoders.decode(&"hyle".into(), None, &blob.data.0),
+            Some(json!({
+                "caller": null,
+                "callees": null,
+                "parameters": { "contract_name": "amm" }
+            }))
+        );
+    }
+
+    fn field(name: Option<&str>, declaration: &str) -> FieldDefinition {
+        FieldDefinition {
+            name: name.map(str::to_string),
+            declaration: declaration.to_string(),
+        }
+    }
+
+    fn variant(discriminant: i64, name: &str, declaration: &str) -> EnumVariantDefinition {
+        EnumVariantDefinition {
+            discriminant,
+            name: name.to_string(),
+            declaration: declaration.to_string(),
+        }
+    }
+
+    fn bytes_sequence() -> TypeDefinition {
+        TypeDefinition::Sequence {
+            length_width: 4,
+            min_length: 0,
+            max_length: u32::MAX as u64,
+            elements: "u8".to_string(),
+        }
+    }
+
+    /// Schema of the actions of a token, as exported from `borsh::BorshSchema`
+    fn token_schema(action_encoding: ActionEncoding) -> ContractSchema {
+        let definitions = [
+            (
+                "TokenAction",
+                TypeDefinition::Enum {
+                    tag_width: 1,
+                    variants: vec![
+                        variant(0, "Reset", "TokenActionReset"),
+                        variant(1, "Transfer", "TokenActionTransfer"),
+                        variant(2, "Burn", "TokenActionBurn"),
+                    ],
+                },
+            ),
+            (
+                "TokenActionReset",
+                TypeDefinition::Struct { fields: vec![] },
+            ),
+            (
+                "TokenActionTransfer",
+                TypeDefinition::Struct {
+                    fields: vec![
+                        field(Some("recipient"), "Identity"),
+                        field(Some("amount"), "u128"),
+                        field(Some("memo"), "Option<Vec<u8>>"),
+                    ],
+                },
+            ),
+            (
+                "TokenActionBurn",
+                TypeDefinition::Struct {
+                    fields: vec![field(None, "[u8; 2]")],
+                },
+            ),
+            (
+                "Identity",
+                TypeDefinition::Struct {
+                    fields: vec![field(None, "String")],
+                },
+            ),
+            (
+                "Option<Vec<u8>>",
+                TypeDefinition::Enum {
+                    tag_width: 1,
+                    variants: vec![variant(0, "None", "()"), variant(1, "Some", "Vec<u8>")],
+                },
+            ),
+            (
+                "[u8; 2]",
+                TypeDefinition::Sequence {
+                    length_width: 0,
+                    min_length: 2,
+                    max_length: 2,
+                    elements: "u8".to_string(),
+                },
+            ),
+            ("String", bytes_sequence()),
+            ("Vec<u8>", bytes_sequence()),
+            ("()", TypeDefinition::Primitive { size: 0 }),
+            ("u8", TypeDefinition::Primitive { size: 1 }),
+            ("u128", TypeDefinition::Primitive { size: 16 }),
+        ];
+        ContractSchema {
+            action: "TokenAction".to_string(),
+            action_encoding,
+            state: None,
+            program_outputs: None,
+            definitions: definitions
+                .into_iter()
+                .map(|(declaration, definition)| (declaration.to_string(), definition))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_schema_decoder() {
+        let mut decoders = BlobDecoders::default();
+        decoders.register_schemas(&HashMap::from([
+            ("raw_token".to_string(), token_schema(ActionEncoding::Raw)),
+            (
+                "structured_token".to_string(),
+                token_schema(ActionEncoding::Structured),
+            ),
+        ]));
+
+        let mut data = vec![1];
+        data.extend(borsh::to_vec(&("bob@hydentity", u128::MAX, Some(vec![0xab_u8]))).unwrap());
+        assert_eq!(
+            decoders.decode(&"raw_token".into(), None, &data),
+            Some(json!({
+                "caller": null,
+                "callees": null,
+                "parameters": { "Transfer": {
+                    "recipient": "bob@hydentity",
+                    "amount": u128::MAX.to_string(),
+                    "memo": "ab"
+                }}
+            }))
+        );
+        assert_eq!(
+            decoders.decode(&"raw_token".into(), None, &[2, 0xca, 0xfe]),
+            Some(json!({ "caller": null, "callees": null, "parameters": { "Burn": "cafe" } }))
+        );
+
+        let data: BlobData = StructuredBlobData {
+            caller: Some(BlobIndex(0)),
+            callees: None,
+            parameters: 0_u8,
+        }
+        .into();
+        assert_eq!(
+            decoders.decode(&"structured_token".into(), None, &data.0),
+            Some(json!({ "caller": 0, "callees": null, "parameters": "Reset" }))
+        );
+        // The action encoding of the schema is followed
+        assert_eq!(decoders.decode(&"raw_token".into(), None, &data.0), None);
+
+        // Trailing bytes, truncated data and unknown variants are rejected
+        assert_eq!(decoders.decode(&"raw_token".into(), None, &[0, 0]), None);
+        assert_eq!(decoders.decode(&"raw_token".into(), None, &[2, 0xca]), None);
+        assert_eq!(decoders.decode(&"raw_token".into(), None, &[3]), None);
+        assert_eq!(decoders.decode(&"other".into(), None, &data.0), None);
+    }
+
+    #[test]
+    fn test_schema_decoder_limits() {
+        // A recursive type that doesn't consume any byte
+        let schema = ContractSchema {
+            action: "Loop".to_string(),
+            action_encoding: ActionEncoding::Raw,
+            state: None,
+            program_outputs: None,
+            definitions: [(
+                "Loop".to_string(),
+                TypeDefinition::Struct {
+                    fields: vec![field(None, "Loop")],
+                },
+            )]
+            .into_iter()
+            .collect(),
+        };
+        assert_eq!(schema.decode(&[]), None);
+
+        // Lengths larger than the data are rejected before reading the elements
+        let mut schema = token_schema(ActionEncoding::Raw);
+        schema.action = "Vec<u128>".to_string();
+        schema.definitions.insert(
+            "Vec<u128>".to_string(),
+            TypeDefinition::Sequence {
+                length_width: 4,
+                min_length: 0,
+                max_length: u32::MAX as u64,
+                elements: "u128".to_string(),
+            },
+        );
+        assert_eq!(schema.decode(&u32::MAX.to_le_bytes()), None);
+    }
+}
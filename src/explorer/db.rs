@@ -0,0 +1,115 @@
+//! Database backends of the indexer and the explorer.
+//!
+//! Postgres is used by default. A `sqlite:` database url selects an embedded SQLite database,
+//! which must be a file when both the indexer and the explorer run, as each of them opens its own pool.
+
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use sqlx::{
+    postgres::PgPoolOptions,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    PgPool, SqlitePool,
+};
+
+pub static PG_MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./src/indexer/migrations");
+pub static SQLITE_MIGRATOR: sqlx::migrate::Migrator =
+    sqlx::migrate!("./src/indexer/migrations_sqlite");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbBackend {
+    Postgres,
+    Sqlite,
+}
+
+impl DbBackend {
+    pub fn from_url(database_url: &str) -> Self {
+        if database_url.starts_with("sqlite:") {
+            DbBackend::Sqlite
+        } else {
+            DbBackend::Postgres
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum IndexerDb {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+}
+
+impl IndexerDb {
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let db = match DbBackend::from_url(database_url) {
+            DbBackend::Postgres => IndexerDb::Postgres(
+                PgPoolOptions::new()
+                    .max_connections(20)
+                    .acquire_timeout(std::time::Duration::from_secs(1))
+                    .connect(database_url)
+                    .await
+                    .context("Failed to connect to the database")?,
+            ),
+            DbBackend::Sqlite => {
+                let options = SqliteConnectOptions::from_str(database_url)
+                    .context("Parsing SQLite database url")?
+                    .create_if_missing(true)
+                    .journal_mode(SqliteJournalMode::Wal)
+                    .foreign_keys(true);
+                IndexerDb::Sqlite(
+                    SqlitePoolOptions::new()
+                        .max_connections(20)
+                        .acquire_timeout(std::time::Duration::from_secs(1))
+                        .connect_with(options)
+                        .await
+                        .context("Failed to open the SQLite database")?,
+                )
+            }
+        };
+        Ok(db)
+    }
+
+    pub async fn migrate(&self) -> Result<()> {
+        let migrations = async {
+            match self {
+                IndexerDb::Postgres(pool) => PG_MIGRATOR.run(pool).await,
+                IndexerDb::Sqlite(pool) => SQLITE_MIGRATOR.run(pool).await,
+            }
+        };
+        tokio::time::timeout(tokio::time::Duration::from_secs(60), migrations)
+            .await
+            .context("Timed out running migrations")?
+            .context("Running migrations")
+    }
+
+    pub fn backend(&self) -> DbBackend {
+        match self {
+            IndexerDb::Postgres(_) => DbBackend::Postgres,
+            IndexerDb::Sqlite(_) => DbBackend::Sqlite,
+        }
+    }
+
+    /// Upper bound of bind parameters in a single statement, with some security margin.
+    pub fn max_bind_params(&self) -> usize {
+        self.by_backend(65000, 32000)
+    }
+
+    /// Picks the backend-specific flavour of a query, for the few that can't be written portably.
+    pub fn by_backend<T>(&self, postgres: T, sqlite: T) -> T {
+        match self.backend() {
+            DbBackend::Postgres => postgres,
+            DbBackend::Sqlite => sqlite,
+        }
+    }
+}
+
+/// Runs the closure-like body against the pool of the backend in use.
+/// The body is type-checked once for each backend, so it must be valid for both of them.
+macro_rules! with_db {
+    ($db:expr, |$pool:ident| $body:expr) => {
+        match $db {
+            $crate::explorer::db::IndexerDb::Postgres($pool) => $body,
+            $crate::explorer::db::IndexerDb::Sqlite($pool) => $body,
+        }
+    };
+}
+pub(crate) use with_db;
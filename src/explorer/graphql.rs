@@ -0,0 +1,1401 @@
+//! GraphQL API over the indexer database, enabled with the `graphql` feature.
+//!
+//! Queries are served on `POST /graphql`, with GraphiQL on `GET /graphql`. Subscriptions use the
+//! `graphql-transport-ws` or `graphql-ws` protocols on `/graphql/ws`, and are fed by the same
+//! bus events as the explorer websocket.
+//!
+//! Lists are paginated from the most recent item, with opaque `after` cursors.
+
+use std::{future::ready, str::FromStr};
+
+use async_graphql::{
+    connection::{Connection, CursorType, Edge},
+    http::{GraphiQLSource, Protocols, WsMessage, ALL_WEBSOCKET_PROTOCOLS},
+    Context, EmptyMutation, Enum, Error, InputObject, Json as GraphQLJson, Object, OutputType,
+    Request, Response, Result, Schema, SimpleObject, Subscription,
+};
+use axum::{
+    extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade},
+    http::{header::SEC_WEBSOCKET_PROTOCOL, HeaderMap},
+    response::{Html, IntoResponse},
+    routing::get,
+    Extension, Json, Router,
+};
+use futures::{SinkExt, Stream, StreamExt};
+use hyle_model::api::{APIBlock, ExplorerWsEvent, TransactionStatusDb, TransactionTypeDb};
+use hyle_modules::log_error;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+
+use super::{
+    api::{BlockDb, DataProposalHashDb, TimeoutWindowDb, TransactionDb, TxHashDb},
+    db::with_db,
+    ExplorerApiState,
+};
+use crate::model::{
+    ConsensusProposalHash, ContractName, DataProposalHash, ProgramId, TimeoutWindow, TxHash,
+};
+
+/// Page size when `first` is not given
+const DEFAULT_PAGE_SIZE: i32 = 20;
+const MAX_PAGE_SIZE: i32 = 100;
+
+/// Bounds the work a single query can trigger, nested relations each run a query
+const MAX_QUERY_DEPTH: usize = 10;
+const MAX_QUERY_COMPLEXITY: usize = 2000;
+
+pub type ExplorerSchema = Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+
+pub fn schema(state: ExplorerApiState) -> ExplorerSchema {
+    Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot)
+        .data(state)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .limit_complexity(MAX_QUERY_COMPLEXITY)
+        .finish()
+}
+
+/// Routes of the GraphQL API, to merge with the explorer routes
+pub(super) fn routes(state: &ExplorerApiState) -> Router<ExplorerApiState> {
+    Router::new()
+        .route("/graphql", get(graphiql).post(graphql))
+        .route("/graphql/ws", get(graphql_ws))
+        .layer(Extension(schema(state.clone())))
+}
+
+async fn graphiql() -> impl IntoResponse {
+    Html(
+        GraphiQLSource::build()
+            .endpoint("/v1/indexer/graphql")
+            .subscription_endpoint("/v1/indexer/graphql/ws")
+            .finish(),
+    )
+}
+
+async fn graphql(
+    Extension(schema): Extension<ExplorerSchema>,
+    Json(request): Json<Request>,
+) -> Json<Response> {
+    Json(schema.execute(request).await)
+}
+
+async fn graphql_ws(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    Extension(schema): Extension<ExplorerSchema>,
+) -> impl IntoResponse {
+    let protocol = headers
+        .get(SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|protocols| protocols.to_str().ok())
+        .and_then(|protocols| {
+            protocols
+                .split(',')
+                .find_map(|protocol| Protocols::from_str(protocol.trim()).ok())
+        })
+        .unwrap_or(Protocols::GraphQLWS);
+
+    ws.protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |socket| serve_ws(socket, schema, protocol))
+}
+
+async fn serve_ws(socket: WebSocket, schema: ExplorerSchema, protocol: Protocols) {
+    let (mut ws_tx, ws_rx) = socket.split();
+    let requests = ws_rx
+        .take_while(|message| ready(message.is_ok()))
+        .filter_map(|message| {
+            ready(match message {
+                Ok(Message::Text(text)) => Some(text.as_str().as_bytes().to_vec()),
+                Ok(Message::Binary(bytes)) => Some(bytes.to_vec()),
+                _ => None,
+            })
+        });
+
+    let mut replies = std::pin::pin!(async_graphql::http::WebSocket::new(
+        schema, requests, protocol
+    ));
+    while let Some(reply) = replies.next().await {
+        let message = match reply {
+            WsMessage::Text(text) => Message::Text(text.into()),
+            WsMessage::Close(code, reason) => Message::Close(Some(CloseFrame {
+                code,
+                reason: reason.into(),
+            })),
+        };
+        if ws_tx.send(message).await.is_err() {
+            break;
+        }
+    }
+}
+
+fn state<'a>(ctx: &Context<'a>) -> Result<&'a ExplorerApiState> {
+    ctx.data::<ExplorerApiState>()
+}
+
+fn db_error(_: anyhow::Error) -> Error {
+    Error::new("Failed to query the indexer database")
+}
+
+/// Number of rows to fetch for a page, one more than its size to know if a next page exists
+fn page_limit(first: Option<i32>) -> Result<i64> {
+    let first = first.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&first) {
+        return Err(Error::new(format!(
+            "`first` must be between 1 and {MAX_PAGE_SIZE}"
+        )));
+    }
+    Ok(i64::from(first) + 1)
+}
+
+fn connection<C, N>(
+    mut nodes: Vec<N>,
+    limit: i64,
+    has_previous_page: bool,
+    cursor: impl Fn(&N) -> C,
+) -> Connection<C, N>
+where
+    C: CursorType + Send + Sync,
+    N: OutputType,
+{
+    let page_size = usize::try_from(limit - 1).unwrap_or_default();
+    let has_next_page = nodes.len() > page_size;
+    nodes.truncate(page_size);
+
+    let mut connection = Connection::new(has_previous_page, has_next_page);
+    connection
+        .edges
+        .extend(nodes.into_iter().map(|node| Edge::new(cursor(&node), node)));
+    connection
+}
+
+/// Positions of an item in the chain, e.g. `height:index` for a transaction
+fn cursor_positions<const N: usize>(cursor: Option<&str>) -> Result<[Option<i64>; N]> {
+    let mut positions = [None; N];
+    if let Some(cursor) = cursor {
+        let parts = cursor
+            .split(':')
+            .map(i64::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| Error::new(format!("Invalid cursor {cursor}")))?;
+        if parts.len() != N {
+            return Err(Error::new(format!("Invalid cursor {cursor}")));
+        }
+        for (position, part) in positions.iter_mut().zip(parts) {
+            *position = Some(part);
+        }
+    }
+    Ok(positions)
+}
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+#[graphql(remote = "hyle_model::api::TransactionTypeDb")]
+pub enum TransactionType {
+    BlobTransaction,
+    ProofTransaction,
+    RegisterContractTransaction,
+    Stake,
+}
+
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
+#[graphql(remote = "hyle_model::api::TransactionStatusDb")]
+pub enum TransactionStatus {
+    WaitingDissemination,
+    DataProposalCreated,
+    Success,
+    Failure,
+    Sequenced,
+    TimedOut,
+}
+
+#[derive(InputObject, Clone, Debug, Default)]
+pub struct TransactionFilter {
+    /// Transactions with a blob or a proof for this contract
+    pub contract_name: Option<String>,
+    pub identity: Option<String>,
+    pub transaction_type: Option<TransactionType>,
+    pub transaction_status: Option<TransactionStatus>,
+    pub from_height: Option<i64>,
+    pub to_height: Option<i64>,
+}
+
+pub struct Block {
+    hash: ConsensusProposalHash,
+    parent_hash: ConsensusProposalHash,
+    height: i64,
+    timestamp: i64,
+    total_txs: i64,
+}
+
+impl From<APIBlock> for Block {
+    fn from(block: APIBlock) -> Self {
+        Block {
+            hash: block.hash,
+            parent_hash: block.parent_hash,
+            height: i64::try_from(block.height).unwrap_or(i64::MAX),
+            timestamp: block.timestamp,
+            total_txs: i64::try_from(block.total_txs).unwrap_or(i64::MAX),
+        }
+    }
+}
+
+impl From<BlockDb> for Block {
+    fn from(block: BlockDb) -> Self {
+        APIBlock::from(block).into()
+    }
+}
+
+async fn block_by_hash(ctx: &Context<'_>, hash: &str) -> Result<Option<Block>> {
+    let block = log_error!(
+        with_db!(&state(ctx)?.db, |pool| sqlx::query_as::<_, BlockDb>(
+            "SELECT * FROM blocks WHERE hash = $1"
+        )
+        .bind(hash)
+        .fetch_optional(pool)
+        .await),
+        "Failed to fetch block by hash"
+    )
+    .map_err(db_error)?;
+    Ok(block.map(Block::from))
+}
+
+#[Object]
+impl Block {
+    async fn hash(&self) -> &str {
+        &self.hash.0
+    }
+
+    async fn parent_hash(&self) -> &str {
+        &self.parent_hash.0
+    }
+
+    async fn height(&self) -> i64 {
+        self.height
+    }
+
+    /// Milliseconds since the UNIX epoch
+    async fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    async fn total_txs(&self) -> i64 {
+        self.total_txs
+    }
+
+    async fn parent(&self, ctx: &Context<'_>) -> Result<Option<Block>> {
+        block_by_hash(ctx, &self.parent_hash.0).await
+    }
+
+    /// Transactions of the block, last one first
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        filter: Option<TransactionFilter>,
+    ) -> Result<Connection<String, Transaction>> {
+        let filter = TransactionFilter {
+            from_height: Some(self.height),
+            to_height: Some(self.height),
+            ..filter.unwrap_or_default()
+        };
+        transactions(ctx, first, after, filter).await
+    }
+
+    /// States of the contracts updated in this block
+    async fn contract_states(&self, ctx: &Context<'_>) -> Result<Vec<ContractState>> {
+        let states = log_error!(
+            with_db!(&state(ctx)?.db, |pool| {
+                sqlx::query_as::<_, ContractStateRow>(
+                    r#"
+SELECT cs.contract_name, cs.block_hash, cs.state_commitment, b.height
+FROM contract_state cs
+JOIN blocks b ON cs.block_hash = b.hash
+WHERE cs.block_hash = $1
+ORDER BY cs.contract_name
+"#,
+                )
+                .bind(&self.hash.0)
+                .fetch_all(pool)
+                .await
+            }),
+            "Failed to fetch contract states of block"
+        )
+        .map_err(db_error)?;
+        Ok(states.into_iter().map(ContractState::from).collect())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct TransactionRow {
+    #[sqlx(flatten)]
+    tx: TransactionDb,
+    height: Option<i64>,
+}
+
+pub struct Transaction {
+    tx_hash: TxHash,
+    parent_dp_hash: DataProposalHash,
+    block_hash: Option<ConsensusProposalHash>,
+    block_height: Option<i64>,
+    index: Option<u32>,
+    version: u32,
+    transaction_type: TransactionTypeDb,
+    transaction_status: TransactionStatusDb,
+    timestamp: Option<i64>,
+    lane_id: Option<String>,
+    identity: Option<String>,
+}
+
+impl From<TransactionRow> for Transaction {
+    fn from(TransactionRow { tx, height }: TransactionRow) -> Self {
+        Transaction {
+            tx_hash: tx.tx_hash.0,
+            parent_dp_hash: tx.parent_dp_hash,
+            block_hash: tx.block_hash,
+            block_height: height,
+            index: tx.index,
+            version: tx.version,
+            transaction_type: tx.transaction_type,
+            transaction_status: tx.transaction_status,
+            timestamp: tx.timestamp.map(|t| t.and_utc().timestamp_millis()),
+            lane_id: tx.lane_id.map(|lane_id| hex::encode(&lane_id.0 .0 .0)),
+            identity: tx.identity,
+        }
+    }
+}
+
+impl Transaction {
+    fn cursor(&self) -> String {
+        format!(
+            "{}:{}",
+            self.block_height.unwrap_or_default(),
+            self.index.unwrap_or_default()
+        )
+    }
+}
+
+async fn transaction_by_id(
+    ctx: &Context<'_>,
+    tx_hash: &TxHash,
+    parent_dp_hash: &DataProposalHash,
+) -> Result<Option<Transaction>> {
+    let transaction = log_error!(
+        with_db!(&state(ctx)?.db, |pool| sqlx::query_as::<_, TransactionRow>(
+            r#"
+SELECT t.*, b.timestamp, b.height
+FROM transactions t
+LEFT JOIN blocks b ON t.block_hash = b.hash
+WHERE t.tx_hash = $1 AND t.parent_dp_hash = $2
+"#
+        )
+        .bind(&tx_hash.0)
+        .bind(&parent_dp_hash.0)
+        .fetch_optional(pool)
+        .await),
+        "Failed to fetch transaction"
+    )
+    .map_err(db_error)?;
+    Ok(transaction.map(Transaction::from))
+}
+
+async fn transactions(
+    ctx: &Context<'_>,
+    first: Option<i32>,
+    after: Option<String>,
+    filter: TransactionFilter,
+) -> Result<Connection<String, Transaction>> {
+    let limit = page_limit(first)?;
+    let [after_height, after_index] = cursor_positions::<2>(after.as_deref())?;
+    let transaction_type = filter.transaction_type.map(TransactionTypeDb::from);
+    let transaction_status = filter.transaction_status.map(TransactionStatusDb::from);
+
+    let rows = log_error!(
+        with_db!(&state(ctx)?.db, |pool| sqlx::query_as::<_, TransactionRow>(
+            r#"
+SELECT t.*, b.timestamp, b.height
+FROM transactions t
+JOIN blocks b ON t.block_hash = b.hash
+WHERE ($1 IS NULL OR EXISTS (
+        SELECT 1 FROM txs_contracts tc
+        WHERE tc.tx_hash = t.tx_hash AND tc.parent_dp_hash = t.parent_dp_hash AND tc.contract_name = $1
+    ))
+    AND ($2 IS NULL OR t.identity = $2)
+    AND ($3 IS NULL OR t.transaction_type = $3)
+    AND ($4 IS NULL OR t.transaction_status = $4)
+    AND ($5 IS NULL OR b.height >= $5)
+    AND ($6 IS NULL OR b.height <= $6)
+    AND ($7 IS NULL OR b.height < $7 OR (b.height = $7 AND t."index" < $8))
+ORDER BY b.height DESC, t."index" DESC
+LIMIT $9
+"#
+        )
+        .bind(&filter.contract_name)
+        .bind(&filter.identity)
+        .bind(&transaction_type)
+        .bind(&transaction_status)
+        .bind(filter.from_height)
+        .bind(filter.to_height)
+        .bind(after_height)
+        .bind(after_index)
+        .bind(limit)
+        .fetch_all(pool)
+        .await),
+        "Failed to fetch transactions"
+    )
+    .map_err(db_error)?;
+
+    Ok(connection(
+        rows.into_iter().map(Transaction::from).collect(),
+        limit,
+        after.is_some(),
+        Transaction::cursor,
+    ))
+}
+
+#[derive(SimpleObject)]
+pub struct TransactionEvents {
+    block_hash: String,
+    block_height: i64,
+    events: GraphQLJson<Vec<serde_json::Value>>,
+}
+
+#[derive(sqlx::FromRow)]
+struct TransactionEventsRow {
+    block_hash: String,
+    height: i64,
+    #[sqlx(json)]
+    events: Vec<serde_json::Value>,
+}
+
+#[Object]
+impl Transaction {
+    async fn tx_hash(&self) -> &str {
+        &self.tx_hash.0
+    }
+
+    async fn parent_dp_hash(&self) -> &str {
+        &self.parent_dp_hash.0
+    }
+
+    async fn block_hash(&self) -> Option<&str> {
+        self.block_hash.as_ref().map(|hash| hash.0.as_str())
+    }
+
+    async fn block_height(&self) -> Option<i64> {
+        self.block_height
+    }
+
+    /// Position of the transaction in its block
+    async fn index(&self) -> Option<u32> {
+        self.index
+    }
+
+    async fn version(&self) -> u32 {
+        self.version
+    }
+
+    async fn transaction_type(&self) -> TransactionType {
+        self.transaction_type.clone().into()
+    }
+
+    async fn transaction_status(&self) -> TransactionStatus {
+        self.transaction_status.clone().into()
+    }
+
+    /// Milliseconds since the UNIX epoch, of the block including the transaction
+    async fn timestamp(&self) -> Option<i64> {
+        self.timestamp
+    }
+
+    async fn lane_id(&self) -> Option<&str> {
+        self.lane_id.as_deref()
+    }
+
+    async fn identity(&self) -> Option<&str> {
+        self.identity.as_deref()
+    }
+
+    async fn block(&self, ctx: &Context<'_>) -> Result<Option<Block>> {
+        match &self.block_hash {
+            Some(hash) => block_by_hash(ctx, &hash.0).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn blobs(&self, ctx: &Context<'_>) -> Result<Vec<Blob>> {
+        let blobs = log_error!(
+            with_db!(&state(ctx)?.db, |pool| sqlx::query_as::<_, BlobRow>(
+                r#"
+SELECT bl.tx_hash, bl.parent_dp_hash, bl.blob_index, bl.identity, bl.contract_name, bl.data,
+    bl.verified, c.program_id
+FROM blobs bl
+LEFT JOIN contracts c ON c.contract_name = bl.contract_name
+WHERE bl.tx_hash = $1 AND bl.parent_dp_hash = $2
+ORDER BY bl.blob_index
+"#
+            )
+            .bind(&self.tx_hash.0)
+            .bind(&self.parent_dp_hash.0)
+            .fetch_all(pool)
+            .await),
+            "Failed to fetch blobs of transaction"
+        )
+        .map_err(db_error)?;
+        Ok(blobs.into_iter().map(Blob::from).collect())
+    }
+
+    /// Proof outputs of the blobs of a blob transaction, or proven by a proof transaction
+    async fn proofs(&self, ctx: &Context<'_>) -> Result<Vec<Proof>> {
+        let query = format!(
+            "{PROOF_SELECT} WHERE (bpo.blob_tx_hash = $1 AND bpo.blob_parent_dp_hash = $2) OR (bpo.proof_tx_hash = $1 AND bpo.proof_parent_dp_hash = $2) ORDER BY bpo.blob_index, bpo.blob_proof_output_index"
+        );
+        let proofs = log_error!(
+            with_db!(&state(ctx)?.db, |pool| sqlx::query_as::<_, ProofRow>(
+                &query
+            )
+            .bind(&self.tx_hash.0)
+            .bind(&self.parent_dp_hash.0)
+            .fetch_all(pool)
+            .await),
+            "Failed to fetch proofs of transaction"
+        )
+        .map_err(db_error)?;
+        Ok(proofs.into_iter().map(Proof::from).collect())
+    }
+
+    /// Events emitted while settling the transaction
+    async fn events(&self, ctx: &Context<'_>) -> Result<Vec<TransactionEvents>> {
+        let events = log_error!(
+            with_db!(&state(ctx)?.db, |pool| sqlx::query_as::<
+                _,
+                TransactionEventsRow,
+            >(
+                r#"
+SELECT e.block_hash, b.height, e.events
+FROM transaction_state_events e
+JOIN blocks b ON e.block_hash = b.hash
+WHERE e.tx_hash = $1 AND e.parent_dp_hash = $2
+ORDER BY b.height, e."index"
+"#
+            )
+            .bind(&self.tx_hash.0)
+            .bind(&self.parent_dp_hash.0)
+            .fetch_all(pool)
+            .await),
+            "Failed to fetch events of transaction"
+        )
+        .map_err(db_error)?;
+        Ok(events
+            .into_iter()
+            .map(|row| TransactionEvents {
+                block_hash: row.block_hash,
+                block_height: row.height,
+                events: GraphQLJson(row.events),
+            })
+            .collect())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct BlobRow {
+    tx_hash: TxHashDb,
+    parent_dp_hash: DataProposalHashDb,
+    #[sqlx(try_from = "i32")]
+    blob_index: u32,
+    identity: String,
+    contract_name: String,
+    data: Vec<u8>,
+    verified: bool,
+    program_id: Option<Vec<u8>>,
+}
+
+pub struct Blob {
+    tx_hash: TxHash,
+    parent_dp_hash: DataProposalHash,
+    blob_index: u32,
+    identity: String,
+    contract_name: String,
+    data: Vec<u8>,
+    verified: bool,
+    program_id: Option<ProgramId>,
+}
+
+impl From<BlobRow> for Blob {
+    fn from(row: BlobRow) -> Self {
+        Blob {
+            tx_hash: row.tx_hash.0,
+            parent_dp_hash: row.parent_dp_hash.0,
+            blob_index: row.blob_index,
+            identity: row.identity,
+            contract_name: row.contract_name,
+            data: row.data,
+            verified: row.verified,
+            program_id: row.program_id.map(ProgramId),
+        }
+    }
+}
+
+#[Object]
+impl Blob {
+    async fn tx_hash(&self) -> &str {
+        &self.tx_hash.0
+    }
+
+    async fn parent_dp_hash(&self) -> &str {
+        &self.parent_dp_hash.0
+    }
+
+    async fn blob_index(&self) -> u32 {
+        self.blob_index
+    }
+
+    async fn identity(&self) -> &str {
+        &self.identity
+    }
+
+    async fn contract_name(&self) -> &str {
+        &self.contract_name
+    }
+
+    /// Hex encoded
+    async fn data(&self) -> String {
+        hex::encode(&self.data)
+    }
+
+    /// Blob data as JSON, for contracts with a known decoder
+    async fn decoded_data(
+        &self,
+        ctx: &Context<'_>,
+    ) -> Result<Option<GraphQLJson<serde_json::Value>>> {
+        Ok(state(ctx)?
+            .blob_decoders
+            .decode(
+                &ContractName(self.contract_name.clone()),
+                self.program_id.as_ref(),
+                &self.data,
+            )
+            .map(GraphQLJson))
+    }
+
+    async fn verified(&self) -> bool {
+        self.verified
+    }
+
+    async fn transaction(&self, ctx: &Context<'_>) -> Result<Option<Transaction>> {
+        transaction_by_id(ctx, &self.tx_hash, &self.parent_dp_hash).await
+    }
+
+    async fn contract(&self, ctx: &Context<'_>) -> Result<Option<Contract>> {
+        contract_by_name(ctx, &self.contract_name).await
+    }
+
+    async fn proofs(&self, ctx: &Context<'_>) -> Result<Vec<Proof>> {
+        let query = format!(
+            "{PROOF_SELECT} WHERE bpo.blob_tx_hash = $1 AND bpo.blob_parent_dp_hash = $2 AND bpo.blob_index = $3 ORDER BY b.height, t.\"index\""
+        );
+        let blob_index = i64::from(self.blob_index);
+        let proofs = log_error!(
+            with_db!(&state(ctx)?.db, |pool| sqlx::query_as::<_, ProofRow>(
+                &query
+            )
+            .bind(&self.tx_hash.0)
+            .bind(&self.parent_dp_hash.0)
+            .bind(blob_index)
+            .fetch_all(pool)
+            .await),
+            "Failed to fetch proofs of blob"
+        )
+        .map_err(db_error)?;
+        Ok(proofs.into_iter().map(Proof::from).collect())
+    }
+}
+
+/// Proof outputs with the block including their proof transaction
+const PROOF_SELECT: &str = r#"
+SELECT bpo.proof_tx_hash, bpo.proof_parent_dp_hash, bpo.blob_tx_hash, bpo.blob_parent_dp_hash,
+    bpo.blob_index, bpo.blob_proof_output_index, bpo.contract_name, bpo.hyle_output, bpo.settled,
+    b.height, t."index"
+FROM blob_proof_outputs bpo
+LEFT JOIN transactions t ON t.tx_hash = bpo.proof_tx_hash AND t.parent_dp_hash = bpo.proof_parent_dp_hash
+LEFT JOIN blocks b ON t.block_hash = b.hash
+"#;
+
+#[derive(sqlx::FromRow)]
+struct ProofRow {
+    proof_tx_hash: TxHashDb,
+    proof_parent_dp_hash: DataProposalHashDb,
+    blob_tx_hash: TxHashDb,
+    blob_parent_dp_hash: DataProposalHashDb,
+    blob_index: i32,
+    blob_proof_output_index: i32,
+    contract_name: String,
+    hyle_output: serde_json::Value,
+    settled: bool,
+    height: Option<i64>,
+    index: Option<i32>,
+}
+
+/// Output of a proof for one blob
+pub struct Proof {
+    proof_tx_hash: TxHash,
+    proof_parent_dp_hash: DataProposalHash,
+    blob_tx_hash: TxHash,
+    blob_parent_dp_hash: DataProposalHash,
+    blob_index: i32,
+    output_index: i32,
+    contract_name: String,
+    hyle_output: serde_json::Value,
+    settled: bool,
+    block_height: Option<i64>,
+    tx_index: Option<i32>,
+}
+
+impl From<ProofRow> for Proof {
+    fn from(row: ProofRow) -> Self {
+        Proof {
+            proof_tx_hash: row.proof_tx_hash.0,
+            proof_parent_dp_hash: row.proof_parent_dp_hash.0,
+            blob_tx_hash: row.blob_tx_hash.0,
+            blob_parent_dp_hash: row.blob_parent_dp_hash.0,
+            blob_index: row.blob_index,
+            output_index: row.blob_proof_output_index,
+            contract_name: row.contract_name,
+            hyle_output: row.hyle_output,
+            settled: row.settled,
+            block_height: row.height,
+            tx_index: row.index,
+        }
+    }
+}
+
+impl Proof {
+    fn cursor(&self) -> String {
+        format!(
+            "{}:{}:{}",
+            self.block_height.unwrap_or_default(),
+            self.tx_index.unwrap_or_default(),
+            self.output_index
+        )
+    }
+}
+
+async fn proofs(
+    ctx: &Context<'_>,
+    first: Option<i32>,
+    after: Option<String>,
+    contract_name: Option<String>,
+) -> Result<Connection<String, Proof>> {
+    let limit = page_limit(first)?;
+    let [after_height, after_index, after_output] = cursor_positions::<3>(after.as_deref())?;
+    let query = format!(
+        r#"{PROOF_SELECT}
+WHERE b.height IS NOT NULL
+    AND ($1 IS NULL OR bpo.contract_name = $1)
+    AND ($2 IS NULL OR b.height < $2 OR (b.height = $2 AND (t."index" < $3
+        OR (t."index" = $3 AND bpo.blob_proof_output_index < $4))))
+ORDER BY b.height DESC, t."index" DESC, bpo.blob_proof_output_index DESC
+LIMIT $5
+"#
+    );
+
+    let rows = log_error!(
+        with_db!(&state(ctx)?.db, |pool| sqlx::query_as::<_, ProofRow>(
+            &query
+        )
+        .bind(&contract_name)
+        .bind(after_height)
+        .bind(after_index)
+        .bind(after_output)
+        .bind(limit)
+        .fetch_all(pool)
+        .await),
+        "Failed to fetch proofs"
+    )
+    .map_err(db_error)?;
+
+    Ok(connection(
+        rows.into_iter().map(Proof::from).collect(),
+        limit,
+        after.is_some(),
+        Proof::cursor,
+    ))
+}
+
+#[Object]
+impl Proof {
+    async fn proof_tx_hash(&self) -> &str {
+        &self.proof_tx_hash.0
+    }
+
+    async fn blob_tx_hash(&self) -> &str {
+        &self.blob_tx_hash.0
+    }
+
+    async fn blob_index(&self) -> i32 {
+        self.blob_index
+    }
+
+    /// Position of the output in the proof
+    async fn output_index(&self) -> i32 {
+        self.output_index
+    }
+
+    async fn contract_name(&self) -> &str {
+        &self.contract_name
+    }
+
+    async fn hyle_output(&self) -> GraphQLJson<serde_json::Value> {
+        GraphQLJson(self.hyle_output.clone())
+    }
+
+    /// Whether this output was used to settle the blob
+    async fn settled(&self) -> bool {
+        self.settled
+    }
+
+    /// Height of the block including the proof transaction
+    async fn block_height(&self) -> Option<i64> {
+        self.block_height
+    }
+
+    async fn proof_transaction(&self, ctx: &Context<'_>) -> Result<Option<Transaction>> {
+        transaction_by_id(ctx, &self.proof_tx_hash, &self.proof_parent_dp_hash).await
+    }
+
+    async fn blob_transaction(&self, ctx: &Context<'_>) -> Result<Option<Transaction>> {
+        transaction_by_id(ctx, &self.blob_tx_hash, &self.blob_parent_dp_hash).await
+    }
+
+    async fn blob(&self, ctx: &Context<'_>) -> Result<Option<Blob>> {
+        let blob_index = i64::from(self.blob_index);
+        let blob = log_error!(
+            with_db!(&state(ctx)?.db, |pool| sqlx::query_as::<_, BlobRow>(
+                r#"
+SELECT bl.tx_hash, bl.parent_dp_hash, bl.blob_index, bl.identity, bl.contract_name, bl.data,
+    bl.verified, c.program_id
+FROM blobs bl
+LEFT JOIN contracts c ON c.contract_name = bl.contract_name
+WHERE bl.tx_hash = $1 AND bl.parent_dp_hash = $2 AND bl.blob_index = $3
+"#
+            )
+            .bind(&self.blob_tx_hash.0)
+            .bind(&self.blob_parent_dp_hash.0)
+            .bind(blob_index)
+            .fetch_optional(pool)
+            .await),
+            "Failed to fetch blob of proof"
+        )
+        .map_err(db_error)?;
+        Ok(blob.map(Blob::from))
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ContractRow {
+    contract_name: String,
+    verifier: String,
+    program_id: Vec<u8>,
+    state_commitment: Vec<u8>,
+    timeout_window: TimeoutWindowDb,
+    tx_hash: TxHashDb,
+    parent_dp_hash: DataProposalHashDb,
+}
+
+pub struct Contract {
+    contract_name: String,
+    verifier: String,
+    program_id: Vec<u8>,
+    state_commitment: Vec<u8>,
+    timeout_window: TimeoutWindow,
+    tx_hash: TxHash,
+    parent_dp_hash: DataProposalHash,
+}
+
+impl From<ContractRow> for Contract {
+    fn from(row: ContractRow) -> Self {
+        Contract {
+            contract_name: row.contract_name,
+            verifier: row.verifier,
+            program_id: row.program_id,
+            state_commitment: row.state_commitment,
+            timeout_window: row.timeout_window.0,
+            tx_hash: row.tx_hash.0,
+            parent_dp_hash: row.parent_dp_hash.0,
+        }
+    }
+}
+
+const CONTRACT_SELECT: &str = "SELECT contract_name, verifier, program_id, state_commitment, timeout_window, tx_hash, parent_dp_hash FROM contracts";
+
+async fn contract_by_name(ctx: &Context<'_>, contract_name: &str) -> Result<Option<Contract>> {
+    let query = format!("{CONTRACT_SELECT} WHERE contract_name = $1");
+    let contract = log_error!(
+        with_db!(&state(ctx)?.db, |pool| sqlx::query_as::<_, ContractRow>(
+            &query
+        )
+        .bind(contract_name)
+        .fetch_optional(pool)
+        .await),
+        "Failed to fetch contract"
+    )
+    .map_err(db_error)?;
+    Ok(contract.map(Contract::from))
+}
+
+#[Object]
+impl Contract {
+    async fn contract_name(&self) -> &str {
+        &self.contract_name
+    }
+
+    async fn verifier(&self) -> &str {
+        &self.verifier
+    }
+
+    /// Hex encoded
+    async fn program_id(&self) -> String {
+        hex::encode(&self.program_id)
+    }
+
+    /// Hex encoded
+    async fn state_commitment(&self) -> String {
+        hex::encode(&self.state_commitment)
+    }
+
+    /// Number of blocks before an unsettled transaction times out, none if it never does
+    async fn timeout_window(&self) -> Option<u64> {
+        match self.timeout_window {
+            TimeoutWindow::NoTimeout => None,
+            TimeoutWindow::Timeout(window) => Some(window.0),
+        }
+    }
+
+    async fn registration_transaction(&self, ctx: &Context<'_>) -> Result<Option<Transaction>> {
+        transaction_by_id(ctx, &self.tx_hash, &self.parent_dp_hash).await
+    }
+
+    /// Past states of the contract, most recent first
+    async fn states(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<Connection<String, ContractState>> {
+        let limit = page_limit(first)?;
+        let [after_height] = cursor_positions::<1>(after.as_deref())?;
+        let rows = log_error!(
+            with_db!(&state(ctx)?.db, |pool| {
+                sqlx::query_as::<_, ContractStateRow>(
+                    r#"
+SELECT cs.contract_name, cs.block_hash, cs.state_commitment, b.height
+FROM contract_state cs
+JOIN blocks b ON cs.block_hash = b.hash
+WHERE cs.contract_name = $1 AND ($2 IS NULL OR b.height < $2)
+ORDER BY b.height DESC
+LIMIT $3
+"#,
+                )
+                .bind(&self.contract_name)
+                .bind(after_height)
+                .bind(limit)
+                .fetch_all(pool)
+                .await
+            }),
+            "Failed to fetch contract states"
+        )
+        .map_err(db_error)?;
+
+        Ok(connection(
+            rows.into_iter().map(ContractState::from).collect(),
+            limit,
+            after.is_some(),
+            |state| state.block_height.to_string(),
+        ))
+    }
+
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        filter: Option<TransactionFilter>,
+    ) -> Result<Connection<String, Transaction>> {
+        let filter = TransactionFilter {
+            contract_name: Some(self.contract_name.clone()),
+            ..filter.unwrap_or_default()
+        };
+        transactions(ctx, first, after, filter).await
+    }
+
+    async fn proofs(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<Connection<String, Proof>> {
+        proofs(ctx, first, after, Some(self.contract_name.clone())).await
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ContractStateRow {
+    contract_name: String,
+    block_hash: ConsensusProposalHash,
+    state_commitment: Vec<u8>,
+    height: i64,
+}
+
+pub struct ContractState {
+    contract_name: String,
+    block_hash: ConsensusProposalHash,
+    block_height: i64,
+    state_commitment: Vec<u8>,
+}
+
+impl From<ContractStateRow> for ContractState {
+    fn from(row: ContractStateRow) -> Self {
+        ContractState {
+            contract_name: row.contract_name,
+            block_hash: row.block_hash,
+            block_height: row.height,
+            state_commitment: row.state_commitment,
+        }
+    }
+}
+
+#[Object]
+impl ContractState {
+    async fn contract_name(&self) -> &str {
+        &self.contract_name
+    }
+
+    async fn block_hash(&self) -> &str {
+        &self.block_hash.0
+    }
+
+    async fn block_height(&self) -> i64 {
+        self.block_height
+    }
+
+    /// Hex encoded
+    async fn state_commitment(&self) -> String {
+        hex::encode(&self.state_commitment)
+    }
+
+    async fn block(&self, ctx: &Context<'_>) -> Result<Option<Block>> {
+        block_by_hash(ctx, &self.block_hash.0).await
+    }
+
+    async fn contract(&self, ctx: &Context<'_>) -> Result<Option<Contract>> {
+        contract_by_name(ctx, &self.contract_name).await
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Block by height or hash, the last indexed block if neither is given
+    async fn block(
+        &self,
+        ctx: &Context<'_>,
+        height: Option<i64>,
+        hash: Option<String>,
+    ) -> Result<Option<Block>> {
+        if let Some(hash) = hash {
+            return block_by_hash(ctx, &hash).await;
+        }
+        let block = log_error!(
+            with_db!(&state(ctx)?.db, |pool| sqlx::query_as::<_, BlockDb>(
+                "SELECT * FROM blocks WHERE ($1 IS NULL OR height = $1) ORDER BY height DESC LIMIT 1"
+            )
+            .bind(height)
+            .fetch_optional(pool)
+            .await),
+            "Failed to fetch block"
+        )
+        .map_err(db_error)?;
+        Ok(block.map(Block::from))
+    }
+
+    /// Blocks, most recent first
+    async fn blocks(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        from_height: Option<i64>,
+        to_height: Option<i64>,
+    ) -> Result<Connection<String, Block>> {
+        let limit = page_limit(first)?;
+        let [after_height] = cursor_positions::<1>(after.as_deref())?;
+        let rows = log_error!(
+            with_db!(&state(ctx)?.db, |pool| sqlx::query_as::<_, BlockDb>(
+                r#"
+SELECT * FROM blocks
+WHERE ($1 IS NULL OR height >= $1) AND ($2 IS NULL OR height <= $2) AND ($3 IS NULL OR height < $3)
+ORDER BY height DESC
+LIMIT $4
+"#
+            )
+            .bind(from_height)
+            .bind(to_height)
+            .bind(after_height)
+            .bind(limit)
+            .fetch_all(pool)
+            .await),
+            "Failed to fetch blocks"
+        )
+        .map_err(db_error)?;
+
+        Ok(connection(
+            rows.into_iter().map(Block::from).collect(),
+            limit,
+            after.is_some(),
+            |block| block.height.to_string(),
+        ))
+    }
+
+    /// Last occurrence of a transaction
+    async fn transaction(&self, ctx: &Context<'_>, tx_hash: String) -> Result<Option<Transaction>> {
+        let transaction = log_error!(
+            with_db!(&state(ctx)?.db, |pool| sqlx::query_as::<_, TransactionRow>(
+                r#"
+SELECT t.*, b.timestamp, b.height
+FROM transactions t
+LEFT JOIN blocks b ON t.block_hash = b.hash
+WHERE t.tx_hash = $1
+ORDER BY t.block_height DESC NULLS FIRST, t."index" DESC
+LIMIT 1
+"#
+            )
+            .bind(&tx_hash)
+            .fetch_optional(pool)
+            .await),
+            "Failed to fetch transaction by hash"
+        )
+        .map_err(db_error)?;
+        Ok(transaction.map(Transaction::from))
+    }
+
+    /// Transactions included in a block, most recent first
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        filter: Option<TransactionFilter>,
+    ) -> Result<Connection<String, Transaction>> {
+        transactions(ctx, first, after, filter.unwrap_or_default()).await
+    }
+
+    /// Proof outputs, most recent first
+    async fn proofs(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        contract_name: Option<String>,
+    ) -> Result<Connection<String, Proof>> {
+        proofs(ctx, first, after, contract_name).await
+    }
+
+    async fn contract(&self, ctx: &Context<'_>, contract_name: String) -> Result<Option<Contract>> {
+        contract_by_name(ctx, &contract_name).await
+    }
+
+    async fn contracts(&self, ctx: &Context<'_>) -> Result<Vec<Contract>> {
+        let query = format!("{CONTRACT_SELECT} ORDER BY contract_name");
+        let contracts = log_error!(
+            with_db!(&state(ctx)?.db, |pool| sqlx::query_as::<_, ContractRow>(
+                &query
+            )
+            .fetch_all(pool)
+            .await),
+            "Failed to fetch contracts"
+        )
+        .map_err(db_error)?;
+        Ok(contracts.into_iter().map(Contract::from).collect())
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct TransactionStatusEvent {
+    tx_hash: String,
+    status: TransactionStatus,
+    block_hash: String,
+    block_height: u64,
+}
+
+#[derive(SimpleObject)]
+pub struct ProofEvent {
+    contract_name: String,
+    proof_tx_hash: String,
+    blob_tx_hash: String,
+    blob_index: u32,
+    block_height: u64,
+}
+
+/// Events published by the explorer, lagging subscribers skip the events they missed
+fn explorer_events(ctx: &Context<'_>) -> Result<impl Stream<Item = ExplorerWsEvent>> {
+    let mut events = state(ctx)?.ws_events.subscribe();
+    Ok(async_stream::stream! {
+        loop {
+            match events.recv().await {
+                Ok(event) => yield event.as_ref().clone(),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("GraphQL subscriber lagged, skipping {skipped} events");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// Blocks, once indexed
+    async fn blocks(&self, ctx: &Context<'_>) -> Result<impl Stream<Item = Block>> {
+        Ok(explorer_events(ctx)?.filter_map(|event| {
+            ready(match event {
+                ExplorerWsEvent::Block { block } => Some(Block::from(block)),
+                _ => None,
+            })
+        }))
+    }
+
+    async fn transaction_status(
+        &self,
+        ctx: &Context<'_>,
+        tx_hash: String,
+    ) -> Result<impl Stream<Item = TransactionStatusEvent>> {
+        Ok(explorer_events(ctx)?.filter_map(move |event| {
+            ready(match event {
+                ExplorerWsEvent::TransactionStatus {
+                    tx_hash: event_tx_hash,
+                    status,
+                    block_hash,
+                    block_height,
+                } if event_tx_hash.0 == tx_hash => Some(TransactionStatusEvent {
+                    tx_hash: event_tx_hash.0,
+                    status: status.into(),
+                    block_hash: block_hash.0,
+                    block_height: block_height.0,
+                }),
+                _ => None,
+            })
+        }))
+    }
+
+    /// Blob transactions with a blob for the contract, once sequenced
+    async fn contract_transactions(
+        &self,
+        ctx: &Context<'_>,
+        contract_name: String,
+    ) -> Result<impl Stream<Item = Transaction>> {
+        Ok(explorer_events(ctx)?.filter_map(move |event| {
+            ready(match event {
+                ExplorerWsEvent::Transaction { transaction }
+                    if transaction
+                        .blobs
+                        .iter()
+                        .any(|blob| blob.contract_name == contract_name) =>
+                {
+                    Some(Transaction {
+                        tx_hash: transaction.tx_hash,
+                        parent_dp_hash: transaction.parent_dp_hash,
+                        block_hash: Some(transaction.block_hash),
+                        block_height: None,
+                        index: Some(transaction.index),
+                        version: transaction.version,
+                        transaction_type: transaction.transaction_type,
+                        transaction_status: transaction.transaction_status,
+                        timestamp: transaction
+                            .timestamp
+                            .and_then(|timestamp| i64::try_from(timestamp.0).ok()),
+                        lane_id: transaction
+                            .lane_id
+                            .map(|lane_id| hex::encode(&lane_id.0 .0)),
+                        identity: Some(transaction.identity),
+                    })
+                }
+                _ => None,
+            })
+        }))
+    }
+
+    async fn contract_proofs(
+        &self,
+        ctx: &Context<'_>,
+        contract_name: String,
+    ) -> Result<impl Stream<Item = ProofEvent>> {
+        Ok(explorer_events(ctx)?.filter_map(move |event| {
+            ready(match event {
+                ExplorerWsEvent::Proof {
+                    contract_name: event_contract_name,
+                    proof_tx_hash,
+                    blob_tx_hash,
+                    blob_index,
+                    block_height,
+                } if event_contract_name.0 == contract_name => Some(ProofEvent {
+                    contract_name: event_contract_name.0,
+                    proof_tx_hash: proof_tx_hash.0,
+                    blob_tx_hash: blob_tx_hash.0,
+                    blob_index,
+                    block_height: block_height.0,
+                }),
+                _ => None,
+            })
+        }))
+    }
+
+    async fn contract_states(
+        &self,
+        ctx: &Context<'_>,
+        contract_name: String,
+    ) -> Result<impl Stream<Item = ContractState>> {
+        Ok(explorer_events(ctx)?.filter_map(move |event| {
+            ready(match event {
+                ExplorerWsEvent::ContractState {
+                    contract_name: event_contract_name,
+                    state_commitment,
+                    block_hash,
+                    block_height,
+                } if event_contract_name.0 == contract_name => Some(ContractState {
+                    contract_name: event_contract_name.0,
+                    block_hash,
+                    block_height: i64::try_from(block_height.0).unwrap_or(i64::MAX),
+                    state_commitment,
+                }),
+                _ => None,
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_positions() {
+        assert_eq!(cursor_positions::<2>(None).ok(), Some([None, None]));
+        assert_eq!(
+            cursor_positions::<2>(Some("12:3")).ok(),
+            Some([Some(12), Some(3)])
+        );
+        assert!(cursor_positions::<2>(Some("12")).is_err());
+        assert!(cursor_positions::<1>(Some("twelve")).is_err());
+    }
+
+    #[test]
+    fn test_page_limit() {
+        assert_eq!(
+            page_limit(None).ok(),
+            Some(i64::from(DEFAULT_PAGE_SIZE) + 1)
+        );
+        assert_eq!(page_limit(Some(1)).ok(), Some(2));
+        assert!(page_limit(Some(0)).is_err());
+        assert!(page_limit(Some(MAX_PAGE_SIZE + 1)).is_err());
+    }
+}
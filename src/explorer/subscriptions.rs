@@ -0,0 +1,305 @@
+//! Subscriptions of the explorer websocket.
+//!
+//! Clients send `{"action": "subscribe", "topic": "blocks"}` or
+//! `{"action": "unsubscribe", "topic": "transaction_status", "tx_hash": "..."}` and receive the
+//! events of the topics they are subscribed to, tagged by an `event` field.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket};
+use futures::{SinkExt, StreamExt};
+use hyle_model::api::{
+    APIBlock, ExplorerWsEvent, ExplorerWsRequest, ExplorerWsTopic, TransactionStatusDb,
+};
+use hyle_modules::log_error;
+use tokio::select;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, error};
+
+use crate::model::*;
+
+/// Events buffered for each connection, slower clients get a `lagged` event
+pub(crate) const WS_EVENTS_CAPACITY: usize = 1000;
+
+/// Maximum number of topics a single connection can subscribe to
+const MAX_TOPICS_PER_CONNECTION: usize = 256;
+
+/// Events of a block, transaction statuses in the order they happened
+pub(crate) fn block_events(block: &Block) -> Vec<ExplorerWsEvent> {
+    let mut events = vec![ExplorerWsEvent::Block {
+        block: APIBlock {
+            hash: block.hash.clone(),
+            parent_hash: block.parent_hash.clone(),
+            height: block.block_height.0,
+            timestamp: block.block_timestamp.0 as i64,
+            total_txs: block.txs.len() as u64,
+        },
+    }];
+
+    let tx_status =
+        |tx_hash: &TxHash, status: TransactionStatusDb| ExplorerWsEvent::TransactionStatus {
+            tx_hash: tx_hash.clone(),
+            status,
+            block_hash: block.hash.clone(),
+            block_height: block.block_height,
+        };
+
+    // Mirrors the statuses the indexer stores
+    events.extend(
+        block
+            .txs
+            .iter()
+            .map(|(tx_id, tx)| match tx.transaction_data {
+                TransactionData::Blob(_) => tx_status(&tx_id.1, TransactionStatusDb::Sequenced),
+                TransactionData::Proof(_) | TransactionData::VerifiedProof(_) => {
+                    tx_status(&tx_id.1, TransactionStatusDb::Success)
+                }
+            }),
+    );
+    events.extend(
+        block
+            .successful_txs
+            .iter()
+            .map(|tx_hash| tx_status(tx_hash, TransactionStatusDb::Success)),
+    );
+    events.extend(
+        block
+            .failed_txs
+            .iter()
+            .map(|tx_hash| tx_status(tx_hash, TransactionStatusDb::Failure)),
+    );
+    events.extend(
+        block
+            .timed_out_txs
+            .iter()
+            .map(|tx_hash| tx_status(tx_hash, TransactionStatusDb::TimedOut)),
+    );
+
+    events.extend(
+        block
+            .blob_proof_outputs
+            .iter()
+            .map(|output| ExplorerWsEvent::Proof {
+                contract_name: output.contract_name.clone(),
+                proof_tx_hash: output.proof_tx_hash.clone(),
+                blob_tx_hash: output.blob_tx_hash.clone(),
+                blob_index: output.blob_index.0 as u32,
+                block_height: block.block_height,
+            }),
+    );
+
+    let contract_state = |contract_name: &ContractName, state_commitment: &StateCommitment| {
+        ExplorerWsEvent::ContractState {
+            contract_name: contract_name.clone(),
+            state_commitment: state_commitment.0.clone(),
+            block_hash: block.hash.clone(),
+            block_height: block.block_height,
+        }
+    };
+    events.extend(
+        block
+            .registered_contracts
+            .iter()
+            .map(|(contract_name, (_, effect, _))| {
+                contract_state(contract_name, &effect.state_commitment)
+            }),
+    );
+    events.extend(
+        block
+            .updated_states
+            .iter()
+            .map(|(contract_name, state_commitment)| {
+                contract_state(contract_name, state_commitment)
+            }),
+    );
+
+    events
+}
+
+fn is_subscribed(topics: &HashSet<ExplorerWsTopic>, event: &ExplorerWsEvent) -> bool {
+    match event {
+        ExplorerWsEvent::Block { .. } => topics.contains(&ExplorerWsTopic::Blocks),
+        ExplorerWsEvent::TransactionStatus { tx_hash, .. } => {
+            topics.contains(&ExplorerWsTopic::TransactionStatus {
+                tx_hash: tx_hash.clone(),
+            })
+        }
+        ExplorerWsEvent::Transaction { transaction } => transaction.blobs.iter().any(|blob| {
+            topics.contains(&ExplorerWsTopic::ContractTransactions {
+                contract_name: ContractName(blob.contract_name.clone()),
+            })
+        }),
+        ExplorerWsEvent::Proof { contract_name, .. } => {
+            topics.contains(&ExplorerWsTopic::ContractProofs {
+                contract_name: contract_name.clone(),
+            })
+        }
+        ExplorerWsEvent::ContractState { contract_name, .. } => {
+            topics.contains(&ExplorerWsTopic::ContractState {
+                contract_name: contract_name.clone(),
+            })
+        }
+        // Replies to a client, never broadcast
+        ExplorerWsEvent::Subscribed { .. }
+        | ExplorerWsEvent::Unsubscribed { .. }
+        | ExplorerWsEvent::Lagged { .. }
+        | ExplorerWsEvent::Error { .. } => false,
+    }
+}
+
+fn handle_request(topics: &mut HashSet<ExplorerWsTopic>, request: &str) -> ExplorerWsEvent {
+    match serde_json::from_str::<ExplorerWsRequest>(request) {
+        Ok(ExplorerWsRequest::Subscribe(topic)) => {
+            if topics.len() >= MAX_TOPICS_PER_CONNECTION && !topics.contains(&topic) {
+                return ExplorerWsEvent::Error {
+                    message: format!(
+                        "Cannot subscribe to more than {MAX_TOPICS_PER_CONNECTION} topics"
+                    ),
+                };
+            }
+            topics.insert(topic.clone());
+            ExplorerWsEvent::Subscribed { topic }
+        }
+        Ok(ExplorerWsRequest::Unsubscribe(topic)) => {
+            topics.remove(&topic);
+            ExplorerWsEvent::Unsubscribed { topic }
+        }
+        Err(e) => ExplorerWsEvent::Error {
+            message: format!("Invalid request: {e}"),
+        },
+    }
+}
+
+/// Serves a websocket connection until the client leaves
+pub(crate) async fn handle_socket(
+    socket: WebSocket,
+    mut events: broadcast::Receiver<Arc<ExplorerWsEvent>>,
+) {
+    let (mut ws_tx, mut ws_rx) = socket.split();
+    let mut topics = HashSet::new();
+
+    loop {
+        let event = select! {
+            event = events.recv() => match event {
+                Ok(event) if is_subscribed(&topics, &event) => event,
+                Ok(_) => continue,
+                // Events are dropped instead of slowing down the explorer
+                Err(RecvError::Lagged(skipped)) => Arc::new(ExplorerWsEvent::Lagged { skipped }),
+                Err(RecvError::Closed) => break,
+            },
+            message = ws_rx.next() => match message {
+                Some(Ok(Message::Text(request))) => {
+                    Arc::new(handle_request(&mut topics, request.as_str()))
+                }
+                Some(Ok(Message::Close(frame))) => {
+                    debug!("WS closed by client: {:?}", frame);
+                    let _ = ws_tx.send(Message::Close(frame)).await;
+                    break;
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => {
+                    error!("Error while getting message from WS: {}", e);
+                    break;
+                }
+                None => break,
+            },
+        };
+
+        if let Ok(json) = log_error!(
+            serde_json::to_string(event.as_ref()),
+            "Serialize websocket event to JSON"
+        ) {
+            if ws_tx.send(Message::Text(json.into())).await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscription_requests() {
+        let mut topics = HashSet::new();
+        let tx_hash = TxHash::new("aaaa");
+
+        let reply = handle_request(
+            &mut topics,
+            r#"{"action": "subscribe", "topic": "transaction_status", "tx_hash": "aaaa"}"#,
+        );
+        let topic = ExplorerWsTopic::TransactionStatus {
+            tx_hash: tx_hash.clone(),
+        };
+        assert_eq!(
+            reply,
+            ExplorerWsEvent::Subscribed {
+                topic: topic.clone()
+            }
+        );
+
+        let status = ExplorerWsEvent::TransactionStatus {
+            tx_hash,
+            status: TransactionStatusDb::Success,
+            block_hash: ConsensusProposalHash::default(),
+            block_height: BlockHeight(1),
+        };
+        assert!(is_subscribed(&topics, &status));
+        assert!(!is_subscribed(
+            &topics,
+            block_events(&Block::default()).first().unwrap()
+        ));
+
+        let reply = handle_request(
+            &mut topics,
+            r#"{"action": "unsubscribe", "topic": "transaction_status", "tx_hash": "aaaa"}"#,
+        );
+        assert_eq!(reply, ExplorerWsEvent::Unsubscribed { topic });
+        assert!(!is_subscribed(&topics, &status));
+
+        assert!(matches!(
+            handle_request(
+                &mut topics,
+                r#"{"action": "subscribe", "topic": "unknown"}"#
+            ),
+            ExplorerWsEvent::Error { .. }
+        ));
+    }
+
+    #[test]
+    fn test_block_events_order() {
+        let tx = Transaction::from(BlobTransaction::new(
+            "test@hydentity",
+            vec![Blob {
+                contract_name: "hydentity".into(),
+                data: BlobData(vec![1]),
+            }],
+        ));
+        let tx_hash = tx.hashed();
+        let block = Block {
+            block_height: BlockHeight(3),
+            txs: vec![(TxId(DataProposalHash::default(), tx_hash.clone()), tx)],
+            successful_txs: vec![tx_hash.clone()],
+            updated_states: [("hydentity".into(), StateCommitment(vec![4]))].into(),
+            ..Block::default()
+        };
+
+        let statuses = block_events(&block)
+            .into_iter()
+            .filter_map(|event| match event {
+                ExplorerWsEvent::TransactionStatus { status, .. } => Some(status),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![TransactionStatusDb::Sequenced, TransactionStatusDb::Success]
+        );
+        assert!(block_events(&block).iter().any(|event| matches!(
+            event,
+            ExplorerWsEvent::ContractState { block_height, .. } if *block_height == BlockHeight(3)
+        )));
+    }
+}
@@ -708,6 +708,7 @@ impl Genesis {
                     })
                     .collect(),
                 parent_hash: ConsensusProposalHash("genesis".into()),
+                timed_out_leaders: vec![],
             },
         }
     }
@@ -1,11 +1,15 @@
 //! Index system for historical data.
 
+pub mod export;
 mod handler;
+pub mod reindex;
 
+use std::collections::HashMap;
 use std::ops::Deref;
 
 use crate::explorer::api::{DataProposalHashDb, TxHashDb};
-use crate::explorer::WsExplorerBlobTx;
+use crate::explorer::db::{with_db, IndexerDb};
+use crate::explorer::{WsExplorerBlobTx, WsExplorerBlock};
 use crate::node_state::module::NodeStateEvent;
 use crate::utils::conf::Conf;
 use crate::{model::*, utils::conf::SharedConf};
@@ -13,6 +17,7 @@ use anyhow::{Context, Result};
 use handler::IndexerHandlerStore;
 use hyle_model::utils::TimestampMs;
 use hyle_modules::bus::BusClientSender;
+use hyle_modules::modules::contract_state_indexer::storage::ContractStateStorageKind;
 use hyle_modules::node_state::module::NodeStateModule;
 use hyle_modules::node_state::{NodeState, NodeStateStore};
 use hyle_modules::{
@@ -21,13 +26,12 @@ use hyle_modules::{
     modules::{module_bus_client, Module, SharedBuildApiCtx},
 };
 use serde::{Deserialize, Serialize};
-use sqlx::Row;
-use sqlx::{postgres::PgPoolOptions, PgPool, Pool, Postgres};
 
 module_bus_client! {
 #[derive(Debug)]
 struct IndexerBusClient {
     sender(WsExplorerBlobTx),
+    sender(WsExplorerBlock),
     sender(NodeStateEvent),
     receiver(DataEvent),
     receiver(MempoolStatusEvent),
@@ -37,7 +41,7 @@ struct IndexerBusClient {
 #[derive(Debug)]
 pub struct Indexer {
     bus: IndexerBusClient,
-    db: PgPool,
+    db: IndexerDb,
     node_state: NodeState,
     handler_store: IndexerHandlerStore,
     conf: Conf,
@@ -46,24 +50,22 @@ pub struct Indexer {
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct IndexerConf {
     query_buffer_size: usize,
+    /// Schemas of the contracts without a built-in decoder, used to decode their blobs, by contract name
+    #[serde(default)]
+    pub blob_schemas: HashMap<String, ContractSchema>,
+    /// Backend persisting the stores of the contract state indexers
+    #[serde(default)]
+    pub contract_state_storage: ContractStateStorageKind,
 }
 
-pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./src/indexer/migrations");
-
 impl Module for Indexer {
     type Context = (SharedConf, SharedBuildApiCtx);
 
     async fn build(bus: SharedMessageBus, ctx: Self::Context) -> Result<Self> {
         let bus = IndexerBusClient::new_from_bus(bus.new_handle()).await;
 
-        let pool = PgPoolOptions::new()
-            .max_connections(20)
-            .acquire_timeout(std::time::Duration::from_secs(1))
-            .connect(&ctx.0.database_url)
-            .await
-            .context("Failed to connect to the database")?;
-
-        tokio::time::timeout(tokio::time::Duration::from_secs(60), MIGRATOR.run(&pool)).await??;
+        let db = IndexerDb::connect(&ctx.0.database_url).await?;
+        db.migrate().await?;
 
         // Load node state from node_state.bin if it exists or create a new default
         let node_state_path = ctx.0.data_directory.join("indexer_node_state.bin");
@@ -77,7 +79,7 @@ impl Module for Indexer {
 
         let indexer = Indexer {
             bus,
-            db: pool,
+            db,
             node_state,
             handler_store: IndexerHandlerStore::default(),
             conf,
@@ -142,13 +144,12 @@ impl Indexer {
     }
 
     pub async fn get_last_block(&self) -> Result<Option<BlockHeight>> {
-        let rows = sqlx::query("SELECT max(height) as max FROM blocks")
-            .fetch_one(&self.db)
-            .await?;
-        Ok(rows
-            .try_get("max")
-            .map(|m: i64| Some(BlockHeight(m as u64)))
-            .unwrap_or(None))
+        let max: Option<i64> = with_db!(&self.db, |pool| sqlx::query_scalar(
+            "SELECT max(height) as max FROM blocks"
+        )
+        .fetch_one(pool)
+        .await?);
+        Ok(max.map(|m| BlockHeight(m as u64)))
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -177,7 +178,7 @@ impl Indexer {
 }
 
 impl std::ops::Deref for Indexer {
-    type Target = Pool<Postgres>;
+    type Target = IndexerDb;
 
     fn deref(&self) -> &Self::Target {
         &self.db
@@ -189,18 +190,24 @@ mod test {
     use assert_json_diff::assert_json_include;
     use axum_test::TestServer;
     use client_sdk::transaction_builder::ProvableBlobTx;
+    use futures::{SinkExt, StreamExt};
     use hydentity::{client::tx_executor_handler::register_identity, HydentityAction};
     use hyle_contract_sdk::{BlobIndex, HyleOutput, Identity, ProgramId, StateCommitment, TxHash};
     use hyle_model::api::{
-        APIBlob, APIBlock, APIContract, APITransaction, APITransactionEvents, TransactionStatusDb,
+        APIBlob, APIBlock, APIContract, APIContractSettlementStats, APIContractStateDiff,
+        APIContractStateTransition, APIContractStateTransitionKind, APIIdentitySummary,
+        APILatencyPercentiles, APIProofDetails, APISearchResult, APISearchResultKind,
+        APITransaction, APITransactionEvents, ExplorerWsEvent, ExplorerWsTopic,
+        TransactionStatusDb, TransactionWithBlobs,
     };
     use serde_json::json;
+    use std::collections::BTreeMap;
     use std::future::IntoFuture;
     use utils::TimestampMs;
 
     use crate::{
         bus::SharedMessageBus,
-        explorer::Explorer,
+        explorer::{blob_decoders::BlobDecoders, Explorer},
         model::{
             Blob, BlobData, BlobProofOutput, ProofData, SignedBlock, Transaction, TransactionData,
             VerifiedProofTransaction,
@@ -210,6 +217,7 @@ mod test {
 
     use super::*;
 
+    use crate::explorer::db::PG_MIGRATOR;
     use sqlx::postgres::PgPoolOptions;
     use testcontainers_modules::{
         postgres::Postgres,
@@ -221,25 +229,35 @@ mod test {
         TestServer::new(router)
     }
 
-    async fn new_indexer(pool: PgPool) -> (Indexer, Explorer) {
+    async fn contract_names(indexer: &Indexer) -> Result<Vec<String>> {
+        with_db!(&indexer.db, |pool| sqlx::query_scalar(
+            "SELECT contract_name FROM contracts"
+        )
+        .fetch_all(pool)
+        .await)
+        .context("fetch contracts")
+    }
+
+    async fn new_indexer(db: IndexerDb) -> (Indexer, Explorer) {
         let bus = SharedMessageBus::default();
 
         let conf = Conf {
             indexer: IndexerConf {
                 query_buffer_size: 100,
+                ..IndexerConf::default()
             },
             ..Conf::default()
         };
         (
             Indexer {
                 bus: IndexerBusClient::new_from_bus(bus.new_handle()).await,
-                db: pool.clone(),
+                db: db.clone(),
                 node_state: NodeState::create("indexer".to_string(), "indexer"),
 
                 handler_store: IndexerHandlerStore::default(),
                 conf,
             },
-            Explorer::new(bus, pool).await,
+            Explorer::new(bus, db, BlobDecoders::with_builtins()).await,
         )
     }
 
@@ -382,9 +400,9 @@ mod test {
             ))
             .await
             .unwrap();
-        MIGRATOR.run(&db).await.unwrap();
+        PG_MIGRATOR.run(&db).await.unwrap();
 
-        let (mut indexer, explorer) = new_indexer(db).await;
+        let (mut indexer, explorer) = new_indexer(IndexerDb::Postgres(db)).await;
         let server = setup_test_server(&explorer).await?;
 
         let initial_state = StateCommitment(vec![1, 2, 3]);
@@ -698,6 +716,31 @@ mod test {
         let transactions_response = server.get("/contract/d1").await;
         transactions_response.assert_status_not_found();
 
+        let settlement_stats = server
+            .get("/stats/contract/c1/settlement?from=0&to=3600000&slo_secs=1")
+            .await
+            .json::<APIContractSettlementStats>();
+        assert_eq!(settlement_stats.points.len(), 1);
+        let point = settlement_stats.points.first().unwrap();
+        assert_eq!(point.sequenced_txs, 2);
+        assert_eq!(point.successful_txs, 1);
+        assert_eq!(point.blob_bytes, 6);
+        assert_eq!(point.settled_within_slo, Some(1));
+        assert_eq!(
+            point.settlement_latency,
+            Some(APILatencyPercentiles {
+                count: 1,
+                p50: 0,
+                p90: 0,
+                p99: 0,
+                max: 0
+            })
+        );
+        assert_eq!(
+            point.proof_settlement_lag.as_ref().map(|lag| lag.count),
+            Some(1)
+        );
+
         let blob_transactions_response = server.get("/blob_transactions/contract/c1").await;
         blob_transactions_response.assert_status_ok();
         assert_json_include!(
@@ -837,9 +880,15 @@ mod test {
             ))
             .await
             .unwrap();
-        MIGRATOR.run(&db).await.unwrap();
-        let (indexer, _) = new_indexer(db).await;
+        PG_MIGRATOR.run(&db).await.unwrap();
+        let (indexer, _) = new_indexer(IndexerDb::Postgres(db)).await;
 
+        let (b1, b2, b3) = contracts_blocks();
+        Ok((container, indexer, b1, b2, b3))
+    }
+
+    /// Registers contracts a, b and c, then deletes and re-registers some of them over two blocks
+    fn contracts_blocks() -> (Block, Block, Block) {
         let mut node_state = NodeState {
             store: NodeStateStore::default(),
             metrics: NodeStateMetrics::global("test".to_string(), "test"),
@@ -952,7 +1001,7 @@ mod test {
             DataProposalHash("test".to_string()),
         );
 
-        Ok((container, indexer, b1, b2, b3))
+        (b1, b2, b3)
     }
 
     #[test_log::test(tokio::test)]
@@ -961,43 +1010,16 @@ mod test {
 
         indexer.handle_processed_block(b1.clone()).unwrap();
         indexer.dump_store_to_db().await.unwrap();
-        let rows = sqlx::query("SELECT * FROM contracts")
-            .fetch_all(&indexer.db)
-            .await
-            .context("fetch contracts")?;
-        assert_eq!(
-            rows.iter()
-                .map(|r| r.get::<String, _>("contract_name"))
-                .collect::<Vec<_>>(),
-            vec!["a", "b", "c"]
-        );
+        assert_eq!(contract_names(&indexer).await?, vec!["a", "b", "c"]);
 
         indexer.handle_processed_block(b2.clone()).unwrap();
         indexer.dump_store_to_db().await.unwrap();
-        let rows = sqlx::query("SELECT * FROM contracts")
-            .fetch_all(&indexer.db)
-            .await
-            .context("fetch contracts")?;
-        assert_eq!(
-            rows.iter()
-                .map(|r| r.get::<String, _>("contract_name"))
-                .collect::<Vec<_>>(),
-            vec!["b"]
-        );
+        assert_eq!(contract_names(&indexer).await?, vec!["b"]);
 
         indexer.handle_processed_block(b3.clone()).unwrap();
         indexer.dump_store_to_db().await.unwrap();
 
-        let rows = sqlx::query("SELECT * FROM contracts")
-            .fetch_all(&indexer.db)
-            .await
-            .context("fetch contracts")?;
-        assert_eq!(
-            rows.iter()
-                .map(|r| r.get::<String, _>("contract_name"))
-                .collect::<Vec<_>>(),
-            vec!["a"]
-        );
+        assert_eq!(contract_names(&indexer).await?, vec!["a"]);
         Ok(())
     }
 
@@ -1009,21 +1031,495 @@ mod test {
         indexer.handle_processed_block(b3).unwrap();
         indexer.dump_store_to_db().await.unwrap();
 
-        let rows = sqlx::query("SELECT * FROM contracts")
-            .fetch_all(&indexer.db)
+        assert_eq!(contract_names(&indexer).await?, vec!["a"]);
+        Ok(())
+    }
+
+    // In case of duplicate tx hash, should return information of the tx with the highest block height
+    // or index (position in the block)
+    #[test_log::test(tokio::test)]
+    async fn test_indexer_sqlite_backend() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = IndexerDb::connect(&format!(
+            "sqlite://{}",
+            dir.path().join("indexer.db").display()
+        ))
+        .await?;
+        db.migrate().await?;
+        let (mut indexer, explorer) = new_indexer(db).await;
+        let server = setup_test_server(&explorer).await?;
+
+        let (b1, b2, b3) = contracts_blocks();
+        indexer.handle_processed_block(b1)?;
+        indexer.dump_store_to_db().await?;
+        assert_eq!(contract_names(&indexer).await?, vec!["a", "b", "c"]);
+
+        indexer.handle_processed_block(b2)?;
+        indexer.handle_processed_block(b3)?;
+        indexer.dump_store_to_db().await?;
+        assert_eq!(contract_names(&indexer).await?, vec!["a"]);
+
+        assert_eq!(indexer.get_last_block().await?, Some(BlockHeight(5)));
+
+        let blocks = server.get("/blocks").await.json::<Vec<APIBlock>>();
+        assert_eq!(
+            blocks.iter().map(|b| b.height).collect::<Vec<_>>(),
+            vec![5, 4, 3]
+        );
+
+        let contract = server.get("/contract/a").await;
+        contract.assert_status_ok();
+        assert_eq!(contract.json::<APIContract>().contract_name, "a");
+
+        let transactions = server
+            .get("/transactions/contract/hyle")
+            .await
+            .json::<Vec<APITransaction>>();
+        assert!(!transactions.is_empty());
+
+        let blob_transactions = server
+            .get("/blob_transactions/contract/hyle")
+            .await
+            .json::<Vec<TransactionWithBlobs>>();
+        assert!(!blob_transactions.is_empty());
+        assert!(blob_transactions
+            .iter()
+            .all(|tx| tx.blobs.iter().any(|b| b.contract_name == "hyle")));
+
+        let blobs = server
+            .get(&format!(
+                "/blobs/hash/{}",
+                blob_transactions.first().unwrap().tx_hash.0
+            ))
+            .await
+            .json::<Vec<APIBlob>>();
+        assert!(!blobs.is_empty());
+
+        let proofs = server.get("/proofs").await.json::<Vec<APITransaction>>();
+        assert!(!proofs.is_empty());
+        let proof = server
+            .get(&format!(
+                "/proof/hash/{}",
+                proofs.first().unwrap().tx_hash.0
+            ))
+            .await
+            .json::<APIProofDetails>();
+        assert_eq!(proof.proof_outputs.len(), 1);
+
+        let summary = server
+            .get("/identity/hyli@wallet/summary")
+            .await
+            .json::<APIIdentitySummary>();
+        assert_eq!(summary.first_seen_block, Some(BlockHeight(4)));
+
+        server.get("/stats").await.assert_status_ok();
+        server.get("/stats/proofs").await.assert_status_ok();
+        server
+            .get("/stats/contract/hyle/settlement")
+            .await
+            .assert_status_ok();
+
+        // Settlement analytics are maintained by the indexer and aggregated by the explorer
+        let settlement_stats = server
+            .get("/stats/contract/wallet/settlement?from=0&to=7200000&slo_secs=1")
+            .await
+            .json::<APIContractSettlementStats>();
+        assert_eq!(settlement_stats.points.len(), 2);
+        let point = settlement_stats.points.first().unwrap();
+        assert_eq!(point.sequenced_txs, 5);
+        assert_eq!(point.successful_txs, 5);
+        assert_eq!(point.blob_bytes, 100);
+        assert_eq!(point.settled_within_slo, Some(5));
+        assert_eq!(
+            point.settlement_latency,
+            Some(APILatencyPercentiles {
+                count: 5,
+                p50: 0,
+                p90: 0,
+                p99: 0,
+                max: 0
+            })
+        );
+        assert_eq!(
+            point.proof_settlement_lag.as_ref().map(|lag| lag.count),
+            Some(5)
+        );
+        let point = settlement_stats.points.get(1).unwrap();
+        assert_eq!(point.sequenced_txs, 0);
+        assert_eq!(point.settlement_latency, None);
+
+        // Percentiles are taken at the nearest rank
+        let txs: Vec<(String, String)> = with_db!(&indexer.db, |pool| sqlx::query_as(
+            "SELECT tx_hash, parent_dp_hash FROM transactions LIMIT 10"
+        )
+        .fetch_all(pool)
+        .await)?;
+        assert_eq!(txs.len(), 10);
+        let insert = "INSERT INTO contract_settlements (contract_name, tx_hash, parent_dp_hash, sequenced_at, blob_bytes, transaction_status, settlement_latency) VALUES ('z', $1, $2, 0, 0, 'success', $3)";
+        for (latency, (tx_hash, parent_dp_hash)) in (1..).zip(txs) {
+            with_db!(&indexer.db, |pool| sqlx::query(insert)
+                .bind(&tx_hash)
+                .bind(&parent_dp_hash)
+                .bind(latency * 100)
+                .execute(pool)
+                .await
+                .map(|_| ()))?;
+        }
+        let settlement_stats = server
+            .get("/stats/contract/z/settlement?from=0&to=3600000")
             .await
-            .context("fetch contracts")?;
+            .json::<APIContractSettlementStats>();
         assert_eq!(
-            rows.iter()
-                .map(|r| r.get::<String, _>("contract_name"))
+            settlement_stats.points.first().unwrap().settlement_latency,
+            Some(APILatencyPercentiles {
+                count: 10,
+                p50: 500,
+                p90: 900,
+                p99: 1000,
+                max: 1000
+            })
+        );
+
+        server
+            .get(&format!(
+                "/stats/contract/wallet/settlement?bucket_secs={}",
+                u64::MAX
+            ))
+            .await
+            .assert_status_bad_request();
+        server
+            .get("/stats/contract/wallet/settlement?from=0&to=3600000&bucket_secs=1")
+            .await
+            .assert_status_bad_request();
+
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_contract_state_history() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = IndexerDb::connect(&format!(
+            "sqlite://{}",
+            dir.path().join("indexer.db").display()
+        ))
+        .await?;
+        db.migrate().await?;
+        let (mut indexer, explorer) = new_indexer(db).await;
+        let server = setup_test_server(&explorer).await?;
+
+        let (b1, b2, b3) = contracts_blocks();
+        indexer.handle_processed_block(b1)?;
+        indexer.handle_processed_block(b2)?;
+        indexer.handle_processed_block(b3)?;
+        indexer.dump_store_to_db().await?;
+
+        let registrations = server
+            .get("/state/contract/a/transitions")
+            .await
+            .json::<Vec<APIContractStateTransition>>();
+        let registration = registrations.first().context("registration of a")?;
+        assert_eq!(
+            registration.kind,
+            APIContractStateTransitionKind::Registration
+        );
+        assert_eq!(registration.block_height, BlockHeight(3));
+        assert!(registration.tx_hash.is_some());
+        assert!(registration.program_id.is_some());
+
+        // The wallet is registered in a block that is not indexed, only its settlements are known
+        let transitions = server
+            .get("/state/contract/wallet/transitions")
+            .await
+            .json::<Vec<APIContractStateTransition>>();
+        assert!(transitions.len() >= 2);
+        assert!(transitions
+            .iter()
+            .all(|t| t.kind == APIContractStateTransitionKind::Settlement && t.tx_hash.is_some()));
+        assert_eq!(
+            transitions
+                .iter()
+                .take(2)
+                .map(|t| (
+                    t.block_height,
+                    t.initial_state.clone(),
+                    t.next_state.clone()
+                ))
                 .collect::<Vec<_>>(),
-            vec!["a"]
+            vec![
+                (
+                    BlockHeight(4),
+                    Some(StateCommitment(vec![0])),
+                    StateCommitment(vec![1])
+                ),
+                (
+                    BlockHeight(4),
+                    Some(StateCommitment(vec![1])),
+                    StateCommitment(vec![2])
+                ),
+            ]
         );
+        for (previous, next) in transitions.iter().zip(transitions.iter().skip(1)) {
+            assert_eq!(next.initial_state, Some(previous.next_state.clone()));
+        }
+
+        let page = server
+            .get("/state/contract/wallet/transitions?from_height=4&to_height=4&nb_results=1")
+            .await
+            .json::<Vec<APIContractStateTransition>>();
+        assert_eq!(page, transitions.get(..1).unwrap());
+
+        let diff = server
+            .get("/state/contract/wallet/diff?from=3&to=4")
+            .await
+            .json::<APIContractStateDiff>();
+        assert_eq!(diff.from_state, None);
+        assert_eq!(diff.to_state, Some(StateCommitment(vec![2])));
+        assert_eq!(diff.transitions, transitions.get(..2).unwrap());
+        assert!(!diff.truncated);
+
+        server
+            .get("/state/contract/wallet/diff?from=5&to=4")
+            .await
+            .assert_status_bad_request();
+
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_registration_settled_in_same_block() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = IndexerDb::connect(&format!(
+            "sqlite://{}",
+            dir.path().join("indexer.db").display()
+        ))
+        .await?;
+        db.migrate().await?;
+        let (mut indexer, explorer) = new_indexer(db).await;
+        let server = setup_test_server(&explorer).await?;
+
+        let mut node_state = NodeState {
+            store: NodeStateStore::default(),
+            metrics: NodeStateMetrics::global("test".to_string(), "test"),
+        };
+        let blob_tx: Transaction = BlobTransaction::new(
+            "test@z",
+            vec![Blob {
+                contract_name: "z".into(),
+                data: BlobData(vec![1]),
+            }],
+        )
+        .into();
+        let proof_tx = new_proof_tx(
+            "test@z".into(),
+            "z".into(),
+            BlobIndex(0),
+            &blob_tx,
+            StateCommitment(vec![5]),
+            StateCommitment(vec![6]),
+        );
+        let block = node_state.craft_block_and_handle(
+            1,
+            vec![
+                new_register_tx("z".into(), StateCommitment(vec![5])).into(),
+                blob_tx,
+                proof_tx,
+            ],
+        );
+        indexer.handle_processed_block(block)?;
+        indexer.dump_store_to_db().await?;
+
+        // The registration keeps its own state, the settlement follows it
+        let transitions = server
+            .get("/state/contract/z/transitions")
+            .await
+            .json::<Vec<APIContractStateTransition>>();
+        assert_eq!(
+            transitions
+                .iter()
+                .map(|t| (t.kind, t.initial_state.clone(), t.next_state.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    APIContractStateTransitionKind::Registration,
+                    None,
+                    StateCommitment(vec![5])
+                ),
+                (
+                    APIContractStateTransitionKind::Settlement,
+                    Some(StateCommitment(vec![5])),
+                    StateCommitment(vec![6])
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_contract_schema() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = IndexerDb::connect(&format!(
+            "sqlite://{}",
+            dir.path().join("indexer.db").display()
+        ))
+        .await?;
+        db.migrate().await?;
+        let (mut indexer, explorer) = new_indexer(db).await;
+        let server = setup_test_server(&explorer).await?;
+
+        let schema = ContractSchema {
+            action: "u64".to_string(),
+            action_encoding: ActionEncoding::Raw,
+            state: None,
+            program_outputs: Some(ProgramOutputsEncoding::Utf8),
+            definitions: BTreeMap::from([(
+                "u64".to_string(),
+                TypeDefinition::Primitive { size: 8 },
+            )]),
+        };
+        let register_with_schema = BlobTransaction::new(
+            "hyle@hyle",
+            vec![RegisterContractAction {
+                verifier: "test".into(),
+                program_id: ProgramId(vec![3, 2, 1]),
+                contract_name: "with_schema".into(),
+                constructor_metadata: Some(
+                    ConstructorMetadata {
+                        schema: Some(schema.clone()),
+                        initial_state: None,
+                    }
+                    .to_bytes(),
+                ),
+                ..Default::default()
+            }
+            .as_blob("hyle".into(), None, None)],
+        );
+
+        let mut node_state = NodeState {
+            store: NodeStateStore::default(),
+            metrics: NodeStateMetrics::global("test".to_string(), "test"),
+        };
+        let block = node_state.craft_block_and_handle(
+            1,
+            vec![
+                register_with_schema.into(),
+                new_register_tx("without_schema".into(), StateCommitment(vec![])).into(),
+            ],
+        );
+        indexer.handle_processed_block(block)?;
+        indexer.dump_store_to_db().await?;
+
+        let fetched = server
+            .get("/contract/with_schema/schema")
+            .await
+            .json::<ContractSchema>();
+        assert_eq!(fetched, schema);
+
+        server
+            .get("/contract/without_schema/schema")
+            .await
+            .assert_status_not_found();
+        server
+            .get("/contract/unknown/schema")
+            .await
+            .assert_status_not_found();
+
+        Ok(())
+    }
+
+    #[cfg(feature = "graphql")]
+    #[test_log::test(tokio::test)]
+    async fn test_graphql_api() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = IndexerDb::connect(&format!(
+            "sqlite://{}",
+            dir.path().join("indexer.db").display()
+        ))
+        .await?;
+        db.migrate().await?;
+        let (mut indexer, explorer) = new_indexer(db).await;
+        let server = setup_test_server(&explorer).await?;
+
+        let (b1, b2, b3) = contracts_blocks();
+        indexer.handle_processed_block(b1)?;
+        indexer.handle_processed_block(b2)?;
+        indexer.handle_processed_block(b3)?;
+        indexer.dump_store_to_db().await?;
+
+        let query = |query: &str| server.post("/graphql").json(&json!({ "query": query }));
+
+        let page = query("{ blocks(first: 2) { nodes { height } pageInfo { hasNextPage } } }")
+            .await
+            .json::<serde_json::Value>();
+        assert_eq!(
+            page,
+            json!({ "data": { "blocks": {
+                "nodes": [{ "height": 5 }, { "height": 4 }],
+                "pageInfo": { "hasNextPage": true }
+            } } })
+        );
+        let page = query(
+            r#"{ blocks(first: 2, after: "4") { nodes { height } pageInfo { hasNextPage } } }"#,
+        )
+        .await
+        .json::<serde_json::Value>();
+        assert_eq!(
+            page,
+            json!({ "data": { "blocks": {
+                "nodes": [{ "height": 3 }],
+                "pageInfo": { "hasNextPage": false }
+            } } })
+        );
+
+        let transactions = query(
+            r#"{ transactions(filter: { contractName: "hyle", transactionType: BLOB_TRANSACTION }) {
+                nodes { blockHeight block { height } blobs { contractName } proofs { settled } }
+            } }"#,
+        )
+        .await
+        .json::<serde_json::Value>();
+        let nodes = transactions["data"]["transactions"]["nodes"]
+            .as_array()
+            .context("transactions")?;
+        assert!(!nodes.is_empty());
+        for node in nodes {
+            assert_eq!(node["blockHeight"], node["block"]["height"]);
+            assert!(node["blobs"]
+                .as_array()
+                .context("blobs")?
+                .iter()
+                .any(|blob| blob["contractName"] == "hyle"));
+        }
+
+        let proofs = query(
+            r#"{ proofs(contractName: "wallet") { nodes { contractName blobTransaction { identity } } } }"#,
+        )
+        .await
+        .json::<serde_json::Value>();
+        let nodes = proofs["data"]["proofs"]["nodes"]
+            .as_array()
+            .context("proofs")?;
+        assert!(!nodes.is_empty());
+        assert!(nodes
+            .iter()
+            .all(|proof| proof["blobTransaction"]["identity"] == "hyli@wallet"));
+
+        let contract = query(r#"{ contract(contractName: "a") { contractName } }"#)
+            .await
+            .json::<serde_json::Value>();
+        assert_eq!(
+            contract,
+            json!({ "data": { "contract": { "contractName": "a" } } })
+        );
+
+        let error = query("{ blocks(first: 1000) { nodes { height } } }")
+            .await
+            .json::<serde_json::Value>();
+        assert!(error["errors"].is_array());
+
         Ok(())
     }
 
-    // In case of duplicate tx hash, should return information of the tx with the highest block height
-    // or index (position in the block)
     #[test_log::test(tokio::test)]
     async fn test_indexer_api_doubles() -> Result<()> {
         let container = Postgres::default()
@@ -1039,13 +1535,13 @@ mod test {
             ))
             .await
             .unwrap();
-        MIGRATOR.run(&db).await.unwrap();
+        PG_MIGRATOR.run(&db).await.unwrap();
         sqlx::raw_sql(include_str!("../tests/fixtures/test_data.sql"))
             .execute(&db)
             .await
             .context("insert test data")?;
 
-        let (_indexer, explorer) = new_indexer(db).await;
+        let (_indexer, explorer) = new_indexer(IndexerDb::Postgres(db)).await;
         let server = setup_test_server(&explorer).await?;
 
         // Multiple txs with same hash -- all in different blocks
@@ -1146,13 +1642,13 @@ mod test {
             ))
             .await
             .unwrap();
-        MIGRATOR.run(&db).await.unwrap();
+        PG_MIGRATOR.run(&db).await.unwrap();
         sqlx::raw_sql(include_str!("../tests/fixtures/test_data.sql"))
             .execute(&db)
             .await
             .context("insert test data")?;
 
-        let (_indexer, mut explorer) = new_indexer(db).await;
+        let (_indexer, mut explorer) = new_indexer(IndexerDb::Postgres(db)).await;
         let server = setup_test_server(&explorer).await?;
 
         // Blocks
@@ -1226,6 +1722,148 @@ mod test {
         transactions_response.assert_status_ok();
         assert_eq!(transactions_response.text(), "[]");
 
+        // Get transactions by identity
+        let transactions_response = server.get("/transactions/identity/bob@contract_1").await;
+        transactions_response.assert_status_ok();
+        let transactions = transactions_response.json::<Vec<APITransaction>>();
+        // Transaction 2 was sent in two data proposals, it is only returned once
+        assert_eq!(transactions.len(), 3);
+        assert_eq!(transactions.first().unwrap().index, Some(0));
+
+        // Paginate with a cursor
+        let transactions_response = server
+            .get("/transactions/identity/bob@contract_1?before_block=3&before_index=1&nb_results=1")
+            .await;
+        transactions_response.assert_status_ok();
+        let transactions = transactions_response.json::<Vec<APITransaction>>();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(
+            transactions.first().unwrap().tx_hash,
+            TxHash("test_tx_hash_2aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_string())
+        );
+
+        // The cursor needs both the block and the index
+        let transactions_response = server
+            .get("/transactions/identity/bob@contract_1?before_block=3")
+            .await;
+        transactions_response.assert_status_bad_request();
+
+        // Filter by status and contract
+        let transactions_response = server
+            .get("/transactions/identity/bob@contract_1?status=Sequenced&contract=contract_1")
+            .await;
+        transactions_response.assert_status_ok();
+        assert_eq!(transactions_response.json::<Vec<APITransaction>>().len(), 1);
+        let transactions_response = server
+            .get("/transactions/identity/bob@contract_1?contract=unknown_contract")
+            .await;
+        transactions_response.assert_status_ok();
+        assert_eq!(transactions_response.text(), "[]");
+
+        // Identity summary
+        let summary_response = server.get("/identity/bob@contract_1/summary").await;
+        summary_response.assert_status_ok();
+        let summary = summary_response.json::<APIIdentitySummary>();
+        assert_eq!(summary.first_seen_block, Some(BlockHeight(2)));
+        assert_eq!(summary.total_txs, 3);
+        assert_eq!(
+            summary.txs_by_status,
+            vec![
+                (TransactionStatusDb::Success, 2),
+                (TransactionStatusDb::Sequenced, 1)
+            ]
+        );
+        assert_eq!(summary.contracts, vec!["contract_1".to_string()]);
+
+        let summary_response = server.get("/identity/unknown@contract_1/summary").await;
+        summary_response.assert_status_not_found();
+
+        // Get transactions by data proposal
+        let transactions_response = server
+            .get("/transactions/data_proposal/dp_hashbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb")
+            .await;
+        transactions_response.assert_status_ok();
+        assert_eq!(transactions_response.json::<Vec<APITransaction>>().len(), 2);
+
+        // Search
+        let results = server
+            .get("/search?q=2")
+            .await
+            .json::<Vec<APISearchResult>>();
+        assert_eq!(
+            results,
+            vec![APISearchResult {
+                kind: APISearchResultKind::Block,
+                id: "2".to_string(),
+                link: "/block/height/2".to_string(),
+            }]
+        );
+
+        let results = server
+            .get("/search?q=block2aa")
+            .await
+            .json::<Vec<APISearchResult>>();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results.first().unwrap().link,
+            "/block/hash/block2aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+        );
+
+        let results = server
+            .get("/search?q=test_tx_hash_3")
+            .await
+            .json::<Vec<APISearchResult>>();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results.first().unwrap().kind, APISearchResultKind::Proof);
+
+        // Underscores are not wildcards
+        let results = server
+            .get("/search?q=test_tx_hash_")
+            .await
+            .json::<Vec<APISearchResult>>();
+        assert_eq!(results.len(), 5);
+        let results = server
+            .get("/search?q=testxtx")
+            .await
+            .json::<Vec<APISearchResult>>();
+        assert!(results.is_empty());
+
+        let results = server
+            .get("/search?q=dp_hashb")
+            .await
+            .json::<Vec<APISearchResult>>();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results.first().unwrap().kind,
+            APISearchResultKind::DataProposal
+        );
+
+        let results = server
+            .get("/search?q=contract_")
+            .await
+            .json::<Vec<APISearchResult>>();
+        assert_eq!(
+            results.first().unwrap(),
+            &APISearchResult {
+                kind: APISearchResultKind::Contract,
+                id: "contract_1".to_string(),
+                link: "/contract/contract_1".to_string(),
+            }
+        );
+
+        let results = server
+            .get("/search?q=bob@contract_1")
+            .await
+            .json::<Vec<APISearchResult>>();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results.first().unwrap().kind, APISearchResultKind::Identity);
+
+        let results = server
+            .get("/search?q=test_tx_hash_&nb_results=2")
+            .await
+            .json::<Vec<APISearchResult>>();
+        assert_eq!(results.len(), 2);
+
         // Get an existing transaction by hash
         let transactions_response = server
             .get("/transaction/hash/test_tx_hash_1aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa")
@@ -1305,6 +1943,23 @@ mod test {
             assert_eq!(contract_name, ContractName::new("contract_1"));
         }
 
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+            .await
+            .unwrap();
+        socket
+            .send(tokio_tungstenite::tungstenite::Message::Text(
+                r#"{"action": "subscribe", "topic": "blocks"}"#.into(),
+            ))
+            .await
+            .unwrap();
+        let reply = socket.next().await.unwrap().unwrap();
+        assert_eq!(
+            serde_json::from_str::<ExplorerWsEvent>(reply.to_text().unwrap()).unwrap(),
+            ExplorerWsEvent::Subscribed {
+                topic: ExplorerWsTopic::Blocks
+            }
+        );
+
         Ok(())
     }
 }
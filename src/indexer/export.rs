@@ -0,0 +1,684 @@
+//! Export of the indexed data of a range of blocks to CSV or Parquet files, for offline analytics.
+//!
+//! Each table is written to `<output>/<table>/date=<YYYY-MM-DD>/<first block height>.<format>`,
+//! partitioned by the day of the block timestamps.
+
+use std::{fs::File, path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Result};
+use arrow_array::{
+    ArrayRef, BinaryArray, BooleanArray, Int64Array, RecordBatch, StringArray,
+    TimestampMillisecondArray,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::{DateTime, NaiveDate, SecondsFormat};
+use hyle_model::api::{TransactionStatusDb, TransactionTypeDb};
+use parquet::arrow::ArrowWriter;
+use sqlx::types::chrono::NaiveDateTime;
+use tracing::info;
+
+use crate::explorer::{
+    blob_decoders::BlobDecoders,
+    db::{with_db, IndexerDb},
+};
+use crate::model::*;
+
+/// Number of blocks whose rows are loaded at once
+const EXPORT_CHUNK_SIZE: u64 = 1000;
+
+/// Number of rows of a Parquet row group
+const PARQUET_BATCH_SIZE: usize = 8192;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportConf {
+    /// First block to export
+    pub from: BlockHeight,
+    /// Last block to export, defaults to the last indexed block
+    pub to: Option<BlockHeight>,
+    pub format: ExportFormat,
+    /// Directory the tables are written to
+    pub output: PathBuf,
+    /// Adds a `decoded_data` JSON column to the blobs, for contracts with a known decoder
+    pub decode_blobs: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ColumnType {
+    Int,
+    Text,
+    Bool,
+    Binary,
+    Timestamp,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Int(Option<i64>),
+    Text(Option<String>),
+    Bool(Option<bool>),
+    Binary(Option<Vec<u8>>),
+    /// UNIX timestamp in milliseconds
+    Timestamp(Option<i64>),
+}
+
+impl Value {
+    fn to_csv_field(&self) -> String {
+        match self {
+            Value::Int(v) => v.map(|v| v.to_string()).unwrap_or_default(),
+            Value::Text(v) => v.clone().unwrap_or_default(),
+            Value::Bool(v) => v.map(|v| v.to_string()).unwrap_or_default(),
+            Value::Binary(v) => v.as_ref().map(hex::encode).unwrap_or_default(),
+            Value::Timestamp(v) => v
+                .and_then(DateTime::from_timestamp_millis)
+                .map(|date| date.to_rfc3339_opts(SecondsFormat::Millis, true))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+struct Table {
+    name: &'static str,
+    columns: Vec<(&'static str, ColumnType)>,
+}
+
+impl Table {
+    fn schema(&self) -> SchemaRef {
+        Arc::new(Schema::new(
+            self.columns
+                .iter()
+                .map(|(name, column_type)| {
+                    let data_type = match column_type {
+                        ColumnType::Int => DataType::Int64,
+                        ColumnType::Text => DataType::Utf8,
+                        ColumnType::Bool => DataType::Boolean,
+                        ColumnType::Binary => DataType::Binary,
+                        ColumnType::Timestamp => {
+                            DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))
+                        }
+                    };
+                    Field::new(*name, data_type, true)
+                })
+                .collect::<Vec<_>>(),
+        ))
+    }
+}
+
+fn record_batch(table: &Table, schema: SchemaRef, rows: Vec<Vec<Value>>) -> Result<RecordBatch> {
+    let mut columns: Vec<Vec<Value>> = table
+        .columns
+        .iter()
+        .map(|_| Vec::with_capacity(rows.len()))
+        .collect();
+    for row in rows {
+        for (column, value) in columns.iter_mut().zip(row) {
+            column.push(value);
+        }
+    }
+
+    let arrays = table
+        .columns
+        .iter()
+        .zip(columns)
+        .map(|((name, column_type), values)| {
+            let values = values.into_iter();
+            let array: ArrayRef = match column_type {
+                ColumnType::Int => Arc::new(
+                    values
+                        .map(|v| match v {
+                            Value::Int(v) => Ok(v),
+                            _ => Err(anyhow::anyhow!("Column {name} expects integers")),
+                        })
+                        .collect::<Result<Int64Array>>()?,
+                ),
+                ColumnType::Text => Arc::new(
+                    values
+                        .map(|v| match v {
+                            Value::Text(v) => Ok(v),
+                            _ => Err(anyhow::anyhow!("Column {name} expects text")),
+                        })
+                        .collect::<Result<StringArray>>()?,
+                ),
+                ColumnType::Bool => Arc::new(
+                    values
+                        .map(|v| match v {
+                            Value::Bool(v) => Ok(v),
+                            _ => Err(anyhow::anyhow!("Column {name} expects booleans")),
+                        })
+                        .collect::<Result<BooleanArray>>()?,
+                ),
+                ColumnType::Binary => Arc::new(
+                    values
+                        .map(|v| match v {
+                            Value::Binary(v) => Ok(v),
+                            _ => Err(anyhow::anyhow!("Column {name} expects bytes")),
+                        })
+                        .collect::<Result<BinaryArray>>()?,
+                ),
+                ColumnType::Timestamp => Arc::new(
+                    values
+                        .map(|v| match v {
+                            Value::Timestamp(v) => Ok(v),
+                            _ => Err(anyhow::anyhow!("Column {name} expects timestamps")),
+                        })
+                        .collect::<Result<TimestampMillisecondArray>>()?
+                        .with_timezone("UTC"),
+                ),
+            };
+            Ok(array)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    RecordBatch::try_new(schema, arrays).context("Building record batch")
+}
+
+enum PartitionWriter {
+    Csv(csv::Writer<File>),
+    Parquet {
+        writer: ArrowWriter<File>,
+        schema: SchemaRef,
+        rows: Vec<Vec<Value>>,
+    },
+}
+
+/// Writes the rows of a table, in a new file each time the day changes
+struct TableExporter {
+    table: Table,
+    format: ExportFormat,
+    output: PathBuf,
+    partition: Option<(NaiveDate, PartitionWriter)>,
+    exported_rows: u64,
+}
+
+impl TableExporter {
+    fn new(table: Table, format: ExportFormat, output: &std::path::Path) -> Self {
+        TableExporter {
+            output: output.join(table.name),
+            table,
+            format,
+            partition: None,
+            exported_rows: 0,
+        }
+    }
+
+    fn write(&mut self, day: NaiveDate, height: BlockHeight, row: Vec<Value>) -> Result<()> {
+        if self.partition.as_ref().is_none_or(|(d, _)| *d != day) {
+            self.finish_partition()?;
+            self.partition = Some((day, self.create_partition(day, height)?));
+        }
+        let Some((_, writer)) = self.partition.as_mut() else {
+            anyhow::bail!("No partition to write table {} to", self.table.name);
+        };
+
+        match writer {
+            PartitionWriter::Csv(writer) => {
+                writer.write_record(row.iter().map(Value::to_csv_field))?;
+            }
+            PartitionWriter::Parquet {
+                writer,
+                schema,
+                rows,
+            } => {
+                rows.push(row);
+                if rows.len() >= PARQUET_BATCH_SIZE {
+                    let batch = record_batch(&self.table, schema.clone(), std::mem::take(rows))?;
+                    writer.write(&batch)?;
+                }
+            }
+        }
+        self.exported_rows += 1;
+        Ok(())
+    }
+
+    fn create_partition(&self, day: NaiveDate, height: BlockHeight) -> Result<PartitionWriter> {
+        let dir = self.output.join(format!("date={day}"));
+        std::fs::create_dir_all(&dir).context(format!("Creating {}", dir.display()))?;
+        let path = dir.join(format!("{}.{}", height.0, self.format.extension()));
+        let file = File::create(&path).context(format!("Creating {}", path.display()))?;
+
+        Ok(match self.format {
+            ExportFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(file);
+                writer.write_record(self.table.columns.iter().map(|(name, _)| name))?;
+                PartitionWriter::Csv(writer)
+            }
+            ExportFormat::Parquet => {
+                let schema = self.table.schema();
+                PartitionWriter::Parquet {
+                    writer: ArrowWriter::try_new(file, schema.clone(), None)?,
+                    schema,
+                    rows: vec![],
+                }
+            }
+        })
+    }
+
+    fn finish_partition(&mut self) -> Result<()> {
+        match self.partition.take() {
+            Some((_, PartitionWriter::Csv(mut writer))) => writer.flush()?,
+            Some((
+                _,
+                PartitionWriter::Parquet {
+                    mut writer,
+                    schema,
+                    rows,
+                },
+            )) => {
+                if !rows.is_empty() {
+                    writer.write(&record_batch(&self.table, schema, rows)?)?;
+                }
+                writer.close()?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+fn timestamp(date: &NaiveDateTime) -> Value {
+    Value::Timestamp(Some(date.and_utc().timestamp_millis()))
+}
+
+fn height(height: i64) -> BlockHeight {
+    BlockHeight(u64::try_from(height).unwrap_or_default())
+}
+
+#[derive(sqlx::FromRow, Debug)]
+struct BlockRow {
+    hash: String,
+    parent_hash: String,
+    height: i64,
+    timestamp: NaiveDateTime,
+    total_txs: i64,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+struct TransactionRow {
+    timestamp: NaiveDateTime,
+    height: i64,
+    index: Option<i32>,
+    tx_hash: String,
+    parent_dp_hash: String,
+    version: i32,
+    transaction_type: TransactionTypeDb,
+    transaction_status: TransactionStatusDb,
+    lane_id: Option<String>,
+    identity: Option<String>,
+    settled_block_height: Option<i64>,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+struct BlobRow {
+    timestamp: NaiveDateTime,
+    height: i64,
+    tx_hash: String,
+    parent_dp_hash: String,
+    blob_index: i32,
+    identity: String,
+    contract_name: String,
+    data: Vec<u8>,
+    verified: bool,
+    program_id: Option<Vec<u8>>,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+struct ProofOutputRow {
+    timestamp: NaiveDateTime,
+    height: i64,
+    proof_tx_hash: String,
+    proof_parent_dp_hash: String,
+    blob_tx_hash: String,
+    blob_parent_dp_hash: String,
+    blob_index: i32,
+    blob_proof_output_index: i32,
+    contract_name: String,
+    hyle_output: serde_json::Value,
+    settled: bool,
+}
+
+/// Name of an enum value, as served by the explorer API
+fn enum_name<T: serde::Serialize>(value: &T) -> Option<String> {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+}
+
+/// Exports the blocks, transactions, blobs and proof outputs of the range
+pub async fn export(db: &IndexerDb, conf: &ExportConf, blob_decoders: &BlobDecoders) -> Result<()> {
+    let to = match conf.to {
+        Some(to) => to,
+        None => {
+            let max: Option<i64> = with_db!(db, |pool| sqlx::query_scalar(
+                "SELECT max(height) as max FROM blocks"
+            )
+            .fetch_one(pool)
+            .await?);
+            height(max.context("No block indexed yet")?)
+        }
+    };
+    info!(
+        "📤 Exporting blocks {} to {to} as {:?} into {}",
+        conf.from,
+        conf.format,
+        conf.output.display()
+    );
+
+    let mut blocks = TableExporter::new(
+        Table {
+            name: "blocks",
+            columns: vec![
+                ("timestamp", ColumnType::Timestamp),
+                ("height", ColumnType::Int),
+                ("hash", ColumnType::Text),
+                ("parent_hash", ColumnType::Text),
+                ("total_txs", ColumnType::Int),
+            ],
+        },
+        conf.format,
+        &conf.output,
+    );
+    let mut transactions = TableExporter::new(
+        Table {
+            name: "transactions",
+            columns: vec![
+                ("timestamp", ColumnType::Timestamp),
+                ("block_height", ColumnType::Int),
+                ("index", ColumnType::Int),
+                ("tx_hash", ColumnType::Text),
+                ("parent_dp_hash", ColumnType::Text),
+                ("version", ColumnType::Int),
+                ("transaction_type", ColumnType::Text),
+                ("transaction_status", ColumnType::Text),
+                ("lane_id", ColumnType::Text),
+                ("identity", ColumnType::Text),
+                ("settled_block_height", ColumnType::Int),
+            ],
+        },
+        conf.format,
+        &conf.output,
+    );
+    let mut blob_columns = vec![
+        ("timestamp", ColumnType::Timestamp),
+        ("block_height", ColumnType::Int),
+        ("tx_hash", ColumnType::Text),
+        ("parent_dp_hash", ColumnType::Text),
+        ("blob_index", ColumnType::Int),
+        ("identity", ColumnType::Text),
+        ("contract_name", ColumnType::Text),
+        ("data", ColumnType::Binary),
+        ("verified", ColumnType::Bool),
+    ];
+    if conf.decode_blobs {
+        blob_columns.push(("decoded_data", ColumnType::Text));
+    }
+    let mut blobs = TableExporter::new(
+        Table {
+            name: "blobs",
+            columns: blob_columns,
+        },
+        conf.format,
+        &conf.output,
+    );
+    let mut proof_outputs = TableExporter::new(
+        Table {
+            name: "blob_proof_outputs",
+            columns: vec![
+                ("timestamp", ColumnType::Timestamp),
+                ("block_height", ColumnType::Int),
+                ("proof_tx_hash", ColumnType::Text),
+                ("proof_parent_dp_hash", ColumnType::Text),
+                ("blob_tx_hash", ColumnType::Text),
+                ("blob_parent_dp_hash", ColumnType::Text),
+                ("blob_index", ColumnType::Int),
+                ("blob_proof_output_index", ColumnType::Int),
+                ("contract_name", ColumnType::Text),
+                ("hyle_output", ColumnType::Text),
+                ("settled", ColumnType::Bool),
+            ],
+        },
+        conf.format,
+        &conf.output,
+    );
+
+    for start in (conf.from.0..=to.0).step_by(EXPORT_CHUNK_SIZE as usize) {
+        let end = (start + EXPORT_CHUNK_SIZE - 1).min(to.0);
+        let (start_bind, end_bind) = (start as i64, end as i64);
+
+        let rows = with_db!(db, |pool| sqlx::query_as::<_, BlockRow>(
+            "SELECT hash, parent_hash, height, timestamp, total_txs FROM blocks WHERE height >= $1 AND height <= $2 ORDER BY height"
+        )
+        .bind(start_bind)
+        .bind(end_bind)
+        .fetch_all(pool)
+        .await)
+        .context("Fetching blocks")?;
+        for row in rows {
+            blocks.write(
+                row.timestamp.date(),
+                height(row.height),
+                vec![
+                    timestamp(&row.timestamp),
+                    Value::Int(Some(row.height)),
+                    Value::Text(Some(row.hash)),
+                    Value::Text(Some(row.parent_hash)),
+                    Value::Int(Some(row.total_txs)),
+                ],
+            )?;
+        }
+
+        let rows = with_db!(db, |pool| sqlx::query_as::<_, TransactionRow>(
+            r#"
+SELECT b.timestamp, b.height, t."index", t.tx_hash, t.parent_dp_hash, t.version, t.transaction_type,
+  t.transaction_status, t.lane_id, t.identity, t.settled_block_height
+FROM transactions t
+JOIN blocks b ON t.block_hash = b.hash
+WHERE b.height >= $1 AND b.height <= $2
+ORDER BY b.height, t."index"
+            "#
+        )
+        .bind(start_bind)
+        .bind(end_bind)
+        .fetch_all(pool)
+        .await)
+        .context("Fetching transactions")?;
+        for row in rows {
+            transactions.write(
+                row.timestamp.date(),
+                height(row.height),
+                vec![
+                    timestamp(&row.timestamp),
+                    Value::Int(Some(row.height)),
+                    Value::Int(row.index.map(i64::from)),
+                    Value::Text(Some(row.tx_hash)),
+                    Value::Text(Some(row.parent_dp_hash)),
+                    Value::Int(Some(i64::from(row.version))),
+                    Value::Text(enum_name(&row.transaction_type)),
+                    Value::Text(enum_name(&row.transaction_status)),
+                    Value::Text(row.lane_id),
+                    Value::Text(row.identity),
+                    Value::Int(row.settled_block_height),
+                ],
+            )?;
+        }
+
+        let rows = with_db!(db, |pool| sqlx::query_as::<_, BlobRow>(
+            r#"
+SELECT b.timestamp, b.height, bl.tx_hash, bl.parent_dp_hash, bl.blob_index, bl.identity,
+  bl.contract_name, bl.data, bl.verified, c.program_id
+FROM blobs bl
+JOIN transactions t ON t.tx_hash = bl.tx_hash AND t.parent_dp_hash = bl.parent_dp_hash
+JOIN blocks b ON t.block_hash = b.hash
+LEFT JOIN contracts c ON c.contract_name = bl.contract_name
+WHERE b.height >= $1 AND b.height <= $2
+ORDER BY b.height, t."index", bl.blob_index
+            "#
+        )
+        .bind(start_bind)
+        .bind(end_bind)
+        .fetch_all(pool)
+        .await)
+        .context("Fetching blobs")?;
+        for row in rows {
+            let decoded_data = conf
+                .decode_blobs
+                .then(|| {
+                    blob_decoders.decode(
+                        &ContractName(row.contract_name.clone()),
+                        row.program_id.clone().map(ProgramId).as_ref(),
+                        &row.data,
+                    )
+                })
+                .flatten();
+            let mut values = vec![
+                timestamp(&row.timestamp),
+                Value::Int(Some(row.height)),
+                Value::Text(Some(row.tx_hash)),
+                Value::Text(Some(row.parent_dp_hash)),
+                Value::Int(Some(i64::from(row.blob_index))),
+                Value::Text(Some(row.identity)),
+                Value::Text(Some(row.contract_name)),
+                Value::Binary(Some(row.data)),
+                Value::Bool(Some(row.verified)),
+            ];
+            if conf.decode_blobs {
+                values.push(Value::Text(decoded_data.map(|data| data.to_string())));
+            }
+            blobs.write(row.timestamp.date(), height(row.height), values)?;
+        }
+
+        let rows = with_db!(db, |pool| sqlx::query_as::<_, ProofOutputRow>(
+            r#"
+SELECT b.timestamp, b.height, bpo.proof_tx_hash, bpo.proof_parent_dp_hash, bpo.blob_tx_hash,
+  bpo.blob_parent_dp_hash, bpo.blob_index, bpo.blob_proof_output_index, bpo.contract_name,
+  bpo.hyle_output, bpo.settled
+FROM blob_proof_outputs bpo
+JOIN transactions t ON t.tx_hash = bpo.proof_tx_hash AND t.parent_dp_hash = bpo.proof_parent_dp_hash
+JOIN blocks b ON t.block_hash = b.hash
+WHERE b.height >= $1 AND b.height <= $2
+ORDER BY b.height, t."index", bpo.blob_proof_output_index
+            "#
+        )
+        .bind(start_bind)
+        .bind(end_bind)
+        .fetch_all(pool)
+        .await)
+        .context("Fetching blob proof outputs")?;
+        for row in rows {
+            proof_outputs.write(
+                row.timestamp.date(),
+                height(row.height),
+                vec![
+                    timestamp(&row.timestamp),
+                    Value::Int(Some(row.height)),
+                    Value::Text(Some(row.proof_tx_hash)),
+                    Value::Text(Some(row.proof_parent_dp_hash)),
+                    Value::Text(Some(row.blob_tx_hash)),
+                    Value::Text(Some(row.blob_parent_dp_hash)),
+                    Value::Int(Some(i64::from(row.blob_index))),
+                    Value::Int(Some(i64::from(row.blob_proof_output_index))),
+                    Value::Text(Some(row.contract_name)),
+                    Value::Text(Some(row.hyle_output.to_string())),
+                    Value::Bool(Some(row.settled)),
+                ],
+            )?;
+        }
+
+        info!("📤 Exported blocks {start} to {end}");
+    }
+
+    for exporter in [
+        &mut blocks,
+        &mut transactions,
+        &mut blobs,
+        &mut proof_outputs,
+    ] {
+        exporter.finish_partition()?;
+        info!(
+            "✅ Exported {} rows of {}",
+            exporter.exported_rows, exporter.table.name
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    fn test_table() -> Table {
+        Table {
+            name: "test",
+            columns: vec![
+                ("timestamp", ColumnType::Timestamp),
+                ("height", ColumnType::Int),
+                ("data", ColumnType::Binary),
+                ("verified", ColumnType::Bool),
+                ("identity", ColumnType::Text),
+            ],
+        }
+    }
+
+    fn test_row(height: i64) -> Vec<Value> {
+        vec![
+            Value::Timestamp(Some(height * 1000)),
+            Value::Int(Some(height)),
+            Value::Binary(Some(vec![1, 2])),
+            Value::Bool(Some(true)),
+            Value::Text(None),
+        ]
+    }
+
+    #[test]
+    fn test_export_partitions_by_day() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (day_1, day_2) = (
+            NaiveDate::from_ymd_opt(2025, 1, 1).context("date")?,
+            NaiveDate::from_ymd_opt(2025, 1, 2).context("date")?,
+        );
+
+        for format in [ExportFormat::Csv, ExportFormat::Parquet] {
+            let mut exporter = TableExporter::new(test_table(), format, dir.path());
+            exporter.write(day_1, BlockHeight(1), test_row(1))?;
+            exporter.write(day_1, BlockHeight(2), test_row(2))?;
+            exporter.write(day_2, BlockHeight(3), test_row(3))?;
+            exporter.finish_partition()?;
+            assert_eq!(exporter.exported_rows, 3);
+        }
+
+        let csv = std::fs::read_to_string(dir.path().join("test/date=2025-01-01/1.csv"))?;
+        assert_eq!(
+            csv,
+            "timestamp,height,data,verified,identity\n\
+             1970-01-01T00:00:01.000Z,1,0102,true,\n\
+             1970-01-01T00:00:02.000Z,2,0102,true,\n"
+        );
+        assert!(dir.path().join("test/date=2025-01-02/3.csv").exists());
+
+        let reader = SerializedFileReader::new(File::open(
+            dir.path().join("test/date=2025-01-01/1.parquet"),
+        )?)?;
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        let reader = SerializedFileReader::new(File::open(
+            dir.path().join("test/date=2025-01-02/3.parquet"),
+        )?)?;
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 1);
+
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
 use crate::explorer::api::*;
+use crate::explorer::db::with_db;
+use crate::explorer::WsExplorerBlock;
 use crate::model::*;
 use crate::node_state::module::NodeStateEvent;
 use anyhow::{bail, Context, Error, Result};
@@ -8,18 +10,16 @@ use hyle_model::api::{TransactionStatusDb, TransactionTypeDb};
 use hyle_model::utils::TimestampMs;
 use hyle_modules::{log_error, log_warn};
 use hyle_net::clock::TimestampMsClock;
-use sqlx::Postgres;
 use sqlx::QueryBuilder;
 use sqlx::Row;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::DerefMut;
 use std::sync::Arc;
 use tracing::{debug, info, trace};
 
 use super::Indexer;
 
-fn calculate_optimal_batch_size(params_per_item: usize) -> usize {
-    let max_params: usize = 65000; // Security margin
+fn calculate_optimal_batch_size(max_params: usize, params_per_item: usize) -> usize {
     if params_per_item == 0 {
         return 1;
     }
@@ -66,6 +66,7 @@ pub struct TxContractStore {
     pub timeout_window: Option<TimeoutWindowDb>,
     pub state_commitment: Vec<u8>,
     pub contract_name: String,
+    pub contract_schema: Option<String>,
 }
 
 #[derive(Debug)]
@@ -73,6 +74,8 @@ pub struct TxContractStateStore {
     pub contract_name: String,
     pub block_hash: ConsensusProposalHash,
     pub state_commitment: Vec<u8>,
+    pub tx_hash: TxHashDb,
+    pub program_id: Vec<u8>,
 }
 
 #[derive(Debug)]
@@ -86,6 +89,63 @@ pub struct TxBlobProofOutputStore {
     pub contract_name: String,
     pub hyle_output: String,
     pub settled: bool,
+    pub program_id: Vec<u8>,
+    pub proved_at: i64,
+}
+
+#[derive(Debug)]
+pub struct TxContractSettlementStore {
+    pub contract_name: String,
+    pub tx_hash: TxHashDb,
+    pub parent_data_proposal_hash: DataProposalHashDb,
+    pub sequenced_at: i64,
+    pub blob_bytes: i64,
+}
+
+/// Updates of already indexed rows, applied once the batched inserts are done.
+#[derive(Debug)]
+pub enum SqlUpdate {
+    TransactionStatus {
+        tx_hash: TxHashDb,
+        parent_dp_hash: DataProposalHashDb,
+        status: TransactionStatusDb,
+        settled_block_height: i64,
+    },
+    BlobVerified {
+        tx_hash: TxHashDb,
+        parent_dp_hash: DataProposalHashDb,
+        blob_index: i32,
+    },
+    BlobProofOutputSettled {
+        blob_tx_hash: TxHashDb,
+        blob_parent_dp_hash: DataProposalHashDb,
+        blob_index: i32,
+        blob_proof_output_index: i32,
+        settled_at: i64,
+    },
+    ContractSettlement {
+        tx_hash: TxHashDb,
+        parent_dp_hash: DataProposalHashDb,
+        status: TransactionStatusDb,
+        settled_at: i64,
+    },
+    BlockContractState {
+        contract_name: String,
+        block_hash: ConsensusProposalHash,
+        state_commitment: Vec<u8>,
+    },
+    ContractStateCommitment {
+        contract_name: String,
+        state_commitment: Vec<u8>,
+    },
+    ContractProgramId {
+        contract_name: String,
+        program_id: Vec<u8>,
+    },
+    ContractTimeoutWindow {
+        contract_name: String,
+        timeout_window: TimeoutWindowDb,
+    },
 }
 
 #[derive(Default)]
@@ -95,17 +155,12 @@ pub(crate) struct IndexerHandlerStore {
     tx_data: Vec<TxDataStore>,
     tx_data_proofs: Vec<TxProofStore>,
     transactions_events: Vec<TxEventStore>,
-    sql_updates: Vec<
-        sqlx::query::Query<
-            'static,
-            Postgres,
-            <sqlx::Postgres as sqlx::Database>::Arguments<'static>,
-        >,
-    >,
+    sql_updates: Vec<SqlUpdate>,
     contracts: HashMap<ContractName, TxContractStore>,
     contract_states: Vec<TxContractStateStore>,
     deleted_contracts: HashSet<ContractName>,
     blob_proof_outputs: Vec<TxBlobProofOutputStore>,
+    contract_settlements: Vec<TxContractSettlementStore>,
 }
 
 impl std::fmt::Debug for IndexerHandlerStore {
@@ -120,6 +175,7 @@ impl std::fmt::Debug for IndexerHandlerStore {
             .field("contracts", &self.contracts.len())
             .field("contract_states", &self.contract_states.len())
             .field("blob_proof_outputs", &self.blob_proof_outputs.len())
+            .field("contract_settlements", &self.contract_settlements.len())
             .finish()
     }
 }
@@ -148,6 +204,11 @@ impl Indexer {
         Ok(())
     }
 
+    /// Number of blocks waiting to be dumped to the database
+    pub(crate) fn buffered_blocks(&self) -> usize {
+        self.handler_store.blocks.len()
+    }
+
     pub(crate) fn empty_store(&self) -> bool {
         self.handler_store.blocks.is_empty()
             && self.handler_store.block_txs.is_empty()
@@ -159,131 +220,170 @@ impl Indexer {
             && self.handler_store.contract_states.is_empty()
             && self.handler_store.deleted_contracts.is_empty()
             && self.handler_store.blob_proof_outputs.is_empty()
+            && self.handler_store.contract_settlements.is_empty()
     }
 
     pub(crate) async fn dump_store_to_db(&mut self) -> Result<()> {
-        if self.handler_store.blocks.is_empty() {
-            return Ok(());
-        }
-
-        let mut transaction = self.db.begin().await?;
+        self.dump_store(None).await.map(|_| ())
+    }
 
-        // Insert blocks into the database
-        if !self.handler_store.blocks.is_empty() {
-            let mut query_builder = QueryBuilder::<Postgres>::new(
-                "INSERT INTO blocks (hash, parent_hash, height, timestamp, total_txs) ",
-            );
-            _ = log_error!(
-                query_builder
-                    .push_values(self.handler_store.blocks.drain(..), |mut b, block| {
-                        let block_hash = block.hash.clone();
-                        let block_height = log_error!(
-                            i64::try_from(block.block_height.0).map_err(|_| anyhow::anyhow!(
-                                "Block height is too large to fit into an i64"
-                            )),
-                            "Converting block height into i64"
-                        )
-                        .unwrap_or_default();
-                        let total_txs = block.txs.len() as i64;
+    /// Deletes the indexed blocks from `from` to `to` and dumps the store in their place, in a
+    /// single transaction. Returns the number of deleted blocks.
+    pub(crate) async fn replace_blocks_in_db(
+        &mut self,
+        from: BlockHeight,
+        to: BlockHeight,
+    ) -> Result<u64> {
+        self.dump_store(Some((from, to))).await
+    }
 
-                        let block_timestamp = into_utc_date_time(&block.block_timestamp)
-                            .context("Block's timestamp is incorrect")
-                            .unwrap_or_else(|_| {
-                                // If the timestamp is incorrect, we can use the current time as a fallback.
-                                Utc::now()
-                            });
+    async fn dump_store(&mut self, replaced: Option<(BlockHeight, BlockHeight)>) -> Result<u64> {
+        if self.handler_store.blocks.is_empty() && replaced.is_none() {
+            return Ok(0);
+        }
 
-                        b.push_bind(block_hash)
-                            .push_bind(block.parent_hash.clone())
-                            .push_bind(block_height)
-                            .push_bind(block_timestamp)
-                            .push_bind(total_txs);
-                    })
-                    .build()
+        // Explorer subscribers are notified once the blocks can be queried
+        let dumped_blocks = self.handler_store.blocks.clone();
+
+        let db = self.db.clone();
+        let max_params = db.max_bind_params();
+        let json_cast = db.by_backend("::jsonb", "");
+        let mut deleted = 0;
+        with_db!(&db, |pool| {
+            let mut transaction = pool.begin().await?;
+
+            // Rows of the transactions of the replaced blocks are deleted in cascade
+            if let Some((from, to)) = replaced {
+                deleted = sqlx::query("DELETE FROM blocks WHERE height >= $1 AND height <= $2")
+                    .bind(from.0 as i64)
+                    .bind(to.0 as i64)
                     .execute(&mut *transaction)
-                    .await,
-                "Inserting blocks"
-            )?;
-        }
+                    .await
+                    .context("Deleting replaced blocks")?
+                    .rows_affected();
+            }
 
-        // Insert transactions into the database with batching
-        if !self.handler_store.block_txs.is_empty() {
-            const TRANSACTIONS_PARAMS: usize = 10; // tx_hash, parent_dp_hash, version, transaction_type, transaction_status, block_hash, block_height, index, lane_id, identity
-            let transactions_batch_size = calculate_optimal_batch_size(TRANSACTIONS_PARAMS);
-            let block_txs = std::mem::take(&mut self.handler_store.block_txs);
-            let block_txs_vec: Vec<_> = block_txs.into_iter().collect();
-            let chunks: Vec<_> = block_txs_vec.chunks(transactions_batch_size).collect();
+            // Insert blocks into the database
+            if !self.handler_store.blocks.is_empty() {
+                let mut query_builder = QueryBuilder::new(
+                    "INSERT INTO blocks (hash, parent_hash, height, timestamp, total_txs) ",
+                );
+                _ = log_error!(
+                    query_builder
+                        .push_values(self.handler_store.blocks.drain(..), |mut b, block| {
+                            let block_hash = block.hash.clone();
+                            let block_height = log_error!(
+                                i64::try_from(block.block_height.0).map_err(|_| anyhow::anyhow!(
+                                    "Block height is too large to fit into an i64"
+                                )),
+                                "Converting block height into i64"
+                            )
+                            .unwrap_or_default();
+                            let total_txs = block.txs.len() as i64;
+
+                            let block_timestamp = into_utc_date_time(&block.block_timestamp)
+                                .context("Block's timestamp is incorrect")
+                                .unwrap_or_else(|_| {
+                                    // If the timestamp is incorrect, we can use the current time as a fallback.
+                                    Utc::now()
+                                });
+
+                            b.push_bind(block_hash)
+                                .push_bind(block.parent_hash.clone())
+                                .push_bind(block_height)
+                                .push_bind(block_timestamp.naive_utc())
+                                .push_bind(total_txs);
+                        })
+                        .build()
+                        .execute(&mut *transaction)
+                        .await,
+                    "Inserting blocks"
+                )?;
+            }
 
-            info!(
-                "Inserting {} transactions in {} batches of up to {} items each (calculated from {} params per item)",
-                block_txs_vec.len(),
-                chunks.len(),
-                transactions_batch_size,
-                TRANSACTIONS_PARAMS
-            );
+            // Insert transactions into the database with batching
+            if !self.handler_store.block_txs.is_empty() {
+                const TRANSACTIONS_PARAMS: usize = 10; // tx_hash, parent_dp_hash, version, transaction_type, transaction_status, block_hash, block_height, index, lane_id, identity
+                let transactions_batch_size =
+                    calculate_optimal_batch_size(max_params, TRANSACTIONS_PARAMS);
+                let block_txs = std::mem::take(&mut self.handler_store.block_txs);
+                let block_txs_vec: Vec<_> = block_txs.into_iter().collect();
+                let chunks: Vec<_> = block_txs_vec.chunks(transactions_batch_size).collect();
 
-            for (batch_idx, chunk) in chunks.iter().enumerate() {
-                let mut query_builder = QueryBuilder::<Postgres>::new(
-                    "INSERT INTO transactions (tx_hash, parent_dp_hash, version, transaction_type, transaction_status, block_hash, block_height, index, lane_id, identity) ",
+                info!(
+                    "Inserting {} transactions in {} batches of up to {} items each (calculated from {} params per item)",
+                    block_txs_vec.len(),
+                    chunks.len(),
+                    transactions_batch_size,
+                    TRANSACTIONS_PARAMS
                 );
-                query_builder.push_values(chunk.iter(), |mut b, (tx_id, (index, block, tx))| {
-                    let version = log_error!(
-                        i32::try_from(tx.version).map_err(|_| anyhow::anyhow!(
-                            "Tx version is too large to fit into an i32"
-                        )),
-                        "Converting tx version into i32"
-                    )
-                    .unwrap_or_default();
 
-                    let block_height = log_error!(
-                        i64::try_from(block.block_height.0).map_err(|_| anyhow::anyhow!(
-                            "Block height is too large to fit into an i64"
-                        )),
-                        "Converting block height into i64"
-                    )
-                    .unwrap_or_default();
-
-                    let tx_type = TransactionTypeDb::from(tx);
-                    let tx_status = match tx.transaction_data {
-                        TransactionData::Blob(_) => TransactionStatusDb::Sequenced,
-                        TransactionData::Proof(_) => TransactionStatusDb::Success,
-                        TransactionData::VerifiedProof(_) => TransactionStatusDb::Success,
-                    };
-
-                    let lane_id: LaneIdDb = log_error!(
-                        block
-                            .lane_ids
-                            .get(&tx_id.1)
-                            .context(format!("No lane id present for tx {tx_id}")),
-                        "Getting lane id for tx"
-                    )
-                    .unwrap_or(&LaneId::default())
-                    .clone()
-                    .into();
-
-                    let identity = match tx.transaction_data {
-                        TransactionData::Blob(ref tx) => Some(tx.identity.0.clone()),
-                        _ => None,
-                    };
-
-                    let parent_data_proposal_hash: DataProposalHashDb = tx_id.0.clone().into();
-                    let tx_hash: TxHashDb = tx_id.1.clone().into();
-
-                    b.push_bind(tx_hash)
-                        .push_bind(parent_data_proposal_hash)
-                        .push_bind(version)
-                        .push_bind(tx_type)
-                        .push_bind(tx_status)
-                        .push_bind(block.hash.clone())
-                        .push_bind(block_height)
-                        .push_bind(index)
-                        .push_bind(lane_id)
-                        .push_bind(identity);
-                });
-                query_builder.push(" ON CONFLICT(tx_hash, parent_dp_hash) DO UPDATE SET ");
-                query_builder.push("block_hash=EXCLUDED.block_hash, block_height=EXCLUDED.block_height, index=EXCLUDED.index, lane_id=EXCLUDED.lane_id, identity=EXCLUDED.identity,");
-                // This data comes from post-consensus so always erase earlier statuses, but not later ones.
-                query_builder.push(
+                for (batch_idx, chunk) in chunks.iter().enumerate() {
+                    let mut query_builder = QueryBuilder::new(
+                    "INSERT INTO transactions (tx_hash, parent_dp_hash, version, transaction_type, transaction_status, block_hash, block_height, \"index\", lane_id, identity) ",
+                );
+                    query_builder.push_values(
+                        chunk.iter(),
+                        |mut b, (tx_id, (index, block, tx))| {
+                            let version = log_error!(
+                                i32::try_from(tx.version).map_err(|_| anyhow::anyhow!(
+                                    "Tx version is too large to fit into an i32"
+                                )),
+                                "Converting tx version into i32"
+                            )
+                            .unwrap_or_default();
+
+                            let block_height = log_error!(
+                                i64::try_from(block.block_height.0).map_err(|_| anyhow::anyhow!(
+                                    "Block height is too large to fit into an i64"
+                                )),
+                                "Converting block height into i64"
+                            )
+                            .unwrap_or_default();
+
+                            let tx_type = TransactionTypeDb::from(tx);
+                            let tx_status = match tx.transaction_data {
+                                TransactionData::Blob(_) => TransactionStatusDb::Sequenced,
+                                TransactionData::Proof(_) => TransactionStatusDb::Success,
+                                TransactionData::VerifiedProof(_) => TransactionStatusDb::Success,
+                            };
+
+                            let lane_id: LaneIdDb = log_error!(
+                                block
+                                    .lane_ids
+                                    .get(&tx_id.1)
+                                    .context(format!("No lane id present for tx {tx_id}")),
+                                "Getting lane id for tx"
+                            )
+                            .unwrap_or(&LaneId::default())
+                            .clone()
+                            .into();
+
+                            let identity = match tx.transaction_data {
+                                TransactionData::Blob(ref tx) => Some(tx.identity.0.clone()),
+                                _ => None,
+                            };
+
+                            let parent_data_proposal_hash: DataProposalHashDb =
+                                tx_id.0.clone().into();
+                            let tx_hash: TxHashDb = tx_id.1.clone().into();
+
+                            b.push_bind(tx_hash)
+                                .push_bind(parent_data_proposal_hash)
+                                .push_bind(version)
+                                .push_bind(tx_type)
+                                .push_bind(tx_status)
+                                .push_bind(block.hash.clone())
+                                .push_bind(block_height)
+                                .push_bind(index)
+                                .push_bind(lane_id)
+                                .push_bind(identity);
+                        },
+                    );
+                    query_builder.push(" ON CONFLICT(tx_hash, parent_dp_hash) DO UPDATE SET ");
+                    query_builder.push("block_hash=EXCLUDED.block_hash, block_height=EXCLUDED.block_height, \"index\"=EXCLUDED.\"index\", lane_id=EXCLUDED.lane_id, identity=EXCLUDED.identity,");
+                    // This data comes from post-consensus so always erase earlier statuses, but not later ones.
+                    query_builder.push(
                     "transaction_status = case
                         when transactions.transaction_status IS NULL THEN EXCLUDED.transaction_status
                         when transactions.transaction_status = 'waiting_dissemination' THEN EXCLUDED.transaction_status
@@ -292,466 +392,593 @@ impl Indexer {
                     end",
                 );
 
-                _ = log_error!(
-                    query_builder
-                        .build()
-                        .execute(&mut *transaction)
-                        .await
-                        .with_context(|| {
-                            format!(
-                                "Inserting transactions batch {} of {}",
-                                batch_idx + 1,
-                                chunks.len()
-                            )
-                        }),
-                    "Inserting transactions"
-                )?;
+                    _ = log_error!(
+                        query_builder
+                            .build()
+                            .execute(&mut *transaction)
+                            .await
+                            .with_context(|| {
+                                format!(
+                                    "Inserting transactions batch {} of {}",
+                                    batch_idx + 1,
+                                    chunks.len()
+                                )
+                            }),
+                        "Inserting transactions"
+                    )?;
+                }
             }
-        }
 
-        // Insert blobs into the database with batching
-        if !self.handler_store.tx_data.is_empty() {
-            const TX_DATA_PARAMS: usize = 7; // tx_hash, parent_dp_hash, blob_index, identity, contract_name, data, verified
-            let blob_batch_size = calculate_optimal_batch_size(TX_DATA_PARAMS);
+            // Insert blobs into the database with batching
+            if !self.handler_store.tx_data.is_empty() {
+                const TX_DATA_PARAMS: usize = 7; // tx_hash, parent_dp_hash, blob_index, identity, contract_name, data, verified
+                let blob_batch_size = calculate_optimal_batch_size(max_params, TX_DATA_PARAMS);
 
-            let tx_data = std::mem::take(&mut self.handler_store.tx_data);
-            let chunks: Vec<_> = tx_data.chunks(blob_batch_size).collect();
+                let tx_data = std::mem::take(&mut self.handler_store.tx_data);
+                let chunks: Vec<_> = tx_data.chunks(blob_batch_size).collect();
 
-            info!(
-                "Inserting {} blobs in {} batches of up to {} items each (calculated from {} params per item)",
-                tx_data.len(),
-                chunks.len(),
-                blob_batch_size,
-                TX_DATA_PARAMS
-            );
+                info!(
+                    "Inserting {} blobs in {} batches of up to {} items each (calculated from {} params per item)",
+                    tx_data.len(),
+                    chunks.len(),
+                    blob_batch_size,
+                    TX_DATA_PARAMS
+                );
 
-            for (batch_idx, chunk) in chunks.iter().enumerate() {
-                let mut query_builder = QueryBuilder::<Postgres>::new(
+                for (batch_idx, chunk) in chunks.iter().enumerate() {
+                    let mut query_builder = QueryBuilder::new(
                     "INSERT INTO blobs (tx_hash, parent_dp_hash, blob_index, identity, contract_name, data, verified) ",
                 );
 
-                query_builder.push_values(chunk.iter(), |mut b, s| {
-                    let TxDataStore {
-                        tx_hash,
-                        parent_data_proposal_hash,
-                        blob_index,
-                        identity,
-                        contract_name,
-                        blob_data,
-                        verified,
-                    } = s;
-
-                    b.push_bind(tx_hash)
-                        .push_bind(parent_data_proposal_hash)
-                        .push_bind(blob_index)
-                        .push_bind(identity)
-                        .push_bind(contract_name)
-                        .push_bind(blob_data)
-                        .push_bind(verified);
-                });
+                    query_builder.push_values(chunk.iter(), |mut b, s| {
+                        let TxDataStore {
+                            tx_hash,
+                            parent_data_proposal_hash,
+                            blob_index,
+                            identity,
+                            contract_name,
+                            blob_data,
+                            verified,
+                        } = s;
+
+                        b.push_bind(tx_hash)
+                            .push_bind(parent_data_proposal_hash)
+                            .push_bind(blob_index)
+                            .push_bind(identity)
+                            .push_bind(contract_name)
+                            .push_bind(blob_data)
+                            .push_bind(verified);
+                    });
 
-                query_builder.push(" ON CONFLICT DO NOTHING");
+                    query_builder.push(" ON CONFLICT DO NOTHING");
+
+                    log_error!(
+                        query_builder
+                            .build()
+                            .execute(&mut *transaction)
+                            .await
+                            .with_context(|| {
+                                format!(
+                                    "Inserting blobs batch {} of {}",
+                                    batch_idx + 1,
+                                    chunks.len()
+                                )
+                            }),
+                        "Inserting blobs"
+                    )?;
+                }
 
-                log_error!(
-                    query_builder
-                        .build()
-                        .execute(&mut *transaction)
-                        .await
-                        .with_context(|| {
-                            format!(
-                                "Inserting blobs batch {} of {}",
-                                batch_idx + 1,
-                                chunks.len()
-                            )
-                        }),
-                    "Inserting blobs"
-                )?;
-            }
+                // Insert txs_contracts with batching
+                const TX_CONTRACTS_PARAMS: usize = 3; // tx_hash, parent_dp_hash, contract_name
+                let tx_contracts_batch_size =
+                    calculate_optimal_batch_size(max_params, TX_CONTRACTS_PARAMS);
+                let tx_contracts_chunks: Vec<_> = tx_data.chunks(tx_contracts_batch_size).collect();
 
-            // Insert txs_contracts with batching
-            const TX_CONTRACTS_PARAMS: usize = 3; // tx_hash, parent_dp_hash, contract_name
-            let tx_contracts_batch_size = calculate_optimal_batch_size(TX_CONTRACTS_PARAMS);
-            let tx_contracts_chunks: Vec<_> = tx_data.chunks(tx_contracts_batch_size).collect();
+                for (batch_idx, chunk) in tx_contracts_chunks.iter().enumerate() {
+                    let mut query_builder = QueryBuilder::new(
+                        "INSERT INTO txs_contracts (tx_hash, parent_dp_hash, contract_name) ",
+                    );
 
-            for (batch_idx, chunk) in tx_contracts_chunks.iter().enumerate() {
-                let mut query_builder = QueryBuilder::<Postgres>::new(
-                    "INSERT INTO txs_contracts (tx_hash, parent_dp_hash, contract_name) ",
-                );
+                    query_builder.push_values(chunk.iter(), |mut b, s| {
+                        let TxDataStore {
+                            tx_hash,
+                            parent_data_proposal_hash,
+                            contract_name,
+                            ..
+                        } = s;
 
-                query_builder.push_values(chunk.iter(), |mut b, s| {
-                    let TxDataStore {
-                        tx_hash,
-                        parent_data_proposal_hash,
-                        contract_name,
-                        ..
-                    } = s;
-
-                    b.push_bind(tx_hash)
-                        .push_bind(parent_data_proposal_hash)
-                        .push_bind(contract_name);
-                });
+                        b.push_bind(tx_hash)
+                            .push_bind(parent_data_proposal_hash)
+                            .push_bind(contract_name);
+                    });
 
-                query_builder.push(" ON CONFLICT DO NOTHING");
-                _ = log_error!(
-                    query_builder
-                        .build()
-                        .execute(&mut *transaction)
-                        .await
-                        .with_context(|| {
-                            format!(
-                                "Inserting txs_contracts batch {} of {}",
-                                batch_idx + 1,
-                                tx_contracts_chunks.len()
-                            )
-                        }),
-                    "Inserting txs_contracts"
-                )?;
+                    query_builder.push(" ON CONFLICT DO NOTHING");
+                    _ = log_error!(
+                        query_builder
+                            .build()
+                            .execute(&mut *transaction)
+                            .await
+                            .with_context(|| {
+                                format!(
+                                    "Inserting txs_contracts batch {} of {}",
+                                    batch_idx + 1,
+                                    tx_contracts_chunks.len()
+                                )
+                            }),
+                        "Inserting txs_contracts"
+                    )?;
+                }
             }
-        }
 
-        // Insert proofs into the database with batching
-        if !self.handler_store.tx_data_proofs.is_empty() {
-            const PROOFS_PARAMS: usize = 3; // parent_dp_hash, tx_hash, proof
-            let proofs_batch_size = calculate_optimal_batch_size(PROOFS_PARAMS);
-            let tx_data_proofs = std::mem::take(&mut self.handler_store.tx_data_proofs);
-            let chunks: Vec<_> = tx_data_proofs.chunks(proofs_batch_size).collect();
+            // Insert the settlement analytics rows of the sequenced blob transactions with batching
+            if !self.handler_store.contract_settlements.is_empty() {
+                const CONTRACT_SETTLEMENTS_PARAMS: usize = 6; // contract_name, tx_hash, parent_dp_hash, sequenced_at, blob_bytes, transaction_status
+                let contract_settlements_batch_size =
+                    calculate_optimal_batch_size(max_params, CONTRACT_SETTLEMENTS_PARAMS);
+                let contract_settlements =
+                    std::mem::take(&mut self.handler_store.contract_settlements);
+                let chunks: Vec<_> = contract_settlements
+                    .chunks(contract_settlements_batch_size)
+                    .collect();
 
-            info!(
-                "Inserting {} proofs in {} batches of up to {} items each (calculated from {} params per item)",
-                tx_data_proofs.len(),
-                chunks.len(),
-                proofs_batch_size,
-                PROOFS_PARAMS
-            );
+                for (batch_idx, chunk) in chunks.iter().enumerate() {
+                    let mut query_builder = QueryBuilder::new(
+                        "INSERT INTO contract_settlements (contract_name, tx_hash, parent_dp_hash, sequenced_at, blob_bytes, transaction_status) ",
+                    );
+
+                    query_builder.push_values(chunk.iter(), |mut b, s| {
+                        let TxContractSettlementStore {
+                            contract_name,
+                            tx_hash,
+                            parent_data_proposal_hash,
+                            sequenced_at,
+                            blob_bytes,
+                        } = s;
+
+                        b.push_bind(contract_name)
+                            .push_bind(tx_hash)
+                            .push_bind(parent_data_proposal_hash)
+                            .push_bind(sequenced_at)
+                            .push_bind(blob_bytes)
+                            .push_bind(TransactionStatusDb::Sequenced);
+                    });
+
+                    // Replaced blocks sequence their transactions again
+                    query_builder.push(" ON CONFLICT(contract_name, parent_dp_hash, tx_hash) DO UPDATE SET sequenced_at = EXCLUDED.sequenced_at, blob_bytes = EXCLUDED.blob_bytes");
+                    _ = log_error!(
+                        query_builder
+                            .build()
+                            .execute(&mut *transaction)
+                            .await
+                            .with_context(|| {
+                                format!(
+                                    "Inserting contract settlements batch {} of {}",
+                                    batch_idx + 1,
+                                    chunks.len()
+                                )
+                            }),
+                        "Inserting contract settlements"
+                    )?;
+                }
+            }
+
+            // Insert proofs into the database with batching
+            if !self.handler_store.tx_data_proofs.is_empty() {
+                const PROOFS_PARAMS: usize = 3; // parent_dp_hash, tx_hash, proof
+                let proofs_batch_size = calculate_optimal_batch_size(max_params, PROOFS_PARAMS);
+                let tx_data_proofs = std::mem::take(&mut self.handler_store.tx_data_proofs);
+                let chunks: Vec<_> = tx_data_proofs.chunks(proofs_batch_size).collect();
 
-            for (batch_idx, chunk) in chunks.iter().enumerate() {
-                let mut query_builder = QueryBuilder::<Postgres>::new(
-                    "INSERT INTO proofs (parent_dp_hash, tx_hash, proof) ",
+                info!(
+                    "Inserting {} proofs in {} batches of up to {} items each (calculated from {} params per item)",
+                    tx_data_proofs.len(),
+                    chunks.len(),
+                    proofs_batch_size,
+                    PROOFS_PARAMS
                 );
 
-                query_builder.push_values(chunk.iter(), |mut b, s| {
-                    let TxProofStore {
-                        tx_hash,
-                        parent_data_proposal_hash,
-                        proof,
-                    } = s;
+                for (batch_idx, chunk) in chunks.iter().enumerate() {
+                    let mut query_builder =
+                        QueryBuilder::new("INSERT INTO proofs (parent_dp_hash, tx_hash, proof) ");
 
-                    b.push_bind(parent_data_proposal_hash)
-                        .push_bind(tx_hash)
-                        .push_bind(proof);
-                });
+                    query_builder.push_values(chunk.iter(), |mut b, s| {
+                        let TxProofStore {
+                            tx_hash,
+                            parent_data_proposal_hash,
+                            proof,
+                        } = s;
 
-                query_builder.push(" ON CONFLICT(parent_dp_hash, tx_hash) DO NOTHING");
+                        b.push_bind(parent_data_proposal_hash)
+                            .push_bind(tx_hash)
+                            .push_bind(proof);
+                    });
 
-                _ = log_error!(
-                    query_builder
-                        .build()
-                        .execute(&mut *transaction)
-                        .await
-                        .with_context(|| {
-                            format!(
-                                "Inserting proofs batch {} of {}",
-                                batch_idx + 1,
-                                chunks.len()
-                            )
-                        }),
-                    "Inserting proofs"
-                )?;
+                    query_builder.push(" ON CONFLICT(parent_dp_hash, tx_hash) DO NOTHING");
+
+                    _ = log_error!(
+                        query_builder
+                            .build()
+                            .execute(&mut *transaction)
+                            .await
+                            .with_context(|| {
+                                format!(
+                                    "Inserting proofs batch {} of {}",
+                                    batch_idx + 1,
+                                    chunks.len()
+                                )
+                            }),
+                        "Inserting proofs"
+                    )?;
+                }
             }
-        }
 
-        // Insert transaction events into the database with batching
-        if !self.handler_store.transactions_events.is_empty() {
-            const TX_EVENTS_PARAMS: usize = 6; // block_hash, block_height, index, tx_hash, parent_dp_hash, events
-            let tx_events_batch_size = calculate_optimal_batch_size(TX_EVENTS_PARAMS);
-            let transactions_events = std::mem::take(&mut self.handler_store.transactions_events);
-            let chunks: Vec<_> = transactions_events.chunks(tx_events_batch_size).collect();
+            // Insert transaction events into the database with batching
+            if !self.handler_store.transactions_events.is_empty() {
+                const TX_EVENTS_PARAMS: usize = 6; // block_hash, block_height, index, tx_hash, parent_dp_hash, events
+                let tx_events_batch_size =
+                    calculate_optimal_batch_size(max_params, TX_EVENTS_PARAMS);
+                let transactions_events =
+                    std::mem::take(&mut self.handler_store.transactions_events);
+                let chunks: Vec<_> = transactions_events.chunks(tx_events_batch_size).collect();
 
-            info!(
-                "Inserting {} transaction events in {} batches of up to {} items each (calculated from {} params per item)",
-                transactions_events.len(),
-                chunks.len(),
-                tx_events_batch_size,
-                TX_EVENTS_PARAMS
-            );
+                info!(
+                    "Inserting {} transaction events in {} batches of up to {} items each (calculated from {} params per item)",
+                    transactions_events.len(),
+                    chunks.len(),
+                    tx_events_batch_size,
+                    TX_EVENTS_PARAMS
+                );
 
-            for (batch_idx, chunk) in chunks.iter().enumerate() {
-                let mut query_builder = QueryBuilder::<Postgres>::new(
-                    "INSERT INTO transaction_state_events (block_hash, block_height, index, tx_hash, parent_dp_hash, events) ",
+                for (batch_idx, chunk) in chunks.iter().enumerate() {
+                    let mut query_builder = QueryBuilder::new(
+                    "INSERT INTO transaction_state_events (block_hash, block_height, \"index\", tx_hash, parent_dp_hash, events) ",
                 );
-                query_builder.push_values(chunk.iter(), |mut b, s| {
-                    let TxEventStore {
-                        block_hash,
-                        block_height,
-                        index,
-                        tx_hash,
-                        parent_data_proposal_hash,
-                        events,
-                    } = s;
-
-                    b.push_bind(block_hash)
-                        .push_bind(block_height)
-                        .push_bind(index)
-                        .push_bind(tx_hash)
-                        .push_bind(parent_data_proposal_hash)
-                        .push_bind(events)
-                        .push_unseparated("::jsonb");
-                });
+                    query_builder.push_values(chunk.iter(), |mut b, s| {
+                        let TxEventStore {
+                            block_hash,
+                            block_height,
+                            index,
+                            tx_hash,
+                            parent_data_proposal_hash,
+                            events,
+                        } = s;
 
-                _ = log_error!(
-                    query_builder
-                        .build()
-                        .execute(&mut *transaction)
-                        .await
-                        .with_context(|| {
-                            format!(
-                                "Inserting transaction events batch {} of {}",
-                                batch_idx + 1,
-                                chunks.len()
-                            )
-                        }),
-                    "Inserting transaction events"
-                )?;
+                        b.push_bind(block_hash)
+                            .push_bind(block_height)
+                            .push_bind(index)
+                            .push_bind(tx_hash)
+                            .push_bind(parent_data_proposal_hash)
+                            .push_bind(events)
+                            .push_unseparated(json_cast);
+                    });
+
+                    _ = log_error!(
+                        query_builder
+                            .build()
+                            .execute(&mut *transaction)
+                            .await
+                            .with_context(|| {
+                                format!(
+                                    "Inserting transaction events batch {} of {}",
+                                    batch_idx + 1,
+                                    chunks.len()
+                                )
+                            }),
+                        "Inserting transaction events"
+                    )?;
+                }
             }
-        }
 
-        // Insert contracts into the database with batching
-        if !self.handler_store.contracts.is_empty() {
-            const CONTRACTS_PARAMS: usize = 6; // tx_hash, parent_dp_hash, verifier, program_id, state_commitment, contract_name
-            let contracts_batch_size = calculate_optimal_batch_size(CONTRACTS_PARAMS);
-            let contracts = std::mem::take(&mut self.handler_store.contracts);
-            let contracts_vec: Vec<_> = contracts.into_values().collect();
-            let chunks: Vec<_> = contracts_vec.chunks(contracts_batch_size).collect();
+            // Insert contracts into the database with batching
+            if !self.handler_store.contracts.is_empty() {
+                const CONTRACTS_PARAMS: usize = 8; // tx_hash, parent_dp_hash, verifier, program_id, timeout_window, state_commitment, contract_name, contract_schema
+                let contracts_batch_size =
+                    calculate_optimal_batch_size(max_params, CONTRACTS_PARAMS);
+                let contracts = std::mem::take(&mut self.handler_store.contracts);
+                let contracts_vec: Vec<_> = contracts.into_values().collect();
+                let chunks: Vec<_> = contracts_vec.chunks(contracts_batch_size).collect();
 
-            info!(
-                "Inserting {} contracts in {} batches of up to {} items each (calculated from {} params per item)",
-                contracts_vec.len(),
-                chunks.len(),
-                contracts_batch_size,
-                CONTRACTS_PARAMS
-            );
-
-            for (batch_idx, chunk) in chunks.iter().enumerate() {
-                let mut query_builder = QueryBuilder::<Postgres>::new(
-                    "INSERT INTO contracts (tx_hash, parent_dp_hash, verifier, program_id, timeout_window, state_commitment, contract_name) ",
+                info!(
+                    "Inserting {} contracts in {} batches of up to {} items each (calculated from {} params per item)",
+                    contracts_vec.len(),
+                    chunks.len(),
+                    contracts_batch_size,
+                    CONTRACTS_PARAMS
                 );
 
-                query_builder.push_values(chunk.iter(), |mut b, s| {
-                    let TxContractStore {
-                        tx_hash,
-                        parent_data_proposal_hash,
-                        verifier,
-                        program_id,
-                        timeout_window,
-                        state_commitment,
-                        contract_name,
-                    } = s;
+                for (batch_idx, chunk) in chunks.iter().enumerate() {
+                    let mut query_builder = QueryBuilder::new(
+                    "INSERT INTO contracts (tx_hash, parent_dp_hash, verifier, program_id, timeout_window, state_commitment, contract_name, contract_schema) ",
+                );
 
-                    info!(
-                        "Inserting contract {} with tx hash {} and parent data proposal hash {}",
-                        contract_name, tx_hash.0, parent_data_proposal_hash.0
-                    );
+                    query_builder.push_values(chunk.iter(), |mut b, s| {
+                        let TxContractStore {
+                            tx_hash,
+                            parent_data_proposal_hash,
+                            verifier,
+                            program_id,
+                            timeout_window,
+                            state_commitment,
+                            contract_name,
+                            contract_schema,
+                        } = s;
 
-                    b.push_bind(tx_hash)
-                        .push_bind(parent_data_proposal_hash)
-                        .push_bind(verifier)
-                        .push_bind(program_id)
-                        .push_bind(timeout_window)
-                        .push_bind(state_commitment)
-                        .push_bind(contract_name);
-                });
+                        info!(
+                            "Inserting contract {} with tx hash {} and parent data proposal hash {}",
+                            contract_name, tx_hash.0, parent_data_proposal_hash.0
+                        );
 
-                query_builder.push(" ON CONFLICT (contract_name) DO UPDATE SET ");
-                query_builder.push("tx_hash = EXCLUDED.tx_hash, ");
-                query_builder.push("parent_dp_hash = EXCLUDED.parent_dp_hash, ");
-                query_builder.push("verifier = EXCLUDED.verifier, ");
-                query_builder.push("program_id = EXCLUDED.program_id, ");
-                query_builder.push("timeout_window = EXCLUDED.timeout_window, ");
-                query_builder.push("state_commitment = EXCLUDED.state_commitment ");
+                        b.push_bind(tx_hash)
+                            .push_bind(parent_data_proposal_hash)
+                            .push_bind(verifier)
+                            .push_bind(program_id)
+                            .push_bind(timeout_window)
+                            .push_bind(state_commitment)
+                            .push_bind(contract_name)
+                            .push_bind(contract_schema)
+                            .push_unseparated(json_cast);
+                    });
 
-                _ = log_error!(
-                    query_builder
-                        .build()
-                        .execute(&mut *transaction)
-                        .await
-                        .with_context(|| {
-                            format!(
-                                "Inserting contracts batch {} of {}",
-                                batch_idx + 1,
-                                chunks.len()
-                            )
-                        }),
-                    "Inserting contracts"
-                )?;
+                    query_builder.push(" ON CONFLICT (contract_name) DO UPDATE SET ");
+                    query_builder.push("tx_hash = EXCLUDED.tx_hash, ");
+                    query_builder.push("parent_dp_hash = EXCLUDED.parent_dp_hash, ");
+                    query_builder.push("verifier = EXCLUDED.verifier, ");
+                    query_builder.push("program_id = EXCLUDED.program_id, ");
+                    query_builder.push("timeout_window = EXCLUDED.timeout_window, ");
+                    query_builder.push("state_commitment = EXCLUDED.state_commitment, ");
+                    query_builder.push("contract_schema = EXCLUDED.contract_schema ");
+
+                    _ = log_error!(
+                        query_builder
+                            .build()
+                            .execute(&mut *transaction)
+                            .await
+                            .with_context(|| {
+                                format!(
+                                    "Inserting contracts batch {} of {}",
+                                    batch_idx + 1,
+                                    chunks.len()
+                                )
+                            }),
+                        "Inserting contracts"
+                    )?;
+                }
             }
-        }
 
-        // Insert contract states into the database with batching
-        if !self.handler_store.contract_states.is_empty() {
-            const CONTRACT_STATES_PARAMS: usize = 3; // contract_name, block_hash, state_commitment
-            let contract_states_batch_size = calculate_optimal_batch_size(CONTRACT_STATES_PARAMS);
-            let contract_states = std::mem::take(&mut self.handler_store.contract_states);
-            let chunks: Vec<_> = contract_states.chunks(contract_states_batch_size).collect();
+            // Insert contract states into the database with batching
+            if !self.handler_store.contract_states.is_empty() {
+                const CONTRACT_STATES_PARAMS: usize = 6; // contract_name, block_hash, state_commitment, registered_state_commitment, tx_hash, program_id
+                let contract_states_batch_size =
+                    calculate_optimal_batch_size(max_params, CONTRACT_STATES_PARAMS);
+                let contract_states = std::mem::take(&mut self.handler_store.contract_states);
+                let chunks: Vec<_> = contract_states.chunks(contract_states_batch_size).collect();
 
-            info!(
-                "Inserting {} contract states in {} batches of up to {} items each (calculated from {} params per item)",
-                contract_states.len(),
-                chunks.len(),
-                contract_states_batch_size,
-                CONTRACT_STATES_PARAMS
-            );
-
-            for (batch_idx, chunk) in chunks.iter().enumerate() {
-                let mut query_builder = QueryBuilder::<Postgres>::new(
-                    "INSERT INTO contract_state (contract_name, block_hash, state_commitment) ",
+                info!(
+                    "Inserting {} contract states in {} batches of up to {} items each (calculated from {} params per item)",
+                    contract_states.len(),
+                    chunks.len(),
+                    contract_states_batch_size,
+                    CONTRACT_STATES_PARAMS
                 );
 
-                query_builder.push_values(chunk.iter(), |mut b, s| {
-                    let TxContractStateStore {
-                        contract_name,
-                        block_hash,
-                        state_commitment,
-                    } = s;
+                for (batch_idx, chunk) in chunks.iter().enumerate() {
+                    let mut query_builder = QueryBuilder::new(
+                        "INSERT INTO contract_state (contract_name, block_hash, state_commitment, registered_state_commitment, tx_hash, program_id) ",
+                    );
 
-                    b.push_bind(contract_name)
-                        .push_bind(block_hash)
-                        .push_bind(state_commitment);
-                });
+                    query_builder.push_values(chunk.iter(), |mut b, s| {
+                        let TxContractStateStore {
+                            contract_name,
+                            block_hash,
+                            state_commitment,
+                            tx_hash,
+                            program_id,
+                        } = s;
+
+                        // Both start at the registered state, only state_commitment follows the settled transactions
+                        b.push_bind(contract_name)
+                            .push_bind(block_hash)
+                            .push_bind(state_commitment)
+                            .push_bind(state_commitment)
+                            .push_bind(tx_hash)
+                            .push_bind(program_id);
+                    });
 
-                _ = log_error!(
-                    query_builder
-                        .build()
-                        .execute(&mut *transaction)
-                        .await
-                        .with_context(|| {
-                            format!(
-                                "Inserting contract states batch {} of {}",
-                                batch_idx + 1,
-                                chunks.len()
-                            )
-                        }),
-                    "Inserting contract states"
-                )?;
+                    _ = log_error!(
+                        query_builder
+                            .build()
+                            .execute(&mut *transaction)
+                            .await
+                            .with_context(|| {
+                                format!(
+                                    "Inserting contract states batch {} of {}",
+                                    batch_idx + 1,
+                                    chunks.len()
+                                )
+                            }),
+                        "Inserting contract states"
+                    )?;
+                }
             }
-        }
 
-        // Then delete contracts that were deleted (slightly inefficient but we don't expect many deletions)
-        for contract_name in self.handler_store.deleted_contracts.drain() {
-            sqlx::query("DELETE FROM contracts WHERE contract_name = $1")
-                .bind(contract_name.0)
-                .execute(&mut *transaction)
-                .await
-                .context("Deleting contracts")?;
-        }
+            // Then delete contracts that were deleted (slightly inefficient but we don't expect many deletions)
+            for contract_name in self.handler_store.deleted_contracts.drain() {
+                sqlx::query("DELETE FROM contracts WHERE contract_name = $1")
+                    .bind(contract_name.0)
+                    .execute(&mut *transaction)
+                    .await
+                    .context("Deleting contracts")?;
+            }
 
-        // Insert blob proof outputs into the database with batching
-        if !self.handler_store.blob_proof_outputs.is_empty() {
-            const BLOB_PROOF_OUTPUT_PARAMS: usize = 9; // proof_tx_hash, proof_parent_dp_hash, blob_tx_hash, blob_parent_dp_hash, blob_index, blob_proof_output_index, contract_name, hyle_output, settled
-            let blob_proof_outputs_batch_size =
-                calculate_optimal_batch_size(BLOB_PROOF_OUTPUT_PARAMS);
+            // Insert blob proof outputs into the database with batching
+            if !self.handler_store.blob_proof_outputs.is_empty() {
+                const BLOB_PROOF_OUTPUT_PARAMS: usize = 11; // proof_tx_hash, proof_parent_dp_hash, blob_tx_hash, blob_parent_dp_hash, blob_index, blob_proof_output_index, contract_name, hyle_output, settled, program_id, proved_at
+                let blob_proof_outputs_batch_size =
+                    calculate_optimal_batch_size(max_params, BLOB_PROOF_OUTPUT_PARAMS);
 
-            let blob_proof_outputs = std::mem::take(&mut self.handler_store.blob_proof_outputs);
-            let chunks: Vec<_> = blob_proof_outputs
-                .chunks(blob_proof_outputs_batch_size)
-                .collect();
+                let blob_proof_outputs = std::mem::take(&mut self.handler_store.blob_proof_outputs);
+                let chunks: Vec<_> = blob_proof_outputs
+                    .chunks(blob_proof_outputs_batch_size)
+                    .collect();
 
-            info!(
-                "Inserting {} blob proof outputs in {} batches of up to {} items each (calculated from {} params per item)",
-                blob_proof_outputs.len(),
-                chunks.len(),
-                blob_proof_outputs_batch_size,
-                BLOB_PROOF_OUTPUT_PARAMS
-            );
+                info!(
+                    "Inserting {} blob proof outputs in {} batches of up to {} items each (calculated from {} params per item)",
+                    blob_proof_outputs.len(),
+                    chunks.len(),
+                    blob_proof_outputs_batch_size,
+                    BLOB_PROOF_OUTPUT_PARAMS
+                );
 
-            for (batch_idx, chunk) in chunks.iter().enumerate() {
-                let mut query_builder = QueryBuilder::<Postgres>::new(
-                    "INSERT INTO blob_proof_outputs (proof_tx_hash, proof_parent_dp_hash, blob_tx_hash, blob_parent_dp_hash, blob_index, blob_proof_output_index, contract_name, hyle_output, settled) ",
+                for (batch_idx, chunk) in chunks.iter().enumerate() {
+                    let mut query_builder = QueryBuilder::new(
+                    "INSERT INTO blob_proof_outputs (proof_tx_hash, proof_parent_dp_hash, blob_tx_hash, blob_parent_dp_hash, blob_index, blob_proof_output_index, contract_name, hyle_output, settled, program_id, proved_at) ",
                 );
 
-                query_builder.push_values(chunk.iter(), |mut b, s| {
-                    let TxBlobProofOutputStore {
-                        proof_tx_hash,
-                        proof_parent_dp_hash,
-                        blob_tx_hash,
-                        blob_parent_dp_hash,
-                        blob_index,
-                        blob_proof_output_index,
-                        contract_name,
-                        hyle_output,
-                        settled,
-                    } = s;
-
-                    b.push_bind(proof_tx_hash)
-                        .push_bind(proof_parent_dp_hash)
-                        .push_bind(blob_tx_hash)
-                        .push_bind(blob_parent_dp_hash)
-                        .push_bind(blob_index)
-                        .push_bind(blob_proof_output_index)
-                        .push_bind(contract_name)
-                        .push_bind(hyle_output)
-                        .push_unseparated("::jsonb")
-                        .push_bind(settled);
-                });
+                    query_builder.push_values(chunk.iter(), |mut b, s| {
+                        let TxBlobProofOutputStore {
+                            proof_tx_hash,
+                            proof_parent_dp_hash,
+                            blob_tx_hash,
+                            blob_parent_dp_hash,
+                            blob_index,
+                            blob_proof_output_index,
+                            contract_name,
+                            hyle_output,
+                            settled,
+                            program_id,
+                            proved_at,
+                        } = s;
+
+                        b.push_bind(proof_tx_hash)
+                            .push_bind(proof_parent_dp_hash)
+                            .push_bind(blob_tx_hash)
+                            .push_bind(blob_parent_dp_hash)
+                            .push_bind(blob_index)
+                            .push_bind(blob_proof_output_index)
+                            .push_bind(contract_name)
+                            .push_bind(hyle_output)
+                            .push_unseparated(json_cast)
+                            .push_bind(settled)
+                            .push_bind(program_id)
+                            .push_bind(proved_at);
+                    });
 
-                _ = log_error!(
-                    query_builder
-                        .build()
-                        .execute(&mut *transaction)
-                        .await
-                        .with_context(|| {
-                            format!(
-                                "Inserting blob proof outputs batch {} of {}",
-                                batch_idx + 1,
-                                chunks.len()
-                            )
-                        }),
-                    "Inserting blob proof outputs"
-                )?;
+                    _ = log_error!(
+                        query_builder
+                            .build()
+                            .execute(&mut *transaction)
+                            .await
+                            .with_context(|| {
+                                format!(
+                                    "Inserting blob proof outputs batch {} of {}",
+                                    batch_idx + 1,
+                                    chunks.len()
+                                )
+                            }),
+                        "Inserting blob proof outputs"
+                    )?;
+                }
             }
-        }
 
-        if !self.handler_store.sql_updates.is_empty() {
             for sql_update in self.handler_store.sql_updates.drain(..) {
+                let query = match sql_update {
+                    SqlUpdate::TransactionStatus { tx_hash, parent_dp_hash, status, settled_block_height } => {
+                        sqlx::query("UPDATE transactions SET transaction_status = $1, settled_block_height = $2 WHERE tx_hash = $3 AND parent_dp_hash = $4")
+                            .bind(status)
+                            .bind(settled_block_height)
+                            .bind(tx_hash)
+                            .bind(parent_dp_hash)
+                    }
+                    SqlUpdate::BlobVerified { tx_hash, parent_dp_hash, blob_index } => {
+                        sqlx::query("UPDATE blobs SET verified = true WHERE tx_hash = $1 AND parent_dp_hash = $2 AND blob_index = $3")
+                            .bind(tx_hash)
+                            .bind(parent_dp_hash)
+                            .bind(blob_index)
+                    }
+                    SqlUpdate::BlobProofOutputSettled { blob_tx_hash, blob_parent_dp_hash, blob_index, blob_proof_output_index, settled_at } => {
+                        sqlx::query("UPDATE blob_proof_outputs SET settled = true, settlement_lag = $1 - proved_at WHERE blob_tx_hash = $2 AND blob_parent_dp_hash = $3 AND blob_index = $4 AND blob_proof_output_index = $5")
+                            .bind(settled_at)
+                            .bind(blob_tx_hash)
+                            .bind(blob_parent_dp_hash)
+                            .bind(blob_index)
+                            .bind(blob_proof_output_index)
+                    }
+                    SqlUpdate::ContractSettlement { tx_hash, parent_dp_hash, status, settled_at } => {
+                        sqlx::query("UPDATE contract_settlements SET transaction_status = $1, settlement_latency = $2 - sequenced_at WHERE tx_hash = $3 AND parent_dp_hash = $4")
+                            .bind(status)
+                            .bind(settled_at)
+                            .bind(tx_hash)
+                            .bind(parent_dp_hash)
+                    }
+                    SqlUpdate::BlockContractState { contract_name, block_hash, state_commitment } => {
+                        sqlx::query("UPDATE contract_state SET state_commitment = $1 WHERE contract_name = $2 AND block_hash = $3")
+                            .bind(state_commitment)
+                            .bind(contract_name)
+                            .bind(block_hash)
+                    }
+                    SqlUpdate::ContractStateCommitment { contract_name, state_commitment } => {
+                        sqlx::query("UPDATE contracts SET state_commitment = $1 WHERE contract_name = $2")
+                            .bind(state_commitment)
+                            .bind(contract_name)
+                    }
+                    SqlUpdate::ContractProgramId { contract_name, program_id } => {
+                        sqlx::query("UPDATE contracts SET program_id = $1 WHERE contract_name = $2")
+                            .bind(program_id)
+                            .bind(contract_name)
+                    }
+                    SqlUpdate::ContractTimeoutWindow { contract_name, timeout_window } => {
+                        sqlx::query("UPDATE contracts SET timeout_window = $1 WHERE contract_name = $2")
+                            .bind(timeout_window)
+                            .bind(contract_name)
+                    }
+                };
                 _ = log_error!(
-                    sql_update.execute(&mut *transaction).await,
+                    query.execute(&mut *transaction).await,
                     "Executing SQL update"
                 )?;
             }
-        }
 
-        transaction.commit().await?;
+            transaction.commit().await?;
+        });
 
-        Ok(())
+        for block in dumped_blocks {
+            let _ = self.bus.send(WsExplorerBlock(block));
+        }
+
+        Ok(deleted)
     }
 
     pub async fn handle_mempool_status_event(&mut self, event: MempoolStatusEvent) -> Result<()> {
-        let mut transaction = self.db.begin().await?;
-        match event {
-            MempoolStatusEvent::WaitingDissemination {
-                parent_data_proposal_hash,
-                tx,
-            } => {
-                let parent_data_proposal_hash_db: DataProposalHashDb =
-                    parent_data_proposal_hash.into();
-                let tx_hash: TxHash = tx.hashed();
-                let version = i32::try_from(tx.version)
-                    .map_err(|_| anyhow::anyhow!("Tx version is too large to fit into an i32"))?;
-
-                // Insert the transaction into the transactions table
-                let tx_type = TransactionTypeDb::from(&tx);
-                let tx_hash: &TxHashDb = &tx_hash.into();
+        let db = self.db.clone();
+        with_db!(&db, |pool| {
+            let mut transaction = pool.begin().await?;
+            match event {
+                MempoolStatusEvent::WaitingDissemination {
+                    parent_data_proposal_hash,
+                    tx,
+                } => {
+                    let parent_data_proposal_hash_db: DataProposalHashDb =
+                        parent_data_proposal_hash.into();
+                    let tx_hash: TxHash = tx.hashed();
+                    let version = i32::try_from(tx.version).map_err(|_| {
+                        anyhow::anyhow!("Tx version is too large to fit into an i32")
+                    })?;
 
-                info!(
-                    "Inserting waiting_dissemination TX {} with parent data proposal hash {}",
-                    tx_hash.0, parent_data_proposal_hash_db.0
-                );
+                    // Insert the transaction into the transactions table
+                    let tx_type = TransactionTypeDb::from(&tx);
+                    let tx_hash: &TxHashDb = &tx_hash.into();
 
-                // If the TX is already present, we can assume it's more up-to-date so do nothing.
-                sqlx::query(
-                    "INSERT INTO transactions (tx_hash, parent_dp_hash, version, transaction_type, transaction_status)
-                    VALUES ($1, $2, $3, $4, 'waiting_dissemination')
-                    ON CONFLICT(tx_hash, parent_dp_hash) DO NOTHING",
-                )
+                    info!(
+                        "Inserting waiting_dissemination TX {} with parent data proposal hash {}",
+                        tx_hash.0, parent_data_proposal_hash_db.0
+                    );
+
+                    // If the TX is already present, we can assume it's more up-to-date so do nothing.
+                    sqlx::query(
+                        "INSERT INTO transactions (tx_hash, parent_dp_hash, version, transaction_type, transaction_status)
+                        VALUES ($1, $2, $3, $4, 'waiting_dissemination')
+                        ON CONFLICT(tx_hash, parent_dp_hash) DO NOTHING",
+                    )
                     .bind(tx_hash)
                     .bind(parent_data_proposal_hash_db.clone())
                     .bind(version)
@@ -759,134 +986,139 @@ impl Indexer {
                     .execute(&mut *transaction)
                     .await?;
 
-                _ = log_warn!(
-                    self.insert_tx_data(tx_hash, &tx, parent_data_proposal_hash_db,),
-                    "Inserting tx data at status 'waiting dissemination'"
-                );
-            }
-
-            MempoolStatusEvent::DataProposalCreated {
-                parent_data_proposal_hash,
-                data_proposal_hash,
-                txs_metadatas,
-            } => {
-                let mut seen = HashSet::new();
-                let unique_txs_metadatas: Vec<_> = txs_metadatas
-                    .into_iter()
-                    .filter(|value| {
-                        let key = (value.id.1.clone(), value.id.0.clone());
-                        seen.insert(key)
-                    })
-                    .collect();
+                    _ = log_warn!(
+                        self.insert_tx_data(tx_hash, &tx, parent_data_proposal_hash_db,),
+                        "Inserting tx data at status 'waiting dissemination'"
+                    );
+                }
 
-                let mut query_builder = QueryBuilder::new(
+                MempoolStatusEvent::DataProposalCreated {
+                    parent_data_proposal_hash,
+                    data_proposal_hash,
+                    txs_metadatas,
+                } => {
+                    let mut seen = HashSet::new();
+                    let unique_txs_metadatas: Vec<_> = txs_metadatas
+                        .into_iter()
+                        .filter(|value| {
+                            let key = (value.id.1.clone(), value.id.0.clone());
+                            seen.insert(key)
+                        })
+                        .collect();
+
+                    let mut query_builder = QueryBuilder::new(
                     "INSERT INTO transactions (tx_hash, parent_dp_hash, version, transaction_type, transaction_status)",
                 );
 
-                query_builder.push_values(unique_txs_metadatas, |mut b, value| {
-                    let tx_type: TransactionTypeDb = value.transaction_kind.into();
-                    let version = log_error!(
-                        i32::try_from(value.version).map_err(|_| anyhow::anyhow!(
-                            "Tx version is too large to fit into an i32"
-                        )),
-                        "Converting version number into i32"
-                    )
-                    .unwrap_or(0);
-
-                    let tx_hash: TxHashDb = value.id.1.into();
-                    let parent_data_proposal_hash_db: DataProposalHashDb = value.id.0.into();
+                    query_builder.push_values(unique_txs_metadatas, |mut b, value| {
+                        let tx_type: TransactionTypeDb = value.transaction_kind.into();
+                        let version = log_error!(
+                            i32::try_from(value.version).map_err(|_| anyhow::anyhow!(
+                                "Tx version is too large to fit into an i32"
+                            )),
+                            "Converting version number into i32"
+                        )
+                        .unwrap_or(0);
 
-                    info!(
-                        "Inserting data_proposal_created TX {} with parent data proposal hash {}",
-                        tx_hash.0, parent_data_proposal_hash_db.0
-                    );
+                        let tx_hash: TxHashDb = value.id.1.into();
+                        let parent_data_proposal_hash_db: DataProposalHashDb = value.id.0.into();
 
-                    b.push_bind(tx_hash)
-                        .push_bind(parent_data_proposal_hash_db)
-                        .push_bind(version)
-                        .push_bind(tx_type)
-                        .push_bind(TransactionStatusDb::DataProposalCreated);
-                });
+                        info!(
+                            "Inserting data_proposal_created TX {} with parent data proposal hash {}",
+                            tx_hash.0, parent_data_proposal_hash_db.0
+                        );
 
-                // If the TX is already present, we try to update its status, only if the status is lower ('waiting_dissemination').
-                query_builder.push(" ON CONFLICT(tx_hash, parent_dp_hash) DO UPDATE SET ");
+                        b.push_bind(tx_hash)
+                            .push_bind(parent_data_proposal_hash_db)
+                            .push_bind(version)
+                            .push_bind(tx_type)
+                            .push_bind(TransactionStatusDb::DataProposalCreated);
+                    });
 
-                query_builder.push("transaction_status=");
-                query_builder.push_bind(TransactionStatusDb::DataProposalCreated);
-                query_builder
-                    .push(" WHERE transactions.transaction_status='waiting_dissemination'");
+                    // If the TX is already present, we try to update its status, only if the status is lower ('waiting_dissemination').
+                    query_builder.push(" ON CONFLICT(tx_hash, parent_dp_hash) DO UPDATE SET ");
 
-                query_builder
-                    .build()
-                    .execute(transaction.deref_mut())
-                    .await
-                    .context("Upserting data at status data_proposal_created")?;
-
-                // Second step - any TX that was skipped for this DP needs to have its ID updated
-                // (this should be all TXs with the same parent as us still in waiting dissemination).
-                let mut query_builder =
-                    QueryBuilder::new("UPDATE transactions SET parent_dp_hash = ");
-                let parent_data_proposal_hash_db: DataProposalHashDb =
-                    parent_data_proposal_hash.clone().into();
-                let data_proposal_hash_db: DataProposalHashDb = data_proposal_hash.clone().into();
-                query_builder
-                    .push_bind(data_proposal_hash_db.clone())
-                    .push(" WHERE parent_dp_hash = ")
-                    .push_bind(parent_data_proposal_hash_db)
-                    .push(" AND transaction_status = 'waiting_dissemination' RETURNING tx_hash");
-                let txs = query_builder
-                    .build()
-                    .fetch_all(transaction.deref_mut())
-                    .await
-                    .context("Updating parent data proposal hash")?;
-                // Then we need to update blobs
-                let tx_hashes = txs
-                    .iter()
-                    .filter_map(|row| {
-                        if let Ok(tx_hash) = row.try_get::<TxHashDb, _>(0) {
-                            self.handler_store.tx_data.iter_mut().for_each(|tx_data| {
-                                if tx_data.tx_hash == tx_hash
-                                    && tx_data.parent_data_proposal_hash.0
-                                        == parent_data_proposal_hash
-                                {
-                                    tx_data.parent_data_proposal_hash =
-                                        data_proposal_hash_db.clone();
-                                }
-                            });
-                            return Some(tx_hash);
-                        }
-                        None
-                    })
-                    .collect::<Vec<_>>();
-                if !tx_hashes.is_empty() {
-                    let mut query_builder = QueryBuilder::new("UPDATE blobs SET parent_dp_hash = ");
-                    let parent_data_proposal_hash_db: DataProposalHashDb =
-                        parent_data_proposal_hash.into();
-                    let data_proposal_hash_db: DataProposalHashDb = data_proposal_hash.into();
+                    query_builder.push("transaction_status=");
+                    query_builder.push_bind(TransactionStatusDb::DataProposalCreated);
+                    query_builder
+                        .push(" WHERE transactions.transaction_status='waiting_dissemination'");
 
-                    info!(
-                        "Updating skipped TXs with parent data proposal hash {} to new DP hash {}: {:?}",
-                        parent_data_proposal_hash_db.0, data_proposal_hash_db.0, tx_hashes
-                    );
+                    query_builder
+                        .build()
+                        .execute(transaction.deref_mut())
+                        .await
+                        .context("Upserting data at status data_proposal_created")?;
 
+                    // Second step - any TX that was skipped for this DP needs to have its ID updated
+                    // (this should be all TXs with the same parent as us still in waiting dissemination).
+                    let mut query_builder =
+                        QueryBuilder::new("UPDATE transactions SET parent_dp_hash = ");
+                    let parent_data_proposal_hash_db: DataProposalHashDb =
+                        parent_data_proposal_hash.clone().into();
+                    let data_proposal_hash_db: DataProposalHashDb =
+                        data_proposal_hash.clone().into();
                     query_builder
                         .push_bind(data_proposal_hash_db.clone())
                         .push(" WHERE parent_dp_hash = ")
                         .push_bind(parent_data_proposal_hash_db)
-                        .push(" AND tx_hash in ");
-                    query_builder.push_tuples(tx_hashes, |mut b, tx_hash| {
-                        b.push_bind(tx_hash);
-                    });
-                    query_builder
+                        .push(
+                            " AND transaction_status = 'waiting_dissemination' RETURNING tx_hash",
+                        );
+                    let txs = query_builder
                         .build()
-                        .execute(transaction.deref_mut())
+                        .fetch_all(transaction.deref_mut())
                         .await
                         .context("Updating parent data proposal hash")?;
+                    // Then we need to update blobs
+                    let tx_hashes = txs
+                        .iter()
+                        .filter_map(|row| {
+                            if let Ok(tx_hash) = row.try_get::<TxHashDb, _>(0) {
+                                self.handler_store.tx_data.iter_mut().for_each(|tx_data| {
+                                    if tx_data.tx_hash == tx_hash
+                                        && tx_data.parent_data_proposal_hash.0
+                                            == parent_data_proposal_hash
+                                    {
+                                        tx_data.parent_data_proposal_hash =
+                                            data_proposal_hash_db.clone();
+                                    }
+                                });
+                                return Some(tx_hash);
+                            }
+                            None
+                        })
+                        .collect::<Vec<_>>();
+                    if !tx_hashes.is_empty() {
+                        let mut query_builder =
+                            QueryBuilder::new("UPDATE blobs SET parent_dp_hash = ");
+                        let parent_data_proposal_hash_db: DataProposalHashDb =
+                            parent_data_proposal_hash.into();
+                        let data_proposal_hash_db: DataProposalHashDb = data_proposal_hash.into();
+
+                        info!(
+                            "Updating skipped TXs with parent data proposal hash {} to new DP hash {}: {:?}",
+                            parent_data_proposal_hash_db.0, data_proposal_hash_db.0, tx_hashes
+                        );
+
+                        query_builder
+                            .push_bind(data_proposal_hash_db.clone())
+                            .push(" WHERE parent_dp_hash = ")
+                            .push_bind(parent_data_proposal_hash_db)
+                            .push(" AND tx_hash in ");
+                        query_builder.push_tuples(tx_hashes, |mut b, tx_hash| {
+                            b.push_bind(tx_hash);
+                        });
+                        query_builder
+                            .build()
+                            .execute(transaction.deref_mut())
+                            .await
+                            .context("Updating parent data proposal hash")?;
+                    }
                 }
             }
-        }
 
-        transaction.commit().await?;
+            transaction.commit().await?;
+        });
 
         Ok(())
     }
@@ -966,6 +1198,8 @@ impl Indexer {
 
         let block_height = i64::try_from(block.block_height.0)
             .map_err(|_| anyhow::anyhow!("Block height is too large to fit into an i64"))?;
+        let block_timestamp = i64::try_from(block.block_timestamp.0)
+            .map_err(|_| anyhow::anyhow!("Block timestamp is too large to fit into an i64"))?;
 
         let mut i: i32 = 0;
         #[allow(clippy::explicit_counter_loop)]
@@ -991,6 +1225,22 @@ impl Indexer {
                 Err(_) => (None, None),
             };
             if let TransactionData::Blob(blob_tx) = &tx.transaction_data {
+                let mut blob_bytes = BTreeMap::<&ContractName, i64>::new();
+                for blob in &blob_tx.blobs {
+                    *blob_bytes.entry(&blob.contract_name).or_default() += blob.data.0.len() as i64;
+                }
+                self.handler_store
+                    .contract_settlements
+                    .extend(blob_bytes.into_iter().map(|(contract_name, blob_bytes)| {
+                        TxContractSettlementStore {
+                            contract_name: contract_name.0.clone(),
+                            tx_hash: tx_hash.clone(),
+                            parent_data_proposal_hash: parent_data_proposal_hash.clone(),
+                            sequenced_at: block_timestamp,
+                            blob_bytes,
+                        }
+                    }));
+
                 // Send the transaction to all websocket subscribers
                 self.send_blob_transaction_to_websocket_subscribers(
                     blob_tx,
@@ -1048,12 +1298,22 @@ impl Indexer {
                 .clone()
                 .into();
             let tx_hash: TxHashDb = settled_blob_tx_hash.into();
-            self.handler_store.sql_updates.push(
-                sqlx::query("UPDATE transactions SET transaction_status = $1 WHERE tx_hash = $2 AND parent_dp_hash = $3")
-                    .bind(TransactionStatusDb::Success)
-                    .bind(tx_hash)
-                    .bind(dp_hash_db)
-            );
+            self.handler_store
+                .sql_updates
+                .push(SqlUpdate::ContractSettlement {
+                    tx_hash: tx_hash.clone(),
+                    parent_dp_hash: dp_hash_db.clone(),
+                    status: TransactionStatusDb::Success,
+                    settled_at: block_timestamp,
+                });
+            self.handler_store
+                .sql_updates
+                .push(SqlUpdate::TransactionStatus {
+                    tx_hash,
+                    parent_dp_hash: dp_hash_db,
+                    status: TransactionStatusDb::Success,
+                    settled_block_height: block_height,
+                });
         }
 
         for failed_blob_tx_hash in block.failed_txs {
@@ -1067,12 +1327,22 @@ impl Indexer {
                 .clone()
                 .into();
             let tx_hash: TxHashDb = failed_blob_tx_hash.into();
-            self.handler_store.sql_updates.push(
-                sqlx::query("UPDATE transactions SET transaction_status = $1 WHERE tx_hash = $2 AND parent_dp_hash = $3")
-                    .bind(TransactionStatusDb::Failure)
-                    .bind(tx_hash)
-                    .bind(dp_hash_db)
-            );
+            self.handler_store
+                .sql_updates
+                .push(SqlUpdate::ContractSettlement {
+                    tx_hash: tx_hash.clone(),
+                    parent_dp_hash: dp_hash_db.clone(),
+                    status: TransactionStatusDb::Failure,
+                    settled_at: block_timestamp,
+                });
+            self.handler_store
+                .sql_updates
+                .push(SqlUpdate::TransactionStatus {
+                    tx_hash,
+                    parent_dp_hash: dp_hash_db,
+                    status: TransactionStatusDb::Failure,
+                    settled_block_height: block_height,
+                });
         }
 
         // Handling timed out blob transactions
@@ -1087,12 +1357,22 @@ impl Indexer {
                 .clone()
                 .into();
             let tx_hash: TxHashDb = timed_out_tx_hash.into();
-            self.handler_store.sql_updates.push(
-                sqlx::query("UPDATE transactions SET transaction_status = $1 WHERE tx_hash = $2 AND parent_dp_hash = $3")
-                    .bind(TransactionStatusDb::TimedOut)
-                    .bind(tx_hash)
-                    .bind(dp_hash_db)
-            );
+            self.handler_store
+                .sql_updates
+                .push(SqlUpdate::ContractSettlement {
+                    tx_hash: tx_hash.clone(),
+                    parent_dp_hash: dp_hash_db.clone(),
+                    status: TransactionStatusDb::TimedOut,
+                    settled_at: block_timestamp,
+                });
+            self.handler_store
+                .sql_updates
+                .push(SqlUpdate::TransactionStatus {
+                    tx_hash,
+                    parent_dp_hash: dp_hash_db,
+                    status: TransactionStatusDb::TimedOut,
+                    settled_block_height: block_height,
+                });
         }
 
         for handled_blob_proof_output in block.blob_proof_outputs {
@@ -1137,6 +1417,8 @@ impl Indexer {
                     contract_name: handled_blob_proof_output.contract_name.0.clone(),
                     hyle_output: serialized_hyle_output,
                     settled: false,
+                    program_id: handled_blob_proof_output.program_id.0,
+                    proved_at: block_timestamp,
                 });
         }
 
@@ -1155,12 +1437,13 @@ impl Indexer {
             let blob_index = i32::try_from(blob_index.0)
                 .map_err(|_| anyhow::anyhow!("Blob index is too large to fit into an i32"))?;
 
-            self.handler_store.sql_updates.push(
-                sqlx::query("UPDATE blobs SET verified = true WHERE tx_hash = $1 AND parent_dp_hash = $2 AND blob_index = $3")
-                    .bind(blob_tx_hash.clone())
-                    .bind(blob_tx_parent_dp_hash.clone())
-                    .bind(blob_index)
-            );
+            self.handler_store
+                .sql_updates
+                .push(SqlUpdate::BlobVerified {
+                    tx_hash: blob_tx_hash.clone(),
+                    parent_dp_hash: blob_tx_parent_dp_hash.clone(),
+                    blob_index,
+                });
 
             if let Some(blob_proof_output_index) = blob_proof_output_index {
                 let blob_proof_output_index =
@@ -1168,18 +1451,29 @@ impl Indexer {
                         anyhow::anyhow!("Blob proof output index is too large to fit into an i32")
                     })?;
 
-                self.handler_store.sql_updates.push(
-                    sqlx::query("UPDATE blob_proof_outputs SET settled = true WHERE blob_tx_hash = $1 AND blob_parent_dp_hash = $2 AND blob_index = $3 AND blob_proof_output_index = $4")
-                        .bind(blob_tx_hash)
-                        .bind(blob_tx_parent_dp_hash)
-                        .bind(blob_index)
-                        .bind(blob_proof_output_index)
-                );
+                self.handler_store
+                    .sql_updates
+                    .push(SqlUpdate::BlobProofOutputSettled {
+                        blob_tx_hash,
+                        blob_parent_dp_hash: blob_tx_parent_dp_hash,
+                        blob_index,
+                        blob_proof_output_index,
+                        settled_at: block_timestamp,
+                    });
             }
         }
 
         // After TXes as it refers to those (for now)
-        for (tx_hash, contract, _) in block.registered_contracts.values() {
+        for (tx_hash, contract, metadata) in block.registered_contracts.values() {
+            // Metadata is user input, a malformed schema must not stop the indexing
+            let contract_schema = log_warn!(
+                ConstructorMetadata::schema_of(metadata),
+                "Reading schema of contract {}",
+                contract.contract_name
+            )
+            .unwrap_or_default()
+            .map(|schema| serde_json::to_string(&schema))
+            .transpose()?;
             let verifier = &contract.verifier.0;
             let program_id = &contract.program_id.0;
             let state_commitment = &contract.state_commitment.0;
@@ -1211,6 +1505,7 @@ impl Indexer {
                     timeout_window: contract.timeout_window.clone().map(|tw| tw.into()),
                     state_commitment: state_commitment.clone(),
                     contract_name: contract_name.clone(),
+                    contract_schema,
                 },
             );
 
@@ -1221,6 +1516,8 @@ impl Indexer {
                     contract_name: contract_name.clone(),
                     block_hash: block.hash.clone(),
                     state_commitment: state_commitment.clone(),
+                    tx_hash: tx_hash.clone(),
+                    program_id: program_id.clone(),
                 });
         }
 
@@ -1234,22 +1531,20 @@ impl Indexer {
         for (contract_name, state_commitment) in block.updated_states {
             let contract_name = contract_name.0;
             let state_commitment = state_commitment.0;
-            self.handler_store.sql_updates.push(
-                sqlx::query(
-                    "UPDATE contract_state SET state_commitment = $1 WHERE contract_name = $2 AND block_hash = $3",
-                )
-                    .bind(state_commitment.clone())
-                    .bind(contract_name.clone())
-                    .bind(block.hash.clone())
-            );
+            self.handler_store
+                .sql_updates
+                .push(SqlUpdate::BlockContractState {
+                    contract_name: contract_name.clone(),
+                    block_hash: block.hash.clone(),
+                    state_commitment: state_commitment.clone(),
+                });
 
-            self.handler_store.sql_updates.push(
-                sqlx::query::<Postgres>(
-                    "UPDATE contracts SET state_commitment = $1 WHERE contract_name = $2",
-                )
-                .bind(state_commitment)
-                .bind(contract_name),
-            );
+            self.handler_store
+                .sql_updates
+                .push(SqlUpdate::ContractStateCommitment {
+                    contract_name,
+                    state_commitment,
+                });
         }
 
         // Handling updated contract program ids
@@ -1257,13 +1552,12 @@ impl Indexer {
             let contract_name = contract_name.0;
             let program_id = program_id.0;
 
-            self.handler_store.sql_updates.push(
-                sqlx::query::<Postgres>(
-                    "UPDATE contracts SET program_id = $1 WHERE contract_name = $2",
-                )
-                .bind(program_id)
-                .bind(contract_name),
-            );
+            self.handler_store
+                .sql_updates
+                .push(SqlUpdate::ContractProgramId {
+                    contract_name,
+                    program_id,
+                });
         }
 
         // Handling updated contract program ids
@@ -1271,13 +1565,12 @@ impl Indexer {
             let contract_name = contract_name.0;
 
             let timeout_window_db: TimeoutWindowDb = timeout_window.into();
-            self.handler_store.sql_updates.push(
-                sqlx::query::<Postgres>(
-                    "UPDATE contracts SET timeout_window = $1 WHERE contract_name = $2",
-                )
-                .bind(timeout_window_db)
-                .bind(contract_name),
-            );
+            self.handler_store
+                .sql_updates
+                .push(SqlUpdate::ContractTimeoutWindow {
+                    contract_name,
+                    timeout_window: timeout_window_db,
+                });
         }
 
         Ok(())
@@ -0,0 +1,5 @@
+-- Program that verified each blob proof output, for contract state history
+ALTER TABLE blob_proof_outputs ADD COLUMN program_id BYTEA;
+-- Registration transaction and program behind each contract state row
+ALTER TABLE contract_state ADD COLUMN tx_hash TEXT;
+ALTER TABLE contract_state ADD COLUMN program_id BYTEA;
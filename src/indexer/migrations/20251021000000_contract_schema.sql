@@ -0,0 +1,2 @@
+-- Schema given in the constructor metadata of the contract registration
+ALTER TABLE contracts ADD COLUMN contract_schema JSONB;
@@ -0,0 +1,5 @@
+-- Lets the search endpoint use indexes for its LIKE 'prefix%' queries, whatever the database collation
+CREATE INDEX idx_blocks_hash_prefix ON blocks(hash text_pattern_ops);
+CREATE INDEX idx_transactions_tx_hash_prefix ON transactions(tx_hash text_pattern_ops);
+CREATE INDEX idx_transactions_parent_dp_hash_prefix ON transactions(parent_dp_hash text_pattern_ops);
+CREATE INDEX idx_contracts_contract_name_prefix ON contracts(contract_name text_pattern_ops);
@@ -0,0 +1,17 @@
+-- Settlement analytics of each blob transaction for each contract it has blobs for, maintained by the indexer
+CREATE TABLE contract_settlements (
+    contract_name TEXT NOT NULL,
+    parent_dp_hash TEXT NOT NULL,
+    tx_hash TEXT NOT NULL,
+    sequenced_at BIGINT NOT NULL,                   -- UNIX timestamp of the sequencing block, in milliseconds
+    blob_bytes BIGINT NOT NULL,                     -- Size of the blobs of the contract
+    transaction_status transaction_status NOT NULL,
+    settlement_latency BIGINT,                      -- From sequencing to settlement in milliseconds, NULL until settled
+    PRIMARY KEY (contract_name, parent_dp_hash, tx_hash),
+    FOREIGN KEY (parent_dp_hash, tx_hash) REFERENCES transactions(parent_dp_hash, tx_hash) ON DELETE CASCADE
+);
+CREATE INDEX idx_contract_settlements_sequenced_at ON contract_settlements(contract_name, sequenced_at);
+
+-- UNIX timestamp of the block of the proof in milliseconds, and time from then to settlement
+ALTER TABLE blob_proof_outputs ADD COLUMN proved_at BIGINT;
+ALTER TABLE blob_proof_outputs ADD COLUMN settlement_lag BIGINT;
@@ -0,0 +1,115 @@
+-- SQLite flavour of the indexer schema, see ../migrations for the Postgres one.
+-- Enums are stored as text, timestamps as UTC `YYYY-MM-DD HH:MM:SS.SSS` text and JSON documents as text.
+CREATE TABLE blocks (
+    hash TEXT PRIMARY KEY,          -- Corresponds to BlockHash
+    parent_hash TEXT NOT NULL,      -- Parent block hash (BlockHash)
+    height INTEGER NOT NULL,        -- Corresponds to BlockHeight (u64)
+    timestamp TEXT NOT NULL,        -- Block timestamp
+    total_txs INTEGER NOT NULL,     -- Total number of transactions in the block
+    UNIQUE (height),                -- Ensure each block height is unique
+    CHECK (length(hash) = 64),      -- Ensure the hash is exactly 64
+    CHECK (height >= 0)             -- Ensure the height is positive
+);
+
+CREATE TABLE transactions (
+    parent_dp_hash TEXT NOT NULL,                           -- Data Proposal hash
+    tx_hash TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    transaction_type TEXT NOT NULL CHECK (transaction_type IN ('blob_transaction', 'proof_transaction', 'stake')),
+    transaction_status TEXT NOT NULL CHECK (transaction_status IN ('data_proposal_created', 'waiting_dissemination', 'success', 'failure', 'sequenced', 'timed_out')),
+    block_hash TEXT REFERENCES blocks(hash) ON DELETE CASCADE,
+    block_height INTEGER,
+    lane_id TEXT,                           -- Lane ID
+    "index" INTEGER,                        -- Index of the transaction within the block
+    identity TEXT,                          -- Identity (NULL except for blob transactions)
+    PRIMARY KEY (parent_dp_hash, tx_hash),
+    CHECK (length(tx_hash) = 64)
+);
+
+CREATE INDEX idx_transactions_lane_id ON transactions(lane_id);
+
+CREATE TABLE blobs (
+    parent_dp_hash TEXT NOT NULL,
+    tx_hash TEXT NOT NULL,
+    blob_index INTEGER NOT NULL,       -- Index of the blob within the transaction
+    identity TEXT NOT NULL,            -- Identity field from the original BlobTransaction struct
+    contract_name TEXT NOT NULL,       -- Contract name associated with the blob
+    data BLOB NOT NULL,                -- Actual blob data
+    verified BOOLEAN NOT NULL,         -- Field to indicate if the blob is verified
+    PRIMARY KEY (parent_dp_hash, tx_hash, blob_index),
+    CHECK (blob_index >= 0),
+    FOREIGN KEY (parent_dp_hash, tx_hash) REFERENCES transactions(parent_dp_hash, tx_hash) ON DELETE CASCADE
+);
+CREATE INDEX idx_blobs_contract_name ON blobs(contract_name);
+
+-- This table stores actual proofs, which may not be present in all indexers
+CREATE TABLE proofs (
+    tx_hash TEXT NOT NULL,
+    parent_dp_hash TEXT NOT NULL,
+    proof BLOB NOT NULL,
+    FOREIGN KEY (parent_dp_hash, tx_hash) REFERENCES transactions(parent_dp_hash, tx_hash) ON DELETE CASCADE,
+    PRIMARY KEY (tx_hash, parent_dp_hash)
+);
+
+-- This table stores one line for each hyle output in a VerifiedProof
+CREATE TABLE blob_proof_outputs (
+    blob_parent_dp_hash TEXT NOT NULL,
+    blob_tx_hash TEXT NOT NULL,
+    proof_parent_dp_hash TEXT NOT NULL,
+    proof_tx_hash TEXT NOT NULL,
+    blob_index INTEGER NOT NULL,              -- Index of the blob within the transaction
+    blob_proof_output_index INTEGER NOT NULL, -- Index of the blob proof output within the proof
+    contract_name TEXT NOT NULL,              -- Contract name associated with the blob
+    hyle_output TEXT NOT NULL,                -- JSON serialized hyle output
+    settled BOOLEAN NOT NULL,                 -- Was this blob proof output used in settlement ?
+    PRIMARY KEY (proof_parent_dp_hash, proof_tx_hash, blob_parent_dp_hash, blob_tx_hash, blob_index, blob_proof_output_index),
+    FOREIGN KEY (blob_parent_dp_hash, blob_tx_hash, blob_index) REFERENCES blobs(parent_dp_hash, tx_hash, blob_index) ON DELETE CASCADE,
+    FOREIGN KEY (blob_tx_hash, blob_parent_dp_hash) REFERENCES transactions(tx_hash, parent_dp_hash) ON DELETE CASCADE,
+    FOREIGN KEY (proof_tx_hash, proof_parent_dp_hash) REFERENCES transactions(tx_hash, parent_dp_hash) ON DELETE CASCADE,
+    UNIQUE (blob_parent_dp_hash, blob_tx_hash, blob_index, blob_proof_output_index)
+);
+
+CREATE TABLE contracts (
+    tx_hash TEXT NOT NULL,
+    parent_dp_hash TEXT NOT NULL,
+    verifier TEXT NOT NULL,
+    program_id BLOB NOT NULL,
+    timeout_window INTEGER,
+    state_commitment BLOB NOT NULL,
+    contract_name TEXT PRIMARY KEY NOT NULL,
+    FOREIGN KEY (parent_dp_hash, tx_hash) REFERENCES transactions(parent_dp_hash, tx_hash) ON DELETE CASCADE
+);
+
+CREATE TABLE contract_state (
+    contract_name TEXT NOT NULL,                                          -- Name of the contract
+    block_hash TEXT NOT NULL REFERENCES blocks(hash) ON DELETE CASCADE,   -- Block where the state is captured
+    state_commitment BLOB NOT NULL,
+    PRIMARY KEY (contract_name, block_hash)
+);
+
+CREATE TABLE transaction_state_events (
+    block_hash TEXT NOT NULL REFERENCES blocks(hash) ON DELETE CASCADE,
+    block_height INTEGER,
+    "index" INTEGER,
+    tx_hash TEXT NOT NULL,
+    parent_dp_hash TEXT NOT NULL,
+    events TEXT NOT NULL,                                                 -- JSON serialized events
+    FOREIGN KEY (tx_hash, parent_dp_hash) REFERENCES transactions(tx_hash, parent_dp_hash) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_bpo_on_proof_tx ON blob_proof_outputs (proof_tx_hash);
+
+CREATE INDEX idx_proofs_on_tx_hash ON proofs (tx_hash);
+
+CREATE INDEX idx_bpo_prooftx_contract ON blob_proof_outputs (proof_tx_hash, contract_name);
+
+-- Index for get tx by hash
+CREATE INDEX idx_tx_fast_lookup ON transactions (tx_hash, transaction_type, block_height DESC, "index" DESC, block_hash);
+
+CREATE TABLE txs_contracts (
+    parent_dp_hash TEXT NOT NULL,
+    tx_hash TEXT NOT NULL,
+    contract_name TEXT NOT NULL,
+    PRIMARY KEY (parent_dp_hash, tx_hash, contract_name)
+);
+CREATE INDEX idx_txs_contracts_name ON txs_contracts(contract_name);
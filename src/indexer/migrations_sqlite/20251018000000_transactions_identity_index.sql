@@ -0,0 +1,2 @@
+-- Speeds up the identity-centric explorer endpoints
+CREATE INDEX idx_transactions_identity ON transactions(identity, block_height DESC, "index" DESC);
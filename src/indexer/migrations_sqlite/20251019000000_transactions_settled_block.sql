@@ -0,0 +1,2 @@
+-- Block where a blob transaction settled, succeeded, failed or timed out, for settlement analytics
+ALTER TABLE transactions ADD COLUMN settled_block_height BIGINT;
@@ -0,0 +1,2 @@
+-- Schema given in the constructor metadata of the contract registration, JSON serialized
+ALTER TABLE contracts ADD COLUMN contract_schema TEXT;
@@ -0,0 +1,2 @@
+-- State commitment the contract was registered with, state_commitment also follows the transactions settled in the same block
+ALTER TABLE contract_state ADD COLUMN registered_state_commitment BLOB;
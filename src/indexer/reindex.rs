@@ -0,0 +1,576 @@
+//! Re-indexing of a range of blocks, replayed from a DA server or a local block dump.
+//!
+//! Settling transactions depends on all the previous blocks, so the node state is rebuilt from
+//! genesis in memory. Only the blocks of the range are written to the database, in chunks of
+//! `query_buffer_size` blocks each replacing its indexed blocks in a single transaction.
+
+use std::{
+    collections::BTreeMap,
+    ops::Deref,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use futures::StreamExt;
+use hyle_modules::{
+    bus::SharedMessageBus,
+    log_error,
+    modules::{data_availability::blocks_fjall::Blocks, Module},
+    node_state::NodeState,
+    utils::da_codec::{DataAvailabilityClient, DataAvailabilityEvent, DataAvailabilityRequest},
+};
+use hyle_net::logged_task::logged_task;
+use opentelemetry::{
+    metrics::{Counter, Gauge},
+    InstrumentationScope,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::{info, warn};
+
+use super::{Indexer, IndexerBusClient, IndexerHandlerStore};
+use crate::explorer::db::IndexerDb;
+use crate::{model::*, utils::conf::SharedConf};
+
+/// Number of blocks fetched by each connection to the DA server
+const DA_CHUNK_SIZE: u64 = 500;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReindexConf {
+    /// First block to index again
+    pub from: BlockHeight,
+    /// Last block to index again, defaults to the last indexed block
+    pub to: Option<BlockHeight>,
+    /// Number of blocks ranges fetched in parallel
+    pub fetchers: usize,
+}
+
+#[derive(Debug, Clone)]
+struct ReindexMetrics {
+    target_height: Gauge<u64>,
+    current_height: Gauge<u64>,
+    replayed_blocks: Counter<u64>,
+    indexed_blocks: Counter<u64>,
+}
+
+impl ReindexMetrics {
+    fn global(node_name: String) -> ReindexMetrics {
+        let scope = InstrumentationScope::builder(node_name).build();
+        let my_meter = opentelemetry::global::meter_with_scope(scope);
+
+        let reindex = "reindex";
+
+        ReindexMetrics {
+            target_height: my_meter
+                .u64_gauge(format!("{reindex}_target_height"))
+                .build(),
+            current_height: my_meter
+                .u64_gauge(format!("{reindex}_current_height"))
+                .build(),
+            replayed_blocks: my_meter
+                .u64_counter(format!("{reindex}_replayed_blocks"))
+                .build(),
+            indexed_blocks: my_meter
+                .u64_counter(format!("{reindex}_indexed_blocks"))
+                .build(),
+        }
+    }
+}
+
+/// Module deleting then indexing again a range of blocks. It exits once done.
+pub struct Reindexer {
+    indexer: Indexer,
+    conf: ReindexConf,
+    metrics: ReindexMetrics,
+}
+
+impl Module for Reindexer {
+    type Context = SharedConf;
+
+    async fn build(bus: SharedMessageBus, ctx: Self::Context) -> Result<Self> {
+        let Some(conf) = ctx.reindex.clone() else {
+            bail!("Reindexer needs a reindex configuration");
+        };
+
+        let db = IndexerDb::connect(&ctx.database_url).await?;
+        db.migrate().await?;
+
+        let indexer = Indexer {
+            bus: IndexerBusClient::new_from_bus(bus.new_handle()).await,
+            db,
+            // The node state of the running indexer is left untouched
+            node_state: NodeState::create(ctx.id.clone(), "reindexer"),
+            handler_store: IndexerHandlerStore::default(),
+            conf: ctx.deref().clone(),
+        };
+
+        Ok(Reindexer {
+            indexer,
+            conf,
+            metrics: ReindexMetrics::global(ctx.id.clone()),
+        })
+    }
+
+    fn run(&mut self) -> impl futures::Future<Output = Result<()>> + Send {
+        self.start()
+    }
+}
+
+impl Reindexer {
+    pub async fn start(&mut self) -> Result<()> {
+        let last_indexed = self.indexer.get_last_block().await?;
+        let from = self.conf.from;
+        let Some(to) = self.conf.to.or(last_indexed) else {
+            bail!("No block indexed yet, the last block to reindex must be given");
+        };
+        if from > to {
+            bail!("Cannot reindex from block {from} to block {to}");
+        }
+        if last_indexed.is_some_and(|last| last > to) {
+            warn!(
+                "Blocks after {to} stay indexed: transactions they settled and contracts they updated keep their state as of block {to}"
+            );
+        }
+        self.metrics.target_height.record(to.0, &[]);
+
+        // A blocks source failing midway leaves the chunks not written yet as they were indexed
+        let (mut blocks, source) = self.fetch_blocks(to)?;
+        let mut next = BlockHeight(0);
+        let mut chunk_start = None;
+        let mut deleted = 0;
+        while let Some(block) = blocks.recv().await {
+            let height = block.height();
+            // A missing block would silently change the replayed state
+            if height != next {
+                bail!("Expected block {next} from the blocks source, got block {height}");
+            }
+            next = height + 1;
+            let processed_block = self
+                .indexer
+                .node_state
+                .handle_signed_block(&block)
+                .context("Failed to handle block in node state")?;
+            self.metrics.replayed_blocks.add(1, &[]);
+            self.metrics.current_height.record(height.0, &[]);
+
+            if height >= from {
+                self.indexer.handle_processed_block(processed_block)?;
+                let start = *chunk_start.get_or_insert(height);
+                if height >= to
+                    || self.indexer.buffered_blocks() >= self.indexer.conf.indexer.query_buffer_size
+                {
+                    let indexed_blocks = self.indexer.buffered_blocks() as u64;
+                    deleted += self.indexer.replace_blocks_in_db(start, height).await?;
+                    self.metrics.indexed_blocks.add(indexed_blocks, &[]);
+                    chunk_start = None;
+                }
+            }
+
+            if height.0 % 1000 == 0 {
+                info!("📦 Reindexing, replayed block {height} of {to}");
+            }
+            if height >= to {
+                break;
+            }
+        }
+
+        if self.indexer.node_state.current_height < to {
+            source
+                .await
+                .context("Blocks source task failed")?
+                .context("Reading blocks source")?;
+            bail!(
+                "Blocks source ended at block {}, before block {to}",
+                self.indexer.node_state.current_height
+            );
+        }
+
+        info!("✅ Reindexed blocks {from} to {to}, replacing {deleted} indexed blocks");
+
+        Ok(())
+    }
+
+    /// Streams the blocks from genesis to `to` in order, from the configured blocks source:
+    /// a `folder:` of block dumps, a `da:` storage directory, or the address of a DA server.
+    /// The returned task holds the error of the blocks source, if it stopped early.
+    fn fetch_blocks(
+        &self,
+        to: BlockHeight,
+    ) -> Result<(mpsc::Receiver<SignedBlock>, JoinHandle<Result<()>>)> {
+        let (sender, receiver) = mpsc::channel(DA_CHUNK_SIZE as usize);
+        let source = self.indexer.conf.da_read_from.clone();
+        let fetchers = self.conf.fetchers.max(1);
+
+        let task = if let Some(folder) = source.strip_prefix("folder:") {
+            let folder = PathBuf::from(folder);
+            logged_task(async move {
+                log_error!(
+                    read_folder(folder, to, fetchers, sender).await,
+                    "Reading blocks folder"
+                )
+            })
+        } else if let Some(folder) = source.strip_prefix("da:") {
+            let mut blocks = Blocks::new(&PathBuf::from(folder))?;
+            tokio::task::spawn_blocking(move || {
+                log_error!(
+                    read_da_storage(&mut blocks, to, sender),
+                    "Reading DA storage"
+                )
+            })
+        } else {
+            let da = DaSource {
+                address: source,
+                max_frame_length: self.indexer.conf.da_max_frame_length,
+                timeout: Duration::from_secs(self.indexer.conf.da_timeout_client_secs),
+            };
+            logged_task(async move {
+                log_error!(
+                    fetch_from_da(da, to, fetchers, sender).await,
+                    "Fetching blocks from DA"
+                )
+            })
+        };
+
+        Ok((receiver, task))
+    }
+}
+
+/// Reads the block dumps of a folder, named after the height of their block. Only the files up
+/// to `to` are decoded, in order. Any file that can't be read or decoded fails the read.
+async fn read_folder(
+    folder: PathBuf,
+    to: BlockHeight,
+    fetchers: usize,
+    sender: mpsc::Sender<SignedBlock>,
+) -> Result<()> {
+    info!("Reading blocks from folder {}", folder.display());
+    let mut paths = vec![];
+    for entry in
+        std::fs::read_dir(&folder).context(format!("Listing blocks folder {}", folder.display()))?
+    {
+        let path = entry
+            .context(format!("Listing blocks folder {}", folder.display()))?
+            .path();
+        if path.extension().is_none_or(|ext| ext != "bin") {
+            continue;
+        }
+        let height = block_file_height(&path).context(format!(
+            "Block file {} is not named after a block height",
+            path.display()
+        ))?;
+        if height <= to {
+            paths.push((height, path));
+        }
+    }
+    paths.sort_by_key(|(height, _)| *height);
+
+    let mut blocks = futures::stream::iter(paths)
+        .map(|(height, path)| async move {
+            let bytes = tokio::fs::read(&path)
+                .await
+                .context(format!("Reading block file {}", path.display()))?;
+            let (block, _) = borsh::from_slice::<(SignedBlock, usize)>(&bytes)
+                .context(format!("Decoding block file {}", path.display()))?;
+            if block.height() != height {
+                bail!(
+                    "Block file {} holds block {}",
+                    path.display(),
+                    block.height()
+                );
+            }
+            Ok(block)
+        })
+        .buffered(fetchers);
+    while let Some(block) = blocks.next().await {
+        sender.send(block?).await?;
+    }
+    Ok(())
+}
+
+/// Height of the block dumped in a file, given by the leading digits of its name
+fn block_file_height(path: &Path) -> Option<BlockHeight> {
+    let stem = path.file_stem()?.to_str()?;
+    let digits = stem
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(stem.len());
+    stem[..digits].parse().ok().map(BlockHeight)
+}
+
+fn read_da_storage(
+    blocks: &mut Blocks,
+    to: BlockHeight,
+    sender: mpsc::Sender<SignedBlock>,
+) -> Result<()> {
+    let block_hashes = blocks
+        .range(BlockHeight(0), to + 1)
+        .collect::<Result<Vec<_>>>()?;
+    for block_hash in block_hashes {
+        let block = blocks
+            .get(&block_hash)?
+            .context(format!("Block {block_hash} missing from DA storage"))?;
+        sender.blocking_send(block)?;
+    }
+    Ok(())
+}
+
+struct DaSource {
+    address: String,
+    max_frame_length: usize,
+    timeout: Duration,
+}
+
+/// Splits the heights from genesis to `to` in ranges of at most `DA_CHUNK_SIZE` blocks
+fn da_chunks(to: BlockHeight) -> impl Iterator<Item = (BlockHeight, BlockHeight)> {
+    (0..=to.0)
+        .step_by(DA_CHUNK_SIZE as usize)
+        .map(move |start| {
+            (
+                BlockHeight(start),
+                BlockHeight((start + DA_CHUNK_SIZE - 1).min(to.0)),
+            )
+        })
+}
+
+/// Fetches chunks of blocks in parallel, each from its own DA connection, and forwards them in order
+async fn fetch_from_da(
+    da: DaSource,
+    to: BlockHeight,
+    fetchers: usize,
+    sender: mpsc::Sender<SignedBlock>,
+) -> Result<()> {
+    let mut fetched = futures::stream::iter(da_chunks(to))
+        .map(|(start, end)| fetch_da_chunk(&da, start, end))
+        .buffered(fetchers);
+    while let Some(blocks) = fetched.next().await {
+        for block in blocks? {
+            sender.send(block).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn connect_da(da: &DaSource, start: BlockHeight) -> Result<DataAvailabilityClient> {
+    let mut client = DataAvailabilityClient::connect_with_opts(
+        "reindexer".to_string(),
+        Some(da.max_frame_length),
+        da.address.clone(),
+    )
+    .await
+    .context("Connecting to DA server")?;
+    client.send(DataAvailabilityRequest(start)).await?;
+    Ok(client)
+}
+
+async fn fetch_da_chunk(
+    da: &DaSource,
+    start: BlockHeight,
+    end: BlockHeight,
+) -> Result<Vec<SignedBlock>> {
+    let mut blocks = BTreeMap::new();
+    // First height missing from the chunk, DA doesn't guarantee blocks are sent in order
+    let mut next = start;
+    let mut client = connect_da(da, start).await?;
+
+    while next <= end {
+        match tokio::time::timeout(da.timeout, client.recv()).await {
+            Ok(Some(DataAvailabilityEvent::SignedBlock(block))) => {
+                let height = block.height();
+                if height >= next && height <= end {
+                    blocks.insert(height, block);
+                    while blocks.contains_key(&next) {
+                        next = next + 1;
+                    }
+                }
+                if let Err(e) = client.ping().await {
+                    warn!("Ping failed: {}. Restarting client...", e);
+                    client = connect_da(da, next).await?;
+                }
+            }
+            Ok(Some(DataAvailabilityEvent::MempoolStatusEvent(_))) => {}
+            Ok(None) => {
+                warn!("DA stream connection lost. Reconnecting...");
+                client = connect_da(da, next).await?;
+            }
+            Err(_) => {
+                warn!(
+                    "No blocks received in the last {} seconds, restarting client",
+                    da.timeout.as_secs()
+                );
+                client = connect_da(da, next).await?;
+            }
+        }
+    }
+
+    Ok(blocks.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use hyle_modules::node_state::test::craft_signed_block;
+
+    use super::*;
+    use crate::{explorer::db::with_db, indexer::IndexerConf, utils::conf::Conf};
+
+    fn register_tx(contract_name: &str) -> Transaction {
+        BlobTransaction::new(
+            "hyle@hyle",
+            vec![RegisterContractAction {
+                verifier: "test".into(),
+                program_id: ProgramId(vec![1, 2, 3]),
+                state_commitment: StateCommitment(vec![]),
+                contract_name: contract_name.into(),
+                ..Default::default()
+            }
+            .as_blob("hyle".into(), None, None)],
+        )
+        .into()
+    }
+
+    /// Blocks 0 to 3, each registering a contract
+    fn signed_blocks() -> Vec<SignedBlock> {
+        ["a", "b", "c", "d"]
+            .iter()
+            .enumerate()
+            .map(|(height, name)| craft_signed_block(height as u64, vec![register_tx(name)]))
+            .collect()
+    }
+
+    fn write_blocks(folder: &std::path::Path, blocks: &[SignedBlock]) -> Result<()> {
+        for (index, block) in blocks.iter().enumerate() {
+            std::fs::write(
+                folder.join(format!("{}.bin", block.height())),
+                borsh::to_vec(&(block, index))?,
+            )?;
+        }
+        Ok(())
+    }
+
+    async fn new_reindexer(
+        db: IndexerDb,
+        folder: &std::path::Path,
+        from: u64,
+        to: Option<u64>,
+    ) -> Reindexer {
+        let bus = SharedMessageBus::default();
+        let conf = Conf {
+            da_read_from: format!("folder:{}", folder.display()),
+            indexer: IndexerConf {
+                query_buffer_size: 2,
+                ..IndexerConf::default()
+            },
+            ..Conf::default()
+        };
+        Reindexer {
+            indexer: Indexer {
+                bus: IndexerBusClient::new_from_bus(bus.new_handle()).await,
+                db,
+                node_state: NodeState::create("reindexer".to_string(), "reindexer"),
+                handler_store: IndexerHandlerStore::default(),
+                conf,
+            },
+            conf: ReindexConf {
+                from: BlockHeight(from),
+                to: to.map(BlockHeight),
+                fetchers: 2,
+            },
+            metrics: ReindexMetrics::global("test".to_string()),
+        }
+    }
+
+    async fn indexed(db: &IndexerDb) -> Result<(Vec<(i64, String)>, i64)> {
+        let blocks = with_db!(db, |pool| sqlx::query_as(
+            "SELECT height, hash FROM blocks ORDER BY height"
+        )
+        .fetch_all(pool)
+        .await)?;
+        let txs = with_db!(db, |pool| sqlx::query_scalar(
+            "SELECT COUNT(*) FROM transactions"
+        )
+        .fetch_one(pool)
+        .await)?;
+        Ok((blocks, txs))
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_reindex_from_folder() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let folder = dir.path().join("blocks");
+        std::fs::create_dir(&folder)?;
+        let db = IndexerDb::connect(&format!(
+            "sqlite://{}",
+            dir.path().join("indexer.db").display()
+        ))
+        .await?;
+        db.migrate().await?;
+
+        let blocks = signed_blocks();
+        write_blocks(&folder, &blocks)?;
+
+        // Index the blocks once, as the running indexer would
+        let mut reindexer = new_reindexer(db.clone(), &folder, 0, Some(3)).await;
+        for block in &blocks {
+            let processed = reindexer.indexer.node_state.handle_signed_block(block)?;
+            reindexer.indexer.handle_processed_block(processed)?;
+        }
+        reindexer.indexer.dump_store_to_db().await?;
+        let (indexed_blocks, indexed_txs) = indexed(&db).await?;
+        assert_eq!(indexed_blocks.len(), 4);
+        assert_eq!(indexed_txs, 4);
+
+        // Reindexing the last blocks gives back the same rows
+        new_reindexer(db.clone(), &folder, 2, None)
+            .await
+            .start()
+            .await?;
+        assert_eq!(indexed(&db).await?, (indexed_blocks.clone(), indexed_txs));
+
+        // A source ending before the range leaves the indexed blocks as they were
+        let err = new_reindexer(db.clone(), &folder, 2, Some(5))
+            .await
+            .start()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("before block 5"), "{err:#}");
+        assert_eq!(indexed(&db).await?, (indexed_blocks.clone(), indexed_txs));
+
+        // So does a missing block file
+        std::fs::remove_file(folder.join("1.bin"))?;
+        let err = new_reindexer(db.clone(), &folder, 2, None)
+            .await
+            .start()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Expected block 1"), "{err:#}");
+        assert_eq!(indexed(&db).await?, (indexed_blocks.clone(), indexed_txs));
+        write_blocks(&folder, &blocks)?;
+
+        // And an unreadable block file
+        std::fs::write(folder.join("garbage.bin"), b"not a block")?;
+        let err = new_reindexer(db.clone(), &folder, 2, None)
+            .await
+            .start()
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("garbage.bin"), "{err:#}");
+        assert_eq!(indexed(&db).await?, (indexed_blocks, indexed_txs));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_da_chunks() {
+        assert_eq!(
+            da_chunks(BlockHeight(0)).collect::<Vec<_>>(),
+            vec![(BlockHeight(0), BlockHeight(0))]
+        );
+        assert_eq!(
+            da_chunks(BlockHeight(1000)).collect::<Vec<_>>(),
+            vec![
+                (BlockHeight(0), BlockHeight(499)),
+                (BlockHeight(500), BlockHeight(999)),
+                (BlockHeight(1000), BlockHeight(1000)),
+            ]
+        );
+    }
+}
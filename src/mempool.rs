@@ -9,22 +9,27 @@ use crate::{
     p2p::network::{
         HeaderSignableData, HeaderSigner, IntoHeaderSignableData, MsgWithHeader, OutboundMessage,
     },
-    utils::{conf::SharedConf, serialize::BorshableIndexMap},
+    utils::{
+        conf::{DisseminationMode, SharedConf},
+        serialize::BorshableIndexMap,
+    },
 };
 use anyhow::{bail, Context, Result};
 use api::RestApiMessage;
 use block_construction::BlockUnderConstruction;
 use borsh::{BorshDeserialize, BorshSerialize};
 use client_sdk::tcp_client::TcpServerMessage;
+use erasure::DataProposalShard;
 use hyle_crypto::SharedBlstCrypto;
 use hyle_modules::{bus::BusMessage, log_warn, module_bus_client, utils::static_type_map::Pick};
 use hyle_net::{logged_task::logged_task, ordered_join_set::OrderedJoinSet};
 use indexmap::IndexSet;
 use metrics::MempoolMetrics;
 use serde::{Deserialize, Serialize};
+use shards::{LaneShards, PendingReconstructions};
 use staking::state::Staking;
 use std::{
-    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     fmt::Display,
     ops::{Deref, DerefMut},
     path::PathBuf,
@@ -42,9 +47,11 @@ use tracing::{debug, info, trace};
 
 pub mod api;
 pub mod block_construction;
+pub mod erasure;
 pub mod metrics;
 pub mod module;
 pub mod own_lane;
+pub mod shards;
 pub mod storage;
 pub mod storage_fjall;
 pub mod storage_memory;
@@ -96,6 +103,11 @@ pub struct MempoolStore {
     #[borsh(skip)]
     cached_dp_votes: HashMap<(LaneId, DataProposalHash), DataProposalVerdict>,
 
+    // shards.rs
+    lane_shards: LaneShards,
+    #[borsh(skip)]
+    pending_reconstructions: PendingReconstructions,
+
     // Dedicated thread pool for data proposal and tx hashing
     #[borsh(skip)]
     long_tasks_runtime: LongTasksRuntime,
@@ -196,6 +208,14 @@ pub enum MempoolNetMessage {
     PoDAUpdate(DataProposalHash, Vec<ValidatorDAG>),
     SyncRequest(Option<DataProposalHash>, Option<DataProposalHash>),
     SyncReply(LaneEntryMetadata, DataProposal),
+    DataProposalShard(DataProposalHash, LaneEntryMetadata, DataProposalShard),
+    ShardSyncRequest(LaneId, DataProposalHash),
+    ShardSyncReply(
+        LaneId,
+        DataProposalHash,
+        LaneEntryMetadata,
+        DataProposalShard,
+    ),
 }
 
 /// Validator Data Availability Guarantee
@@ -235,6 +255,33 @@ impl IntoHeaderSignableData for MempoolNetMessage {
                 ];
                 HeaderSignableData(hash.concat())
             }
+            // The chunk itself is checked against the signed commitments
+            MempoolNetMessage::DataProposalShard(hash, metadata, shard) => {
+                let hash = [
+                    hash.0.clone().into_bytes(),
+                    borsh::to_vec(&metadata).unwrap_or_default(),
+                    borsh::to_vec(&(shard.index, &shard.body_hash, &shard.commitments))
+                        .unwrap_or_default(),
+                ];
+                HeaderSignableData(hash.concat())
+            }
+            MempoolNetMessage::ShardSyncRequest(lane_id, hash) => HeaderSignableData(
+                [
+                    borsh::to_vec(&lane_id).unwrap_or_default(),
+                    hash.0.clone().into_bytes(),
+                ]
+                .concat(),
+            ),
+            MempoolNetMessage::ShardSyncReply(lane_id, hash, metadata, shard) => {
+                let hash = [
+                    borsh::to_vec(&lane_id).unwrap_or_default(),
+                    hash.0.clone().into_bytes(),
+                    borsh::to_vec(&metadata).unwrap_or_default(),
+                    borsh::to_vec(&(shard.index, &shard.body_hash, &shard.commitments))
+                        .unwrap_or_default(),
+                ];
+                HeaderSignableData(hash.concat())
+            }
         }
     }
 }
@@ -243,6 +290,7 @@ impl IntoHeaderSignableData for MempoolNetMessage {
 pub enum ProcessedDPEvent {
     OnHashedDataProposal((LaneId, DataProposal)),
     OnProcessedDataProposal((LaneId, DataProposalVerdict, DataProposal)),
+    OnReconstructedDataProposal((LaneId, usize, LaneEntryMetadata, DataProposal)),
 }
 
 impl Mempool {
@@ -275,16 +323,34 @@ impl Mempool {
             .map(|ccp| ccp.consensus_proposal.cut.clone())
             .unwrap_or_default();
 
+        // Lanes we only hold shards of can still be put in the cut
+        let lane_ids: BTreeSet<&LaneId> = self
+            .lanes
+            .get_lane_ids()
+            .chain(self.lane_shards.get_lane_ids())
+            .collect();
+
         // For each lane, we get the last CAR and put it in the cut
         let mut cut: Cut = vec![];
-        for lane_id in self.lanes.get_lane_ids() {
+        for lane_id in lane_ids {
             let previous_entry = previous_cut
                 .iter()
                 .find(|(lane_id_, _, _, _)| lane_id_ == lane_id);
-            if let Some((dp_hash, cumul_size, poda)) =
-                self.lanes
+            let mut car = self
+                .lanes
+                .get_latest_car(lane_id, &staking.0, previous_entry)?;
+            if let Some(shard_car) =
+                self.lane_shards
                     .get_latest_car(lane_id, &staking.0, previous_entry)?
             {
+                let previous_size = previous_entry.map(|(_, _, size, _)| *size);
+                if car.as_ref().is_none_or(|(_, size, _)| shard_car.1 > *size)
+                    && previous_size.is_none_or(|size| shard_car.1 >= size)
+                {
+                    car = Some(shard_car);
+                }
+            }
+            if let Some((dp_hash, cumul_size, poda)) = car {
                 cut.push((lane_id.clone(), dp_hash, cumul_size, poda));
             } else if let Some(lane) = previous_entry {
                 cut.push(lane.clone());
@@ -293,7 +359,7 @@ impl Mempool {
         Ok(cut)
     }
 
-    fn handle_internal_event(&mut self, event: ProcessedDPEvent) -> Result<()> {
+    async fn handle_internal_event(&mut self, event: ProcessedDPEvent) -> Result<()> {
         match event {
             ProcessedDPEvent::OnHashedDataProposal((lane_id, data_proposal)) => self
                 .on_hashed_data_proposal(&lane_id, data_proposal)
@@ -301,6 +367,20 @@ impl Mempool {
             ProcessedDPEvent::OnProcessedDataProposal((lane_id, verdict, data_proposal)) => self
                 .on_processed_data_proposal(lane_id, verdict, data_proposal)
                 .context("Processing data proposal"),
+            ProcessedDPEvent::OnReconstructedDataProposal((
+                lane_id,
+                dropped_txs,
+                metadata,
+                data_proposal,
+            )) => self
+                .on_verified_reconstructed_data_proposal(
+                    lane_id,
+                    dropped_txs,
+                    metadata,
+                    data_proposal,
+                )
+                .await
+                .context("Storing reconstructed data proposal"),
         }
     }
 
@@ -372,6 +452,17 @@ impl Mempool {
                 self.on_sync_reply(validator, metadata, data_proposal)
                     .await?;
             }
+            MempoolNetMessage::DataProposalShard(data_proposal_hash, metadata, shard) => {
+                let lane_id = self.get_lane(validator);
+                self.on_data_proposal_shard(&lane_id, data_proposal_hash, metadata, shard)?;
+            }
+            MempoolNetMessage::ShardSyncRequest(lane_id, data_proposal_hash) => {
+                self.on_shard_sync_request(validator, lane_id, data_proposal_hash)?;
+            }
+            MempoolNetMessage::ShardSyncReply(lane_id, data_proposal_hash, metadata, shard) => {
+                self.on_shard_sync_reply(validator, lane_id, data_proposal_hash, metadata, shard)
+                    .await?;
+            }
         }
         Ok(())
     }
@@ -493,6 +584,14 @@ impl Mempool {
             lane_id
         );
 
+        if self.lane_shards.contains(lane_id, data_proposal_hash) {
+            self.lane_shards
+                .add_signatures(lane_id, data_proposal_hash, podas.clone())?;
+            if !self.lanes.contains(lane_id, data_proposal_hash) {
+                return Ok(());
+            }
+        }
+
         if log_warn!(
             self.lanes
                 .add_signatures(lane_id, data_proposal_hash, podas.clone()),
@@ -534,6 +633,12 @@ impl Mempool {
         from_data_proposal_hash: Option<&DataProposalHash>,
         to_data_proposal_hash: Option<&DataProposalHash>,
     ) -> Result<()> {
+        if self.conf.mempool.dissemination == DisseminationMode::ErasureCoded {
+            if let Some(to_data_proposal_hash) = to_data_proposal_hash {
+                return self.send_shard_sync_request(lane_id, to_data_proposal_hash);
+            }
+        }
+
         // TODO: use a more clever targeting system.
         let validator = &lane_id.0;
         debug!(
@@ -684,6 +789,18 @@ pub mod test {
             }
         }
 
+        pub fn set_dissemination_mode(&mut self, mode: DisseminationMode) {
+            let mut conf = (*self.mempool.conf).clone();
+            conf.mempool.dissemination = mode;
+            self.mempool.conf = Arc::new(conf);
+        }
+
+        pub fn request_shard_sync(&mut self, lane_id: &LaneId, dp_hash: &DataProposalHash) {
+            self.mempool
+                .send_shard_sync_request(lane_id, dp_hash)
+                .expect("should send shard sync request");
+        }
+
         pub fn own_lane(&self) -> LaneId {
             self.mempool
                 .get_lane(self.mempool.crypto.validator_pubkey())
@@ -771,6 +888,7 @@ pub mod test {
                 .expect("No event received");
             self.mempool
                 .handle_internal_event(event)
+                .await
                 .expect("fail to handle event");
         }
 
@@ -939,6 +1057,10 @@ pub mod test {
             ((last_metadata, last_dp), last_dp_hash.clone())
         }
 
+        pub fn get_dp(&self, lane_id: &LaneId, dp_hash: &DataProposalHash) -> Option<DataProposal> {
+            self.mempool.lanes.get_dp_by_hash(lane_id, dp_hash).unwrap()
+        }
+
         pub fn current_size(&self) -> Option<LaneBytesSize> {
             let lane_id = LaneId(self.validator_pubkey().clone());
             self.current_size_of(&lane_id)
@@ -989,7 +1111,15 @@ pub mod test {
             parent_hash: Option<DataProposalHash>,
             txs: &[Transaction],
         ) -> DataProposal {
-            DataProposal::new(parent_hash, txs.to_vec())
+            let dp = DataProposal::new(parent_hash, txs.to_vec());
+            if self.mempool.conf.mempool.dissemination != DisseminationMode::ErasureCoded {
+                return dp;
+            }
+            let chunk_root =
+                erasure::ErasureCoder::for_validators(self.mempool.staking.bonded().len())
+                    .and_then(|coder| coder.chunk_root(&dp))
+                    .expect("compute chunk root");
+            dp.with_chunk_root(chunk_root)
         }
 
         pub fn create_data_proposal_on_top(
@@ -1033,6 +1163,7 @@ pub mod test {
                             staking_actions: vec![],
                             timestamp: TimestampMs(777),
                             parent_hash: ConsensusProposalHash("test".to_string()),
+                            timed_out_leaders: vec![],
                         },
                         certificate: AggregateSignature::default(),
                     },
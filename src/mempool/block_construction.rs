@@ -139,6 +139,11 @@ impl super::Mempool {
                 consensus_proposal: buc.ccp.consensus_proposal.clone(),
             }))?;
 
+        // Lagging validators may still need our shards to build the block
+        self.inner
+            .lane_shards
+            .on_built_block(&buc.ccp.consensus_proposal.cut);
+
         Ok(())
     }
 
@@ -455,6 +460,7 @@ pub mod test {
             staking_actions: vec![],
             timestamp: TimestampMs(0),
             parent_hash: ConsensusProposalHash("test".to_string()),
+            timed_out_leaders: vec![],
         };
 
         // Add the block to mempool 1
@@ -564,6 +570,7 @@ pub mod test {
             staking_actions: vec![],
             timestamp: TimestampMs(0),
             parent_hash: ConsensusProposalHash("test".to_string()),
+            timed_out_leaders: vec![],
         };
 
         // Add the block to the mempool
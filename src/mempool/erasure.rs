@@ -0,0 +1,438 @@
+//! Reed-Solomon erasure coding of data proposals, used by the erasure-coded dissemination mode.
+//!
+//! A DataProposal is borsh-encoded and split into `f + 1` data chunks, which are then extended
+//! with parity chunks so that there is one chunk per bonded validator.
+//! Any `f + 1` chunks are enough to rebuild the DataProposal.
+//! Chunks are evaluations of a polynomial over GF(2^8), at the index of the shard.
+//!
+//! Only the parent and transactions of the DataProposal are encoded. The root of the chunk
+//! commitments is then part of the DataProposal hash, so that a validator can check its shard
+//! belongs to the encoding the DataProposal hash commits to before voting.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+use crate::model::{DataProposal, DataProposalHash, Hashed, Transaction};
+
+/// Hash of a single chunk.
+pub type ChunkCommitment = [u8; 32];
+/// Hash of the commitments of all chunks of a DataProposal.
+pub type ChunkRoot = [u8; 32];
+
+/// Shard of a DataProposal, sent to a single validator.
+/// Each shard carries the commitments of all chunks, so that any chunk can be checked
+/// before being used for a reconstruction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct DataProposalShard {
+    /// Index of the shard, which is also the index of the validator in the bonded set.
+    pub index: u16,
+    /// Number of shards required to reconstruct the DataProposal.
+    pub data_shards: u16,
+    /// Size of the encoded DataProposal, used to remove padding after reconstruction.
+    pub payload_len: u64,
+    /// Hash of the DataProposal without its chunk root.
+    pub body_hash: DataProposalHash,
+    pub chunk: Vec<u8>,
+    pub commitments: Vec<ChunkCommitment>,
+}
+
+impl DataProposalShard {
+    pub fn total_shards(&self) -> usize {
+        self.commitments.len()
+    }
+
+    pub fn chunk_root(&self) -> ChunkRoot {
+        chunk_root(&self.commitments)
+    }
+
+    /// Hash of the DataProposal this shard is bound to.
+    pub fn data_proposal_hash(&self) -> DataProposalHash {
+        DataProposal::hash_with_chunk_root(&self.body_hash, &self.chunk_root())
+    }
+
+    /// Checks the chunk against its commitment.
+    pub fn verify(&self) -> Result<()> {
+        if self.data_shards == 0 || self.data_shards as usize > self.total_shards() {
+            bail!(
+                "Invalid shard parameters: {} data shards out of {}",
+                self.data_shards,
+                self.total_shards()
+            );
+        }
+        if self.payload_len > (self.chunk.len() * self.data_shards as usize) as u64 {
+            bail!(
+                "Shard chunk of {} bytes is too small for a payload of {} bytes",
+                self.chunk.len(),
+                self.payload_len
+            );
+        }
+        let Some(expected) = self.commitments.get(self.index as usize) else {
+            bail!(
+                "Shard index {} out of bounds ({} shards)",
+                self.index,
+                self.total_shards()
+            );
+        };
+        if &chunk_commitment(&self.chunk) != expected {
+            bail!("Shard {} does not match its commitment", self.index);
+        }
+        Ok(())
+    }
+}
+
+pub struct ErasureCoder {
+    data_shards: usize,
+    total_shards: usize,
+}
+
+impl ErasureCoder {
+    /// One shard per validator, any f+1 of them rebuild the DataProposal.
+    pub fn for_validators(nb_validators: usize) -> Result<Self> {
+        if nb_validators == 0 || nb_validators > 256 {
+            bail!("Can't erasure-code a DataProposal for {nb_validators} validators");
+        }
+        Ok(Self {
+            data_shards: (nb_validators - 1) / 3 + 1,
+            total_shards: nb_validators,
+        })
+    }
+
+    pub fn data_shards(&self) -> usize {
+        self.data_shards
+    }
+
+    /// Root of the chunks of the DataProposal, to bind it to its encoding with [DataProposal::with_chunk_root].
+    pub fn chunk_root(&self, data_proposal: &DataProposal) -> Result<ChunkRoot> {
+        match self.encode(data_proposal)?.first() {
+            Some(shard) => Ok(shard.chunk_root()),
+            None => bail!("No shard for DataProposal {}", data_proposal.hashed()),
+        }
+    }
+
+    pub fn encode(&self, data_proposal: &DataProposal) -> Result<Vec<DataProposalShard>> {
+        let payload =
+            borsh::to_vec(&(&data_proposal.parent_data_proposal_hash, &data_proposal.txs))
+                .context("Encoding DataProposal")?;
+        let body_hash = data_proposal.body_hash();
+        let chunk_len = payload.len().div_ceil(self.data_shards).max(1);
+
+        let mut chunks: Vec<Vec<u8>> = payload
+            .chunks(chunk_len)
+            .map(|chunk| {
+                let mut chunk = chunk.to_vec();
+                chunk.resize(chunk_len, 0);
+                chunk
+            })
+            .collect();
+        chunks.resize(self.data_shards, vec![0; chunk_len]);
+
+        let data_points: Vec<u8> = (0..self.data_shards).map(|x| x as u8).collect();
+        let parity_chunks: Vec<Vec<u8>> = (self.data_shards..self.total_shards)
+            .map(|x| {
+                let coefficients = lagrange_coefficients(&data_points, x as u8);
+                combine(&coefficients, chunks.iter(), chunk_len)
+            })
+            .collect();
+        chunks.extend(parity_chunks);
+
+        let commitments: Vec<ChunkCommitment> =
+            chunks.iter().map(|chunk| chunk_commitment(chunk)).collect();
+
+        Ok(chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, chunk)| DataProposalShard {
+                index: index as u16,
+                data_shards: self.data_shards as u16,
+                payload_len: payload.len() as u64,
+                body_hash: body_hash.clone(),
+                chunk,
+                commitments: commitments.clone(),
+            })
+            .collect())
+    }
+
+    /// Rebuilds a DataProposal from any f+1 valid shards sharing the same commitments, and binds
+    /// it to their root. Fails if not enough shards are available, or if the result does not
+    /// match the expected hash.
+    pub fn reconstruct(
+        shards: &[DataProposalShard],
+        expected_hash: &DataProposalHash,
+    ) -> Result<DataProposal> {
+        // A faulty lane operator could have sent different commitments to different validators,
+        // so only shards committing to the same chunks are combined together.
+        let mut by_commitments: BTreeMap<&Vec<ChunkCommitment>, Vec<&DataProposalShard>> =
+            BTreeMap::new();
+        for shard in shards {
+            if shard.verify().is_err() {
+                continue;
+            }
+            let group = by_commitments.entry(&shard.commitments).or_default();
+            if group.iter().any(|s| s.index == shard.index) {
+                continue;
+            }
+            group.push(shard);
+        }
+
+        for group in by_commitments.into_values() {
+            let Some(first) = group.first() else {
+                continue;
+            };
+            let data_shards = first.data_shards as usize;
+            let selected: Vec<&DataProposalShard> = group
+                .iter()
+                .filter(|s| {
+                    s.data_shards == first.data_shards
+                        && s.payload_len == first.payload_len
+                        && s.chunk.len() == first.chunk.len()
+                })
+                .take(data_shards)
+                .copied()
+                .collect();
+            if selected.len() < data_shards {
+                continue;
+            }
+
+            if let Ok(data_proposal) = Self::decode(&selected) {
+                if &data_proposal.hashed() == expected_hash {
+                    return Ok(data_proposal);
+                }
+            }
+        }
+
+        bail!("Not enough valid shards to reconstruct DataProposal {expected_hash}")
+    }
+
+    fn decode(shards: &[&DataProposalShard]) -> Result<DataProposal> {
+        let Some(first) = shards.first() else {
+            bail!("No shard to decode");
+        };
+        let chunk_len = first.chunk.len();
+        let points: Vec<u8> = shards.iter().map(|s| s.index as u8).collect();
+
+        let mut payload = Vec::with_capacity(first.data_shards as usize * chunk_len);
+        for x in 0..first.data_shards {
+            match shards.iter().find(|s| s.index == x) {
+                Some(shard) => payload.extend_from_slice(&shard.chunk),
+                None => {
+                    let coefficients = lagrange_coefficients(&points, x as u8);
+                    payload.extend(combine(
+                        &coefficients,
+                        shards.iter().map(|s| &s.chunk),
+                        chunk_len,
+                    ));
+                }
+            }
+        }
+        payload.truncate(first.payload_len as usize);
+
+        let (parent_data_proposal_hash, txs): (Option<DataProposalHash>, Vec<Transaction>) =
+            borsh::from_slice(&payload).context("Decoding reconstructed DataProposal")?;
+        Ok(DataProposal::new(parent_data_proposal_hash, txs).with_chunk_root(first.chunk_root()))
+    }
+}
+
+fn chunk_commitment(chunk: &[u8]) -> ChunkCommitment {
+    let mut commitment = ChunkCommitment::default();
+    commitment.copy_from_slice(&Sha3_256::digest(chunk));
+    commitment
+}
+
+fn chunk_root(commitments: &[ChunkCommitment]) -> ChunkRoot {
+    let mut root = ChunkRoot::default();
+    root.copy_from_slice(&Sha3_256::digest(commitments.concat()));
+    root
+}
+
+/// Coefficients of the Lagrange basis polynomials defined on `points`, evaluated at `x`.
+fn lagrange_coefficients(points: &[u8], x: u8) -> Vec<u8> {
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, &xi)| {
+            let (num, den) = points.iter().enumerate().filter(|(m, _)| *m != i).fold(
+                (1, 1),
+                |(num, den), (_, &xm)| {
+                    // Subtraction is a XOR in GF(2^8)
+                    (gf_mul(num, x ^ xm), gf_mul(den, xi ^ xm))
+                },
+            );
+            gf_div(num, den)
+        })
+        .collect()
+}
+
+/// Linear combination of chunks with the given coefficients.
+fn combine<'a>(
+    coefficients: &[u8],
+    chunks: impl Iterator<Item = &'a Vec<u8>>,
+    chunk_len: usize,
+) -> Vec<u8> {
+    let mut out = vec![0; chunk_len];
+    for (&coefficient, chunk) in coefficients.iter().zip(chunks) {
+        if coefficient == 0 {
+            continue;
+        }
+        for (o, &b) in out.iter_mut().zip(chunk.iter()) {
+            *o ^= gf_mul(coefficient, b);
+        }
+    }
+    out
+}
+
+/// Exponential and logarithm tables of GF(2^8), with the 0x11d reduction polynomial.
+/// The exponential table is doubled to skip a modulo when multiplying.
+const GF_TABLES: ([u8; 512], [u8; 256]) = gf_tables();
+
+#[allow(
+    clippy::indexing_slicing,
+    reason = "all indices are bounded by the loops"
+)]
+const fn gf_tables() -> ([u8; 512], [u8; 256]) {
+    let mut exp = [0u8; 512];
+    let mut log = [0u8; 256];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = x as u8;
+        log[x as usize] = i as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= 0x11d;
+        }
+        i += 1;
+    }
+    while i < 512 {
+        exp[i] = exp[i - 255];
+        i += 1;
+    }
+    (exp, log)
+}
+
+#[allow(clippy::indexing_slicing, reason = "log values are < 255")]
+fn gf_mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    let (exp, log) = &GF_TABLES;
+    exp[log[a as usize] as usize + log[b as usize] as usize]
+}
+
+#[allow(clippy::indexing_slicing, reason = "log values are < 255")]
+fn gf_div(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        // Never divide by zero: points used for interpolation are always distinct
+        return 0;
+    }
+    let (exp, log) = &GF_TABLES;
+    exp[log[a as usize] as usize + 255 - log[b as usize] as usize]
+}
+
+#[allow(clippy::indexing_slicing)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mempool::test::make_register_contract_tx;
+    use crate::model::ContractName;
+
+    fn data_proposal(coder: &ErasureCoder) -> DataProposal {
+        let dp = DataProposal::new(
+            None,
+            (0..10)
+                .map(|i| make_register_contract_tx(ContractName::new(format!("contract-{i}"))))
+                .collect(),
+        );
+        let chunk_root = coder.chunk_root(&dp).unwrap();
+        dp.with_chunk_root(chunk_root)
+    }
+
+    #[test]
+    fn test_gf_arithmetic() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, 1), a);
+            assert_eq!(gf_div(gf_mul(a, 7), 7), a);
+            assert_eq!(gf_mul(a, gf_div(1, a)), 1);
+        }
+    }
+
+    #[test]
+    fn test_reconstruct_from_any_f_plus_one_shards() {
+        let coder = ErasureCoder::for_validators(7).unwrap();
+        let dp = data_proposal(&coder);
+        assert_eq!(coder.data_shards(), 3);
+
+        let shards = coder.encode(&dp).unwrap();
+        assert_eq!(shards.len(), 7);
+        for shard in &shards {
+            shard.verify().unwrap();
+            assert_eq!(shard.data_proposal_hash(), dp.hashed());
+        }
+
+        for subset in [[0, 1, 2], [4, 5, 6], [0, 3, 6], [6, 2, 4]] {
+            let selected: Vec<DataProposalShard> = subset
+                .iter()
+                .map(|i| shards.get(*i).unwrap().clone())
+                .collect();
+            let rebuilt = ErasureCoder::reconstruct(&selected, &dp.hashed()).unwrap();
+            assert_eq!(rebuilt.txs, dp.txs);
+            assert_eq!(rebuilt.chunk_root, dp.chunk_root);
+        }
+    }
+
+    #[test]
+    fn test_reconstruct_needs_enough_valid_shards() {
+        let coder = ErasureCoder::for_validators(4).unwrap();
+        let dp = data_proposal(&coder);
+        let mut shards = coder.encode(&dp).unwrap();
+
+        // f + 1 = 2 shards needed
+        assert!(ErasureCoder::reconstruct(&shards[0..1], &dp.hashed()).is_err());
+
+        // Corrupted shards are skipped
+        shards.get_mut(0).unwrap().chunk[0] ^= 1;
+        assert!(ErasureCoder::reconstruct(&shards[0..2], &dp.hashed()).is_err());
+        assert!(ErasureCoder::reconstruct(&shards, &dp.hashed()).is_ok());
+
+        // Wrong expected hash
+        assert!(
+            ErasureCoder::reconstruct(&shards, &DataProposalHash("wrong".to_string())).is_err()
+        );
+    }
+
+    #[test]
+    fn test_single_validator() {
+        let coder = ErasureCoder::for_validators(1).unwrap();
+        let dp = data_proposal(&coder);
+        let shards = coder.encode(&dp).unwrap();
+        assert_eq!(shards.len(), 1);
+        let rebuilt = ErasureCoder::reconstruct(&shards, &dp.hashed()).unwrap();
+        assert_eq!(rebuilt.txs, dp.txs);
+    }
+
+    #[test]
+    fn test_shards_are_bound_to_the_data_proposal_hash() {
+        let coder = ErasureCoder::for_validators(4).unwrap();
+        let dp = data_proposal(&coder);
+        let unbound = DataProposal::new(None, dp.txs.clone());
+
+        // Without its chunk root, the DataProposal hash does not match the shards
+        let shards = coder.encode(&unbound).unwrap();
+        assert_ne!(unbound.hashed(), dp.hashed());
+        assert!(shards.iter().all(|s| s.data_proposal_hash() == dp.hashed()));
+        assert!(ErasureCoder::reconstruct(&shards, &unbound.hashed()).is_err());
+
+        // Shards of another encoding are bound to another hash
+        let other = data_proposal(&ErasureCoder::for_validators(7).unwrap());
+        let other_shards = ErasureCoder::for_validators(7)
+            .unwrap()
+            .encode(&other)
+            .unwrap();
+        assert!(other_shards
+            .iter()
+            .all(|s| s.data_proposal_hash() != dp.hashed()));
+    }
+}
@@ -21,10 +21,14 @@ pub struct MempoolMetrics {
     received_dp: Counter<u64>,
     hashed_dp: Counter<u64>,
     processed_dp: Counter<u64>,
+    received_shard: Counter<u64>,
+    reconstructed_dp: Counter<u64>,
     pub constructed_block: Counter<u64>,
     pub on_data_vote: Counter<u64>,
     // Number of individual DPs sent (counting one per validator)
     pub dp_disseminations: Counter<u64>,
+    // Number of individual DP shards sent (counting one per validator)
+    pub shard_disseminations: Counter<u64>,
     pub created_data_proposals: Counter<u64>,
 }
 
@@ -57,6 +61,12 @@ impl MempoolMetrics {
             processed_dp: my_meter
                 .u64_counter(format!("{mempool}_processed_dp"))
                 .build(),
+            received_shard: my_meter
+                .u64_counter(format!("{mempool}_received_shard"))
+                .build(),
+            reconstructed_dp: my_meter
+                .u64_counter(format!("{mempool}_reconstructed_dp"))
+                .build(),
             constructed_block: my_meter
                 .u64_counter(format!("{mempool}_constructed_block"))
                 .build(),
@@ -66,6 +76,9 @@ impl MempoolMetrics {
             dp_disseminations: my_meter
                 .u64_counter(format!("{mempool}_dp_disseminations"))
                 .build(),
+            shard_disseminations: my_meter
+                .u64_counter(format!("{mempool}_shard_disseminations"))
+                .build(),
             created_data_proposals: my_meter
                 .u64_counter(format!("{mempool}_created_data_proposals"))
                 .build(),
@@ -121,6 +134,16 @@ impl MempoolMetrics {
             .add(1, &[KeyValue::new("lane_id", format!("{lane_id}"))])
     }
 
+    pub fn add_received_shard(&self, lane_id: &LaneId) {
+        self.received_shard
+            .add(1, &[KeyValue::new("lane_id", format!("{lane_id}"))])
+    }
+
+    pub fn add_reconstructed_dp(&self, lane_id: &LaneId) {
+        self.reconstructed_dp
+            .add(1, &[KeyValue::new("lane_id", format!("{lane_id}"))])
+    }
+
     pub fn add_hashed_dp(&self, lane_id: &LaneId) {
         self.hashed_dp
             .add(1, &[KeyValue::new("lane_id", format!("{lane_id}"))])
@@ -1,14 +1,16 @@
 //! Logic for processing the API inbound TXs in the mempool.
 
 use crate::mempool::storage::MetadataOrMissingHash;
+use crate::utils::conf::DisseminationMode;
 use crate::{bus::BusClientSender, model::*};
 
 use anyhow::{bail, Context, Result};
 use client_sdk::tcp_client::TcpServerMessage;
 use futures::StreamExt;
 use std::collections::HashSet;
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
 
+use super::erasure::ErasureCoder;
 use super::storage::LaneEntryMetadata;
 use super::verifiers::{verify_proof, verify_recursive_proof};
 use super::{api::RestApiMessage, storage::Storage};
@@ -77,9 +79,28 @@ impl super::Mempool {
 
         let handle = self.inner.long_tasks_runtime.handle();
 
-        self.inner
-            .own_data_proposal_in_preparation
-            .spawn_on(async move { (dp.hashed(), dp) }, handle);
+        // Shards are bound to the DataProposal through its hash
+        let coder = match self.conf.mempool.dissemination {
+            DisseminationMode::ErasureCoded => {
+                ErasureCoder::for_validators(self.staking.bonded().len()).ok()
+            }
+            DisseminationMode::Full => None,
+        };
+
+        self.inner.own_data_proposal_in_preparation.spawn_on(
+            async move {
+                let dp = match coder.map(|coder| coder.chunk_root(&dp)) {
+                    Some(Ok(chunk_root)) => dp.with_chunk_root(chunk_root),
+                    Some(Err(e)) => {
+                        warn!("Could not bind DataProposal to its shards: {:#}", e);
+                        dp
+                    }
+                    None => dp,
+                };
+                (dp.hashed(), dp)
+            },
+            handle,
+        );
 
         Ok(true)
     }
@@ -169,6 +190,14 @@ impl super::Mempool {
         entry_metadata: &LaneEntryMetadata,
         dp_hash: &DataProposalHash,
     ) -> Result<bool> {
+        if self.conf.mempool.dissemination == DisseminationMode::ErasureCoded {
+            if let Some(disseminated) =
+                self.disseminate_data_proposal_shards(entry_metadata, dp_hash)?
+            {
+                return Ok(disseminated);
+            }
+        }
+
         // If there's only 1 signature (=own signature), broadcast it to everyone
         let there_are_other_validators = !self.staking.is_bonded(self.crypto.validator_pubkey())
             || self.staking.bonded().len() >= 2;
@@ -0,0 +1,882 @@
+//! Erasure-coded dissemination of data proposals.
+//!
+//! Instead of broadcasting full DataProposals, the lane operator sends each bonded validator
+//! its own shard. Validators store and vote on their shard, and reconstruct the DataProposal
+//! from f+1 shards fetched from other validators once it is committed.
+//! The DataProposal hash commits to the root of its chunks, so votes are bound to one encoding.
+//! Votes only attest that the shards are available: proofs are verified once the DataProposal is
+//! reconstructed, and invalid proof transactions are dropped from its execution rather than
+//! refusing a DataProposal that is already committed.
+//! Shards are kept for [SHARD_RETENTION_BLOCKS] built blocks, so that lagging validators can
+//! still reconstruct the DataProposals they commit.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+use hyle_crypto::BlstCrypto;
+use staking::state::Staking;
+use tracing::{debug, trace, warn};
+
+use crate::model::*;
+
+use super::{
+    erasure::{DataProposalShard, ErasureCoder},
+    storage::{latest_car_from_tip, LaneEntryMetadata, Storage},
+    MempoolNetMessage, ProcessedDPEvent, ValidatorDAG,
+};
+
+/// Maximum number of DataProposals being reconstructed at once, the oldest requests are dropped first.
+const MAX_PENDING_RECONSTRUCTIONS: usize = 1_000;
+/// Reconstructions still missing shards after this delay are dropped until requested again.
+const PENDING_RECONSTRUCTION_TIMEOUT: Duration = Duration::from_secs(60);
+/// Number of built blocks the shards of the DataProposals they commit are kept for.
+const SHARD_RETENTION_BLOCKS: usize = 100;
+
+/// Shards received for a DataProposal we asked to reconstruct.
+pub struct PendingReconstruction {
+    requested_at: Instant,
+    shards: Vec<DataProposalShard>,
+}
+
+/// DataProposals being reconstructed, by lane and hash.
+pub type PendingReconstructions = HashMap<(LaneId, DataProposalHash), PendingReconstruction>;
+
+/// Shards stored by this validator for lanes it does not operate.
+#[derive(Default, BorshSerialize, BorshDeserialize)]
+pub struct LaneShards {
+    lanes_tip: BTreeMap<LaneId, (DataProposalHash, LaneBytesSize)>,
+    by_hash: BTreeMap<LaneId, BTreeMap<DataProposalHash, (LaneEntryMetadata, DataProposalShard)>>,
+    /// Lane tips committed by the last built blocks, oldest first
+    built_cuts: VecDeque<Vec<(LaneId, DataProposalHash)>>,
+}
+
+impl LaneShards {
+    pub fn contains(&self, lane_id: &LaneId, dp_hash: &DataProposalHash) -> bool {
+        self.get(lane_id, dp_hash).is_some()
+    }
+
+    pub fn get(
+        &self,
+        lane_id: &LaneId,
+        dp_hash: &DataProposalHash,
+    ) -> Option<&(LaneEntryMetadata, DataProposalShard)> {
+        self.by_hash.get(lane_id).and_then(|lane| lane.get(dp_hash))
+    }
+
+    pub fn get_lane_ids(&self) -> impl Iterator<Item = &LaneId> {
+        self.lanes_tip.keys()
+    }
+
+    /// Stores a shard, and moves the lane tip if the shard is on top of it.
+    pub fn put(
+        &mut self,
+        lane_id: LaneId,
+        dp_hash: DataProposalHash,
+        metadata: LaneEntryMetadata,
+        shard: DataProposalShard,
+    ) {
+        let tip = self.lanes_tip.get(&lane_id).map(|(hash, _)| hash);
+        if tip.is_none() || tip == metadata.parent_data_proposal_hash.as_ref() {
+            self.lanes_tip
+                .insert(lane_id.clone(), (dp_hash.clone(), metadata.cumul_size));
+        }
+        self.by_hash
+            .entry(lane_id)
+            .or_default()
+            .insert(dp_hash, (metadata, shard));
+    }
+
+    pub fn add_signatures<T: IntoIterator<Item = ValidatorDAG>>(
+        &mut self,
+        lane_id: &LaneId,
+        dp_hash: &DataProposalHash,
+        vote_msgs: T,
+    ) -> Result<Vec<ValidatorDAG>> {
+        let Some((metadata, _)) = self
+            .by_hash
+            .get_mut(lane_id)
+            .and_then(|lane| lane.get_mut(dp_hash))
+        else {
+            bail!("Can't find shard {} for lane {}", dp_hash, lane_id);
+        };
+
+        for msg in vote_msgs {
+            let (dph, cumul_size) = &msg.msg;
+            if &metadata.cumul_size != cumul_size || dp_hash != dph {
+                warn!(
+                    "Received a DataVote message with wrong hash or size: {:?}",
+                    msg.msg
+                );
+                continue;
+            }
+            if let Err(pos) = metadata
+                .signatures
+                .binary_search_by(|probe| probe.signature.cmp(&msg.signature))
+            {
+                metadata.signatures.insert(pos, msg);
+            }
+        }
+        Ok(metadata.signatures.clone())
+    }
+
+    pub fn get_latest_car(
+        &self,
+        lane_id: &LaneId,
+        staking: &Staking,
+        previous_committed_car: Option<&(LaneId, DataProposalHash, LaneBytesSize, PoDA)>,
+    ) -> Result<Option<(DataProposalHash, LaneBytesSize, PoDA)>> {
+        latest_car_from_tip(
+            lane_id,
+            self.lanes_tip.get(lane_id).map(|(hash, _)| hash),
+            |dp_hash| {
+                Ok(self
+                    .get(lane_id, dp_hash)
+                    .map(|(metadata, _)| metadata.clone()))
+            },
+            staking,
+            previous_committed_car,
+        )
+    }
+
+    /// Records the cut of a built block, and removes the shards of the DataProposals committed
+    /// [SHARD_RETENTION_BLOCKS] built blocks ago.
+    pub fn on_built_block(&mut self, cut: &Cut) {
+        self.built_cuts.push_back(
+            cut.iter()
+                .map(|(lane_id, dp_hash, _, _)| (lane_id.clone(), dp_hash.clone()))
+                .collect(),
+        );
+        while self.built_cuts.len() > SHARD_RETENTION_BLOCKS {
+            for (lane_id, dp_hash) in self.built_cuts.pop_front().unwrap_or_default() {
+                self.prune(&lane_id, &dp_hash);
+            }
+        }
+    }
+
+    /// Removes the shards of a committed DataProposal and of all its ancestors.
+    fn prune(&mut self, lane_id: &LaneId, committed_dp_hash: &DataProposalHash) {
+        let Some(lane) = self.by_hash.get_mut(lane_id) else {
+            return;
+        };
+        let mut next = Some(committed_dp_hash.clone());
+        while let Some(dp_hash) = next.take() {
+            next = lane
+                .remove(&dp_hash)
+                .and_then(|(metadata, _)| metadata.parent_data_proposal_hash);
+        }
+    }
+}
+
+impl super::Mempool {
+    fn own_shard_index(&self) -> Option<usize> {
+        self.staking
+            .bonded()
+            .iter()
+            .position(|v| v == self.crypto.validator_pubkey())
+    }
+
+    /// Checks the lane operator signed the entry, and the shard matches its commitment
+    /// and is bound to the DataProposal hash.
+    fn check_shard_entry(
+        &self,
+        lane_id: &LaneId,
+        dp_hash: &DataProposalHash,
+        metadata: &LaneEntryMetadata,
+        shard: &DataProposalShard,
+    ) -> Result<()> {
+        let lane_operator = self.get_lane_operator(lane_id);
+        let expected_message = (dp_hash.clone(), metadata.cumul_size);
+        let signed_by_operator = metadata.signatures.iter().any(|s| {
+            &s.signature.validator == lane_operator
+                && s.msg == expected_message
+                && BlstCrypto::verify(s).unwrap_or(false)
+        });
+        if !signed_by_operator {
+            bail!(
+                "Shard of DataProposal {} is missing signature from lane operator {}",
+                dp_hash,
+                lane_operator
+            );
+        }
+        shard.verify()?;
+        if &shard.data_proposal_hash() != dp_hash {
+            bail!(
+                "Shard {} is not bound to DataProposal {}, its chunks commit to {}",
+                shard.index,
+                dp_hash,
+                shard.data_proposal_hash()
+            );
+        }
+        Ok(())
+    }
+
+    /// Sends each bonded validator that has not signed the DataProposal yet its own shard.
+    /// Returns None if the DataProposal is not bound to its shards for the current validators,
+    /// in which case it has to be disseminated in full.
+    pub(super) fn disseminate_data_proposal_shards(
+        &mut self,
+        entry_metadata: &LaneEntryMetadata,
+        dp_hash: &DataProposalHash,
+    ) -> Result<Option<bool>> {
+        let validator_that_has_signed: HashSet<&ValidatorPublicKey> = entry_metadata
+            .signatures
+            .iter()
+            .map(|s| &s.signature.validator)
+            .collect();
+
+        let recipients: Vec<(usize, ValidatorPublicKey)> = self
+            .staking
+            .bonded()
+            .iter()
+            .enumerate()
+            .filter(|(_, pubkey)| {
+                *pubkey != self.crypto.validator_pubkey()
+                    && !validator_that_has_signed.contains(pubkey)
+            })
+            .map(|(index, pubkey)| (index, pubkey.clone()))
+            .collect();
+
+        if recipients.is_empty() {
+            return Ok(Some(false));
+        }
+
+        let Some(data_proposal) = self.lanes.get_dp_by_hash(&self.own_lane_id(), dp_hash)? else {
+            bail!(
+                "Can't find DataProposal {} in lane {}",
+                dp_hash,
+                self.own_lane_id()
+            );
+        };
+
+        let shards =
+            ErasureCoder::for_validators(self.staking.bonded().len())?.encode(&data_proposal)?;
+        if shards.first().map(|shard| shard.data_proposal_hash()) != Some(dp_hash.clone()) {
+            debug!(
+                "DataProposal {} is not bound to its shards for the current validators",
+                dp_hash
+            );
+            return Ok(None);
+        }
+
+        debug!(
+            "🧩 Disseminate DataProposal {} as shards (only for {} validators, {} txs)",
+            dp_hash,
+            recipients.len(),
+            data_proposal.txs.len()
+        );
+
+        self.metrics
+            .shard_disseminations
+            .add(recipients.len() as u64, &[]);
+
+        for (index, validator) in recipients {
+            let Some(shard) = shards.get(index).cloned() else {
+                bail!("Missing shard {} for DataProposal {}", index, dp_hash);
+            };
+            self.send_net_message(
+                validator,
+                MempoolNetMessage::DataProposalShard(
+                    dp_hash.clone(),
+                    entry_metadata.clone(),
+                    shard,
+                ),
+            )?;
+        }
+
+        Ok(Some(true))
+    }
+
+    pub(super) fn on_data_proposal_shard(
+        &mut self,
+        lane_id: &LaneId,
+        dp_hash: DataProposalHash,
+        metadata: LaneEntryMetadata,
+        shard: DataProposalShard,
+    ) -> Result<()> {
+        debug!(
+            "Received shard {} of DataProposal {} on lane {}",
+            shard.index, dp_hash, lane_id
+        );
+        self.metrics.add_received_shard(lane_id);
+
+        if self.lanes.contains(lane_id, &dp_hash) || self.lane_shards.contains(lane_id, &dp_hash) {
+            // Already stored, just resend our vote
+            return self.send_vote(
+                self.get_lane_operator(lane_id),
+                dp_hash,
+                metadata.cumul_size,
+            );
+        }
+
+        self.check_shard_entry(lane_id, &dp_hash, &metadata, &shard)?;
+
+        if shard.total_shards() != self.staking.bonded().len()
+            || self.own_shard_index() != Some(shard.index as usize)
+        {
+            bail!(
+                "Received shard {} of DataProposal {}, which is not ours",
+                shard.index,
+                dp_hash
+            );
+        }
+
+        let cumul_size = metadata.cumul_size;
+        // Only keep the signature of the lane operator, votes will come through PoDA updates
+        let lane_operator = self.get_lane_operator(lane_id).clone();
+        let metadata = LaneEntryMetadata {
+            signatures: metadata
+                .signatures
+                .into_iter()
+                .filter(|s| s.signature.validator == lane_operator)
+                .collect(),
+            ..metadata
+        };
+        self.lane_shards
+            .put(lane_id.clone(), dp_hash.clone(), metadata, shard);
+
+        self.send_vote(&lane_operator, dp_hash.clone(), cumul_size)?;
+
+        while let Some(poda_signatures) = self
+            .inner
+            .buffered_podas
+            .get_mut(lane_id)
+            .and_then(|lane| lane.get_mut(&dp_hash))
+            .and_then(|podas_list| podas_list.pop())
+        {
+            self.lane_shards
+                .add_signatures(lane_id, &dp_hash, poda_signatures)?;
+        }
+
+        Ok(())
+    }
+
+    /// Asks all bonded validators for their shard of a DataProposal we need to reconstruct.
+    pub(super) fn send_shard_sync_request(
+        &mut self,
+        lane_id: &LaneId,
+        dp_hash: &DataProposalHash,
+    ) -> Result<()> {
+        let key = (lane_id.clone(), dp_hash.clone());
+
+        debug!(
+            "🔍 Sending ShardSyncRequest for DataProposal {} of lane {}",
+            dp_hash, lane_id
+        );
+        self.metrics
+            .sync_request_send(lane_id, self.crypto.validator_pubkey());
+
+        let now = Instant::now();
+        self.pending_reconstructions.retain(|_, pending| {
+            now.duration_since(pending.requested_at) < PENDING_RECONSTRUCTION_TIMEOUT
+        });
+        if !self.pending_reconstructions.contains_key(&key)
+            && self.pending_reconstructions.len() >= MAX_PENDING_RECONSTRUCTIONS
+        {
+            let oldest = self
+                .pending_reconstructions
+                .iter()
+                .min_by_key(|(_, pending)| pending.requested_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.pending_reconstructions.remove(&oldest);
+            }
+        }
+
+        // Our own shard counts towards the reconstruction
+        let own_shard = self
+            .lane_shards
+            .get(lane_id, dp_hash)
+            .map(|(_, shard)| shard.clone());
+        let pending =
+            self.pending_reconstructions
+                .entry(key)
+                .or_insert_with(|| PendingReconstruction {
+                    requested_at: now,
+                    shards: vec![],
+                });
+        pending.requested_at = now;
+        if let Some(shard) = own_shard {
+            if !pending.shards.iter().any(|s| s.index == shard.index) {
+                pending.shards.push(shard);
+            }
+        }
+
+        let only_for: HashSet<ValidatorPublicKey> = self
+            .staking
+            .bonded()
+            .iter()
+            .filter(|pubkey| *pubkey != self.crypto.validator_pubkey())
+            .cloned()
+            .collect();
+
+        self.broadcast_only_for_net_message(
+            only_for,
+            MempoolNetMessage::ShardSyncRequest(lane_id.clone(), dp_hash.clone()),
+        )
+    }
+
+    pub(super) fn on_shard_sync_request(
+        &mut self,
+        validator: &ValidatorPublicKey,
+        lane_id: LaneId,
+        dp_hash: DataProposalHash,
+    ) -> Result<()> {
+        trace!(
+            "ShardSyncRequest received from validator {validator} for DataProposal {} of lane {}",
+            dp_hash,
+            lane_id
+        );
+
+        let reply = match self.lane_shards.get(&lane_id, &dp_hash).cloned() {
+            Some(entry) => Some(entry),
+            None => {
+                // We have the full DataProposal (we operate the lane, or reconstructed it already)
+                match (
+                    self.own_shard_index(),
+                    self.lanes.get_metadata_by_hash(&lane_id, &dp_hash)?,
+                    self.lanes.get_dp_by_hash(&lane_id, &dp_hash)?,
+                ) {
+                    (Some(index), Some(metadata), Some(data_proposal)) => {
+                        // Once its proofs are removed, a DataProposal no longer encodes to its
+                        // original chunks: only the lane operator can rebuild them, others
+                        // answer with the shard they stored.
+                        let shards = ErasureCoder::for_validators(self.staking.bonded().len())?
+                            .encode(&data_proposal)?;
+                        shards
+                            .into_iter()
+                            .nth(index)
+                            .filter(|shard| shard.data_proposal_hash() == dp_hash)
+                            .map(|shard| (metadata, shard))
+                    }
+                    _ => None,
+                }
+            }
+        };
+
+        let Some((metadata, shard)) = reply else {
+            debug!(
+                "No shard of DataProposal {} of lane {} to reply to {}",
+                dp_hash, lane_id, validator
+            );
+            return Ok(());
+        };
+
+        self.metrics.mempool_sync_processed(&lane_id, validator);
+        self.send_net_message(
+            validator.clone(),
+            MempoolNetMessage::ShardSyncReply(lane_id, dp_hash, metadata, shard),
+        )
+    }
+
+    pub(super) async fn on_shard_sync_reply(
+        &mut self,
+        validator: &ValidatorPublicKey,
+        lane_id: LaneId,
+        dp_hash: DataProposalHash,
+        metadata: LaneEntryMetadata,
+        shard: DataProposalShard,
+    ) -> Result<()> {
+        debug!(
+            "ShardSyncReply from validator {validator} with shard {} of DataProposal {}",
+            shard.index, dp_hash
+        );
+        self.metrics
+            .sync_reply_receive(&lane_id, self.crypto.validator_pubkey());
+
+        if self.lanes.contains(&lane_id, &dp_hash) {
+            trace!("DataProposal {} already reconstructed", dp_hash);
+            return Ok(());
+        }
+
+        let key = (lane_id.clone(), dp_hash.clone());
+        if !self.pending_reconstructions.contains_key(&key) {
+            trace!(
+                "Ignoring shard of DataProposal {}, its reconstruction was not requested",
+                dp_hash
+            );
+            return Ok(());
+        }
+
+        self.check_shard_entry(&lane_id, &dp_hash, &metadata, &shard)?;
+
+        let Some(pending) = self.pending_reconstructions.get_mut(&key) else {
+            return Ok(());
+        };
+        if pending.shards.iter().any(|s| s.index == shard.index) {
+            return Ok(());
+        }
+        let data_shards = shard.data_shards as usize;
+        pending.shards.push(shard);
+
+        if pending.shards.len() < data_shards {
+            return Ok(());
+        }
+
+        let mut data_proposal = match ErasureCoder::reconstruct(&pending.shards, &dp_hash) {
+            Ok(data_proposal) => data_proposal,
+            Err(e) => {
+                // Keep the shards, more replies might allow the reconstruction
+                debug!("Could not reconstruct DataProposal yet: {:#}", e);
+                return Ok(());
+            }
+        };
+        self.pending_reconstructions.remove(&key);
+
+        debug!(
+            "🧩 Reconstructed DataProposal {} of lane {} ({} txs)",
+            dp_hash,
+            lane_id,
+            data_proposal.txs.len()
+        );
+        self.metrics.add_reconstructed_dp(&lane_id);
+
+        // Validators only voted on their shard, so proofs are verified now
+        self.inner.processing_dps.spawn_on(
+            async move {
+                let dropped_txs = Self::drop_invalid_proofs(&mut data_proposal);
+                Ok(ProcessedDPEvent::OnReconstructedDataProposal((
+                    lane_id,
+                    dropped_txs,
+                    metadata,
+                    data_proposal,
+                )))
+            },
+            self.inner.long_tasks_runtime.handle(),
+        );
+        Ok(())
+    }
+
+    pub(super) async fn on_verified_reconstructed_data_proposal(
+        &mut self,
+        lane_id: LaneId,
+        dropped_txs: usize,
+        metadata: LaneEntryMetadata,
+        data_proposal: DataProposal,
+    ) -> Result<()> {
+        let dp_hash = data_proposal.hashed();
+        if dropped_txs > 0 {
+            warn!(
+                "Reconstructed DataProposal {} of lane {} holds {} invalid transactions, they are dropped from its execution",
+                dp_hash, lane_id, dropped_txs
+            );
+        }
+
+        // Prefer our own metadata, which holds the PoDA signatures we received
+        let metadata = self
+            .lane_shards
+            .get(&lane_id, &dp_hash)
+            .map(|(metadata, _)| metadata.clone())
+            .unwrap_or(metadata);
+
+        // Like SyncReply, this only fills holes in the lane and never updates the lane tip
+        self.lanes
+            .put_no_verification(lane_id, (metadata, data_proposal))?;
+
+        self.try_to_send_full_signed_blocks().await
+    }
+}
+
+#[allow(clippy::indexing_slicing)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mempool::test::*;
+    use crate::p2p::network::HeaderSigner;
+    use crate::utils::conf::DisseminationMode;
+    use anyhow::Result;
+    use hyle_contract_sdk::{HyleOutput, ProgramId};
+
+    struct ShardsTestCtx {
+        ctx: MempoolTestCtx,
+        operator: BlstCrypto,
+        others: Vec<BlstCrypto>,
+    }
+
+    impl ShardsTestCtx {
+        async fn new() -> Self {
+            let mut ctx = MempoolTestCtx::new("node-0").await;
+            let operator = BlstCrypto::new("node-1").unwrap();
+            let others = vec![
+                BlstCrypto::new("node-2").unwrap(),
+                BlstCrypto::new("node-3").unwrap(),
+            ];
+            let own = (*ctx.mempool.crypto).clone();
+            ctx.setup_node(&[own, operator.clone(), others[0].clone(), others[1].clone()]);
+            ctx.set_dissemination_mode(DisseminationMode::ErasureCoded);
+            Self {
+                ctx,
+                operator,
+                others,
+            }
+        }
+
+        fn lane_id(&self) -> LaneId {
+            LaneId(self.operator.validator_pubkey().clone())
+        }
+
+        fn coder(&self) -> ErasureCoder {
+            ErasureCoder::for_validators(4).unwrap()
+        }
+
+        fn bound_data_proposal(&self, txs: Vec<Transaction>) -> DataProposal {
+            let dp = DataProposal::new(None, txs);
+            let chunk_root = self.coder().chunk_root(&dp).unwrap();
+            dp.with_chunk_root(chunk_root)
+        }
+
+        fn signed_metadata(&self, dp: &DataProposal) -> LaneEntryMetadata {
+            let size = LaneBytesSize(dp.estimate_size() as u64);
+            LaneEntryMetadata {
+                parent_data_proposal_hash: None,
+                cumul_size: size,
+                signatures: vec![self.operator.sign((dp.hashed(), size)).unwrap()],
+            }
+        }
+
+        fn shard_index(&self, validator: &ValidatorPublicKey) -> usize {
+            self.ctx
+                .mempool
+                .staking
+                .bonded()
+                .iter()
+                .position(|v| v == validator)
+                .unwrap()
+        }
+
+        async fn send_shard(&mut self, dp: &DataProposal, shard: DataProposalShard) -> Result<()> {
+            let msg = self
+                .operator
+                .sign_msg_with_header(MempoolNetMessage::DataProposalShard(
+                    dp.hashed(),
+                    self.signed_metadata(dp),
+                    shard,
+                ))?;
+            self.ctx
+                .mempool
+                .handle_net_message(msg, &self.ctx.mempool_sync_request_sender)
+                .await
+        }
+
+        /// Requests the reconstruction of the DataProposal, and replies with the shards of the other validators.
+        async fn reconstruct(&mut self, dp: &DataProposal) -> Result<()> {
+            let lane_id = self.lane_id();
+            self.ctx.request_shard_sync(&lane_id, &dp.hashed());
+            let shards = self.coder().encode(dp)?;
+            for validator in self.others.clone() {
+                let shard = shards[self.shard_index(validator.validator_pubkey())].clone();
+                let msg = validator.sign_msg_with_header(MempoolNetMessage::ShardSyncReply(
+                    lane_id.clone(),
+                    dp.hashed(),
+                    self.signed_metadata(dp),
+                    shard,
+                ))?;
+                self.ctx
+                    .mempool
+                    .handle_net_message(msg, &self.ctx.mempool_sync_request_sender)
+                    .await?;
+            }
+            Ok(())
+        }
+    }
+
+    fn proof_tx(proof: ProofData) -> Transaction {
+        VerifiedProofTransaction {
+            contract_name: ContractName::new("test1"),
+            verifier: "test".into(),
+            program_id: ProgramId(vec![]),
+            proof_hash: proof.hashed(),
+            proof_size: proof.0.len(),
+            proof: Some(proof),
+            proven_blobs: vec![BlobProofOutput {
+                original_proof_hash: ProofDataHash("test".to_string()),
+                blob_tx_hash: TxHash("test".to_string()),
+                program_id: ProgramId(vec![]),
+                verifier: "test".into(),
+                hyle_output: HyleOutput::default(),
+            }],
+            is_recursive: false,
+        }
+        .into()
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_valid_shard_is_stored_and_voted() -> Result<()> {
+        let mut shards_ctx = ShardsTestCtx::new().await;
+        let dp = shards_ctx
+            .bound_data_proposal(vec![make_register_contract_tx(ContractName::new("test1"))]);
+        let own_index = shards_ctx.shard_index(shards_ctx.ctx.validator_pubkey());
+        let shard = shards_ctx.coder().encode(&dp)?.swap_remove(own_index);
+
+        shards_ctx.send_shard(&dp, shard).await?;
+
+        let lane_id = shards_ctx.lane_id();
+        assert!(shards_ctx
+            .ctx
+            .mempool
+            .lane_shards
+            .contains(&lane_id, &dp.hashed()));
+        let operator = shards_ctx.operator.validator_pubkey().clone();
+        match shards_ctx.ctx.assert_send(&operator, "DataVote").await.msg {
+            MempoolNetMessage::DataVote(vote) => assert_eq!(vote.msg.0, dp.hashed()),
+            _ => panic!("Expected DataVote message"),
+        }
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_corrupted_shard_is_refused() -> Result<()> {
+        let mut shards_ctx = ShardsTestCtx::new().await;
+        let dp = shards_ctx
+            .bound_data_proposal(vec![make_register_contract_tx(ContractName::new("test1"))]);
+        let own_index = shards_ctx.shard_index(shards_ctx.ctx.validator_pubkey());
+        let mut shard = shards_ctx.coder().encode(&dp)?.swap_remove(own_index);
+        shard.chunk[0] ^= 1;
+
+        assert!(shards_ctx.send_shard(&dp, shard).await.is_err());
+
+        let lane_id = shards_ctx.lane_id();
+        assert!(!shards_ctx
+            .ctx
+            .mempool
+            .lane_shards
+            .contains(&lane_id, &dp.hashed()));
+        assert!(shards_ctx.ctx.out_receiver.try_recv().is_err());
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_shard_not_bound_to_data_proposal_is_refused() -> Result<()> {
+        let mut shards_ctx = ShardsTestCtx::new().await;
+        // The operator signs a DataProposal hash that does not commit to the chunks it sends
+        let dp = DataProposal::new(
+            None,
+            vec![make_register_contract_tx(ContractName::new("test1"))],
+        );
+        let own_index = shards_ctx.shard_index(shards_ctx.ctx.validator_pubkey());
+        let shard = shards_ctx.coder().encode(&dp)?.swap_remove(own_index);
+
+        assert!(shards_ctx.send_shard(&dp, shard).await.is_err());
+
+        let lane_id = shards_ctx.lane_id();
+        assert!(!shards_ctx
+            .ctx
+            .mempool
+            .lane_shards
+            .contains(&lane_id, &dp.hashed()));
+        assert!(shards_ctx.ctx.out_receiver.try_recv().is_err());
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_unrequested_shard_sync_reply_is_ignored() -> Result<()> {
+        let mut shards_ctx = ShardsTestCtx::new().await;
+        let dp = shards_ctx
+            .bound_data_proposal(vec![make_register_contract_tx(ContractName::new("test1"))]);
+        let validator = shards_ctx.others[0].clone();
+        let shard = shards_ctx
+            .coder()
+            .encode(&dp)?
+            .swap_remove(shards_ctx.shard_index(validator.validator_pubkey()));
+
+        let msg = validator.sign_msg_with_header(MempoolNetMessage::ShardSyncReply(
+            shards_ctx.lane_id(),
+            dp.hashed(),
+            shards_ctx.signed_metadata(&dp),
+            shard,
+        ))?;
+        shards_ctx
+            .ctx
+            .mempool
+            .handle_net_message(msg, &shards_ctx.ctx.mempool_sync_request_sender)
+            .await?;
+
+        assert!(shards_ctx.ctx.mempool.pending_reconstructions.is_empty());
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_reconstructed_data_proposal_is_verified() -> Result<()> {
+        let mut shards_ctx = ShardsTestCtx::new().await;
+        let proof = ProofData(borsh::to_vec(&vec![HyleOutput::default()])?);
+        let dp = shards_ctx.bound_data_proposal(vec![proof_tx(proof)]);
+
+        shards_ctx.reconstruct(&dp).await?;
+        shards_ctx.ctx.handle_processed_data_proposals().await;
+
+        let lane_id = shards_ctx.lane_id();
+        let stored = shards_ctx
+            .ctx
+            .mempool
+            .lanes
+            .get_dp_by_hash(&lane_id, &dp.hashed())?
+            .expect("DataProposal should be reconstructed");
+        match &stored.txs[0].transaction_data {
+            TransactionData::VerifiedProof(proof_tx) => assert!(proof_tx.proof.is_none()),
+            _ => panic!("Expected VerifiedProof transaction"),
+        }
+        assert!(shards_ctx.ctx.mempool.pending_reconstructions.is_empty());
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_reconstructed_data_proposal_with_invalid_proof_is_kept() -> Result<()> {
+        let mut shards_ctx = ShardsTestCtx::new().await;
+        let dp = shards_ctx.bound_data_proposal(vec![
+            make_register_contract_tx(ContractName::new("test1")),
+            proof_tx(ProofData(vec![1, 2, 3])),
+        ]);
+
+        shards_ctx.reconstruct(&dp).await?;
+        shards_ctx.ctx.handle_processed_data_proposals().await;
+
+        // The DataProposal is committed, so it is stored under its hash for the block to be built,
+        // but the invalid proof settles nothing
+        let lane_id = shards_ctx.lane_id();
+        let stored = shards_ctx
+            .ctx
+            .mempool
+            .lanes
+            .get_dp_by_hash(&lane_id, &dp.hashed())?
+            .expect("DataProposal should be reconstructed");
+        assert_eq!(stored.txs[0], dp.txs[0]);
+        match &stored.txs[1].transaction_data {
+            TransactionData::VerifiedProof(proof_tx) => {
+                assert!(proof_tx.proof.is_none());
+                assert!(proof_tx.proven_blobs.is_empty());
+            }
+            _ => panic!("Expected VerifiedProof transaction"),
+        }
+        assert_eq!(stored.txs[1].hashed(), dp.txs[1].hashed());
+        Ok(())
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_shards_are_kept_for_retention_blocks() -> Result<()> {
+        let mut shards_ctx = ShardsTestCtx::new().await;
+        let dp = shards_ctx
+            .bound_data_proposal(vec![make_register_contract_tx(ContractName::new("test1"))]);
+        let own_index = shards_ctx.shard_index(shards_ctx.ctx.validator_pubkey());
+        let shard = shards_ctx.coder().encode(&dp)?.swap_remove(own_index);
+        shards_ctx.send_shard(&dp, shard).await?;
+
+        let lane_id = shards_ctx.lane_id();
+        let cut: Cut = vec![(
+            lane_id.clone(),
+            dp.hashed(),
+            LaneBytesSize(dp.estimate_size() as u64),
+            AggregateSignature::default(),
+        )];
+        let lane_shards = &mut shards_ctx.ctx.mempool.inner.lane_shards;
+        for _ in 0..SHARD_RETENTION_BLOCKS {
+            lane_shards.on_built_block(&cut);
+            assert!(lane_shards.contains(&lane_id, &dp.hashed()));
+        }
+        lane_shards.on_built_block(&vec![]);
+        assert!(!lane_shards.contains(&lane_id, &dp.hashed()));
+        Ok(())
+    }
+}
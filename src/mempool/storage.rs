@@ -43,6 +43,72 @@ pub struct LaneEntryMetadata {
     pub signatures: Vec<ValidatorDAG>,
 }
 
+/// Walks back a lane from its tip, until finding a DataProposal with enough signatures to create a PoDA.
+pub fn latest_car_from_tip(
+    lane_id: &LaneId,
+    tip: Option<&DataProposalHash>,
+    get_metadata: impl Fn(&DataProposalHash) -> Result<Option<LaneEntryMetadata>>,
+    staking: &Staking,
+    previous_committed_car: Option<&(LaneId, DataProposalHash, LaneBytesSize, PoDA)>,
+) -> Result<Option<(DataProposalHash, LaneBytesSize, PoDA)>> {
+    let bonded_validators = staking.bonded();
+    // We start from the tip of the lane, and go backup until we find a DP with enough signatures
+    if let Some(tip_dp_hash) = tip {
+        let mut dp_hash = tip_dp_hash.clone();
+        while let Some(le) = get_metadata(&dp_hash)? {
+            if let Some((_, hash, _, poda)) = previous_committed_car {
+                if &dp_hash == hash {
+                    // Latest car has already been committed
+                    return Ok(Some((hash.clone(), le.cumul_size, poda.clone())));
+                }
+            }
+            // Filter signatures on DataProposal to only keep the ones from the current validators
+            let filtered_signatures: Vec<SignedByValidator<(DataProposalHash, LaneBytesSize)>> = le
+                .signatures
+                .iter()
+                .filter(|signed_msg| bonded_validators.contains(&signed_msg.signature.validator))
+                .cloned()
+                .collect();
+
+            // Collect all filtered validators that signed the DataProposal
+            let filtered_validators: Vec<ValidatorPublicKey> = filtered_signatures
+                .iter()
+                .map(|s| s.signature.validator.clone())
+                .collect();
+
+            // Compute their voting power to check if the DataProposal received enough votes
+            let voting_power = staking.compute_voting_power(filtered_validators.as_slice());
+            let f = staking.compute_f();
+            if voting_power < f + 1 {
+                // Check if previous DataProposals received enough votes
+                if let Some(parent_dp_hash) = le.parent_data_proposal_hash.clone() {
+                    dp_hash = parent_dp_hash;
+                    continue;
+                }
+                return Ok(None);
+            }
+
+            // Aggregate the signatures in a PoDA
+            let poda = match BlstCrypto::aggregate(
+                (dp_hash.clone(), le.cumul_size),
+                &filtered_signatures.iter().collect::<Vec<_>>(),
+            ) {
+                Ok(poda) => poda,
+                Err(e) => {
+                    error!(
+                    "Could not aggregate signatures for validator {} and data proposal hash {}: {}",
+                    lane_id, dp_hash, e
+                );
+                    break;
+                }
+            };
+            return Ok(Some((dp_hash.clone(), le.cumul_size, poda.signature)));
+        }
+    }
+
+    Ok(None)
+}
+
 pub trait Storage {
     fn persist(&self) -> Result<()>;
 
@@ -93,64 +159,13 @@ pub trait Storage {
         staking: &Staking,
         previous_committed_car: Option<&(LaneId, DataProposalHash, LaneBytesSize, PoDA)>,
     ) -> Result<Option<(DataProposalHash, LaneBytesSize, PoDA)>> {
-        let bonded_validators = staking.bonded();
-        // We start from the tip of the lane, and go backup until we find a DP with enough signatures
-        if let Some(tip_dp_hash) = self.get_lane_hash_tip(lane_id) {
-            let mut dp_hash = tip_dp_hash.clone();
-            while let Some(le) = self.get_metadata_by_hash(lane_id, &dp_hash)? {
-                if let Some((_, hash, _, poda)) = previous_committed_car {
-                    if &dp_hash == hash {
-                        // Latest car has already been committed
-                        return Ok(Some((hash.clone(), le.cumul_size, poda.clone())));
-                    }
-                }
-                // Filter signatures on DataProposal to only keep the ones from the current validators
-                let filtered_signatures: Vec<SignedByValidator<(DataProposalHash, LaneBytesSize)>> =
-                    le.signatures
-                        .iter()
-                        .filter(|signed_msg| {
-                            bonded_validators.contains(&signed_msg.signature.validator)
-                        })
-                        .cloned()
-                        .collect();
-
-                // Collect all filtered validators that signed the DataProposal
-                let filtered_validators: Vec<ValidatorPublicKey> = filtered_signatures
-                    .iter()
-                    .map(|s| s.signature.validator.clone())
-                    .collect();
-
-                // Compute their voting power to check if the DataProposal received enough votes
-                let voting_power = staking.compute_voting_power(filtered_validators.as_slice());
-                let f = staking.compute_f();
-                if voting_power < f + 1 {
-                    // Check if previous DataProposals received enough votes
-                    if let Some(parent_dp_hash) = le.parent_data_proposal_hash.clone() {
-                        dp_hash = parent_dp_hash;
-                        continue;
-                    }
-                    return Ok(None);
-                }
-
-                // Aggregate the signatures in a PoDA
-                let poda = match BlstCrypto::aggregate(
-                    (dp_hash.clone(), le.cumul_size),
-                    &filtered_signatures.iter().collect::<Vec<_>>(),
-                ) {
-                    Ok(poda) => poda,
-                    Err(e) => {
-                        error!(
-                        "Could not aggregate signatures for validator {} and data proposal hash {}: {}",
-                        lane_id, dp_hash, e
-                    );
-                        break;
-                    }
-                };
-                return Ok(Some((dp_hash.clone(), le.cumul_size, poda.signature)));
-            }
-        }
-
-        Ok(None)
+        latest_car_from_tip(
+            lane_id,
+            self.get_lane_hash_tip(lane_id),
+            |dp_hash| self.get_metadata_by_hash(lane_id, dp_hash),
+            staking,
+            previous_committed_car,
+        )
     }
 
     /// Signs the data proposal before creating a new LaneEntry and puting it in the lane
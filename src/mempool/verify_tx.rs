@@ -5,7 +5,7 @@ use tracing::{debug, trace, warn};
 
 use crate::{
     mempool::{MempoolNetMessage, ProcessedDPEvent},
-    model::{BlobProofOutput, DataProposal, Hashed, TransactionData},
+    model::{BlobProofOutput, DataProposal, Hashed, Transaction, TransactionData},
 };
 
 use super::{
@@ -316,85 +316,11 @@ impl super::Mempool {
         }
     }
 
-    fn process_data_proposal(data_proposal: &mut DataProposal) -> DataProposalVerdict {
+    pub(super) fn process_data_proposal(data_proposal: &mut DataProposal) -> DataProposalVerdict {
         for tx in &data_proposal.txs {
-            match &tx.transaction_data {
-                TransactionData::Blob(_) => {
-                    // Accepting all blob transactions
-                    // TODO: find out what we want to do here
-                }
-                TransactionData::Proof(_) => {
-                    warn!("Refusing DataProposal: unverified recursive proof transaction");
-                    return DataProposalVerdict::Refuse;
-                }
-                TransactionData::VerifiedProof(proof_tx) => {
-                    // TODO: figure out what we want to do with the contracts.
-                    // Extract the proof
-                    let proof = match &proof_tx.proof {
-                        Some(proof) => proof,
-                        None => {
-                            warn!("Refusing DataProposal: proof is missing");
-                            return DataProposalVerdict::Refuse;
-                        }
-                    };
-                    let verifier = &proof_tx.verifier;
-                    let program_id = &proof_tx.program_id;
-                    // TODO: figure out how to generalize this
-                    let is_recursive = proof_tx.contract_name.0 == "risc0-recursion";
-
-                    if is_recursive {
-                        match verify_recursive_proof(proof, verifier, program_id) {
-                            Ok((local_program_ids, local_hyle_outputs)) => {
-                                let data_matches = local_program_ids
-                                    .iter()
-                                    .zip(local_hyle_outputs.iter())
-                                    .zip(proof_tx.proven_blobs.iter())
-                                    .all(
-                                        |(
-                                            (local_program_id, local_hyle_output),
-                                            BlobProofOutput {
-                                                program_id,
-                                                hyle_output,
-                                                ..
-                                            },
-                                        )| {
-                                            local_hyle_output == hyle_output
-                                                && local_program_id == program_id
-                                        },
-                                    );
-                                if local_program_ids.len() != proof_tx.proven_blobs.len()
-                                    || !data_matches
-                                {
-                                    warn!("Refusing DataProposal: incorrect HyleOutput in proof transaction");
-                                    return DataProposalVerdict::Refuse;
-                                }
-                            }
-                            Err(e) => {
-                                warn!("Refusing DataProposal: invalid recursive proof transaction: {}", e);
-                                return DataProposalVerdict::Refuse;
-                            }
-                        }
-                    } else {
-                        match verify_proof(proof, verifier, program_id) {
-                            Ok(outputs) => {
-                                // TODO: we could check the blob hash here too.
-                                if outputs.len() != proof_tx.proven_blobs.len()
-                                    && std::iter::zip(outputs.iter(), proof_tx.proven_blobs.iter())
-                                        .any(|(output, BlobProofOutput { hyle_output, .. })| {
-                                            output != hyle_output
-                                        })
-                                {
-                                    warn!("Refusing DataProposal: incorrect HyleOutput in proof transaction");
-                                    return DataProposalVerdict::Refuse;
-                                }
-                            }
-                            Err(e) => {
-                                warn!("Refusing DataProposal: invalid proof transaction: {}", e);
-                                return DataProposalVerdict::Refuse;
-                            }
-                        }
-                    }
-                }
+            if let Err(reason) = Self::verify_transaction(tx) {
+                warn!("Refusing DataProposal: {}", reason);
+                return DataProposalVerdict::Refuse;
             }
         }
 
@@ -404,12 +330,98 @@ impl super::Mempool {
         DataProposalVerdict::Vote
     }
 
+    /// Verifies the proofs of a DataProposal that was committed without its transactions being
+    /// checked, which has to be kept as is since its hash is part of the chain.
+    /// Invalid proof transactions have their proven blobs cleared, which leaves their hash
+    /// unchanged, so that they settle nothing when the block is executed. Unverified proof
+    /// transactions are already skipped by the node state.
+    /// Returns the number of transactions dropped this way.
+    pub(super) fn drop_invalid_proofs(data_proposal: &mut DataProposal) -> usize {
+        let invalid_txs: Vec<usize> = data_proposal
+            .txs
+            .iter()
+            .enumerate()
+            .filter_map(|(index, tx)| {
+                let reason = Self::verify_transaction(tx).err()?;
+                warn!(
+                    "Dropping transaction {} of DataProposal: {}",
+                    tx.hashed(),
+                    reason
+                );
+                Some(index)
+            })
+            .collect();
+
+        data_proposal.remove_proofs_and_invalid_outputs(&invalid_txs);
+
+        invalid_txs.len()
+    }
+
+    fn verify_transaction(tx: &Transaction) -> Result<(), String> {
+        match &tx.transaction_data {
+            TransactionData::Blob(_) => {
+                // Accepting all blob transactions
+                // TODO: find out what we want to do here
+                Ok(())
+            }
+            TransactionData::Proof(_) => Err("unverified recursive proof transaction".to_string()),
+            TransactionData::VerifiedProof(proof_tx) => {
+                // TODO: figure out what we want to do with the contracts.
+                // Extract the proof
+                let Some(proof) = &proof_tx.proof else {
+                    return Err("proof is missing".to_string());
+                };
+                let verifier = &proof_tx.verifier;
+                let program_id = &proof_tx.program_id;
+                // TODO: figure out how to generalize this
+                let is_recursive = proof_tx.contract_name.0 == "risc0-recursion";
+
+                if is_recursive {
+                    let (local_program_ids, local_hyle_outputs) =
+                        verify_recursive_proof(proof, verifier, program_id)
+                            .map_err(|e| format!("invalid recursive proof transaction: {e}"))?;
+                    let data_matches = local_program_ids
+                        .iter()
+                        .zip(local_hyle_outputs.iter())
+                        .zip(proof_tx.proven_blobs.iter())
+                        .all(
+                            |(
+                                (local_program_id, local_hyle_output),
+                                BlobProofOutput {
+                                    program_id,
+                                    hyle_output,
+                                    ..
+                                },
+                            )| {
+                                local_hyle_output == hyle_output && local_program_id == program_id
+                            },
+                        );
+                    if local_program_ids.len() != proof_tx.proven_blobs.len() || !data_matches {
+                        return Err("incorrect HyleOutput in proof transaction".to_string());
+                    }
+                } else {
+                    let outputs = verify_proof(proof, verifier, program_id)
+                        .map_err(|e| format!("invalid proof transaction: {e}"))?;
+                    // TODO: we could check the blob hash here too.
+                    if outputs.len() != proof_tx.proven_blobs.len()
+                        && std::iter::zip(outputs.iter(), proof_tx.proven_blobs.iter()).any(
+                            |(output, BlobProofOutput { hyle_output, .. })| output != hyle_output,
+                        )
+                    {
+                        return Err("incorrect HyleOutput in proof transaction".to_string());
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
     /// Remove proofs from all transactions in the DataProposal
     fn remove_proofs(dp: &mut DataProposal) {
         dp.remove_proofs();
     }
 
-    fn send_vote(
+    pub(super) fn send_vote(
         &mut self,
         validator: &ValidatorPublicKey,
         data_proposal_hash: DataProposalHash,
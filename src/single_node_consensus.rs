@@ -202,6 +202,7 @@ impl SingleNodeConsensus {
             cut: self.store.last_cut.clone(),
             staking_actions: vec![],
             parent_hash: std::mem::take(&mut self.store.last_consensus_proposal_hash),
+            timed_out_leaders: vec![],
         };
 
         self.store.last_timestamp = consensus_proposal.timestamp.clone();
@@ -280,6 +280,7 @@ use crate::model::*;
 use crate::node_state::module::NodeStateEvent;
 use crate::p2p::network::OutboundMessage;
 use crate::p2p::P2PCommand;
+use crate::utils::conf::DisseminationMode;
 use hyle_crypto::BlstCrypto;
 use hyle_modules::handle_messages;
 use tracing::info;
@@ -610,6 +611,118 @@ async fn mempool_broadcast_multiple_data_proposals() {
     };
 }
 
+#[test_log::test(tokio::test)]
+async fn mempool_erasure_coded_dissemination() {
+    let (mut node1, mut node2, mut node3, mut node4) = build_nodes!(4).await;
+
+    for ctx in [
+        &mut node1.mempool_ctx,
+        &mut node2.mempool_ctx,
+        &mut node3.mempool_ctx,
+        &mut node4.mempool_ctx,
+    ] {
+        ctx.set_dissemination_mode(DisseminationMode::ErasureCoded);
+    }
+
+    let register_tx = make_register_contract_tx(ContractName::new("test1"));
+    let register_tx_2 = make_register_contract_tx(ContractName::new("test2"));
+
+    let dp = node1
+        .mempool_ctx
+        .create_data_proposal(None, &[register_tx, register_tx_2]);
+    let dp_hash = dp.hashed();
+    node1
+        .mempool_ctx
+        .process_new_data_proposal(dp.clone())
+        .unwrap();
+    node1.mempool_ctx.timer_tick().await.unwrap();
+
+    // Each validator only receives its own shard
+    send! {
+        description: "Disseminate shards",
+        from: [
+            node1.mempool_ctx; MempoolNetMessage::DataProposalShard(hash, _, shard) => {
+                assert_eq!(hash, &dp_hash);
+                assert_eq!(shard.index, 1);
+            }
+        ],
+        to: node2.mempool_ctx
+    };
+    send! {
+        description: "Disseminate shards",
+        from: [
+            node1.mempool_ctx; MempoolNetMessage::DataProposalShard(hash, _, shard) => {
+                assert_eq!(hash, &dp_hash);
+                assert_eq!(shard.index, 2);
+            }
+        ],
+        to: node3.mempool_ctx
+    };
+    send! {
+        description: "Disseminate shards",
+        from: [
+            node1.mempool_ctx; MempoolNetMessage::DataProposalShard(hash, _, shard) => {
+                assert_eq!(hash, &dp_hash);
+                assert_eq!(shard.index, 3);
+            }
+        ],
+        to: node4.mempool_ctx
+    };
+
+    send! {
+        description: "Shard Vote",
+        from: [node2.mempool_ctx, node3.mempool_ctx, node4.mempool_ctx], to: node1.mempool_ctx,
+        message_matches: MempoolNetMessage::DataVote(..)
+    };
+
+    // The votes only attest that the shards are available
+    for nb_signatures in 2..=4 {
+        let poda = node1.mempool_ctx.assert_broadcast("PoDAUpdate").await;
+        let MempoolNetMessage::PoDAUpdate(hash, signatures) = poda.msg else {
+            panic!("Expected a PoDAUpdate, got {:?}", poda.msg);
+        };
+        assert_eq!(hash, dp_hash);
+        assert_eq!(signatures.len(), nb_signatures);
+    }
+
+    let lane_id = node1.mempool_ctx.own_lane();
+    assert!(node2.mempool_ctx.get_dp(&lane_id, &dp_hash).is_none());
+
+    // Node 2 rebuilds the full DataProposal from its own shard and the ones of its peers
+    node2.mempool_ctx.request_shard_sync(&lane_id, &dp_hash);
+
+    let sync_request = node2
+        .mempool_ctx
+        .assert_broadcast_only_for("ShardSyncRequest");
+    assert_matches!(sync_request.msg, MempoolNetMessage::ShardSyncRequest(..));
+
+    node1
+        .mempool_ctx
+        .handle_msg(&sync_request, "ShardSyncRequest")
+        .await;
+    node3
+        .mempool_ctx
+        .handle_msg(&sync_request, "ShardSyncRequest")
+        .await;
+
+    send! {
+        description: "ShardSyncReply",
+        from: [node1.mempool_ctx, node3.mempool_ctx], to: node2.mempool_ctx,
+        message_matches: MempoolNetMessage::ShardSyncReply(..)
+    };
+
+    // The reconstructed DataProposal is verified before being stored
+    assert!(node2.mempool_ctx.get_dp(&lane_id, &dp_hash).is_none());
+    node2.mempool_ctx.handle_processed_data_proposals().await;
+
+    assert!(node2.mempool_ctx.get_dp(&lane_id, &dp_hash).is_some());
+    let reconstructed = node2
+        .mempool_ctx
+        .get_dp(&lane_id, &dp_hash)
+        .expect("DataProposal should be reconstructed");
+    assert_eq!(reconstructed, dp);
+}
+
 #[test_log::test(tokio::test)]
 async fn mempool_podaupdate_too_early() {
     let (mut node1, mut node2, mut node3, mut node4) = build_nodes!(4).await;
@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use config::{Config, Environment, File};
 use hyle_modules::modules::websocket::WebSocketConfig;
 use serde::{Deserialize, Serialize};
@@ -7,7 +7,8 @@ use serde_with::DurationMilliSeconds;
 use std::{collections::HashMap, fmt::Debug, path::PathBuf, sync::Arc, time::Duration};
 use strum_macros::IntoStaticStr;
 
-use crate::indexer::IndexerConf;
+use crate::consensus::leader_election::REPUTATION_WINDOW;
+use crate::indexer::{reindex::ReindexConf, IndexerConf};
 
 #[serde_as]
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -22,6 +23,10 @@ pub struct Consensus {
     pub solo: bool,
     /// The timestamp of the genesis block, in seconds since the Unix epoch.
     pub genesis_timestamp: u64,
+    /// How the leader of each slot and view is elected
+    pub leader_election: LeaderElectionPolicy,
+    /// Number of committed proposals kept to help validators catching up through state sync.
+    pub state_sync_history_size: usize,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, IntoStaticStr)]
@@ -35,6 +40,17 @@ pub enum TimestampCheck {
     NoCheck,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, IntoStaticStr)]
+pub enum LeaderElectionPolicy {
+    /// Each bonded validator leads in turn
+    #[default]
+    RoundRobin,
+    /// Validators lead a share of the slots proportional to their stake
+    StakeWeighted,
+    /// Stake-weighted, skipping validators that recently timed out as leaders
+    Reputation,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct GenesisConf {
     /// Initial bonded stakers and their stakes
@@ -43,6 +59,22 @@ pub struct GenesisConf {
     pub keep_tokens_in_faucet: bool,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MempoolConf {
+    /// How data proposals of the own lane are disseminated to other validators
+    pub dissemination: DisseminationMode,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, IntoStaticStr)]
+pub enum DisseminationMode {
+    /// Every validator receives the full DataProposal and votes on it.
+    #[default]
+    Full,
+    /// Every validator receives its own Reed-Solomon shard of the DataProposal and votes on it.
+    /// DataProposals are reconstructed from any f+1 shards once committed.
+    ErasureCoded,
+}
+
 /// Configuration for the P2P layer
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct P2pConf {
@@ -117,6 +149,8 @@ pub struct Conf {
     pub consensus: Consensus,
     /// Genesis block configuration
     pub genesis: GenesisConf,
+    /// Mempool configuration
+    pub mempool: MempoolConf,
 
     // Module options below
     /// Public IP address of the DA port of the node.
@@ -147,7 +181,8 @@ pub struct Conf {
     /// Whether to run the explorer (read from db)
     pub run_explorer: bool,
 
-    /// If running the indexer, the postgres address to connect to
+    /// If running the indexer or the explorer, the database to connect to:
+    /// a postgres address, or `sqlite://path/to/indexer.db` for an embedded SQLite database
     pub database_url: String,
     /// When running only the indexer, the address of the DA server to connect to
     pub da_read_from: String,
@@ -159,6 +194,9 @@ pub struct Conf {
 
     /// Configuration for the indexer module
     pub indexer: IndexerConf,
+    /// Range of blocks to index again, set from the indexer command line only
+    #[serde(skip)]
+    pub reindex: Option<ReindexConf>,
 }
 
 impl Conf {
@@ -204,6 +242,14 @@ impl Conf {
                 .unwrap_or(1000),
             );
         }
+        // The leader reputation is rebuilt from the committed history on restart
+        if conf.consensus.state_sync_history_size < REPUTATION_WINDOW as usize {
+            bail!(
+                "consensus.state_sync_history_size ({}) must be at least the reputation window ({})",
+                conf.consensus.state_sync_history_size,
+                REPUTATION_WINDOW
+            );
+        }
         Ok(conf)
     }
 }
@@ -219,6 +265,15 @@ mod tests {
         assert_ok!(Conf::new(vec![], None, None));
     }
 
+    #[test]
+    fn test_state_sync_history_covers_reputation_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_file = dir.path().join("config.toml");
+        std::fs::write(&config_file, "[consensus]\nstate_sync_history_size = 10\n").unwrap();
+        let config_file = config_file.to_string_lossy().to_string();
+        assert!(Conf::new(vec![config_file], None, None).is_err());
+    }
+
     #[test]
     fn test_override_da_public_address() {
         let conf = Conf::new(vec![], None, None).unwrap();
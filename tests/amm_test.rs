@@ -10,7 +10,7 @@ use anyhow::Result;
 
 mod e2e_amm {
     use amm::{
-        client::tx_executor_handler::{new_pair, swap},
+        client::tx_executor_handler::{add_liquidity, new_pair, remove_liquidity, swap},
         Amm,
     };
 
@@ -101,9 +101,16 @@ mod e2e_amm {
         //    By sending 20 hyllar to amm
         //    By sending 50 hyllar2 to amm
 
-        // Bob swaps 5 hyllar for 10 hyllar2
+        //    With a 0.3% fee, getting 31 shares of the pool
+
+        // Bob swaps 5 hyllar for 9 hyllar2
         //    By sending 5 hyllar to amm
-        //    By sending 10 hyllar2 to bob (from amm)
+        //    By sending 9 hyllar2 to bob (from amm)
+
+        // Bob removes 15 shares of liquidity
+        //    By sending 12 hyllar and 19 hyllar2 to bob (from amm)
+
+        // Bob adds 6 hyllar and 10 hyllar2 of liquidity, for 7 shares
 
         let hydentity: Hydentity = ctx
             .indexer_client()
@@ -177,7 +184,7 @@ mod e2e_amm {
         )?;
         transfer(&mut tx, "hyllar".into(), "bob@hydentity".into(), 25)?;
 
-        ctx.send_provable_blob_tx(&tx).await?;
+        let tx_hash = ctx.send_provable_blob_tx(&tx).await?;
         let tx = executor.process(tx)?;
         let mut proofs = tx.iter_prove();
 
@@ -190,8 +197,8 @@ mod e2e_amm {
         info!("➡️  Sending proof for hyllar");
         ctx.send_proof_single(bob_transfer_proof).await?;
 
-        info!("➡️  Waiting for height 5 on indexer");
-        ctx.wait_indexer_height(5).await?;
+        info!("➡️  Waiting for the transaction to settle");
+        ctx.wait_settled(&tx_hash).await?;
 
         let state: Hyllar = ctx
             .indexer_client()
@@ -226,7 +233,7 @@ mod e2e_amm {
         )?;
         transfer(&mut tx, "hyllar2".into(), "bob@hydentity".into(), 50)?;
 
-        ctx.send_provable_blob_tx(&tx).await?;
+        let tx_hash = ctx.send_provable_blob_tx(&tx).await?;
         let tx = executor.process(tx)?;
         let mut proofs = tx.iter_prove();
 
@@ -239,8 +246,8 @@ mod e2e_amm {
         info!("➡️  Sending proof for hyllar");
         ctx.send_proof_single(bob_transfer_proof).await?;
 
-        info!("➡️  Waiting for height 5 on indexer");
-        ctx.wait_indexer_height(5).await?;
+        info!("➡️  Waiting for the transaction to settle");
+        ctx.wait_settled(&tx_hash).await?;
 
         assert_multiple_balances(
             &ctx,
@@ -271,7 +278,7 @@ mod e2e_amm {
         )?;
         approve(&mut tx, "hyllar".into(), AMM_CONTRACT_NAME.into(), 100)?;
 
-        ctx.send_provable_blob_tx(&tx).await?;
+        let tx_hash = ctx.send_provable_blob_tx(&tx).await?;
         let tx = executor.process(tx)?;
         let mut proofs = tx.iter_prove();
 
@@ -284,8 +291,8 @@ mod e2e_amm {
         info!("➡️  Sending proof for approve hyllar");
         ctx.send_proof_single(bob_approve_hyllar_proof).await?;
 
-        info!("➡️  Waiting for height 5 on indexer");
-        ctx.wait_indexer_height(5).await?;
+        info!("➡️  Waiting for the transaction to settle");
+        ctx.wait_settled(&tx_hash).await?;
 
         assert_account_allowance(&ctx, "hyllar", "bob@hydentity", AMM_CONTRACT_NAME, 100).await?;
         /////////////////////////////////////////////////////////////////////
@@ -302,7 +309,7 @@ mod e2e_amm {
         )?;
         approve(&mut tx, "hyllar2".into(), AMM_CONTRACT_NAME.into(), 100)?;
 
-        ctx.send_provable_blob_tx(&tx).await?;
+        let tx_hash = ctx.send_provable_blob_tx(&tx).await?;
         let tx = executor.process(tx)?;
         let mut proofs = tx.iter_prove();
 
@@ -315,8 +322,8 @@ mod e2e_amm {
         info!("➡️  Sending proof for approve hyllar2");
         ctx.send_proof_single(bob_approve_hyllar2_proof).await?;
 
-        info!("➡️  Waiting for height 5 on indexer");
-        ctx.wait_indexer_height(5).await?;
+        info!("➡️  Waiting for the transaction to settle");
+        ctx.wait_settled(&tx_hash).await?;
 
         assert_account_allowance(&ctx, "hyllar2", "bob@hydentity", AMM_CONTRACT_NAME, 100).await?;
         /////////////////////////////////////////////////////////////////////
@@ -337,9 +344,10 @@ mod e2e_amm {
             AMM_CONTRACT_NAME.into(),
             ("hyllar".into(), "hyllar2".into()),
             (20, 50),
+            30,
         )?;
 
-        ctx.send_provable_blob_tx(&tx).await?;
+        let tx_hash = ctx.send_provable_blob_tx(&tx).await?;
         let tx = executor.process(tx)?;
         let mut proofs = tx.iter_prove();
 
@@ -360,8 +368,8 @@ mod e2e_amm {
         info!("➡️  Sending proof for hyllar2");
         ctx.send_proof_single(bob_transfer_hyllar2_proof).await?;
 
-        info!("➡️  Waiting for height 5 on indexer");
-        ctx.wait_indexer_height(5).await?;
+        info!("➡️  Waiting for the transaction to settle");
+        ctx.wait_settled(&tx_hash).await?;
 
         assert_multiple_balances(
             &ctx,
@@ -397,11 +405,18 @@ mod e2e_amm {
             "password".into(),
         )?;
 
+        let amount_out = executor
+            .amm
+            .get_paired_amount("hyllar".into(), "hyllar2".into(), 5)
+            .expect("pair not found");
+        assert_eq!(amount_out, 9);
         swap(
             &mut tx,
             AMM_CONTRACT_NAME.into(),
             ("hyllar".into(), "hyllar2".into()),
-            (5, 10),
+            5,
+            amount_out,
+            9,
         )?;
 
         let blob_tx_hash = ctx.send_provable_blob_tx(&tx).await?;
@@ -444,8 +459,8 @@ mod e2e_amm {
         )
         .await?;
 
-        info!("➡️  Waiting for height 5 on indexer");
-        ctx.wait_indexer_height(5).await?;
+        info!("➡️  Waiting for the transaction to settle");
+        ctx.wait_settled(&blob_tx_hash).await?;
 
         assert_multiple_balances(
             &ctx,
@@ -462,13 +477,144 @@ mod e2e_amm {
             &ctx,
             "hyllar2",
             &[
-                ("bob@hydentity", 10),
-                (AMM_CONTRACT_NAME, 40),
+                ("bob@hydentity", 9),
+                (AMM_CONTRACT_NAME, 41),
                 (FAUCET_ID, hyllar2_initial_total_amount - 50),
             ],
         )
         .await?;
         /////////////////////////////////////////////////////////////////////
+
+        ///////////////////// Bob removes liquidity /////////////////////////
+        info!("➡️  Bob removes 15 shares of liquidity");
+
+        assert_eq!(
+            executor
+                .amm
+                .get_liquidity_amounts("hyllar".into(), "hyllar2".into(), 15),
+            Some((12, 19))
+        );
+
+        let mut tx = ProvableBlobTx::new("bob@hydentity".into());
+        verify_identity(
+            &mut tx,
+            "hydentity".into(),
+            &executor.hydentity,
+            "password".into(),
+        )?;
+
+        remove_liquidity(
+            &mut tx,
+            AMM_CONTRACT_NAME.into(),
+            ("hyllar".into(), "hyllar2".into()),
+            15,
+            (12, 19),
+            (12, 19),
+        )?;
+
+        let tx_hash = ctx.send_provable_blob_tx(&tx).await?;
+        let tx = executor.process(tx)?;
+        let mut proofs = tx.iter_prove();
+
+        let hydentity_proof = proofs.next().unwrap().await?;
+        let bob_remove_liquidity_proof = proofs.next().unwrap().await?;
+        let amm_transfer_hyllar_proof = proofs.next().unwrap().await?;
+        let amm_transfer_hyllar2_proof = proofs.next().unwrap().await?;
+
+        info!("➡️  Sending proof for hydentity");
+        ctx.send_proof_single(hydentity_proof).await?;
+
+        info!("➡️  Sending proof for remove liquidity");
+        ctx.send_proof_single(bob_remove_liquidity_proof).await?;
+
+        info!("➡️  Sending proof for hyllar");
+        ctx.send_proof_single(amm_transfer_hyllar_proof).await?;
+
+        info!("➡️  Sending proof for hyllar2");
+        ctx.send_proof_single(amm_transfer_hyllar2_proof).await?;
+
+        info!("➡️  Waiting for the transaction to settle");
+        ctx.wait_settled(&tx_hash).await?;
+
+        assert_multiple_balances(
+            &ctx,
+            "hyllar",
+            &[("bob@hydentity", 12), (AMM_CONTRACT_NAME, 13)],
+        )
+        .await?;
+
+        assert_multiple_balances(
+            &ctx,
+            "hyllar2",
+            &[("bob@hydentity", 28), (AMM_CONTRACT_NAME, 22)],
+        )
+        .await?;
+        /////////////////////////////////////////////////////////////////////
+
+        ////////////////////// Bob adds liquidity ///////////////////////////
+        info!("➡️  Bob adds liquidity");
+
+        let mut tx = ProvableBlobTx::new("bob@hydentity".into());
+        verify_identity(
+            &mut tx,
+            "hydentity".into(),
+            &executor.hydentity,
+            "password".into(),
+        )?;
+
+        add_liquidity(
+            &mut tx,
+            AMM_CONTRACT_NAME.into(),
+            ("hyllar".into(), "hyllar2".into()),
+            (6, 10),
+            7,
+        )?;
+
+        let tx_hash = ctx.send_provable_blob_tx(&tx).await?;
+        let tx = executor.process(tx)?;
+        let mut proofs = tx.iter_prove();
+
+        let hydentity_proof = proofs.next().unwrap().await?;
+        let bob_add_liquidity_proof = proofs.next().unwrap().await?;
+        let bob_transfer_hyllar_proof = proofs.next().unwrap().await?;
+        let bob_transfer_hyllar2_proof = proofs.next().unwrap().await?;
+
+        info!("➡️  Sending proof for hydentity");
+        ctx.send_proof_single(hydentity_proof).await?;
+
+        info!("➡️  Sending proof for add liquidity");
+        ctx.send_proof_single(bob_add_liquidity_proof).await?;
+
+        info!("➡️  Sending proof for hyllar");
+        ctx.send_proof_single(bob_transfer_hyllar_proof).await?;
+
+        info!("➡️  Sending proof for hyllar2");
+        ctx.send_proof_single(bob_transfer_hyllar2_proof).await?;
+
+        info!("➡️  Waiting for the transaction to settle");
+        ctx.wait_settled(&tx_hash).await?;
+
+        assert_multiple_balances(
+            &ctx,
+            "hyllar",
+            &[("bob@hydentity", 6), (AMM_CONTRACT_NAME, 19)],
+        )
+        .await?;
+
+        assert_multiple_balances(
+            &ctx,
+            "hyllar2",
+            &[("bob@hydentity", 18), (AMM_CONTRACT_NAME, 32)],
+        )
+        .await?;
+
+        let pool = executor
+            .amm
+            .pool("hyllar".into(), "hyllar2".into())
+            .expect("pair not found");
+        assert_eq!(pool.total_shares, 23);
+        assert_eq!(pool.shares_of("bob@hydentity"), 23);
+        /////////////////////////////////////////////////////////////////////
         Ok(())
     }
 
@@ -3,13 +3,18 @@
 
 use std::{
     net::{Ipv4Addr, TcpListener},
+    sync::Arc,
     time::Duration,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use api::APIContract;
 use assertables::assert_ok;
-use client_sdk::{rest_client::NodeApiClient, transaction_builder::ProvableBlobTx};
+use client_sdk::{
+    rest_client::NodeApiClient,
+    transaction_builder::ProvableBlobTx,
+    tx_tracker::{TxOutcome, TxTracker, WaitUntil},
+};
 use hyle_model::api::APINodeContract;
 use testcontainers_modules::{
     postgres::Postgres,
@@ -450,6 +455,19 @@ impl E2ECtx {
         wait_indexer_height(self.indexer_client(), height).await
     }
 
+    /// Waits for the blob transaction to settle as a success, and for the indexer to index the
+    /// block it settled in
+    pub async fn wait_settled(&self, tx_hash: &TxHash) -> Result<()> {
+        let outcome = TxTracker::new(Arc::new(self.client().clone()))
+            .with_indexer(self.indexer_client().clone())
+            .wait(tx_hash, WaitUntil::Settled)
+            .await?;
+        let TxOutcome::Success { block_height } = outcome else {
+            bail!("Transaction {tx_hash} did not settle as a success: {outcome:?}");
+        };
+        self.wait_indexer_height(block_height.0).await
+    }
+
     pub async fn get_contract(&self, name: &str) -> Result<APINodeContract> {
         self.client().get_contract(name.into()).await
     }